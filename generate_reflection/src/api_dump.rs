@@ -39,16 +39,6 @@ pub struct DumpClass {
 pub enum DumpClassMember {
     Property(DumpClassProperty),
 
-    #[serde(rename_all = "PascalCase")]
-    Function {
-        name: String,
-    },
-
-    #[serde(rename_all = "PascalCase")]
-    Event {
-        name: String,
-    },
-
     #[serde(other)]
     Unknown,
 }
@@ -109,7 +99,6 @@ pub struct PropertySecurity {
 #[serde(rename_all = "PascalCase")]
 pub struct Serialization {
     pub can_save: bool,
-    pub can_load: bool,
 }
 
 #[derive(Debug, Deserialize)]
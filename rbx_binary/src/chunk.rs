@@ -1,52 +1,355 @@
 use std::{
+    borrow::Cow,
     fmt,
-    io::{self, Read, Write},
+    fs::File,
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
     str,
 };
 
-use crate::core::{RbxReadExt, RbxWriteExt};
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::{
+    core::{RbxReadExt, RbxWriteExt},
+    serializer::CompressionOptions,
+};
+
+/// The size of the header at the start of every chunk.
+pub const CHUNK_HEADER_LEN: u64 = 16;
 
 /// Represents one chunk from a binary model file.
+///
+/// The contents of uncompressed chunks decoded from a slice are borrowed from
+/// it, and are owned otherwise.
 #[derive(Debug)]
-pub struct Chunk {
+pub struct Chunk<'a> {
     pub name: [u8; 4],
-    pub data: Vec<u8>,
-}
+    pub data: Cow<'a, [u8]>,
 
-impl Chunk {
-    /// Reads and decodes a `Chunk` from the given reader.
-    pub fn decode<R: Read>(mut reader: R) -> io::Result<Chunk> {
-        let header = decode_chunk_header(&mut reader)?;
+    /// The number of bytes the chunk took up in the file, including its
+    /// header.
+    pub file_len: u64,
+}
 
+impl<'a> Chunk<'a> {
+    /// Decodes a `Chunk` from the start of `input`, advancing `input` past it.
+    ///
+    /// Uncompressed chunks borrow their contents from `input` instead of
+    /// copying them. Compressed chunks are decompressed into a new buffer
+    /// that the `Chunk` owns, just like with [`decode`](Self::decode).
+    pub fn decode_slice(
+        input: &mut &'a [u8],
+        max_len: Option<usize>,
+    ) -> Result<Chunk<'a>, ChunkError> {
+        let header = decode_chunk_header(input).map_err(ChunkError::Read)?;
         log::trace!("{}", header);
+        header.check_len(max_len)?;
+
+        let stored_len = header.stored_len() as usize;
+        if input.len() < stored_len {
+            return Err(ChunkError::Read(truncated_error(
+                header,
+                stored_len,
+                input.len(),
+            )));
+        }
+
+        let (data, rest) = input.split_at(stored_len);
+        *input = rest;
 
         let data = if header.compressed_len == 0 {
-            let mut data = Vec::with_capacity(header.len as usize);
-            reader.take(header.len as u64).read_to_end(&mut data)?;
-            data
+            Cow::Borrowed(data)
         } else {
-            let mut compressed_data = Vec::with_capacity(header.compressed_len as usize);
-            reader
-                .take(header.compressed_len as u64)
-                .read_to_end(&mut compressed_data)?;
-
-            lz4::block::decompress(&compressed_data, Some(header.len as i32))?
+            Cow::Owned(decompress_data(header, data)?)
         };
 
-        assert_eq!(data.len(), header.len as usize);
-
         Ok(Chunk {
             name: header.name,
             data,
+            file_len: CHUNK_HEADER_LEN + stored_len as u64,
+        })
+    }
+}
+
+impl Chunk<'static> {
+    /// Reads and decodes a `Chunk` from the given reader.
+    #[cfg(any(test, feature = "unstable_text_format"))]
+    pub fn decode<R: Read>(reader: R) -> io::Result<Chunk<'static>> {
+        Ok(RawChunk::read(reader, None)?.decompress()?)
+    }
+
+    /// Reads and decodes up to `max` chunks from the given reader, stopping
+    /// early after the END chunk or the first chunk that can't be read. The
+    /// returned list is never empty.
+    ///
+    /// With the `rayon` feature enabled, the chunks are decompressed in
+    /// parallel.
+    pub fn decode_batch<R: Read>(
+        mut reader: R,
+        max: usize,
+        max_len: Option<usize>,
+    ) -> Vec<Result<Chunk<'static>, ChunkError>> {
+        let mut raw_chunks = Vec::with_capacity(max);
+        let mut read_error = None;
+
+        while raw_chunks.len() < max {
+            match RawChunk::read(&mut reader, max_len) {
+                Ok(raw_chunk) => {
+                    let is_end = &raw_chunk.header.name == b"END\0";
+                    raw_chunks.push(raw_chunk);
+
+                    if is_end {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    read_error = Some(err);
+                    break;
+                }
+            }
+        }
+
+        #[cfg(feature = "rayon")]
+        let mut chunks: Vec<_> = {
+            use rayon::prelude::*;
+
+            raw_chunks
+                .into_par_iter()
+                .map(RawChunk::decompress)
+                .collect()
+        };
+
+        #[cfg(not(feature = "rayon"))]
+        let mut chunks: Vec<_> = raw_chunks.into_iter().map(RawChunk::decompress).collect();
+
+        chunks.extend(read_error.map(Err));
+        chunks
+    }
+
+    /// Reads and decodes a single `Chunk` from the given asynchronous reader.
+    #[cfg(feature = "tokio")]
+    pub async fn decode_async<R: AsyncRead + Unpin>(
+        reader: &mut R,
+        max_len: Option<usize>,
+    ) -> Result<Chunk<'static>, ChunkError> {
+        RawChunk::read_async(reader, max_len).await?.decompress()
+    }
+}
+
+/// An error encountered while decoding a chunk from a binary model file.
+#[derive(Debug)]
+pub enum ChunkError {
+    /// The chunk couldn't be read from the file. Nothing after it can be read
+    /// either, since we no longer know where the next chunk starts.
+    Read(io::Error),
+
+    /// The chunk was read, but its contents couldn't be decompressed.
+    /// `file_len` is how many bytes the chunk took up in the file, so that
+    /// the chunks after it can still be located.
+    Decompress {
+        name: [u8; 4],
+        file_len: u64,
+        source: io::Error,
+    },
+
+    /// The chunk's header says that it's larger than the most the reader
+    /// was willing to read, so its contents weren't read.
+    TooLarge { name: [u8; 4], len: u64, max: usize },
+}
+
+impl From<ChunkError> for io::Error {
+    fn from(err: ChunkError) -> Self {
+        match err {
+            ChunkError::Read(source) | ChunkError::Decompress { source, .. } => source,
+            ChunkError::TooLarge { name, len, max } => io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "chunk {} is {} bytes, more than the maximum of {}",
+                    String::from_utf8_lossy(&name),
+                    len,
+                    max
+                ),
+            ),
+        }
+    }
+}
+
+/// The number of chunks worth batching together so that they can be
+/// compressed or decompressed at the same time.
+///
+/// Without the `rayon` feature there's nothing to gain from batching, so chunks
+/// are handled one at a time.
+pub fn batch_size() -> usize {
+    #[cfg(feature = "rayon")]
+    {
+        rayon::current_num_threads()
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    {
+        1
+    }
+}
+
+/// A chunk that has been read from a binary model file, but not decompressed.
+pub struct RawChunk {
+    header: ChunkHeader,
+    data: Vec<u8>,
+}
+
+impl RawChunk {
+    fn read<R: Read>(mut reader: R, max_len: Option<usize>) -> Result<RawChunk, ChunkError> {
+        let header = decode_chunk_header(&mut reader).map_err(ChunkError::Read)?;
+        header.check_len(max_len)?;
+        Self::read_body(header, reader).map_err(ChunkError::Read)
+    }
+
+    #[cfg(feature = "tokio")]
+    async fn read_async<R: AsyncRead + Unpin>(
+        reader: &mut R,
+        max_len: Option<usize>,
+    ) -> Result<RawChunk, ChunkError> {
+        let mut header = [0; CHUNK_HEADER_LEN as usize];
+        reader
+            .read_exact(&mut header)
+            .await
+            .map_err(ChunkError::Read)?;
+        let header = decode_chunk_header(&mut header.as_slice()).map_err(ChunkError::Read)?;
+        header.check_len(max_len)?;
+
+        let stored_len = header.stored_len();
+        let mut data = Vec::with_capacity(stored_len as usize);
+        reader
+            .take(stored_len as u64)
+            .read_to_end(&mut data)
+            .await
+            .map_err(ChunkError::Read)?;
+
+        Self::read_body(header, data.as_slice()).map_err(ChunkError::Read)
+    }
+
+    /// Reads the contents of a chunk whose header has already been read.
+    pub fn read_body<R: Read>(header: ChunkHeader, reader: R) -> io::Result<RawChunk> {
+        log::trace!("{}", header);
+
+        let stored_len = header.stored_len();
+
+        let mut data = Vec::with_capacity(stored_len as usize);
+        reader.take(stored_len as u64).read_to_end(&mut data)?;
+
+        if data.len() < stored_len as usize {
+            return Err(truncated_error(header, stored_len as usize, data.len()));
+        }
+
+        Ok(RawChunk { header, data })
+    }
+
+    pub fn decompress(self) -> Result<Chunk<'static>, ChunkError> {
+        let data = if self.header.compressed_len == 0 {
+            self.data
+        } else {
+            decompress_data(self.header, &self.data)?
+        };
+
+        Ok(Chunk {
+            name: self.header.name,
+            data: Cow::Owned(data),
+            file_len: CHUNK_HEADER_LEN + self.header.stored_len() as u64,
         })
     }
 }
 
+/// Decompresses the stored contents of the compressed chunk described by
+/// `header`.
+fn decompress_data(header: ChunkHeader, data: &[u8]) -> Result<Vec<u8>, ChunkError> {
+    let to_error = |source| ChunkError::Decompress {
+        name: header.name,
+        file_len: CHUNK_HEADER_LEN + header.stored_len() as u64,
+        source,
+    };
+
+    let data = if data.starts_with(&ZSTD_MAGIC) {
+        decompress_zstd(data, header.len as usize)
+    } else {
+        lz4_decompress(data, header.len as usize)
+    }
+    .map_err(to_error)?;
+
+    if data.len() != header.len as usize {
+        return Err(to_error(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} decompressed to {} bytes instead of {}",
+                header,
+                data.len(),
+                header.len
+            ),
+        )));
+    }
+
+    Ok(data)
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn lz4_decompress(data: &[u8], len: usize) -> io::Result<Vec<u8>> {
+    lz4::block::decompress(data, Some(len as i32))
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn lz4_decompress(data: &[u8], len: usize) -> io::Result<Vec<u8>> {
+    lz4_flex::block::decompress(data, len)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Every Zstandard frame starts with these bytes, which is how a chunk
+/// compressed with it is told apart from one compressed with LZ4.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+#[cfg(feature = "zstd")]
+fn decompress_zstd(data: &[u8], len: usize) -> io::Result<Vec<u8>> {
+    zstd::bulk::decompress(data, len)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress_zstd(_data: &[u8], _len: usize) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "chunk is compressed with Zstandard, which needs the zstd feature of rbx_binary",
+    ))
+}
+
+fn truncated_error(header: ChunkHeader, expected: usize, found: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        format!(
+            "{} was truncated: expected {} bytes, found {}",
+            header, expected, found
+        ),
+    )
+}
+
+/// Creates the temporary file that a chunk is spilled to.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn spill_file() -> io::Result<File> {
+    tempfile::tempfile()
+}
+
+/// wasm32-unknown-unknown has no temporary files, so chunks are never spilled
+/// there and `tempfile` isn't a dependency.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn spill_file() -> io::Result<File> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "chunks cannot be spilled to temporary files on this target",
+    ))
+}
+
 /// The compression format of a chunk in the binary model format.
 #[derive(Debug, Clone, Copy)]
 pub enum ChunkCompression {
-    /// The contents of the chunk should be LZ4 compressed.
-    Compressed,
+    /// The contents of the chunk should be compressed according to the given
+    /// options.
+    Compressed(CompressionOptions),
 
     /// The contents of the chunk should be uncompressed.
     Uncompressed,
@@ -57,11 +360,22 @@ pub enum ChunkCompression {
 /// This type intended to be written into via io::Write and then dumped into the
 /// output stream all at once. It handles compression and chunk header output
 /// automatically.
+///
+/// If a spill threshold is set, chunk contents that grow past it are moved out
+/// of memory and into a temporary file. Spilled chunks are always written
+/// uncompressed, since compressing a chunk needs all of it in memory.
 #[must_use]
 pub struct ChunkBuilder {
     chunk_name: &'static [u8],
     compression: ChunkCompression,
-    buffer: Vec<u8>,
+    spill_threshold: Option<usize>,
+    buffer: ChunkBuffer,
+}
+
+/// Where the contents of a `ChunkBuilder` are currently being stored.
+enum ChunkBuffer {
+    Memory(Vec<u8>),
+    Spilled { file: BufWriter<File>, len: usize },
 }
 
 impl ChunkBuilder {
@@ -71,30 +385,159 @@ impl ChunkBuilder {
         ChunkBuilder {
             chunk_name,
             compression,
-            buffer: Vec::new(),
+            spill_threshold: None,
+            buffer: ChunkBuffer::Memory(Vec::new()),
         }
     }
 
+    /// Sets the number of bytes this chunk can hold in memory before its
+    /// contents are moved to a temporary file. `None` keeps the chunk in
+    /// memory no matter how large it gets.
+    ///
+    /// There are no temporary files on wasm32-unknown-unknown, so chunks are
+    /// always kept in memory there.
+    pub fn spill_threshold(mut self, threshold: Option<usize>) -> Self {
+        if cfg!(not(all(target_arch = "wasm32", target_os = "unknown"))) {
+            self.spill_threshold = threshold;
+        }
+        self
+    }
+
     /// Consume the chunk and write it to the given writer.
-    pub fn dump<W: Write>(self, mut writer: W) -> io::Result<()> {
-        writer.write_all(self.chunk_name)?;
+    pub fn dump<W: Write>(self, writer: W) -> io::Result<()> {
+        self.encode()?.write_to(writer)
+    }
+
+    /// Consume the chunk, compressing its contents if needed, so that it's
+    /// ready to be written out.
+    pub fn encode(self) -> io::Result<EncodedChunk> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "rbx_binary::encode_chunk",
+            name = String::from_utf8_lossy(self.chunk_name).as_ref(),
+            len = match &self.buffer {
+                ChunkBuffer::Memory(buffer) => buffer.len(),
+                ChunkBuffer::Spilled { len, .. } => *len,
+            },
+        )
+        .entered();
+
+        let chunk_name = self.chunk_name;
+
+        Ok(match (self.buffer, self.compression) {
+            (ChunkBuffer::Memory(buffer), ChunkCompression::Compressed(options)) => {
+                match options.compress(&buffer)? {
+                    Some(data) => EncodedChunk::Compressed {
+                        chunk_name,
+                        data,
+                        len: buffer.len(),
+                    },
+                    None => EncodedChunk::Uncompressed {
+                        chunk_name,
+                        data: buffer,
+                    },
+                }
+            }
+            (ChunkBuffer::Memory(data), ChunkCompression::Uncompressed) => {
+                EncodedChunk::Uncompressed { chunk_name, data }
+            }
+            (ChunkBuffer::Spilled { file, len }, _) => EncodedChunk::Spilled {
+                chunk_name,
+                file: file.into_inner().map_err(|err| err.into_error())?,
+                len,
+            },
+        })
+    }
+
+    /// Encodes all of the given chunks, in parallel if the `rayon` feature is
+    /// enabled, and writes them to the given writer in order.
+    pub fn dump_all<W: Write>(chunks: Vec<ChunkBuilder>, mut writer: W) -> io::Result<()> {
+        #[cfg(feature = "rayon")]
+        let encoded: Vec<_> = {
+            use rayon::prelude::*;
+
+            // Chunks are encoded on other threads, so their spans are given
+            // this thread's span as a parent by hand.
+            #[cfg(feature = "tracing")]
+            let span = tracing::Span::current();
+
+            chunks
+                .into_par_iter()
+                .map(|chunk| {
+                    #[cfg(feature = "tracing")]
+                    let _span = span.enter();
 
-        match self.compression {
-            ChunkCompression::Compressed => {
-                let compressed = lz4::block::compress(&self.buffer, None, false)?;
+                    chunk.encode()
+                })
+                .collect()
+        };
+
+        #[cfg(not(feature = "rayon"))]
+        let encoded: Vec<_> = chunks.into_iter().map(ChunkBuilder::encode).collect();
+
+        for chunk in encoded {
+            chunk?.write_to(&mut writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A chunk whose contents are ready to be written to a file.
+pub enum EncodedChunk {
+    Compressed {
+        chunk_name: &'static [u8],
+        data: Vec<u8>,
+        len: usize,
+    },
+    Uncompressed {
+        chunk_name: &'static [u8],
+        data: Vec<u8>,
+    },
+    Spilled {
+        chunk_name: &'static [u8],
+        file: File,
+        len: usize,
+    },
+}
 
-                writer.write_le_u32(compressed.len() as u32)?;
-                writer.write_le_u32(self.buffer.len() as u32)?;
+impl EncodedChunk {
+    /// Write this chunk, including its header, to the given writer.
+    pub fn write_to<W: Write>(self, mut writer: W) -> io::Result<()> {
+        match self {
+            EncodedChunk::Compressed {
+                chunk_name,
+                data,
+                len,
+            } => {
+                writer.write_all(chunk_name)?;
+                writer.write_le_u32(data.len() as u32)?;
+                writer.write_le_u32(len as u32)?;
                 writer.write_le_u32(0)?;
 
-                writer.write_all(&compressed)?;
+                writer.write_all(&data)?;
             }
-            ChunkCompression::Uncompressed => {
+            EncodedChunk::Uncompressed { chunk_name, data } => {
+                writer.write_all(chunk_name)?;
                 writer.write_le_u32(0)?;
-                writer.write_le_u32(self.buffer.len() as u32)?;
+                writer.write_le_u32(data.len() as u32)?;
                 writer.write_le_u32(0)?;
 
-                writer.write_all(&self.buffer)?;
+                writer.write_all(&data)?;
+            }
+            EncodedChunk::Spilled {
+                chunk_name,
+                mut file,
+                len,
+            } => {
+                file.seek(SeekFrom::Start(0))?;
+
+                writer.write_all(chunk_name)?;
+                writer.write_le_u32(0)?;
+                writer.write_le_u32(len as u32)?;
+                writer.write_le_u32(0)?;
+
+                io::copy(&mut file.take(len as u64), &mut writer)?;
             }
         }
 
@@ -104,7 +547,38 @@ impl ChunkBuilder {
 
 impl Write for ChunkBuilder {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.buffer.write(buf)
+        match &mut self.buffer {
+            ChunkBuffer::Memory(buffer) => {
+                let over_threshold = self
+                    .spill_threshold
+                    .is_some_and(|threshold| buffer.len() + buf.len() > threshold);
+
+                if !over_threshold {
+                    return buffer.write(buf);
+                }
+
+                log::trace!(
+                    "Spilling {} bytes of chunk {:?} to disk",
+                    buffer.len(),
+                    str::from_utf8(self.chunk_name)
+                );
+
+                let mut file = BufWriter::new(spill_file()?);
+                file.write_all(buffer)?;
+                file.write_all(buf)?;
+
+                let len = buffer.len() + buf.len();
+                self.buffer = ChunkBuffer::Spilled { file, len };
+
+                Ok(buf.len())
+            }
+            ChunkBuffer::Spilled { file, len } => {
+                file.write_all(buf)?;
+                *len += buf.len();
+
+                Ok(buf.len())
+            }
+        }
     }
 
     fn flush(&mut self) -> io::Result<()> {
@@ -112,23 +586,49 @@ impl Write for ChunkBuilder {
     }
 }
 
-#[derive(Debug)]
-struct ChunkHeader {
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkHeader {
     /// 4-byte short name for the chunk, like "INST" or "PRNT"
-    name: [u8; 4],
+    pub name: [u8; 4],
 
     /// The length of the chunk's compressed data. For uncompressed chunks, this
     /// is always zero.
-    compressed_len: u32,
+    pub compressed_len: u32,
 
     /// The length that the chunk's data will have when decompressed. For
     /// uncompressed chunks, this is their length as-is.
-    len: u32,
+    pub len: u32,
 
     /// Always zero.
     reserved: u32,
 }
 
+impl ChunkHeader {
+    /// The number of bytes that follow this header in the file.
+    pub fn stored_len(&self) -> u32 {
+        if self.compressed_len == 0 {
+            self.len
+        } else {
+            self.compressed_len
+        }
+    }
+
+    /// Checks that neither the stored nor the decompressed contents of this
+    /// chunk are larger than `max_len`, if it's set.
+    pub fn check_len(&self, max_len: Option<usize>) -> Result<(), ChunkError> {
+        let len = self.len.max(self.stored_len()) as u64;
+
+        match max_len {
+            Some(max) if len > max as u64 => Err(ChunkError::TooLarge {
+                name: self.name,
+                len,
+                max,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
 impl fmt::Display for ChunkHeader {
     fn fmt(&self, output: &mut fmt::Formatter) -> fmt::Result {
         let name = if let Ok(name) = str::from_utf8(&self.name) {
@@ -145,7 +645,7 @@ impl fmt::Display for ChunkHeader {
     }
 }
 
-fn decode_chunk_header<R: Read>(source: &mut R) -> io::Result<ChunkHeader> {
+pub fn decode_chunk_header<R: Read>(source: &mut R) -> io::Result<ChunkHeader> {
     let mut name = [0; 4];
     source.read_exact(&mut name)?;
 
@@ -154,10 +654,13 @@ fn decode_chunk_header<R: Read>(source: &mut R) -> io::Result<ChunkHeader> {
     let reserved = source.read_le_u32()?;
 
     if reserved != 0 {
-        panic!(
-            "Chunk reserved space was not zero, it was {}. This chunk may be malformed.",
-            reserved
-        );
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Chunk reserved space was not zero, it was {}. This chunk may be malformed.",
+                reserved
+            ),
+        ));
     }
 
     Ok(ChunkHeader {
@@ -0,0 +1,191 @@
+//! The low-level encodings used for property data in the Roblox binary
+//! format.
+//!
+//! Most numeric arrays in PROP and PRNT chunks are stored *interleaved*: an
+//! array of `N`-byte values is written as the first byte of every value, then
+//! the second byte of every value, and so on. Before being interleaved,
+//! integers are zigzag encoded and floats have their sign bit moved to the
+//! end, which keeps small values mostly made of zero bytes and helps
+//! compression.
+//!
+//! These functions are the same ones rbx_binary uses internally, and are
+//! exposed for tools that want to read or write chunk contents themselves.
+//!
+//! ```
+//! use rbx_binary::codec;
+//!
+//! let encoded = codec::encode_i32_array(&[1, -1, 300]);
+//! assert_eq!(codec::decode_i32_array(&encoded)?, vec![1, -1, 300]);
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::io;
+
+use crate::core::{RbxReadExt, RbxWriteExt};
+
+pub use crate::core::{
+    transform_f32, transform_i32, transform_i64, untransform_f32, untransform_i32, untransform_i64,
+};
+
+/// Interleaves `bytes`, which holds values that are each `width` bytes long.
+///
+/// ## Panics
+/// Panics if `width` is zero or the length of `bytes` is not a multiple of
+/// `width`.
+pub fn interleave(bytes: &[u8], width: usize) -> Vec<u8> {
+    let len = value_count(bytes, width);
+    let mut output = vec![0; bytes.len()];
+
+    for (i, value) in bytes.chunks_exact(width).enumerate() {
+        for (j, byte) in value.iter().enumerate() {
+            output[i + len * j] = *byte;
+        }
+    }
+
+    output
+}
+
+/// The inverse of [`interleave`].
+///
+/// ## Panics
+/// Panics if `width` is zero or the length of `bytes` is not a multiple of
+/// `width`.
+pub fn deinterleave(bytes: &[u8], width: usize) -> Vec<u8> {
+    let len = value_count(bytes, width);
+    let mut output = vec![0; bytes.len()];
+
+    for (i, value) in output.chunks_exact_mut(width).enumerate() {
+        for (j, byte) in value.iter_mut().enumerate() {
+            *byte = bytes[i + len * j];
+        }
+    }
+
+    output
+}
+
+/// Encodes `values` as an interleaved array of transformed `i32`s, the
+/// format used by Int32 properties among others.
+pub fn encode_i32_array(values: &[i32]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(values.len() * 4);
+    output
+        .write_interleaved_i32_array(values.iter().copied())
+        .unwrap();
+    output
+}
+
+/// Decodes an array written by [`encode_i32_array`].
+///
+/// Returns an error if the length of `bytes` is not a multiple of 4.
+pub fn decode_i32_array(bytes: &[u8]) -> io::Result<Vec<i32>> {
+    let mut output = vec![0; checked_value_count(bytes, 4)?];
+    let mut reader = bytes;
+    reader.read_interleaved_i32_array(&mut output)?;
+    Ok(output)
+}
+
+/// Encodes `values` as an interleaved array of big-endian `u32`s, the format
+/// used by Enum and SharedString properties.
+pub fn encode_u32_array(values: &[u32]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(values.len() * 4);
+    output.write_interleaved_u32_array(values).unwrap();
+    output
+}
+
+/// Decodes an array written by [`encode_u32_array`].
+///
+/// Returns an error if the length of `bytes` is not a multiple of 4.
+pub fn decode_u32_array(bytes: &[u8]) -> io::Result<Vec<u32>> {
+    let mut output = vec![0; checked_value_count(bytes, 4)?];
+    let mut reader = bytes;
+    reader.read_interleaved_u32_array(&mut output)?;
+    Ok(output)
+}
+
+/// Encodes `values` as an interleaved array of transformed `i64`s, the
+/// format used by Int64 properties.
+pub fn encode_i64_array(values: &[i64]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(values.len() * 8);
+    output
+        .write_interleaved_i64_array(values.iter().copied())
+        .unwrap();
+    output
+}
+
+/// Decodes an array written by [`encode_i64_array`].
+///
+/// Returns an error if the length of `bytes` is not a multiple of 8.
+pub fn decode_i64_array(bytes: &[u8]) -> io::Result<Vec<i64>> {
+    let mut output = vec![0; checked_value_count(bytes, 8)?];
+    let mut reader = bytes;
+    reader.read_interleaved_i64_array(&mut output)?;
+    Ok(output)
+}
+
+/// Encodes `values` as an interleaved array of transformed `f32`s, the format
+/// used by Float32 properties and the components of most vector types.
+pub fn encode_f32_array(values: &[f32]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(values.len() * 4);
+    output
+        .write_interleaved_f32_array(values.iter().copied())
+        .unwrap();
+    output
+}
+
+/// Decodes an array written by [`encode_f32_array`].
+///
+/// Returns an error if the length of `bytes` is not a multiple of 4.
+pub fn decode_f32_array(bytes: &[u8]) -> io::Result<Vec<f32>> {
+    let mut output = vec![0.0; checked_value_count(bytes, 4)?];
+    let mut reader = bytes;
+    reader.read_interleaved_f32_array(&mut output)?;
+    Ok(output)
+}
+
+/// Encodes `values` as an array of referents: each value is stored as the
+/// difference from the one before it, then encoded like
+/// [`encode_i32_array`].
+pub fn encode_referent_array(values: &[i32]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(values.len() * 4);
+    output.write_referent_array(values.iter().copied()).unwrap();
+    output
+}
+
+/// Decodes an array written by [`encode_referent_array`].
+///
+/// Returns an error if the length of `bytes` is not a multiple of 4.
+pub fn decode_referent_array(bytes: &[u8]) -> io::Result<Vec<i32>> {
+    let mut output = vec![0; checked_value_count(bytes, 4)?];
+    let mut reader = bytes;
+    reader.read_referent_array(&mut output)?;
+    Ok(output)
+}
+
+fn value_count(bytes: &[u8], width: usize) -> usize {
+    assert!(
+        width > 0,
+        "interleaved values must be at least one byte wide"
+    );
+    assert!(
+        bytes.len().is_multiple_of(width),
+        "length {} is not a multiple of the value width {}",
+        bytes.len(),
+        width
+    );
+
+    bytes.len() / width
+}
+
+fn checked_value_count(bytes: &[u8], width: usize) -> io::Result<usize> {
+    if bytes.len().is_multiple_of(width) {
+        Ok(bytes.len() / width)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "length {} is not a multiple of the value width {}",
+                bytes.len(),
+                width
+            ),
+        ))
+    }
+}
@@ -1,11 +1,9 @@
 use std::{
     io::{self, Read, Write},
-    mem,
+    mem, str,
 };
 
-use rbx_reflection::{
-    ClassDescriptor, PropertyDescriptor, PropertyKind, PropertySerialization, ReflectionDatabase,
-};
+use rbx_reflection::ReflectionDatabase;
 
 pub static FILE_MAGIC_HEADER: &[u8] = b"<roblox!";
 pub static FILE_SIGNATURE: &[u8] = b"\x89\xff\x0d\x0a\x1a\x0a";
@@ -74,6 +72,7 @@ pub trait RbxReadExt: Read {
     /// no guarantees about encoding of things it calls strings. rbx_binary
     /// makes a semantic differentiation between strings and binary buffers,
     /// which makes it more strict than Roblox but more likely to be correct.
+    #[cfg(any(test, feature = "unstable_text_format"))]
     fn read_binary_string(&mut self) -> io::Result<Vec<u8>> {
         let length = self.read_le_u32()?;
 
@@ -86,6 +85,7 @@ pub trait RbxReadExt: Read {
     /// Read a UTF-8 encoded string encoded how Roblox model files encode
     /// strings. This function isn't always appropriate because Roblox's formats
     /// generally aren't dilligent about data being valid Unicode.
+    #[cfg(any(test, feature = "unstable_text_format"))]
     fn read_string(&mut self) -> io::Result<String> {
         let length = self.read_le_u32()?;
         let mut value = String::with_capacity(length as usize);
@@ -160,7 +160,7 @@ pub trait RbxReadExt: Read {
             let v2 = buf[i + output.len() * 2] as u32;
             let v3 = buf[i + output.len() * 3] as u32;
 
-            output[i] = f32::from_bits(((v0 << 24) | (v1 << 16) | (v2 << 8) | v3).rotate_right(1));
+            output[i] = untransform_f32((v0 << 24) | (v1 << 16) | (v2 << 8) | v3);
         }
         Ok(())
     }
@@ -170,8 +170,9 @@ pub trait RbxReadExt: Read {
 
         let mut last = 0;
 
+        // Corrupt files can contain deltas that overflow, which Roblox wraps.
         for referent in output.iter_mut() {
-            *referent += last;
+            *referent = referent.wrapping_add(last);
             last = *referent;
         }
 
@@ -210,6 +211,49 @@ pub trait RbxReadExt: Read {
 
 impl<R> RbxReadExt for R where R: Read {}
 
+/// Reads strings out of a buffer that's already in memory, like the contents
+/// of a decompressed chunk, by borrowing them from the buffer.
+///
+/// Most strings still need to be copied once to end up in the DOM, but this
+/// avoids allocating a buffer for values that are decoded into something
+/// else, like Tags and Attributes, and reads strings in one copy.
+pub trait RbxReadSliceExt<'a> {
+    /// Read a binary "string" like [`RbxReadExt::read_binary_string`],
+    /// without copying it.
+    fn read_binary_str(&mut self) -> io::Result<&'a [u8]>;
+
+    /// Read a UTF-8 encoded string like [`RbxReadExt::read_string`], without
+    /// copying it.
+    fn read_str(&mut self) -> io::Result<&'a str>;
+}
+
+impl<'a> RbxReadSliceExt<'a> for &'a [u8] {
+    fn read_binary_str(&mut self) -> io::Result<&'a [u8]> {
+        let length = self.read_le_u32()? as usize;
+
+        if self.len() < length {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "failed to fill whole buffer",
+            ));
+        }
+
+        let (value, rest) = self.split_at(length);
+        *self = rest;
+
+        Ok(value)
+    }
+
+    fn read_str(&mut self) -> io::Result<&'a str> {
+        str::from_utf8(self.read_binary_str()?).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )
+        })
+    }
+}
+
 pub trait RbxWriteExt: Write {
     fn write_le_u32(&mut self, value: u32) -> io::Result<()> {
         self.write_all(&value.to_le_bytes())?;
@@ -314,7 +358,7 @@ pub trait RbxWriteExt: Write {
 
         for shift in &[24, 16, 8, 0] {
             for value in values.iter().copied() {
-                let encoded = value.to_bits().rotate_left(1) >> shift;
+                let encoded = transform_f32(value) >> shift;
                 self.write_u8(encoded as u8)?;
             }
         }
@@ -328,7 +372,7 @@ pub trait RbxWriteExt: Write {
     {
         let mut last_value = 0;
         let delta_encoded = values.map(|value| {
-            let encoded = value - last_value;
+            let encoded = value.wrapping_sub(last_value);
             last_value = value;
             encoded
         });
@@ -366,144 +410,37 @@ pub fn untransform_i32(value: i32) -> i32 {
     ((value as u32) >> 1) as i32 ^ -(value & 1)
 }
 
+/// The 64-bit version of `transform_i32`.
 pub fn transform_i64(value: i64) -> i64 {
     (value << 1) ^ (value >> 63)
 }
 
+/// The inverse of `transform_i64`.
 pub fn untransform_i64(value: i64) -> i64 {
     ((value as u64) >> 1) as i64 ^ -(value & 1)
 }
 
-pub struct PropertyDescriptors<'a> {
-    pub canonical: &'a PropertyDescriptor<'a>,
-    pub serialized: Option<&'a PropertyDescriptor<'a>>,
+/// Applies the float transformation used in property data in the Roblox
+/// binary format, which moves the sign bit to the end.
+pub fn transform_f32(value: f32) -> u32 {
+    value.to_bits().rotate_left(1)
 }
 
-/// Find both the canonical and serialized property descriptors for a given
-/// class and property name pair. These might be the same descriptor!
-pub fn find_property_descriptors<'a>(
-    database: &'a ReflectionDatabase<'a>,
-    class_name: &str,
-    property_name: &str,
-) -> Option<PropertyDescriptors<'a>> {
-    let mut class_descriptor = database.classes.get(class_name)?;
-
-    // We need to find the canonical property descriptor associated with
-    // the property we're working with.
-    //
-    // At each step of the loop, we're checking a new class descriptor to see if
-    // it has an entry for the property name we're looking for. If that class
-    // doesn't have the property, we'll check its superclass until we reach the
-    // root.
-    loop {
-        // If this class descriptor knows about this property name, we're pretty
-        // much done!
-        if let Some(property_descriptor) = class_descriptor.properties.get(property_name) {
-            match &property_descriptor.kind {
-                // This property descriptor is the canonical form of this
-                // logical property. That means we've found one of the two
-                // descriptors we're looking for!
-                PropertyKind::Canonical { serialization } => {
-                    let serialized = find_serialized_from_canonical(
-                        class_descriptor,
-                        property_descriptor,
-                        serialization,
-                    );
-
-                    return Some(PropertyDescriptors {
-                        canonical: property_descriptor,
-                        serialized,
-                    });
-                }
-
-                // This descriptor is an alias for another property. While this
-                // descriptor might be one of the two descriptors we need to
-                // return, it's possible that both the canonical and serialized
-                // forms are different.
-                PropertyKind::Alias { alias_for } => {
-                    let canonical = class_descriptor.properties.get(alias_for.as_ref()).unwrap();
-
-                    if let PropertyKind::Canonical { serialization } = &canonical.kind {
-                        let serialized = find_serialized_from_canonical(
-                            class_descriptor,
-                            canonical,
-                            serialization,
-                        );
-
-                        return Some(PropertyDescriptors {
-                            canonical,
-                            serialized,
-                        });
-                    } else {
-                        // If one property in the database calls itself an alias
-                        // of another property, that property must be canonical.
-                        log::error!(
-                            "Property {}.{} is marked as an alias for {}.{}, but the latter is not canonical.",
-                            class_descriptor.name,
-                            property_descriptor.name,
-                            class_descriptor.name,
-                            alias_for
-                        );
-
-                        return None;
-                    }
-                }
-
-                // This descriptor is of an unknown kind and we don't know how
-                // to deal with it -- maybe rbx_binary is out of date?
-                _ => return None,
-            }
-        }
-
-        if let Some(superclass_name) = &class_descriptor.superclass {
-            // If a property descriptor isn't found in our class, check our
-            // superclass.
-
-            class_descriptor = database
-                .classes
-                .get(superclass_name)
-                .expect("Superclass in reflection database didn't exist");
-        } else {
-            // This property isn't known by any class in the reflection
-            // database.
-
-            return None;
-        }
-    }
+/// The inverse of `transform_f32`.
+pub fn untransform_f32(value: u32) -> f32 {
+    f32::from_bits(value.rotate_right(1))
 }
 
-/// Given the canonical property descriptor for a logical property along with
-/// its serialization, returns the serialized form of the logical property if
-/// this property is serializable.
-fn find_serialized_from_canonical<'a>(
-    class: &'a ClassDescriptor<'a>,
-    canonical: &'a PropertyDescriptor<'a>,
-    serialization: &'a PropertySerialization<'a>,
-) -> Option<&'a PropertyDescriptor<'a>> {
-    match serialization {
-        // This property serializes as-is. This is the happiest path: both the
-        // canonical and serialized descriptors are the same!
-        PropertySerialization::Serializes | PropertySerialization::Migrate { .. } => {
-            Some(canonical)
-        }
-
-        // This property serializes under an alias. That property should have a
-        // corresponding property descriptor within the same class descriptor.
-        PropertySerialization::SerializesAs(serialized_name) => {
-            let serialized_descriptor = class.properties.get(serialized_name.as_ref()).unwrap();
-
-            Some(serialized_descriptor)
-        }
-
-        // If this property does not serialize, there is no serialized
-        // descriptor to use.
-        PropertySerialization::DoesNotSerialize => None,
+/// The global reflection database from rbx_reflection_database, or `None` if
+/// the `reflection_database` feature is disabled.
+#[cfg(feature = "reflection_database")]
+pub fn reflection_database() -> Option<&'static ReflectionDatabase<'static>> {
+    Some(rbx_reflection_database::global())
+}
 
-        // This case will be hit if a new form of property serialization is
-        // introduced to the reflection database. This might happen if the
-        // database starts including more complex aliasing rules, like for the
-        // Grip properties of Tool, or the Rotation and Orientation properties
-        // of BasePart.
-        _ => None,
-    }
+/// The global reflection database from rbx_reflection_database, or `None` if
+/// the `reflection_database` feature is disabled.
+#[cfg(not(feature = "reflection_database"))]
+pub fn reflection_database() -> Option<&'static ReflectionDatabase<'static>> {
+    None
 }
@@ -0,0 +1,127 @@
+//! Access to the parts that make up a UnionOperation.
+//!
+//! When Studio performs a solid modeling operation, it stores the parts that
+//! went into it in the resulting instance's `ChildData` property, itself
+//! encoded as a binary model. Nested unions and negated parts keep their own
+//! `ChildData`, so the whole tree of primitives can be recovered from it.
+//!
+//! The triangle mesh in `MeshData` is obfuscated and isn't decoded here.
+//! Unions whose geometry is stored as a cloud asset (referenced by `AssetId`)
+//! have no `ChildData` at all.
+
+use rbx_dom_weak::{
+    types::{CFrame, Enum, Ref, Variant, Vector3},
+    Instance, WeakDom,
+};
+
+use crate::{deserializer::Error, Deserializer};
+
+/// A part that makes up a UnionOperation, found by [`union_primitives`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct CsgPrimitive {
+    /// The ClassName of the part, like `Part` or `WedgePart`.
+    pub class: String,
+
+    /// The Name of the part.
+    pub name: String,
+
+    /// Where the part was when the operation was performed.
+    pub cframe: CFrame,
+
+    /// The size of the part.
+    pub size: Vector3,
+
+    /// The part's `Shape` property, if it has one.
+    pub shape: Option<Enum>,
+
+    /// Whether this part is subtracted from the union instead of added to
+    /// it. Negating a part twice cancels out.
+    pub negated: bool,
+}
+
+/// Decodes the parts an instance was built from out of its `ChildData`
+/// property. Returns `None` if the instance has no `ChildData` or it's empty.
+pub fn decode_child_data(instance: &Instance) -> Option<Result<WeakDom, Error>> {
+    let data = child_data(instance)?;
+
+    Some(Deserializer::new().deserialize_slice(data))
+}
+
+/// Lists every primitive part that a UnionOperation, NegateOperation, or
+/// IntersectOperation was built from, descending into nested operations.
+///
+/// Returns an empty list if the instance has no `ChildData`.
+pub fn union_primitives(instance: &Instance) -> Result<Vec<CsgPrimitive>, Error> {
+    let mut primitives = Vec::new();
+    collect_primitives(instance, false, &mut primitives)?;
+
+    Ok(primitives)
+}
+
+fn collect_primitives(
+    instance: &Instance,
+    negated: bool,
+    primitives: &mut Vec<CsgPrimitive>,
+) -> Result<(), Error> {
+    let dom = match decode_child_data(instance) {
+        Some(dom) => dom?,
+        None => return Ok(()),
+    };
+
+    let mut to_visit: Vec<Ref> = dom.root().children().to_vec();
+    to_visit.reverse();
+
+    while let Some(referent) = to_visit.pop() {
+        let child = dom.get_by_ref(referent).unwrap();
+        let child_negated = negated ^ (child.class == "NegateOperation");
+
+        if child_data(child).is_some() {
+            collect_primitives(child, child_negated, primitives)?;
+        } else if let Some(primitive) = primitive(child, child_negated) {
+            primitives.push(primitive);
+        }
+
+        to_visit.extend(child.children().iter().rev());
+    }
+
+    Ok(())
+}
+
+fn primitive(instance: &Instance, negated: bool) -> Option<CsgPrimitive> {
+    let cframe = match instance.properties.get("CFrame") {
+        Some(Variant::CFrame(cframe)) => *cframe,
+        _ => return None,
+    };
+    let size = match instance.properties.get("Size") {
+        Some(Variant::Vector3(size)) => *size,
+        _ => return None,
+    };
+    let shape = match instance.properties.get("Shape") {
+        Some(Variant::Enum(shape)) => Some(*shape),
+        _ => None,
+    };
+
+    Some(CsgPrimitive {
+        class: instance.class.clone(),
+        name: instance.name.clone(),
+        cframe,
+        size,
+        shape,
+        negated,
+    })
+}
+
+fn child_data(instance: &Instance) -> Option<&[u8]> {
+    let data: &[u8] = match instance.properties.get("ChildData") {
+        Some(Variant::BinaryString(data)) => data.as_ref(),
+        Some(Variant::SharedString(data)) => data.data(),
+        _ => return None,
+    };
+
+    if data.is_empty() {
+        None
+    } else {
+        Some(data)
+    }
+}
@@ -0,0 +1,251 @@
+use std::io::{self, Read};
+
+use crate::chunk::{decode_chunk_header, RawChunk, CHUNK_HEADER_LEN};
+
+use super::{
+    error::InnerError,
+    header::{FileHeader, FILE_HEADER_LEN},
+    limits::DecodeLimits,
+    Error,
+};
+
+/// Iterates over the raw chunks of a Roblox binary model or place without
+/// decoding any instances, reporting where each chunk is and how large it is.
+///
+/// This is useful for analyzing what takes up space in a file, or for
+/// pinpointing the chunk that causes a file to fail to load.
+///
+/// ## Example
+/// ```no_run
+/// use std::fs::File;
+/// use std::io::BufReader;
+///
+/// use rbx_binary::ChunkReader;
+///
+/// let input = BufReader::new(File::open("Place.rbxl")?);
+///
+/// for chunk in ChunkReader::new(input)? {
+///     let chunk = chunk?;
+///     println!(
+///         "{:?} at {}: {} bytes ({} compressed)",
+///         chunk.kind(),
+///         chunk.offset(),
+///         chunk.uncompressed_len(),
+///         chunk.compressed_len(),
+///     );
+/// }
+///
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct ChunkReader<R> {
+    input: R,
+    header: FileHeader,
+    offset: u64,
+    read_contents: bool,
+    limits: DecodeLimits,
+    total_len: u64,
+    done: bool,
+}
+
+impl<R: Read> ChunkReader<R> {
+    /// Reads the file header from the given stream and creates a
+    /// `ChunkReader` positioned at the first chunk.
+    pub fn new(mut input: R) -> Result<Self, Error> {
+        let header = FileHeader::decode(&mut input)?;
+
+        Ok(Self {
+            input,
+            header,
+            offset: FILE_HEADER_LEN,
+            read_contents: false,
+            limits: DecodeLimits::new(),
+            total_len: 0,
+            done: false,
+        })
+    }
+
+    /// Sets whether the decompressed contents of each chunk should be
+    /// returned. By default, chunk contents are skipped over without being
+    /// decompressed.
+    pub fn read_contents(mut self, enabled: bool) -> Self {
+        self.read_contents = enabled;
+        self
+    }
+
+    /// Sets limits on how much will be decompressed when
+    /// [`read_contents`](Self::read_contents) is enabled. Chunks are checked
+    /// against [`DecodeLimits::max_chunk_len`] and
+    /// [`DecodeLimits::max_total_len`] before they're read, so that a chunk
+    /// header can't make the reader allocate as much memory as it likes. The
+    /// other limits don't apply, since no instances are decoded.
+    pub fn limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The number of instance types the file header says the file contains.
+    pub fn num_types(&self) -> u32 {
+        self.header.num_types
+    }
+
+    /// The number of instances the file header says the file contains.
+    pub fn num_instances(&self) -> u32 {
+        self.header.num_instances
+    }
+
+    fn read_chunk(&mut self) -> Result<ChunkInfo, InnerError> {
+        let header = decode_chunk_header(&mut self.input)?;
+        let stored_len = header.stored_len();
+
+        let contents = if self.read_contents {
+            header.check_len(self.limits.chunk_len())?;
+
+            self.total_len += header.len as u64;
+            self.limits.check_total_len(self.total_len)?;
+
+            let raw_chunk = RawChunk::read_body(header, &mut self.input)?;
+            Some(raw_chunk.decompress()?.data.into_owned())
+        } else {
+            let skipped = io::copy(
+                &mut (&mut self.input).take(stored_len as u64),
+                &mut io::sink(),
+            )?;
+
+            if skipped < stored_len as u64 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "file ended in the middle of a chunk",
+                )
+                .into());
+            }
+
+            None
+        };
+
+        let info = ChunkInfo {
+            name: header.name,
+            offset: self.offset,
+            compressed_len: header.compressed_len,
+            uncompressed_len: header.len,
+            contents,
+        };
+
+        self.offset += CHUNK_HEADER_LEN + stored_len as u64;
+
+        Ok(info)
+    }
+}
+
+impl<R: Read> Iterator for ChunkReader<R> {
+    type Item = Result<ChunkInfo, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result = self.read_chunk();
+        self.done = match &result {
+            Ok(chunk) => chunk.kind() == ChunkKind::End,
+            Err(_) => true,
+        };
+
+        Some(result.map_err(Error::from))
+    }
+}
+
+/// The kind of a chunk, determined by its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChunkKind {
+    /// A `META` chunk, containing metadata about the file.
+    Meta,
+
+    /// An `SSTR` chunk, containing the file's SharedStrings.
+    SharedStrings,
+
+    /// A `SIGN` chunk, containing signatures that newer versions of Roblox
+    /// Studio add to some files.
+    Signatures,
+
+    /// An `INST` chunk, declaring every instance of one class.
+    Instances,
+
+    /// A `PROP` chunk, containing the values of one property for every
+    /// instance of one class.
+    Properties,
+
+    /// A `PRNT` chunk, containing the parent of every instance.
+    Parents,
+
+    /// The `END` chunk that marks the end of the file.
+    End,
+
+    /// A chunk with a name that rbx_binary doesn't know about.
+    Unknown,
+}
+
+impl ChunkKind {
+    /// Finds the kind of a chunk from its four byte name.
+    pub(crate) fn from_name(name: &[u8; 4]) -> Self {
+        match name {
+            b"META" => ChunkKind::Meta,
+            b"SSTR" => ChunkKind::SharedStrings,
+            b"SIGN" => ChunkKind::Signatures,
+            b"INST" => ChunkKind::Instances,
+            b"PROP" => ChunkKind::Properties,
+            b"PRNT" => ChunkKind::Parents,
+            b"END\0" => ChunkKind::End,
+            _ => ChunkKind::Unknown,
+        }
+    }
+}
+
+/// Information about a single chunk read by a [`ChunkReader`].
+#[derive(Debug, Clone)]
+pub struct ChunkInfo {
+    name: [u8; 4],
+    offset: u64,
+    compressed_len: u32,
+    uncompressed_len: u32,
+    contents: Option<Vec<u8>>,
+}
+
+impl ChunkInfo {
+    /// The kind of this chunk.
+    pub fn kind(&self) -> ChunkKind {
+        ChunkKind::from_name(&self.name)
+    }
+
+    /// The raw four byte name of this chunk, like `b"PROP"`.
+    pub fn name(&self) -> &[u8; 4] {
+        &self.name
+    }
+
+    /// The offset of this chunk's header from the start of the file.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Whether this chunk's contents are compressed.
+    pub fn is_compressed(&self) -> bool {
+        self.compressed_len != 0
+    }
+
+    /// The size of this chunk's contents as stored in the file, or zero if
+    /// the chunk is not compressed.
+    pub fn compressed_len(&self) -> u32 {
+        self.compressed_len
+    }
+
+    /// The size of this chunk's contents once decompressed.
+    pub fn uncompressed_len(&self) -> u32 {
+        self.uncompressed_len
+    }
+
+    /// The decompressed contents of this chunk, if the `ChunkReader` was
+    /// configured to read them.
+    pub fn contents(&self) -> Option<&[u8]> {
+        self.contents.as_deref()
+    }
+}
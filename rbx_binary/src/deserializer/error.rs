@@ -1,8 +1,11 @@
-use std::io;
+use std::{fmt, io};
 
+use rbx_dom_weak::types::{DomError, ErrorCode, ErrorLocation, Ref};
 use thiserror::Error;
 
-use crate::types::InvalidTypeError;
+use crate::{chunk::ChunkError, types::InvalidTypeError};
+
+use super::report::DecodeWarning;
 
 /// Represents an error that occurred during deserialization.
 #[derive(Debug, Error)]
@@ -11,6 +14,101 @@ pub struct Error {
     source: Box<InnerError>,
 }
 
+impl Error {
+    /// Tells whether this error happened because deserialization was
+    /// cancelled with a
+    /// [`Deserializer::cancel_token`][crate::Deserializer::cancel_token].
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.source.root(), InnerError::Cancelled)
+    }
+
+    /// Tells whether this error happened because the file was larger than
+    /// one of the deserializer's [`DecodeLimits`][crate::DecodeLimits].
+    pub fn is_limit_exceeded(&self) -> bool {
+        matches!(self.source.root(), InnerError::LimitExceeded { .. })
+    }
+
+    /// The index of the chunk that was being read or decoded when the error
+    /// happened, counting from zero, if the error happened in a chunk.
+    pub fn chunk_index(&self) -> Option<usize> {
+        self.chunk_location().map(|(index, _, _)| index)
+    }
+
+    /// The byte offset from the start of the file of the chunk that was being
+    /// read or decoded when the error happened.
+    pub fn offset(&self) -> Option<u64> {
+        self.chunk_location().map(|(_, offset, _)| offset)
+    }
+
+    /// How many bytes into the chunk's decompressed contents decoding had
+    /// gotten when the error happened, if the chunk was read successfully.
+    pub fn position_in_chunk(&self) -> Option<usize> {
+        self.chunk_location().and_then(|(_, _, position)| position)
+    }
+
+    fn chunk_location(&self) -> Option<(usize, u64, Option<usize>)> {
+        match &*self.source {
+            InnerError::InChunk {
+                index,
+                offset,
+                position,
+                ..
+            } => Some((*index, *offset, *position)),
+            _ => None,
+        }
+    }
+}
+
+impl DomError for Error {
+    fn code(&self) -> ErrorCode {
+        self.source.root().code()
+    }
+
+    fn location(&self) -> Option<ErrorLocation> {
+        self.chunk_location()
+            .map(|(index, offset, position)| ErrorLocation::Chunk {
+                index,
+                offset,
+                position,
+            })
+    }
+
+    fn referent(&self) -> Option<Ref> {
+        match self.source.root() {
+            InnerError::UnknownEnumValue { referent, .. }
+            | InnerError::Warning {
+                warning: DecodeWarning::UnknownEnumValue { referent, .. },
+            } => Some(*referent),
+            _ => None,
+        }
+    }
+
+    fn class_name(&self) -> Option<&str> {
+        self.source
+            .root()
+            .names()
+            .map(|(class_name, _)| class_name)
+            .or_else(|| self.context()?.class_name.as_deref())
+    }
+
+    fn property_name(&self) -> Option<&str> {
+        self.source
+            .root()
+            .names()
+            .and_then(|(_, property_name)| property_name)
+            .or_else(|| self.context()?.property_name.as_deref())
+    }
+}
+
+impl Error {
+    fn context(&self) -> Option<&ChunkContext> {
+        match &*self.source {
+            InnerError::InChunk { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+}
+
 impl From<InnerError> for Error {
     fn from(inner: InnerError) -> Self {
         Self {
@@ -30,6 +128,49 @@ pub(crate) enum InnerError {
     #[error("Invalid file header")]
     BadHeader,
 
+    #[error("Deserialization was cancelled")]
+    Cancelled,
+
+    #[error("{source} (in {context}, chunk {index} at byte {offset}{})", describe_position(*.position))]
+    InChunk {
+        index: usize,
+        offset: u64,
+        position: Option<usize>,
+        context: ChunkContext,
+        source: Box<InnerError>,
+    },
+
+    #[error(
+        "{count} values of {width} bytes each can't fit in the {available} bytes left in the chunk"
+    )]
+    CountTooLarge {
+        count: u32,
+        width: usize,
+        available: usize,
+    },
+
+    #[error("Strict mode: {len} unexpected bytes were left at the end of the chunk")]
+    TrailingData { len: usize },
+
+    #[error("Strict mode: unknown chunk {name}")]
+    UnknownChunk { name: String },
+
+    #[error("Strict mode: instance ID {id} was declared more than once")]
+    DuplicateInstanceId { id: i32 },
+
+    #[error("Instance ID {id} was given a parent more than once")]
+    DuplicateParent { id: i32 },
+
+    #[error("Strict mode: {warning:?}")]
+    Warning { warning: DecodeWarning },
+
+    #[error("File exceeded the limit on {limit}: {value} is more than the maximum of {max}")]
+    LimitExceeded {
+        limit: &'static str,
+        value: u64,
+        max: u64,
+    },
+
     #[error("Unknown file version {version}. Known versions are: 0")]
     UnknownFileVersion { version: u16 },
 
@@ -63,9 +204,21 @@ pub(crate) enum InnerError {
         actual_value: String,
     },
 
+    #[error("Property {type_name}.{prop_name} had the value {value}, which is not an item of the enum {enum_name}")]
+    UnknownEnumValue {
+        type_name: String,
+        prop_name: String,
+        enum_name: String,
+        value: u32,
+        referent: Ref,
+    },
+
     #[error("File referred to type ID {type_id}, which was not declared")]
     InvalidTypeId { type_id: u32 },
 
+    #[error("File referred to instance ID {id}, which was not declared")]
+    InvalidInstanceId { id: i32 },
+
     #[error("Invalid property data: CFrame property {type_name}.{prop_name} had an invalid rotation ID {id:02x}")]
     BadRotationId {
         type_name: String,
@@ -87,3 +240,146 @@ pub(crate) enum InnerError {
         class_name: String,
     },
 }
+
+impl InnerError {
+    /// The error that caused this one, looking past any information about
+    /// where it happened.
+    pub(crate) fn root(&self) -> &InnerError {
+        match self {
+            InnerError::InChunk { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
+    pub(crate) fn code(&self) -> ErrorCode {
+        match self {
+            InnerError::Io { source } if source.kind() == io::ErrorKind::UnexpectedEof => {
+                ErrorCode::UnexpectedEof
+            }
+            InnerError::Io { .. } => ErrorCode::Io,
+            InnerError::BadHeader => ErrorCode::InvalidHeader,
+            InnerError::Cancelled => ErrorCode::Cancelled,
+            InnerError::InChunk { source, .. } => source.code(),
+            InnerError::CountTooLarge { .. } => ErrorCode::CountTooLarge,
+            InnerError::TrailingData { .. } => ErrorCode::TrailingData,
+            InnerError::UnknownChunk { .. } => ErrorCode::UnknownChunk,
+            InnerError::DuplicateInstanceId { .. } | InnerError::DuplicateParent { .. } => {
+                ErrorCode::DuplicateReferent
+            }
+            InnerError::Warning { .. } => ErrorCode::StrictWarning,
+            InnerError::LimitExceeded { .. } => ErrorCode::LimitExceeded,
+            InnerError::UnknownFileVersion { .. } | InnerError::UnknownChunkVersion { .. } => {
+                ErrorCode::UnknownVersion
+            }
+            InnerError::InvalidTypeError { .. } => ErrorCode::UnsupportedPropertyType,
+            InnerError::PropTypeMismatch { .. } => ErrorCode::PropertyTypeMismatch,
+            InnerError::InvalidPropData { .. } | InnerError::BadOptionalCFrameFormat { .. } => {
+                ErrorCode::InvalidPropertyValue
+            }
+            InnerError::UnknownEnumValue { .. } => ErrorCode::UnknownEnumValue,
+            InnerError::InvalidTypeId { .. } => ErrorCode::UnknownTypeId,
+            InnerError::InvalidInstanceId { .. } => ErrorCode::UnknownReferent,
+            InnerError::BadRotationId { .. } => ErrorCode::InvalidRotation,
+            InnerError::BadPropertyValue { source, .. } => source.code(),
+        }
+    }
+
+    /// The ClassName and property name that this error is about, if it
+    /// names them itself.
+    fn names(&self) -> Option<(&str, Option<&str>)> {
+        match self {
+            InnerError::PropTypeMismatch {
+                type_name,
+                prop_name,
+                ..
+            }
+            | InnerError::InvalidPropData {
+                type_name,
+                prop_name,
+                ..
+            }
+            | InnerError::UnknownEnumValue {
+                type_name,
+                prop_name,
+                ..
+            }
+            | InnerError::BadRotationId {
+                type_name,
+                prop_name,
+                ..
+            }
+            | InnerError::BadPropertyValue {
+                class_name: type_name,
+                prop_name,
+                ..
+            } => Some((type_name, Some(prop_name))),
+
+            InnerError::Warning { warning } => match warning {
+                DecodeWarning::UnknownClass { class_name, .. }
+                | DecodeWarning::DuplicateReferent { class_name, .. } => Some((class_name, None)),
+                DecodeWarning::UnknownPropertyType {
+                    class_name,
+                    property_name,
+                    ..
+                }
+                | DecodeWarning::PropTypeMismatch {
+                    class_name,
+                    property_name,
+                    ..
+                }
+                | DecodeWarning::UnknownEnumValue {
+                    class_name,
+                    property_name,
+                    ..
+                } => Some((class_name, Some(property_name))),
+            },
+
+            _ => None,
+        }
+    }
+}
+
+/// Describes the chunk that an error happened in, like `PROP chunk for
+/// Part.Size`, along with the class and property it's for if its header
+/// could be read.
+#[derive(Debug, Default)]
+pub(crate) struct ChunkContext {
+    pub description: String,
+    pub class_name: Option<String>,
+    pub property_name: Option<String>,
+}
+
+impl ChunkContext {
+    pub fn new(description: impl Into<String>) -> Self {
+        ChunkContext {
+            description: description.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for ChunkContext {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&self.description)
+    }
+}
+
+fn describe_position(position: Option<usize>) -> String {
+    match position {
+        Some(position) => format!(", {} bytes into its contents", position),
+        None => String::new(),
+    }
+}
+
+impl From<ChunkError> for InnerError {
+    fn from(err: ChunkError) -> Self {
+        match err {
+            ChunkError::TooLarge { len, max, .. } => InnerError::LimitExceeded {
+                limit: "chunk size",
+                value: len,
+                max: max as u64,
+            },
+            err => InnerError::Io { source: err.into() },
+        }
+    }
+}
@@ -4,6 +4,9 @@ use crate::core::{RbxReadExt, FILE_MAGIC_HEADER, FILE_SIGNATURE, FILE_VERSION};
 
 use super::error::InnerError;
 
+/// The size of the file header that comes before the first chunk.
+pub(crate) const FILE_HEADER_LEN: u64 = 32;
+
 /// All the information contained in the header before any chunks are read from
 /// the file.
 pub(crate) struct FileHeader {
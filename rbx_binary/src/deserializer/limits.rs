@@ -0,0 +1,104 @@
+use super::error::InnerError;
+
+/// Limits on how much a [`Deserializer`](crate::Deserializer) will decode,
+/// for reading files that can't be trusted without letting them use up all
+/// of the available memory.
+///
+/// Every limit is off by default. Files that break a limit fail to decode
+/// with an error, even in [recovery mode](crate::Deserializer::recover);
+/// [`Error::is_limit_exceeded`](crate::DecodeError::is_limit_exceeded) tells
+/// these errors apart from others.
+///
+/// ## Example
+/// ```
+/// use rbx_binary::{DecodeLimits, Deserializer};
+///
+/// let limits = DecodeLimits::new()
+///     .max_instances(Some(100_000))
+///     .max_string_len(Some(4 * 1024 * 1024))
+///     .max_chunk_len(Some(16 * 1024 * 1024))
+///     .max_total_len(Some(256 * 1024 * 1024));
+///
+/// let deserializer = Deserializer::new().limits(limits);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DecodeLimits {
+    max_instances: Option<usize>,
+    max_string_len: Option<usize>,
+    max_chunk_len: Option<usize>,
+    max_total_len: Option<u64>,
+}
+
+impl DecodeLimits {
+    /// Create a new `DecodeLimits` with no limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the most instances a file can contain. This is checked against
+    /// the count in the file header before anything is allocated for them,
+    /// and against the instances each INST chunk declares.
+    pub fn max_instances(mut self, max: Option<usize>) -> Self {
+        self.max_instances = max;
+        self
+    }
+
+    /// Sets the longest string, in bytes, that can appear anywhere in the
+    /// file, including class names, property names, and String or
+    /// BinaryString values.
+    pub fn max_string_len(mut self, max: Option<usize>) -> Self {
+        self.max_string_len = max;
+        self
+    }
+
+    /// Sets the largest a single chunk can be, in bytes, both as stored in
+    /// the file and once decompressed. This is checked against the chunk's
+    /// header before its contents are read.
+    pub fn max_chunk_len(mut self, max: Option<usize>) -> Self {
+        self.max_chunk_len = max;
+        self
+    }
+
+    /// Sets the largest the contents of every chunk in the file can be once
+    /// decompressed, added together. Since decoded values are built from
+    /// these contents, this roughly bounds how much memory the decoded file
+    /// will take up.
+    pub fn max_total_len(mut self, max: Option<u64>) -> Self {
+        self.max_total_len = max;
+        self
+    }
+
+    pub(crate) fn chunk_len(&self) -> Option<usize> {
+        self.max_chunk_len
+    }
+
+    pub(crate) fn check_instances(&self, count: usize) -> Result<(), InnerError> {
+        check(
+            "instances",
+            count as u64,
+            self.max_instances.map(|max| max as u64),
+        )
+    }
+
+    pub(crate) fn check_string<T: AsRef<[u8]> + ?Sized>(
+        &self,
+        value: &T,
+    ) -> Result<(), InnerError> {
+        check(
+            "string length",
+            value.as_ref().len() as u64,
+            self.max_string_len.map(|max| max as u64),
+        )
+    }
+
+    pub(crate) fn check_total_len(&self, len: u64) -> Result<(), InnerError> {
+        check("total decompressed size", len, self.max_total_len)
+    }
+}
+
+fn check(limit: &'static str, value: u64, max: Option<u64>) -> Result<(), InnerError> {
+    match max {
+        Some(max) if value > max => Err(InnerError::LimitExceeded { limit, value, max }),
+        _ => Ok(()),
+    }
+}
@@ -1,17 +1,65 @@
+mod chunk_reader;
 mod error;
 mod header;
+mod limits;
+mod progress;
+mod report;
+mod sink;
 mod state;
+mod stats;
 
-use std::{io::Read, str};
+use std::{
+    collections::HashSet,
+    io::Read,
+    str,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 use rbx_dom_weak::WeakDom;
 use rbx_reflection::ReflectionDatabase;
 
-use self::state::DeserializerState;
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt};
 
+use crate::{
+    chunk::{Chunk, ChunkError},
+    clock::Instant,
+    core::reflection_database,
+};
+
+use self::{
+    error::{ChunkContext, InnerError},
+    header::FILE_HEADER_LEN,
+    sink::DomSink,
+    state::DeserializerState,
+};
+
+#[cfg(any(test, feature = "unstable_text_format"))]
 pub(crate) use self::header::FileHeader;
 
-pub use self::error::Error;
+pub use self::{
+    chunk_reader::{ChunkInfo, ChunkKind, ChunkReader},
+    error::Error,
+    limits::DecodeLimits,
+    progress::DecodeProgress,
+    report::{Damage, DecodeReport, DecodeWarning, SkippedProperty, UnknownProperty},
+    sink::DecodeSink,
+    stats::{ChunkStats, ClassStats, DecodeStats},
+};
+
+/// A predicate deciding whether the properties of instances of a given class
+/// should be decoded.
+type ClassFilter<'a> = Box<dyn Fn(&str) -> bool + Send + Sync + 'a>;
+
+/// A predicate deciding whether a property of instances of a given class
+/// should be decoded.
+type PropertyFilter<'a> = Box<dyn Fn(&str, &str) -> bool + Send + Sync + 'a>;
+
+/// A callback told how far deserialization has gotten after each chunk.
+type ProgressCallback<'a> = Box<dyn Fn(&DecodeProgress) + Send + Sync + 'a>;
 
 /// A configurable deserializer for Roblox binary models and places.
 ///
@@ -40,44 +88,570 @@ pub use self::error::Error;
 /// ```
 pub struct Deserializer<'a> {
     database: Option<&'a ReflectionDatabase<'a>>,
+    reflection: bool,
+    class_filter: Option<ClassFilter<'a>>,
+    property_filter: Option<PropertyFilter<'a>>,
+    preserve_unknown_properties: bool,
+    unknown_enum_values: UnknownEnumValues,
+    migrate_properties: bool,
+    recover: bool,
+    strict: bool,
+    collect_stats: bool,
+    limits: DecodeLimits,
+    progress: Option<ProgressCallback<'a>>,
+    cancel_token: Option<Arc<AtomicBool>>,
+}
+
+/// What a [`Deserializer`] does with an Enum property whose value isn't one
+/// of the items of its enum in the reflection database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UnknownEnumValues {
+    /// The value is kept exactly as it is in the file. This is the default.
+    Preserve,
+
+    /// The value is replaced with the property's default value from the
+    /// reflection database, or with the enum's lowest item if the database
+    /// has no default for the property.
+    Default,
+
+    /// Deserialization fails with an error.
+    Error,
 }
 
 impl<'a> Deserializer<'a> {
     /// Create a new `Deserializer` with the default settings.
     pub fn new() -> Self {
         Self {
-            database: Some(rbx_reflection_database::get()),
+            database: reflection_database(),
+            reflection: true,
+            class_filter: None,
+            property_filter: None,
+            preserve_unknown_properties: false,
+            unknown_enum_values: UnknownEnumValues::Preserve,
+            migrate_properties: true,
+            recover: false,
+            strict: false,
+            collect_stats: false,
+            limits: DecodeLimits::new(),
+            progress: None,
+            cancel_token: None,
         }
     }
 
+    /// Only decode the properties of instances whose ClassName is accepted by
+    /// `filter`.
+    ///
+    /// Instances of every class are still created and placed in the tree with
+    /// their names, but all of their other properties are skipped without
+    /// being decoded. Use
+    /// [`deserialize_with_report`](Self::deserialize_with_report) to find out
+    /// which properties were skipped.
+    pub fn class_filter<F: Fn(&str) -> bool + Send + Sync + 'a>(mut self, filter: F) -> Self {
+        self.class_filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Only decode the properties of instances with one of the given
+    /// ClassNames. See [`class_filter`](Self::class_filter).
+    pub fn include_classes<I, S>(self, classes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let classes: HashSet<String> = classes.into_iter().map(Into::into).collect();
+        self.class_filter(move |class| classes.contains(class))
+    }
+
+    /// Decode the properties of every instance except those with one of the
+    /// given ClassNames. See [`class_filter`](Self::class_filter).
+    pub fn exclude_classes<I, S>(self, classes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let classes: HashSet<String> = classes.into_iter().map(Into::into).collect();
+        self.class_filter(move |class| !classes.contains(class))
+    }
+
+    /// Enables or disables the use of the reflection database. Defaults to
+    /// `true`.
+    ///
+    /// With reflection turned off, property names and types appear exactly as
+    /// they are stored in the file, like `Part.size` instead of `Part.Size`,
+    /// and no properties are migrated. Strings are decoded as BinaryStrings,
+    /// since their contents can't be known to be valid UTF-8. This is useful
+    /// for debugging the format, or for converting a file to another format
+    /// without losing anything rbx_binary doesn't know about; see
+    /// [`Serializer::reflection`][crate::Serializer::reflection].
+    ///
+    /// If rbx_binary is built without the `reflection_database` feature,
+    /// there is no database to use unless one is given with
+    /// [`reflection_database`](Self::reflection_database), so reflection is
+    /// off.
+    pub fn reflection(mut self, enabled: bool) -> Self {
+        self.reflection = enabled;
+        self
+    }
+
+    /// Sets the reflection database to use instead of
+    /// `rbx_reflection_database::global`, like one for the version of Roblox
+    /// that the files being read were saved with. These can come
+    /// from `rbx_reflection_database::get_bundled` or
+    /// `rbx_reflection_database::load`.
+    pub fn reflection_database(mut self, database: &'a ReflectionDatabase<'a>) -> Self {
+        self.database = Some(database);
+        self
+    }
+
+    /// The reflection database to use, if reflection is on.
+    pub(crate) fn database(&self) -> Option<&'a ReflectionDatabase<'a>> {
+        self.database.filter(|_| self.reflection)
+    }
+
+    /// Sets what happens to Enum properties with values that aren't part of
+    /// their enum, which can happen with files saved by newer versions of
+    /// Roblox or by other tools.
+    ///
+    /// Every such value is listed in the
+    /// [`DecodeReport`](DecodeReport::warnings) unless deserialization fails
+    /// because of it. Values are only checked when
+    /// [reflection](Self::reflection) is on.
+    pub fn unknown_enum_values(mut self, handling: UnknownEnumValues) -> Self {
+        self.unknown_enum_values = handling;
+        self
+    }
+
+    /// Determines whether properties that Roblox has replaced with newer ones
+    /// are migrated to them, like `TextLabel.Font` to `TextLabel.FontFace`.
+    /// Defaults to `true`.
+    ///
+    /// When this is disabled, older properties keep their names and values,
+    /// so files come out the way they were saved. See
+    /// [`ReflectionDatabase::migrate_property`][rbx_reflection::ReflectionDatabase::migrate_property]
+    /// for migrating them later. Properties are only migrated when
+    /// [reflection](Self::reflection) is on.
+    pub fn migrate_properties(mut self, enabled: bool) -> Self {
+        self.migrate_properties = enabled;
+        self
+    }
+
+    /// Only decode the properties accepted by `filter`, which is given the
+    /// ClassName of an instance and the name of one of its properties.
+    ///
+    /// Property names are the ones they'll have in the DOM, like `Size` instead
+    /// of `size`. The tree is still built in full, and Names are always
+    /// decoded. This is useful for tools that only need a few properties from
+    /// a large file, like the `Source` of every script. Use
+    /// [`deserialize_with_report`](Self::deserialize_with_report) to find out
+    /// which properties were skipped.
+    pub fn property_filter<F: Fn(&str, &str) -> bool + Send + Sync + 'a>(
+        mut self,
+        filter: F,
+    ) -> Self {
+        self.property_filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Only decode properties with one of the given names, on instances of
+    /// any class. See [`property_filter`](Self::property_filter).
+    pub fn include_properties<I, S>(self, properties: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let properties: HashSet<String> = properties.into_iter().map(Into::into).collect();
+        self.property_filter(move |_, property| properties.contains(property))
+    }
+
+    /// Keep the raw data of properties whose type rbx_binary doesn't
+    /// understand instead of dropping them. They are returned as part of the
+    /// [`DecodeReport`] from
+    /// [`deserialize_with_report`](Self::deserialize_with_report), and can be
+    /// written back out by
+    /// [`Serializer::unknown_properties`][crate::Serializer::unknown_properties].
+    pub fn preserve_unknown_properties(mut self, enabled: bool) -> Self {
+        self.preserve_unknown_properties = enabled;
+        self
+    }
+
+    /// Enables or disables recovery mode, for rescuing what's left of a damaged
+    /// file.
+    ///
+    /// In recovery mode, chunks that can't be decompressed or decoded are
+    /// skipped, a file that ends early or without an END chunk is accepted,
+    /// and instances that never had their parent decoded are placed at the
+    /// top level. Everything that was worked around is listed in the
+    /// [`DecodeReport`] returned by
+    /// [`deserialize_with_report`](Self::deserialize_with_report).
+    ///
+    /// Errors in the file header are still reported as errors, since nothing
+    /// can be recovered without it.
+    pub fn recover(mut self, enabled: bool) -> Self {
+        self.recover = enabled;
+        self
+    }
+
+    /// Sets limits on how large of a file will be decoded. See
+    /// [`DecodeLimits`].
+    pub fn limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Enables or disables collecting [`DecodeStats`], which measure how long
+    /// each chunk took to decode and how much of the file each class takes
+    /// up. The stats are part of the [`DecodeReport`] returned by
+    /// [`deserialize_with_report`](Self::deserialize_with_report). Defaults
+    /// to `false`.
+    pub fn collect_stats(mut self, enabled: bool) -> Self {
+        self.collect_stats = enabled;
+        self
+    }
+
+    /// Enables or disables strict mode, for checking that a file is
+    /// well-formed. Defaults to `false`.
+    ///
+    /// In strict mode, problems that would otherwise be tolerated are
+    /// errors: chunks with unexpected bytes left at the end, chunks with
+    /// unknown names, instance IDs that are declared more than once, and
+    /// anything that would be listed among the report's
+    /// [warnings](DecodeReport::warnings).
+    ///
+    /// Whether or not strict mode is on, errors in a chunk describe which
+    /// chunk it was, where it is in the file, and what was being decoded;
+    /// see [`Error::chunk_index`], [`Error::offset`], and
+    /// [`Error::position_in_chunk`].
+    pub fn strict(mut self, enabled: bool) -> Self {
+        self.strict = enabled;
+        self
+    }
+
+    /// Calls `callback` after each chunk of the file is decoded with how far
+    /// the deserializer has gotten, which is useful for showing a progress
+    /// bar while loading large places.
+    pub fn on_progress<F: Fn(&DecodeProgress) + Send + Sync + 'a>(mut self, callback: F) -> Self {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Sets a flag that cancels deserialization when set to `true`, from this
+    /// thread or any other.
+    ///
+    /// The flag is checked before each chunk is decoded. Once it's set,
+    /// deserialization stops with an error for which
+    /// [`Error::is_cancelled`] returns `true`.
+    pub fn cancel_token(mut self, token: Arc<AtomicBool>) -> Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel_token
+            .as_ref()
+            .is_some_and(|token| token.load(Ordering::Relaxed))
+    }
+
     /// Deserialize a Roblox binary model or place from the given stream using
     /// this deserializer.
     pub fn deserialize<R: Read>(&self, reader: R) -> Result<WeakDom, Error> {
         profiling::scope!("rbx_binary::deserialize");
 
-        let mut deserializer = DeserializerState::new(self, reader)?;
+        let (sink, _) = DeserializerState::run(self, reader, DomSink::new())?;
+        Ok(sink.finish())
+    }
+
+    /// Deserialize a Roblox binary model or place from the given stream,
+    /// returning a [`DecodeReport`] alongside the DOM that describes anything
+    /// that was skipped or preserved without being decoded.
+    pub fn deserialize_with_report<R: Read>(
+        &self,
+        reader: R,
+    ) -> Result<(WeakDom, DecodeReport), Error> {
+        profiling::scope!("rbx_binary::deserialize_with_report");
+
+        let (sink, report) = DeserializerState::run(self, reader, DomSink::new())?;
+        Ok((sink.finish(), report))
+    }
+
+    /// Deserialize a Roblox binary model or place from the given stream, handing
+    /// instances and properties to `sink` as they are decoded instead of
+    /// building a `WeakDom`.
+    ///
+    /// The stream is read one chunk at a time, so only the chunk currently
+    /// being decoded needs to be held in memory by the deserializer itself.
+    pub fn deserialize_into<R: Read, S: DecodeSink>(
+        &self,
+        reader: R,
+        sink: &mut S,
+    ) -> Result<(), Error> {
+        profiling::scope!("rbx_binary::deserialize_into");
+
+        DeserializerState::run(self, reader, sink)?;
+        Ok(())
+    }
+
+    /// Like [`deserialize_into`](Self::deserialize_into), but returns a
+    /// [`DecodeReport`] describing anything that was skipped or preserved
+    /// without being decoded.
+    pub fn deserialize_into_with_report<R: Read, S: DecodeSink>(
+        &self,
+        reader: R,
+        sink: &mut S,
+    ) -> Result<DecodeReport, Error> {
+        profiling::scope!("rbx_binary::deserialize_into_with_report");
+
+        let (_, report) = DeserializerState::run(self, reader, sink)?;
+        Ok(report)
+    }
+
+    /// Deserialize a Roblox binary model or place that's already in memory,
+    /// like a file that has been memory-mapped.
+    ///
+    /// Uncompressed chunks are decoded in place instead of being copied out
+    /// of `input` first, so this needs less memory than
+    /// [`deserialize`](Self::deserialize) for files that have them. This isn't
+    /// zero-copy: compressed chunks, which make up most files saved by
+    /// Roblox, are still decompressed into buffers of their own, and strings
+    /// and SharedStrings are copied into the DOM as it's built, since it owns
+    /// its values.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use rbx_binary::Deserializer;
+    ///
+    /// // A memory map from a crate like memmap2 works here too.
+    /// let input = std::fs::read("Place.rbxl")?;
+    ///
+    /// let dom = Deserializer::new().deserialize_slice(&input)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn deserialize_slice(&self, input: &[u8]) -> Result<WeakDom, Error> {
+        profiling::scope!("rbx_binary::deserialize_slice");
+
+        let (dom, _) = self.slice_with_report(input)?;
+        Ok(dom)
+    }
+
+    /// Like [`deserialize_slice`](Self::deserialize_slice), but returns a
+    /// [`DecodeReport`] alongside the DOM describing anything that was
+    /// skipped or preserved without being decoded.
+    pub fn deserialize_slice_with_report(
+        &self,
+        input: &[u8],
+    ) -> Result<(WeakDom, DecodeReport), Error> {
+        profiling::scope!("rbx_binary::deserialize_slice_with_report");
+
+        self.slice_with_report(input)
+    }
+
+    fn slice_with_report(&self, input: &[u8]) -> Result<(WeakDom, DecodeReport), Error> {
+        let header_len = input.len().min(FILE_HEADER_LEN as usize);
+        let (header, mut rest) = input.split_at(header_len);
+
+        let mut deserializer = DeserializerState::new(self, header, DomSink::new())?;
+
+        for index in 0.. {
+            let chunk = Chunk::decode_slice(&mut rest, self.limits.chunk_len());
+            if deserializer.handle_chunk(index, chunk)? {
+                break;
+            }
+        }
+
+        let (sink, report) = deserializer.finish();
+        Ok((sink.finish(), report))
+    }
+
+    /// Deserialize a Roblox binary model or place from the given asynchronous
+    /// stream using this deserializer.
+    ///
+    /// The stream is read one chunk at a time, so the file never needs to be
+    /// buffered in memory as a whole. Decoding each chunk happens on the
+    /// calling task.
+    #[cfg(feature = "tokio")]
+    pub async fn deserialize_async<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+    ) -> Result<WeakDom, Error> {
+        profiling::scope!("rbx_binary::deserialize_async");
 
-        loop {
-            let chunk = deserializer.next_chunk()?;
+        let (dom, _) = self.async_with_report(reader).await?;
+        Ok(dom)
+    }
+
+    /// Like [`deserialize_async`](Self::deserialize_async), but returns a
+    /// [`DecodeReport`] alongside the DOM describing anything that was
+    /// skipped or preserved without being decoded.
+    #[cfg(feature = "tokio")]
+    pub async fn deserialize_async_with_report<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+    ) -> Result<(WeakDom, DecodeReport), Error> {
+        profiling::scope!("rbx_binary::deserialize_async_with_report");
+
+        self.async_with_report(reader).await
+    }
+
+    #[cfg(feature = "tokio")]
+    async fn async_with_report<R: AsyncRead + Unpin>(
+        &self,
+        mut reader: R,
+    ) -> Result<(WeakDom, DecodeReport), Error> {
+        let mut header = [0; FILE_HEADER_LEN as usize];
+        reader
+            .read_exact(&mut header)
+            .await
+            .map_err(InnerError::from)?;
+
+        let mut deserializer = DeserializerState::new(self, header.as_slice(), DomSink::new())?;
+
+        for index in 0.. {
+            let chunk = Chunk::decode_async(&mut reader, self.limits.chunk_len()).await;
+            if deserializer.handle_chunk(index, chunk)? {
+                break;
+            }
+        }
 
-            match &chunk.name {
-                b"META" => deserializer.decode_meta_chunk(&chunk.data)?,
-                b"SSTR" => deserializer.decode_sstr_chunk(&chunk.data)?,
-                b"INST" => deserializer.decode_inst_chunk(&chunk.data)?,
-                b"PROP" => deserializer.decode_prop_chunk(&chunk.data)?,
-                b"PRNT" => deserializer.decode_prnt_chunk(&chunk.data)?,
-                b"END\0" => {
-                    deserializer.decode_end_chunk(&chunk.data)?;
-                    break;
+        let (sink, report) = deserializer.finish();
+        Ok((sink.finish(), report))
+    }
+}
+
+impl<'a, R: Read, S: DecodeSink> DeserializerState<'a, R, S> {
+    fn run(
+        deserializer: &'a Deserializer<'a>,
+        reader: R,
+        sink: S,
+    ) -> Result<(S, DecodeReport), Error> {
+        let mut deserializer = DeserializerState::new(deserializer, reader, sink)?;
+
+        for index in 0.. {
+            let chunk = deserializer.next_chunk();
+            if deserializer.handle_chunk(index, chunk)? {
+                break;
+            }
+        }
+
+        Ok(deserializer.finish())
+    }
+
+    /// Decodes the result of reading the chunk at `index`, returning whether
+    /// there's nothing left to read. In recovery mode, damaged chunks are
+    /// recorded in the report instead of returned as errors.
+    fn handle_chunk(
+        &mut self,
+        index: usize,
+        chunk: Result<Chunk<'_>, ChunkError>,
+    ) -> Result<bool, Error> {
+        let recover = self.deserializer.recover;
+
+        if self.deserializer.is_cancelled() {
+            return Err(InnerError::Cancelled.into());
+        }
+
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(ChunkError::Decompress {
+                name,
+                file_len,
+                source,
+            }) if recover => {
+                self.record_damage(Damage::CorruptChunk {
+                    index,
+                    name: String::from_utf8_lossy(&name).into_owned(),
+                    message: source.to_string(),
+                });
+                self.skip_chunk(file_len);
+                return Ok(false);
+            }
+            Err(ChunkError::Read(source)) if recover => {
+                self.record_damage(Damage::Truncated {
+                    index,
+                    message: source.to_string(),
+                });
+                return Ok(true);
+            }
+            Err(err) => {
+                let context = ChunkContext::new("reading the chunk");
+                return Err(self.locate(index, None, context, err.into()).into());
+            }
+        };
+
+        #[cfg(feature = "tracing")]
+        let _span = self.chunk_span(index, &chunk).entered();
+
+        if let Err(err) = self.check_total_len(&chunk) {
+            let context = self.chunk_context(&chunk);
+            return Err(self.locate(index, None, context, err).into());
+        }
+
+        let decode_started = Instant::now();
+        let mut contents: &[u8] = &chunk.data;
+        let is_end = match self.decode_chunk(&chunk.name, &mut contents) {
+            Ok(is_end) => is_end,
+            // Files that are too large shouldn't be decoded at all, so limits
+            // can't be recovered from.
+            Err(err) if recover && !matches!(err, InnerError::LimitExceeded { .. }) => {
+                self.record_damage(Damage::CorruptChunk {
+                    index,
+                    name: String::from_utf8_lossy(&chunk.name).into_owned(),
+                    message: err.to_string(),
+                });
+                false
+            }
+            Err(err) => {
+                let position = chunk.data.len() - contents.len();
+                let context = self.chunk_context(&chunk);
+                return Err(self.locate(index, Some(position), context, err).into());
+            }
+        };
+
+        self.record_chunk_stats(&chunk, decode_started.elapsed());
+        self.report_progress(&chunk);
+
+        Ok(is_end)
+    }
+
+    /// Decodes a single chunk with the given name, advancing `contents` past
+    /// what was decoded and returning whether it was the END chunk.
+    fn decode_chunk(&mut self, name: &[u8; 4], contents: &mut &[u8]) -> Result<bool, InnerError> {
+        match name {
+            b"META" => self.decode_meta_chunk(contents)?,
+            b"SSTR" => self.decode_sstr_chunk(contents)?,
+            b"SIGN" => self.decode_sign_chunk(contents)?,
+            b"INST" => self.decode_inst_chunk(contents)?,
+            b"PROP" => self.decode_prop_chunk(contents)?,
+            b"PRNT" => self.decode_prnt_chunk(contents)?,
+            b"END\0" => {
+                self.decode_end_chunk(contents)?;
+                return Ok(true);
+            }
+            _ => {
+                if self.deserializer.strict {
+                    return Err(InnerError::UnknownChunk {
+                        name: String::from_utf8_lossy(name).into_owned(),
+                    });
                 }
-                _ => match str::from_utf8(&chunk.name) {
+
+                match str::from_utf8(name) {
                     Ok(name) => log::info!("Unknown binary chunk name {}", name),
-                    Err(_) => log::info!("Unknown binary chunk name {:?}", chunk.name),
-                },
+                    Err(_) => log::info!("Unknown binary chunk name {:?}", name),
+                }
             }
         }
 
-        Ok(deserializer.finish())
+        // PROP chunks check for themselves, since they can be skipped
+        // without being read.
+        if self.deserializer.strict
+            && matches!(name, b"META" | b"SSTR" | b"INST" | b"PRNT")
+            && !contents.is_empty()
+        {
+            return Err(InnerError::TrailingData {
+                len: contents.len(),
+            });
+        }
+
+        Ok(false)
     }
 }
 
@@ -0,0 +1,36 @@
+use super::ChunkKind;
+
+/// How far a [`Deserializer`][crate::Deserializer] has gotten through a file,
+/// passed to the callback given to
+/// [`Deserializer::on_progress`][crate::Deserializer::on_progress] after
+/// each chunk is decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeProgress {
+    pub(crate) bytes_read: u64,
+    pub(crate) instances_decoded: usize,
+    pub(crate) total_instances: usize,
+    pub(crate) chunk: ChunkKind,
+}
+
+impl DecodeProgress {
+    /// The number of bytes of the file that have been read and decoded,
+    /// including the file header.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// The number of instances that have been declared by the file so far.
+    pub fn instances_decoded(&self) -> usize {
+        self.instances_decoded
+    }
+
+    /// The number of instances the file header says the file contains.
+    pub fn total_instances(&self) -> usize {
+        self.total_instances
+    }
+
+    /// The kind of the chunk that was just decoded.
+    pub fn chunk(&self) -> ChunkKind {
+        self.chunk
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+
+use rbx_dom_weak::types::{Ref, VariantType};
+
+use super::stats::DecodeStats;
+
+/// Extra information gathered while deserializing a file, returned by
+/// [`Deserializer::deserialize_with_report`][crate::Deserializer::deserialize_with_report].
+#[derive(Debug, Clone, Default)]
+pub struct DecodeReport {
+    pub(crate) skipped_properties: Vec<SkippedProperty>,
+    pub(crate) unknown_properties: Vec<UnknownProperty>,
+    pub(crate) damage: Vec<Damage>,
+    pub(crate) warnings: Vec<DecodeWarning>,
+    pub(crate) metadata: HashMap<String, String>,
+    pub(crate) signatures: Option<Vec<u8>>,
+    pub(crate) stats: Option<DecodeStats>,
+}
+
+impl DecodeReport {
+    /// Every property that was skipped because of the deserializer's class or
+    /// property filter, in the order they appeared in the file.
+    pub fn skipped_properties(&self) -> &[SkippedProperty] {
+        &self.skipped_properties
+    }
+
+    /// Every property with a type that rbx_binary doesn't understand, if the
+    /// deserializer was configured to preserve them.
+    pub fn unknown_properties(&self) -> &[UnknownProperty] {
+        &self.unknown_properties
+    }
+
+    /// Every problem that was worked around while decoding a damaged file, if
+    /// the deserializer was in recovery mode.
+    pub fn damage(&self) -> &[Damage] {
+        &self.damage
+    }
+
+    /// Tells whether any damage was found in the file. Only a deserializer in
+    /// recovery mode can find damage without returning an error.
+    pub fn is_damaged(&self) -> bool {
+        !self.damage.is_empty()
+    }
+
+    /// Every problem found in the file that didn't stop it from being decoded,
+    /// but may mean that some of its data is missing from the DOM, in the
+    /// order they were found.
+    pub fn warnings(&self) -> &[DecodeWarning] {
+        &self.warnings
+    }
+
+    /// The key/value pairs stored in the file's META chunk, like
+    /// `ExplicitAutoJoints`. These can be written back out with
+    /// [`Serializer::metadata`][crate::Serializer::metadata].
+    pub fn metadata(&self) -> &HashMap<String, String> {
+        &self.metadata
+    }
+
+    /// The raw contents of the file's SIGN chunk, if it has one. These can be
+    /// written back out with
+    /// [`Serializer::signatures`][crate::Serializer::signatures].
+    pub fn signatures(&self) -> Option<&[u8]> {
+        self.signatures.as_deref()
+    }
+
+    /// Measurements taken while deserializing, if the deserializer was
+    /// configured to [collect them](crate::Deserializer::collect_stats).
+    pub fn stats(&self) -> Option<&DecodeStats> {
+        self.stats.as_ref()
+    }
+
+    /// Consumes the report, returning the unknown properties it contains so
+    /// that they can be handed to
+    /// [`Serializer::unknown_properties`][crate::Serializer::unknown_properties].
+    pub fn into_unknown_properties(self) -> Vec<UnknownProperty> {
+        self.unknown_properties
+    }
+}
+
+/// Describes a PROP chunk that was skipped instead of decoded because it was
+/// excluded by the deserializer's class or property filter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedProperty {
+    class_name: String,
+    property_name: String,
+    size: usize,
+}
+
+impl SkippedProperty {
+    pub(crate) fn new(class_name: String, property_name: String, size: usize) -> Self {
+        Self {
+            class_name,
+            property_name,
+            size,
+        }
+    }
+
+    /// The ClassName of the instances whose property was skipped.
+    pub fn class_name(&self) -> &str {
+        &self.class_name
+    }
+
+    /// The name of the property as it appears in the file.
+    pub fn property_name(&self) -> &str {
+        &self.property_name
+    }
+
+    /// The size, in bytes, of the property data that was not decoded.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// The raw contents of a PROP chunk whose value type rbx_binary doesn't
+/// understand, usually because it was introduced by a newer version of Roblox
+/// Studio.
+///
+/// Because the values can't be split apart without knowing their type, the
+/// data covers every instance of the class at once. The instances it applies
+/// to are recorded in the order the file stored them in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownProperty {
+    pub(crate) class_name: String,
+    pub(crate) property_name: String,
+    pub(crate) type_id: u8,
+    pub(crate) referents: Vec<Ref>,
+    pub(crate) data: Vec<u8>,
+}
+
+impl UnknownProperty {
+    /// Creates an `UnknownProperty` from values that were stored somewhere
+    /// else, like in a file converted to another format. `data` must be
+    /// exactly what a binary file stores for the property, with the values
+    /// of `referents` in the same order.
+    pub fn new(
+        class_name: String,
+        property_name: String,
+        type_id: u8,
+        referents: Vec<Ref>,
+        data: Vec<u8>,
+    ) -> Self {
+        UnknownProperty {
+            class_name,
+            property_name,
+            type_id,
+            referents,
+            data,
+        }
+    }
+
+    /// The ClassName of the instances that have this property.
+    pub fn class_name(&self) -> &str {
+        &self.class_name
+    }
+
+    /// The name of the property as it appears in the file.
+    pub fn property_name(&self) -> &str {
+        &self.property_name
+    }
+
+    /// The binary format's ID for the type of this property.
+    pub fn type_id(&self) -> u8 {
+        self.type_id
+    }
+
+    /// The instances that this property's values belong to, in the order
+    /// their values appear in [`data`](Self::data).
+    pub fn referents(&self) -> &[Ref] {
+        &self.referents
+    }
+
+    /// The encoded values of this property, exactly as they appeared in the
+    /// file.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// A problem found in a damaged file by a deserializer in
+/// [recovery mode](crate::Deserializer::recover).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Damage {
+    /// A chunk was read from the file, but couldn't be decompressed or decoded
+    /// and was skipped. Part of its contents may have been decoded before the
+    /// problem was found.
+    CorruptChunk {
+        /// The position of the chunk in the file, starting from zero.
+        index: usize,
+
+        /// The name of the chunk, like `PROP` or `INST`.
+        name: String,
+
+        /// A description of what was wrong with the chunk.
+        message: String,
+    },
+
+    /// The file ended, or became impossible to read, before its END chunk.
+    /// Nothing past this point could be recovered.
+    Truncated {
+        /// The position in the file, starting from zero, of the chunk that
+        /// couldn't be read.
+        index: usize,
+
+        /// A description of why the chunk couldn't be read.
+        message: String,
+    },
+
+    /// Some instances never had their parent decoded, and were placed at the
+    /// top level of the tree instead.
+    MissingParents {
+        /// The number of instances that were missing a parent.
+        count: usize,
+    },
+
+    /// Some instances were given a parent more than once. Only the first
+    /// parent of each was kept.
+    DuplicateParents {
+        /// The number of parents that were ignored.
+        count: usize,
+    },
+}
+
+/// A problem found while decoding a file that rbx_binary worked around
+/// instead of returning an error.
+///
+/// Every warning records the offset of the chunk it was found in, from the
+/// start of the file, which can be matched up with the chunks returned by a
+/// [`ChunkReader`](crate::ChunkReader).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DecodeWarning {
+    /// The file declared instances of a class that isn't in the reflection
+    /// database. The instances are still decoded, but their properties are
+    /// given the types they were stored with.
+    UnknownClass {
+        /// The ClassName of the instances.
+        class_name: String,
+
+        /// The instances of the unknown class.
+        referents: Vec<Ref>,
+
+        /// The offset of the INST chunk declaring the class.
+        offset: u64,
+    },
+
+    /// A property was stored with a type that rbx_binary doesn't understand,
+    /// and was dropped from every instance that had it unless the
+    /// deserializer was configured to
+    /// [preserve](crate::Deserializer::preserve_unknown_properties) it.
+    UnknownPropertyType {
+        /// The ClassName of the instances that have the property.
+        class_name: String,
+
+        /// The name of the property as it appears in the file.
+        property_name: String,
+
+        /// The binary format's ID for the type of the property.
+        type_id: u8,
+
+        /// The instances that have the property.
+        referents: Vec<Ref>,
+
+        /// The offset of the PROP chunk containing the property.
+        offset: u64,
+    },
+
+    /// A property was stored with a type that can't be converted to the type
+    /// the reflection database gives it, and was dropped from every instance
+    /// that had it.
+    PropTypeMismatch {
+        /// The ClassName of the instances that have the property.
+        class_name: String,
+
+        /// The name of the property as it appears in the file.
+        property_name: String,
+
+        /// The binary format's ID for the type the property was stored with.
+        type_id: u8,
+
+        /// The type the reflection database says the property should have.
+        expected_type: VariantType,
+
+        /// The instances that have the property.
+        referents: Vec<Ref>,
+
+        /// The offset of the PROP chunk containing the property.
+        offset: u64,
+    },
+
+    /// An instance has an Enum property set to a value that isn't one of the
+    /// enum's items. See
+    /// [`Deserializer::unknown_enum_values`][crate::Deserializer::unknown_enum_values].
+    UnknownEnumValue {
+        /// The ClassName of the instance.
+        class_name: String,
+
+        /// The name of the property as it appears in the file.
+        property_name: String,
+
+        /// The name of the enum the property should be, like `Material`.
+        enum_name: String,
+
+        /// The value that isn't part of the enum.
+        value: u32,
+
+        /// The value the property was given instead, or `None` if the value
+        /// was kept as-is.
+        replaced_with: Option<u32>,
+
+        /// The instance with the unknown value.
+        referent: Ref,
+
+        /// The offset of the PROP chunk containing the property.
+        offset: u64,
+    },
+
+    /// More than one instance was declared with the same referent. Only the
+    /// last of them gets the properties and parent stored for that referent,
+    /// so the earlier one never gets a parent and is left out of the tree, or
+    /// placed at its root in [recovery mode](crate::Deserializer::recover).
+    /// [Strict mode](crate::Deserializer::strict) rejects these files instead.
+    DuplicateReferent {
+        /// The ClassName of the instance declared last.
+        class_name: String,
+
+        /// The referent as it appears in the file.
+        id: i32,
+
+        /// The instance that was declared first, which loses the referent.
+        replaced: Ref,
+
+        /// The instance that was declared last, which keeps the referent.
+        referent: Ref,
+
+        /// The offset of the INST chunk declaring the instance that was
+        /// declared last.
+        offset: u64,
+    },
+}
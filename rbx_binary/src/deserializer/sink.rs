@@ -0,0 +1,236 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use rbx_dom_weak::{
+    types::{Ref, Variant, VariantType},
+    InstanceBuilder, WeakDom,
+};
+
+/// Receives instances and properties from a [`Deserializer`][crate::Deserializer]
+/// as they are decoded, used with
+/// [`Deserializer::deserialize_into`][crate::Deserializer::deserialize_into].
+///
+/// Binary files store every instance of a class together, followed by every
+/// value of each property for that class, followed by the parent of every
+/// instance at the very end. Events are delivered in that same order:
+///
+/// 1. [`DecodeSink::instance`] is called once for every instance.
+/// 2. [`DecodeSink::name`] and [`DecodeSink::property`] are called for each
+///    property value as it is decoded.
+/// 3. [`DecodeSink::parent`] is called once for every instance, in the order
+///    that the instances should appear in their parent's children.
+///
+/// Values are handed to the sink as soon as they are decoded, so a sink that
+/// writes them somewhere else instead of holding onto them keeps memory use
+/// bounded by the size of the largest chunk in the file. Names, class names,
+/// and String and Content values are borrowed from the file's contents, so
+/// they're only copied if the sink keeps them.
+pub trait DecodeSink {
+    /// Called when an instance with the given ClassName is declared. Returns
+    /// the referent that the instance should be identified by in later calls,
+    /// and that Ref properties pointing to it will have.
+    fn instance(&mut self, class: &str) -> Ref;
+
+    /// Called when the Name of an instance is decoded.
+    fn name(&mut self, referent: Ref, name: &str);
+
+    /// Called when a property of an instance is decoded.
+    fn property(&mut self, referent: Ref, name: &str, value: Variant);
+
+    /// Called instead of [`DecodeSink::property`] when a String or Content
+    /// property is decoded, with the value borrowed from the file. `ty` is
+    /// either `VariantType::String` or `VariantType::Content`.
+    ///
+    /// By default, the value is copied into a [`Variant`] of that type and
+    /// passed to [`DecodeSink::property`].
+    fn str_property(&mut self, referent: Ref, name: &str, ty: VariantType, value: &str) {
+        let value = match ty {
+            VariantType::Content => Variant::Content(value.into()),
+            _ => Variant::String(value.to_owned()),
+        };
+
+        self.property(referent, name, value)
+    }
+
+    /// Called when the parent of an instance is decoded. A `parent` of
+    /// `Ref::none()` means the instance is at the top level of the file.
+    fn parent(&mut self, referent: Ref, parent: Ref);
+}
+
+impl<S: DecodeSink + ?Sized> DecodeSink for &mut S {
+    fn instance(&mut self, class: &str) -> Ref {
+        (**self).instance(class)
+    }
+
+    fn name(&mut self, referent: Ref, name: &str) {
+        (**self).name(referent, name)
+    }
+
+    fn property(&mut self, referent: Ref, name: &str, value: Variant) {
+        (**self).property(referent, name, value)
+    }
+
+    fn str_property(&mut self, referent: Ref, name: &str, ty: VariantType, value: &str) {
+        (**self).str_property(referent, name, ty, value)
+    }
+
+    fn parent(&mut self, referent: Ref, parent: Ref) {
+        (**self).parent(referent, parent)
+    }
+}
+
+/// The `DecodeSink` used by [`Deserializer::deserialize`][crate::Deserializer::deserialize],
+/// which collects everything into a `WeakDom` with a `DataModel` at its root.
+pub(crate) struct DomSink {
+    /// All of the instances that have been declared but not yet inserted into
+    /// the tree.
+    instances: HashMap<Ref, PendingInstance>,
+
+    /// Referents for all of the instances with no parent, in order they appear
+    /// in the file.
+    root_refs: Vec<Ref>,
+
+    /// Every ClassName given to an instance so far, shared between all of the
+    /// pending instances of that class.
+    classes: HashSet<Arc<str>>,
+}
+
+/// Contains all the information we need to gather in order to construct an
+/// instance. Incrementally built up as we decode different chunks.
+struct PendingInstance {
+    /// A work-in-progress builder that will be used to construct this instance.
+    builder: InstanceBuilder,
+
+    /// The ClassName of this instance, which is only given to the builder
+    /// once the instance is constructed. Until then, the builder has an empty
+    /// ClassName and Name, so that the Name decoded from the file can replace
+    /// it without first allocating a copy of the ClassName to use as a
+    /// default.
+    class: Arc<str>,
+
+    /// Whether a Name has been decoded for this instance.
+    named: bool,
+
+    /// Whether this instance has been given a parent. Instances can only be
+    /// placed in the tree once, so any later parents are ignored.
+    parented: bool,
+
+    /// Referents of this instance's children, in order.
+    children: Vec<Ref>,
+}
+
+impl DomSink {
+    pub(crate) fn new() -> Self {
+        Self {
+            instances: HashMap::new(),
+            root_refs: Vec::new(),
+            classes: HashSet::new(),
+        }
+    }
+
+    /// Combines together all the decoded information to build and emplace
+    /// instances in a new tree.
+    #[profiling::function]
+    pub(crate) fn finish(mut self) -> WeakDom {
+        log::trace!("Constructing tree from deserialized data");
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "rbx_binary::construct_dom",
+            instances = self.instances.len()
+        )
+        .entered();
+
+        let mut tree = WeakDom::new(InstanceBuilder::new("DataModel"));
+
+        // Track all the instances we need to construct. Order of construction
+        // is important to preserve for both determinism and sometimes
+        // functionality of models we handle.
+        let mut instances_to_construct = std::collections::VecDeque::new();
+
+        // Any instance with a parent of -1 will be at the top level of the
+        // tree. Because of the way rbx_dom_weak generally works, we need to
+        // start at the top of the tree to begin construction.
+        let root_ref = tree.root_ref();
+        for &referent in &self.root_refs {
+            instances_to_construct.push_back((referent, root_ref));
+        }
+
+        while let Some((referent, parent_ref)) = instances_to_construct.pop_front() {
+            let mut instance = match self.instances.remove(&referent) {
+                Some(instance) => instance,
+                None => continue,
+            };
+
+            if !instance.named {
+                instance.builder.set_name(&*instance.class);
+            }
+            instance.builder.set_class(&*instance.class);
+
+            let id = tree.insert(parent_ref, instance.builder);
+
+            for referent in instance.children {
+                instances_to_construct.push_back((referent, id));
+            }
+        }
+
+        tree
+    }
+}
+
+impl DecodeSink for DomSink {
+    fn instance(&mut self, class: &str) -> Ref {
+        let builder = InstanceBuilder::empty();
+        let referent = builder.referent();
+
+        let class = match self.classes.get(class) {
+            Some(class) => Arc::clone(class),
+            None => {
+                let class: Arc<str> = Arc::from(class);
+                self.classes.insert(Arc::clone(&class));
+                class
+            }
+        };
+
+        self.instances.insert(
+            referent,
+            PendingInstance {
+                builder,
+                class,
+                named: false,
+                parented: false,
+                children: Vec::new(),
+            },
+        );
+
+        referent
+    }
+
+    fn name(&mut self, referent: Ref, name: &str) {
+        if let Some(instance) = self.instances.get_mut(&referent) {
+            instance.builder.set_name(name);
+            instance.named = true;
+        }
+    }
+
+    fn property(&mut self, referent: Ref, name: &str, value: Variant) {
+        if let Some(instance) = self.instances.get_mut(&referent) {
+            instance.builder.add_property(name, value);
+        }
+    }
+
+    fn parent(&mut self, referent: Ref, parent: Ref) {
+        match self.instances.get_mut(&referent) {
+            Some(instance) if !instance.parented => instance.parented = true,
+            _ => return,
+        }
+
+        if parent.is_none() {
+            self.root_refs.push(referent);
+        } else if let Some(instance) = self.instances.get_mut(&parent) {
+            instance.children.push(referent);
+        }
+    }
+}
@@ -2,38 +2,58 @@ use std::{
     collections::{HashMap, HashSet, VecDeque},
     convert::TryInto,
     io::Read,
+    sync::Arc,
+    time::Duration,
 };
 
-use rbx_dom_weak::{
-    types::{
-        Attributes, Axes, BinaryString, BrickColor, CFrame, Color3, Color3uint8, ColorSequence,
-        ColorSequenceKeypoint, Content, CustomPhysicalProperties, Enum, Faces, Font, FontStyle,
-        FontWeight, Matrix3, NumberRange, NumberSequence, NumberSequenceKeypoint,
-        PhysicalProperties, Ray, Rect, Ref, SharedString, Tags, UDim, UDim2, UniqueId, Variant,
-        VariantType, Vector2, Vector3, Vector3int16,
-    },
-    InstanceBuilder, WeakDom,
+use rbx_dom_weak::types::{
+    Attributes, Axes, BinaryString, BrickColor, CFrame, Color3, Color3uint8, ColorSequence,
+    ColorSequenceKeypoint, Content, CustomPhysicalProperties, Enum, Faces, Font, FontStyle,
+    FontWeight, Matrix3, NumberRange, NumberSequence, NumberSequenceKeypoint, PhysicalProperties,
+    Ray, Rect, Ref, SecurityCapabilities, SharedString, Tags, UDim, UDim2, UniqueId, Variant,
+    VariantType, Vector2, Vector3, Vector3int16,
+};
+use rbx_reflection::{
+    DataType, EnumDescriptor, PropertyMigration, ReflectionDatabase, SerializationBehavior,
 };
-use rbx_reflection::{DataType, PropertyKind, PropertySerialization, ReflectionDatabase};
 
 use crate::{
-    chunk::Chunk,
-    core::{find_property_descriptors, RbxReadExt},
+    chunk::{self, Chunk, ChunkError},
+    clock::Instant,
+    core::{RbxReadExt, RbxReadSliceExt},
     types::Type,
 };
 
-use super::{error::InnerError, header::FileHeader, Deserializer};
+use super::{
+    chunk_reader::ChunkKind,
+    error::{ChunkContext, InnerError},
+    header::{FileHeader, FILE_HEADER_LEN},
+    progress::DecodeProgress,
+    report::{Damage, DecodeReport, DecodeWarning, SkippedProperty, UnknownProperty},
+    sink::DecodeSink,
+    stats::{ChunkStats, DecodeStats},
+    Deserializer, UnknownEnumValues,
+};
+
+/// The most entries we'll reserve space for up front based on counts read
+/// from the file, which can't be trusted until the data has actually been
+/// read.
+const MAX_PREALLOCATION: usize = 1 << 16;
 
-pub(super) struct DeserializerState<'a, R> {
+pub(super) struct DeserializerState<'a, R, S> {
     /// The user-provided configuration that we should use.
-    deserializer: &'a Deserializer<'a>,
+    pub(super) deserializer: &'a Deserializer<'a>,
 
     /// The input data encoded as a binary model.
     input: R,
 
-    /// The tree that instances should be written into. Eventually returned to
-    /// the user.
-    tree: WeakDom,
+    /// Receives instances and properties as they are decoded.
+    sink: S,
+
+    /// Chunks that have been read and decompressed ahead of time, but not yet
+    /// decoded. This only holds more than one chunk when chunks are being
+    /// decompressed in parallel.
+    pending_chunks: VecDeque<Result<Chunk<'static>, ChunkError>>,
 
     /// The metadata contained in the file, which affects how some constructs
     /// are interpreted by Roblox.
@@ -46,17 +66,50 @@ pub(super) struct DeserializerState<'a, R> {
     /// All of the instance types described by the file so far.
     type_infos: HashMap<u32, TypeInfo>,
 
-    /// All of the instances known by the deserializer.
-    instances_by_ref: HashMap<i32, Instance>,
+    /// Every class and property name read from the file so far. Names are
+    /// repeated across many chunks, so each one is only allocated the first
+    /// time it's read and shared after that.
+    names: HashSet<Arc<str>>,
 
-    /// Referents for all of the instances with no parent, in order they appear
-    /// in the file.
-    root_instance_refs: Vec<i32>,
+    /// The referents given by the sink to every instance declared in the file,
+    /// keyed by the IDs the file uses for them.
+    refs_by_id: HashMap<i32, Ref>,
 
     /// Contains a set of unknown type IDs that we've encountered so far while
     /// deserializing this file. We use this map in order to ensure we only
     /// print one warning per unknown type ID when deserializing a file.
     unknown_type_ids: HashSet<u8>,
+
+    /// Everything skipped or preserved without being decoded so far.
+    report: DecodeReport,
+
+    /// In recovery mode, every instance declared by the file so far, in the
+    /// order they were declared.
+    declared: Vec<Ref>,
+
+    /// Every instance that has been given a parent, so that instances given
+    /// more than one can be caught.
+    parented: HashSet<Ref>,
+
+    /// The number of instances the file header says the file contains.
+    total_instances: usize,
+
+    /// The number of bytes of the file that have been decoded so far.
+    bytes_read: u64,
+
+    /// When deserialization started, for the report's stats.
+    started: Instant,
+
+    /// The combined size of every chunk's decompressed contents so far.
+    total_chunk_len: u64,
+
+    /// The number of property values that have been decoded so far, counting
+    /// one for every instance a PROP chunk has a value for.
+    property_values: usize,
+
+    /// The span that the spans of each chunk are children of.
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
 }
 
 /// Represents a unique instance class. Binary models define all their instance
@@ -67,21 +120,20 @@ struct TypeInfo {
     type_id: u32,
 
     /// The common name for this type like `Folder` or `UserInputService`.
-    type_name: String,
+    type_name: Arc<str>,
 
     /// A list of the instances described by this file that are this type.
-    referents: Vec<i32>,
-}
+    referents: Vec<Ref>,
 
-/// Contains all the information we need to gather in order to construct an
-/// instance. Incrementally built up by the deserializer as we decode different
-/// chunks.
-struct Instance {
-    /// A work-in-progress builder that will be used to construct this instance.
-    builder: InstanceBuilder,
+    /// The names of every property that has been given to the instances of
+    /// this type so far. Because PROP chunks contain a value for every
+    /// instance of a type, this tells us whether any one instance of this type
+    /// has a property.
+    properties: HashSet<Arc<str>>,
 
-    /// Document-defined IDs for the children of this instance.
-    children: Vec<i32>,
+    /// Whether the class filter excluded this type, meaning that all of its
+    /// properties except Name should be skipped.
+    skipped: bool,
 }
 
 /// Properties may be serialized under different names or types than
@@ -93,17 +145,38 @@ struct Instance {
 struct CanonicalProperty<'a> {
     name: &'a str,
     ty: VariantType,
-    migration: Option<&'a PropertySerialization<'a>>,
+    enum_name: Option<&'a str>,
+    migration: Option<&'a PropertyMigration>,
+}
+
+/// Finds the value to give an Enum property in place of one that isn't part
+/// of its enum: the property's default for the class if the database has
+/// one, or the enum's lowest item otherwise.
+fn default_enum_value(
+    database: &ReflectionDatabase,
+    class_name: &str,
+    prop_name: &str,
+    descriptor: &EnumDescriptor,
+) -> u32 {
+    match database.class_default(class_name, prop_name) {
+        Some(Variant::Enum(default)) => default.to_u32(),
+        _ => descriptor.items.values().copied().min().unwrap_or(0),
+    }
 }
 
 fn find_canonical_property<'de>(
-    database: &'de ReflectionDatabase,
+    database: Option<&'de ReflectionDatabase>,
     binary_type: Type,
     class_name: &str,
     prop_name: &'de str,
 ) -> Option<CanonicalProperty<'de>> {
-    match find_property_descriptors(database, class_name, prop_name) {
-        Some(descriptors) => {
+    let descriptors = database.and_then(|database| {
+        let descriptors = database.find_property_descriptors(class_name, prop_name)?;
+        Some((descriptors, database.serialization(class_name, prop_name)?))
+    });
+
+    match descriptors {
+        Some((descriptors, serialization)) => {
             // If this descriptor is known but wasn't supposed to be
             // serialized, we should skip it.
             //
@@ -119,32 +192,31 @@ fn find_canonical_property<'de>(
             // In case we run into a model serialized during that period, or
             // this happens again, we need to make sure that the name we
             // found is the one that's supposed to serialize.
-            if let PropertyKind::Canonical { serialization } = &descriptors.canonical.kind {
-                if matches!(serialization, PropertySerialization::DoesNotSerialize) {
+            //
+            // Properties that are only loaded from older files are migrated
+            // to the property that replaced them.
+            let migration = match serialization {
+                SerializationBehavior::Serializes { .. } => None,
+                SerializationBehavior::LoadOnly { migration, .. } => Some(migration),
+                _ => {
                     log::debug!(
                         "Skipping property {} as it is canonical and should not serialize.",
                         descriptors.canonical.name
                     );
                     return None;
                 }
-            }
+            };
 
             // TODO: Do we need an additional fix here?
             let canonical_name = &descriptors.canonical.name;
-            let canonical_type = match &descriptors.canonical.data_type {
-                DataType::Value(ty) => *ty,
-                DataType::Enum(_) => VariantType::Enum,
+            let (canonical_type, enum_name) = match &descriptors.canonical.data_type {
+                DataType::Value(ty) => (*ty, None),
+                DataType::Enum(name) => (VariantType::Enum, Some(name.as_ref())),
                 _ => {
                     // TODO: Configurable handling of unknown types?
                     return None;
                 }
             };
-            let migration = match &descriptors.canonical.kind {
-                PropertyKind::Canonical {
-                    serialization: migration @ PropertySerialization::Migrate(_),
-                } => Some(migration),
-                _ => None,
-            };
 
             log::trace!(
                 "Known prop, canonical name {} and type {:?}, with {:?} migration",
@@ -156,6 +228,7 @@ fn find_canonical_property<'de>(
             Some(CanonicalProperty {
                 name: canonical_name,
                 ty: canonical_type,
+                enum_name,
                 migration,
             })
         }
@@ -173,89 +246,339 @@ fn find_canonical_property<'de>(
             Some(CanonicalProperty {
                 name: prop_name,
                 ty: canonical_type,
+                enum_name: None,
                 migration: None,
             })
         }
     }
 }
 
-fn add_property(instance: &mut Instance, canonical_property: &CanonicalProperty, value: Variant) {
-    if let Some(PropertySerialization::Migrate(migration)) = canonical_property.migration {
+/// Records `warning` in the report, or returns it as an error in strict mode.
+fn warn(report: &mut DecodeReport, strict: bool, warning: DecodeWarning) -> Result<(), InnerError> {
+    if strict {
+        return Err(InnerError::Warning { warning });
+    }
+
+    report.warnings.push(warning);
+    Ok(())
+}
+
+/// Returns the shared copy of `name` from `names`, adding one if this is the
+/// first time it has been seen.
+fn intern(names: &mut HashSet<Arc<str>>, name: &str) -> Arc<str> {
+    if let Some(name) = names.get(name) {
+        return Arc::clone(name);
+    }
+
+    let name: Arc<str> = Arc::from(name);
+    names.insert(Arc::clone(&name));
+    name
+}
+
+/// Checks that `count` values of `width` bytes each can fit in what's left of
+/// `chunk`, so that a corrupt count can't make us allocate more memory than
+/// the chunk could ever fill.
+fn check_count(chunk: &[u8], count: u32, width: usize) -> Result<usize, InnerError> {
+    let len = count as usize;
+
+    match len.checked_mul(width) {
+        Some(needed) if needed <= chunk.len() => Ok(len),
+        _ => Err(InnerError::CountTooLarge {
+            count,
+            width,
+            available: chunk.len(),
+        }),
+    }
+}
+
+/// Counts a decoded PROP chunk in the report's stats, if they're being
+/// collected.
+fn count_property(report: &mut DecodeReport, property_values: &mut usize, type_info: &TypeInfo) {
+    *property_values += type_info.referents.len();
+
+    if let Some(stats) = &mut report.stats {
+        let class = stats
+            .classes
+            .entry(type_info.type_name.to_string())
+            .or_default();
+        class.properties += 1;
+        class.property_values += type_info.referents.len();
+    }
+}
+
+fn add_property<S: DecodeSink>(
+    sink: &mut S,
+    referent: Ref,
+    canonical_property: &CanonicalProperty,
+    value: Variant,
+) {
+    if let Some(migration) = canonical_property.migration {
         let new_property_name = &migration.new_property_name;
         let old_property_name = canonical_property.name;
 
-        if !instance.builder.has_property(new_property_name) {
-            log::trace!(
-                "Attempting to migrate property {old_property_name} to {new_property_name}"
-            );
-            match migration.perform(&value) {
-                Ok(new_value) => {
-                    instance.builder.add_property(new_property_name, new_value);
-                    log::trace!(
-                        "Successfully migrated property {old_property_name} to {new_property_name}"
-                    );
-                }
-                Err(e) => {
-                    log::warn!(
-                        "Failed to migrate property {old_property_name} to {new_property_name} because: {}",
-                        e.to_string()
-                    );
-                }
-            };
-        }
+        log::trace!("Attempting to migrate property {old_property_name} to {new_property_name}");
+        match migration.perform(&value) {
+            Ok(new_value) => {
+                sink.property(referent, new_property_name, new_value);
+                log::trace!(
+                    "Successfully migrated property {old_property_name} to {new_property_name}"
+                );
+            }
+            Err(e) => {
+                log::warn!(
+                    "Failed to migrate property {old_property_name} to {new_property_name} because: {}",
+                    e
+                );
+            }
+        };
     } else {
-        instance
-            .builder
-            .add_property(canonical_property.name, value)
+        sink.property(referent, canonical_property.name, value)
+    }
+}
+
+/// Gives a String or Content value to the sink without copying it, unless it
+/// has to be turned into a `Variant` to be migrated.
+fn add_str_property<S: DecodeSink>(
+    sink: &mut S,
+    referent: Ref,
+    canonical_property: &CanonicalProperty,
+    value: &str,
+) {
+    if canonical_property.migration.is_none() {
+        sink.str_property(
+            referent,
+            canonical_property.name,
+            canonical_property.ty,
+            value,
+        );
+        return;
     }
+
+    let value = match canonical_property.ty {
+        VariantType::Content => Content::from(value).into(),
+        _ => value.to_owned().into(),
+    };
+    add_property(sink, referent, canonical_property, value);
 }
 
-impl<'a, R: Read> DeserializerState<'a, R> {
+impl<'a, R: Read, S: DecodeSink> DeserializerState<'a, R, S> {
     pub(super) fn new(
         deserializer: &'a Deserializer<'a>,
         mut input: R,
+        sink: S,
     ) -> Result<Self, InnerError> {
-        let tree = WeakDom::new(InstanceBuilder::new("DataModel"));
-
         let header = FileHeader::decode(&mut input)?;
+        deserializer
+            .limits
+            .check_instances(header.num_instances as usize)?;
 
-        let type_infos = HashMap::with_capacity(header.num_types as usize);
-        let instances_by_ref = HashMap::with_capacity(1 + header.num_instances as usize);
+        let type_infos = HashMap::with_capacity((header.num_types as usize).min(MAX_PREALLOCATION));
+        let refs_by_id =
+            HashMap::with_capacity((header.num_instances as usize).min(MAX_PREALLOCATION));
+
+        let mut report = DecodeReport::default();
+        if deserializer.collect_stats {
+            report.stats = Some(DecodeStats::default());
+        }
 
         Ok(DeserializerState {
             deserializer,
             input,
-            tree,
+            sink,
+            pending_chunks: VecDeque::new(),
             metadata: HashMap::new(),
             shared_strings: Vec::new(),
             type_infos,
-            instances_by_ref,
-            root_instance_refs: Vec::new(),
+            names: HashSet::new(),
+            refs_by_id,
             unknown_type_ids: HashSet::new(),
+            report,
+            declared: Vec::new(),
+            parented: HashSet::new(),
+            total_instances: header.num_instances as usize,
+            bytes_read: FILE_HEADER_LEN,
+            started: Instant::now(),
+            total_chunk_len: 0,
+            property_values: 0,
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                "rbx_binary::deserialize",
+                instances = tracing::field::Empty,
+                property_values = tracing::field::Empty,
+            ),
         })
     }
 
-    pub(super) fn next_chunk(&mut self) -> Result<Chunk, InnerError> {
-        Ok(Chunk::decode(&mut self.input)?)
+    pub(super) fn next_chunk(&mut self) -> Result<Chunk<'static>, ChunkError> {
+        if self.pending_chunks.is_empty() {
+            let batch = Chunk::decode_batch(
+                &mut self.input,
+                chunk::batch_size(),
+                self.deserializer.limits.chunk_len(),
+            );
+            self.pending_chunks.extend(batch);
+        }
+
+        // decode_batch always returns at least one chunk or error.
+        self.pending_chunks.pop_front().unwrap()
+    }
+
+    /// Records that `chunk` has been decoded and tells the deserializer's
+    /// progress callback, if it has one.
+    pub(super) fn report_progress(&mut self, chunk: &Chunk<'_>) {
+        self.bytes_read += chunk.file_len;
+
+        if let Some(callback) = &self.deserializer.progress {
+            callback(&DecodeProgress {
+                bytes_read: self.bytes_read,
+                instances_decoded: self.refs_by_id.len(),
+                total_instances: self.total_instances,
+                chunk: ChunkKind::from_name(&chunk.name),
+            });
+        }
+    }
+
+    /// Records that a chunk that took up `file_len` bytes of the file was
+    /// skipped instead of decoded, so that the chunks after it are still given
+    /// the right offsets.
+    pub(super) fn skip_chunk(&mut self, file_len: u64) {
+        self.bytes_read += file_len;
+    }
+
+    /// Checks that decoding `chunk` won't put the file over the
+    /// deserializer's limit on total decompressed size.
+    pub(super) fn check_total_len(&mut self, chunk: &Chunk<'_>) -> Result<(), InnerError> {
+        self.total_chunk_len += chunk.data.len() as u64;
+        self.deserializer
+            .limits
+            .check_total_len(self.total_chunk_len)
+    }
+
+    /// Records how long `chunk` took to decode in the report's stats, if
+    /// they're being collected. This must be called before `report_progress`.
+    pub(super) fn record_chunk_stats(&mut self, chunk: &Chunk<'_>, decode_time: Duration) {
+        if let Some(stats) = &mut self.report.stats {
+            stats.chunks.push(ChunkStats {
+                name: chunk.name,
+                offset: self.bytes_read,
+                file_len: chunk.file_len,
+                uncompressed_len: chunk.data.len(),
+                decode_time,
+            });
+        }
+    }
+
+    /// Starts the span that decoding `chunk` happens in.
+    #[cfg(feature = "tracing")]
+    pub(super) fn chunk_span(&self, index: usize, chunk: &Chunk<'_>) -> tracing::Span {
+        let name = String::from_utf8_lossy(&chunk.name);
+
+        tracing::debug_span!(
+            parent: &self.span,
+            "rbx_binary::decode_chunk",
+            index,
+            name = name.trim_end_matches('\0'),
+            offset = self.bytes_read,
+            len = chunk.data.len(),
+        )
+    }
+
+    pub(super) fn record_damage(&mut self, damage: Damage) {
+        log::warn!("Recovering from damaged file: {:?}", damage);
+        self.report.damage.push(damage);
+    }
+
+    /// Wraps `source` with where it happened: in the chunk at `index`, which
+    /// starts at the current offset into the file, `position` bytes into its
+    /// contents.
+    pub(super) fn locate(
+        &self,
+        index: usize,
+        position: Option<usize>,
+        context: ChunkContext,
+        source: InnerError,
+    ) -> InnerError {
+        InnerError::InChunk {
+            index,
+            offset: self.bytes_read,
+            position,
+            context,
+            source: Box::new(source),
+        }
+    }
+
+    /// Describes what `chunk` contains for error messages, like
+    /// `PROP chunk for Part.Size`, as best as its header can be read.
+    pub(super) fn chunk_context(&self, chunk: &Chunk<'_>) -> ChunkContext {
+        let name = String::from_utf8_lossy(&chunk.name);
+        let name = name.trim_end_matches('\0');
+        let mut contents: &[u8] = &chunk.data;
+
+        let described = match &chunk.name {
+            b"INST" => contents.read_le_u32().ok().and_then(|_| {
+                let type_name = contents.read_str().ok()?;
+                Some(ChunkContext {
+                    description: format!("{} chunk for {}", name, type_name),
+                    class_name: Some(type_name.to_owned()),
+                    property_name: None,
+                })
+            }),
+            b"PROP" => contents.read_le_u32().ok().and_then(|type_id| {
+                let prop_name = contents.read_str().ok()?;
+                Some(match self.type_infos.get(&type_id) {
+                    Some(type_info) => ChunkContext {
+                        description: format!(
+                            "{} chunk for {}.{}",
+                            name, type_info.type_name, prop_name
+                        ),
+                        class_name: Some(type_info.type_name.to_string()),
+                        property_name: Some(prop_name.to_owned()),
+                    },
+                    None => ChunkContext {
+                        description: format!(
+                            "{} chunk for type ID {}, {}",
+                            name, type_id, prop_name
+                        ),
+                        class_name: None,
+                        property_name: Some(prop_name.to_owned()),
+                    },
+                })
+            }),
+            _ => None,
+        };
+
+        described.unwrap_or_else(|| ChunkContext::new(format!("{} chunk", name)))
+    }
+
+    /// In strict mode, checks that nothing is left over in `chunk` after it
+    /// has been decoded.
+    fn check_trailing_data(&self, chunk: &[u8]) -> Result<(), InnerError> {
+        if self.deserializer.strict && !chunk.is_empty() {
+            return Err(InnerError::TrailingData { len: chunk.len() });
+        }
+
+        Ok(())
     }
 
     #[profiling::function]
-    pub(super) fn decode_meta_chunk(&mut self, mut chunk: &[u8]) -> Result<(), InnerError> {
+    pub(super) fn decode_meta_chunk(&mut self, chunk: &mut &[u8]) -> Result<(), InnerError> {
         let len = chunk.read_le_u32()?;
-        self.metadata.reserve(len as usize);
+        self.metadata.reserve((len as usize).min(MAX_PREALLOCATION));
 
         for _ in 0..len {
-            let key = chunk.read_string()?;
-            let value = chunk.read_string()?;
+            let key = chunk.read_str()?;
+            let value = chunk.read_str()?;
+            self.deserializer.limits.check_string(key)?;
+            self.deserializer.limits.check_string(value)?;
 
-            self.metadata.insert(key, value);
+            self.metadata.insert(key.to_owned(), value.to_owned());
         }
 
         Ok(())
     }
 
     #[profiling::function]
-    pub(super) fn decode_sstr_chunk(&mut self, mut chunk: &[u8]) -> Result<(), InnerError> {
+    pub(super) fn decode_sstr_chunk(&mut self, chunk: &mut &[u8]) -> Result<(), InnerError> {
         let version = chunk.read_le_u32()?;
 
         if version != 0 {
@@ -269,19 +592,42 @@ impl<'a, R: Read> DeserializerState<'a, R> {
 
         for _ in 0..num_entries {
             chunk.read_exact(&mut [0; 16])?; // We don't do anything with the hash.
-            let data = chunk.read_binary_string()?;
-            self.shared_strings.push(SharedString::new(data));
+            let data = chunk.read_binary_str()?;
+            self.deserializer.limits.check_string(data)?;
+
+            if let Some(stats) = &mut self.report.stats {
+                stats.shared_strings += 1;
+                stats.shared_string_bytes += data.len() as u64;
+            }
+
+            self.shared_strings.push(SharedString::new(data.to_vec()));
         }
 
         Ok(())
     }
 
     #[profiling::function]
-    pub(super) fn decode_inst_chunk(&mut self, mut chunk: &[u8]) -> Result<(), InnerError> {
+    pub(super) fn decode_sign_chunk(&mut self, chunk: &mut &[u8]) -> Result<(), InnerError> {
+        log::trace!("SIGN chunk ({} bytes)", chunk.len());
+
+        // rbx_binary doesn't know how to check signatures, so they're kept
+        // as-is for anyone who wants to write them back out.
+        self.report.signatures = Some(chunk.to_vec());
+
+        Ok(())
+    }
+
+    #[profiling::function]
+    pub(super) fn decode_inst_chunk(&mut self, chunk: &mut &[u8]) -> Result<(), InnerError> {
         let type_id = chunk.read_le_u32()?;
-        let type_name = chunk.read_string()?;
+        let type_name = chunk.read_str()?;
+        self.deserializer.limits.check_string(type_name)?;
+        let type_name = intern(&mut self.names, type_name);
         let object_format = chunk.read_u8()?;
         let number_instances = chunk.read_le_u32()?;
+        self.deserializer
+            .limits
+            .check_instances(self.refs_by_id.len() + number_instances as usize)?;
 
         log::trace!(
             "INST chunk (type ID {}, type name {}, format {}, {} instances)",
@@ -291,27 +637,72 @@ impl<'a, R: Read> DeserializerState<'a, R> {
             number_instances,
         );
 
-        let mut referents = vec![0; number_instances as usize];
-        chunk.read_referent_array(&mut referents)?;
+        let mut ids = vec![0; check_count(chunk, number_instances, 4)?];
+        chunk.read_referent_array(&mut ids)?;
 
         // TODO: Check object_format and check for service markers if it's 1?
 
-        for &referent in &referents {
-            self.instances_by_ref.insert(
-                referent,
-                Instance {
-                    builder: InstanceBuilder::new(&type_name),
-                    children: Vec::new(),
-                },
-            );
+        let mut referents = Vec::with_capacity(ids.len());
+        for id in ids {
+            if self.deserializer.strict && self.refs_by_id.contains_key(&id) {
+                return Err(InnerError::DuplicateInstanceId { id });
+            }
+
+            let referent = self.sink.instance(&type_name);
+            referents.push(referent);
+
+            if let Some(replaced) = self.refs_by_id.insert(id, referent) {
+                log::debug!("Instance ID {} was declared more than once", id);
+
+                self.report.warnings.push(DecodeWarning::DuplicateReferent {
+                    class_name: type_name.to_string(),
+                    id,
+                    replaced,
+                    referent,
+                    offset: self.bytes_read,
+                });
+            }
         }
 
+        if self.deserializer.recover {
+            self.declared.extend_from_slice(&referents);
+        }
+
+        if let Some(stats) = &mut self.report.stats {
+            stats
+                .classes
+                .entry(type_name.to_string())
+                .or_default()
+                .instances += referents.len();
+        }
+
+        if let Some(database) = self.deserializer.database() {
+            if !database.classes.contains_key(&*type_name) {
+                log::debug!("Unknown class {}", type_name);
+
+                let warning = DecodeWarning::UnknownClass {
+                    class_name: type_name.to_string(),
+                    referents: referents.clone(),
+                    offset: self.bytes_read,
+                };
+                warn(&mut self.report, self.deserializer.strict, warning)?;
+            }
+        }
+
+        let skipped = self
+            .deserializer
+            .class_filter
+            .as_ref()
+            .is_some_and(|filter| !filter(&type_name));
+
         self.type_infos.insert(
             type_id,
             TypeInfo {
                 type_id,
                 type_name,
                 referents,
+                properties: HashSet::new(),
+                skipped,
             },
         );
 
@@ -319,15 +710,31 @@ impl<'a, R: Read> DeserializerState<'a, R> {
     }
 
     #[profiling::function]
-    pub(super) fn decode_prop_chunk(&mut self, mut chunk: &[u8]) -> Result<(), InnerError> {
+    pub(super) fn decode_prop_chunk(&mut self, chunk: &mut &[u8]) -> Result<(), InnerError> {
         let type_id = chunk.read_le_u32()?;
-        let prop_name = chunk.read_string()?;
+        let prop_name = chunk.read_str()?;
+        self.deserializer.limits.check_string(prop_name)?;
+        let prop_name = intern(&mut self.names, prop_name);
 
         let type_info = self
             .type_infos
             .get(&type_id)
             .ok_or(InnerError::InvalidTypeId { type_id })?;
 
+        // Names are cheap to decode and make a partial tree much easier to
+        // navigate, so we always keep them.
+        if type_info.skipped && &*prop_name != "Name" {
+            log::trace!("Skipping PROP chunk {}.{}", type_info.type_name, prop_name);
+
+            self.report.skipped_properties.push(SkippedProperty::new(
+                type_info.type_name.to_string(),
+                prop_name.to_string(),
+                chunk.len(),
+            ));
+
+            return Ok(());
+        }
+
         // PROP chunks that contain no type byte are ignored by Roblox. This can
         // happen when a new type is introduced.
         //
@@ -353,6 +760,25 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                     );
                 }
 
+                let warning = DecodeWarning::UnknownPropertyType {
+                    class_name: type_info.type_name.to_string(),
+                    property_name: prop_name.to_string(),
+                    type_id: binary_type_byte,
+                    referents: type_info.referents.clone(),
+                    offset: self.bytes_read,
+                };
+                warn(&mut self.report, self.deserializer.strict, warning)?;
+
+                if self.deserializer.preserve_unknown_properties {
+                    self.report.unknown_properties.push(UnknownProperty {
+                        class_name: type_info.type_name.to_string(),
+                        property_name: prop_name.to_string(),
+                        type_id: binary_type_byte,
+                        referents: type_info.referents.clone(),
+                        data: chunk.to_vec(),
+                    });
+                }
+
                 return Ok(());
             }
         };
@@ -367,22 +793,23 @@ impl<'a, R: Read> DeserializerState<'a, R> {
 
         // The `Name` prop is special and is routed to a different spot for
         // rbx_dom_weak, so we handle it specially here.
-        if prop_name == "Name" {
+        if &*prop_name == "Name" {
             // TODO: If an instance is never assigned a name through this code
             // path, we should use the reflection database to figure out its
             // default name. This should be rare: effectively never!
+            count_property(&mut self.report, &mut self.property_values, type_info);
 
             for referent in &type_info.referents {
-                let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                let value = chunk.read_string()?;
-                instance.builder.set_name(value);
+                let value = chunk.read_str()?;
+                self.deserializer.limits.check_string(value)?;
+                self.sink.name(*referent, value);
             }
 
-            return Ok(());
+            return self.check_trailing_data(chunk);
         }
 
-        let property = if let Some(property) = find_canonical_property(
-            self.deserializer.database.unwrap(),
+        let mut property = if let Some(property) = find_canonical_property(
+            self.deserializer.database(),
             binary_type,
             &type_info.type_name,
             &prop_name,
@@ -392,62 +819,140 @@ impl<'a, R: Read> DeserializerState<'a, R> {
             return Ok(());
         };
 
+        if !self.deserializer.migrate_properties {
+            property.migration = None;
+        }
+
+        // Migrations only apply when the file does not also contain the
+        // property being migrated to. If it does, we skip this property.
+        let output_name = match property.migration {
+            Some(migration) => &migration.new_property_name,
+            None => property.name,
+        };
+
+        if property.migration.is_some() && type_info.properties.contains(output_name) {
+            return Ok(());
+        }
+
+        if !self
+            .deserializer
+            .property_filter
+            .as_ref()
+            .is_none_or(|filter| filter(&type_info.type_name, output_name))
+        {
+            log::trace!("Skipping PROP chunk {}.{}", type_info.type_name, prop_name);
+
+            self.report.skipped_properties.push(SkippedProperty::new(
+                type_info.type_name.to_string(),
+                prop_name.to_string(),
+                chunk.len(),
+            ));
+
+            return Ok(());
+        }
+
+        let output_name = intern(&mut self.names, output_name);
+        count_property(&mut self.report, &mut self.property_values, type_info);
+
+        let result = self.decode_prop_values(type_id, &prop_name, binary_type, &property, chunk);
+
+        if let Err(InnerError::PropTypeMismatch { prop_name, .. }) = result {
+            let type_info = &self.type_infos[&type_id];
+
+            log::warn!(
+                "Property {}.{} should be {:?}, but the file stored it as {:?}. Skipping property.",
+                type_info.type_name,
+                prop_name,
+                property.ty,
+                binary_type,
+            );
+
+            let warning = DecodeWarning::PropTypeMismatch {
+                class_name: type_info.type_name.to_string(),
+                property_name: prop_name,
+                type_id: binary_type as u8,
+                expected_type: property.ty,
+                referents: type_info.referents.clone(),
+                offset: self.bytes_read,
+            };
+            warn(&mut self.report, self.deserializer.strict, warning)?;
+
+            return Ok(());
+        }
+
+        result?;
+        self.check_trailing_data(chunk)?;
+
+        self.type_infos
+            .get_mut(&type_id)
+            .unwrap()
+            .properties
+            .insert(output_name);
+
+        Ok(())
+    }
+
+    /// Decodes the values of a PROP chunk for every instance of the type
+    /// with ID `type_id`, whose header has already been read from `chunk`.
+    fn decode_prop_values(
+        &mut self,
+        type_id: u32,
+        prop_name: &str,
+        binary_type: Type,
+        property: &CanonicalProperty,
+        chunk: &mut &[u8],
+    ) -> Result<(), InnerError> {
+        let type_info = &self.type_infos[&type_id];
         let canonical_type = property.ty;
+        let limits = self.deserializer.limits;
 
         match binary_type {
             Type::String => match canonical_type {
-                VariantType::String => {
+                VariantType::String | VariantType::Content => {
                     for referent in &type_info.referents {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        let value = chunk.read_string()?;
-                        add_property(instance, &property, value.into());
-                    }
-                }
-                VariantType::Content => {
-                    for referent in &type_info.referents {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        let value: Content = chunk.read_string()?.into();
-                        add_property(instance, &property, value.into());
+                        let value = chunk.read_str()?;
+                        limits.check_string(value)?;
+                        add_str_property(&mut self.sink, *referent, property, value);
                     }
                 }
                 VariantType::BinaryString => {
                     for referent in &type_info.referents {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        let value: BinaryString = chunk.read_binary_string()?.into();
-                        add_property(instance, &property, value.into());
+                        let value = chunk.read_binary_str()?;
+                        limits.check_string(value)?;
+                        let value = BinaryString::from(value.to_vec());
+                        add_property(&mut self.sink, *referent, property, value.into());
                     }
                 }
                 VariantType::Tags => {
                     for referent in &type_info.referents {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        let buffer = chunk.read_binary_string()?;
+                        let buffer = chunk.read_binary_str()?;
+                        limits.check_string(buffer)?;
 
-                        let value = Tags::decode(buffer.as_ref()).map_err(|_| {
-                            InnerError::InvalidPropData {
-                                type_name: type_info.type_name.clone(),
-                                prop_name: prop_name.clone(),
+                        let value =
+                            Tags::decode(buffer).map_err(|_| InnerError::InvalidPropData {
+                                type_name: type_info.type_name.to_string(),
+                                prop_name: prop_name.to_owned(),
                                 valid_value: "a list of valid null-delimited UTF-8 strings",
                                 actual_value: "invalid UTF-8".to_string(),
-                            }
-                        })?;
+                            })?;
 
-                        add_property(instance, &property, value.into());
+                        add_property(&mut self.sink, *referent, property, value.into());
                     }
                 }
                 VariantType::Attributes => {
                     for referent in &type_info.referents {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        let buffer = chunk.read_binary_string()?;
+                        let buffer = chunk.read_binary_str()?;
+                        limits.check_string(buffer)?;
 
-                        match Attributes::from_reader(buffer.as_slice()) {
+                        match Attributes::from_reader(buffer) {
                             Ok(value) => {
-                                add_property(instance, &property, value.into());
+                                add_property(&mut self.sink, *referent, property, value.into());
                             }
                             Err(err) => {
                                 return Err(InnerError::BadPropertyValue {
                                     source: err,
                                     class_name: type_info.type_name.to_string(),
-                                    prop_name,
+                                    prop_name: prop_name.to_owned(),
                                 })
                             }
                         }
@@ -455,8 +960,8 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "String, Content, Tags, Attributes, or BinaryString",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -465,15 +970,14 @@ impl<'a, R: Read> DeserializerState<'a, R> {
             Type::Bool => match canonical_type {
                 VariantType::Bool => {
                     for referent in &type_info.referents {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
                         let value = chunk.read_bool()?;
-                        add_property(instance, &property, value.into());
+                        add_property(&mut self.sink, *referent, property, value.into());
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "Bool",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -485,8 +989,7 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                     chunk.read_interleaved_i32_array(&mut values)?;
 
                     for (value, referent) in values.into_iter().zip(&type_info.referents) {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        add_property(instance, &property, value.into());
+                        add_property(&mut self.sink, *referent, property, value.into());
                     }
                 }
                 // This branch allows values serialized as Int32 to be converted to Int64 when we expect a Int64
@@ -497,15 +1000,14 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                     chunk.read_interleaved_i32_array(&mut values)?;
 
                     for (value, referent) in values.into_iter().zip(&type_info.referents) {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
                         let value_converted = i64::from(value);
-                        add_property(instance, &property, value_converted.into());
+                        add_property(&mut self.sink, *referent, property, value_converted.into());
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "Int32",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -517,14 +1019,13 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                     chunk.read_interleaved_f32_array(&mut values)?;
 
                     for (value, referent) in values.into_iter().zip(&type_info.referents) {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        add_property(instance, &property, value.into());
+                        add_property(&mut self.sink, *referent, property, value.into());
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "Float32",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -533,9 +1034,8 @@ impl<'a, R: Read> DeserializerState<'a, R> {
             Type::Float64 => match canonical_type {
                 VariantType::Float64 => {
                     for referent in &type_info.referents {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
                         let value = chunk.read_le_f64()?;
-                        add_property(instance, &property, value.into());
+                        add_property(&mut self.sink, *referent, property, value.into());
                     }
                 }
                 // This branch allows values serialized as Float32 to be converted to Float64 when we expect a Float64
@@ -546,15 +1046,14 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                     chunk.read_interleaved_f32_array(&mut values)?;
 
                     for (value, referent) in values.into_iter().zip(&type_info.referents) {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
                         let converted_value = f64::from(value);
-                        add_property(instance, &property, converted_value.into());
+                        add_property(&mut self.sink, *referent, property, converted_value.into());
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "Float64",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -574,14 +1073,13 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                         .map(|(scale, offset)| UDim::new(scale, offset));
 
                     for (value, referent) in values.zip(&type_info.referents) {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        add_property(instance, &property, value.into());
+                        add_property(&mut self.sink, *referent, property, value.into());
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "UDim",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -613,14 +1111,13 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                     let values = x.zip(y).map(|(x, y)| UDim2::new(x, y));
 
                     for (value, referent) in values.zip(&type_info.referents) {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        add_property(instance, &property, value.into());
+                        add_property(&mut self.sink, *referent, property, value.into());
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "UDim2",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -636,11 +1133,10 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                         let direction_y = chunk.read_le_f32()?;
                         let direction_z = chunk.read_le_f32()?;
 
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-
                         add_property(
-                            instance,
-                            &property,
+                            &mut self.sink,
+                            *referent,
+                            property,
                             Ray::new(
                                 Vector3::new(origin_x, origin_y, origin_z),
                                 Vector3::new(direction_x, direction_y, direction_z),
@@ -651,8 +1147,8 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "Ray",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -661,23 +1157,22 @@ impl<'a, R: Read> DeserializerState<'a, R> {
             Type::Faces => match canonical_type {
                 VariantType::Faces => {
                     for referent in &type_info.referents {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
                         let value = chunk.read_u8()?;
                         let faces =
                             Faces::from_bits(value).ok_or_else(|| InnerError::InvalidPropData {
-                                type_name: type_info.type_name.clone(),
-                                prop_name: prop_name.clone(),
+                                type_name: type_info.type_name.to_string(),
+                                prop_name: prop_name.to_owned(),
                                 valid_value: "less than 63",
                                 actual_value: value.to_string(),
                             })?;
 
-                        add_property(instance, &property, faces.into());
+                        add_property(&mut self.sink, *referent, property, faces.into());
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "Faces",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -686,24 +1181,23 @@ impl<'a, R: Read> DeserializerState<'a, R> {
             Type::Axes => match canonical_type {
                 VariantType::Axes => {
                     for referent in &type_info.referents {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
                         let value = chunk.read_u8()?;
 
                         let axes =
                             Axes::from_bits(value).ok_or_else(|| InnerError::InvalidPropData {
-                                type_name: type_info.type_name.clone(),
-                                prop_name: prop_name.clone(),
+                                type_name: type_info.type_name.to_string(),
+                                prop_name: prop_name.to_owned(),
                                 valid_value: "less than 7",
                                 actual_value: value.to_string(),
                             })?;
 
-                        add_property(instance, &property, axes.into());
+                        add_property(&mut self.sink, *referent, property, axes.into());
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "Axes",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -715,25 +1209,24 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                     chunk.read_interleaved_u32_array(&mut values)?;
 
                     for (value, referent) in values.into_iter().zip(&type_info.referents) {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
                         let color = value
                             .try_into()
                             .ok()
                             .and_then(BrickColor::from_number)
                             .ok_or_else(|| InnerError::InvalidPropData {
-                                type_name: type_info.type_name.clone(),
-                                prop_name: prop_name.clone(),
+                                type_name: type_info.type_name.to_string(),
+                                prop_name: prop_name.to_owned(),
                                 valid_value: "a valid BrickColor",
                                 actual_value: value.to_string(),
                             })?;
 
-                        add_property(instance, &property, color.into());
+                        add_property(&mut self.sink, *referent, property, color.into());
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "BrickColor",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -756,14 +1249,13 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                         .map(|((r, g), b)| Color3::new(r, g, b));
 
                     for (color, referent) in colors.zip(&type_info.referents) {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        add_property(instance, &property, color.into());
+                        add_property(&mut self.sink, *referent, property, color.into());
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "Color3",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -780,14 +1272,13 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                     let values = x.into_iter().zip(y).map(|(x, y)| Vector2::new(x, y));
 
                     for (value, referent) in values.zip(&type_info.referents) {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        add_property(instance, &property, value.into());
+                        add_property(&mut self.sink, *referent, property, value.into());
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "Vector2",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -810,14 +1301,13 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                         .map(|((x, y), z)| Vector3::new(x, y, z));
 
                     for (value, referent) in values.zip(&type_info.referents) {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        add_property(instance, &property, value.into());
+                        add_property(&mut self.sink, *referent, property, value.into());
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "Vector3",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -852,8 +1342,8 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                             rotations.push(basic_rotation);
                         } else {
                             return Err(InnerError::BadRotationId {
-                                type_name: type_info.type_name.clone(),
-                                prop_name,
+                                type_name: type_info.type_name.to_string(),
+                                prop_name: prop_name.to_owned(),
                                 id,
                             });
                         }
@@ -876,14 +1366,13 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                         .map(|(position, rotation)| CFrame::new(position, rotation));
 
                     for (cframe, referent) in values.zip(referents) {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        add_property(instance, &property, cframe.into());
+                        add_property(&mut self.sink, *referent, property, cframe.into());
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "CFrame",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -894,15 +1383,60 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                     let mut values = vec![0; type_info.referents.len()];
                     chunk.read_interleaved_u32_array(&mut values)?;
 
-                    for (value, referent) in values.into_iter().zip(&type_info.referents) {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        add_property(instance, &property, Enum::from_u32(value).into());
+                    let descriptor = property
+                        .enum_name
+                        .zip(self.deserializer.database())
+                        .and_then(|(name, database)| Some((database, database.enums.get(name)?)));
+
+                    for (mut value, referent) in values.into_iter().zip(&type_info.referents) {
+                        if let Some((database, descriptor)) = descriptor {
+                            if !descriptor.items.values().any(|&item| item == value) {
+                                let replaced_with = match self.deserializer.unknown_enum_values {
+                                    UnknownEnumValues::Preserve => None,
+                                    UnknownEnumValues::Default => Some(default_enum_value(
+                                        database,
+                                        &type_info.type_name,
+                                        property.name,
+                                        descriptor,
+                                    )),
+                                    UnknownEnumValues::Error => {
+                                        return Err(InnerError::UnknownEnumValue {
+                                            type_name: type_info.type_name.to_string(),
+                                            prop_name: prop_name.to_owned(),
+                                            enum_name: descriptor.name.to_string(),
+                                            value,
+                                            referent: *referent,
+                                        })
+                                    }
+                                };
+
+                                let warning = DecodeWarning::UnknownEnumValue {
+                                    class_name: type_info.type_name.to_string(),
+                                    property_name: prop_name.to_owned(),
+                                    enum_name: descriptor.name.to_string(),
+                                    value,
+                                    replaced_with,
+                                    referent: *referent,
+                                    offset: self.bytes_read,
+                                };
+                                warn(&mut self.report, self.deserializer.strict, warning)?;
+
+                                value = replaced_with.unwrap_or(value);
+                            }
+                        }
+
+                        add_property(
+                            &mut self.sink,
+                            *referent,
+                            property,
+                            Enum::from_u32(value).into(),
+                        );
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "Enum",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -914,20 +1448,18 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                     chunk.read_referent_array(&mut refs)?;
 
                     for (value, referent) in refs.into_iter().zip(&type_info.referents) {
-                        let rbx_value = if let Some(instance) = self.instances_by_ref.get(&value) {
-                            instance.builder.referent()
-                        } else {
-                            Ref::none()
-                        };
-
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        add_property(instance, &property, rbx_value.into());
+                        let rbx_value = self
+                            .refs_by_id
+                            .get(&value)
+                            .copied()
+                            .unwrap_or_else(Ref::none);
+                        add_property(&mut self.sink, *referent, property, rbx_value.into());
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "Ref",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -936,10 +1468,10 @@ impl<'a, R: Read> DeserializerState<'a, R> {
             Type::Vector3int16 => match canonical_type {
                 VariantType::Vector3int16 => {
                     for referent in &type_info.referents {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
                         add_property(
-                            instance,
-                            &property,
+                            &mut self.sink,
+                            *referent,
+                            property,
                             Vector3int16::new(
                                 chunk.read_le_i16()?,
                                 chunk.read_le_i16()?,
@@ -951,8 +1483,8 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "Vector3int16",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -961,22 +1493,24 @@ impl<'a, R: Read> DeserializerState<'a, R> {
             Type::Font => match canonical_type {
                 VariantType::Font => {
                     for referent in &type_info.referents {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-
-                        let family = chunk.read_string()?;
+                        let family = chunk.read_str()?;
+                        limits.check_string(family)?;
+                        let family = family.to_owned();
                         let weight = FontWeight::from_u16(chunk.read_le_u16()?).unwrap_or_default();
                         let style = FontStyle::from_u8(chunk.read_u8()?).unwrap_or_default();
-                        let cached_face_id = chunk.read_string()?;
+                        let cached_face_id = chunk.read_str()?;
+                        limits.check_string(cached_face_id)?;
 
                         let cached_face_id = if cached_face_id.is_empty() {
                             None
                         } else {
-                            Some(cached_face_id)
+                            Some(cached_face_id.to_owned())
                         };
 
                         add_property(
-                            instance,
-                            &property,
+                            &mut self.sink,
+                            *referent,
+                            property,
                             Font {
                                 family,
                                 weight,
@@ -989,8 +1523,8 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "Font",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -999,9 +1533,9 @@ impl<'a, R: Read> DeserializerState<'a, R> {
             Type::NumberSequence => match canonical_type {
                 VariantType::NumberSequence => {
                     for referent in &type_info.referents {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
                         let keypoint_count = chunk.read_le_u32()?;
-                        let mut keypoints = Vec::with_capacity(keypoint_count as usize);
+                        let keypoint_count = check_count(chunk, keypoint_count, 12)?;
+                        let mut keypoints = Vec::with_capacity(keypoint_count);
 
                         for _ in 0..keypoint_count {
                             keypoints.push(NumberSequenceKeypoint::new(
@@ -1011,13 +1545,18 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                             ))
                         }
 
-                        add_property(instance, &property, NumberSequence { keypoints }.into())
+                        add_property(
+                            &mut self.sink,
+                            *referent,
+                            property,
+                            NumberSequence { keypoints }.into(),
+                        )
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "NumberSequence",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -1026,8 +1565,8 @@ impl<'a, R: Read> DeserializerState<'a, R> {
             Type::ColorSequence => match canonical_type {
                 VariantType::ColorSequence => {
                     for referent in &type_info.referents {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        let keypoint_count = chunk.read_le_u32()? as usize;
+                        let keypoint_count = chunk.read_le_u32()?;
+                        let keypoint_count = check_count(chunk, keypoint_count, 16)?;
                         let mut keypoints = Vec::with_capacity(keypoint_count);
 
                         for _ in 0..keypoint_count {
@@ -1044,13 +1583,18 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                             chunk.read_le_f32()?;
                         }
 
-                        add_property(instance, &property, ColorSequence { keypoints }.into())
+                        add_property(
+                            &mut self.sink,
+                            *referent,
+                            property,
+                            ColorSequence { keypoints }.into(),
+                        )
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "ColorSequence",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -1059,18 +1603,18 @@ impl<'a, R: Read> DeserializerState<'a, R> {
             Type::NumberRange => match canonical_type {
                 VariantType::NumberRange => {
                     for referent in &type_info.referents {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
                         add_property(
-                            instance,
-                            &property,
+                            &mut self.sink,
+                            *referent,
+                            property,
                             NumberRange::new(chunk.read_le_f32()?, chunk.read_le_f32()?).into(),
                         )
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "NumberRange",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -1096,14 +1640,13 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                     );
 
                     for (value, referent) in values.zip(&type_info.referents) {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        add_property(instance, &property, value.into())
+                        add_property(&mut self.sink, *referent, property, value.into())
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "Rect",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -1112,7 +1655,6 @@ impl<'a, R: Read> DeserializerState<'a, R> {
             Type::PhysicalProperties => match canonical_type {
                 VariantType::PhysicalProperties => {
                     for referent in &type_info.referents {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
                         let value = if chunk.read_u8()? == 1 {
                             Variant::PhysicalProperties(PhysicalProperties::Custom(
                                 CustomPhysicalProperties {
@@ -1127,20 +1669,20 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                             Variant::PhysicalProperties(PhysicalProperties::Default)
                         };
 
-                        add_property(instance, &property, value);
+                        add_property(&mut self.sink, *referent, property, value);
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "PhysicalProperties",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
                 }
             },
             Type::Color3uint8 => match canonical_type {
-                VariantType::Color3 => {
+                VariantType::Color3 | VariantType::Color3uint8 => {
                     let len = type_info.referents.len();
                     let mut r = vec![0; len];
                     let mut g = vec![0; len];
@@ -1157,14 +1699,13 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                         .map(|((r, g), b)| Color3uint8::new(r, g, b));
 
                     for (color, referent) in colors.into_iter().zip(&type_info.referents) {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        add_property(instance, &property, color.into());
+                        add_property(&mut self.sink, *referent, property, color.into());
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "Color3",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -1176,14 +1717,13 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                     chunk.read_interleaved_i64_array(&mut values)?;
 
                     for (value, referent) in values.into_iter().zip(&type_info.referents) {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        add_property(instance, &property, value.into());
+                        add_property(&mut self.sink, *referent, property, value.into());
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "Int64",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -1198,22 +1738,25 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                         let shared_string =
                             self.shared_strings.get(value as usize).ok_or_else(|| {
                                 InnerError::InvalidPropData {
-                                    type_name: type_info.type_name.clone(),
-                                    prop_name: prop_name.clone(),
+                                    type_name: type_info.type_name.to_string(),
+                                    prop_name: prop_name.to_owned(),
                                     valid_value: "a valid SharedString",
                                     actual_value: format!("{:?}", value),
                                 }
                             })?;
 
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-
-                        add_property(instance, &property, shared_string.clone().into());
+                        add_property(
+                            &mut self.sink,
+                            *referent,
+                            property,
+                            shared_string.clone().into(),
+                        );
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "SharedString",
                         actual_type_name: format!("{:?}", invalid_type),
                     })
@@ -1260,8 +1803,8 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                             rotations.push(basic_rotation);
                         } else {
                             return Err(InnerError::BadRotationId {
-                                type_name: type_info.type_name.clone(),
-                                prop_name,
+                                type_name: type_info.type_name.to_string(),
+                                prop_name: prop_name.to_owned(),
                                 id,
                             });
                         }
@@ -1287,14 +1830,18 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                         });
                     }
 
+                    let mut present = vec![0; referents.len()];
+                    chunk.read_exact(&mut present)?;
+
                     let values = x
                         .into_iter()
                         .zip(y)
                         .zip(z)
                         .map(|((x, y), z)| Vector3::new(x, y, z))
                         .zip(rotations)
-                        .map(|(position, rotation)| {
-                            if chunk.read_u8().ok()? == 0 {
+                        .zip(present)
+                        .map(|((position, rotation), present)| {
+                            if present == 0 {
                                 None
                             } else {
                                 Some(CFrame::new(position, rotation))
@@ -1302,14 +1849,13 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                         });
 
                     for (cframe, referent) in values.zip(referents) {
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        add_property(instance, &property, cframe.into());
+                        add_property(&mut self.sink, *referent, property, cframe.into());
                     }
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "OptionalCFrame",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
@@ -1323,10 +1869,10 @@ impl<'a, R: Read> DeserializerState<'a, R> {
 
                     for (i, referent) in type_info.referents.iter().enumerate() {
                         let mut value = values[i].as_slice();
-                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
                         add_property(
-                            instance,
-                            &property,
+                            &mut self.sink,
+                            *referent,
+                            property,
                             UniqueId::new(
                                 value.read_be_u32()?,
                                 value.read_be_u32()?,
@@ -1338,20 +1884,43 @@ impl<'a, R: Read> DeserializerState<'a, R> {
                 }
                 invalid_type => {
                     return Err(InnerError::PropTypeMismatch {
-                        type_name: type_info.type_name.clone(),
-                        prop_name,
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
                         valid_type_names: "UniqueId",
                         actual_type_name: format!("{:?}", invalid_type),
                     });
                 }
             },
+            Type::SecurityCapabilities => match canonical_type {
+                VariantType::SecurityCapabilities => {
+                    let mut values = vec![0; type_info.referents.len()];
+                    chunk.read_interleaved_i64_array(&mut values)?;
+
+                    for (value, referent) in values.into_iter().zip(&type_info.referents) {
+                        add_property(
+                            &mut self.sink,
+                            *referent,
+                            property,
+                            SecurityCapabilities::from_bits(value as u64).into(),
+                        );
+                    }
+                }
+                invalid_type => {
+                    return Err(InnerError::PropTypeMismatch {
+                        type_name: type_info.type_name.to_string(),
+                        prop_name: prop_name.to_owned(),
+                        valid_type_names: "SecurityCapabilities",
+                        actual_type_name: format!("{:?}", invalid_type),
+                    });
+                }
+            },
         }
 
         Ok(())
     }
 
     #[profiling::function]
-    pub(super) fn decode_prnt_chunk(&mut self, mut chunk: &[u8]) -> Result<(), InnerError> {
+    pub(super) fn decode_prnt_chunk(&mut self, chunk: &mut &[u8]) -> Result<(), InnerError> {
         let version = chunk.read_u8()?;
 
         if version != 0 {
@@ -1365,26 +1934,55 @@ impl<'a, R: Read> DeserializerState<'a, R> {
 
         log::trace!("PRNT chunk ({} instances)", number_objects);
 
-        let mut subjects = vec![0; number_objects as usize];
-        let mut parents = vec![0; number_objects as usize];
+        // Each object has both a subject and a parent referent.
+        let number_objects = check_count(chunk, number_objects, 8)?;
+        let mut subjects = vec![0; number_objects];
+        let mut parents = vec![0; number_objects];
 
         chunk.read_referent_array(&mut subjects)?;
         chunk.read_referent_array(&mut parents)?;
 
-        for (id, parent_ref) in subjects.iter().copied().zip(parents.iter().copied()) {
-            if parent_ref == -1 {
-                self.root_instance_refs.push(id);
+        let refs_by_id = &self.refs_by_id;
+        let lookup = |id: i32| {
+            refs_by_id
+                .get(&id)
+                .copied()
+                .ok_or(InnerError::InvalidInstanceId { id })
+        };
+
+        let mut duplicates = 0;
+
+        for (id, parent_id) in subjects.iter().copied().zip(parents.iter().copied()) {
+            let referent = lookup(id)?;
+            let parent_ref = if parent_id == -1 {
+                Ref::none()
             } else {
-                let instance = self.instances_by_ref.get_mut(&parent_ref).unwrap();
-                instance.children.push(id);
+                lookup(parent_id)?
+            };
+
+            // An instance can only be in one place in the tree, so only the
+            // first parent it's given counts.
+            if !self.parented.insert(referent) {
+                if self.deserializer.recover {
+                    duplicates += 1;
+                    continue;
+                }
+
+                return Err(InnerError::DuplicateParent { id });
             }
+
+            self.sink.parent(referent, parent_ref);
+        }
+
+        if duplicates > 0 {
+            self.record_damage(Damage::DuplicateParents { count: duplicates });
         }
 
         Ok(())
     }
 
     #[profiling::function]
-    pub(super) fn decode_end_chunk(&mut self, _chunk: &[u8]) -> Result<(), InnerError> {
+    pub(super) fn decode_end_chunk(&mut self, _chunk: &mut &[u8]) -> Result<(), InnerError> {
         log::trace!("END chunk");
 
         // We don't do any validation on the END chunk. There's no useful
@@ -1394,34 +1992,46 @@ impl<'a, R: Read> DeserializerState<'a, R> {
         Ok(())
     }
 
-    /// Combines together all the decoded information to build and emplace
-    /// instances in our tree.
-    #[profiling::function]
-    pub(super) fn finish(mut self) -> WeakDom {
-        log::trace!("Constructing tree from deserialized data");
-
-        // Track all the instances we need to construct. Order of construction
-        // is important to preserve for both determinism and sometimes
-        // functionality of models we handle.
-        let mut instances_to_construct = VecDeque::new();
-
-        // Any instance with a parent of -1 will be at the top level of the
-        // tree. Because of the way rbx_dom_weak generally works, we need to
-        // start at the top of the tree to begin construction.
-        let root_ref = self.tree.root_ref();
-        for &referent in &self.root_instance_refs {
-            instances_to_construct.push_back((referent, root_ref));
+    /// Consumes the state, returning the sink that all decoded information
+    /// was written into.
+    pub(super) fn finish(mut self) -> (S, DecodeReport) {
+        if self.deserializer.recover {
+            let orphans: Vec<Ref> = self
+                .declared
+                .iter()
+                .copied()
+                .filter(|referent| !self.parented.contains(referent))
+                .collect();
+
+            if !orphans.is_empty() {
+                for &referent in &orphans {
+                    self.sink.parent(referent, Ref::none());
+                }
+
+                self.record_damage(Damage::MissingParents {
+                    count: orphans.len(),
+                });
+            }
         }
 
-        while let Some((referent, parent_ref)) = instances_to_construct.pop_front() {
-            let instance = self.instances_by_ref.remove(&referent).unwrap();
-            let id = self.tree.insert(parent_ref, instance.builder);
+        self.report.metadata = self.metadata;
 
-            for referent in instance.children {
-                instances_to_construct.push_back((referent, id));
-            }
+        if let Some(stats) = &mut self.report.stats {
+            stats.total_time = self.started.elapsed();
+        }
+
+        log::debug!(
+            "Decoded {} instances and {} property values",
+            self.refs_by_id.len(),
+            self.property_values
+        );
+
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("instances", self.refs_by_id.len());
+            self.span.record("property_values", self.property_values);
         }
 
-        self.tree
+        (self.sink, self.report)
     }
 }
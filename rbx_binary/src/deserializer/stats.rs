@@ -0,0 +1,133 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::chunk::CHUNK_HEADER_LEN;
+
+use super::chunk_reader::ChunkKind;
+
+/// Measurements taken while deserializing a file, returned as part of a
+/// [`DecodeReport`][crate::DecodeReport] when
+/// [`Deserializer::collect_stats`][crate::Deserializer::collect_stats] is
+/// turned on.
+#[derive(Debug, Clone, Default)]
+pub struct DecodeStats {
+    pub(crate) chunks: Vec<ChunkStats>,
+    pub(crate) classes: BTreeMap<String, ClassStats>,
+    pub(crate) shared_strings: usize,
+    pub(crate) shared_string_bytes: u64,
+    pub(crate) total_time: Duration,
+}
+
+impl DecodeStats {
+    /// Every chunk that was decoded, in the order they appear in the file.
+    pub fn chunks(&self) -> &[ChunkStats] {
+        &self.chunks
+    }
+
+    /// The number of instances and properties decoded for each class, keyed
+    /// by ClassName.
+    pub fn classes(&self) -> &BTreeMap<String, ClassStats> {
+        &self.classes
+    }
+
+    /// The number of SharedStrings in the file.
+    pub fn shared_strings(&self) -> usize {
+        self.shared_strings
+    }
+
+    /// The combined size of every SharedString in the file, in bytes.
+    pub fn shared_string_bytes(&self) -> u64 {
+        self.shared_string_bytes
+    }
+
+    /// How long deserialization took from start to finish, including reading
+    /// and decompressing chunks. This is always zero on wasm32-unknown-unknown,
+    /// which has no clock.
+    pub fn total_time(&self) -> Duration {
+        self.total_time
+    }
+}
+
+/// Measurements for a single chunk, part of [`DecodeStats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkStats {
+    pub(crate) name: [u8; 4],
+    pub(crate) offset: u64,
+    pub(crate) file_len: u64,
+    pub(crate) uncompressed_len: usize,
+    pub(crate) decode_time: Duration,
+}
+
+impl ChunkStats {
+    /// The kind of this chunk.
+    pub fn kind(&self) -> ChunkKind {
+        ChunkKind::from_name(&self.name)
+    }
+
+    /// The raw four byte name of this chunk, like `b"PROP"`.
+    pub fn name(&self) -> &[u8; 4] {
+        &self.name
+    }
+
+    /// The offset of this chunk's header from the start of the file.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The number of bytes this chunk's contents take up in the file, not
+    /// including its header.
+    pub fn stored_len(&self) -> u64 {
+        self.file_len - CHUNK_HEADER_LEN
+    }
+
+    /// The size of this chunk's contents once decompressed.
+    pub fn uncompressed_len(&self) -> usize {
+        self.uncompressed_len
+    }
+
+    /// How many times larger this chunk's contents are once decompressed.
+    /// This is 1.0 for chunks that aren't compressed.
+    pub fn compression_ratio(&self) -> f64 {
+        if self.stored_len() == 0 {
+            1.0
+        } else {
+            self.uncompressed_len as f64 / self.stored_len() as f64
+        }
+    }
+
+    /// How long it took to decode this chunk's contents after they were
+    /// decompressed. Decompression isn't included, since chunks may be
+    /// decompressed ahead of time or in parallel. Like
+    /// [`DecodeStats::total_time`], this is always zero on
+    /// wasm32-unknown-unknown.
+    pub fn decode_time(&self) -> Duration {
+        self.decode_time
+    }
+}
+
+/// The number of instances and property values decoded for one class, part
+/// of [`DecodeStats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClassStats {
+    pub(crate) instances: usize,
+    pub(crate) properties: usize,
+    pub(crate) property_values: usize,
+}
+
+impl ClassStats {
+    /// The number of instances of the class.
+    pub fn instances(&self) -> usize {
+        self.instances
+    }
+
+    /// The number of properties decoded for the class, which is the number
+    /// of PROP chunks for it that weren't skipped.
+    pub fn properties(&self) -> usize {
+        self.properties
+    }
+
+    /// The number of property values decoded for the class, one per instance
+    /// per property.
+    pub fn property_values(&self) -> usize {
+        self.property_values
+    }
+}
@@ -47,13 +47,46 @@ let dom = WeakDom::new(InstanceBuilder::new("Folder"));
 let output = BufWriter::new(File::create("PlainFolder.rbxm")?);
 rbx_binary::to_writer(output, &dom, &[dom.root_ref()])?;
 
+# Ok::<(), Box<dyn std::error::Error>>(())
+```
+
+## Handle errors
+
+Both [`DecodeError`] and [`EncodeError`] implement [`DomError`], which gives a
+machine-readable [`ErrorCode`] as well as where in the file the error happened
+and which instance and property it was about, when those are known.
+
+```no_run
+use rbx_binary::{DomError, ErrorCode};
+
+let input = std::fs::read("MyModel.rbxm")?;
+
+match rbx_binary::from_reader(input.as_slice()) {
+    Ok(dom) => println!("Read {} root instances", dom.root().children().len()),
+    Err(err) if err.code() == ErrorCode::UnexpectedEof => println!("The file was cut off"),
+    Err(err) => {
+        println!("{} error: {}", err.code(), err);
+
+        if let Some(location) = err.location() {
+            println!("at {}", location);
+        }
+        if let Some(property_name) = err.property_name() {
+            println!("while reading {}", property_name);
+        }
+    }
+}
+
 # Ok::<(), Box<dyn std::error::Error>>(())
 ```
 */
 
 #![deny(missing_docs)]
 
+pub mod codec;
+pub mod csg;
+
 mod chunk;
+mod clock;
 mod core;
 mod deserializer;
 mod serializer;
@@ -69,6 +102,9 @@ use std::io::{Read, Write};
 
 use rbx_dom_weak::{types::Ref, WeakDom};
 
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncWrite};
+
 /// An unstable textual format that can be used to debug binary models.
 #[cfg(feature = "unstable_text_format")]
 pub mod text_format {
@@ -76,17 +112,58 @@ pub mod text_format {
 }
 
 pub use crate::{
-    deserializer::{Deserializer, Error as DecodeError},
-    serializer::{Error as EncodeError, Serializer},
+    deserializer::{
+        ChunkInfo, ChunkKind, ChunkReader, ChunkStats, ClassStats, Damage, DecodeLimits,
+        DecodeProgress, DecodeReport, DecodeSink, DecodeStats, DecodeWarning, Deserializer,
+        Error as DecodeError, SkippedProperty, UnknownEnumValues, UnknownProperty,
+    },
+    serializer::{
+        CompressionAlgorithm, CompressionOptions, EncodeReport, Error as EncodeError,
+        ExternalReferences, NulledReference, PropertyOrder, Serializer,
+    },
 };
 
+pub use rbx_dom_weak::types::{DomError, ErrorCode, ErrorLocation};
+
 /// Deserialize a Roblox binary model or place from a stream.
 pub fn from_reader<R: Read>(reader: R) -> Result<WeakDom, DecodeError> {
     Deserializer::new().deserialize(reader)
 }
 
+/// Deserialize a Roblox binary model or place that's already in memory. See
+/// [`Deserializer::deserialize_slice`].
+pub fn from_slice(input: &[u8]) -> Result<WeakDom, DecodeError> {
+    Deserializer::new().deserialize_slice(input)
+}
+
 /// Serializes a subset of the given DOM to a binary format model or place,
 /// writing to something that implements the `std::io::Write` trait.
 pub fn to_writer<W: Write>(writer: W, dom: &WeakDom, refs: &[Ref]) -> Result<(), EncodeError> {
     Serializer::new().serialize(writer, dom, refs)
 }
+
+/// Serializes a subset of the given DOM to a binary format model or place,
+/// returning the bytes. This is handy where there's nothing to stream to, like
+/// from WebAssembly.
+pub fn to_vec(dom: &WeakDom, refs: &[Ref]) -> Result<Vec<u8>, EncodeError> {
+    let mut output = Vec::new();
+    Serializer::new().serialize(&mut output, dom, refs)?;
+    Ok(output)
+}
+
+/// Deserialize a Roblox binary model or place from an asynchronous stream.
+#[cfg(feature = "tokio")]
+pub async fn from_reader_async<R: AsyncRead + Unpin>(reader: R) -> Result<WeakDom, DecodeError> {
+    Deserializer::new().deserialize_async(reader).await
+}
+
+/// Serializes a subset of the given DOM to a binary format model or place,
+/// writing to something that implements tokio's `AsyncWrite` trait.
+#[cfg(feature = "tokio")]
+pub async fn to_writer_async<W: AsyncWrite + Unpin>(
+    writer: W,
+    dom: &WeakDom,
+    refs: &[Ref],
+) -> Result<(), EncodeError> {
+    Serializer::new().serialize_async(writer, dom, refs).await
+}
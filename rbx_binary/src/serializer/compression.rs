@@ -0,0 +1,129 @@
+use std::io;
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use lz4::block::CompressionMode;
+
+/// The algorithm used to compress the chunks of a binary model or place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CompressionAlgorithm {
+    /// Chunks are stored as-is.
+    None,
+
+    /// Chunks are compressed with LZ4, which is what Roblox Studio uses.
+    Lz4,
+
+    /// Chunks are compressed with Zstandard, which Roblox also reads. Needs
+    /// the `zstd` feature.
+    ///
+    /// Levels go from 1 to 22, where higher levels produce smaller output.
+    /// A level of `None` uses Zstandard's default, which is 3.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+/// Controls how a [`Serializer`](crate::Serializer) compresses the chunks it
+/// writes.
+///
+/// The defaults match what rbx_binary has always done: every chunk is
+/// compressed with LZ4 at its default level.
+///
+/// ## Example
+/// ```
+/// use rbx_binary::{CompressionAlgorithm, CompressionOptions, Serializer};
+///
+/// // Spend more CPU time for a smaller file, and don't bother compressing
+/// // chunks that are too small to benefit from it.
+/// let options = CompressionOptions::new()
+///     .algorithm(CompressionAlgorithm::Lz4)
+///     .level(Some(9))
+///     .min_size(64);
+///
+/// let serializer = Serializer::new().compression(options);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionOptions {
+    algorithm: CompressionAlgorithm,
+    level: Option<i32>,
+    min_size: usize,
+}
+
+impl CompressionOptions {
+    /// Create a new `CompressionOptions` with the default settings.
+    pub fn new() -> Self {
+        Self {
+            algorithm: CompressionAlgorithm::Lz4,
+            level: None,
+            min_size: 0,
+        }
+    }
+
+    /// Create a `CompressionOptions` that stores every chunk uncompressed.
+    pub fn uncompressed() -> Self {
+        Self::new().algorithm(CompressionAlgorithm::None)
+    }
+
+    /// Sets the algorithm used to compress chunks.
+    pub fn algorithm(mut self, algorithm: CompressionAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Sets the compression level. `None` uses the algorithm's default level.
+    ///
+    /// For LZ4, any level selects the slower LZ4 HC compressor, where higher
+    /// levels produce smaller output. LZ4 HC isn't available on
+    /// wasm32-unknown-unknown, so the level is ignored there.
+    pub fn level(mut self, level: Option<i32>) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Sets the size, in bytes, below which chunks are stored uncompressed
+    /// because compressing them isn't worth the time.
+    pub fn min_size(mut self, min_size: usize) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// Compresses the contents of a chunk, returning `None` if it should be
+    /// stored uncompressed instead.
+    pub(crate) fn compress(&self, data: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        if data.len() < self.min_size {
+            return Ok(None);
+        }
+
+        match self.algorithm {
+            CompressionAlgorithm::None => Ok(None),
+            CompressionAlgorithm::Lz4 => lz4_compress(data, self.level).map(Some),
+            #[cfg(feature = "zstd")]
+            CompressionAlgorithm::Zstd => {
+                let level = self.level.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL);
+                zstd::bulk::compress(data, level).map(Some)
+            }
+        }
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn lz4_compress(data: &[u8], level: Option<i32>) -> io::Result<Vec<u8>> {
+    let mode = match level {
+        Some(level) => CompressionMode::HIGHCOMPRESSION(level),
+        None => CompressionMode::DEFAULT,
+    };
+
+    lz4::block::compress(data, Some(mode), false)
+}
+
+/// lz4_flex, which is used on wasm32-unknown-unknown, has no LZ4 HC
+/// compressor, so the level is ignored there.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn lz4_compress(data: &[u8], _level: Option<i32>) -> io::Result<Vec<u8>> {
+    Ok(lz4_flex::block::compress(data))
+}
+
+impl Default for CompressionOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
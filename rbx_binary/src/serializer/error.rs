@@ -1,6 +1,6 @@
 use std::io;
 
-use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::types::{DomError, ErrorCode, Ref, VariantType};
 use thiserror::Error;
 
 /// Represents an error that occurred during serialization.
@@ -10,6 +10,46 @@ pub struct Error {
     source: Box<InnerError>,
 }
 
+impl DomError for Error {
+    fn code(&self) -> ErrorCode {
+        match &*self.source {
+            InnerError::Io { .. } => ErrorCode::Io,
+            InnerError::PropTypeMismatch { .. } => ErrorCode::PropertyTypeMismatch,
+            InnerError::UnsupportedPropType { .. } => ErrorCode::UnsupportedPropertyType,
+            InnerError::InvalidPropValue { .. } => ErrorCode::InvalidPropertyValue,
+            InnerError::InvalidInstanceId { .. } => ErrorCode::UnknownReferent,
+        }
+    }
+
+    fn referent(&self) -> Option<Ref> {
+        match &*self.source {
+            InnerError::Io { .. } => None,
+            InnerError::PropTypeMismatch { referent, .. }
+            | InnerError::UnsupportedPropType { referent, .. }
+            | InnerError::InvalidPropValue { referent, .. }
+            | InnerError::InvalidInstanceId { referent } => Some(*referent),
+        }
+    }
+
+    fn class_name(&self) -> Option<&str> {
+        match &*self.source {
+            InnerError::PropTypeMismatch { type_name, .. }
+            | InnerError::UnsupportedPropType { type_name, .. }
+            | InnerError::InvalidPropValue { type_name, .. } => Some(type_name),
+            _ => None,
+        }
+    }
+
+    fn property_name(&self) -> Option<&str> {
+        match &*self.source {
+            InnerError::PropTypeMismatch { prop_name, .. }
+            | InnerError::UnsupportedPropType { prop_name, .. }
+            | InnerError::InvalidPropValue { prop_name, .. } => Some(prop_name),
+            _ => None,
+        }
+    }
+}
+
 impl From<InnerError> for Error {
     fn from(inner: InnerError) -> Self {
         Self {
@@ -28,14 +68,15 @@ pub(super) enum InnerError {
 
     #[error(
         "Property type mismatch: Expected {type_name}.{prop_name} to be of type {valid_type_names}, \
-        but it was of type {actual_type_name} on instance {instance_full_name}",
+        but it was of type {actual_type:?} on instance {instance_full_name}",
     )]
     PropTypeMismatch {
         type_name: String,
         prop_name: String,
         valid_type_names: &'static str,
-        actual_type_name: String,
+        actual_type: VariantType,
         instance_full_name: String,
+        referent: Ref,
     },
 
     #[error("Unsupported property type: {type_name}.{prop_name} is of type {prop_type}")]
@@ -43,18 +84,20 @@ pub(super) enum InnerError {
         type_name: String,
         prop_name: String,
         prop_type: String,
+        referent: Ref,
     },
 
     #[error(
         "Invalid property value: The instance {instance_full_name} had a property \
-        ({type_name}.{prop_name}) of type {prop_type} with a value that could \
+        ({type_name}.{prop_name}) of type {prop_type:?} with a value that could \
         not be written."
     )]
     InvalidPropValue {
         instance_full_name: String,
         type_name: String,
         prop_name: String,
-        prop_type: String,
+        prop_type: VariantType,
+        referent: Ref,
     },
 
     #[error("The instance with referent {referent:?} was not present in the dom.")]
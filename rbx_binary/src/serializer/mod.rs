@@ -1,13 +1,31 @@
+mod compression;
 mod error;
+mod report;
 mod state;
 
-use std::io::Write;
+use std::{collections::HashMap, io::Write};
 
 use rbx_dom_weak::{types::Ref, WeakDom};
+use rbx_reflection::ReflectionDatabase;
+
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::{core::reflection_database, deserializer::UnknownProperty};
+
+#[cfg(feature = "tokio")]
+use crate::chunk;
 
 use self::state::SerializerState;
 
-pub use self::error::Error;
+#[cfg(feature = "tokio")]
+use self::error::InnerError;
+
+pub use self::{
+    compression::{CompressionAlgorithm, CompressionOptions},
+    error::Error,
+    report::{EncodeReport, NulledReference},
+};
 
 /// A configurable serializer for Roblox binary models and places.
 ///
@@ -27,16 +45,267 @@ pub use self::error::Error;
 ///
 /// # Ok::<(), Box<dyn std::error::Error>>(())
 /// ```
+///
+/// Each chunk is written to the output as soon as it has been built, so the
+/// serializer never holds the whole file in memory. Pass a file or socket
+/// instead of a `Vec<u8>` to avoid doing so yourself. For very large places,
+/// [`low_memory`](Self::low_memory) additionally keeps individual chunks from
+/// growing too large in memory.
 // future settings:
-// * reflection_database: Option<ReflectionDatabase> = default
 // * recursive: bool = true
 #[non_exhaustive]
-pub struct Serializer {}
+pub struct Serializer {
+    spill_threshold: Option<usize>,
+    compression: CompressionOptions,
+    unknown_properties: Vec<UnknownProperty>,
+    metadata: HashMap<String, String>,
+    signatures: Option<Vec<u8>>,
+    shared_string_dedup_threshold: usize,
+    shared_string_promotion_threshold: Option<usize>,
+    property_order: PropertyOrder,
+    external_references: ExternalReferences,
+    include_default_properties: bool,
+    deterministic: bool,
+    normalize_floats: bool,
+    reflection: bool,
+    database: Option<&'static ReflectionDatabase<'static>>,
+}
+
+/// What a [`Serializer`] does with Ref properties that point to instances
+/// outside of the ones being serialized, like an ObjectValue whose Value is
+/// somewhere else in the DOM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExternalReferences {
+    /// The properties are written as empty. This is the default.
+    Null,
+
+    /// The instances they point to are written too, along with their
+    /// descendants, as extra roots of the file. Instances included this way
+    /// can point to even more instances, which are included as well.
+    Include,
+}
+
+/// The order that a [`Serializer`] writes the properties of each class in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PropertyOrder {
+    /// Properties are sorted by name. This is the default.
+    Alphabetical,
+
+    /// Properties are grouped by the class that declares them in the
+    /// reflection database, starting with `Instance` and ending with the most
+    /// specific class. Within each class, properties are sorted by name.
+    /// Properties not known to the reflection database come last.
+    Reflection,
+}
+
+/// The spill threshold used by [`Serializer::low_memory`].
+const LOW_MEMORY_SPILL_THRESHOLD: usize = 4 * 1024 * 1024;
 
 impl Serializer {
     /// Create a new `Serializer` with the default settings.
     pub fn new() -> Self {
-        Serializer {}
+        Serializer {
+            spill_threshold: None,
+            compression: CompressionOptions::new(),
+            unknown_properties: Vec::new(),
+            metadata: HashMap::new(),
+            signatures: None,
+            shared_string_dedup_threshold: 0,
+            shared_string_promotion_threshold: None,
+            property_order: PropertyOrder::Alphabetical,
+            external_references: ExternalReferences::Null,
+            include_default_properties: true,
+            deterministic: false,
+            normalize_floats: false,
+            reflection: true,
+            database: reflection_database(),
+        }
+    }
+
+    /// Enables or disables low-memory mode. In low-memory mode, any chunk that
+    /// grows past 4 MiB while it is being built, like the property table for
+    /// a class with many instances, is spilled to a temporary file and written
+    /// uncompressed.
+    ///
+    /// This trades a larger output file and some disk I/O for a much lower
+    /// peak memory usage. On wasm32-unknown-unknown, which has no temporary
+    /// files, this does nothing.
+    pub fn low_memory(self, enabled: bool) -> Self {
+        let threshold = if enabled {
+            Some(LOW_MEMORY_SPILL_THRESHOLD)
+        } else {
+            None
+        };
+
+        self.spill_threshold(threshold)
+    }
+
+    /// Sets the size, in bytes, past which a chunk is spilled to a temporary
+    /// file while it is being built. `None`, the default, keeps every chunk in
+    /// memory. Chunks are never spilled on wasm32-unknown-unknown.
+    ///
+    /// Spilled chunks are written uncompressed, whatever the
+    /// [`compression`](Self::compression) settings are. Both LZ4 and Zstandard
+    /// compress a chunk as one block, which would mean reading the whole chunk
+    /// back into memory. Chunks that stay under the threshold are still
+    /// compressed as usual.
+    pub fn spill_threshold(mut self, threshold: Option<usize>) -> Self {
+        self.spill_threshold = threshold;
+        self
+    }
+
+    /// Sets how chunks are compressed. See [`CompressionOptions`] for the
+    /// available settings.
+    pub fn compression(mut self, options: CompressionOptions) -> Self {
+        self.compression = options;
+        self
+    }
+
+    /// Sets properties of types unknown to rbx_binary that should be written
+    /// back out, usually taken from the [`DecodeReport`][crate::DecodeReport]
+    /// of a deserializer with
+    /// [`preserve_unknown_properties`][crate::Deserializer::preserve_unknown_properties]
+    /// enabled.
+    ///
+    /// An unknown property is only written if every instance it was read from
+    /// is being serialized, and no others of its class are. Otherwise, it is
+    /// dropped with a warning.
+    pub fn unknown_properties(mut self, properties: Vec<UnknownProperty>) -> Self {
+        self.unknown_properties = properties;
+        self
+    }
+
+    /// Sets the key/value pairs written to the file's META chunk, usually
+    /// taken from the [`DecodeReport`][crate::DecodeReport] of the file being
+    /// rewritten. Roblox Studio uses these to record settings about the whole
+    /// file, like `ExplicitAutoJoints`.
+    ///
+    /// No META chunk is written if there are none, which is the default.
+    pub fn metadata(mut self, metadata: HashMap<String, String>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Sets the contents of a SIGN chunk to write, usually taken from the
+    /// [`DecodeReport`][crate::DecodeReport] of the file being rewritten.
+    ///
+    /// rbx_binary doesn't know how signatures are computed, so they're only
+    /// meaningful when rewriting a file without changing it. Roblox may
+    /// reject a file whose signatures don't match its contents. No SIGN chunk
+    /// is written by default.
+    pub fn signatures(mut self, signatures: Option<Vec<u8>>) -> Self {
+        self.signatures = signatures;
+        self
+    }
+
+    /// Sets the size, in bytes, that a SharedString must be to be deduplicated.
+    ///
+    /// SharedStrings at least this large are stored once in the file no matter
+    /// how many instances use them. Smaller SharedStrings are stored once for
+    /// every instance that uses them. The default of 0 deduplicates every
+    /// SharedString, like Roblox Studio does.
+    pub fn shared_string_dedup_threshold(mut self, threshold: usize) -> Self {
+        self.shared_string_dedup_threshold = threshold;
+        self
+    }
+
+    /// Sets the size, in bytes, past which identical BinaryString values are
+    /// written as SharedStrings so that they're only stored once. `None`, the
+    /// default, writes every BinaryString as-is.
+    ///
+    /// Only properties that Roblox also accepts as a SharedString are
+    /// promoted, like the `ChildData` and `MeshData` of unions, which are
+    /// written as `ChildData2` and `MeshData2`. A value is promoted when it
+    /// appears on more than one instance being serialized. This recovers the
+    /// deduplication Studio does for files regenerated by other tools.
+    pub fn shared_string_promotion_threshold(mut self, threshold: Option<usize>) -> Self {
+        self.shared_string_promotion_threshold = threshold;
+        self
+    }
+
+    /// Sets the order that the properties of each class are written in.
+    pub fn property_order(mut self, order: PropertyOrder) -> Self {
+        self.property_order = order;
+        self
+    }
+
+    /// Sets what happens to Ref properties that point to instances that
+    /// aren't being serialized. Use
+    /// [`serialize_with_report`](Self::serialize_with_report) to find out
+    /// which instances were included and which properties were written as
+    /// empty.
+    pub fn external_references(mut self, handling: ExternalReferences) -> Self {
+        self.external_references = handling;
+        self
+    }
+
+    /// Sets whether properties that have their default value on every
+    /// instance of a class are written. Defaults to `true`.
+    ///
+    /// Values are compared against the defaults in the reflection database.
+    /// Leaving them out makes files smaller, and files loaded into Roblox
+    /// will use the same defaults.
+    pub fn include_default_properties(mut self, include: bool) -> Self {
+        self.include_default_properties = include;
+        self
+    }
+
+    /// Enables or disables deterministic output. When enabled, serializing the
+    /// same DOM with the same settings always produces byte-identical files,
+    /// which content-addressed caches and diffing tools rely on.
+    ///
+    /// Classes, properties, referents, and SharedStrings are always written in
+    /// a stable order. This setting additionally assigns type IDs
+    /// alphabetically and uses fixed values in place of generated defaults.
+    /// Instances that are missing a `UniqueId` are each given one derived from
+    /// their referent in the file instead of a random one.
+    pub fn deterministic(mut self, enabled: bool) -> Self {
+        self.deterministic = enabled;
+        self
+    }
+
+    /// Enables or disables float normalization. Defaults to `false`.
+    ///
+    /// Normally, every float is written with exactly the bits it has in the
+    /// DOM, so deserializing a file and serializing it again reproduces its
+    /// floats exactly, including negative zero and the payloads of NaNs.
+    /// With normalization, negative zero is written as zero, every NaN is
+    /// written as the same NaN, and rotations that are within rounding error
+    /// of a right-angle rotation are written as that rotation. This makes
+    /// files that differ only by rounding produce identical output.
+    pub fn normalize_floats(mut self, enabled: bool) -> Self {
+        self.normalize_floats = enabled;
+        self
+    }
+
+    /// Enables or disables the use of the reflection database for properties.
+    /// Defaults to `true`.
+    ///
+    /// With reflection turned off, properties are written with exactly the
+    /// names and types they have in the DOM, and missing values are filled in
+    /// with a generic default for their type. This is the counterpart to
+    /// [`Deserializer::reflection`][crate::Deserializer::reflection].
+    /// [SharedString promotion](Self::shared_string_promotion_threshold) is
+    /// skipped, since it renames properties.
+    ///
+    /// If rbx_binary is built without the `reflection_database` feature,
+    /// there is no database to use unless one is given with
+    /// [`reflection_database`](Self::reflection_database), so reflection is
+    /// off and no instances are marked as services.
+    pub fn reflection(mut self, enabled: bool) -> Self {
+        self.reflection = enabled;
+        self
+    }
+
+    /// Sets the reflection database to use instead of
+    /// `rbx_reflection_database::global`, like one for the version of Roblox
+    /// that the files being written should open in. These can
+    /// come from `rbx_reflection_database::get_bundled`.
+    pub fn reflection_database(mut self, database: &'static ReflectionDatabase<'static>) -> Self {
+        self.database = Some(database);
+        self
     }
 
     /// Serialize a Roblox binary model or place into the given stream using
@@ -44,22 +313,116 @@ impl Serializer {
     pub fn serialize<W: Write>(&self, writer: W, dom: &WeakDom, refs: &[Ref]) -> Result<(), Error> {
         profiling::scope!("rbx_binary::seserialize");
 
-        let mut serializer = SerializerState::new(dom, writer);
+        self.serialize_with_report(writer, dom, refs)?;
+
+        Ok(())
+    }
+
+    /// Serialize a Roblox binary model or place into the given stream,
+    /// returning an [`EncodeReport`] that describes how Ref properties
+    /// pointing outside of the given instances were handled.
+    pub fn serialize_with_report<W: Write>(
+        &self,
+        writer: W,
+        dom: &WeakDom,
+        refs: &[Ref],
+    ) -> Result<EncodeReport, Error> {
+        profiling::scope!("rbx_binary::serialize_with_report");
+
+        let mut serializer = SerializerState::new(self, dom, writer);
+
+        #[cfg(feature = "tracing")]
+        let _span = serializer.span.clone().entered();
 
         serializer.add_instances(refs)?;
         serializer.generate_referents();
         serializer.write_header()?;
         serializer.serialize_metadata()?;
         serializer.serialize_shared_strings()?;
+        serializer.serialize_signatures()?;
         serializer.serialize_instances()?;
         serializer.serialize_properties()?;
         serializer.serialize_parents()?;
         serializer.serialize_end()?;
 
+        Ok(serializer.finish())
+    }
+
+    /// Serializes a subset of the given DOM to a binary format model or place,
+    /// writing to an asynchronous stream.
+    ///
+    /// Each PROP chunk is encoded in memory and written to `writer` as soon
+    /// as it's finished, so at most one property's values are held in memory
+    /// at once. The chunks before them (like SSTR and INST) and after them
+    /// (PRNT and END) are each written as a group once they're all finished.
+    /// With the `rayon` feature, PROP chunks are written in batches that are
+    /// compressed in parallel instead of one at a time.
+    #[cfg(feature = "tokio")]
+    pub async fn serialize_async<W: AsyncWrite + Unpin>(
+        &self,
+        mut writer: W,
+        dom: &WeakDom,
+        refs: &[Ref],
+    ) -> Result<(), Error> {
+        profiling::scope!("rbx_binary::serialize_async");
+
+        let mut serializer = SerializerState::new(self, dom, Vec::new());
+
+        // The serializer's span is only entered while encoding, since a span
+        // can't stay entered across an await.
+        {
+            #[cfg(feature = "tracing")]
+            let _span = serializer.span.clone().entered();
+
+            serializer.add_instances(refs)?;
+            serializer.generate_referents();
+            serializer.write_header()?;
+            serializer.serialize_metadata()?;
+            serializer.serialize_shared_strings()?;
+            serializer.serialize_signatures()?;
+            serializer.serialize_instances()?;
+        }
+        drain_output(serializer.output_mut(), &mut writer).await?;
+
+        let chunks = serializer.property_chunks();
+        for batch in chunks.chunks(chunk::batch_size()) {
+            {
+                #[cfg(feature = "tracing")]
+                let _span = serializer.span.clone().entered();
+
+                serializer.serialize_property_chunks(batch)?;
+            }
+            drain_output(serializer.output_mut(), &mut writer).await?;
+        }
+
+        {
+            #[cfg(feature = "tracing")]
+            let _span = serializer.span.clone().entered();
+
+            serializer.serialize_parents()?;
+            serializer.serialize_end()?;
+        }
+        drain_output(serializer.output_mut(), &mut writer).await?;
+
+        writer.flush().await.map_err(InnerError::from)?;
+        serializer.finish();
+
         Ok(())
     }
 }
 
+/// Writes everything buffered in `output` to `writer`, leaving `output` empty.
+#[cfg(feature = "tokio")]
+async fn drain_output<W: AsyncWrite + Unpin>(
+    output: &mut Vec<u8>,
+    writer: &mut W,
+) -> Result<(), Error> {
+    writer.write_all(output).await.map_err(InnerError::from)?;
+    output.clear();
+
+    Ok(())
+}
+
 impl Default for Serializer {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,60 @@
+use rbx_dom_weak::types::Ref;
+
+/// Extra information gathered while serializing a file, returned by
+/// [`Serializer::serialize_with_report`][crate::Serializer::serialize_with_report].
+#[derive(Debug, Clone, Default)]
+pub struct EncodeReport {
+    pub(crate) included_instances: Vec<Ref>,
+    pub(crate) nulled_references: Vec<NulledReference>,
+}
+
+impl EncodeReport {
+    /// Every instance that wasn't one of the given roots or their descendants,
+    /// but was written along with its descendants because a Ref property
+    /// pointed to it. Only a serializer configured with
+    /// [`ExternalReferences::Include`][crate::ExternalReferences::Include]
+    /// includes extra instances.
+    pub fn included_instances(&self) -> &[Ref] {
+        &self.included_instances
+    }
+
+    /// Every Ref property that was written as empty because it pointed to an
+    /// instance that isn't part of the file.
+    pub fn nulled_references(&self) -> &[NulledReference] {
+        &self.nulled_references
+    }
+}
+
+/// Describes a Ref property that pointed outside of the instances being
+/// serialized, and was written as empty instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NulledReference {
+    instance: Ref,
+    property_name: String,
+    target: Ref,
+}
+
+impl NulledReference {
+    pub(crate) fn new(instance: Ref, property_name: String, target: Ref) -> Self {
+        Self {
+            instance,
+            property_name,
+            target,
+        }
+    }
+
+    /// The instance that has the property.
+    pub fn instance(&self) -> Ref {
+        self.instance
+    }
+
+    /// The name of the property.
+    pub fn property_name(&self) -> &str {
+        &self.property_name
+    }
+
+    /// The instance the property pointed to.
+    pub fn target(&self) -> Ref {
+        self.target
+    }
+}
@@ -3,37 +3,54 @@ use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     convert::TryInto,
     io::Write,
-    u32,
+    mem,
 };
 
 use rbx_dom_weak::{
     types::{
         Attributes, Axes, BinaryString, BrickColor, CFrame, Color3, Color3uint8, ColorSequence,
-        ColorSequenceKeypoint, Content, Enum, Faces, Font, Matrix3, NumberRange, NumberSequence,
-        NumberSequenceKeypoint, PhysicalProperties, Ray, Rect, Ref, SharedString, Tags, UDim,
-        UDim2, UniqueId, Variant, VariantType, Vector2, Vector3, Vector3int16,
+        ColorSequenceKeypoint, Content, CustomPhysicalProperties, Enum, Faces, Font, Matrix3,
+        NumberRange, NumberSequence, NumberSequenceKeypoint, PhysicalProperties, Ray, Rect, Ref,
+        SecurityCapabilities, SharedString, Tags, UDim, UDim2, UniqueId, Variant, VariantType,
+        Vector2, Vector3, Vector3int16,
     },
     Instance, WeakDom,
 };
 
-use rbx_reflection::{ClassDescriptor, ClassTag, DataType};
+use rbx_reflection::{
+    ClassDescriptor, ClassTag, DataType, ReflectionDatabase, SerializationBehavior,
+};
 
 use crate::{
-    chunk::{ChunkBuilder, ChunkCompression},
-    core::{
-        find_property_descriptors, RbxWriteExt, FILE_MAGIC_HEADER, FILE_SIGNATURE, FILE_VERSION,
-    },
+    chunk::{self, ChunkBuilder, ChunkCompression},
+    core::{RbxWriteExt, FILE_MAGIC_HEADER, FILE_SIGNATURE, FILE_VERSION},
     types::Type,
 };
 
-use super::error::InnerError;
+use crate::deserializer::UnknownProperty;
+
+use super::{
+    error::InnerError,
+    report::{EncodeReport, NulledReference},
+    ExternalReferences, PropertyOrder, Serializer,
+};
 
 static FILE_FOOTER: &[u8] = b"</roblox>";
 
+/// BinaryString properties that Roblox also accepts as a SharedString under
+/// another name, as pairs of the BinaryString property and the SharedString
+/// property. Studio writes the SharedString forms, which lets identical
+/// values on different instances be stored only once.
+static PROMOTABLE_PROPERTIES: &[(&str, &str)] =
+    &[("ChildData", "ChildData2"), ("MeshData", "MeshData2")];
+
 /// Represents all of the state during a single serialization session. A new
 /// `BinarySerializer` object should be created every time we want to serialize
 /// a binary model file.
 pub(super) struct SerializerState<'dom, W> {
+    /// The user-provided configuration that we should use.
+    serializer: &'dom Serializer,
+
     /// The dom containing all of the instances that we're serializing.
     dom: &'dom WeakDom,
 
@@ -48,17 +65,52 @@ pub(super) struct SerializerState<'dom, W> {
     /// the binary model format, signed integers.
     id_to_referent: HashMap<Ref, i32>,
 
+    /// What happened to Ref properties that pointed outside of the instances
+    /// being serialized.
+    report: EncodeReport,
+
     /// All of the types of instance discovered by our serializer that we'll be
     /// writing into the output.
     type_infos: TypeInfos<'dom>,
 
     /// All of the SharedStrings in the DOM, in the order they'll be written
-    // in.
-    shared_strings: Vec<SharedString>,
+    // in. SharedStrings smaller than the serializer's dedup threshold get one
+    // entry per instance that uses them, which is recorded alongside them.
+    shared_strings: Vec<(SharedString, Option<Ref>)>,
 
     /// A map of SharedStrings to where it is in the SSTR chunk. This is used
     /// for writing PROP chunks.
     shared_string_ids: HashMap<SharedString, u32>,
+
+    /// Like `shared_string_ids`, but for the SharedStrings that aren't
+    /// deduplicated across instances.
+    instance_shared_string_ids: HashMap<(Ref, SharedString), u32>,
+
+    /// BinaryString properties that are being written as SharedStrings
+    /// instead, keyed by the instance they belong to.
+    promotions: HashMap<Ref, Vec<Promotion>>,
+
+    /// The number of property values that have been written so far, counting
+    /// one for every instance a PROP chunk has a value for.
+    property_values: usize,
+
+    /// The span that serializing happens in.
+    #[cfg(feature = "tracing")]
+    pub(super) span: tracing::Span,
+}
+
+/// A BinaryString property on an instance that will be written as a
+/// SharedString property, because the same value is used by other instances.
+#[derive(Debug)]
+struct Promotion {
+    /// The name of the BinaryString property, which is left empty.
+    from: &'static str,
+
+    /// The name of the SharedString property that the value is written to.
+    to: &'static str,
+
+    /// The value of the BinaryString property, as a SharedString.
+    value: Variant,
 }
 
 /// An instance class that our serializer knows about. We should have one struct
@@ -92,6 +144,10 @@ struct TypeInfo<'dom> {
     /// processed. This helps us avoid traversing the reflection database
     /// multiple times if there are many copies of the same kind of instance.
     properties_visited: HashSet<(Cow<'static, str>, VariantType)>,
+
+    /// Properties of an unknown type that were preserved from a file we read
+    /// earlier and should be written back out as-is.
+    unknown_properties: Vec<&'dom UnknownProperty>,
 }
 
 /// A property on a specific class that our serializer knows about.
@@ -136,6 +192,99 @@ struct PropInfo {
     default_value: Cow<'static, Variant>,
 }
 
+impl<'dom> TypeInfo<'dom> {
+    /// Returns the properties of this type in the order they should be written
+    /// in.
+    fn ordered_properties(
+        &self,
+        order: PropertyOrder,
+        database: Option<&ReflectionDatabase>,
+    ) -> Vec<(&Cow<'static, str>, &PropInfo)> {
+        // Properties are stored sorted by name, so this is already
+        // alphabetical.
+        let mut properties: Vec<_> = self.properties.iter().collect();
+
+        if order == PropertyOrder::Reflection {
+            let mut ancestry: Vec<_> = match (database, self.class_descriptor) {
+                (Some(database), Some(descriptor)) => {
+                    database.superclass_iter(&descriptor.name).collect()
+                }
+                _ => Vec::new(),
+            };
+
+            ancestry.reverse();
+
+            // This sort is stable, so properties stay alphabetical within each
+            // class. Properties that aren't in the reflection database go last.
+            properties.sort_by_key(|(name, _)| {
+                ancestry
+                    .iter()
+                    .position(|descriptor| descriptor.properties.contains_key(name.as_ref()))
+                    .unwrap_or(ancestry.len())
+            });
+        }
+
+        properties
+    }
+}
+
+impl PropInfo {
+    /// Finds the value of this property on the given instance, falling back to
+    /// the default value if the instance doesn't have it.
+    fn value_for<'a>(&'a self, canonical_name: &str, instance: &'a Instance) -> Cow<'a, Variant> {
+        if let Some(value) = self.instance_value(canonical_name, instance) {
+            return value;
+        }
+
+        // Finally, we can fall back to the default value we computed for this
+        // PropInfo. This is sourced from the reflection database if available,
+        // or falls back to a reasonable default.
+        Cow::Borrowed(self.default_value.borrow())
+    }
+
+    /// Finds the value of this property on the given instance, or `None` if
+    /// the instance doesn't have it.
+    fn instance_value<'a>(
+        &'a self,
+        canonical_name: &str,
+        instance: &'a Instance,
+    ) -> Option<Cow<'a, Variant>> {
+        // We store the Name property in a different field for convenience,
+        // but when serializing to the binary model format we need to handle it
+        // just like other properties.
+        if canonical_name == "Name" {
+            return Some(Cow::Owned(Variant::String(instance.name.clone())));
+        }
+
+        // Most properties will be stored on instances using the property's
+        // canonical name, so we'll try that first.
+        if let Some(property) = instance.properties.get(canonical_name) {
+            return Some(Cow::Borrowed(property));
+        }
+
+        // If there were any known aliases for this property used as part of
+        // this file, we can check those next.
+        self.aliases
+            .iter()
+            .find_map(|alias| instance.properties.get(alias))
+            .map(Cow::Borrowed)
+    }
+}
+
+/// One of the PROP chunks that a serializer writes, named by the class and
+/// property it's for.
+pub(super) enum PropertyChunk {
+    /// A property rbx_binary knows the type of.
+    Known {
+        type_name: String,
+        prop_name: Cow<'static, str>,
+    },
+
+    /// One of the unknown properties of a class, by its position in
+    /// `TypeInfo::unknown_properties`.
+    Unknown { type_name: String, index: usize },
+}
+
 /// Contains all of the `TypeInfo` objects known to the serializer so far. This
 /// struct was broken out to help encapsulate the behavior here and to ease
 /// self-borrowing issues from BinarySerializer getting too large.
@@ -163,12 +312,20 @@ impl<'dom> TypeInfos<'dom> {
 
     /// Finds the type info from the given ClassName if it exists, or creates
     /// one and returns a reference to it if not.
-    fn get_or_create(&mut self, class: &str) -> &mut TypeInfo<'dom> {
+    ///
+    /// Without `reflection`, the reflection database is only used to find out
+    /// whether the class is a service, and never for property defaults.
+    fn get_or_create(
+        &mut self,
+        class: &str,
+        database: Option<&'static ReflectionDatabase<'static>>,
+        reflection: bool,
+    ) -> &mut TypeInfo<'dom> {
         if !self.values.contains_key(class) {
             let type_id = self.next_type_id;
             self.next_type_id += 1;
 
-            let class_descriptor = rbx_reflection_database::get().classes.get(class);
+            let class_descriptor = database.and_then(|database| database.classes.get(class));
 
             let is_service = if let Some(descriptor) = &class_descriptor {
                 descriptor.tags.contains(&ClassTag::Service)
@@ -177,6 +334,8 @@ impl<'dom> TypeInfos<'dom> {
                 false
             };
 
+            let class_descriptor = class_descriptor.filter(|_| reflection);
+
             let mut properties = BTreeMap::new();
 
             // Every instance has a property named Name. Even though
@@ -205,6 +364,7 @@ impl<'dom> TypeInfos<'dom> {
                     properties,
                     class_descriptor,
                     properties_visited: HashSet::new(),
+                    unknown_properties: Vec::new(),
                 },
             );
         }
@@ -216,18 +376,35 @@ impl<'dom> TypeInfos<'dom> {
 }
 
 impl<'dom, W: Write> SerializerState<'dom, W> {
-    pub fn new(dom: &'dom WeakDom, output: W) -> Self {
+    pub fn new(serializer: &'dom Serializer, dom: &'dom WeakDom, output: W) -> Self {
         SerializerState {
+            serializer,
             dom,
             output,
             relevant_instances: Vec::new(),
             id_to_referent: HashMap::new(),
+            report: EncodeReport::default(),
             type_infos: TypeInfos::new(),
             shared_strings: Vec::new(),
             shared_string_ids: HashMap::new(),
+            instance_shared_string_ids: HashMap::new(),
+            promotions: HashMap::new(),
+            property_values: 0,
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                "rbx_binary::serialize",
+                instances = tracing::field::Empty,
+                property_values = tracing::field::Empty,
+            ),
         }
     }
 
+    /// The output that has been written so far and not taken out yet.
+    #[cfg(feature = "tokio")]
+    pub fn output_mut(&mut self) -> &mut W {
+        &mut self.output
+    }
+
     /// Mark the given instance IDs and all of their descendants as intended for
     /// serialization with this serializer.
     #[profiling::function]
@@ -242,16 +419,42 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
                 .ok_or(InnerError::InvalidInstanceId { referent })?;
 
             self.relevant_instances.push(referent);
-            self.collect_type_info(instance)?;
-
             to_visit.extend(instance.children());
         }
 
+        self.resolve_external_references();
+        self.find_promotions();
+
+        let dom = self.dom;
+        for index in 0..self.relevant_instances.len() {
+            let instance = dom.get_by_ref(self.relevant_instances[index]).unwrap();
+            self.collect_type_info(instance)?;
+        }
+
+        self.collect_default_shared_strings();
+
         // Sort shared_strings by their hash, to ensure they are deterministically added
         // into the SSTR chunk, then assign them corresponding ids
-        self.shared_strings.sort_by_key(SharedString::hash);
-        for (id, shared_string) in self.shared_strings.iter().cloned().enumerate() {
-            self.shared_string_ids.insert(shared_string, id as u32);
+        self.shared_strings
+            .sort_by_key(|(shared_string, _)| shared_string.hash());
+        for (id, (shared_string, owner)) in self.shared_strings.iter().cloned().enumerate() {
+            match owner {
+                Some(owner) => self
+                    .instance_shared_string_ids
+                    .insert((owner, shared_string), id as u32),
+                None => self.shared_string_ids.insert(shared_string, id as u32),
+            };
+        }
+
+        self.collect_unknown_properties();
+
+        // Type IDs are normally handed out in the order types are discovered,
+        // which depends on the order of children in the tree. Deterministic
+        // output assigns them in the same, alphabetical, order as INST chunks.
+        if self.serializer.deterministic {
+            for (type_id, type_info) in self.type_infos.values.values_mut().enumerate() {
+                type_info.type_id = type_id as u32;
+            }
         }
 
         log::debug!("Type info discovered: {:#?}", self.type_infos);
@@ -259,6 +462,259 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
         Ok(())
     }
 
+    /// Track the default values of SharedString properties that some
+    /// instances are missing, since those instances will be written with the
+    /// default value instead.
+    fn collect_default_shared_strings(&mut self) {
+        for type_info in self.type_infos.values.values() {
+            for (prop_name, prop_info) in &type_info.properties {
+                let default = match prop_info.default_value.as_ref() {
+                    Variant::SharedString(default) => default,
+                    _ => continue,
+                };
+
+                if self.shared_string_ids.contains_key(default) {
+                    continue;
+                }
+
+                let uses_default = type_info.instances.iter().any(|instance| {
+                    let promoted =
+                        self.promotions
+                            .get(&instance.referent())
+                            .is_some_and(|promotions| {
+                                promotions.iter().any(|promotion| promotion.to == prop_name)
+                            });
+
+                    !promoted
+                        && !instance.properties.contains_key(prop_name.as_ref())
+                        && !prop_info
+                            .aliases
+                            .iter()
+                            .any(|alias| instance.properties.contains_key(alias))
+                });
+
+                if uses_default {
+                    self.shared_string_ids.insert(default.clone(), 0);
+                    self.shared_strings.push((default.clone(), None));
+                }
+            }
+        }
+    }
+
+    /// Find the Ref properties that point outside of the relevant instances,
+    /// and either include the instances they point to or record that they'll
+    /// be written as empty, depending on the serializer's configuration.
+    fn resolve_external_references(&mut self) {
+        let include = self.serializer.external_references == ExternalReferences::Include;
+        let mut relevant: HashSet<Ref> = self.relevant_instances.iter().copied().collect();
+
+        // Instances are appended to relevant_instances as they're included, so
+        // their properties are checked in turn.
+        let mut index = 0;
+        while index < self.relevant_instances.len() {
+            let referent = self.relevant_instances[index];
+            let instance = self.dom.get_by_ref(referent).unwrap();
+            index += 1;
+
+            // Properties are visited in a fixed order so that included
+            // instances are always added in the same order.
+            let mut properties: Vec<_> = instance
+                .properties
+                .iter()
+                .filter_map(|(name, value)| match value {
+                    Variant::Ref(target) if target.is_some() && !relevant.contains(target) => {
+                        Some((name, *target))
+                    }
+                    _ => None,
+                })
+                .collect();
+            properties.sort_unstable_by_key(|(name, _)| name.as_str());
+
+            for (name, target) in properties {
+                if relevant.contains(&target) {
+                    continue;
+                }
+
+                if include && self.dom.get_by_ref(target).is_some() {
+                    log::debug!("Including {:?}, referred to by {}", target, name);
+
+                    let mut to_visit = VecDeque::new();
+                    to_visit.push_back(target);
+
+                    while let Some(referent) = to_visit.pop_front() {
+                        if relevant.insert(referent) {
+                            self.relevant_instances.push(referent);
+                        }
+
+                        let instance = self.dom.get_by_ref(referent).unwrap();
+                        to_visit.extend(instance.children());
+                    }
+
+                    self.report.included_instances.push(target);
+                } else {
+                    self.report.nulled_references.push(NulledReference::new(
+                        referent,
+                        name.clone(),
+                        target,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Find the BinaryString properties that should be written as
+    /// SharedStrings, if the serializer is configured to promote them.
+    ///
+    /// A value is promoted if it's at least as large as the promotion
+    /// threshold and the same value appears on more than one instance.
+    fn find_promotions(&mut self) {
+        let threshold = match self.serializer.shared_string_promotion_threshold {
+            Some(threshold) if self.serializer.reflection => threshold,
+            _ => return,
+        };
+
+        let database = match self.serializer.database {
+            Some(database) => database,
+            None => return,
+        };
+        let mut candidates = Vec::new();
+        let mut counts: HashMap<&'dom [u8], usize> = HashMap::new();
+
+        for &referent in &self.relevant_instances {
+            let instance = self.dom.get_by_ref(referent).unwrap();
+
+            for &(from, to) in PROMOTABLE_PROPERTIES {
+                let data: &[u8] = match instance.properties.get(from) {
+                    Some(Variant::BinaryString(value)) => value.as_ref(),
+                    _ => continue,
+                };
+
+                if data.is_empty() || data.len() < threshold || instance.properties.contains_key(to)
+                {
+                    continue;
+                }
+
+                let accepts_shared_string = database
+                    .find_property_descriptors(&instance.class, to)
+                    .and_then(|descriptors| descriptors.serialized)
+                    .is_some_and(|descriptor| {
+                        matches!(
+                            descriptor.data_type,
+                            DataType::Value(VariantType::SharedString)
+                        )
+                    });
+
+                if accepts_shared_string {
+                    *counts.entry(data).or_default() += 1;
+                    candidates.push((referent, from, to, data));
+                }
+            }
+        }
+
+        let mut shared_strings: HashMap<&[u8], SharedString> = HashMap::new();
+
+        for (referent, from, to, data) in candidates {
+            if counts[data] < 2 {
+                continue;
+            }
+
+            let value = shared_strings
+                .entry(data)
+                .or_insert_with(|| SharedString::new(data.to_vec()))
+                .clone();
+
+            log::trace!(
+                "Writing {} of {} as a SharedString",
+                from,
+                self.full_name_for(referent)
+            );
+
+            self.promotions
+                .entry(referent)
+                .or_default()
+                .push(Promotion {
+                    from,
+                    to,
+                    value: Variant::SharedString(value),
+                });
+        }
+    }
+
+    /// Attach the unknown properties given to the serializer to the types they
+    /// belong to.
+    ///
+    /// An unknown property's data can only be written out if the same
+    /// instances it was read from are being serialized, and only in the same
+    /// order as before, since the individual values can't be told apart. The
+    /// instances of the type are reordered to match if needed. Properties that
+    /// don't line up with the instances being serialized are dropped.
+    fn collect_unknown_properties(&mut self) {
+        let serializer = self.serializer;
+
+        for unknown in &serializer.unknown_properties {
+            let type_info = match self.type_infos.values.get_mut(&unknown.class_name) {
+                Some(type_info) => type_info,
+                None => continue,
+            };
+
+            let drop_property = |reason: &str| {
+                log::warn!(
+                    "Dropping unknown property {}.{} because {}",
+                    unknown.class_name,
+                    unknown.property_name,
+                    reason
+                );
+            };
+
+            let already_known = type_info
+                .properties
+                .values()
+                .any(|prop_info| prop_info.serialized_name == unknown.property_name.as_str());
+
+            if already_known {
+                drop_property("the instances being serialized have a known value for it");
+                continue;
+            }
+
+            if type_info.unknown_properties.is_empty() {
+                let positions: HashMap<Ref, usize> = unknown
+                    .referents
+                    .iter()
+                    .enumerate()
+                    .map(|(index, &referent)| (referent, index))
+                    .collect();
+
+                let same_instances = positions.len() == type_info.instances.len()
+                    && type_info
+                        .instances
+                        .iter()
+                        .all(|instance| positions.contains_key(&instance.referent()));
+
+                if !same_instances {
+                    drop_property("the set of instances it belongs to has changed");
+                    continue;
+                }
+
+                type_info
+                    .instances
+                    .sort_by_key(|instance| positions[&instance.referent()]);
+            } else {
+                let same_order = type_info
+                    .instances
+                    .iter()
+                    .map(|instance| instance.referent())
+                    .eq(unknown.referents.iter().copied());
+
+                if !same_order {
+                    drop_property("it conflicts with another unknown property's instance order");
+                    continue;
+                }
+            }
+
+            type_info.unknown_properties.push(unknown);
+        }
+    }
+
     /// Collect information about all the different types of instance and their
     /// properties.
     // Using the entry API here, as Clippy suggests, would require us to
@@ -266,17 +722,49 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
     #[allow(clippy::map_entry)]
     #[profiling::function]
     pub fn collect_type_info(&mut self, instance: &'dom Instance) -> Result<(), InnerError> {
-        let type_info = self.type_infos.get_or_create(&instance.class);
+        let deterministic = self.serializer.deterministic;
+        let reflection = self.serializer.reflection;
+        let database = self.serializer.database;
+        let type_info = self
+            .type_infos
+            .get_or_create(&instance.class, database, reflection);
         type_info.instances.push(instance);
 
-        for (prop_name, prop_value) in &instance.properties {
+        // Promoted properties are collected under the name of the SharedString
+        // property they're written to instead of their own.
+        let promotions = self
+            .promotions
+            .get(&instance.referent())
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        let properties = instance
+            .properties
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+            .filter(|(name, _)| !promotions.iter().any(|promotion| promotion.from == *name))
+            .chain(
+                promotions
+                    .iter()
+                    .map(|promotion| (promotion.to, &promotion.value)),
+            );
+
+        for (prop_name, prop_value) in properties {
             // Discover and track any shared strings we come across.
             if let Variant::SharedString(shared_string) = prop_value {
-                if !self.shared_string_ids.contains_key(shared_string) {
+                if shared_string.data().len() < self.serializer.shared_string_dedup_threshold {
+                    let key = (instance.referent(), shared_string.clone());
+
+                    if !self.instance_shared_string_ids.contains_key(&key) {
+                        self.instance_shared_string_ids.insert(key, 0);
+                        self.shared_strings
+                            .push((shared_string.clone(), Some(instance.referent())));
+                    }
+                } else if !self.shared_string_ids.contains_key(shared_string) {
                     // We insert it with a dummy id of 0 so that we can check for contains_key.
                     // The actual id is set in `add_instances`
                     self.shared_string_ids.insert(shared_string.clone(), 0);
-                    self.shared_strings.push(shared_string.clone())
+                    self.shared_strings.push((shared_string.clone(), None))
                 }
             }
 
@@ -292,23 +780,33 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
             // it.
             type_info
                 .properties_visited
-                .insert((Cow::Owned(prop_name.clone()), prop_value.ty()));
+                .insert((Cow::Owned(prop_name.to_owned()), prop_value.ty()));
 
             let canonical_name;
             let serialized_name;
             let serialized_ty;
 
-            let database = rbx_reflection_database::get();
-            match find_property_descriptors(database, &instance.class, prop_name) {
-                Some(descriptors) => {
+            let descriptors = database.filter(|_| reflection).and_then(|database| {
+                let descriptors = database.find_property_descriptors(&instance.class, prop_name)?;
+                Some((
+                    descriptors.canonical,
+                    database.serialization(&instance.class, prop_name)?,
+                ))
+            });
+
+            match descriptors {
+                Some((canonical, serialization)) => {
                     // For any properties that do not serialize, we can skip
-                    // adding them to the set of type_infos.
-                    let serialized = match descriptors.serialized {
-                        Some(descriptor) => descriptor,
-                        None => continue,
+                    // adding them to the set of type_infos. Properties that
+                    // are only loaded are still written if the DOM has them,
+                    // since they haven't been migrated.
+                    let serialized = match serialization {
+                        SerializationBehavior::Serializes { serialized }
+                        | SerializationBehavior::LoadOnly { serialized, .. } => serialized,
+                        _ => continue,
                     };
 
-                    canonical_name = descriptors.canonical.name.clone();
+                    canonical_name = canonical.name.clone();
                     serialized_name = serialized.name.clone();
 
                     serialized_ty = match &serialized.data_type {
@@ -320,16 +818,17 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
                             // of property, whatever it is.
                             return Err(InnerError::UnsupportedPropType {
                                 type_name: instance.class.clone(),
-                                prop_name: prop_name.clone(),
+                                prop_name: prop_name.to_owned(),
                                 prop_type: format!("{:?}", unknown_ty),
+                                referent: instance.referent(),
                             });
                         }
                     };
                 }
 
                 None => {
-                    canonical_name = Cow::Owned(prop_name.clone());
-                    serialized_name = Cow::Owned(prop_name.clone());
+                    canonical_name = Cow::Owned(prop_name.to_owned());
+                    serialized_name = Cow::Owned(prop_name.to_owned());
                     serialized_ty = prop_value.ty();
                 }
             }
@@ -337,7 +836,7 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
             // In order to prevent cloning canonical_name in a rare branch,
             // we conditionally clone here if we'll need canonical_name after
             // it's inserted into type_info.properties.
-            let canonical_name_if_different = if prop_name != &canonical_name {
+            let canonical_name_if_different = if prop_name != canonical_name {
                 Some(canonical_name.clone())
             } else {
                 None
@@ -352,7 +851,9 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
                             .get(&canonical_name)
                             .map(Cow::Borrowed)
                     })
-                    .or_else(|| Self::fallback_default_value(serialized_ty).map(Cow::Owned))
+                    .or_else(|| {
+                        Self::fallback_default_value(serialized_ty, deterministic).map(Cow::Owned)
+                    })
                     .ok_or_else(|| {
                         // Since we don't know how to generate the default value
                         // for this property, we consider it unsupported.
@@ -360,6 +861,7 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
                             type_name: instance.class.clone(),
                             prop_name: canonical_name.to_string(),
                             prop_type: format!("{:?}", serialized_ty),
+                            referent: instance.referent(),
                         }
                     })?;
 
@@ -371,6 +873,7 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
                         type_name: instance.class.clone(),
                         prop_name: serialized_name.to_string(),
                         prop_type: format!("{:?}", serialized_ty),
+                        referent: instance.referent(),
                     }
                 })?;
 
@@ -392,7 +895,7 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
                 let prop_info = type_info.properties.get_mut(&canonical_name).unwrap();
 
                 if !prop_info.aliases.contains(prop_name) {
-                    prop_info.aliases.insert(prop_name.clone());
+                    prop_info.aliases.insert(prop_name.to_owned());
                 }
             }
         }
@@ -400,6 +903,41 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
         Ok(())
     }
 
+    /// Finds the value of the given property on an instance, taking into
+    /// account any properties that are being promoted to SharedStrings.
+    fn value_for<'a>(
+        &'a self,
+        prop_info: &'a PropInfo,
+        canonical_name: &str,
+        instance: &'a Instance,
+    ) -> Cow<'a, Variant> {
+        if let Some(promotions) = self.promotions.get(&instance.referent()) {
+            for promotion in promotions {
+                if promotion.to == canonical_name {
+                    return Cow::Borrowed(&promotion.value);
+                }
+
+                if promotion.from == canonical_name {
+                    return Cow::Owned(Variant::BinaryString(BinaryString::new()));
+                }
+            }
+        }
+
+        // UniqueIds have to be unique, so deterministic output can't give
+        // every instance that's missing one the same default. Each gets one
+        // derived from its referent in the file instead.
+        if self.serializer.deterministic
+            && prop_info.prop_type == Type::UniqueId
+            && prop_info.instance_value(canonical_name, instance).is_none()
+        {
+            if let Some(&referent) = self.id_to_referent.get(&instance.referent()) {
+                return Cow::Owned(Variant::UniqueId(UniqueId::new(referent as u32, 0, 0)));
+            }
+        }
+
+        prop_info.value_for(canonical_name, instance)
+    }
+
     /// Populate the map from rbx-dom's instance ID space to the IDs that we'll
     /// be serializing to the model.
     #[profiling::function]
@@ -431,9 +969,47 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
     }
 
     /// Write out any metadata about this file, stored in a chunk named META.
+    #[profiling::function]
     pub fn serialize_metadata(&mut self) -> Result<(), InnerError> {
-        log::trace!("Writing metadata (currently no-op)");
-        // TODO: There is no concept of metadata in a dom yet.
+        log::trace!("Writing metadata");
+
+        let metadata = &self.serializer.metadata;
+        if metadata.is_empty() {
+            return Ok(());
+        }
+
+        // Entries are sorted to keep the output stable between runs.
+        let mut entries: Vec<_> = metadata.iter().collect();
+        entries.sort_unstable();
+
+        let mut chunk = self.new_chunk(b"META");
+        chunk.write_le_u32(entries.len() as u32)?;
+
+        for (key, value) in entries {
+            chunk.write_string(key)?;
+            chunk.write_string(value)?;
+        }
+
+        chunk.dump(&mut self.output)?;
+
+        Ok(())
+    }
+
+    /// Write out the signatures given to the serializer, if any, stored in a
+    /// chunk named SIGN.
+    #[profiling::function]
+    pub fn serialize_signatures(&mut self) -> Result<(), InnerError> {
+        let signatures = match &self.serializer.signatures {
+            Some(signatures) => signatures,
+            None => return Ok(()),
+        };
+
+        log::trace!("Writing signature chunk");
+
+        let mut chunk = self.new_chunk(b"SIGN");
+        chunk.write_all(signatures)?;
+        chunk.dump(&mut self.output)?;
+
         Ok(())
     }
 
@@ -447,12 +1023,12 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
             return Ok(());
         }
 
-        let mut chunk = ChunkBuilder::new(b"SSTR", ChunkCompression::Compressed);
+        let mut chunk = self.new_chunk(b"SSTR");
 
         chunk.write_le_u32(0)?; // SSTR version number
         chunk.write_le_u32(self.shared_strings.len() as u32)?;
 
-        for shared_string in &self.shared_strings {
+        for (shared_string, _) in &self.shared_strings {
             // Better to write nothing than write half a hash
             chunk.write_all(&[0; 16])?;
             chunk.write_binary_string(shared_string.data())?;
@@ -469,6 +1045,9 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
     pub fn serialize_instances(&mut self) -> Result<(), InnerError> {
         log::trace!("Writing instance chunks");
 
+        let batch_size = chunk::batch_size();
+        let mut pending = Vec::with_capacity(batch_size);
+
         for (type_name, type_info) in &self.type_infos.values {
             log::trace!(
                 "Writing chunk for {} ({} instances)",
@@ -476,7 +1055,7 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
                 type_info.instances.len()
             );
 
-            let mut chunk = ChunkBuilder::new(b"INST", ChunkCompression::Compressed);
+            let mut chunk = self.new_chunk(b"INST");
 
             chunk.write_le_u32(type_info.type_id)?;
             chunk.write_string(type_name)?;
@@ -511,9 +1090,14 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
                 }
             }
 
-            chunk.dump(&mut self.output)?;
+            pending.push(chunk);
+            if pending.len() >= batch_size {
+                ChunkBuilder::dump_all(mem::take(&mut pending), &mut self.output)?;
+            }
         }
 
+        ChunkBuilder::dump_all(pending, &mut self.output)?;
+
         Ok(())
     }
 
@@ -524,619 +1108,727 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
     pub fn serialize_properties(&mut self) -> Result<(), InnerError> {
         log::trace!("Writing properties");
 
+        let chunks = self.property_chunks();
+        self.serialize_property_chunks(&chunks)
+    }
+
+    /// Lists the PROP chunks that might be written, in the order they're
+    /// written in.
+    pub fn property_chunks(&self) -> Vec<PropertyChunk> {
+        let mut chunks = Vec::new();
+
         for (type_name, type_info) in &self.type_infos.values {
-            for (prop_name, prop_info) in &type_info.properties {
-                profiling::scope!("serialize property", prop_name.borrow());
-                log::trace!(
-                    "Writing property {}.{} (type {:?})",
+            for (prop_name, _) in type_info
+                .ordered_properties(self.serializer.property_order, self.serializer.database)
+            {
+                chunks.push(PropertyChunk::Known {
+                    type_name: type_name.clone(),
+                    prop_name: prop_name.clone(),
+                });
+            }
+
+            for index in 0..type_info.unknown_properties.len() {
+                chunks.push(PropertyChunk::Unknown {
+                    type_name: type_name.clone(),
+                    index,
+                });
+            }
+        }
+
+        chunks
+    }
+
+    /// Writes out the given PROP chunks, which come from
+    /// [`property_chunks`](Self::property_chunks).
+    pub fn serialize_property_chunks(
+        &mut self,
+        chunks: &[PropertyChunk],
+    ) -> Result<(), InnerError> {
+        let batch_size = chunk::batch_size();
+        let mut pending = Vec::with_capacity(batch_size);
+
+        for property_chunk in chunks {
+            let chunk = match property_chunk {
+                PropertyChunk::Known {
                     type_name,
                     prop_name,
-                    prop_info.prop_type
-                );
+                } => match self.known_property_chunk(type_name, prop_name)? {
+                    Some(chunk) => chunk,
+                    None => continue,
+                },
+                PropertyChunk::Unknown { type_name, index } => {
+                    self.unknown_property_chunk(type_name, *index)?
+                }
+            };
 
-                let mut chunk = ChunkBuilder::new(b"PROP", ChunkCompression::Compressed);
+            pending.push(chunk);
+            if pending.len() >= batch_size {
+                ChunkBuilder::dump_all(mem::take(&mut pending), &mut self.output)?;
+            }
+        }
 
-                chunk.write_le_u32(type_info.type_id)?;
-                chunk.write_string(&prop_info.serialized_name)?;
-                chunk.write_u8(prop_info.prop_type as u8)?;
+        ChunkBuilder::dump_all(pending, &mut self.output)?;
 
-                let values = type_info
-                    .instances
-                    .iter()
-                    .map(|instance| {
-                        // We store the Name property in a different field for
-                        // convenience, but when serializing to the binary model
-                        // format we need to handle it just like other properties.
-                        if prop_name == "Name" {
-                            return Cow::Owned(Variant::String(instance.name.clone()));
-                        }
+        Ok(())
+    }
 
-                        // Most properties will be stored on instances using the
-                        // property's canonical name, so we'll try that first.
-                        if let Some(property) = instance.properties.get(prop_name.as_ref()) {
-                            return Cow::Borrowed(property);
-                        }
+    /// Encodes the PROP chunk for a property rbx_binary knows the type of,
+    /// unless it's being skipped because it has its default value on every
+    /// instance.
+    fn known_property_chunk(
+        &mut self,
+        type_name: &str,
+        prop_name: &str,
+    ) -> Result<Option<ChunkBuilder>, InnerError> {
+        let (type_name, type_info) = self.type_infos.values.get_key_value(type_name).unwrap();
+        let (prop_name, prop_info) = type_info.properties.get_key_value(prop_name).unwrap();
+
+        // Name is always written, since every instance has one.
+        if !self.serializer.include_default_properties
+            && prop_name != "Name"
+            && type_info.instances.iter().all(|instance| {
+                self.value_for(prop_info, prop_name, instance) == prop_info.default_value
+            })
+        {
+            log::trace!(
+                "Skipping property {}.{} because it has its default value everywhere",
+                type_name,
+                prop_name
+            );
+            return Ok(None);
+        }
 
-                        // If there were any known aliases for this property
-                        // used as part of this file, we can check those next.
-                        for alias in &prop_info.aliases {
-                            if let Some(property) = instance.properties.get(alias) {
-                                return Cow::Borrowed(property);
-                            }
-                        }
+        profiling::scope!("serialize property", prop_name.borrow());
+        log::trace!(
+            "Writing property {}.{} (type {:?})",
+            type_name,
+            prop_name,
+            prop_info.prop_type
+        );
 
-                        // Finally, we can fall back to the default value we
-                        // computed for this PropInfo. This is sourced from the
-                        // reflection database if available, or falls back to a
-                        // reasonable default.
-                        Cow::Borrowed(prop_info.default_value.borrow())
-                    })
-                    .enumerate();
-
-                // Helper to generate a type mismatch error with context from
-                // this chunk.
-                let type_mismatch =
-                    |i: usize, bad_value: &Variant, valid_type_names: &'static str| {
-                        Err(InnerError::PropTypeMismatch {
-                            type_name: type_name.clone(),
-                            prop_name: prop_name.to_string(),
-                            valid_type_names,
-                            actual_type_name: format!("{:?}", bad_value.ty()),
-                            instance_full_name: self
-                                .full_name_for(type_info.instances[i].referent()),
-                        })
-                    };
+        let mut chunk = self.new_chunk(b"PROP");
 
-                let invalid_value = |i: usize, bad_value: &Variant| InnerError::InvalidPropValue {
-                    instance_full_name: self.full_name_for(type_info.instances[i].referent()),
-                    type_name: type_name.clone(),
-                    prop_name: prop_name.to_string(),
-                    prop_type: format!("{:?}", bad_value.ty()),
-                };
+        chunk.write_le_u32(type_info.type_id)?;
+        chunk.write_string(&prop_info.serialized_name)?;
+        chunk.write_u8(prop_info.prop_type as u8)?;
 
-                match prop_info.prop_type {
-                    Type::String => {
-                        for (i, rbx_value) in values {
-                            match rbx_value.as_ref() {
-                                Variant::String(value) => {
-                                    chunk.write_string(value)?;
-                                }
-                                Variant::Content(value) => {
-                                    chunk.write_string(value.as_ref())?;
-                                }
-                                Variant::BinaryString(value) => {
-                                    chunk.write_binary_string(value.as_ref())?;
-                                }
-                                Variant::Tags(value) => {
-                                    let buf = value.encode();
-                                    chunk.write_binary_string(&buf)?;
-                                }
-                                Variant::Attributes(value) => {
-                                    let mut buf = Vec::new();
-
-                                    value
-                                        .to_writer(&mut buf)
-                                        .map_err(|_| invalid_value(i, &rbx_value))?;
-
-                                    chunk.write_binary_string(&buf)?;
-                                }
-                                _ => {
-                                    return type_mismatch(
-                                        i,
-                                        &rbx_value,
-                                        "String, Content, Tags, Attributes, or BinaryString",
-                                    );
-                                }
-                            }
+        let normalize = self.serializer.normalize_floats;
+        let values = type_info
+            .instances
+            .iter()
+            .map(|instance| self.value_for(prop_info, prop_name, instance))
+            .map(|value| {
+                if normalize {
+                    normalize_floats(value)
+                } else {
+                    value
+                }
+            })
+            .enumerate();
+
+        // Helper to generate a type mismatch error with context from
+        // this chunk.
+        let type_mismatch = |i: usize, bad_value: &Variant, valid_type_names: &'static str| {
+            Err(InnerError::PropTypeMismatch {
+                type_name: type_name.clone(),
+                prop_name: prop_name.to_string(),
+                valid_type_names,
+                actual_type: bad_value.ty(),
+                instance_full_name: self.full_name_for(type_info.instances[i].referent()),
+                referent: type_info.instances[i].referent(),
+            })
+        };
+
+        let invalid_value = |i: usize, bad_value: &Variant| InnerError::InvalidPropValue {
+            instance_full_name: self.full_name_for(type_info.instances[i].referent()),
+            type_name: type_name.clone(),
+            prop_name: prop_name.to_string(),
+            prop_type: bad_value.ty(),
+            referent: type_info.instances[i].referent(),
+        };
+
+        match prop_info.prop_type {
+            Type::String => {
+                for (i, rbx_value) in values {
+                    match rbx_value.as_ref() {
+                        Variant::String(value) => {
+                            chunk.write_string(value)?;
                         }
-                    }
-                    Type::Bool => {
-                        for (i, rbx_value) in values {
-                            if let Variant::Bool(value) = rbx_value.as_ref() {
-                                chunk.write_bool(*value)?;
-                            } else {
-                                return type_mismatch(i, &rbx_value, "Bool");
-                            }
+                        Variant::Content(value) => {
+                            chunk.write_string(value.as_ref())?;
                         }
-                    }
-                    Type::Int32 => {
-                        let mut buf = Vec::with_capacity(values.len());
-
-                        for (i, rbx_value) in values {
-                            if let Variant::Int32(value) = rbx_value.as_ref() {
-                                buf.push(*value);
-                            } else {
-                                return type_mismatch(i, &rbx_value, "Int32");
-                            }
+                        Variant::BinaryString(value) => {
+                            chunk.write_binary_string(value.as_ref())?;
                         }
+                        Variant::Tags(value) => {
+                            let buf = value.encode();
+                            chunk.write_binary_string(&buf)?;
+                        }
+                        Variant::Attributes(value) => {
+                            let mut buf = Vec::new();
 
-                        chunk.write_interleaved_i32_array(buf.into_iter())?;
-                    }
-                    Type::Float32 => {
-                        let mut buf = Vec::with_capacity(values.len());
-
-                        for (i, rbx_value) in values {
-                            if let Variant::Float32(value) = rbx_value.as_ref() {
-                                buf.push(*value);
-                            } else {
-                                return type_mismatch(i, &rbx_value, "Float32");
-                            }
+                            value
+                                .to_writer(&mut buf)
+                                .map_err(|_| invalid_value(i, &rbx_value))?;
+
+                            chunk.write_binary_string(&buf)?;
                         }
+                        _ => {
+                            return type_mismatch(
+                                i,
+                                &rbx_value,
+                                "String, Content, Tags, Attributes, or BinaryString",
+                            );
+                        }
+                    }
+                }
+            }
+            Type::Bool => {
+                for (i, rbx_value) in values {
+                    if let Variant::Bool(value) = rbx_value.as_ref() {
+                        chunk.write_bool(*value)?;
+                    } else {
+                        return type_mismatch(i, &rbx_value, "Bool");
+                    }
+                }
+            }
+            Type::Int32 => {
+                let mut buf = Vec::with_capacity(values.len());
+
+                for (i, rbx_value) in values {
+                    if let Variant::Int32(value) = rbx_value.as_ref() {
+                        buf.push(*value);
+                    } else {
+                        return type_mismatch(i, &rbx_value, "Int32");
+                    }
+                }
 
-                        chunk.write_interleaved_f32_array(buf.into_iter())?;
+                chunk.write_interleaved_i32_array(buf.into_iter())?;
+            }
+            Type::Float32 => {
+                let mut buf = Vec::with_capacity(values.len());
+
+                for (i, rbx_value) in values {
+                    if let Variant::Float32(value) = rbx_value.as_ref() {
+                        buf.push(*value);
+                    } else {
+                        return type_mismatch(i, &rbx_value, "Float32");
                     }
-                    Type::Float64 => {
-                        for (i, rbx_value) in values {
-                            match rbx_value.as_ref() {
-                                Variant::Float64(value) => {
-                                    chunk.write_le_f64(*value)?;
-                                }
-                                Variant::Float32(value) => {
-                                    chunk.write_le_f64(*value as f64)?;
-                                }
-                                _ => return type_mismatch(i, &rbx_value, "Float64"),
-                            }
+                }
+
+                chunk.write_interleaved_f32_array(buf.into_iter())?;
+            }
+            Type::Float64 => {
+                for (i, rbx_value) in values {
+                    match rbx_value.as_ref() {
+                        Variant::Float64(value) => {
+                            chunk.write_le_f64(*value)?;
                         }
-                    }
-                    Type::UDim => {
-                        let mut scale = Vec::with_capacity(values.len());
-                        let mut offset = Vec::with_capacity(values.len());
-
-                        for (i, rbx_value) in values {
-                            if let Variant::UDim(value) = rbx_value.as_ref() {
-                                scale.push(value.scale);
-                                offset.push(value.offset);
-                            } else {
-                                return type_mismatch(i, &rbx_value, "UDim");
-                            }
+                        Variant::Float32(value) => {
+                            chunk.write_le_f64(*value as f64)?;
                         }
+                        _ => return type_mismatch(i, &rbx_value, "Float64"),
+                    }
+                }
+            }
+            Type::UDim => {
+                let mut scale = Vec::with_capacity(values.len());
+                let mut offset = Vec::with_capacity(values.len());
+
+                for (i, rbx_value) in values {
+                    if let Variant::UDim(value) = rbx_value.as_ref() {
+                        scale.push(value.scale);
+                        offset.push(value.offset);
+                    } else {
+                        return type_mismatch(i, &rbx_value, "UDim");
+                    }
+                }
 
-                        chunk.write_interleaved_f32_array(scale.into_iter())?;
-                        chunk.write_interleaved_i32_array(offset.into_iter())?;
+                chunk.write_interleaved_f32_array(scale.into_iter())?;
+                chunk.write_interleaved_i32_array(offset.into_iter())?;
+            }
+            Type::UDim2 => {
+                let mut scale_x = Vec::with_capacity(values.len());
+                let mut scale_y = Vec::with_capacity(values.len());
+                let mut offset_x = Vec::with_capacity(values.len());
+                let mut offset_y = Vec::with_capacity(values.len());
+
+                for (i, rbx_value) in values {
+                    if let Variant::UDim2(value) = rbx_value.as_ref() {
+                        scale_x.push(value.x.scale);
+                        scale_y.push(value.y.scale);
+                        offset_x.push(value.x.offset);
+                        offset_y.push(value.y.offset);
+                    } else {
+                        return type_mismatch(i, &rbx_value, "UDim2");
                     }
-                    Type::UDim2 => {
-                        let mut scale_x = Vec::with_capacity(values.len());
-                        let mut scale_y = Vec::with_capacity(values.len());
-                        let mut offset_x = Vec::with_capacity(values.len());
-                        let mut offset_y = Vec::with_capacity(values.len());
-
-                        for (i, rbx_value) in values {
-                            if let Variant::UDim2(value) = rbx_value.as_ref() {
-                                scale_x.push(value.x.scale);
-                                scale_y.push(value.y.scale);
-                                offset_x.push(value.x.offset);
-                                offset_y.push(value.y.offset);
-                            } else {
-                                return type_mismatch(i, &rbx_value, "UDim2");
-                            }
-                        }
+                }
 
-                        chunk.write_interleaved_f32_array(scale_x.into_iter())?;
-                        chunk.write_interleaved_f32_array(scale_y.into_iter())?;
-                        chunk.write_interleaved_i32_array(offset_x.into_iter())?;
-                        chunk.write_interleaved_i32_array(offset_y.into_iter())?;
+                chunk.write_interleaved_f32_array(scale_x.into_iter())?;
+                chunk.write_interleaved_f32_array(scale_y.into_iter())?;
+                chunk.write_interleaved_i32_array(offset_x.into_iter())?;
+                chunk.write_interleaved_i32_array(offset_y.into_iter())?;
+            }
+            Type::Font => {
+                for (i, rbx_value) in values {
+                    if let Variant::Font(value) = rbx_value.as_ref() {
+                        chunk.write_string(&value.family)?;
+                        chunk.write_le_u16(value.weight.as_u16())?;
+                        chunk.write_u8(value.style.as_u8())?;
+                        chunk.write_string(&value.cached_face_id.clone().unwrap_or_default())?;
+                    } else {
+                        return type_mismatch(i, &rbx_value, "Font");
                     }
-                    Type::Font => {
-                        for (i, rbx_value) in values {
-                            if let Variant::Font(value) = rbx_value.as_ref() {
-                                chunk.write_string(&value.family)?;
-                                chunk.write_le_u16(value.weight.as_u16())?;
-                                chunk.write_u8(value.style.as_u8())?;
-                                chunk.write_string(
-                                    &value.cached_face_id.clone().unwrap_or_default(),
-                                )?;
-                            } else {
-                                return type_mismatch(i, &rbx_value, "Font");
-                            }
-                        }
+                }
+            }
+            Type::Ray => {
+                for (i, rbx_value) in values {
+                    if let Variant::Ray(value) = rbx_value.as_ref() {
+                        chunk.write_le_f32(value.origin.x)?;
+                        chunk.write_le_f32(value.origin.y)?;
+                        chunk.write_le_f32(value.origin.z)?;
+                        chunk.write_le_f32(value.direction.x)?;
+                        chunk.write_le_f32(value.direction.y)?;
+                        chunk.write_le_f32(value.direction.x)?;
+                    } else {
+                        return type_mismatch(i, &rbx_value, "Ray");
                     }
-                    Type::Ray => {
-                        for (i, rbx_value) in values {
-                            if let Variant::Ray(value) = rbx_value.as_ref() {
-                                chunk.write_le_f32(value.origin.x)?;
-                                chunk.write_le_f32(value.origin.y)?;
-                                chunk.write_le_f32(value.origin.z)?;
-                                chunk.write_le_f32(value.direction.x)?;
-                                chunk.write_le_f32(value.direction.y)?;
-                                chunk.write_le_f32(value.direction.x)?;
-                            } else {
-                                return type_mismatch(i, &rbx_value, "Ray");
-                            }
-                        }
+                }
+            }
+            Type::Faces => {
+                for (i, rbx_value) in values {
+                    if let Variant::Faces(value) = rbx_value.as_ref() {
+                        chunk.write_u8(value.bits())?;
+                    } else {
+                        return type_mismatch(i, &rbx_value, "Faces");
                     }
-                    Type::Faces => {
-                        for (i, rbx_value) in values {
-                            if let Variant::Faces(value) = rbx_value.as_ref() {
-                                chunk.write_u8(value.bits())?;
-                            } else {
-                                return type_mismatch(i, &rbx_value, "Faces");
-                            }
-                        }
+                }
+            }
+            Type::Axes => {
+                for (i, rbx_value) in values {
+                    if let Variant::Axes(value) = rbx_value.as_ref() {
+                        chunk.write_u8(value.bits())?;
+                    } else {
+                        return type_mismatch(i, &rbx_value, "Axes");
                     }
-                    Type::Axes => {
-                        for (i, rbx_value) in values {
-                            if let Variant::Axes(value) = rbx_value.as_ref() {
-                                chunk.write_u8(value.bits())?;
-                            } else {
-                                return type_mismatch(i, &rbx_value, "Axes");
-                            }
-                        }
+                }
+            }
+            Type::BrickColor => {
+                let mut numbers = Vec::with_capacity(values.len());
+
+                for (i, rbx_value) in values {
+                    if let Variant::BrickColor(value) = rbx_value.as_ref() {
+                        numbers.push(*value as u32);
+                    } else if let Variant::Int32(value) = rbx_value.as_ref() {
+                        numbers.push(*value as u32);
+                    } else {
+                        return type_mismatch(i, &rbx_value, "BrickColor");
                     }
-                    Type::BrickColor => {
-                        let mut numbers = Vec::with_capacity(values.len());
-
-                        for (i, rbx_value) in values {
-                            if let Variant::BrickColor(value) = rbx_value.as_ref() {
-                                numbers.push(*value as u32);
-                            } else if let Variant::Int32(value) = rbx_value.as_ref() {
-                                numbers.push(*value as u32);
-                            } else {
-                                return type_mismatch(i, &rbx_value, "BrickColor");
-                            }
-                        }
+                }
 
-                        chunk.write_interleaved_u32_array(&numbers)?;
+                chunk.write_interleaved_u32_array(&numbers)?;
+            }
+            Type::Color3 => {
+                let mut r = Vec::with_capacity(values.len());
+                let mut g = Vec::with_capacity(values.len());
+                let mut b = Vec::with_capacity(values.len());
+
+                for (i, rbx_value) in values {
+                    if let Variant::Color3(value) = rbx_value.as_ref() {
+                        r.push(value.r);
+                        g.push(value.g);
+                        b.push(value.b);
+                    } else {
+                        return type_mismatch(i, &rbx_value, "Color3");
                     }
-                    Type::Color3 => {
-                        let mut r = Vec::with_capacity(values.len());
-                        let mut g = Vec::with_capacity(values.len());
-                        let mut b = Vec::with_capacity(values.len());
-
-                        for (i, rbx_value) in values {
-                            if let Variant::Color3(value) = rbx_value.as_ref() {
-                                r.push(value.r);
-                                g.push(value.g);
-                                b.push(value.b);
-                            } else {
-                                return type_mismatch(i, &rbx_value, "Color3");
-                            }
-                        }
+                }
 
-                        chunk.write_interleaved_f32_array(r.into_iter())?;
-                        chunk.write_interleaved_f32_array(g.into_iter())?;
-                        chunk.write_interleaved_f32_array(b.into_iter())?;
+                chunk.write_interleaved_f32_array(r.into_iter())?;
+                chunk.write_interleaved_f32_array(g.into_iter())?;
+                chunk.write_interleaved_f32_array(b.into_iter())?;
+            }
+            Type::Vector2 => {
+                let mut x = Vec::with_capacity(values.len());
+                let mut y = Vec::with_capacity(values.len());
+
+                for (i, rbx_value) in values {
+                    if let Variant::Vector2(value) = rbx_value.as_ref() {
+                        x.push(value.x);
+                        y.push(value.y)
+                    } else {
+                        return type_mismatch(i, &rbx_value, "Vector2");
                     }
-                    Type::Vector2 => {
-                        let mut x = Vec::with_capacity(values.len());
-                        let mut y = Vec::with_capacity(values.len());
-
-                        for (i, rbx_value) in values {
-                            if let Variant::Vector2(value) = rbx_value.as_ref() {
-                                x.push(value.x);
-                                y.push(value.y)
-                            } else {
-                                return type_mismatch(i, &rbx_value, "Vector2");
-                            }
-                        }
+                }
 
-                        chunk.write_interleaved_f32_array(x.into_iter())?;
-                        chunk.write_interleaved_f32_array(y.into_iter())?;
+                chunk.write_interleaved_f32_array(x.into_iter())?;
+                chunk.write_interleaved_f32_array(y.into_iter())?;
+            }
+            Type::Vector3 => {
+                let mut x = Vec::with_capacity(values.len());
+                let mut y = Vec::with_capacity(values.len());
+                let mut z = Vec::with_capacity(values.len());
+
+                for (i, rbx_value) in values {
+                    if let Variant::Vector3(value) = rbx_value.as_ref() {
+                        x.push(value.x);
+                        y.push(value.y);
+                        z.push(value.z)
+                    } else {
+                        return type_mismatch(i, &rbx_value, "Vector3");
                     }
-                    Type::Vector3 => {
-                        let mut x = Vec::with_capacity(values.len());
-                        let mut y = Vec::with_capacity(values.len());
-                        let mut z = Vec::with_capacity(values.len());
-
-                        for (i, rbx_value) in values {
-                            if let Variant::Vector3(value) = rbx_value.as_ref() {
-                                x.push(value.x);
-                                y.push(value.y);
-                                z.push(value.z)
-                            } else {
-                                return type_mismatch(i, &rbx_value, "Vector3");
-                            }
-                        }
+                }
 
-                        chunk.write_interleaved_f32_array(x.into_iter())?;
-                        chunk.write_interleaved_f32_array(y.into_iter())?;
-                        chunk.write_interleaved_f32_array(z.into_iter())?;
+                chunk.write_interleaved_f32_array(x.into_iter())?;
+                chunk.write_interleaved_f32_array(y.into_iter())?;
+                chunk.write_interleaved_f32_array(z.into_iter())?;
+            }
+            Type::CFrame => {
+                let mut rotations = Vec::with_capacity(values.len());
+                let mut x = Vec::with_capacity(values.len());
+                let mut y = Vec::with_capacity(values.len());
+                let mut z = Vec::with_capacity(values.len());
+
+                for (i, rbx_value) in values {
+                    if let Variant::CFrame(value) = rbx_value.as_ref() {
+                        rotations.push(value.orientation);
+                        x.push(value.position.x);
+                        y.push(value.position.y);
+                        z.push(value.position.z);
+                    } else {
+                        return type_mismatch(i, &rbx_value, "CFrame");
                     }
-                    Type::CFrame => {
-                        let mut rotations = Vec::with_capacity(values.len());
-                        let mut x = Vec::with_capacity(values.len());
-                        let mut y = Vec::with_capacity(values.len());
-                        let mut z = Vec::with_capacity(values.len());
-
-                        for (i, rbx_value) in values {
-                            if let Variant::CFrame(value) = rbx_value.as_ref() {
-                                rotations.push(value.orientation);
-                                x.push(value.position.x);
-                                y.push(value.position.y);
-                                z.push(value.position.z);
-                            } else {
-                                return type_mismatch(i, &rbx_value, "CFrame");
-                            }
-                        }
-
-                        for matrix in rotations {
-                            if let Some(id) = matrix.to_basic_rotation_id() {
-                                chunk.write_u8(id)?;
-                            } else {
-                                chunk.write_u8(0x00)?;
+                }
 
-                                chunk.write_le_f32(matrix.x.x)?;
-                                chunk.write_le_f32(matrix.x.y)?;
-                                chunk.write_le_f32(matrix.x.z)?;
+                for matrix in rotations {
+                    if let Some(id) = basic_rotation_id(&matrix, normalize) {
+                        chunk.write_u8(id)?;
+                    } else {
+                        chunk.write_u8(0x00)?;
 
-                                chunk.write_le_f32(matrix.y.x)?;
-                                chunk.write_le_f32(matrix.y.y)?;
-                                chunk.write_le_f32(matrix.y.z)?;
+                        chunk.write_le_f32(matrix.x.x)?;
+                        chunk.write_le_f32(matrix.x.y)?;
+                        chunk.write_le_f32(matrix.x.z)?;
 
-                                chunk.write_le_f32(matrix.z.x)?;
-                                chunk.write_le_f32(matrix.z.y)?;
-                                chunk.write_le_f32(matrix.z.z)?;
-                            }
-                        }
+                        chunk.write_le_f32(matrix.y.x)?;
+                        chunk.write_le_f32(matrix.y.y)?;
+                        chunk.write_le_f32(matrix.y.z)?;
 
-                        chunk.write_interleaved_f32_array(x.into_iter())?;
-                        chunk.write_interleaved_f32_array(y.into_iter())?;
-                        chunk.write_interleaved_f32_array(z.into_iter())?;
+                        chunk.write_le_f32(matrix.z.x)?;
+                        chunk.write_le_f32(matrix.z.y)?;
+                        chunk.write_le_f32(matrix.z.z)?;
                     }
-                    Type::Enum => {
-                        let mut buf = Vec::with_capacity(values.len());
-
-                        for (i, rbx_value) in values {
-                            if let Variant::Enum(value) = rbx_value.as_ref() {
-                                buf.push(value.to_u32());
-                            } else {
-                                return type_mismatch(i, &rbx_value, "Enum");
-                            }
-                        }
+                }
 
-                        chunk.write_interleaved_u32_array(&buf)?;
+                chunk.write_interleaved_f32_array(x.into_iter())?;
+                chunk.write_interleaved_f32_array(y.into_iter())?;
+                chunk.write_interleaved_f32_array(z.into_iter())?;
+            }
+            Type::Enum => {
+                let mut buf = Vec::with_capacity(values.len());
+
+                for (i, rbx_value) in values {
+                    if let Variant::Enum(value) = rbx_value.as_ref() {
+                        buf.push(value.to_u32());
+                    } else {
+                        return type_mismatch(i, &rbx_value, "Enum");
                     }
-                    Type::Ref => {
-                        let mut buf = Vec::with_capacity(values.len());
-
-                        for (i, rbx_value) in values {
-                            if let Variant::Ref(value) = rbx_value.as_ref() {
-                                if let Some(id) = self.id_to_referent.get(value) {
-                                    buf.push(*id);
-                                } else {
-                                    buf.push(-1);
-                                }
-                            } else {
-                                return type_mismatch(i, &rbx_value, "Ref");
-                            }
+                }
+
+                chunk.write_interleaved_u32_array(&buf)?;
+            }
+            Type::Ref => {
+                let mut buf = Vec::with_capacity(values.len());
+
+                for (i, rbx_value) in values {
+                    if let Variant::Ref(value) = rbx_value.as_ref() {
+                        if let Some(id) = self.id_to_referent.get(value) {
+                            buf.push(*id);
+                        } else {
+                            buf.push(-1);
                         }
+                    } else {
+                        return type_mismatch(i, &rbx_value, "Ref");
+                    }
+                }
 
-                        chunk.write_referent_array(buf.into_iter())?;
+                chunk.write_referent_array(buf.into_iter())?;
+            }
+            Type::Vector3int16 => {
+                for (i, rbx_value) in values {
+                    if let Variant::Vector3int16(value) = rbx_value.as_ref() {
+                        chunk.write_le_i16(value.x)?;
+                        chunk.write_le_i16(value.y)?;
+                        chunk.write_le_i16(value.z)?;
+                    } else {
+                        return type_mismatch(i, &rbx_value, "Vector3int16");
                     }
-                    Type::Vector3int16 => {
-                        for (i, rbx_value) in values {
-                            if let Variant::Vector3int16(value) = rbx_value.as_ref() {
-                                chunk.write_le_i16(value.x)?;
-                                chunk.write_le_i16(value.y)?;
-                                chunk.write_le_i16(value.z)?;
-                            } else {
-                                return type_mismatch(i, &rbx_value, "Vector3int16");
-                            }
+                }
+            }
+            Type::NumberSequence => {
+                for (i, rbx_value) in values {
+                    if let Variant::NumberSequence(value) = rbx_value.as_ref() {
+                        chunk.write_le_u32(value.keypoints.len() as u32)?;
+
+                        for keypoint in &value.keypoints {
+                            chunk.write_le_f32(keypoint.time)?;
+                            chunk.write_le_f32(keypoint.value)?;
+                            chunk.write_le_f32(keypoint.envelope)?;
                         }
+                    } else {
+                        return type_mismatch(i, &rbx_value, "NumberSequence");
                     }
-                    Type::NumberSequence => {
-                        for (i, rbx_value) in values {
-                            if let Variant::NumberSequence(value) = rbx_value.as_ref() {
-                                chunk.write_le_u32(value.keypoints.len() as u32)?;
-
-                                for keypoint in &value.keypoints {
-                                    chunk.write_le_f32(keypoint.time)?;
-                                    chunk.write_le_f32(keypoint.value)?;
-                                    chunk.write_le_f32(keypoint.envelope)?;
-                                }
-                            } else {
-                                return type_mismatch(i, &rbx_value, "NumberSequence");
-                            }
+                }
+            }
+            Type::ColorSequence => {
+                for (i, rbx_value) in values {
+                    if let Variant::ColorSequence(value) = rbx_value.as_ref() {
+                        chunk.write_le_u32(value.keypoints.len() as u32)?;
+
+                        for keypoint in &value.keypoints {
+                            chunk.write_le_f32(keypoint.time)?;
+                            chunk.write_le_f32(keypoint.color.r)?;
+                            chunk.write_le_f32(keypoint.color.g)?;
+                            chunk.write_le_f32(keypoint.color.b)?;
+
+                            // write out a dummy value for envelope, which is serialized but doesn't do anything
+                            chunk.write_le_f32(0.0)?;
                         }
+                    } else {
+                        return type_mismatch(i, &rbx_value, "ColorSequence");
                     }
-                    Type::ColorSequence => {
-                        for (i, rbx_value) in values {
-                            if let Variant::ColorSequence(value) = rbx_value.as_ref() {
-                                chunk.write_le_u32(value.keypoints.len() as u32)?;
-
-                                for keypoint in &value.keypoints {
-                                    chunk.write_le_f32(keypoint.time)?;
-                                    chunk.write_le_f32(keypoint.color.r)?;
-                                    chunk.write_le_f32(keypoint.color.g)?;
-                                    chunk.write_le_f32(keypoint.color.b)?;
-
-                                    // write out a dummy value for envelope, which is serialized but doesn't do anything
-                                    chunk.write_le_f32(0.0)?;
-                                }
-                            } else {
-                                return type_mismatch(i, &rbx_value, "ColorSequence");
-                            }
-                        }
+                }
+            }
+            Type::NumberRange => {
+                for (i, rbx_value) in values {
+                    if let Variant::NumberRange(value) = rbx_value.as_ref() {
+                        chunk.write_le_f32(value.min)?;
+                        chunk.write_le_f32(value.max)?;
+                    } else {
+                        return type_mismatch(i, &rbx_value, "NumberRange");
                     }
-                    Type::NumberRange => {
-                        for (i, rbx_value) in values {
-                            if let Variant::NumberRange(value) = rbx_value.as_ref() {
-                                chunk.write_le_f32(value.min)?;
-                                chunk.write_le_f32(value.max)?;
-                            } else {
-                                return type_mismatch(i, &rbx_value, "NumberRange");
-                            }
-                        }
+                }
+            }
+            Type::Rect => {
+                let mut x_min = Vec::with_capacity(values.len());
+                let mut y_min = Vec::with_capacity(values.len());
+                let mut x_max = Vec::with_capacity(values.len());
+                let mut y_max = Vec::with_capacity(values.len());
+
+                for (i, rbx_value) in values {
+                    if let Variant::Rect(value) = rbx_value.as_ref() {
+                        x_min.push(value.min.x);
+                        y_min.push(value.min.y);
+                        x_max.push(value.max.x);
+                        y_max.push(value.max.y);
+                    } else {
+                        return type_mismatch(i, &rbx_value, "Rect");
                     }
-                    Type::Rect => {
-                        let mut x_min = Vec::with_capacity(values.len());
-                        let mut y_min = Vec::with_capacity(values.len());
-                        let mut x_max = Vec::with_capacity(values.len());
-                        let mut y_max = Vec::with_capacity(values.len());
-
-                        for (i, rbx_value) in values {
-                            if let Variant::Rect(value) = rbx_value.as_ref() {
-                                x_min.push(value.min.x);
-                                y_min.push(value.min.y);
-                                x_max.push(value.max.x);
-                                y_max.push(value.max.y);
-                            } else {
-                                return type_mismatch(i, &rbx_value, "Rect");
-                            }
-                        }
+                }
 
-                        chunk.write_interleaved_f32_array(x_min.into_iter())?;
-                        chunk.write_interleaved_f32_array(y_min.into_iter())?;
-                        chunk.write_interleaved_f32_array(x_max.into_iter())?;
-                        chunk.write_interleaved_f32_array(y_max.into_iter())?;
-                    }
-                    Type::PhysicalProperties => {
-                        for (i, rbx_value) in values {
-                            if let Variant::PhysicalProperties(value) = rbx_value.as_ref() {
-                                if let PhysicalProperties::Custom(props) = value {
-                                    chunk.write_u8(1)?;
-                                    chunk.write_le_f32(props.density)?;
-                                    chunk.write_le_f32(props.friction)?;
-                                    chunk.write_le_f32(props.elasticity)?;
-                                    chunk.write_le_f32(props.friction_weight)?;
-                                    chunk.write_le_f32(props.elasticity_weight)?;
-                                } else {
-                                    chunk.write_u8(0)?;
-                                }
-                            } else {
-                                return type_mismatch(i, &rbx_value, "PhysicalProperties");
-                            }
+                chunk.write_interleaved_f32_array(x_min.into_iter())?;
+                chunk.write_interleaved_f32_array(y_min.into_iter())?;
+                chunk.write_interleaved_f32_array(x_max.into_iter())?;
+                chunk.write_interleaved_f32_array(y_max.into_iter())?;
+            }
+            Type::PhysicalProperties => {
+                for (i, rbx_value) in values {
+                    if let Variant::PhysicalProperties(value) = rbx_value.as_ref() {
+                        if let PhysicalProperties::Custom(props) = value {
+                            chunk.write_u8(1)?;
+                            chunk.write_le_f32(props.density)?;
+                            chunk.write_le_f32(props.friction)?;
+                            chunk.write_le_f32(props.elasticity)?;
+                            chunk.write_le_f32(props.friction_weight)?;
+                            chunk.write_le_f32(props.elasticity_weight)?;
+                        } else {
+                            chunk.write_u8(0)?;
                         }
+                    } else {
+                        return type_mismatch(i, &rbx_value, "PhysicalProperties");
                     }
-                    Type::Color3uint8 => {
-                        let mut r = Vec::with_capacity(values.len());
-                        let mut g = Vec::with_capacity(values.len());
-                        let mut b = Vec::with_capacity(values.len());
-
-                        for (i, rbx_value) in values {
-                            match rbx_value.as_ref() {
-                                Variant::Color3uint8(value) => {
-                                    r.push(value.r);
-                                    g.push(value.g);
-                                    b.push(value.b);
-                                }
-                                Variant::Color3(value) => {
-                                    let color: Color3uint8 = (*value).into();
-
-                                    r.push(color.r);
-                                    g.push(color.g);
-                                    b.push(color.b);
-                                }
-                                _ => return type_mismatch(i, &rbx_value, "Color3uint8 or Color3"),
-                            }
+                }
+            }
+            Type::Color3uint8 => {
+                let mut r = Vec::with_capacity(values.len());
+                let mut g = Vec::with_capacity(values.len());
+                let mut b = Vec::with_capacity(values.len());
+
+                for (i, rbx_value) in values {
+                    match rbx_value.as_ref() {
+                        Variant::Color3uint8(value) => {
+                            r.push(value.r);
+                            g.push(value.g);
+                            b.push(value.b);
                         }
+                        Variant::Color3(value) => {
+                            let color: Color3uint8 = (*value).into();
 
-                        chunk.write_all(r.as_slice())?;
-                        chunk.write_all(g.as_slice())?;
-                        chunk.write_all(b.as_slice())?;
-                    }
-                    Type::Int64 => {
-                        let mut buf = Vec::with_capacity(values.len());
-
-                        for (i, rbx_value) in values {
-                            match rbx_value.as_ref() {
-                                Variant::Int64(value) => {
-                                    buf.push(*value);
-                                }
-                                Variant::Int32(value) => {
-                                    buf.push(*value as i64);
-                                }
-                                _ => return type_mismatch(i, &rbx_value, "Int64"),
-                            }
+                            r.push(color.r);
+                            g.push(color.g);
+                            b.push(color.b);
                         }
-
-                        chunk.write_interleaved_i64_array(buf.into_iter())?;
+                        _ => return type_mismatch(i, &rbx_value, "Color3uint8 or Color3"),
                     }
-                    Type::SharedString => {
-                        let mut entries = Vec::with_capacity(values.len());
-
-                        for (i, rbx_value) in values {
-                            if let Variant::SharedString(value) = rbx_value.as_ref() {
-                                let id = &self.shared_string_ids[value];
-                                entries.push(*id);
-                            } else {
-                                return type_mismatch(i, &rbx_value, "SharedString");
-                            }
+                }
+
+                chunk.write_all(r.as_slice())?;
+                chunk.write_all(g.as_slice())?;
+                chunk.write_all(b.as_slice())?;
+            }
+            Type::Int64 => {
+                let mut buf = Vec::with_capacity(values.len());
+
+                for (i, rbx_value) in values {
+                    match rbx_value.as_ref() {
+                        Variant::Int64(value) => {
+                            buf.push(*value);
+                        }
+                        Variant::Int32(value) => {
+                            buf.push(*value as i64);
                         }
+                        _ => return type_mismatch(i, &rbx_value, "Int64"),
+                    }
+                }
 
-                        chunk.write_interleaved_u32_array(&entries)?;
+                chunk.write_interleaved_i64_array(buf.into_iter())?;
+            }
+            Type::SharedString => {
+                let mut entries = Vec::with_capacity(values.len());
+
+                for (i, rbx_value) in values {
+                    if let Variant::SharedString(value) = rbx_value.as_ref() {
+                        let referent = type_info.instances[i].referent();
+                        let id = self
+                            .instance_shared_string_ids
+                            .get(&(referent, value.clone()))
+                            .unwrap_or_else(|| &self.shared_string_ids[value]);
+                        entries.push(*id);
+                    } else {
+                        return type_mismatch(i, &rbx_value, "SharedString");
                     }
-                    Type::OptionalCFrame => {
-                        let mut rotations = Vec::with_capacity(values.len());
-                        let mut bools = Vec::with_capacity(values.len());
-                        let mut x = Vec::with_capacity(values.len());
-                        let mut y = Vec::with_capacity(values.len());
-                        let mut z = Vec::with_capacity(values.len());
-
-                        chunk.write_u8(Type::CFrame as u8)?;
-
-                        for (i, rbx_value) in values {
-                            if let Variant::OptionalCFrame(value) = rbx_value.as_ref() {
-                                if let Some(value) = value {
-                                    rotations.push(value.orientation);
-                                    x.push(value.position.x);
-                                    y.push(value.position.y);
-                                    z.push(value.position.z);
-                                    bools.push(0x01);
-                                } else {
-                                    rotations.push(Matrix3::identity());
-                                    x.push(0.0);
-                                    y.push(0.0);
-                                    z.push(0.0);
-                                    bools.push(0x00);
-                                }
-                            } else {
-                                return type_mismatch(i, &rbx_value, "OptionalCFrame");
-                            }
+                }
+
+                chunk.write_interleaved_u32_array(&entries)?;
+            }
+            Type::OptionalCFrame => {
+                let mut rotations = Vec::with_capacity(values.len());
+                let mut bools = Vec::with_capacity(values.len());
+                let mut x = Vec::with_capacity(values.len());
+                let mut y = Vec::with_capacity(values.len());
+                let mut z = Vec::with_capacity(values.len());
+
+                chunk.write_u8(Type::CFrame as u8)?;
+
+                for (i, rbx_value) in values {
+                    if let Variant::OptionalCFrame(value) = rbx_value.as_ref() {
+                        if let Some(value) = value {
+                            rotations.push(value.orientation);
+                            x.push(value.position.x);
+                            y.push(value.position.y);
+                            z.push(value.position.z);
+                            bools.push(0x01);
+                        } else {
+                            rotations.push(Matrix3::identity());
+                            x.push(0.0);
+                            y.push(0.0);
+                            z.push(0.0);
+                            bools.push(0x00);
                         }
+                    } else {
+                        return type_mismatch(i, &rbx_value, "OptionalCFrame");
+                    }
+                }
 
-                        for matrix in rotations {
-                            if let Some(id) = matrix.to_basic_rotation_id() {
-                                chunk.write_u8(id)?;
-                            } else {
-                                chunk.write_u8(0x00)?;
+                for matrix in rotations {
+                    if let Some(id) = basic_rotation_id(&matrix, normalize) {
+                        chunk.write_u8(id)?;
+                    } else {
+                        chunk.write_u8(0x00)?;
 
-                                chunk.write_le_f32(matrix.x.x)?;
-                                chunk.write_le_f32(matrix.x.y)?;
-                                chunk.write_le_f32(matrix.x.z)?;
+                        chunk.write_le_f32(matrix.x.x)?;
+                        chunk.write_le_f32(matrix.x.y)?;
+                        chunk.write_le_f32(matrix.x.z)?;
 
-                                chunk.write_le_f32(matrix.y.x)?;
-                                chunk.write_le_f32(matrix.y.y)?;
-                                chunk.write_le_f32(matrix.y.z)?;
+                        chunk.write_le_f32(matrix.y.x)?;
+                        chunk.write_le_f32(matrix.y.y)?;
+                        chunk.write_le_f32(matrix.y.z)?;
 
-                                chunk.write_le_f32(matrix.z.x)?;
-                                chunk.write_le_f32(matrix.z.y)?;
-                                chunk.write_le_f32(matrix.z.z)?;
-                            }
-                        }
+                        chunk.write_le_f32(matrix.z.x)?;
+                        chunk.write_le_f32(matrix.z.y)?;
+                        chunk.write_le_f32(matrix.z.z)?;
+                    }
+                }
 
-                        chunk.write_interleaved_f32_array(x.into_iter())?;
-                        chunk.write_interleaved_f32_array(y.into_iter())?;
-                        chunk.write_interleaved_f32_array(z.into_iter())?;
+                chunk.write_interleaved_f32_array(x.into_iter())?;
+                chunk.write_interleaved_f32_array(y.into_iter())?;
+                chunk.write_interleaved_f32_array(z.into_iter())?;
 
-                        chunk.write_u8(Type::Bool as u8)?;
-                        chunk.write_all(bools.as_slice())?;
+                chunk.write_u8(Type::Bool as u8)?;
+                chunk.write_all(bools.as_slice())?;
+            }
+            Type::UniqueId => {
+                let mut blobs = Vec::with_capacity(values.len());
+                for (i, rbx_value) in values {
+                    if let Variant::UniqueId(value) = rbx_value.as_ref() {
+                        let mut blob = [0; 16];
+                        // This is maybe not the best solution to this
+                        // but we can always change it.
+                        blob[0..4].copy_from_slice(&value.index().to_be_bytes());
+                        blob[4..8].copy_from_slice(&value.time().to_be_bytes());
+                        blob[8..].copy_from_slice(&value.random().rotate_left(1).to_be_bytes());
+                        blobs.push(blob);
+                    } else {
+                        return type_mismatch(i, &rbx_value, "UniqueId");
                     }
-                    Type::UniqueId => {
-                        let mut blobs = Vec::with_capacity(values.len());
-                        for (i, rbx_value) in values {
-                            if let Variant::UniqueId(value) = rbx_value.as_ref() {
-                                let mut blob = [0; 16];
-                                // This is maybe not the best solution to this
-                                // but we can always change it.
-                                blob[0..4].copy_from_slice(&value.index().to_be_bytes());
-                                blob[4..8].copy_from_slice(&value.time().to_be_bytes());
-                                blob[8..]
-                                    .copy_from_slice(&value.random().rotate_left(1).to_be_bytes());
-                                blobs.push(blob);
-                            } else {
-                                return type_mismatch(i, &rbx_value, "UniqueId");
-                            }
-                        }
+                }
 
-                        chunk.write_interleaved_bytes::<16>(&blobs)?;
+                chunk.write_interleaved_bytes::<16>(&blobs)?;
+            }
+            Type::SecurityCapabilities => {
+                let mut buf = Vec::with_capacity(values.len());
+
+                for (i, rbx_value) in values {
+                    if let Variant::SecurityCapabilities(value) = rbx_value.as_ref() {
+                        buf.push(value.bits() as i64);
+                    } else {
+                        return type_mismatch(i, &rbx_value, "SecurityCapabilities");
                     }
                 }
 
-                chunk.dump(&mut self.output)?;
+                chunk.write_interleaved_i64_array(buf.into_iter())?;
             }
         }
 
-        Ok(())
+        let instances = type_info.instances.len();
+        self.property_values += instances;
+        Ok(Some(chunk))
+    }
+
+    /// Encodes the PROP chunk for one of the unknown properties of a class,
+    /// which is written back exactly as it was read.
+    fn unknown_property_chunk(
+        &self,
+        type_name: &str,
+        index: usize,
+    ) -> Result<ChunkBuilder, InnerError> {
+        let type_info = &self.type_infos.values[type_name];
+        let unknown = type_info.unknown_properties[index];
+
+        log::trace!(
+            "Writing unknown property {}.{} (type ID {})",
+            type_name,
+            unknown.property_name,
+            unknown.type_id
+        );
+
+        let mut chunk = self.new_chunk(b"PROP");
+
+        chunk.write_le_u32(type_info.type_id)?;
+        chunk.write_string(&unknown.property_name)?;
+        chunk.write_u8(unknown.type_id)?;
+        chunk.write_all(&unknown.data)?;
+
+        Ok(chunk)
     }
 
     /// Write out the hierarchical relations between instances, stored in a
@@ -1145,7 +1837,7 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
     pub fn serialize_parents(&mut self) -> Result<(), InnerError> {
         log::trace!("Writing parent relationships");
 
-        let mut chunk = ChunkBuilder::new(b"PRNT", ChunkCompression::Compressed);
+        let mut chunk = self.new_chunk(b"PRNT");
 
         chunk.write_u8(0)?; // PRNT version 0
         chunk.write_le_u32(self.relevant_instances.len() as u32)?;
@@ -1193,6 +1885,34 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
         Ok(())
     }
 
+    /// Consumes the serializer state, returning the report of what happened to
+    /// external references.
+    pub fn finish(self) -> EncodeReport {
+        log::debug!(
+            "Encoded {} instances and {} property values",
+            self.relevant_instances.len(),
+            self.property_values
+        );
+
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("instances", self.relevant_instances.len());
+            self.span.record("property_values", self.property_values);
+        }
+
+        self.report
+    }
+
+    /// Creates a chunk that respects this serializer's compression settings
+    /// and spill threshold.
+    fn new_chunk(&self, chunk_name: &'static [u8]) -> ChunkBuilder {
+        ChunkBuilder::new(
+            chunk_name,
+            ChunkCompression::Compressed(self.serializer.compression),
+        )
+        .spill_threshold(self.serializer.spill_threshold)
+    }
+
     /// Equivalent to Instance:GetFullName() from Roblox.
     fn full_name_for(&self, subject_ref: Ref) -> String {
         let mut components = Vec::new();
@@ -1214,7 +1934,10 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
         name
     }
 
-    fn fallback_default_value(rbx_type: VariantType) -> Option<Variant> {
+    /// Guesses a default value for a property of the given type. When
+    /// `deterministic` is set, values that would normally be generated fresh,
+    /// like UniqueIds, are replaced with fixed ones.
+    fn fallback_default_value(rbx_type: VariantType, deterministic: bool) -> Option<Variant> {
         Some(match rbx_type {
             VariantType::String => Variant::String(String::new()),
             VariantType::BinaryString => Variant::BinaryString(BinaryString::new()),
@@ -1269,9 +1992,138 @@ impl<'dom, W: Write> SerializerState<'dom, W> {
             VariantType::Tags => Variant::Tags(Tags::new()),
             VariantType::Content => Variant::Content(Content::new()),
             VariantType::Attributes => Variant::Attributes(Attributes::new()),
+            VariantType::UniqueId if deterministic => Variant::UniqueId(UniqueId::new(0, 0, 0)),
             VariantType::UniqueId => Variant::UniqueId(UniqueId::now().unwrap()),
             VariantType::Font => Variant::Font(Font::default()),
+            VariantType::SecurityCapabilities => {
+                Variant::SecurityCapabilities(SecurityCapabilities::new())
+            }
             _ => return None,
         })
     }
 }
+
+/// Finds the ID of the right-angle rotation `matrix` represents, if it's one.
+///
+/// Unless `approximate` is set, the matrix must match the rotation bit for
+/// bit, so that writing it as an ID doesn't change it.
+fn basic_rotation_id(matrix: &Matrix3, approximate: bool) -> Option<u8> {
+    let id = matrix.to_basic_rotation_id()?;
+
+    if approximate {
+        return Some(id);
+    }
+
+    let basic = Matrix3::from_basic_rotation_id(id).ok()?;
+    let bits = |matrix: &Matrix3| {
+        [matrix.x, matrix.y, matrix.z].map(|row| [row.x, row.y, row.z].map(f32::to_bits))
+    };
+
+    if bits(matrix) == bits(&basic) {
+        Some(id)
+    } else {
+        None
+    }
+}
+
+/// Normalizes every float in `value` for
+/// [`Serializer::normalize_floats`]: negative zero becomes zero and every NaN
+/// becomes the same NaN.
+fn normalize_floats(value: Cow<'_, Variant>) -> Cow<'_, Variant> {
+    fn float(value: f32) -> f32 {
+        if value == 0.0 {
+            0.0
+        } else if value.is_nan() {
+            f32::NAN
+        } else {
+            value
+        }
+    }
+
+    fn vector2(value: Vector2) -> Vector2 {
+        Vector2::new(float(value.x), float(value.y))
+    }
+
+    fn vector3(value: Vector3) -> Vector3 {
+        Vector3::new(float(value.x), float(value.y), float(value.z))
+    }
+
+    fn cframe(value: CFrame) -> CFrame {
+        let orientation = value.orientation;
+        CFrame::new(
+            vector3(value.position),
+            Matrix3::new(
+                vector3(orientation.x),
+                vector3(orientation.y),
+                vector3(orientation.z),
+            ),
+        )
+    }
+
+    fn udim(value: UDim) -> UDim {
+        UDim::new(float(value.scale), value.offset)
+    }
+
+    fn color3(value: Color3) -> Color3 {
+        Color3::new(float(value.r), float(value.g), float(value.b))
+    }
+
+    let normalized = match value.as_ref() {
+        Variant::Float32(value) => Variant::Float32(float(*value)),
+        Variant::Float64(value) => Variant::Float64(if *value == 0.0 {
+            0.0
+        } else if value.is_nan() {
+            f64::NAN
+        } else {
+            *value
+        }),
+        Variant::Vector2(value) => Variant::Vector2(vector2(*value)),
+        Variant::Vector3(value) => Variant::Vector3(vector3(*value)),
+        Variant::CFrame(value) => Variant::CFrame(cframe(*value)),
+        Variant::OptionalCFrame(value) => Variant::OptionalCFrame(value.map(cframe)),
+        Variant::Color3(value) => Variant::Color3(color3(*value)),
+        Variant::UDim(value) => Variant::UDim(udim(*value)),
+        Variant::UDim2(value) => Variant::UDim2(UDim2::new(udim(value.x), udim(value.y))),
+        Variant::Rect(value) => Variant::Rect(Rect::new(vector2(value.min), vector2(value.max))),
+        Variant::Ray(value) => {
+            Variant::Ray(Ray::new(vector3(value.origin), vector3(value.direction)))
+        }
+        Variant::NumberRange(value) => {
+            Variant::NumberRange(NumberRange::new(float(value.min), float(value.max)))
+        }
+        Variant::NumberSequence(value) => Variant::NumberSequence(NumberSequence {
+            keypoints: value
+                .keypoints
+                .iter()
+                .map(|keypoint| {
+                    NumberSequenceKeypoint::new(
+                        float(keypoint.time),
+                        float(keypoint.value),
+                        float(keypoint.envelope),
+                    )
+                })
+                .collect(),
+        }),
+        Variant::ColorSequence(value) => Variant::ColorSequence(ColorSequence {
+            keypoints: value
+                .keypoints
+                .iter()
+                .map(|keypoint| {
+                    ColorSequenceKeypoint::new(float(keypoint.time), color3(keypoint.color))
+                })
+                .collect(),
+        }),
+        Variant::PhysicalProperties(PhysicalProperties::Custom(value)) => {
+            Variant::PhysicalProperties(PhysicalProperties::Custom(CustomPhysicalProperties {
+                density: float(value.density),
+                friction: float(value.friction),
+                elasticity: float(value.elasticity),
+                friction_weight: float(value.friction_weight),
+                elasticity_weight: float(value.elasticity_weight),
+            }))
+        }
+        _ => return value,
+    };
+
+    Cow::Owned(normalized)
+}
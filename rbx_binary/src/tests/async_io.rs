@@ -0,0 +1,146 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use tokio::io::AsyncWrite;
+
+use crate::{
+    chunk, from_reader_async, to_writer, to_writer_async, ChunkKind, ChunkReader, DecodeError,
+    Deserializer,
+};
+
+#[cfg(feature = "reflection_database")]
+use crate::DecodeWarning;
+
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+fn assert_send<T: Send>(value: T) -> T {
+    value
+}
+
+fn test_tree() -> WeakDom {
+    WeakDom::new(
+        InstanceBuilder::new("Folder").with_children((0..20).map(|i| {
+            InstanceBuilder::new("StringValue")
+                .with_name(format!("Value{}", i))
+                .with_property("Value", "Hello")
+        })),
+    )
+}
+
+/// Ensures that the async serializer writes the same bytes as the blocking
+/// one, and that the async deserializer can read them back.
+#[test]
+fn round_trip() {
+    let tree = test_tree();
+
+    let mut expected = Vec::new();
+    to_writer(&mut expected, &tree, &[tree.root_ref()]).unwrap();
+
+    let mut buffer = Vec::new();
+    block_on(assert_send(to_writer_async(
+        &mut buffer,
+        &tree,
+        &[tree.root_ref()],
+    )))
+    .unwrap();
+    assert_eq!(buffer, expected);
+
+    let decoded = block_on(assert_send(from_reader_async(buffer.as_slice()))).unwrap();
+    let folder = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(folder.children().len(), 20);
+}
+
+/// Ensures that a truncated stream is an error, unless recovery is enabled.
+#[test]
+fn truncated() {
+    let tree = test_tree();
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+    buffer.truncate(buffer.len() - 20);
+
+    let result: Result<_, DecodeError> = block_on(from_reader_async(buffer.as_slice()));
+    assert!(result.is_err());
+
+    let deserializer = Deserializer::new().recover(true);
+    let decoded = block_on(deserializer.deserialize_async(buffer.as_slice())).unwrap();
+    assert_eq!(decoded.root().children().len(), 1);
+}
+
+/// Ensures that the async deserializer hands back the same report as the
+/// blocking one.
+#[test]
+#[cfg(feature = "reflection_database")]
+fn report() {
+    let tree = WeakDom::new(InstanceBuilder::new("NotARealClass"));
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    let deserializer = Deserializer::new();
+    let (dom, report) =
+        block_on(deserializer.deserialize_async_with_report(buffer.as_slice())).unwrap();
+    assert_eq!(dom.root().children().len(), 1);
+
+    match report.warnings() {
+        [DecodeWarning::UnknownClass { class_name, .. }] => {
+            assert_eq!(class_name, "NotARealClass")
+        }
+        other => panic!("unexpected warnings {:?}", other),
+    }
+}
+
+/// Records each write it's given separately.
+#[derive(Default)]
+struct RecordingWriter {
+    writes: Vec<Vec<u8>>,
+}
+
+impl AsyncWrite for RecordingWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.writes.push(buf.to_vec());
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Ensures that PROP chunks are written out as they're finished, instead of
+/// all at once after every one of them is encoded.
+#[test]
+fn writes_each_prop_chunk() {
+    let tree = test_tree();
+
+    let mut writer = RecordingWriter::default();
+    block_on(to_writer_async(&mut writer, &tree, &[tree.root_ref()])).unwrap();
+    let output = writer.writes.concat();
+
+    let prop_chunks = ChunkReader::new(output.as_slice())
+        .unwrap()
+        .map(Result::unwrap)
+        .filter(|chunk| chunk.kind() == ChunkKind::Properties)
+        .count();
+    let batches = prop_chunks.div_ceil(chunk::batch_size());
+
+    // One write for the chunks before PROP, one for each batch of PROP
+    // chunks, and one for the chunks after them.
+    assert_eq!(writer.writes.len(), batches + 2);
+}
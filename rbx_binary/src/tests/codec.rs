@@ -0,0 +1,73 @@
+use crate::codec;
+
+#[test]
+fn interleave_round_trip() {
+    let bytes = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+    let interleaved = codec::interleave(&bytes, 4);
+
+    assert_eq!(interleaved, [1, 5, 9, 2, 6, 10, 3, 7, 11, 4, 8, 12]);
+    assert_eq!(codec::deinterleave(&interleaved, 4), bytes);
+}
+
+#[test]
+fn i32_array() {
+    let values = [0, 1, -1, i32::MAX, i32::MIN];
+    let encoded = codec::encode_i32_array(&values);
+
+    // Zigzag encoding maps 1 to 2 and -1 to 1, which end up in the last row.
+    assert_eq!(&encoded[15..], &[0, 2, 1, 0xFE, 0xFF]);
+    assert_eq!(codec::decode_i32_array(&encoded).unwrap(), values);
+}
+
+#[test]
+fn f32_array() {
+    let values = [0.0, -0.5, 1.0, f32::MAX];
+    let encoded = codec::encode_f32_array(&values);
+
+    assert_eq!(codec::decode_f32_array(&encoded).unwrap(), values);
+    assert_eq!(codec::untransform_f32(codec::transform_f32(-0.5)), -0.5);
+}
+
+#[test]
+fn i64_and_u32_arrays() {
+    let signed = [0, -2, i64::MAX, i64::MIN];
+    let unsigned = [0, 7, u32::MAX];
+
+    assert_eq!(
+        codec::decode_i64_array(&codec::encode_i64_array(&signed)).unwrap(),
+        signed
+    );
+    assert_eq!(
+        codec::decode_u32_array(&codec::encode_u32_array(&unsigned)).unwrap(),
+        unsigned
+    );
+}
+
+#[test]
+fn referent_array() {
+    let values = [5, 6, 7, -1, 3];
+    let encoded = codec::encode_referent_array(&values);
+
+    assert_eq!(codec::decode_i32_array(&encoded).unwrap(), [5, 1, 1, -8, 4]);
+    assert_eq!(codec::decode_referent_array(&encoded).unwrap(), values);
+}
+
+/// Ensures that referents whose deltas overflow an i32 wrap around instead of
+/// panicking.
+#[test]
+fn referent_array_overflow() {
+    let values = [i32::MAX, i32::MIN, 0];
+    let encoded = codec::encode_referent_array(&values);
+
+    assert_eq!(
+        codec::decode_i32_array(&encoded).unwrap(),
+        [i32::MAX, 1, i32::MIN]
+    );
+    assert_eq!(codec::decode_referent_array(&encoded).unwrap(), values);
+}
+
+#[test]
+fn bad_length() {
+    assert!(codec::decode_i32_array(&[0; 6]).is_err());
+    assert!(codec::decode_i64_array(&[0; 12]).is_err());
+}
@@ -1,4 +1,4 @@
-use crate::core::{RbxReadExt, RbxWriteExt};
+use crate::core::{RbxReadExt, RbxReadSliceExt, RbxWriteExt};
 
 #[test]
 fn read_interleaved_bytes() {
@@ -67,3 +67,19 @@ fn write_interleaved_bytes() {
 
     assert_eq!(result, expected)
 }
+
+#[test]
+fn read_borrowed_strings() {
+    let mut buffer = Vec::new();
+    buffer.write_string("Hello").unwrap();
+    buffer.write_binary_string(&[0xff, 0x00]).unwrap();
+    buffer.write_binary_string(&[0xff]).unwrap();
+
+    let mut input = buffer.as_slice();
+    assert_eq!(input.read_str().unwrap(), "Hello");
+    assert_eq!(input.read_binary_str().unwrap(), &[0xff, 0x00]);
+    assert!(input.read_str().is_err());
+
+    let mut truncated: &[u8] = &[4, 0, 0, 0, b'a'];
+    assert!(truncated.read_binary_str().is_err());
+}
@@ -0,0 +1,77 @@
+use rbx_dom_weak::{
+    types::{BinaryString, CFrame, Enum, Matrix3, Vector3},
+    InstanceBuilder, WeakDom,
+};
+
+use crate::csg::{self, CsgPrimitive};
+
+/// Encodes `children` the way Studio stores them in a `ChildData` property.
+fn child_data(children: Vec<InstanceBuilder>) -> BinaryString {
+    let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_children(children));
+
+    let mut buffer = Vec::new();
+    crate::to_writer(&mut buffer, &dom, dom.root().children()).unwrap();
+    buffer.into()
+}
+
+fn part(class: &str, name: &str, x: f32) -> InstanceBuilder {
+    InstanceBuilder::new(class)
+        .with_name(name)
+        .with_property(
+            "CFrame",
+            CFrame::new(Vector3::new(x, 0.0, 0.0), Matrix3::identity()),
+        )
+        .with_property("Size", Vector3::new(2.0, 1.0, 4.0))
+}
+
+#[test]
+fn union_primitives() {
+    let negated = InstanceBuilder::new("NegateOperation").with_property(
+        "ChildData",
+        child_data(vec![
+            part("WedgePart", "Cutout", 2.0),
+            part("Part", "Ball", 3.0).with_property("Shape", Enum::from_u32(0)),
+        ]),
+    );
+
+    let union = WeakDom::new(InstanceBuilder::new("UnionOperation").with_property(
+        "ChildData",
+        child_data(vec![part("Part", "Base", 1.0), negated]),
+    ));
+
+    let primitives = csg::union_primitives(union.root()).unwrap();
+
+    let summary: Vec<_> = primitives
+        .iter()
+        .map(|primitive| (primitive.name.as_str(), primitive.negated))
+        .collect();
+    assert_eq!(summary, [("Base", false), ("Cutout", true), ("Ball", true)]);
+
+    let CsgPrimitive {
+        class,
+        cframe,
+        size,
+        shape,
+        ..
+    } = &primitives[2];
+    assert_eq!(class, "Part");
+    assert_eq!(cframe.position, Vector3::new(3.0, 0.0, 0.0));
+    assert_eq!(*size, Vector3::new(2.0, 1.0, 4.0));
+    assert_eq!(*shape, Some(Enum::from_u32(0)));
+}
+
+#[test]
+fn missing_child_data() {
+    let union = WeakDom::new(
+        InstanceBuilder::new("UnionOperation").with_property("ChildData", BinaryString::new()),
+    );
+
+    assert!(csg::decode_child_data(union.root()).is_none());
+    assert!(csg::union_primitives(union.root()).unwrap().is_empty());
+
+    let corrupt = WeakDom::new(
+        InstanceBuilder::new("UnionOperation")
+            .with_property("ChildData", BinaryString::from(b"CSGMDL".as_slice())),
+    );
+    assert!(csg::union_primitives(corrupt.root()).is_err());
+}
@@ -0,0 +1,1278 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use rbx_dom_weak::{
+    types::{BinaryString, Enum, Variant, Vector3},
+    DomViewer, InstanceBuilder, WeakDom,
+};
+
+use crate::{
+    core::RbxWriteExt, deserializer::UnknownProperty, to_writer, ChunkKind, ChunkReader,
+    CompressionOptions, Damage, DecodeLimits, Deserializer, DomError, ErrorCode, ErrorLocation,
+    Serializer,
+};
+
+// These are only used by tests that rely on the names and types from the
+// bundled reflection database.
+#[cfg(feature = "reflection_database")]
+use crate::{DecodeSink, DecodeWarning, UnknownEnumValues};
+#[cfg(feature = "reflection_database")]
+use rbx_dom_weak::types::{Color3, Color3uint8, Ref, VariantType};
+
+/// Records every event it receives so that tests can make assertions about
+/// the order they arrive in.
+#[cfg(feature = "reflection_database")]
+#[derive(Default)]
+struct RecordingSink {
+    events: Vec<String>,
+    names: Vec<Ref>,
+}
+
+#[cfg(feature = "reflection_database")]
+impl RecordingSink {
+    fn index_of(&self, referent: Ref) -> usize {
+        self.names.iter().position(|&r| r == referent).unwrap()
+    }
+}
+
+#[cfg(feature = "reflection_database")]
+impl DecodeSink for RecordingSink {
+    fn instance(&mut self, class: &str) -> Ref {
+        let referent = Ref::new();
+        self.events
+            .push(format!("instance {} {}", self.names.len(), class));
+        self.names.push(referent);
+        referent
+    }
+
+    fn name(&mut self, referent: Ref, name: &str) {
+        let index = self.index_of(referent);
+        self.events.push(format!("name {} {}", index, name));
+    }
+
+    fn property(&mut self, referent: Ref, name: &str, value: Variant) {
+        let index = self.index_of(referent);
+        let value = match value {
+            Variant::Ref(target) if target.is_some() => format!("-> {}", self.index_of(target)),
+            other => format!("{:?}", other),
+        };
+
+        self.events
+            .push(format!("property {} {} {}", index, name, value));
+    }
+
+    fn parent(&mut self, referent: Ref, parent: Ref) {
+        let index = self.index_of(referent);
+        let parent = if parent.is_none() {
+            "none".to_owned()
+        } else {
+            self.index_of(parent).to_string()
+        };
+
+        self.events.push(format!("parent {} {}", index, parent));
+    }
+}
+
+#[test]
+#[cfg(feature = "reflection_database")]
+fn deserialize_into_sink() {
+    let value = InstanceBuilder::new("StringValue")
+        .with_name("Greeting")
+        .with_property("Value", "Hello");
+    let object = InstanceBuilder::new("ObjectValue").with_property("Value", value.referent());
+
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_name("Root")
+            .with_children([value, object]),
+    );
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    let mut sink = RecordingSink::default();
+    Deserializer::new()
+        .deserialize_into(buffer.as_slice(), &mut sink)
+        .unwrap();
+
+    insta::assert_yaml_snapshot!(sink.events);
+}
+
+/// Ensures that a class filter skips the properties of excluded classes while
+/// still building the rest of the tree.
+#[test]
+#[cfg(feature = "reflection_database")]
+fn class_filter() {
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_name("Root")
+            .with_child(
+                InstanceBuilder::new("StringValue")
+                    .with_name("Kept")
+                    .with_property("Value", "Hello"),
+            )
+            .with_child(
+                InstanceBuilder::new("IntValue")
+                    .with_name("Skipped")
+                    .with_property("Value", 5i64),
+            ),
+    );
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    let (dom, report) = Deserializer::new()
+        .exclude_classes(["IntValue"])
+        .deserialize_with_report(buffer.as_slice())
+        .unwrap();
+
+    let root = dom.get_by_ref(dom.root().children()[0]).unwrap();
+    let kept = dom.get_by_ref(root.children()[0]).unwrap();
+    let skipped_instance = dom.get_by_ref(root.children()[1]).unwrap();
+
+    assert_eq!(kept.properties.get("Value"), Some(&Variant::from("Hello")));
+    assert_eq!(skipped_instance.name, "Skipped");
+    assert!(skipped_instance.properties.is_empty());
+
+    let skipped: Vec<_> = report
+        .skipped_properties()
+        .iter()
+        .map(|property| (property.class_name(), property.property_name()))
+        .collect();
+    assert!(skipped.contains(&("IntValue", "Value")));
+    assert!(skipped.iter().all(|(class, _)| *class == "IntValue"));
+}
+
+/// Ensures that a property filter only decodes the properties it accepts,
+/// using the names they have in the DOM.
+#[test]
+fn property_filter() {
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_name("Root")
+            .with_child(
+                InstanceBuilder::new("ModuleScript")
+                    .with_name("Module")
+                    .with_property("Source", "return nil"),
+            )
+            .with_child(
+                InstanceBuilder::new("Part")
+                    .with_name("Part")
+                    .with_property("Size", Vector3::new(1.0, 2.0, 3.0))
+                    .with_property("Anchored", true),
+            ),
+    );
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    let (dom, report) = Deserializer::new()
+        .include_properties(["Source", "Size"])
+        .deserialize_with_report(buffer.as_slice())
+        .unwrap();
+
+    let root = dom.get_by_ref(dom.root().children()[0]).unwrap();
+    let module = dom.get_by_ref(root.children()[0]).unwrap();
+    let part = dom.get_by_ref(root.children()[1]).unwrap();
+
+    assert_eq!(root.name, "Root");
+    assert!(root.properties.is_empty());
+    assert_eq!(module.name, "Module");
+    assert_eq!(
+        module.properties.keys().collect::<Vec<_>>(),
+        [&"Source".to_owned()]
+    );
+    assert_eq!(
+        part.properties.keys().collect::<Vec<_>>(),
+        [&"Size".to_owned()]
+    );
+
+    let skipped: Vec<_> = report
+        .skipped_properties()
+        .iter()
+        .map(|property| (property.class_name(), property.property_name()))
+        .collect();
+    assert!(skipped.contains(&("Part", "Anchored")));
+    assert!(!skipped
+        .iter()
+        .any(|(_, name)| *name == "Name" || *name == "size"));
+}
+
+#[test]
+#[cfg(feature = "reflection_database")]
+fn migrate_properties() {
+    let tree =
+        WeakDom::new(InstanceBuilder::new("TextLabel").with_property("Font", Enum::from_u32(4)));
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    let migrated = Deserializer::new().deserialize(buffer.as_slice()).unwrap();
+    let label = migrated.get_by_ref(migrated.root().children()[0]).unwrap();
+    assert!(matches!(
+        label.properties.get("FontFace"),
+        Some(Variant::Font(_))
+    ));
+    assert!(!label.properties.contains_key("Font"));
+
+    let unmigrated = Deserializer::new()
+        .migrate_properties(false)
+        .deserialize(buffer.as_slice())
+        .unwrap();
+    let label = unmigrated
+        .get_by_ref(unmigrated.root().children()[0])
+        .unwrap();
+    assert_eq!(
+        label.properties.get("Font"),
+        Some(&Variant::Enum(Enum::from_u32(4)))
+    );
+    assert!(!label.properties.contains_key("FontFace"));
+}
+
+/// Ensures that properties with a type rbx_binary doesn't know about survive a
+/// round trip when they're preserved.
+#[test]
+fn unknown_property_round_trip() {
+    let first = InstanceBuilder::new("StringValue").with_name("First");
+    let second = InstanceBuilder::new("StringValue").with_name("Second");
+    let refs = vec![second.referent(), first.referent()];
+
+    let tree = WeakDom::new(InstanceBuilder::new("Folder").with_children([first, second]));
+
+    let unknown = UnknownProperty {
+        class_name: "StringValue".to_owned(),
+        property_name: "FromTheFuture".to_owned(),
+        type_id: 0xfe,
+        referents: refs,
+        data: vec![1, 2, 3, 4],
+    };
+
+    let decode = |buffer: &[u8]| {
+        Deserializer::new()
+            .preserve_unknown_properties(true)
+            .deserialize_with_report(buffer)
+            .unwrap()
+    };
+
+    let mut buffer = Vec::new();
+    Serializer::new()
+        .unknown_properties(vec![unknown])
+        .serialize(&mut buffer, &tree, &[tree.root_ref()])
+        .unwrap();
+
+    let (dom, report) = decode(&buffer);
+    let unknown = &report.unknown_properties()[0];
+    let names: Vec<_> = unknown
+        .referents()
+        .iter()
+        .map(|&referent| dom.get_by_ref(referent).unwrap().name.as_str())
+        .collect();
+
+    assert_eq!(report.unknown_properties().len(), 1);
+    assert_eq!(unknown.property_name(), "FromTheFuture");
+    assert_eq!(unknown.type_id(), 0xfe);
+    assert_eq!(unknown.data(), &[1, 2, 3, 4]);
+    assert_eq!(names, ["Second", "First"]);
+
+    // Serializing the same instances again should keep the property...
+    let mut buffer = Vec::new();
+    Serializer::new()
+        .unknown_properties(report.clone().into_unknown_properties())
+        .serialize(&mut buffer, &dom, dom.root().children())
+        .unwrap();
+
+    let (_, round_tripped) = decode(&buffer);
+    assert_eq!(round_tripped.unknown_properties().len(), 1);
+    assert_eq!(round_tripped.unknown_properties()[0].data(), &[1, 2, 3, 4]);
+
+    // ...but serializing only some of them should drop it.
+    let folder = dom.get_by_ref(dom.root().children()[0]).unwrap();
+    let mut buffer = Vec::new();
+    Serializer::new()
+        .unknown_properties(report.into_unknown_properties())
+        .serialize(&mut buffer, &dom, &folder.children()[..1])
+        .unwrap();
+
+    let (_, dropped) = decode(&buffer);
+    assert!(dropped.unknown_properties().is_empty());
+}
+
+fn find_chunk(buffer: &[u8], name: &[u8; 4]) -> usize {
+    buffer
+        .windows(4)
+        .position(|window| window == name)
+        .expect("chunk not found")
+}
+
+fn recovery_test_tree() -> WeakDom {
+    WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_name("Root")
+            .with_child(InstanceBuilder::new("StringValue").with_property("Value", "Hello")),
+    )
+}
+
+/// Ensures that recovery mode accepts a file that was cut off before its PRNT
+/// chunk, and places the instances it found at the top level.
+#[test]
+fn recover_truncated() {
+    let tree = recovery_test_tree();
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    buffer.truncate(find_chunk(&buffer, b"PRNT"));
+
+    assert!(Deserializer::new().deserialize(buffer.as_slice()).is_err());
+
+    let (dom, report) = Deserializer::new()
+        .recover(true)
+        .deserialize_with_report(buffer.as_slice())
+        .unwrap();
+
+    let mut names: Vec<_> = dom
+        .root()
+        .children()
+        .iter()
+        .map(|&referent| dom.get_by_ref(referent).unwrap().name.as_str())
+        .collect();
+    names.sort_unstable();
+
+    assert_eq!(names, ["Root", "StringValue"]);
+    assert!(matches!(report.damage()[0], Damage::Truncated { .. }));
+    assert_eq!(report.damage()[1], Damage::MissingParents { count: 2 });
+}
+
+/// Ensures that recovery mode skips a chunk that can't be decompressed and
+/// keeps decoding the chunks after it.
+#[test]
+fn recover_corrupt_chunk() {
+    let tree = recovery_test_tree();
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    // Claim that the first PROP chunk is much larger than it really is.
+    let len_offset = find_chunk(&buffer, b"PROP") + 8;
+    buffer[len_offset + 3] = 0x7f;
+
+    assert!(Deserializer::new().deserialize(buffer.as_slice()).is_err());
+
+    let (dom, report) = Deserializer::new()
+        .recover(true)
+        .deserialize_with_report(buffer.as_slice())
+        .unwrap();
+
+    let root = dom.get_by_ref(dom.root().children()[0]).unwrap();
+    assert_eq!(root.children().len(), 1);
+
+    assert_eq!(report.damage().len(), 1);
+    assert!(matches!(
+        &report.damage()[0],
+        Damage::CorruptChunk { name, .. } if name == "PROP"
+    ));
+}
+
+/// Ensures that skipping a corrupt chunk in recovery mode doesn't throw off the
+/// offsets of the chunks after it.
+#[test]
+fn recover_corrupt_chunk_offsets() {
+    let mut buffer = uncompressed_test_file();
+
+    // Mark the PROP chunk as compressed without compressing it, so that it
+    // can't be decompressed but is still the same size in the file.
+    let prop_offset = find_chunk(&buffer, b"PROP");
+    let len = read_u32(&buffer, prop_offset + 8);
+    buffer[prop_offset + 4..prop_offset + 8].copy_from_slice(&len.to_le_bytes());
+
+    // Then limit the total decompressed size to what the chunks before the
+    // PRNT chunk add up to, not counting the corrupt one, so that decoding
+    // fails at the PRNT chunk even in recovery mode.
+    let prnt_offset = find_chunk(&buffer, b"PRNT");
+    let max_total_len = ChunkReader::new(buffer.as_slice())
+        .unwrap()
+        .map(Result::unwrap)
+        .filter(|chunk| chunk.offset() < prnt_offset as u64)
+        .filter(|chunk| chunk.offset() != prop_offset as u64)
+        .map(|chunk| chunk.uncompressed_len() as u64)
+        .sum();
+
+    let err = Deserializer::new()
+        .recover(true)
+        .limits(DecodeLimits::new().max_total_len(Some(max_total_len)))
+        .deserialize(buffer.as_slice())
+        .unwrap_err();
+
+    assert!(err.is_limit_exceeded(), "{}", err);
+    assert_eq!(err.offset(), Some(prnt_offset as u64));
+}
+
+/// Ensures that a PRNT chunk that gives an instance more than one parent is
+/// rejected instead of placing the instance in the tree twice.
+#[test]
+fn duplicate_parents() {
+    let mut buffer = uncompressed_test_file();
+
+    // Replace the PRNT chunk with one that gives the StringValue a second
+    // parent, at the top level of the file.
+    let mut contents = Vec::new();
+    contents.write_u8(0).unwrap();
+    contents.write_le_u32(3).unwrap();
+    contents
+        .write_referent_array([0, 1, 1].iter().copied())
+        .unwrap();
+    contents
+        .write_referent_array([-1, 0, -1].iter().copied())
+        .unwrap();
+
+    let prnt_offset = find_chunk(&buffer, b"PRNT");
+    let old_len = read_u32(&buffer, prnt_offset + 8) as usize;
+    buffer[prnt_offset + 8..prnt_offset + 12]
+        .copy_from_slice(&(contents.len() as u32).to_le_bytes());
+    buffer.splice(
+        prnt_offset + 16..prnt_offset + 16 + old_len,
+        contents.iter().copied(),
+    );
+
+    let err = Deserializer::new()
+        .deserialize(buffer.as_slice())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::DuplicateReferent);
+
+    let (dom, report) = Deserializer::new()
+        .recover(true)
+        .deserialize_with_report(buffer.as_slice())
+        .unwrap();
+
+    assert_eq!(dom.root().children().len(), 1);
+    let root = dom.get_by_ref(dom.root().children()[0]).unwrap();
+    assert_eq!(root.name, "Root");
+    assert_eq!(root.children().len(), 1);
+
+    assert_eq!(report.damage(), [Damage::DuplicateParents { count: 1 }]);
+}
+
+/// Ensures that an instance declared with a referent that's already in use
+/// is reported instead of silently replacing the earlier instance, and is
+/// rejected in strict mode.
+#[test]
+fn duplicate_referents() {
+    let mut buffer = uncompressed_test_file();
+
+    // Give the StringValue the referent of the Folder. Its referent is the
+    // last thing in its INST chunk, after the type ID, the type name, the
+    // object format and the instance count.
+    let inst_offset = ChunkReader::new(buffer.as_slice())
+        .unwrap()
+        .map(Result::unwrap)
+        .filter(|chunk| chunk.kind() == ChunkKind::Instances)
+        .map(|chunk| chunk.offset() as usize)
+        .find(|&offset| buffer[offset + 24..].starts_with(b"StringValue"))
+        .unwrap();
+    let referent_offset = inst_offset + 16 + 8 + "StringValue".len() + 1 + 4;
+
+    let mut referent = Vec::new();
+    referent.write_referent_array([0].iter().copied()).unwrap();
+    buffer[referent_offset..referent_offset + 4].copy_from_slice(&referent);
+
+    // Then replace the PRNT chunk with one that only places that referent at
+    // the top level of the file.
+    let mut contents = Vec::new();
+    contents.write_u8(0).unwrap();
+    contents.write_le_u32(1).unwrap();
+    contents.write_referent_array([0].iter().copied()).unwrap();
+    contents.write_referent_array([-1].iter().copied()).unwrap();
+
+    let prnt_offset = find_chunk(&buffer, b"PRNT");
+    let old_len = read_u32(&buffer, prnt_offset + 8) as usize;
+    buffer[prnt_offset + 8..prnt_offset + 12]
+        .copy_from_slice(&(contents.len() as u32).to_le_bytes());
+    buffer.splice(
+        prnt_offset + 16..prnt_offset + 16 + old_len,
+        contents.iter().copied(),
+    );
+
+    let err = Deserializer::new()
+        .strict(true)
+        .deserialize(buffer.as_slice())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::DuplicateReferent);
+
+    let (dom, report) = Deserializer::new()
+        .deserialize_with_report(buffer.as_slice())
+        .unwrap();
+
+    // The Folder lost its referent to the StringValue, so it was never given
+    // a parent.
+    let string_value = match dom.root().children() {
+        [child] => dom.get_by_ref(*child).unwrap(),
+        other => panic!("expected one instance, got {}", other.len()),
+    };
+    assert_eq!(string_value.class, "StringValue");
+
+    match report.warnings() {
+        [crate::DecodeWarning::DuplicateReferent {
+            class_name,
+            id,
+            referent,
+            offset,
+            ..
+        }] => {
+            assert_eq!(class_name, "StringValue");
+            assert_eq!(*id, 0);
+            assert_eq!(*referent, string_value.referent());
+            assert_eq!(*offset, inst_offset as u64);
+        }
+        other => panic!("unexpected warnings {:?}", other),
+    }
+}
+
+/// Ensures that problems that don't stop a file from being decoded are
+/// reported as warnings, along with where in the file they were found.
+#[test]
+#[cfg(feature = "reflection_database")]
+fn warnings() {
+    let unknown = InstanceBuilder::new("NotARealClass");
+    let string_value = InstanceBuilder::new("StringValue");
+    let part = InstanceBuilder::new("Part").with_property("Shape", Enum::from_u32(99));
+    let string_value_ref = string_value.referent();
+
+    let tree =
+        WeakDom::new(InstanceBuilder::new("Folder").with_children([unknown, string_value, part]));
+
+    // StringValue.Value stored as a Bool can't be decoded into a string.
+    let mismatched = UnknownProperty {
+        class_name: "StringValue".to_owned(),
+        property_name: "Value".to_owned(),
+        type_id: 0x02,
+        referents: vec![string_value_ref],
+        data: vec![1],
+    };
+
+    let mut buffer = Vec::new();
+    Serializer::new()
+        .unknown_properties(vec![mismatched])
+        .serialize(&mut buffer, &tree, &[tree.root_ref()])
+        .unwrap();
+
+    let chunk_offsets = |kind| -> Vec<u64> {
+        ChunkReader::new(buffer.as_slice())
+            .unwrap()
+            .map(Result::unwrap)
+            .filter(|chunk| chunk.kind() == kind)
+            .map(|chunk| chunk.offset())
+            .collect()
+    };
+    let inst_offsets = chunk_offsets(ChunkKind::Instances);
+    let prop_offsets = chunk_offsets(ChunkKind::Properties);
+
+    let (dom, report) = Deserializer::new()
+        .deserialize_with_report(buffer.as_slice())
+        .unwrap();
+
+    let folder = dom.get_by_ref(dom.root().children()[0]).unwrap();
+    assert_eq!(folder.children().len(), 3);
+    let classes: Vec<_> = folder
+        .children()
+        .iter()
+        .map(|&referent| dom.get_by_ref(referent).unwrap().class.as_str())
+        .collect();
+    assert_eq!(classes, ["NotARealClass", "StringValue", "Part"]);
+
+    let string_value = dom.get_by_ref(folder.children()[1]).unwrap();
+    assert!(!string_value.properties.contains_key("Value"));
+
+    let part = dom.get_by_ref(folder.children()[2]).unwrap();
+    assert_eq!(
+        part.properties.get("Shape"),
+        Some(&Variant::Enum(Enum::from_u32(99)))
+    );
+
+    assert_eq!(report.warnings().len(), 3);
+    for warning in report.warnings() {
+        match warning {
+            DecodeWarning::UnknownClass {
+                class_name,
+                referents,
+                offset,
+            } => {
+                assert_eq!(class_name, "NotARealClass");
+                assert_eq!(referents, &[folder.children()[0]]);
+                assert!(inst_offsets.contains(offset));
+            }
+            DecodeWarning::PropTypeMismatch {
+                class_name,
+                property_name,
+                type_id,
+                expected_type,
+                referents,
+                offset,
+            } => {
+                assert_eq!(class_name, "StringValue");
+                assert_eq!(property_name, "Value");
+                assert_eq!(*type_id, 0x02);
+                assert_eq!(*expected_type, VariantType::String);
+                assert_eq!(referents, &[folder.children()[1]]);
+                assert!(prop_offsets.contains(offset));
+            }
+            DecodeWarning::UnknownEnumValue {
+                class_name,
+                enum_name,
+                value,
+                referent,
+                offset,
+                ..
+            } => {
+                assert_eq!(class_name, "Part");
+                assert_eq!(enum_name, "PartType");
+                assert_eq!(*value, 99);
+                assert_eq!(*referent, folder.children()[2]);
+                assert!(prop_offsets.contains(offset));
+            }
+            other => panic!("unexpected warning {:?}", other),
+        }
+    }
+}
+
+/// Ensures that enum values missing from the reflection database are handled
+/// the way the deserializer is configured to.
+#[test]
+#[cfg(feature = "reflection_database")]
+fn unknown_enum_values() {
+    let tree =
+        WeakDom::new(InstanceBuilder::new("Part").with_property("Shape", Enum::from_u32(99)));
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    let (dom, report) = Deserializer::new()
+        .unknown_enum_values(UnknownEnumValues::Default)
+        .deserialize_with_report(buffer.as_slice())
+        .unwrap();
+
+    // Parts are blocks by default.
+    let part = dom.get_by_ref(dom.root().children()[0]).unwrap();
+    assert_eq!(
+        part.properties.get("Shape"),
+        Some(&Variant::Enum(Enum::from_u32(1)))
+    );
+
+    match report.warnings() {
+        [DecodeWarning::UnknownEnumValue {
+            value,
+            replaced_with,
+            ..
+        }] => {
+            assert_eq!(*value, 99);
+            assert_eq!(*replaced_with, Some(1));
+        }
+        other => panic!("unexpected warnings {:?}", other),
+    }
+
+    let result = Deserializer::new()
+        .unknown_enum_values(UnknownEnumValues::Error)
+        .deserialize(buffer.as_slice());
+    assert!(result.is_err());
+}
+
+/// Ensures that with reflection turned off, properties keep the names and
+/// types they were stored with, and survive a round trip back to a file.
+#[test]
+#[cfg(feature = "reflection_database")]
+fn no_reflection_round_trip() {
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_child(
+                InstanceBuilder::new("Part")
+                    .with_property("Size", Vector3::new(1.0, 2.0, 3.0))
+                    .with_property("Color", Color3::new(1.0, 0.0, 0.0)),
+            )
+            .with_child(InstanceBuilder::new("StringValue").with_property("Value", "Hello")),
+    );
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    let raw = Deserializer::new()
+        .reflection(false)
+        .deserialize(buffer.as_slice())
+        .unwrap();
+
+    let folder = raw.get_by_ref(raw.root().children()[0]).unwrap();
+    let part = raw.get_by_ref(folder.children()[0]).unwrap();
+    let string_value = raw.get_by_ref(folder.children()[1]).unwrap();
+
+    assert_eq!(
+        part.properties.get("size"),
+        Some(&Variant::Vector3(Vector3::new(1.0, 2.0, 3.0)))
+    );
+    assert!(!part.properties.contains_key("Size"));
+    assert_eq!(
+        part.properties.get("Color3uint8"),
+        Some(&Variant::Color3uint8(Color3uint8::new(255, 0, 0)))
+    );
+    assert_eq!(
+        string_value.properties.get("Value"),
+        Some(&Variant::BinaryString(BinaryString::from(
+            b"Hello".to_vec()
+        )))
+    );
+
+    let mut buffer = Vec::new();
+    Serializer::new()
+        .reflection(false)
+        .serialize(&mut buffer, &raw, raw.root().children())
+        .unwrap();
+
+    let dom = Deserializer::new().deserialize(buffer.as_slice()).unwrap();
+    let folder = dom.get_by_ref(dom.root().children()[0]).unwrap();
+    let part = dom.get_by_ref(folder.children()[0]).unwrap();
+    let string_value = dom.get_by_ref(folder.children()[1]).unwrap();
+
+    assert_eq!(
+        part.properties.get("Size"),
+        Some(&Variant::Vector3(Vector3::new(1.0, 2.0, 3.0)))
+    );
+    assert_eq!(
+        part.properties.get("Color"),
+        Some(&Variant::Color3uint8(Color3uint8::new(255, 0, 0)))
+    );
+    assert_eq!(
+        string_value.properties.get("Value"),
+        Some(&Variant::String("Hello".to_owned()))
+    );
+}
+
+/// Ensures that raw decoding gives back exactly what's on disk, including the
+/// properties of classes no reflection database knows about. This doesn't
+/// need the bundled database, so it also runs without it.
+#[test]
+fn raw_custom_classes() {
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_child(InstanceBuilder::new("TextLabel").with_property("Font", Enum::from_u32(4)))
+            .with_child(InstanceBuilder::new("ForkOnlyClass").with_property("Charge", 1.5f32)),
+    );
+
+    let mut buffer = Vec::new();
+    Serializer::new()
+        .reflection(false)
+        .serialize(&mut buffer, &tree, &[tree.root_ref()])
+        .unwrap();
+
+    let (raw, report) = Deserializer::new()
+        .reflection(false)
+        .deserialize_with_report(buffer.as_slice())
+        .unwrap();
+    assert!(report.warnings().is_empty());
+
+    let folder = raw.get_by_ref(raw.root().children()[0]).unwrap();
+    let label = raw.get_by_ref(folder.children()[0]).unwrap();
+    let custom = raw.get_by_ref(folder.children()[1]).unwrap();
+
+    assert_eq!(
+        label.properties.get("Font"),
+        Some(&Variant::Enum(Enum::from_u32(4)))
+    );
+    assert!(!label.properties.contains_key("FontFace"));
+    assert_eq!(custom.class, "ForkOnlyClass");
+    assert_eq!(
+        custom.properties.get("Charge"),
+        Some(&Variant::Float32(1.5))
+    );
+}
+
+/// Ensures that ChunkReader reports every chunk in a file, along with offsets
+/// and sizes that account for the whole file.
+#[test]
+fn chunk_reader() {
+    let tree = recovery_test_tree();
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    let reader = ChunkReader::new(buffer.as_slice()).unwrap();
+    assert_eq!(reader.num_types(), 2);
+    assert_eq!(reader.num_instances(), 2);
+
+    let chunks: Vec<_> = reader.map(Result::unwrap).collect();
+    let kinds: Vec<_> = chunks.iter().map(|chunk| chunk.kind()).collect();
+    assert_eq!(kinds.first(), Some(&ChunkKind::Instances));
+    assert_eq!(kinds.last(), Some(&ChunkKind::End));
+    assert!(kinds.contains(&ChunkKind::Properties));
+    assert!(kinds.contains(&ChunkKind::Parents));
+
+    let mut expected_offset = 32;
+    for chunk in &chunks {
+        assert_eq!(chunk.offset(), expected_offset);
+        assert!(chunk.contents().is_none());
+
+        let stored_len = if chunk.is_compressed() {
+            chunk.compressed_len()
+        } else {
+            chunk.uncompressed_len()
+        };
+        expected_offset += 16 + stored_len as u64;
+    }
+    assert_eq!(expected_offset, buffer.len() as u64);
+
+    let with_contents = ChunkReader::new(buffer.as_slice())
+        .unwrap()
+        .read_contents(true);
+    for chunk in with_contents {
+        let chunk = chunk.unwrap();
+        assert_eq!(
+            chunk.contents().map(<[u8]>::len),
+            Some(chunk.uncompressed_len() as usize)
+        );
+    }
+}
+
+/// Ensures that decoding from a slice gives the same tree as decoding from a
+/// reader, whether or not the chunks are compressed.
+#[test]
+fn deserialize_slice() {
+    let tree = WeakDom::new(InstanceBuilder::new("Folder").with_children(
+        (0..50).map(|_| InstanceBuilder::new("StringValue").with_property("Value", "Hello")),
+    ));
+
+    for compression in [
+        CompressionOptions::new(),
+        CompressionOptions::uncompressed(),
+    ] {
+        let mut buffer = Vec::new();
+        Serializer::new()
+            .compression(compression)
+            .serialize(&mut buffer, &tree, &[tree.root_ref()])
+            .unwrap();
+
+        let expected = crate::from_reader(buffer.as_slice()).unwrap();
+        let actual = crate::from_slice(&buffer).unwrap();
+
+        assert_eq!(
+            format!("{:?}", DomViewer::new().view_children(&expected)),
+            format!("{:?}", DomViewer::new().view_children(&actual)),
+        );
+
+        let truncated = &buffer[..find_chunk(&buffer, b"PRNT") + 20];
+        assert!(crate::from_slice(truncated).is_err());
+        assert!(crate::from_slice(&buffer[..10]).is_err());
+    }
+}
+
+/// Ensures that warnings reach the caller through every entry point that
+/// returns a report, not just `deserialize_with_report`.
+#[test]
+#[cfg(feature = "reflection_database")]
+fn report_from_every_entry_point() {
+    let tree = WeakDom::new(InstanceBuilder::new("NotARealClass"));
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    let is_unknown_class = |warnings: &[DecodeWarning]| matches!(warnings, [DecodeWarning::UnknownClass { class_name, .. }] if class_name == "NotARealClass");
+
+    let mut sink = RecordingSink::default();
+    let report = Deserializer::new()
+        .deserialize_into_with_report(buffer.as_slice(), &mut sink)
+        .unwrap();
+    assert!(is_unknown_class(report.warnings()));
+
+    let (dom, report) = Deserializer::new()
+        .deserialize_slice_with_report(&buffer)
+        .unwrap();
+    assert_eq!(dom.root().children().len(), 1);
+    assert!(is_unknown_class(report.warnings()));
+}
+
+#[test]
+fn progress() {
+    let dom = WeakDom::new(InstanceBuilder::new("Folder").with_children([
+        InstanceBuilder::new("Part"),
+        InstanceBuilder::new("Part"),
+        InstanceBuilder::new("Model"),
+    ]));
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &dom, &[dom.root_ref()]).unwrap();
+
+    let updates = Mutex::new(Vec::new());
+    Deserializer::new()
+        .on_progress(|progress| updates.lock().unwrap().push(*progress))
+        .deserialize(buffer.as_slice())
+        .unwrap();
+
+    let updates = updates.into_inner().unwrap();
+    let last = updates.last().unwrap();
+
+    assert_eq!(last.chunk(), ChunkKind::End);
+    assert_eq!(last.bytes_read(), buffer.len() as u64);
+    assert_eq!(last.instances_decoded(), 4);
+    assert_eq!(last.total_instances(), 4);
+
+    assert_eq!(
+        updates.len(),
+        ChunkReader::new(buffer.as_slice()).unwrap().count()
+    );
+    assert!(updates
+        .windows(2)
+        .all(|pair| pair[0].bytes_read() < pair[1].bytes_read()));
+}
+
+#[test]
+fn cancel() {
+    let dom = WeakDom::new(InstanceBuilder::new("Folder").with_child(InstanceBuilder::new("Part")));
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &dom, &[dom.root_ref()]).unwrap();
+
+    let token = Arc::new(AtomicBool::new(false));
+    let chunks_decoded = AtomicUsize::new(0);
+
+    let result = Deserializer::new()
+        .cancel_token(Arc::clone(&token))
+        .on_progress(|_| {
+            chunks_decoded.fetch_add(1, Ordering::Relaxed);
+            token.store(true, Ordering::Relaxed);
+        })
+        .deserialize(buffer.as_slice());
+
+    assert!(result.unwrap_err().is_cancelled());
+    assert_eq!(chunks_decoded.into_inner(), 1);
+
+    // Errors unrelated to cancellation aren't reported as cancelled.
+    let error = Deserializer::new().deserialize(&buffer[..10]).unwrap_err();
+    assert!(!error.is_cancelled());
+}
+
+#[test]
+fn stats() {
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_child(InstanceBuilder::new("Part").with_property("Anchored", true))
+            .with_child(InstanceBuilder::new("Part").with_property("Anchored", false))
+            .with_child(
+                InstanceBuilder::new("BinaryStringValue")
+                    .with_property("Value", BinaryString::from(vec![1; 100])),
+            ),
+    );
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    let (_, report) = Deserializer::new()
+        .deserialize_with_report(buffer.as_slice())
+        .unwrap();
+    assert!(report.stats().is_none());
+
+    let (_, report) = Deserializer::new()
+        .collect_stats(true)
+        .deserialize_with_report(buffer.as_slice())
+        .unwrap();
+    let stats = report.stats().unwrap();
+
+    let chunks: Vec<_> = ChunkReader::new(buffer.as_slice())
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(stats.chunks().len(), chunks.len());
+
+    for (stats, chunk) in stats.chunks().iter().zip(&chunks) {
+        assert_eq!(stats.kind(), chunk.kind());
+        assert_eq!(stats.offset(), chunk.offset());
+        assert_eq!(stats.uncompressed_len(), chunk.uncompressed_len() as usize);
+        assert!(stats.compression_ratio() > 0.0);
+    }
+
+    let parts = &stats.classes()["Part"];
+    assert_eq!(parts.instances(), 2);
+    assert!(parts.properties() > 1);
+    assert_eq!(parts.property_values(), parts.properties() * 2);
+
+    assert_eq!(stats.classes()["Folder"].instances(), 1);
+    assert!(stats.total_time() >= stats.chunks().iter().map(|c| c.decode_time()).sum());
+}
+
+#[test]
+fn limits() {
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_child(InstanceBuilder::new("StringValue").with_property("Value", "a".repeat(100)))
+            .with_child(InstanceBuilder::new("Folder")),
+    );
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    let decode = |limits: DecodeLimits| {
+        Deserializer::new()
+            .limits(limits)
+            .deserialize(buffer.as_slice())
+    };
+    let assert_exceeded = |limits: DecodeLimits| {
+        let err = decode(limits).unwrap_err();
+        assert!(err.is_limit_exceeded(), "unexpected error {}", err);
+
+        // Limits are enforced no matter how the file is read, and can't be
+        // recovered from.
+        let err = Deserializer::new()
+            .limits(limits)
+            .recover(true)
+            .deserialize_slice(&buffer)
+            .unwrap_err();
+        assert!(err.is_limit_exceeded(), "unexpected error {}", err);
+    };
+
+    decode(DecodeLimits::new()).unwrap();
+    decode(
+        DecodeLimits::new()
+            .max_instances(Some(3))
+            .max_string_len(Some(100))
+            .max_chunk_len(Some(1024))
+            .max_total_len(Some(4096)),
+    )
+    .unwrap();
+
+    assert_exceeded(DecodeLimits::new().max_instances(Some(2)));
+    assert_exceeded(DecodeLimits::new().max_string_len(Some(99)));
+    assert_exceeded(DecodeLimits::new().max_chunk_len(Some(16)));
+    assert_exceeded(DecodeLimits::new().max_total_len(Some(64)));
+}
+
+/// Ensures that ChunkReader checks chunk headers against its limits before
+/// reading and decompressing their contents.
+#[test]
+fn chunk_reader_limits() {
+    let tree = recovery_test_tree();
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    let read_all = |limits: DecodeLimits, read_contents: bool| {
+        ChunkReader::new(buffer.as_slice())
+            .unwrap()
+            .limits(limits)
+            .read_contents(read_contents)
+            .collect::<Result<Vec<_>, _>>()
+    };
+
+    read_all(DecodeLimits::new(), true).unwrap();
+
+    let err = read_all(DecodeLimits::new().max_chunk_len(Some(16)), true).unwrap_err();
+    assert!(err.is_limit_exceeded(), "unexpected error {}", err);
+
+    let err = read_all(DecodeLimits::new().max_total_len(Some(64)), true).unwrap_err();
+    assert!(err.is_limit_exceeded(), "unexpected error {}", err);
+
+    // Nothing is decompressed when contents are skipped, so there's nothing
+    // to limit.
+    read_all(DecodeLimits::new().max_chunk_len(Some(16)), false).unwrap();
+
+    // A header claiming a huge chunk is rejected instead of being trusted.
+    let mut huge = buffer.clone();
+    huge[32 + 8..32 + 12].copy_from_slice(&u32::MAX.to_le_bytes());
+    let err = ChunkReader::new(huge.as_slice())
+        .unwrap()
+        .limits(DecodeLimits::new().max_chunk_len(Some(1024 * 1024)))
+        .read_contents(true)
+        .next()
+        .unwrap()
+        .unwrap_err();
+    assert!(err.is_limit_exceeded(), "unexpected error {}", err);
+}
+
+/// Ensures that a file header claiming an absurd number of instances is
+/// rejected before any memory is set aside for them.
+#[test]
+fn limits_huge_header() {
+    let tree = WeakDom::new(InstanceBuilder::new("Folder"));
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    // The instance count follows the magic number, signature, version, and
+    // type count in the header.
+    buffer[20..24].copy_from_slice(&u32::MAX.to_le_bytes());
+
+    let err = Deserializer::new()
+        .limits(DecodeLimits::new().max_instances(Some(1000)))
+        .deserialize(buffer.as_slice())
+        .unwrap_err();
+    assert!(err.is_limit_exceeded());
+}
+
+fn read_u32(buffer: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&buffer[offset..offset + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+fn uncompressed_test_file() -> Vec<u8> {
+    let tree = recovery_test_tree();
+    let mut buffer = Vec::new();
+    Serializer::new()
+        .compression(CompressionOptions::uncompressed())
+        .serialize(&mut buffer, &tree, &[tree.root_ref()])
+        .unwrap();
+    buffer
+}
+
+/// Ensures that errors describe which chunk they happened in and where.
+#[test]
+fn error_location() {
+    let mut buffer = uncompressed_test_file();
+    let prnt_offset = find_chunk(&buffer, b"PRNT");
+
+    // Give the PRNT chunk a version we don't know about. The version is the
+    // first byte after the 16 byte chunk header.
+    buffer[prnt_offset + 16] = 1;
+
+    let prnt_index = ChunkReader::new(buffer.as_slice())
+        .unwrap()
+        .position(|chunk| chunk.unwrap().kind() == ChunkKind::Parents)
+        .unwrap();
+
+    let err = Deserializer::new()
+        .deserialize(buffer.as_slice())
+        .unwrap_err();
+
+    assert_eq!(err.chunk_index(), Some(prnt_index));
+    assert_eq!(err.offset(), Some(prnt_offset as u64));
+    assert_eq!(err.position_in_chunk(), Some(1));
+    assert!(err.to_string().contains("PRNT chunk"), "{}", err);
+    assert_eq!(err.code(), ErrorCode::UnknownVersion);
+    assert_eq!(
+        DomError::location(&err),
+        Some(ErrorLocation::Chunk {
+            index: prnt_index,
+            offset: prnt_offset as u64,
+            position: Some(1),
+        })
+    );
+
+    // PROP chunks are described by the property they contain. Making the
+    // first value of a String PROP chunk longer than the chunk makes it run
+    // off the end. The value comes after the type ID, the property name and
+    // the value type.
+    let mut buffer = uncompressed_test_file();
+    let prop_offset = find_chunk(&buffer, b"PROP") + 16;
+    let name_len = read_u32(&buffer, prop_offset + 4) as usize;
+    let value_offset = prop_offset + 8 + name_len + 1;
+    buffer[value_offset..value_offset + 4].copy_from_slice(&1000u32.to_le_bytes());
+
+    let err = Deserializer::new()
+        .deserialize(buffer.as_slice())
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("PROP chunk for Folder.Name"),
+        "{}",
+        err
+    );
+    // Decoding stops after the length, where the string's contents begin.
+    assert_eq!(
+        err.position_in_chunk(),
+        Some(value_offset - prop_offset + 4)
+    );
+    assert_eq!(err.code(), ErrorCode::UnexpectedEof);
+    assert_eq!(err.class_name(), Some("Folder"));
+    assert_eq!(err.property_name(), Some("Name"));
+}
+
+/// Ensures that strict mode rejects chunks with leftover bytes, which are
+/// otherwise ignored.
+#[test]
+#[cfg(feature = "reflection_database")]
+fn custom_reflection_database() {
+    let mut database = rbx_reflection::ReflectionDatabase::new();
+    database.classes.insert(
+        "Instance".into(),
+        rbx_reflection::ClassDescriptor::new("Instance"),
+    );
+
+    let dom = WeakDom::new(InstanceBuilder::new("Folder").with_child(
+        InstanceBuilder::new("Part").with_property("Size", Vector3::new(1.0, 2.0, 3.0)),
+    ));
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &dom, dom.root().children()).unwrap();
+
+    let read_part = |deserializer: Deserializer| {
+        let decoded = deserializer.deserialize(buffer.as_slice()).unwrap();
+        let part = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+        part.properties.clone()
+    };
+
+    let bundled = read_part(Deserializer::new());
+    assert!(bundled.contains_key("Size"));
+
+    // The custom database doesn't know that Part.size is Part.Size.
+    let custom = read_part(Deserializer::new().reflection_database(&database));
+    assert!(!custom.contains_key("Size"));
+    assert!(custom.contains_key("size"));
+
+    let unreflected = read_part(
+        Deserializer::new()
+            .reflection_database(&database)
+            .reflection(false),
+    );
+    assert!(unreflected.contains_key("size"));
+}
+
+#[test]
+fn strict_trailing_data() {
+    let mut buffer = uncompressed_test_file();
+
+    // Add a byte to the end of the PRNT chunk, which is followed by END.
+    let prnt_offset = find_chunk(&buffer, b"PRNT");
+    let end_offset = find_chunk(&buffer, b"END\0");
+    buffer.insert(end_offset, 0);
+    buffer[prnt_offset + 8] += 1;
+
+    Deserializer::new().deserialize(buffer.as_slice()).unwrap();
+
+    let err = Deserializer::new()
+        .strict(true)
+        .deserialize(buffer.as_slice())
+        .unwrap_err();
+    assert!(err.to_string().contains("1 unexpected bytes"), "{}", err);
+    assert_eq!(err.offset(), Some(prnt_offset as u64));
+    assert_eq!(err.code(), ErrorCode::TrailingData);
+}
+
+/// Ensures that strict mode turns warnings into errors.
+#[test]
+#[cfg(feature = "reflection_database")]
+fn strict_warnings() {
+    let tree = WeakDom::new(InstanceBuilder::new("NotARealClass"));
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    Deserializer::new().deserialize(buffer.as_slice()).unwrap();
+
+    let err = Deserializer::new()
+        .strict(true)
+        .deserialize(buffer.as_slice())
+        .unwrap_err();
+    assert!(err.to_string().contains("UnknownClass"), "{}", err);
+    assert!(
+        err.to_string().contains("INST chunk for NotARealClass"),
+        "{}",
+        err
+    );
+    assert_eq!(err.code(), ErrorCode::StrictWarning);
+    assert_eq!(err.class_name(), Some("NotARealClass"));
+    assert_eq!(err.property_name(), None);
+}
+
+/// Ensures that counts that can't possibly fit in a chunk are rejected
+/// instead of being trusted to size allocations.
+#[test]
+fn huge_counts() {
+    let mut buffer = uncompressed_test_file();
+
+    // The instance count comes after the type ID, the type name and the
+    // object format.
+    let inst_offset = find_chunk(&buffer, b"INST") + 16;
+    let name_len = read_u32(&buffer, inst_offset + 4) as usize;
+    let count_offset = inst_offset + 8 + name_len + 1;
+    buffer[count_offset..count_offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+
+    let err = Deserializer::new()
+        .deserialize(buffer.as_slice())
+        .unwrap_err();
+    assert!(err.to_string().contains("can't fit"), "{}", err);
+    assert_eq!(err.code(), ErrorCode::CountTooLarge);
+}
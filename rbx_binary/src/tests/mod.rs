@@ -1,5 +1,13 @@
+#[cfg(feature = "tokio")]
+mod async_io;
+mod codec;
 mod core_read_write;
+mod csg;
+mod deserializer;
+#[cfg(feature = "reflection_database")]
 mod models;
+#[cfg(feature = "reflection_database")]
 mod places;
 mod serializer;
+#[cfg(feature = "reflection_database")]
 mod util;
@@ -1,9 +1,40 @@
+use std::collections::HashMap;
+
 use rbx_dom_weak::{
-    types::{Color3, Color3uint8, Ref, Region3, Vector3},
-    InstanceBuilder, WeakDom,
+    types::{
+        CFrame, Matrix3, Ref, Region3, SecurityCapabilities, SharedString, UniqueId, Variant,
+        Vector3,
+    },
+    Instance, InstanceBuilder, WeakDom,
+};
+
+use crate::{
+    from_reader,
+    text_deserializer::{DecodedChunk, DecodedModel, DecodedValues},
+    to_writer, ChunkKind, ChunkReader, CompressionAlgorithm, CompressionOptions, Deserializer,
+    DomError, ErrorCode, ExternalReferences, NulledReference, Serializer,
 };
 
-use crate::{text_deserializer::DecodedModel, to_writer};
+// These are only used by tests that rely on the names and types from the
+// bundled reflection database.
+#[cfg(feature = "reflection_database")]
+use crate::PropertyOrder;
+#[cfg(feature = "reflection_database")]
+use rbx_dom_weak::types::{BinaryString, Color3, Color3uint8, Enum};
+
+/// Returns the names of all the properties in the model, in the order they
+/// were written.
+#[cfg(feature = "reflection_database")]
+fn prop_names(model: &DecodedModel) -> Vec<&str> {
+    model
+        .chunks
+        .iter()
+        .filter_map(|chunk| match chunk {
+            DecodedChunk::Prop { prop_name, .. } => Some(prop_name.as_str()),
+            _ => None,
+        })
+        .collect()
+}
 
 /// A basic test to make sure we can serialize the simplest instance: a Folder.
 #[test]
@@ -55,6 +86,7 @@ fn unknown_property() {
 ///
 /// This test will need to be updated once we implement the type used here.
 #[test]
+#[cfg(feature = "reflection_database")]
 fn unimplemented_type_known_property() {
     let tree = WeakDom::new(InstanceBuilder::new("UIListLayout").with_property(
         "Padding",
@@ -62,9 +94,12 @@ fn unimplemented_type_known_property() {
     ));
 
     let mut buffer = Vec::new();
-    let result = to_writer(&mut buffer, &tree, &[tree.root_ref()]);
+    let err = to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap_err();
 
-    assert!(result.is_err());
+    assert_eq!(err.code(), ErrorCode::PropertyTypeMismatch);
+    assert_eq!(err.referent(), Some(tree.root_ref()));
+    assert_eq!(err.class_name(), Some("UIListLayout"));
+    assert_eq!(err.property_name(), Some("Padding"));
 }
 
 /// Ensures that serializing a tree with an unimplemented property type AND an
@@ -94,9 +129,359 @@ fn unknown_id() {
     let tree = WeakDom::new(InstanceBuilder::new("Folder"));
 
     let mut buffer = Vec::new();
-    let result = to_writer(&mut buffer, &tree, &[Ref::new()]);
+    let referent = Ref::new();
+    let err = to_writer(&mut buffer, &tree, &[referent]).unwrap_err();
 
-    assert!(result.is_err());
+    assert_eq!(err.code(), ErrorCode::UnknownReferent);
+    assert_eq!(err.referent(), Some(referent));
+}
+
+/// Ensures that `to_vec` returns the same bytes as `to_writer`.
+#[test]
+fn serialize_to_vec() {
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_child(InstanceBuilder::new("StringValue").with_property("Value", "Hello")),
+    );
+
+    let mut expected = Vec::new();
+    to_writer(&mut expected, &tree, &[tree.root_ref()]).expect("failed to encode model");
+
+    let actual = crate::to_vec(&tree, &[tree.root_ref()]).expect("failed to encode model");
+    assert_eq!(expected, actual);
+}
+
+/// Ensures that chunks spilled to disk by a low spill threshold decode to the
+/// same model as chunks held in memory.
+#[test]
+fn spilled_chunks() {
+    let tree = WeakDom::new(InstanceBuilder::new("Folder").with_children(
+        (0..50).map(|i| InstanceBuilder::new("StringValue").with_property("Value", i.to_string())),
+    ));
+
+    let mut in_memory = Vec::new();
+    to_writer(&mut in_memory, &tree, &[tree.root_ref()]).expect("failed to encode model");
+
+    let mut spilled = Vec::new();
+    Serializer::new()
+        .spill_threshold(Some(16))
+        .serialize(&mut spilled, &tree, &[tree.root_ref()])
+        .expect("failed to encode model");
+
+    assert_ne!(in_memory, spilled);
+
+    let expected = DecodedModel::from_reader(in_memory.as_slice());
+    let actual = DecodedModel::from_reader(spilled.as_slice());
+    assert_eq!(format!("{:?}", expected), format!("{:?}", actual));
+}
+
+/// Ensures that only the chunks that grow past the spill threshold are written
+/// uncompressed, and that the rest still follow the compression settings.
+#[test]
+fn spilled_chunks_uncompressed() {
+    const THRESHOLD: usize = 256;
+
+    let tree = WeakDom::new(InstanceBuilder::new("Folder").with_children(
+        (0..50).map(|_| InstanceBuilder::new("StringValue").with_property("Value", "Hello")),
+    ));
+
+    let mut buffer = Vec::new();
+    Serializer::new()
+        .spill_threshold(Some(THRESHOLD))
+        .serialize(&mut buffer, &tree, &[tree.root_ref()])
+        .expect("failed to encode model");
+
+    let chunks: Vec<_> = ChunkReader::new(buffer.as_slice())
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    let (spilled, kept): (Vec<_>, Vec<_>) = chunks
+        .iter()
+        .filter(|chunk| chunk.kind() != ChunkKind::End)
+        .partition(|chunk| chunk.uncompressed_len() as usize > THRESHOLD);
+
+    assert!(!spilled.is_empty());
+    assert!(!kept.is_empty());
+    assert!(spilled.iter().all(|chunk| !chunk.is_compressed()));
+    assert!(kept.iter().all(|chunk| chunk.is_compressed()));
+}
+
+/// Ensures that every compression setting produces a file that decodes to the
+/// same model.
+#[test]
+fn compression_options() {
+    let tree = WeakDom::new(InstanceBuilder::new("Folder").with_children(
+        (0..50).map(|_| InstanceBuilder::new("StringValue").with_property("Value", "Hello")),
+    ));
+
+    let encode = |options: CompressionOptions| {
+        let mut buffer = Vec::new();
+        Serializer::new()
+            .compression(options)
+            .serialize(&mut buffer, &tree, &[tree.root_ref()])
+            .expect("failed to encode model");
+        buffer
+    };
+
+    let default = encode(CompressionOptions::new());
+    let uncompressed = encode(CompressionOptions::uncompressed());
+    let high = encode(CompressionOptions::new().level(Some(12)));
+    let threshold = encode(
+        CompressionOptions::new()
+            .algorithm(CompressionAlgorithm::Lz4)
+            .min_size(usize::MAX),
+    );
+
+    assert!(uncompressed.len() > default.len());
+    assert_eq!(uncompressed, threshold);
+
+    let expected = format!("{:?}", DecodedModel::from_reader(default.as_slice()));
+    for buffer in [&uncompressed, &high] {
+        let actual = format!("{:?}", DecodedModel::from_reader(buffer.as_slice()));
+        assert_eq!(expected, actual);
+    }
+}
+
+/// Ensures that chunks compressed with Zstandard are smaller than uncompressed
+/// ones and decode to the same model.
+#[test]
+#[cfg(feature = "zstd")]
+fn zstd_compression() {
+    let tree = WeakDom::new(InstanceBuilder::new("Folder").with_children(
+        (0..50).map(|_| InstanceBuilder::new("StringValue").with_property("Value", "Hello")),
+    ));
+
+    let encode = |options: CompressionOptions| {
+        let mut buffer = Vec::new();
+        Serializer::new()
+            .compression(options)
+            .serialize(&mut buffer, &tree, &[tree.root_ref()])
+            .expect("failed to encode model");
+        buffer
+    };
+
+    let uncompressed = encode(CompressionOptions::uncompressed());
+    let zstd = encode(CompressionOptions::new().algorithm(CompressionAlgorithm::Zstd));
+    let high = encode(
+        CompressionOptions::new()
+            .algorithm(CompressionAlgorithm::Zstd)
+            .level(Some(19)),
+    );
+
+    assert!(uncompressed.len() > zstd.len());
+
+    // The first chunk's contents come right after the file header and its own
+    // header, and should be a Zstandard frame.
+    assert_eq!(&zstd[48..52], &[0x28, 0xb5, 0x2f, 0xfd]);
+
+    let expected = format!("{:?}", DecodedModel::from_reader(uncompressed.as_slice()));
+    for buffer in [&zstd, &high] {
+        let actual = format!("{:?}", DecodedModel::from_reader(buffer.as_slice()));
+        assert_eq!(expected, actual);
+    }
+
+    let decoded = from_reader(zstd.as_slice()).unwrap();
+    assert_eq!(
+        decoded
+            .get_by_ref(decoded.root_ref())
+            .unwrap()
+            .children()
+            .len(),
+        1
+    );
+}
+
+/// Ensures that the serializer writes properties in the requested order.
+#[test]
+#[cfg(feature = "reflection_database")]
+fn property_order() {
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Part")
+            .with_property("Anchored", true)
+            .with_property("Shape", Enum::from_u32(1))
+            .with_property("AAA_Custom", "Hi"),
+    );
+
+    let encode = |order: PropertyOrder| {
+        let mut buffer = Vec::new();
+        Serializer::new()
+            .property_order(order)
+            .serialize(&mut buffer, &tree, &[tree.root_ref()])
+            .expect("failed to encode model");
+        DecodedModel::from_reader(buffer.as_slice())
+    };
+
+    let alphabetical = encode(PropertyOrder::Alphabetical);
+    let reflection = encode(PropertyOrder::Reflection);
+
+    assert_eq!(
+        prop_names(&alphabetical),
+        ["AAA_Custom", "Anchored", "Name", "shape"]
+    );
+    assert_eq!(
+        prop_names(&reflection),
+        ["Name", "Anchored", "shape", "AAA_Custom"]
+    );
+}
+
+/// Ensures that properties with their default value everywhere can be left
+/// out of the file.
+#[test]
+fn exclude_default_properties() {
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_child(InstanceBuilder::new("BoolValue").with_property("Value", false))
+            .with_child(InstanceBuilder::new("IntValue").with_property("Value", 5i64)),
+    );
+
+    let mut buffer = Vec::new();
+    Serializer::new()
+        .include_default_properties(false)
+        .serialize(&mut buffer, &tree, tree.root().children())
+        .expect("failed to encode model");
+
+    let model = DecodedModel::from_reader(buffer.as_slice());
+    let props: Vec<_> = model
+        .chunks
+        .iter()
+        .filter_map(|chunk| match chunk {
+            DecodedChunk::Prop {
+                type_id, prop_name, ..
+            } => Some((*type_id, prop_name.as_str())),
+            _ => None,
+        })
+        .collect();
+
+    // BoolValue is type 0 and IntValue is type 1. Only IntValue.Value differs
+    // from its default.
+    assert_eq!(props, [(0, "Name"), (1, "Name"), (1, "Value")]);
+}
+
+/// Ensures that SharedStrings below the dedup threshold are stored once per
+/// instance, while larger ones are still shared.
+#[test]
+fn shared_string_dedup_threshold() {
+    let small = SharedString::new(b"small".to_vec());
+    let large = SharedString::new(vec![7; 64]);
+
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder").with_children((0..3).map(|_| {
+            InstanceBuilder::new("Folder")
+                .with_property("Small", small.clone())
+                .with_property("Large", large.clone())
+        })),
+    );
+
+    let encode = |threshold: usize| {
+        let mut buffer = Vec::new();
+        Serializer::new()
+            .shared_string_dedup_threshold(threshold)
+            .serialize(&mut buffer, &tree, tree.root().children())
+            .expect("failed to encode model");
+        DecodedModel::from_reader(buffer.as_slice())
+    };
+
+    let sstr_len = |model: &DecodedModel| {
+        model
+            .chunks
+            .iter()
+            .find_map(|chunk| match chunk {
+                DecodedChunk::Sstr { entries, .. } => Some(entries.len()),
+                _ => None,
+            })
+            .unwrap()
+    };
+
+    assert_eq!(sstr_len(&encode(0)), 2);
+    assert_eq!(sstr_len(&encode(32)), 4);
+    assert_eq!(sstr_len(&encode(usize::MAX)), 6);
+}
+
+/// Ensures that deterministic output is byte-identical between runs, even when
+/// the serializer has to generate default values.
+#[test]
+fn deterministic_output() {
+    // The second StringValue is missing a UniqueId, so one will be generated.
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_child(
+                InstanceBuilder::new("StringValue")
+                    .with_property("UniqueId", UniqueId::new(1, 2, 3)),
+            )
+            .with_child(InstanceBuilder::new("StringValue"))
+            .with_child(InstanceBuilder::new("BoolValue")),
+    );
+
+    let encode = || {
+        let mut buffer = Vec::new();
+        Serializer::new()
+            .deterministic(true)
+            .serialize(&mut buffer, &tree, tree.root().children())
+            .expect("failed to encode model");
+        buffer
+    };
+
+    let first = encode();
+    assert_eq!(first, encode());
+
+    // Type IDs are assigned alphabetically, even though StringValue was found
+    // first.
+    let model = DecodedModel::from_reader(first.as_slice());
+    let types: Vec<_> = model
+        .chunks
+        .iter()
+        .filter_map(|chunk| match chunk {
+            DecodedChunk::Inst {
+                type_id, type_name, ..
+            } => Some((*type_id, type_name.as_str())),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(types, [(0, "BoolValue"), (1, "StringValue")]);
+}
+
+/// Ensures that deterministic output gives each instance that's missing a
+/// UniqueId a different one.
+#[test]
+fn deterministic_unique_ids() {
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_child(
+                InstanceBuilder::new("StringValue")
+                    .with_property("UniqueId", UniqueId::new(1, 2, 3)),
+            )
+            .with_child(InstanceBuilder::new("StringValue"))
+            .with_child(InstanceBuilder::new("StringValue")),
+    );
+
+    let mut buffer = Vec::new();
+    Serializer::new()
+        .deterministic(true)
+        .serialize(&mut buffer, &tree, tree.root().children())
+        .expect("failed to encode model");
+
+    // Decoding into a WeakDom would replace duplicate UniqueIds, so this
+    // looks at what was actually written instead.
+    let model = DecodedModel::from_reader(buffer.as_slice());
+    let ids = model
+        .chunks
+        .iter()
+        .find_map(|chunk| match chunk {
+            DecodedChunk::Prop {
+                values: Some(DecodedValues::UniqueId(ids)),
+                ..
+            } => Some(ids),
+            _ => None,
+        })
+        .expect("no UniqueId property was written");
+
+    assert_eq!(ids.len(), 3);
+    assert!(ids.contains(&UniqueId::new(1, 2, 3)));
+    assert_ne!(ids[0], ids[1]);
+    assert_ne!(ids[1], ids[2]);
+    assert_ne!(ids[0], ids[2]);
 }
 
 /// Ensures that only one name for each logical property is serialized to a
@@ -106,6 +491,7 @@ fn unknown_id() {
 /// For fun, we also have a part with no size property at all. It should default
 /// to (4.0, 1.2, 2.0), a relic of Roblox's distant past.
 #[test]
+#[cfg(feature = "reflection_database")]
 fn logical_properties_basepart_size() {
     let tree = WeakDom::new(
         InstanceBuilder::new("Folder")
@@ -128,6 +514,7 @@ fn logical_properties_basepart_size() {
 /// Ensures that all valid combinations of color property names and
 /// value types are properly handled.
 #[test]
+#[cfg(feature = "reflection_database")]
 fn part_color() {
     let tree = WeakDom::new(
         InstanceBuilder::new("Folder")
@@ -153,3 +540,498 @@ fn part_color() {
     let decoded = DecodedModel::from_reader(buf.as_slice());
     insta::assert_yaml_snapshot!(decoded);
 }
+
+/// Ensures that SecurityCapabilities values survive a round trip through the
+/// binary format.
+#[test]
+fn security_capabilities() {
+    let capabilities = SecurityCapabilities::from_bits(0x8000_0000_0000_0301);
+    let tree =
+        WeakDom::new(InstanceBuilder::new("Folder").with_property("Capabilities", capabilities));
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).expect("failed to encode model");
+
+    let decoded = crate::from_reader(buffer.as_slice()).expect("failed to decode model");
+    let folder = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+
+    assert_eq!(
+        folder.properties.get("Capabilities"),
+        Some(&Variant::SecurityCapabilities(capabilities))
+    );
+}
+
+/// Ensures that Model pivots, including missing ones and ones with rotations
+/// that can't be written as a basic rotation ID, survive a round trip.
+#[test]
+fn optional_cframe_round_trip() {
+    let pivots = vec![
+        Some(CFrame::new(
+            Vector3::new(1.0, 2.0, 3.0),
+            Matrix3::from_basic_rotation_id(0x05).unwrap(),
+        )),
+        Some(CFrame::new(
+            Vector3::new(-4.0, 5.0, -6.0),
+            Matrix3::new(
+                Vector3::new(0.6, 0.0, 0.8),
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(-0.8, 0.0, 0.6),
+            ),
+        )),
+        None,
+    ];
+
+    let tree = WeakDom::new(InstanceBuilder::new("Folder").with_children(
+        pivots.iter().enumerate().map(|(i, pivot)| {
+            InstanceBuilder::new("Model")
+                .with_name(format!("Model{}", i))
+                .with_property("WorldPivotData", *pivot)
+        }),
+    ));
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, tree.root().children()).expect("failed to encode model");
+
+    let decoded = crate::from_reader(buffer.as_slice()).expect("failed to decode model");
+    let decoded_pivots: Vec<_> = decoded
+        .root()
+        .children()
+        .iter()
+        .map(|&referent| {
+            decoded
+                .get_by_ref(referent)
+                .unwrap()
+                .properties
+                .get("WorldPivotData")
+                .cloned()
+        })
+        .collect();
+
+    let expected: Vec<_> = pivots
+        .into_iter()
+        .map(|pivot| Some(Variant::OptionalCFrame(pivot)))
+        .collect();
+    assert_eq!(decoded_pivots, expected);
+}
+
+/// Ensures that CFrames with one of the 24 basic rotations are written with
+/// a one byte rotation ID instead of nine floats.
+#[test]
+fn cframe_basic_rotation_ids() {
+    let encode = |orientation: Matrix3| {
+        let tree = WeakDom::new(
+            InstanceBuilder::new("Folder").with_children((0..10).map(|_| {
+                InstanceBuilder::new("Part").with_property(
+                    "CFrame",
+                    CFrame::new(Vector3::new(1.0, 2.0, 3.0), orientation),
+                )
+            })),
+        );
+
+        let mut buffer = Vec::new();
+        Serializer::new()
+            .compression(CompressionOptions::uncompressed())
+            .serialize(&mut buffer, &tree, tree.root().children())
+            .expect("failed to encode model");
+        buffer
+    };
+
+    let basic = encode(Matrix3::from_basic_rotation_id(0x09).unwrap());
+    let general = encode(Matrix3::new(
+        Vector3::new(0.6, 0.0, 0.8),
+        Vector3::new(0.0, 1.0, 0.0),
+        Vector3::new(-0.8, 0.0, 0.6),
+    ));
+
+    assert_eq!(general.len() - basic.len(), 10 * 9 * 4);
+}
+
+/// Identical union data on several instances should be written once as a
+/// SharedString when promotion is enabled, while unique values are left
+/// alone.
+#[test]
+#[cfg(feature = "reflection_database")]
+fn shared_string_promotion() {
+    let repeated = BinaryString::from(vec![7; 1024]);
+    let unique = BinaryString::from(vec![9; 1024]);
+
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder").with_children([
+            InstanceBuilder::new("UnionOperation")
+                .with_name("A")
+                .with_property("MeshData", repeated.clone()),
+            InstanceBuilder::new("UnionOperation")
+                .with_name("B")
+                .with_property("MeshData", repeated.clone()),
+            InstanceBuilder::new("UnionOperation")
+                .with_name("C")
+                .with_property("MeshData", unique.clone()),
+        ]),
+    );
+
+    let encode = |serializer: Serializer| {
+        let mut buffer = Vec::new();
+        serializer
+            .compression(CompressionOptions::new().algorithm(CompressionAlgorithm::None))
+            .serialize(&mut buffer, &tree, &[tree.root_ref()])
+            .expect("failed to encode model");
+        buffer
+    };
+
+    let plain = encode(Serializer::new());
+    let promoted = encode(Serializer::new().shared_string_promotion_threshold(Some(512)));
+    assert!(promoted.len() + 512 < plain.len());
+
+    let decoded = crate::from_reader(promoted.as_slice()).expect("failed to decode model");
+    let folder = decoded.root().children()[0];
+
+    for &referent in decoded.get_by_ref(folder).unwrap().children() {
+        let union = decoded.get_by_ref(referent).unwrap();
+        let mesh_data = union.properties.get("MeshData");
+        let mesh_data_2 = union.properties.get("MeshData2");
+
+        if union.name == "C" {
+            assert_eq!(mesh_data, Some(&Variant::BinaryString(unique.clone())));
+        } else {
+            assert_eq!(mesh_data, Some(&Variant::BinaryString(BinaryString::new())));
+
+            match mesh_data_2 {
+                Some(Variant::SharedString(value)) => assert_eq!(value.data(), &[7; 1024][..]),
+                other => panic!("expected MeshData2 to be a SharedString, got {:?}", other),
+            }
+        }
+    }
+}
+
+/// Ensures that metadata given to the serializer is written to a META chunk
+/// at the start of the file, and is read back by the deserializer.
+#[test]
+fn metadata_round_trip() {
+    let tree = WeakDom::new(InstanceBuilder::new("Folder"));
+
+    let mut metadata = HashMap::new();
+    metadata.insert("ExplicitAutoJoints".to_owned(), "true".to_owned());
+    metadata.insert("SomethingElse".to_owned(), "Hello".to_owned());
+
+    let mut buffer = Vec::new();
+    Serializer::new()
+        .metadata(metadata.clone())
+        .serialize(&mut buffer, &tree, &[tree.root_ref()])
+        .unwrap();
+
+    let first_chunk = ChunkReader::new(buffer.as_slice())
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(first_chunk.kind(), ChunkKind::Meta);
+
+    let (_, report) = Deserializer::new()
+        .deserialize_with_report(buffer.as_slice())
+        .unwrap();
+    assert_eq!(report.metadata(), &metadata);
+
+    // Without any metadata, no META chunk should be written at all.
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    let kinds: Vec<_> = ChunkReader::new(buffer.as_slice())
+        .unwrap()
+        .map(|chunk| chunk.unwrap().kind())
+        .collect();
+    assert!(!kinds.contains(&ChunkKind::Meta));
+}
+
+#[test]
+fn signatures_round_trip() {
+    let tree = WeakDom::new(InstanceBuilder::new("Folder"));
+    let signatures = b"\x01\x00\x00\x00not a real signature".to_vec();
+
+    let mut buffer = Vec::new();
+    Serializer::new()
+        .signatures(Some(signatures.clone()))
+        .serialize(&mut buffer, &tree, &[tree.root_ref()])
+        .unwrap();
+
+    let sign_chunk = ChunkReader::new(buffer.as_slice())
+        .unwrap()
+        .read_contents(true)
+        .map(|chunk| chunk.unwrap())
+        .find(|chunk| chunk.kind() == ChunkKind::Signatures)
+        .unwrap();
+    assert_eq!(sign_chunk.contents(), Some(signatures.as_slice()));
+
+    let (decoded, report) = Deserializer::new()
+        .deserialize_with_report(buffer.as_slice())
+        .unwrap();
+    assert_eq!(report.signatures(), Some(signatures.as_slice()));
+    assert_eq!(decoded.root().children().len(), 1);
+
+    // Rewriting the file with the preserved signatures should produce the
+    // same bytes.
+    let mut rewritten = Vec::new();
+    Serializer::new()
+        .signatures(report.signatures().map(<[u8]>::to_vec))
+        .serialize(&mut rewritten, &decoded, decoded.root().children())
+        .unwrap();
+    assert_eq!(rewritten, buffer);
+}
+
+fn find_named<'a>(dom: &'a WeakDom, name: &str) -> Option<&'a Instance> {
+    let mut to_visit = vec![dom.root_ref()];
+
+    while let Some(referent) = to_visit.pop() {
+        let instance = dom.get_by_ref(referent).unwrap();
+        if instance.name == name {
+            return Some(instance);
+        }
+        to_visit.extend(instance.children());
+    }
+
+    None
+}
+
+#[test]
+fn external_references() {
+    let mut tree = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let root = tree.root_ref();
+    let target = tree.insert(root, InstanceBuilder::new("Part").with_name("Target"));
+    let _attachment = tree.insert(target, InstanceBuilder::new("Attachment"));
+    let selected = tree.insert(
+        root,
+        InstanceBuilder::new("Folder")
+            .with_child(InstanceBuilder::new("ObjectValue").with_property("Value", target)),
+    );
+    let value = tree.get_by_ref(selected).unwrap().children()[0];
+
+    // By default, the reference is written as empty.
+    let mut buffer = Vec::new();
+    let report = Serializer::new()
+        .serialize_with_report(&mut buffer, &tree, &[selected])
+        .unwrap();
+    assert!(report.included_instances().is_empty());
+    assert_eq!(
+        report.nulled_references(),
+        &[NulledReference::new(value, "Value".to_owned(), target)]
+    );
+
+    let decoded = from_reader(buffer.as_slice()).unwrap();
+    assert_eq!(decoded.root().children().len(), 1);
+    let decoded_value = find_named(&decoded, "ObjectValue");
+    assert_eq!(
+        decoded_value.unwrap().properties.get("Value"),
+        Some(&Variant::Ref(Ref::none()))
+    );
+
+    // With inclusion, the target and its descendants become extra roots.
+    let mut buffer = Vec::new();
+    let report = Serializer::new()
+        .external_references(ExternalReferences::Include)
+        .serialize_with_report(&mut buffer, &tree, &[selected])
+        .unwrap();
+    assert_eq!(report.included_instances(), &[target]);
+    assert!(report.nulled_references().is_empty());
+
+    let decoded = from_reader(buffer.as_slice()).unwrap();
+    assert_eq!(decoded.root().children().len(), 2);
+    let decoded_target = find_named(&decoded, "Target").unwrap();
+    assert_eq!(decoded_target.children().len(), 1);
+    let decoded_value = find_named(&decoded, "ObjectValue");
+    assert_eq!(
+        decoded_value.unwrap().properties.get("Value"),
+        Some(&Variant::Ref(decoded_target.referent()))
+    );
+}
+
+#[test]
+fn external_reference_to_ancestor() {
+    let mut tree = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let model = tree.insert(tree.root_ref(), InstanceBuilder::new("Model"));
+    let folder = tree.insert(model, InstanceBuilder::new("Folder"));
+    tree.insert(
+        folder,
+        InstanceBuilder::new("ObjectValue").with_property("Value", model),
+    );
+
+    let mut buffer = Vec::new();
+    let report = Serializer::new()
+        .external_references(ExternalReferences::Include)
+        .serialize_with_report(&mut buffer, &tree, &[folder])
+        .unwrap();
+    assert_eq!(report.included_instances(), &[model]);
+
+    // Including the Model brings the Folder back under it instead of
+    // writing it twice.
+    let decoded = from_reader(buffer.as_slice()).unwrap();
+    assert_eq!(decoded.root().children().len(), 1);
+    let decoded_model = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(decoded_model.class, "Model");
+    assert_eq!(decoded_model.children().len(), 1);
+    let decoded_folder = decoded.get_by_ref(decoded_model.children()[0]).unwrap();
+    assert_eq!(decoded_folder.children().len(), 1);
+    assert!(find_named(&decoded, "ObjectValue").is_some());
+}
+
+/// Ensures that floats survive a round trip bit for bit, and that
+/// normalization cleans them up when it's turned on.
+#[test]
+fn float_bits_round_trip() {
+    let nan = f32::from_bits(0x7fc0_1234);
+    let almost_identity = Matrix3::new(
+        Vector3::new(1.0 - f32::EPSILON / 2.0, 0.0, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
+        Vector3::new(0.0, 0.0, 1.0),
+    );
+    let negative_zero_identity = Matrix3::new(
+        Vector3::new(1.0, -0.0, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
+        Vector3::new(0.0, 0.0, 1.0),
+    );
+
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_child(InstanceBuilder::new("Part").with_property(
+                "CFrame",
+                CFrame::new(Vector3::new(-0.0, nan, 1.0), almost_identity),
+            ))
+            .with_child(InstanceBuilder::new("Part").with_property(
+                "CFrame",
+                CFrame::new(Vector3::new(0.0, 0.0, 0.0), negative_zero_identity),
+            ))
+            .with_child(InstanceBuilder::new("NumberValue").with_property("Value", -0.0f64)),
+    );
+
+    let cframe_bits = |dom: &WeakDom, folder: Ref, index: usize| {
+        let folder = dom.get_by_ref(folder).unwrap();
+        let part = dom.get_by_ref(folder.children()[index]).unwrap();
+        match part.properties.get("CFrame") {
+            Some(Variant::CFrame(cframe)) => {
+                let o = cframe.orientation;
+                [
+                    cframe.position.x,
+                    cframe.position.y,
+                    cframe.position.z,
+                    o.x.x,
+                    o.x.y,
+                    o.x.z,
+                    o.y.x,
+                    o.y.y,
+                    o.y.z,
+                    o.z.x,
+                    o.z.y,
+                    o.z.z,
+                ]
+                .map(f32::to_bits)
+            }
+            other => panic!("expected a CFrame, got {:?}", other),
+        }
+    };
+    let number_bits = |dom: &WeakDom| {
+        let folder = dom.get_by_ref(dom.root().children()[0]).unwrap();
+        let value = dom.get_by_ref(folder.children()[2]).unwrap();
+        match value.properties.get("Value") {
+            Some(Variant::Float64(value)) => value.to_bits(),
+            other => panic!("expected a Float64, got {:?}", other),
+        }
+    };
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+    let decoded = Deserializer::new().deserialize(buffer.as_slice()).unwrap();
+
+    let folder = decoded.root().children()[0];
+    for index in 0..2 {
+        assert_eq!(
+            cframe_bits(&decoded, folder, index),
+            cframe_bits(&tree, tree.root_ref(), index)
+        );
+    }
+    assert_eq!(number_bits(&decoded), (-0.0f64).to_bits());
+
+    let mut buffer = Vec::new();
+    Serializer::new()
+        .normalize_floats(true)
+        .serialize(&mut buffer, &tree, &[tree.root_ref()])
+        .unwrap();
+    let decoded = Deserializer::new().deserialize(buffer.as_slice()).unwrap();
+    let folder = decoded.root().children()[0];
+
+    let identity = Matrix3::identity();
+    let normalized = |position: Vector3| {
+        [
+            position.x,
+            position.y,
+            position.z,
+            identity.x.x,
+            identity.x.y,
+            identity.x.z,
+            identity.y.x,
+            identity.y.y,
+            identity.y.z,
+            identity.z.x,
+            identity.z.y,
+            identity.z.z,
+        ]
+        .map(f32::to_bits)
+    };
+    assert_eq!(
+        cframe_bits(&decoded, folder, 0),
+        normalized(Vector3::new(0.0, f32::NAN, 1.0))
+    );
+    assert_eq!(
+        cframe_bits(&decoded, folder, 1),
+        normalized(Vector3::new(0.0, 0.0, 0.0))
+    );
+    assert_eq!(number_bits(&decoded), 0.0f64.to_bits());
+}
+
+/// Ensures that compressing and decompressing chunks in parallel produces the
+/// same file and the same DOM as doing it one chunk at a time.
+#[test]
+#[cfg(feature = "rayon")]
+fn parallel_matches_serial() {
+    use rbx_dom_weak::{compare_doms, DomCompareOptions};
+
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder").with_children((0..200).map(|i| {
+            match i % 4 {
+                0 => InstanceBuilder::new("Part")
+                    .with_name(format!("Part{}", i))
+                    .with_property("Size", Vector3::new(i as f32, 1.0, 2.0)),
+                1 => InstanceBuilder::new("StringValue")
+                    .with_name(format!("String{}", i))
+                    .with_property("Value", "hello ".repeat(i)),
+                2 => InstanceBuilder::new("IntValue")
+                    .with_name(format!("Int{}", i))
+                    .with_property("Value", i as i64),
+                _ => InstanceBuilder::new("Folder").with_name(format!("Folder{}", i)),
+            }
+        })),
+    );
+
+    let run = |threads: usize| {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+
+        pool.install(|| {
+            let mut buffer = Vec::new();
+            Serializer::new()
+                .deterministic(true)
+                .serialize(&mut buffer, &tree, &[tree.root_ref()])
+                .unwrap();
+
+            let dom = Deserializer::new().deserialize(buffer.as_slice()).unwrap();
+            (buffer, dom)
+        })
+    };
+
+    let (serial_buffer, serial_dom) = run(1);
+    let (parallel_buffer, parallel_dom) = run(4);
+
+    assert!(serial_buffer == parallel_buffer);
+
+    let differences = compare_doms(&serial_dom, &parallel_dom, &DomCompareOptions::new());
+    assert!(differences.is_empty(), "{:#?}", differences);
+}
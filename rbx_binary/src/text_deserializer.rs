@@ -9,8 +9,8 @@ use std::{collections::HashMap, convert::TryInto, fmt::Write, io::Read};
 use rbx_dom_weak::types::{
     Axes, BrickColor, CFrame, Color3, Color3uint8, ColorSequence, ColorSequenceKeypoint,
     CustomPhysicalProperties, Enum, Faces, Font, FontStyle, FontWeight, Matrix3, NumberRange,
-    NumberSequence, NumberSequenceKeypoint, PhysicalProperties, Ray, Rect, SharedString, UDim,
-    UDim2, UniqueId, Vector2, Vector3, Vector3int16,
+    NumberSequence, NumberSequenceKeypoint, PhysicalProperties, Ray, Rect, SecurityCapabilities,
+    SharedString, UDim, UDim2, UniqueId, Vector2, Vector3, Vector3int16,
 };
 use serde::{ser::SerializeSeq, Serialize, Serializer};
 
@@ -36,17 +36,11 @@ impl DecodedModel {
             let chunk = Chunk::decode(&mut reader).expect("invalid chunk");
 
             match &chunk.name {
-                b"META" => chunks.push(decode_meta_chunk(chunk.data.as_slice())),
-                b"SSTR" => chunks.push(decode_sstr_chunk(chunk.data.as_slice())),
-                b"INST" => chunks.push(decode_inst_chunk(
-                    chunk.data.as_slice(),
-                    &mut count_by_type_id,
-                )),
-                b"PROP" => chunks.push(decode_prop_chunk(
-                    chunk.data.as_slice(),
-                    &mut count_by_type_id,
-                )),
-                b"PRNT" => chunks.push(decode_prnt_chunk(chunk.data.as_slice())),
+                b"META" => chunks.push(decode_meta_chunk(&chunk.data[..])),
+                b"SSTR" => chunks.push(decode_sstr_chunk(&chunk.data[..])),
+                b"INST" => chunks.push(decode_inst_chunk(&chunk.data[..], &mut count_by_type_id)),
+                b"PROP" => chunks.push(decode_prop_chunk(&chunk.data[..], &mut count_by_type_id)),
+                b"PRNT" => chunks.push(decode_prnt_chunk(&chunk.data[..])),
                 b"END\0" => {
                     chunks.push(DecodedChunk::End);
                     break;
@@ -54,7 +48,7 @@ impl DecodedModel {
                 _ => {
                     chunks.push(DecodedChunk::Unknown {
                         name: String::from_utf8_lossy(&chunk.name[..]).to_string(),
-                        contents: chunk.data,
+                        contents: chunk.data.into_owned(),
                     });
                 }
             }
@@ -224,6 +218,7 @@ pub enum DecodedValues {
     OptionalCFrame(Vec<Option<CFrame>>),
     UniqueId(Vec<UniqueId>),
     Font(Vec<Font>),
+    SecurityCapabilities(Vec<SecurityCapabilities>),
 }
 
 impl DecodedValues {
@@ -712,6 +707,18 @@ impl DecodedValues {
 
                 Some(DecodedValues::UniqueId(values))
             }
+            Type::SecurityCapabilities => {
+                let mut values = vec![0; prop_count];
+
+                reader.read_interleaved_i64_array(&mut values).unwrap();
+
+                let values = values
+                    .into_iter()
+                    .map(|value| SecurityCapabilities::from_bits(value as u64))
+                    .collect();
+
+                Some(DecodedValues::SecurityCapabilities(values))
+            }
         }
     }
 }
@@ -0,0 +1,120 @@
+//! Counts the allocations made while decoding, which needs its own global
+//! allocator and so can't live with the rest of rbx_binary's tests.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use rbx_binary::{DecodeSink, Deserializer};
+use rbx_dom_weak::{
+    types::{Ref, Variant, VariantType},
+    InstanceBuilder, WeakDom,
+};
+
+/// Counts allocations made on the current thread, so that tests running at
+/// the same time don't affect each other's counts.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Looks at every value it's given without keeping any of them.
+#[derive(Default)]
+struct CountingSink {
+    instances: usize,
+    string_bytes: usize,
+}
+
+impl DecodeSink for CountingSink {
+    fn instance(&mut self, _class: &str) -> Ref {
+        self.instances += 1;
+        Ref::new()
+    }
+
+    fn name(&mut self, _referent: Ref, name: &str) {
+        self.string_bytes += name.len();
+    }
+
+    fn property(&mut self, _referent: Ref, _name: &str, _value: Variant) {}
+
+    fn str_property(&mut self, _referent: Ref, _name: &str, _ty: VariantType, value: &str) {
+        self.string_bytes += value.len();
+    }
+
+    fn parent(&mut self, _referent: Ref, _parent: Ref) {}
+}
+
+/// Encodes a model with `count` StringValues, each with its own Name and
+/// Value.
+fn string_values(count: usize) -> Vec<u8> {
+    let dom = WeakDom::new(
+        InstanceBuilder::new("Folder").with_children((0..count).map(|i| {
+            InstanceBuilder::new("StringValue")
+                .with_name(format!("Value{}", i))
+                .with_property("Value", format!("Hello, world! {}", i))
+        })),
+    );
+
+    let mut buffer = Vec::new();
+    rbx_binary::to_writer(&mut buffer, &dom, dom.root().children()).unwrap();
+    buffer
+}
+
+/// Decodes `buffer` into a `CountingSink`, returning how many allocations
+/// were made along the way.
+fn count_allocations(deserializer: &Deserializer, buffer: &[u8]) -> usize {
+    let mut sink = CountingSink::default();
+
+    let before = ALLOCATIONS.with(Cell::get);
+    deserializer.deserialize_into(buffer, &mut sink).unwrap();
+    let after = ALLOCATIONS.with(Cell::get);
+
+    assert!(sink.instances > 0 && sink.string_bytes > 0);
+    after - before
+}
+
+/// Ensures that decoding Names and String values into a sink that doesn't
+/// keep them doesn't allocate for every value, so that the number of
+/// allocations stays about the same no matter how many instances there are.
+#[test]
+fn strings_are_not_copied() {
+    let deserializer = Deserializer::new();
+    let small = string_values(100);
+    let large = string_values(10_000);
+
+    // The first decode loads the reflection database, which shouldn't count.
+    count_allocations(&deserializer, &small);
+
+    let small_count = count_allocations(&deserializer, &small);
+    let large_count = count_allocations(&deserializer, &large);
+
+    // Copying the Name and Value of every instance would be 20,000 more
+    // allocations. What's left comes from tables of instances growing.
+    assert!(
+        large_count < small_count + 50,
+        "decoding 100 instances made {} allocations, but 10,000 made {}",
+        small_count,
+        large_count
+    );
+}
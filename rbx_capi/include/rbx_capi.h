@@ -0,0 +1,355 @@
+#ifndef RBX_CAPI_H
+#define RBX_CAPI_H
+
+/* This file is generated by cbindgen from rbx_capi. Don't edit it by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The file formats a DOM can be saved in.
+ */
+typedef enum RbxFormat {
+  /**
+   * The binary format, used by `.rbxm` and `.rbxl` files.
+   */
+  RBX_FORMAT_BINARY = 0,
+  /**
+   * The XML format, used by `.rbxmx` and `.rbxlx` files.
+   */
+  RBX_FORMAT_XML = 1,
+} RbxFormat;
+
+/**
+ * The result of a call into rbx_capi. Anything other than `Ok` means the
+ * call failed, and [`rbx_last_error`] describes why.
+ */
+typedef enum RbxStatus {
+  RBX_STATUS_OK = 0,
+  /**
+   * A pointer that must not be null was null.
+   */
+  RBX_STATUS_NULL_POINTER = 1,
+  /**
+   * A string wasn't valid UTF-8.
+   */
+  RBX_STATUS_INVALID_UTF8 = 2,
+  /**
+   * A handle didn't name an instance in the DOM.
+   */
+  RBX_STATUS_INVALID_INSTANCE = 3,
+  /**
+   * The instance has no property with the given name.
+   */
+  RBX_STATUS_PROPERTY_NOT_FOUND = 4,
+  /**
+   * The value's type can't be represented by an `RbxValue`.
+   */
+  RBX_STATUS_UNSUPPORTED_TYPE = 5,
+  /**
+   * An index was past the end of a list.
+   */
+  RBX_STATUS_INDEX_OUT_OF_RANGE = 6,
+  /**
+   * The operation isn't allowed, like destroying the root instance.
+   */
+  RBX_STATUS_INVALID_OPERATION = 7,
+  /**
+   * A model or place couldn't be decoded.
+   */
+  RBX_STATUS_DECODE_FAILED = 8,
+  /**
+   * A model or place couldn't be encoded.
+   */
+  RBX_STATUS_ENCODE_FAILED = 9,
+  /**
+   * rbx-dom panicked. This is a bug.
+   */
+  RBX_STATUS_PANICKED = 10,
+} RbxStatus;
+
+/**
+ * Which field of an [`RbxValue`] is set.
+ */
+typedef enum RbxValueType {
+  RBX_VALUE_TYPE_BOOL = 0,
+  RBX_VALUE_TYPE_INT32 = 1,
+  RBX_VALUE_TYPE_INT64 = 2,
+  RBX_VALUE_TYPE_FLOAT32 = 3,
+  RBX_VALUE_TYPE_FLOAT64 = 4,
+  /**
+   * Uses `string`.
+   */
+  RBX_VALUE_TYPE_STRING = 5,
+  /**
+   * Uses `string`.
+   */
+  RBX_VALUE_TYPE_CONTENT = 6,
+  /**
+   * Uses `string`, which may hold any bytes.
+   */
+  RBX_VALUE_TYPE_BINARY_STRING = 7,
+  RBX_VALUE_TYPE_VECTOR2 = 8,
+  RBX_VALUE_TYPE_VECTOR3 = 9,
+  RBX_VALUE_TYPE_COLOR3 = 10,
+  RBX_VALUE_TYPE_COLOR3UINT8 = 11,
+  RBX_VALUE_TYPE_UDIM = 12,
+  RBX_VALUE_TYPE_UDIM2 = 13,
+  RBX_VALUE_TYPE_CFRAME = 14,
+  /**
+   * Uses `enumeration`, the value of the item.
+   */
+  RBX_VALUE_TYPE_ENUM = 15,
+  /**
+   * Uses `referent`.
+   */
+  RBX_VALUE_TYPE_REF = 16,
+} RbxValueType;
+
+/**
+ * A DOM of Roblox instances.
+ */
+typedef struct RbxDom RbxDom;
+
+/**
+ * Bytes owned by the caller, which must be freed with [`rbx_buffer_free`].
+ */
+typedef struct RbxBuffer {
+  uint8_t *data;
+  size_t len;
+} RbxBuffer;
+
+/**
+ * A handle to an instance in an [`RbxDom`]. `0` stands for no instance.
+ */
+typedef uint64_t RbxRef;
+
+/**
+ * Bytes borrowed from a DOM or from the caller. Strings are UTF-8 and aren't
+ * NUL-terminated.
+ */
+typedef struct RbxSlice {
+  const uint8_t *data;
+  size_t len;
+} RbxSlice;
+
+typedef struct RbxVector2 {
+  float x;
+  float y;
+} RbxVector2;
+
+typedef struct RbxVector3 {
+  float x;
+  float y;
+  float z;
+} RbxVector3;
+
+typedef struct RbxColor3 {
+  float r;
+  float g;
+  float b;
+} RbxColor3;
+
+typedef struct RbxColor3uint8 {
+  uint8_t r;
+  uint8_t g;
+  uint8_t b;
+} RbxColor3uint8;
+
+typedef struct RbxUDim {
+  float scale;
+  int32_t offset;
+} RbxUDim;
+
+typedef struct RbxUDim2 {
+  struct RbxUDim x;
+  struct RbxUDim y;
+} RbxUDim2;
+
+/**
+ * A position and an orientation, given as the rows of a rotation matrix.
+ */
+typedef struct RbxCFrame {
+  struct RbxVector3 position;
+  struct RbxVector3 orientation[3];
+} RbxCFrame;
+
+typedef union RbxValueData {
+  bool boolean;
+  int32_t int32;
+  int64_t int64;
+  float float32;
+  double float64;
+  struct RbxSlice string;
+  struct RbxVector2 vector2;
+  struct RbxVector3 vector3;
+  struct RbxColor3 color3;
+  struct RbxColor3uint8 color3uint8;
+  struct RbxUDim udim;
+  struct RbxUDim2 udim2;
+  struct RbxCFrame cframe;
+  uint32_t enumeration;
+  RbxRef referent;
+} RbxValueData;
+
+/**
+ * The value of a property, tagged with its type.
+ */
+typedef struct RbxValue {
+  enum RbxValueType ty;
+  union RbxValueData data;
+} RbxValue;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates a DOM whose root is a new instance of the given class.
+ */
+enum RbxStatus rbx_dom_new(const char *class_name, struct RbxDom **out);
+
+/**
+ * Frees a DOM. Passing null does nothing.
+ */
+void rbx_dom_free(struct RbxDom *dom);
+
+/**
+ * Decodes a model or place in either the binary or XML format. The root of
+ * the new DOM is a `DataModel` holding the instances from the file.
+ */
+enum RbxStatus rbx_dom_load(const uint8_t *data, size_t len, struct RbxDom **out);
+
+/**
+ * Encodes the given instances and their descendants as a model or place.
+ * When `refs` is null, the children of the root are encoded, which is what a
+ * DOM returned by [`rbx_dom_load`] needs.
+ */
+enum RbxStatus rbx_dom_save(const struct RbxDom *dom,
+                            const RbxRef *refs,
+                            size_t ref_count,
+                            enum RbxFormat format,
+                            struct RbxBuffer *out);
+
+/**
+ * Frees a buffer returned by [`rbx_dom_save`]. Passing a buffer whose data
+ * is null does nothing.
+ */
+void rbx_buffer_free(struct RbxBuffer buffer);
+
+/**
+ * Gets the root instance of a DOM.
+ */
+enum RbxStatus rbx_dom_root(const struct RbxDom *dom, RbxRef *out);
+
+/**
+ * Returns a description of the last call on this thread that failed, or null
+ * if none has. The string is valid until the next call that fails.
+ */
+const char *rbx_last_error(void);
+
+/**
+ * Gets the name of an instance.
+ */
+enum RbxStatus rbx_instance_name(const struct RbxDom *dom, RbxRef instance, struct RbxSlice *out);
+
+/**
+ * Renames an instance.
+ */
+enum RbxStatus rbx_instance_set_name(struct RbxDom *dom, RbxRef instance, const char *name);
+
+/**
+ * Gets the ClassName of an instance.
+ */
+enum RbxStatus rbx_instance_class_name(const struct RbxDom *dom,
+                                       RbxRef instance,
+                                       struct RbxSlice *out);
+
+/**
+ * Gets the parent of an instance, which is `0` for the root.
+ */
+enum RbxStatus rbx_instance_parent(const struct RbxDom *dom, RbxRef instance, RbxRef *out);
+
+/**
+ * Gets the number of children an instance has.
+ */
+enum RbxStatus rbx_instance_child_count(const struct RbxDom *dom, RbxRef instance, size_t *out);
+
+/**
+ * Gets the child of an instance at `index`, counting from zero.
+ */
+enum RbxStatus rbx_instance_child(const struct RbxDom *dom,
+                                  RbxRef instance,
+                                  size_t index,
+                                  RbxRef *out);
+
+/**
+ * Creates an instance of the given class, named after it, as the last child
+ * of `parent`.
+ */
+enum RbxStatus rbx_instance_new(struct RbxDom *dom,
+                                RbxRef parent,
+                                const char *class_name,
+                                RbxRef *out);
+
+/**
+ * Destroys an instance and all of its descendants. The root can't be
+ * destroyed.
+ */
+enum RbxStatus rbx_instance_destroy(struct RbxDom *dom, RbxRef instance);
+
+/**
+ * Moves an instance to be the last child of `parent`. The root can't be
+ * moved, and an instance can't be moved into itself or its descendants.
+ */
+enum RbxStatus rbx_instance_set_parent(struct RbxDom *dom, RbxRef instance, RbxRef parent);
+
+/**
+ * Gets the number of properties an instance has. Name and ClassName aren't
+ * counted, since they aren't properties in rbx-dom.
+ */
+enum RbxStatus rbx_instance_property_count(const struct RbxDom *dom,
+                                           RbxRef instance,
+                                           size_t *out);
+
+/**
+ * Gets the name of an instance's property at `index`, counting from zero.
+ * Properties are sorted by name.
+ */
+enum RbxStatus rbx_instance_property_name(const struct RbxDom *dom,
+                                          RbxRef instance,
+                                          size_t index,
+                                          struct RbxSlice *out);
+
+/**
+ * Gets the value of a property. Strings in the value are borrowed from the
+ * DOM.
+ */
+enum RbxStatus rbx_instance_get_property(const struct RbxDom *dom,
+                                         RbxRef instance,
+                                         const char *name,
+                                         struct RbxValue *out);
+
+/**
+ * Sets the value of a property, copying any strings in it.
+ */
+enum RbxStatus rbx_instance_set_property(struct RbxDom *dom,
+                                         RbxRef instance,
+                                         const char *name,
+                                         const struct RbxValue *value);
+
+/**
+ * Removes a property from an instance.
+ */
+enum RbxStatus rbx_instance_remove_property(struct RbxDom *dom,
+                                            RbxRef instance,
+                                            const char *name);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RBX_CAPI_H */
@@ -0,0 +1,213 @@
+use std::{cell::RefCell, collections::HashMap, os::raw::c_char, ptr, slice};
+
+use rbx_dom_weak::{types::Ref, Instance, InstanceBuilder, WeakDom};
+
+use crate::error::{deref, fail, read_str, run, write_out, RbxStatus};
+
+/// A handle to an instance in an [`RbxDom`]. `0` stands for no instance.
+pub type RbxRef = u64;
+
+/// A DOM of Roblox instances.
+pub struct RbxDom {
+    pub(crate) dom: WeakDom,
+    handles: RefCell<Handles>,
+}
+
+/// Handles are numbered in the order instances are first passed out, so that
+/// C code gets a plain integer instead of a 128-bit referent.
+#[derive(Default)]
+struct Handles {
+    referents: Vec<Ref>,
+    by_referent: HashMap<Ref, RbxRef>,
+}
+
+impl RbxDom {
+    pub(crate) fn new(dom: WeakDom) -> Self {
+        RbxDom {
+            dom,
+            handles: RefCell::new(Handles::default()),
+        }
+    }
+
+    /// Returns the handle for an instance, giving it one if it doesn't have
+    /// one yet.
+    pub(crate) fn handle(&self, referent: Ref) -> RbxRef {
+        if referent.is_none() {
+            return 0;
+        }
+
+        let mut handles = self.handles.borrow_mut();
+        if let Some(&handle) = handles.by_referent.get(&referent) {
+            return handle;
+        }
+
+        handles.referents.push(referent);
+        let handle = handles.referents.len() as RbxRef;
+        handles.by_referent.insert(referent, handle);
+        handle
+    }
+
+    /// Returns the referent a handle stands for. Handles that were never
+    /// passed out stand for no instance.
+    pub(crate) fn referent(&self, handle: RbxRef) -> Ref {
+        let handles = self.handles.borrow();
+        match handle.checked_sub(1) {
+            Some(index) => handles
+                .referents
+                .get(index as usize)
+                .copied()
+                .unwrap_or_else(Ref::none),
+            None => Ref::none(),
+        }
+    }
+
+    /// Finds the instance a handle stands for, failing if it has been
+    /// destroyed or never existed.
+    pub(crate) fn instance(&self, handle: RbxRef) -> Result<&Instance, RbxStatus> {
+        self.dom
+            .get_by_ref(self.referent(handle))
+            .ok_or_else(|| invalid_instance(handle))
+    }
+
+    pub(crate) fn instance_mut(&mut self, handle: RbxRef) -> Result<&mut Instance, RbxStatus> {
+        let referent = self.referent(handle);
+        self.dom
+            .get_by_ref_mut(referent)
+            .ok_or_else(|| invalid_instance(handle))
+    }
+}
+
+pub(crate) fn invalid_instance(handle: RbxRef) -> RbxStatus {
+    fail(
+        RbxStatus::InvalidInstance,
+        format_args!("{} is not an instance in this DOM", handle),
+    )
+}
+
+/// The file formats a DOM can be saved in.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RbxFormat {
+    /// The binary format, used by `.rbxm` and `.rbxl` files.
+    Binary = 0,
+
+    /// The XML format, used by `.rbxmx` and `.rbxlx` files.
+    Xml = 1,
+}
+
+/// Bytes owned by the caller, which must be freed with [`rbx_buffer_free`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct RbxBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// Creates a DOM whose root is a new instance of the given class.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_dom_new(
+    class_name: *const c_char,
+    out: *mut *mut RbxDom,
+) -> RbxStatus {
+    run(|| {
+        let class_name = read_str(class_name, "class_name")?;
+        let dom = RbxDom::new(WeakDom::new(InstanceBuilder::new(class_name)));
+
+        write_out(out, "out", Box::into_raw(Box::new(dom)))
+    })
+}
+
+/// Frees a DOM. Passing null does nothing.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_dom_free(dom: *mut RbxDom) {
+    if !dom.is_null() {
+        drop(Box::from_raw(dom));
+    }
+}
+
+/// Decodes a model or place in either the binary or XML format. The root of
+/// the new DOM is a `DataModel` holding the instances from the file.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_dom_load(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut RbxDom,
+) -> RbxStatus {
+    run(|| {
+        if data.is_null() {
+            return Err(fail(RbxStatus::NullPointer, "data is null"));
+        }
+
+        let input = slice::from_raw_parts(data, len);
+        let dom = if input.starts_with(b"<roblox!") {
+            rbx_binary::from_slice(input).map_err(|err| fail(RbxStatus::DecodeFailed, err))?
+        } else {
+            let options = rbx_xml::DecodeOptions::new()
+                .property_behavior(rbx_xml::DecodePropertyBehavior::ReadUnknown);
+            rbx_xml::from_slice(input, options).map_err(|err| fail(RbxStatus::DecodeFailed, err))?
+        };
+
+        write_out(out, "out", Box::into_raw(Box::new(RbxDom::new(dom))))
+    })
+}
+
+/// Encodes the given instances and their descendants as a model or place.
+/// When `refs` is null, the children of the root are encoded, which is what a
+/// DOM returned by [`rbx_dom_load`] needs.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_dom_save(
+    dom: *const RbxDom,
+    refs: *const RbxRef,
+    ref_count: usize,
+    format: RbxFormat,
+    out: *mut RbxBuffer,
+) -> RbxStatus {
+    run(|| {
+        let dom = deref(dom, "dom")?;
+
+        let referents = if refs.is_null() {
+            dom.dom.root().children().to_vec()
+        } else {
+            slice::from_raw_parts(refs, ref_count)
+                .iter()
+                .map(|&handle| dom.instance(handle).map(|instance| instance.referent()))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let output = match format {
+            RbxFormat::Binary => rbx_binary::to_vec(&dom.dom, &referents)
+                .map_err(|err| fail(RbxStatus::EncodeFailed, err))?,
+            RbxFormat::Xml => {
+                let options = rbx_xml::EncodeOptions::new()
+                    .property_behavior(rbx_xml::EncodePropertyBehavior::WriteUnknown);
+                rbx_xml::to_vec(&dom.dom, &referents, options)
+                    .map_err(|err| fail(RbxStatus::EncodeFailed, err))?
+            }
+        };
+
+        let len = output.len();
+        let data = Box::into_raw(output.into_boxed_slice()) as *mut u8;
+        write_out(out, "out", RbxBuffer { data, len })
+    })
+}
+
+/// Frees a buffer returned by [`rbx_dom_save`]. Passing a buffer whose data
+/// is null does nothing.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_buffer_free(buffer: RbxBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            buffer.data,
+            buffer.len,
+        )));
+    }
+}
+
+/// Gets the root instance of a DOM.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_dom_root(dom: *const RbxDom, out: *mut RbxRef) -> RbxStatus {
+    run(|| {
+        let dom = deref(dom, "dom")?;
+        write_out(out, "out", dom.handle(dom.dom.root_ref()))
+    })
+}
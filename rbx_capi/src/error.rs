@@ -0,0 +1,135 @@
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString},
+    fmt,
+    os::raw::c_char,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+/// The result of a call into rbx_capi. Anything other than `Ok` means the
+/// call failed, and [`rbx_last_error`] describes why.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RbxStatus {
+    Ok = 0,
+
+    /// A pointer that must not be null was null.
+    NullPointer = 1,
+
+    /// A string wasn't valid UTF-8.
+    InvalidUtf8 = 2,
+
+    /// A handle didn't name an instance in the DOM.
+    InvalidInstance = 3,
+
+    /// The instance has no property with the given name.
+    PropertyNotFound = 4,
+
+    /// The value's type can't be represented by an `RbxValue`.
+    UnsupportedType = 5,
+
+    /// An index was past the end of a list.
+    IndexOutOfRange = 6,
+
+    /// The operation isn't allowed, like destroying the root instance.
+    InvalidOperation = 7,
+
+    /// A model or place couldn't be decoded.
+    DecodeFailed = 8,
+
+    /// A model or place couldn't be encoded.
+    EncodeFailed = 9,
+
+    /// rbx-dom panicked. This is a bug.
+    Panicked = 10,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Returns a description of the last call on this thread that failed, or null
+/// if none has. The string is valid until the next call that fails.
+#[no_mangle]
+pub extern "C" fn rbx_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| match &*last_error.borrow() {
+        Some(message) => message.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+/// Records `message` as the last error and returns `status`, for reporting a
+/// failure.
+pub(crate) fn fail(status: RbxStatus, message: impl fmt::Display) -> RbxStatus {
+    let message = message.to_string().replace('\0', "\\0");
+    let message = CString::new(message).unwrap();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+
+    status
+}
+
+/// Runs the body of an exported function, turning its result into a status.
+/// Panics can't unwind into C, so they're caught and reported too.
+pub(crate) fn run(body: impl FnOnce() -> Result<(), RbxStatus>) -> RbxStatus {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => RbxStatus::Ok,
+        Ok(Err(status)) => status,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_owned());
+
+            fail(RbxStatus::Panicked, format_args!("panicked: {}", message))
+        }
+    }
+}
+
+/// Dereferences a pointer passed in by the caller.
+pub(crate) unsafe fn deref<'a, T>(pointer: *const T, name: &str) -> Result<&'a T, RbxStatus> {
+    pointer
+        .as_ref()
+        .ok_or_else(|| fail(RbxStatus::NullPointer, format_args!("{} is null", name)))
+}
+
+/// Mutably dereferences a pointer passed in by the caller.
+pub(crate) unsafe fn deref_mut<'a, T>(pointer: *mut T, name: &str) -> Result<&'a mut T, RbxStatus> {
+    pointer
+        .as_mut()
+        .ok_or_else(|| fail(RbxStatus::NullPointer, format_args!("{} is null", name)))
+}
+
+/// Writes a result through an out-pointer passed in by the caller.
+pub(crate) unsafe fn write_out<T>(pointer: *mut T, name: &str, value: T) -> Result<(), RbxStatus> {
+    if pointer.is_null() {
+        return Err(fail(
+            RbxStatus::NullPointer,
+            format_args!("{} is null", name),
+        ));
+    }
+
+    pointer.write(value);
+    Ok(())
+}
+
+/// Reads a NUL-terminated UTF-8 string passed in by the caller.
+pub(crate) unsafe fn read_str<'a>(
+    pointer: *const c_char,
+    name: &str,
+) -> Result<&'a str, RbxStatus> {
+    if pointer.is_null() {
+        return Err(fail(
+            RbxStatus::NullPointer,
+            format_args!("{} is null", name),
+        ));
+    }
+
+    CStr::from_ptr(pointer).to_str().map_err(|_| {
+        fail(
+            RbxStatus::InvalidUtf8,
+            format_args!("{} is not valid UTF-8", name),
+        )
+    })
+}
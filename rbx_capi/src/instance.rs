@@ -0,0 +1,181 @@
+use std::os::raw::c_char;
+
+use rbx_dom_weak::InstanceBuilder;
+
+use crate::{
+    dom::{RbxDom, RbxRef},
+    error::{deref, deref_mut, fail, read_str, run, write_out, RbxStatus},
+    value::RbxSlice,
+};
+
+/// Gets the name of an instance.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_name(
+    dom: *const RbxDom,
+    instance: RbxRef,
+    out: *mut RbxSlice,
+) -> RbxStatus {
+    run(|| {
+        let dom = deref(dom, "dom")?;
+        let instance = dom.instance(instance)?;
+        write_out(out, "out", RbxSlice::new(instance.name.as_bytes()))
+    })
+}
+
+/// Renames an instance.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_set_name(
+    dom: *mut RbxDom,
+    instance: RbxRef,
+    name: *const c_char,
+) -> RbxStatus {
+    run(|| {
+        let dom = deref_mut(dom, "dom")?;
+        let name = read_str(name, "name")?;
+        dom.instance_mut(instance)?.name = name.to_owned();
+        Ok(())
+    })
+}
+
+/// Gets the ClassName of an instance.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_class_name(
+    dom: *const RbxDom,
+    instance: RbxRef,
+    out: *mut RbxSlice,
+) -> RbxStatus {
+    run(|| {
+        let dom = deref(dom, "dom")?;
+        let instance = dom.instance(instance)?;
+        write_out(out, "out", RbxSlice::new(instance.class.as_bytes()))
+    })
+}
+
+/// Gets the parent of an instance, which is `0` for the root.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_parent(
+    dom: *const RbxDom,
+    instance: RbxRef,
+    out: *mut RbxRef,
+) -> RbxStatus {
+    run(|| {
+        let dom = deref(dom, "dom")?;
+        let parent = dom.instance(instance)?.parent();
+        write_out(out, "out", dom.handle(parent))
+    })
+}
+
+/// Gets the number of children an instance has.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_child_count(
+    dom: *const RbxDom,
+    instance: RbxRef,
+    out: *mut usize,
+) -> RbxStatus {
+    run(|| {
+        let dom = deref(dom, "dom")?;
+        let count = dom.instance(instance)?.children().len();
+        write_out(out, "out", count)
+    })
+}
+
+/// Gets the child of an instance at `index`, counting from zero.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_child(
+    dom: *const RbxDom,
+    instance: RbxRef,
+    index: usize,
+    out: *mut RbxRef,
+) -> RbxStatus {
+    run(|| {
+        let dom = deref(dom, "dom")?;
+        let children = dom.instance(instance)?.children();
+        let child = children.get(index).ok_or_else(|| {
+            fail(
+                RbxStatus::IndexOutOfRange,
+                format_args!(
+                    "child {} is out of range for an instance with {} children",
+                    index,
+                    children.len()
+                ),
+            )
+        })?;
+
+        write_out(out, "out", dom.handle(*child))
+    })
+}
+
+/// Creates an instance of the given class, named after it, as the last child
+/// of `parent`.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_new(
+    dom: *mut RbxDom,
+    parent: RbxRef,
+    class_name: *const c_char,
+    out: *mut RbxRef,
+) -> RbxStatus {
+    run(|| {
+        let dom = deref_mut(dom, "dom")?;
+        let class_name = read_str(class_name, "class_name")?;
+        let parent = dom.instance(parent)?.referent();
+
+        let referent = dom.dom.insert(parent, InstanceBuilder::new(class_name));
+        write_out(out, "out", dom.handle(referent))
+    })
+}
+
+/// Destroys an instance and all of its descendants. The root can't be
+/// destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_destroy(dom: *mut RbxDom, instance: RbxRef) -> RbxStatus {
+    run(|| {
+        let dom = deref_mut(dom, "dom")?;
+        let referent = dom.instance(instance)?.referent();
+
+        if referent == dom.dom.root_ref() {
+            return Err(fail(
+                RbxStatus::InvalidOperation,
+                "the root instance can't be destroyed",
+            ));
+        }
+
+        dom.dom.destroy(referent);
+        Ok(())
+    })
+}
+
+/// Moves an instance to be the last child of `parent`. The root can't be
+/// moved, and an instance can't be moved into itself or its descendants.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_set_parent(
+    dom: *mut RbxDom,
+    instance: RbxRef,
+    parent: RbxRef,
+) -> RbxStatus {
+    run(|| {
+        let dom = deref_mut(dom, "dom")?;
+        let referent = dom.instance(instance)?.referent();
+        let parent = dom.instance(parent)?.referent();
+
+        if referent == dom.dom.root_ref() {
+            return Err(fail(
+                RbxStatus::InvalidOperation,
+                "the root instance can't be moved",
+            ));
+        }
+
+        let mut ancestor = parent;
+        while let Some(current) = dom.dom.get_by_ref(ancestor) {
+            if ancestor == referent {
+                return Err(fail(
+                    RbxStatus::InvalidOperation,
+                    "an instance can't be moved into itself or one of its descendants",
+                ));
+            }
+            ancestor = current.parent();
+        }
+
+        dom.dom.transfer_within(referent, parent);
+        Ok(())
+    })
+}
@@ -0,0 +1,59 @@
+/*!
+A C API for rbx-dom, so that engines and editors that aren't written in Rust
+can read, edit, and write Roblox models and places.
+
+The header for this API is `include/rbx_capi.h`, generated by cbindgen.
+
+# Conventions
+
+A DOM is an opaque [`RbxDom`], created by [`rbx_dom_new`] or
+[`rbx_dom_load`] and freed with [`rbx_dom_free`]. Instances in it are named
+by [`RbxRef`] handles, which stay the same for as long as the DOM is alive.
+The handle `0` stands for no instance.
+
+Every function that can fail returns an [`RbxStatus`] and writes its results
+through out-pointers, which are left alone when it fails. After a failure,
+[`rbx_last_error`] describes what went wrong.
+
+Strings passed in are NUL-terminated UTF-8. Strings and bytes passed out are
+[`RbxSlice`]s borrowed from the DOM, which are valid until the DOM is next
+changed or freed. The only memory the caller owns is an [`RbxBuffer`] returned
+by [`rbx_dom_save`], which must be freed with [`rbx_buffer_free`].
+
+# Safety
+
+Every pointer passed to this API must either be null, where a null pointer is
+reported as [`RbxStatus::NullPointer`], or point to a valid value of its type.
+An `RbxDom` isn't thread-safe: it must not be used from more than one thread
+at a time.
+*/
+
+// Every function has the same safety requirements, which are documented above.
+#![allow(clippy::missing_safety_doc)]
+
+mod dom;
+mod error;
+mod instance;
+mod value;
+
+#[cfg(test)]
+mod tests;
+
+pub use crate::{
+    dom::{
+        rbx_buffer_free, rbx_dom_free, rbx_dom_load, rbx_dom_new, rbx_dom_root, rbx_dom_save,
+        RbxBuffer, RbxDom, RbxFormat, RbxRef,
+    },
+    error::{rbx_last_error, RbxStatus},
+    instance::{
+        rbx_instance_child, rbx_instance_child_count, rbx_instance_class_name,
+        rbx_instance_destroy, rbx_instance_name, rbx_instance_new, rbx_instance_parent,
+        rbx_instance_set_name, rbx_instance_set_parent,
+    },
+    value::{
+        rbx_instance_get_property, rbx_instance_property_count, rbx_instance_property_name,
+        rbx_instance_remove_property, rbx_instance_set_property, RbxCFrame, RbxColor3,
+        RbxColor3uint8, RbxSlice, RbxUDim, RbxUDim2, RbxValue, RbxValueData, RbxValueType,
+        RbxVector2, RbxVector3,
+    },
+};
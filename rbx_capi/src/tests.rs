@@ -0,0 +1,295 @@
+use std::{ffi::CStr, ptr, slice, str};
+
+use crate::*;
+
+fn slice_str(slice: RbxSlice) -> &'static str {
+    unsafe { str::from_utf8(slice::from_raw_parts(slice.data, slice.len)).unwrap() }
+}
+
+fn last_error() -> String {
+    unsafe { CStr::from_ptr(rbx_last_error()) }
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+fn new_dom() -> *mut RbxDom {
+    let mut dom = ptr::null_mut();
+    assert_eq!(
+        unsafe { rbx_dom_new(b"DataModel\0".as_ptr().cast(), &mut dom) },
+        RbxStatus::Ok
+    );
+    dom
+}
+
+fn root(dom: *const RbxDom) -> RbxRef {
+    let mut root = 0;
+    assert_eq!(unsafe { rbx_dom_root(dom, &mut root) }, RbxStatus::Ok);
+    root
+}
+
+fn new_instance(dom: *mut RbxDom, parent: RbxRef, class_name: &[u8]) -> RbxRef {
+    let mut instance = 0;
+    assert_eq!(
+        unsafe { rbx_instance_new(dom, parent, class_name.as_ptr().cast(), &mut instance) },
+        RbxStatus::Ok
+    );
+    instance
+}
+
+#[test]
+fn build_tree() {
+    let dom = new_dom();
+    let root = root(dom);
+    let folder = new_instance(dom, root, b"Folder\0");
+    let value = new_instance(dom, folder, b"StringValue\0");
+
+    unsafe {
+        assert_eq!(
+            rbx_instance_set_name(dom, value, b"Greeting\0".as_ptr().cast()),
+            RbxStatus::Ok
+        );
+
+        let mut name = RbxSlice::new(&[]);
+        assert_eq!(rbx_instance_name(dom, value, &mut name), RbxStatus::Ok);
+        assert_eq!(slice_str(name), "Greeting");
+
+        let mut class_name = RbxSlice::new(&[]);
+        assert_eq!(
+            rbx_instance_class_name(dom, folder, &mut class_name),
+            RbxStatus::Ok
+        );
+        assert_eq!(slice_str(class_name), "Folder");
+
+        let mut count = 0;
+        assert_eq!(
+            rbx_instance_child_count(dom, folder, &mut count),
+            RbxStatus::Ok
+        );
+        assert_eq!(count, 1);
+
+        let mut child = 0;
+        assert_eq!(
+            rbx_instance_child(dom, folder, 0, &mut child),
+            RbxStatus::Ok
+        );
+        assert_eq!(child, value);
+        assert_eq!(
+            rbx_instance_child(dom, folder, 1, &mut child),
+            RbxStatus::IndexOutOfRange
+        );
+
+        let mut parent = 0;
+        assert_eq!(rbx_instance_parent(dom, value, &mut parent), RbxStatus::Ok);
+        assert_eq!(parent, folder);
+        assert_eq!(rbx_instance_parent(dom, root, &mut parent), RbxStatus::Ok);
+        assert_eq!(parent, 0);
+
+        assert_eq!(
+            rbx_instance_set_parent(dom, folder, value),
+            RbxStatus::InvalidOperation
+        );
+        assert_eq!(rbx_instance_set_parent(dom, value, root), RbxStatus::Ok);
+        assert_eq!(
+            rbx_instance_child_count(dom, root, &mut count),
+            RbxStatus::Ok
+        );
+        assert_eq!(count, 2);
+
+        assert_eq!(rbx_instance_destroy(dom, root), RbxStatus::InvalidOperation);
+        assert_eq!(rbx_instance_destroy(dom, folder), RbxStatus::Ok);
+        assert_eq!(
+            rbx_instance_name(dom, folder, &mut name),
+            RbxStatus::InvalidInstance
+        );
+
+        rbx_dom_free(dom);
+    }
+}
+
+#[test]
+fn properties() {
+    let dom = new_dom();
+    let part = new_instance(dom, root(dom), b"Part\0");
+    let value = new_instance(dom, root(dom), b"ObjectValue\0");
+
+    unsafe {
+        let size = RbxValue {
+            ty: RbxValueType::Vector3,
+            data: RbxValueData {
+                vector3: RbxVector3 {
+                    x: 4.0,
+                    y: 1.0,
+                    z: 2.0,
+                },
+            },
+        };
+        assert_eq!(
+            rbx_instance_set_property(dom, part, b"Size\0".as_ptr().cast(), &size),
+            RbxStatus::Ok
+        );
+
+        let target = RbxValue {
+            ty: RbxValueType::Ref,
+            data: RbxValueData { referent: part },
+        };
+        assert_eq!(
+            rbx_instance_set_property(dom, value, b"Value\0".as_ptr().cast(), &target),
+            RbxStatus::Ok
+        );
+
+        let text = "Hello";
+        let text = RbxValue {
+            ty: RbxValueType::String,
+            data: RbxValueData {
+                string: RbxSlice::new(text.as_bytes()),
+            },
+        };
+        assert_eq!(
+            rbx_instance_set_property(dom, part, b"Note\0".as_ptr().cast(), &text),
+            RbxStatus::Ok
+        );
+
+        let mut out = size;
+        assert_eq!(
+            rbx_instance_get_property(dom, part, b"Size\0".as_ptr().cast(), &mut out),
+            RbxStatus::Ok
+        );
+        assert_eq!(out.ty, RbxValueType::Vector3);
+        assert_eq!(out.data.vector3.x, 4.0);
+
+        assert_eq!(
+            rbx_instance_get_property(dom, value, b"Value\0".as_ptr().cast(), &mut out),
+            RbxStatus::Ok
+        );
+        assert_eq!(out.ty, RbxValueType::Ref);
+        assert_eq!(out.data.referent, part);
+
+        let mut count = 0;
+        assert_eq!(
+            rbx_instance_property_count(dom, part, &mut count),
+            RbxStatus::Ok
+        );
+        assert_eq!(count, 2);
+
+        let mut name = RbxSlice::new(&[]);
+        assert_eq!(
+            rbx_instance_property_name(dom, part, 0, &mut name),
+            RbxStatus::Ok
+        );
+        assert_eq!(slice_str(name), "Note");
+
+        assert_eq!(
+            rbx_instance_remove_property(dom, part, b"Note\0".as_ptr().cast()),
+            RbxStatus::Ok
+        );
+        assert_eq!(
+            rbx_instance_get_property(dom, part, b"Note\0".as_ptr().cast(), &mut out),
+            RbxStatus::PropertyNotFound
+        );
+
+        rbx_dom_free(dom);
+    }
+}
+
+#[test]
+fn unsupported_type() {
+    let dom = new_dom();
+    let folder = new_instance(dom, root(dom), b"Folder\0");
+
+    unsafe {
+        (*dom)
+            .instance_mut(folder)
+            .unwrap()
+            .properties
+            .insert("Tags".to_owned(), rbx_dom_weak::types::Tags::new().into());
+
+        let mut out = RbxValue {
+            ty: RbxValueType::Bool,
+            data: RbxValueData { boolean: false },
+        };
+        assert_eq!(
+            rbx_instance_get_property(dom, folder, b"Tags\0".as_ptr().cast(), &mut out),
+            RbxStatus::UnsupportedType
+        );
+        assert!(last_error().contains("Tags"));
+
+        rbx_dom_free(dom);
+    }
+}
+
+#[test]
+fn save_and_load() {
+    let dom = new_dom();
+    let value = new_instance(dom, root(dom), b"IntValue\0");
+
+    unsafe {
+        let number = RbxValue {
+            ty: RbxValueType::Int64,
+            data: RbxValueData { int64: 42 },
+        };
+        assert_eq!(
+            rbx_instance_set_property(dom, value, b"Value\0".as_ptr().cast(), &number),
+            RbxStatus::Ok
+        );
+
+        for format in [RbxFormat::Binary, RbxFormat::Xml] {
+            let mut buffer = RbxBuffer {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            assert_eq!(
+                rbx_dom_save(dom, ptr::null(), 0, format, &mut buffer),
+                RbxStatus::Ok
+            );
+
+            let mut loaded = ptr::null_mut();
+            assert_eq!(
+                rbx_dom_load(buffer.data, buffer.len, &mut loaded),
+                RbxStatus::Ok
+            );
+            rbx_buffer_free(buffer);
+
+            let mut child = 0;
+            assert_eq!(
+                rbx_instance_child(loaded, root(loaded), 0, &mut child),
+                RbxStatus::Ok
+            );
+
+            let mut out = number;
+            assert_eq!(
+                rbx_instance_get_property(loaded, child, b"Value\0".as_ptr().cast(), &mut out),
+                RbxStatus::Ok
+            );
+            assert_eq!(out.ty, RbxValueType::Int64);
+            assert_eq!(out.data.int64, 42);
+
+            rbx_dom_free(loaded);
+        }
+
+        let mut loaded = ptr::null_mut();
+        assert_eq!(
+            rbx_dom_load(b"nope".as_ptr(), 4, &mut loaded),
+            RbxStatus::DecodeFailed
+        );
+        assert!(loaded.is_null());
+
+        rbx_dom_free(dom);
+    }
+}
+
+#[test]
+fn null_pointers() {
+    unsafe {
+        assert_eq!(
+            rbx_dom_new(ptr::null(), &mut ptr::null_mut()),
+            RbxStatus::NullPointer
+        );
+        assert_eq!(last_error(), "class_name is null");
+
+        let mut root = 0;
+        assert_eq!(rbx_dom_root(ptr::null(), &mut root), RbxStatus::NullPointer);
+
+        rbx_dom_free(ptr::null_mut());
+    }
+}
@@ -0,0 +1,451 @@
+use std::{os::raw::c_char, slice, str};
+
+use rbx_dom_weak::types::{
+    BinaryString, CFrame, Color3, Color3uint8, Content, Enum, Matrix3, UDim, UDim2, Variant,
+    Vector2, Vector3,
+};
+
+use crate::{
+    dom::{invalid_instance, RbxDom, RbxRef},
+    error::{deref, deref_mut, fail, read_str, run, write_out, RbxStatus},
+};
+
+/// Bytes borrowed from a DOM or from the caller. Strings are UTF-8 and aren't
+/// NUL-terminated.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RbxSlice {
+    pub data: *const u8,
+    pub len: usize,
+}
+
+impl RbxSlice {
+    pub(crate) fn new(bytes: &[u8]) -> Self {
+        RbxSlice {
+            data: bytes.as_ptr(),
+            len: bytes.len(),
+        }
+    }
+
+    unsafe fn as_bytes<'a>(&self) -> Result<&'a [u8], RbxStatus> {
+        if self.data.is_null() {
+            if self.len == 0 {
+                return Ok(&[]);
+            }
+            return Err(fail(RbxStatus::NullPointer, "slice data is null"));
+        }
+
+        Ok(slice::from_raw_parts(self.data, self.len))
+    }
+
+    unsafe fn as_str<'a>(&self) -> Result<&'a str, RbxStatus> {
+        str::from_utf8(self.as_bytes()?)
+            .map_err(|_| fail(RbxStatus::InvalidUtf8, "string value is not valid UTF-8"))
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RbxVector2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RbxVector3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RbxColor3 {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RbxColor3uint8 {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RbxUDim {
+    pub scale: f32,
+    pub offset: i32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RbxUDim2 {
+    pub x: RbxUDim,
+    pub y: RbxUDim,
+}
+
+/// A position and an orientation, given as the rows of a rotation matrix.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RbxCFrame {
+    pub position: RbxVector3,
+    pub orientation: [RbxVector3; 3],
+}
+
+/// Which field of an [`RbxValue`] is set.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RbxValueType {
+    Bool = 0,
+    Int32 = 1,
+    Int64 = 2,
+    Float32 = 3,
+    Float64 = 4,
+    /// Uses `string`.
+    String = 5,
+    /// Uses `string`.
+    Content = 6,
+    /// Uses `string`, which may hold any bytes.
+    BinaryString = 7,
+    Vector2 = 8,
+    Vector3 = 9,
+    Color3 = 10,
+    Color3uint8 = 11,
+    UDim = 12,
+    UDim2 = 13,
+    CFrame = 14,
+    /// Uses `enumeration`, the value of the item.
+    Enum = 15,
+    /// Uses `referent`.
+    Ref = 16,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union RbxValueData {
+    pub boolean: bool,
+    pub int32: i32,
+    pub int64: i64,
+    pub float32: f32,
+    pub float64: f64,
+    pub string: RbxSlice,
+    pub vector2: RbxVector2,
+    pub vector3: RbxVector3,
+    pub color3: RbxColor3,
+    pub color3uint8: RbxColor3uint8,
+    pub udim: RbxUDim,
+    pub udim2: RbxUDim2,
+    pub cframe: RbxCFrame,
+    pub enumeration: u32,
+    pub referent: RbxRef,
+}
+
+/// The value of a property, tagged with its type.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RbxValue {
+    pub ty: RbxValueType,
+    pub data: RbxValueData,
+}
+
+fn vector3(value: Vector3) -> RbxVector3 {
+    RbxVector3 {
+        x: value.x,
+        y: value.y,
+        z: value.z,
+    }
+}
+
+fn udim(value: UDim) -> RbxUDim {
+    RbxUDim {
+        scale: value.scale,
+        offset: value.offset,
+    }
+}
+
+/// Converts a value to an `RbxValue`, borrowing strings from it.
+fn to_value(dom: &RbxDom, name: &str, value: &Variant) -> Result<RbxValue, RbxStatus> {
+    let (ty, data) = match value {
+        Variant::Bool(value) => (RbxValueType::Bool, RbxValueData { boolean: *value }),
+        Variant::Int32(value) => (RbxValueType::Int32, RbxValueData { int32: *value }),
+        Variant::Int64(value) => (RbxValueType::Int64, RbxValueData { int64: *value }),
+        Variant::Float32(value) => (RbxValueType::Float32, RbxValueData { float32: *value }),
+        Variant::Float64(value) => (RbxValueType::Float64, RbxValueData { float64: *value }),
+        Variant::String(value) => (
+            RbxValueType::String,
+            RbxValueData {
+                string: RbxSlice::new(value.as_bytes()),
+            },
+        ),
+        Variant::Content(value) => (
+            RbxValueType::Content,
+            RbxValueData {
+                string: RbxSlice::new(AsRef::<str>::as_ref(value).as_bytes()),
+            },
+        ),
+        Variant::BinaryString(value) => (
+            RbxValueType::BinaryString,
+            RbxValueData {
+                string: RbxSlice::new(value.as_ref()),
+            },
+        ),
+        Variant::Vector2(value) => (
+            RbxValueType::Vector2,
+            RbxValueData {
+                vector2: RbxVector2 {
+                    x: value.x,
+                    y: value.y,
+                },
+            },
+        ),
+        Variant::Vector3(value) => (
+            RbxValueType::Vector3,
+            RbxValueData {
+                vector3: vector3(*value),
+            },
+        ),
+        Variant::Color3(value) => (
+            RbxValueType::Color3,
+            RbxValueData {
+                color3: RbxColor3 {
+                    r: value.r,
+                    g: value.g,
+                    b: value.b,
+                },
+            },
+        ),
+        Variant::Color3uint8(value) => (
+            RbxValueType::Color3uint8,
+            RbxValueData {
+                color3uint8: RbxColor3uint8 {
+                    r: value.r,
+                    g: value.g,
+                    b: value.b,
+                },
+            },
+        ),
+        Variant::UDim(value) => (RbxValueType::UDim, RbxValueData { udim: udim(*value) }),
+        Variant::UDim2(value) => (
+            RbxValueType::UDim2,
+            RbxValueData {
+                udim2: RbxUDim2 {
+                    x: udim(value.x),
+                    y: udim(value.y),
+                },
+            },
+        ),
+        Variant::CFrame(value) => (
+            RbxValueType::CFrame,
+            RbxValueData {
+                cframe: RbxCFrame {
+                    position: vector3(value.position),
+                    orientation: [
+                        vector3(value.orientation.x),
+                        vector3(value.orientation.y),
+                        vector3(value.orientation.z),
+                    ],
+                },
+            },
+        ),
+        Variant::Enum(value) => (
+            RbxValueType::Enum,
+            RbxValueData {
+                enumeration: value.to_u32(),
+            },
+        ),
+        Variant::Ref(value) => (
+            RbxValueType::Ref,
+            RbxValueData {
+                referent: dom.handle(*value),
+            },
+        ),
+        other => {
+            return Err(fail(
+                RbxStatus::UnsupportedType,
+                format_args!(
+                    "property {} is a {:?}, which can't be passed through the C API",
+                    name,
+                    other.ty()
+                ),
+            ))
+        }
+    };
+
+    Ok(RbxValue { ty, data })
+}
+
+/// Converts an `RbxValue` from the caller to a value, copying its strings.
+unsafe fn from_value(dom: &RbxDom, value: &RbxValue) -> Result<Variant, RbxStatus> {
+    let data = &value.data;
+    let vector3 = |value: RbxVector3| Vector3::new(value.x, value.y, value.z);
+    let udim = |value: RbxUDim| UDim::new(value.scale, value.offset);
+
+    Ok(match value.ty {
+        RbxValueType::Bool => Variant::Bool(data.boolean),
+        RbxValueType::Int32 => Variant::Int32(data.int32),
+        RbxValueType::Int64 => Variant::Int64(data.int64),
+        RbxValueType::Float32 => Variant::Float32(data.float32),
+        RbxValueType::Float64 => Variant::Float64(data.float64),
+        RbxValueType::String => Variant::String(data.string.as_str()?.to_owned()),
+        RbxValueType::Content => Variant::Content(Content::from(data.string.as_str()?)),
+        RbxValueType::BinaryString => {
+            Variant::BinaryString(BinaryString::from(data.string.as_bytes()?.to_vec()))
+        }
+        RbxValueType::Vector2 => Variant::Vector2(Vector2::new(data.vector2.x, data.vector2.y)),
+        RbxValueType::Vector3 => Variant::Vector3(vector3(data.vector3)),
+        RbxValueType::Color3 => {
+            let color = data.color3;
+            Variant::Color3(Color3::new(color.r, color.g, color.b))
+        }
+        RbxValueType::Color3uint8 => {
+            let color = data.color3uint8;
+            Variant::Color3uint8(Color3uint8::new(color.r, color.g, color.b))
+        }
+        RbxValueType::UDim => Variant::UDim(udim(data.udim)),
+        RbxValueType::UDim2 => Variant::UDim2(UDim2::new(udim(data.udim2.x), udim(data.udim2.y))),
+        RbxValueType::CFrame => {
+            let cframe = data.cframe;
+            let [x, y, z] = cframe.orientation;
+            Variant::CFrame(CFrame::new(
+                vector3(cframe.position),
+                Matrix3::new(vector3(x), vector3(y), vector3(z)),
+            ))
+        }
+        RbxValueType::Enum => Variant::Enum(Enum::from_u32(data.enumeration)),
+        RbxValueType::Ref => {
+            let handle = data.referent;
+            if handle != 0 && dom.dom.get_by_ref(dom.referent(handle)).is_none() {
+                return Err(invalid_instance(handle));
+            }
+            Variant::Ref(dom.referent(handle))
+        }
+    })
+}
+
+/// The names of an instance's properties, sorted so that indices are stable.
+fn property_names(dom: &RbxDom, instance: RbxRef) -> Result<Vec<&str>, RbxStatus> {
+    let mut names: Vec<&str> = dom
+        .instance(instance)?
+        .properties
+        .keys()
+        .map(String::as_str)
+        .collect();
+    names.sort_unstable();
+
+    Ok(names)
+}
+
+/// Gets the number of properties an instance has. Name and ClassName aren't
+/// counted, since they aren't properties in rbx-dom.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_property_count(
+    dom: *const RbxDom,
+    instance: RbxRef,
+    out: *mut usize,
+) -> RbxStatus {
+    run(|| {
+        let dom = deref(dom, "dom")?;
+        let count = dom.instance(instance)?.properties.len();
+        write_out(out, "out", count)
+    })
+}
+
+/// Gets the name of an instance's property at `index`, counting from zero.
+/// Properties are sorted by name.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_property_name(
+    dom: *const RbxDom,
+    instance: RbxRef,
+    index: usize,
+    out: *mut RbxSlice,
+) -> RbxStatus {
+    run(|| {
+        let dom = deref(dom, "dom")?;
+        let names = property_names(dom, instance)?;
+        let name = names.get(index).ok_or_else(|| {
+            fail(
+                RbxStatus::IndexOutOfRange,
+                format_args!(
+                    "property {} is out of range for an instance with {} properties",
+                    index,
+                    names.len()
+                ),
+            )
+        })?;
+
+        write_out(out, "out", RbxSlice::new(name.as_bytes()))
+    })
+}
+
+/// Gets the value of a property. Strings in the value are borrowed from the
+/// DOM.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_get_property(
+    dom: *const RbxDom,
+    instance: RbxRef,
+    name: *const c_char,
+    out: *mut RbxValue,
+) -> RbxStatus {
+    run(|| {
+        let dom = deref(dom, "dom")?;
+        let name = read_str(name, "name")?;
+        let value = dom
+            .instance(instance)?
+            .properties
+            .get(name)
+            .ok_or_else(|| {
+                fail(
+                    RbxStatus::PropertyNotFound,
+                    format_args!("the instance has no property named {}", name),
+                )
+            })?;
+
+        write_out(out, "out", to_value(dom, name, value)?)
+    })
+}
+
+/// Sets the value of a property, copying any strings in it.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_set_property(
+    dom: *mut RbxDom,
+    instance: RbxRef,
+    name: *const c_char,
+    value: *const RbxValue,
+) -> RbxStatus {
+    run(|| {
+        let dom = deref_mut(dom, "dom")?;
+        let name = read_str(name, "name")?;
+        let value = from_value(dom, deref(value, "value")?)?;
+
+        dom.instance_mut(instance)?
+            .properties
+            .insert(name.to_owned(), value);
+        Ok(())
+    })
+}
+
+/// Removes a property from an instance.
+#[no_mangle]
+pub unsafe extern "C" fn rbx_instance_remove_property(
+    dom: *mut RbxDom,
+    instance: RbxRef,
+    name: *const c_char,
+) -> RbxStatus {
+    run(|| {
+        let dom = deref_mut(dom, "dom")?;
+        let name = read_str(name, "name")?;
+
+        match dom.instance_mut(instance)?.properties.remove(name) {
+            Some(_) => Ok(()),
+            None => Err(fail(
+                RbxStatus::PropertyNotFound,
+                format_args!("the instance has no property named {}", name),
+            )),
+        }
+    })
+}
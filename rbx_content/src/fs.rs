@@ -0,0 +1,140 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::{ContentResolver, ContentUrl, ResolveError};
+
+/// Reads assets from files on disk.
+///
+/// Uploaded assets are found by ID in the asset directory, either as a file
+/// named just the ID or as one with any extension, like `123.png` or
+/// `123.mesh`. `rbxasset://` URLs are paths within the content directory,
+/// which is the asset directory unless set with
+/// [`content_dir`](Self::content_dir). Pointing it at the `content` directory
+/// of a Roblox Studio install finds the files that ship with Roblox.
+#[derive(Debug)]
+pub struct FileSystemResolver {
+    asset_dir: PathBuf,
+    content_dir: PathBuf,
+
+    /// The files in the asset directory by the IDs in their names, read the
+    /// first time an ID is resolved.
+    by_id: OnceLock<HashMap<u64, PathBuf>>,
+}
+
+impl FileSystemResolver {
+    /// Create a new `FileSystemResolver` that reads every asset from `dir`.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        let dir = dir.into();
+
+        Self {
+            content_dir: dir.clone(),
+            asset_dir: dir,
+            by_id: OnceLock::new(),
+        }
+    }
+
+    /// Sets the directory that `rbxasset://` URLs are read from.
+    pub fn content_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.content_dir = dir.into();
+        self
+    }
+
+    fn find_id(&self, id: u64) -> Option<&Path> {
+        let by_id = self.by_id.get_or_init(|| {
+            let mut by_id = HashMap::new();
+            let entries = match fs::read_dir(&self.asset_dir) {
+                Ok(entries) => entries,
+                Err(_) => return by_id,
+            };
+
+            for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+                let id = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse().ok());
+
+                if let Some(id) = id {
+                    if path.is_file() {
+                        by_id.entry(id).or_insert(path);
+                    }
+                }
+            }
+
+            by_id
+        });
+
+        by_id.get(&id).map(PathBuf::as_path)
+    }
+}
+
+impl ContentResolver for FileSystemResolver {
+    fn resolve(&self, url: &str) -> Result<Vec<u8>, ResolveError> {
+        let path = match ContentUrl::parse(url) {
+            Some(ContentUrl::AssetId(id)) => self.find_id(id).map(Path::to_owned),
+            Some(ContentUrl::Asset(path)) => {
+                // Don't let a URL read files from outside of the content
+                // directory.
+                let path = Path::new(path);
+                if !path
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_)))
+                {
+                    return Err(ResolveError::Unsupported(url.to_owned()));
+                }
+
+                Some(self.content_dir.join(path)).filter(|path| path.is_file())
+            }
+            None => return Err(ResolveError::Unsupported(url.to_owned())),
+        };
+
+        let path = path.ok_or_else(|| ResolveError::NotFound(url.to_owned()))?;
+        fs::read(&path).map_err(|error| ResolveError::Io(path, error))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resolve_files() {
+        let assets = tempfile::tempdir().unwrap();
+        fs::write(assets.path().join("123.png"), b"texture").unwrap();
+        fs::write(assets.path().join("456"), b"mesh").unwrap();
+
+        let content = tempfile::tempdir().unwrap();
+        fs::create_dir(content.path().join("textures")).unwrap();
+        fs::write(content.path().join("textures/face.png"), b"face").unwrap();
+
+        let resolver = FileSystemResolver::new(assets.path()).content_dir(content.path());
+
+        assert_eq!(resolver.resolve("rbxassetid://123").unwrap(), b"texture");
+        assert_eq!(
+            resolver
+                .resolve("https://www.roblox.com/asset/?id=456")
+                .unwrap(),
+            b"mesh"
+        );
+        assert_eq!(
+            resolver.resolve("rbxasset://textures/face.png").unwrap(),
+            b"face"
+        );
+
+        assert!(matches!(
+            resolver.resolve("rbxassetid://789"),
+            Err(ResolveError::NotFound(_))
+        ));
+        assert!(matches!(
+            resolver.resolve("rbxasset://../secret.txt"),
+            Err(ResolveError::Unsupported(_))
+        ));
+        assert!(matches!(
+            resolver.resolve("not a url"),
+            Err(ResolveError::Unsupported(_))
+        ));
+    }
+}
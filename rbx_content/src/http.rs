@@ -0,0 +1,75 @@
+use std::io::Read;
+
+use crate::{ContentResolver, ContentUrl, ResolveError};
+
+/// Downloads uploaded assets from Roblox by their IDs.
+///
+/// `rbxasset://` URLs point to files that ship with Roblox rather than
+/// uploaded assets, so this resolver doesn't support them.
+/// [`FileSystemResolver`](crate::FileSystemResolver) can read them from a
+/// Roblox Studio install instead.
+pub struct HttpResolver {
+    agent: ureq::Agent,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl HttpResolver {
+    /// Create a new `HttpResolver` that downloads from Roblox's asset delivery
+    /// API.
+    pub fn new() -> Self {
+        Self {
+            agent: ureq::Agent::new(),
+            base_url: "https://assetdelivery.roblox.com/v1/asset/?id=".to_owned(),
+            api_key: None,
+        }
+    }
+
+    /// Sets the URL that asset IDs are appended to, like to download through
+    /// a proxy or cache.
+    pub fn base_url<S: Into<String>>(mut self, base_url: S) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Sets the Open Cloud API key to send with every request, which Roblox
+    /// requires to download assets that aren't public.
+    pub fn api_key<S: Into<String>>(mut self, api_key: Option<S>) -> Self {
+        self.api_key = api_key.map(Into::into);
+        self
+    }
+}
+
+impl Default for HttpResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContentResolver for HttpResolver {
+    fn resolve(&self, url: &str) -> Result<Vec<u8>, ResolveError> {
+        let id = match ContentUrl::parse(url) {
+            Some(ContentUrl::AssetId(id)) => id,
+            _ => return Err(ResolveError::Unsupported(url.to_owned())),
+        };
+
+        let mut request = self.agent.get(&format!("{}{}", self.base_url, id));
+        if let Some(api_key) = &self.api_key {
+            request = request.set("x-api-key", api_key);
+        }
+
+        let response = match request.call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404, _)) => return Err(ResolveError::NotFound(url.to_owned())),
+            Err(error) => return Err(ResolveError::Http(url.to_owned(), Box::new(error))),
+        };
+
+        let mut bytes = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut bytes)
+            .map_err(|error| ResolveError::Http(url.to_owned(), Box::new(error)))?;
+
+        Ok(bytes)
+    }
+}
@@ -0,0 +1,216 @@
+/*!
+Resolves Roblox Content URLs to the bytes of the assets they point to.
+
+Content properties like `Decal.Texture` and `MeshPart.MeshId` hold URLs
+rather than the assets themselves. Tools that want the assets, like to draw
+meshes or check the sizes of textures, get them through a
+[`ContentResolver`], so that every tool can find assets the same way.
+
+```
+use rbx_content::{ContentResolver, FileSystemResolver};
+
+# let dir = tempfile::tempdir()?;
+# std::fs::write(dir.path().join("123.png"), b"png")?;
+let resolver = FileSystemResolver::new(dir.path());
+assert_eq!(resolver.resolve("rbxassetid://123")?, b"png");
+# Ok::<(), Box<dyn std::error::Error>>(())
+```
+
+[`FileSystemResolver`] reads assets from a directory. With the `http`
+feature, `HttpResolver` downloads them from Roblox, and [`ChainResolver`]
+can combine them to only download what isn't on disk.
+*/
+
+mod fs;
+#[cfg(feature = "http")]
+mod http;
+
+use std::{io, path::PathBuf};
+
+use thiserror::Error;
+
+pub use crate::fs::FileSystemResolver;
+#[cfg(feature = "http")]
+pub use crate::http::HttpResolver;
+
+/// Finds the bytes of the asset that a Content URL points to.
+pub trait ContentResolver {
+    /// Returns the bytes of the asset at `url`, like `rbxassetid://123` or
+    /// `rbxasset://textures/face.png`.
+    fn resolve(&self, url: &str) -> Result<Vec<u8>, ResolveError>;
+}
+
+impl<R: ContentResolver + ?Sized> ContentResolver for &R {
+    fn resolve(&self, url: &str) -> Result<Vec<u8>, ResolveError> {
+        (**self).resolve(url)
+    }
+}
+
+impl<R: ContentResolver + ?Sized> ContentResolver for Box<R> {
+    fn resolve(&self, url: &str) -> Result<Vec<u8>, ResolveError> {
+        (**self).resolve(url)
+    }
+}
+
+/// The kinds of Content URLs that point to assets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentUrl<'a> {
+    /// An asset uploaded to Roblox, from `rbxassetid://<id>` or a URL like
+    /// `https://www.roblox.com/asset/?id=<id>`.
+    AssetId(u64),
+
+    /// A file that ships with Roblox, from `rbxasset://<path>`. The path is
+    /// relative to the client's content directory.
+    Asset(&'a str),
+}
+
+impl<'a> ContentUrl<'a> {
+    /// Parses a Content URL, or returns `None` if it doesn't point to an
+    /// asset, like an empty URL.
+    pub fn parse(url: &'a str) -> Option<Self> {
+        let url = url.trim();
+
+        if let Some(id) = strip_prefix_ignore_case(url, "rbxassetid://") {
+            return id.parse().ok().map(ContentUrl::AssetId);
+        }
+        if let Some(path) = strip_prefix_ignore_case(url, "rbxasset://") {
+            return Some(ContentUrl::Asset(path));
+        }
+
+        let (_, query) = url.split_once('?')?;
+        query
+            .split('&')
+            .find_map(|pair| strip_prefix_ignore_case(pair, "id="))
+            .and_then(|id| id.parse().ok())
+            .map(ContentUrl::AssetId)
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(value: &'a str, prefix: &str) -> Option<&'a str> {
+    let start = value.get(..prefix.len())?;
+    start
+        .eq_ignore_ascii_case(prefix)
+        .then(|| &value[prefix.len()..])
+}
+
+/// Tries several resolvers in order, returning the asset from the first one
+/// that has it.
+///
+/// Resolvers that don't have an asset or don't understand its URL are
+/// skipped. Any other error, like a file that couldn't be read, stops the
+/// search.
+#[derive(Default)]
+pub struct ChainResolver<'a> {
+    resolvers: Vec<Box<dyn ContentResolver + 'a>>,
+}
+
+impl<'a> ChainResolver<'a> {
+    /// Create a new `ChainResolver` with no resolvers, which finds nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a resolver to try after the ones that were already added.
+    pub fn with<R: ContentResolver + 'a>(mut self, resolver: R) -> Self {
+        self.resolvers.push(Box::new(resolver));
+        self
+    }
+}
+
+impl ContentResolver for ChainResolver<'_> {
+    fn resolve(&self, url: &str) -> Result<Vec<u8>, ResolveError> {
+        let mut result = Err(ResolveError::NotFound(url.to_owned()));
+
+        for resolver in &self.resolvers {
+            result = resolver.resolve(url);
+            match result {
+                Err(ResolveError::NotFound(_)) | Err(ResolveError::Unsupported(_)) => {}
+                _ => break,
+            }
+        }
+
+        result
+    }
+}
+
+/// An error that can occur when resolving a Content URL.
+#[derive(Debug, Error)]
+pub enum ResolveError {
+    /// The URL isn't one that this resolver knows how to find.
+    #[error("{0:?} is not a Content URL that can be resolved")]
+    Unsupported(String),
+
+    /// The URL is valid, but there's no asset for it.
+    #[error("no asset found for {0}")]
+    NotFound(String),
+
+    /// The file holding the asset couldn't be read.
+    #[error("couldn't read {}: {}", .0.display(), .1)]
+    Io(PathBuf, #[source] io::Error),
+
+    /// The asset couldn't be downloaded.
+    #[cfg(feature = "http")]
+    #[error("couldn't download {0}: {1}")]
+    Http(String, #[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_urls() {
+        assert_eq!(
+            ContentUrl::parse("rbxassetid://123"),
+            Some(ContentUrl::AssetId(123))
+        );
+        assert_eq!(
+            ContentUrl::parse(" RbxAssetId://123 "),
+            Some(ContentUrl::AssetId(123))
+        );
+        assert_eq!(
+            ContentUrl::parse("http://www.roblox.com/asset/?id=456"),
+            Some(ContentUrl::AssetId(456))
+        );
+        assert_eq!(
+            ContentUrl::parse("https://www.roblox.com/asset/?version=1&ID=789"),
+            Some(ContentUrl::AssetId(789))
+        );
+        assert_eq!(
+            ContentUrl::parse("rbxasset://textures/face.png"),
+            Some(ContentUrl::Asset("textures/face.png"))
+        );
+        assert_eq!(ContentUrl::parse(""), None);
+        assert_eq!(ContentUrl::parse("rbxassetid://abc"), None);
+    }
+
+    struct Fixed(&'static str, &'static [u8]);
+
+    impl ContentResolver for Fixed {
+        fn resolve(&self, url: &str) -> Result<Vec<u8>, ResolveError> {
+            if url == self.0 {
+                Ok(self.1.to_vec())
+            } else {
+                Err(ResolveError::NotFound(url.to_owned()))
+            }
+        }
+    }
+
+    #[test]
+    fn chain() {
+        let resolver = ChainResolver::new()
+            .with(Fixed("rbxassetid://1", b"one"))
+            .with(Fixed("rbxassetid://2", b"two"));
+
+        assert_eq!(resolver.resolve("rbxassetid://1").unwrap(), b"one");
+        assert_eq!(resolver.resolve("rbxassetid://2").unwrap(), b"two");
+        assert!(matches!(
+            resolver.resolve("rbxassetid://3"),
+            Err(ResolveError::NotFound(_))
+        ));
+        assert!(matches!(
+            ChainResolver::new().resolve("rbxassetid://1"),
+            Err(ResolveError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,226 @@
+use rbx_types::{Ref, Variant};
+
+use crate::{Instance, InstanceBuilder, WeakDom};
+
+/**
+Constructs a [`WeakDom`] from a stream of events, without first building a
+tree of [`InstanceBuilder`] objects.
+
+Each instance is described by a call to [`DomBuilder::start_instance`],
+followed by any number of calls to [`DomBuilder::name`] and
+[`DomBuilder::property`], then any children, and finally a call to
+[`DomBuilder::end_instance`]. Instances started at the top level become
+children of the root instance passed to [`DomBuilder::new`].
+
+An instance is only inserted into the dom once its properties are known,
+which is when its first child is started or when it is ended. Until then, it
+is not visible through [`DomBuilder::dom`].
+
+## Examples
+```
+use rbx_dom_weak::{DomBuilder, InstanceBuilder};
+
+let mut builder = DomBuilder::new(InstanceBuilder::new("DataModel"));
+
+builder.start_instance("Workspace");
+builder.property("Gravity", 196.2f32);
+
+builder.start_instance("Part");
+builder.name("Baseplate");
+builder.property("Anchored", true);
+builder.end_instance();
+
+builder.end_instance();
+
+let dom = builder.finish();
+let workspace = dom.get_by_ref(dom.root().children()[0]).unwrap();
+assert_eq!(workspace.children().len(), 1);
+```
+*/
+#[derive(Debug)]
+pub struct DomBuilder {
+    dom: WeakDom,
+    stack: Vec<PendingInstance>,
+}
+
+#[derive(Debug)]
+struct PendingInstance {
+    referent: Ref,
+    parent: Ref,
+    /// Holds the instance until it has been inserted into the dom.
+    builder: Option<InstanceBuilder>,
+}
+
+impl DomBuilder {
+    /// Create a new `DomBuilder` that builds a `WeakDom` with the given root.
+    /// Any children of `root` are inserted as well.
+    pub fn new(root: InstanceBuilder) -> Self {
+        Self {
+            dom: WeakDom::new(root),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Returns the `WeakDom` being built. Instances that have been started but
+    /// whose properties may still change are not yet contained in it.
+    pub fn dom(&self) -> &WeakDom {
+        &self.dom
+    }
+
+    /// Returns how many instances have been started but not yet ended.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Start a new instance with the given ClassName, which is also used as its
+    /// Name unless [`DomBuilder::name`] is called. The instance is a child of
+    /// the most recently started instance that has not been ended, or of the
+    /// root if there is none.
+    ///
+    /// Returns the referent that the instance will have in the finished dom.
+    pub fn start_instance<S: Into<String>>(&mut self, class: S) -> Ref {
+        let parent = match self.stack.last_mut() {
+            Some(parent) => {
+                Self::flush(&mut self.dom, parent);
+                parent.referent
+            }
+            None => self.dom.root_ref(),
+        };
+
+        let builder = InstanceBuilder::new(class);
+        let referent = builder.referent();
+
+        self.stack.push(PendingInstance {
+            referent,
+            parent,
+            builder: Some(builder),
+        });
+
+        referent
+    }
+
+    /// Set the Name of the current instance.
+    ///
+    /// ## Panics
+    /// Panics if there is no current instance.
+    pub fn name<S: Into<String>>(&mut self, name: S) {
+        match self.current() {
+            Some(builder) => builder.set_name(name),
+            None => self.current_in_dom().name = name.into(),
+        }
+    }
+
+    /// Set a property on the current instance.
+    ///
+    /// ## Panics
+    /// Panics if there is no current instance.
+    pub fn property<K: Into<String>, V: Into<Variant>>(&mut self, key: K, value: V) {
+        match self.current() {
+            Some(builder) => builder.add_property(key, value),
+            None => {
+                self.current_in_dom()
+                    .properties
+                    .insert(key.into(), value.into());
+            }
+        }
+    }
+
+    /// End the current instance, making its parent current again.
+    ///
+    /// ## Panics
+    /// Panics if there is no current instance.
+    pub fn end_instance(&mut self) {
+        let mut pending = self
+            .stack
+            .pop()
+            .expect("cannot end an instance when none have been started");
+
+        Self::flush(&mut self.dom, &mut pending);
+    }
+
+    /// Consumes the `DomBuilder`, returning the finished `WeakDom`.
+    ///
+    /// ## Panics
+    /// Panics if any instances have been started but not ended.
+    pub fn finish(self) -> WeakDom {
+        if !self.stack.is_empty() {
+            panic!(
+                "cannot finish a DomBuilder with {} instance(s) that were not ended",
+                self.stack.len()
+            );
+        }
+
+        self.dom
+    }
+
+    fn current(&mut self) -> Option<&mut InstanceBuilder> {
+        self.stack
+            .last_mut()
+            .expect("there is no current instance to modify")
+            .builder
+            .as_mut()
+    }
+
+    fn current_in_dom(&mut self) -> &mut Instance {
+        let referent = self.stack.last().unwrap().referent;
+        self.dom.get_by_ref_mut(referent).unwrap()
+    }
+
+    fn flush(dom: &mut WeakDom, pending: &mut PendingInstance) {
+        if let Some(builder) = pending.builder.take() {
+            dom.insert(pending.parent, builder);
+        }
+    }
+}
+
+impl Default for DomBuilder {
+    /// Creates a `DomBuilder` whose root is a `DataModel`, which is what most
+    /// deserializers produce.
+    fn default() -> Self {
+        Self::new(InstanceBuilder::new("DataModel"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::DomViewer;
+
+    #[test]
+    fn build_tree() {
+        let mut builder = DomBuilder::default();
+
+        let model_ref = builder.start_instance("Model");
+        builder.name("Tower");
+
+        let part_ref = builder.start_instance("Part");
+        builder.property("Size", rbx_types::Vector3::new(4.0, 1.0, 2.0));
+        builder.end_instance();
+
+        // Properties set after a child has been started are still applied.
+        builder.property("PrimaryPart", part_ref);
+        assert_eq!(builder.depth(), 1);
+        builder.end_instance();
+
+        builder.start_instance("Folder");
+        builder.end_instance();
+
+        let dom = builder.finish();
+        assert_eq!(dom.root().children().len(), 2);
+        assert_eq!(dom.get_by_ref(model_ref).unwrap().children(), [part_ref]);
+
+        // This snapshot should contain a Model named Tower with a Part inside
+        // of it and a PrimaryPart pointing to that Part, followed by a Folder.
+        let mut viewer = DomViewer::new();
+        insta::assert_yaml_snapshot!(viewer.view_children(&dom));
+    }
+
+    #[test]
+    #[should_panic(expected = "1 instance(s) that were not ended")]
+    fn unbalanced() {
+        let mut builder = DomBuilder::default();
+        builder.start_instance("Folder");
+        builder.finish();
+    }
+}
@@ -0,0 +1,398 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
+
+use rbx_types::{CFrame, PhysicalProperties, Ref, Variant, Vector3};
+
+use crate::WeakDom;
+
+/// Options controlling how [`compare_doms`] decides whether two doms are
+/// equal.
+#[derive(Debug, Clone)]
+pub struct DomCompareOptions {
+    float_tolerance: f64,
+    ignore_child_order: bool,
+}
+
+impl DomCompareOptions {
+    /// Create a new `DomCompareOptions` with the default float tolerance that
+    /// requires children to be in the same order.
+    pub fn new() -> Self {
+        Self {
+            float_tolerance: 1.0e-5,
+            ignore_child_order: false,
+        }
+    }
+
+    /// Set the largest absolute difference allowed between two floats that are
+    /// considered equal, including floats inside of types like `Vector3` and
+    /// `CFrame`. A tolerance of zero requires floats to be exactly equal.
+    pub fn float_tolerance(self, float_tolerance: f64) -> Self {
+        Self {
+            float_tolerance,
+            ..self
+        }
+    }
+
+    /// Set whether children may appear in a different order in each dom.
+    /// Children are paired up by their ClassName and Name when this is enabled.
+    pub fn ignore_child_order(self, ignore_child_order: bool) -> Self {
+        Self {
+            ignore_child_order,
+            ..self
+        }
+    }
+}
+
+impl Default for DomCompareOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A single way in which two doms compared with [`compare_doms`] differ.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomDifference {
+    /// The path to the instance in the left dom that differs, made from the
+    /// names of its ancestors joined with `.`.
+    pub path: String,
+
+    /// A description of how the instance differs.
+    pub message: String,
+}
+
+impl fmt::Display for DomDifference {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Compares two doms structurally, starting at their roots, and returns every
+/// difference found. An empty list means the doms are equal.
+///
+/// Referents are not compared directly. Instead, two Ref properties are equal if
+/// they point to instances in the same position in each dom.
+pub fn compare_doms(
+    left: &WeakDom,
+    right: &WeakDom,
+    options: &DomCompareOptions,
+) -> Vec<DomDifference> {
+    let mut context = CompareContext {
+        left,
+        right,
+        options,
+        pairs: Vec::new(),
+        ref_map: HashMap::new(),
+        differences: Vec::new(),
+    };
+
+    context.pair_instances(left.root_ref(), right.root_ref(), left.root().name.clone());
+    context.compare_pairs();
+    context.differences
+}
+
+struct CompareContext<'a> {
+    left: &'a WeakDom,
+    right: &'a WeakDom,
+    options: &'a DomCompareOptions,
+    pairs: Vec<(Ref, Ref, String)>,
+    ref_map: HashMap<Ref, Ref>,
+    differences: Vec<DomDifference>,
+}
+
+impl<'a> CompareContext<'a> {
+    /// Walks both trees, pairing up instances that are in the same position,
+    /// so that Ref properties can be compared afterwards.
+    fn pair_instances(&mut self, left_ref: Ref, right_ref: Ref, path: String) {
+        let mut queue = vec![(left_ref, right_ref, path)];
+
+        while let Some((left_ref, right_ref, path)) = queue.pop() {
+            let left = self.left.get_by_ref(left_ref).unwrap();
+            let right = self.right.get_by_ref(right_ref).unwrap();
+
+            self.ref_map.insert(left_ref, right_ref);
+            self.pairs.push((left_ref, right_ref, path.clone()));
+
+            let left_children = left.children();
+            let mut right_children: Vec<Option<Ref>> =
+                right.children().iter().copied().map(Some).collect();
+
+            for (index, &left_child_ref) in left_children.iter().enumerate() {
+                let left_child = self.left.get_by_ref(left_child_ref).unwrap();
+                let child_path = format!("{}.{}", path, left_child.name);
+
+                let matched = if self.options.ignore_child_order {
+                    right_children.iter_mut().find(|candidate| {
+                        candidate.is_some_and(|candidate| {
+                            let right_child = self.right.get_by_ref(candidate).unwrap();
+                            right_child.class == left_child.class
+                                && right_child.name == left_child.name
+                        })
+                    })
+                } else {
+                    right_children.get_mut(index)
+                };
+
+                match matched.and_then(Option::take) {
+                    Some(right_child_ref) => {
+                        queue.push((left_child_ref, right_child_ref, child_path))
+                    }
+                    None => self.difference(child_path, "missing from right dom".to_owned()),
+                }
+            }
+
+            for right_child_ref in right_children.into_iter().flatten() {
+                let right_child = self.right.get_by_ref(right_child_ref).unwrap();
+                self.difference(
+                    format!("{}.{}", path, right_child.name),
+                    "missing from left dom".to_owned(),
+                );
+            }
+        }
+    }
+
+    fn compare_pairs(&mut self) {
+        let pairs = std::mem::take(&mut self.pairs);
+
+        for (left_ref, right_ref, path) in pairs {
+            let left = self.left.get_by_ref(left_ref).unwrap();
+            let right = self.right.get_by_ref(right_ref).unwrap();
+
+            if left.class != right.class {
+                self.difference(
+                    path.clone(),
+                    format!("ClassName differs: {:?} != {:?}", left.class, right.class),
+                );
+            }
+
+            if left.name != right.name {
+                self.difference(
+                    path.clone(),
+                    format!("Name differs: {:?} != {:?}", left.name, right.name),
+                );
+            }
+
+            let names: BTreeSet<&String> = left
+                .properties
+                .keys()
+                .chain(right.properties.keys())
+                .collect();
+
+            for name in names {
+                match (left.properties.get(name), right.properties.get(name)) {
+                    (Some(left_value), Some(right_value)) => {
+                        if !self.variants_eq(left_value, right_value) {
+                            self.difference(
+                                path.clone(),
+                                format!(
+                                    "property {} differs: {:?} != {:?}",
+                                    name, left_value, right_value
+                                ),
+                            );
+                        }
+                    }
+                    (Some(_), None) => self.difference(
+                        path.clone(),
+                        format!("property {} is missing from right dom", name),
+                    ),
+                    (None, Some(_)) => self.difference(
+                        path.clone(),
+                        format!("property {} is missing from left dom", name),
+                    ),
+                    (None, None) => unreachable!(),
+                }
+            }
+        }
+
+        // Instances are visited in no particular order, so we sort to keep
+        // the output stable and readable.
+        self.differences.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+
+    fn variants_eq(&self, left: &Variant, right: &Variant) -> bool {
+        match (left, right) {
+            (Variant::Ref(left), Variant::Ref(right)) => {
+                if left.is_none() || right.is_none() {
+                    left == right
+                } else {
+                    match self.ref_map.get(left) {
+                        Some(mapped) => mapped == right,
+                        // Both refs point outside of the dom.
+                        None => self.right.get_by_ref(*right).is_none(),
+                    }
+                }
+            }
+            (Variant::Attributes(left), Variant::Attributes(right)) => {
+                let mut left = left.iter();
+                let mut right = right.iter();
+
+                loop {
+                    match (left.next(), right.next()) {
+                        (Some((left_key, left_value)), Some((right_key, right_value))) => {
+                            if left_key != right_key || !self.variants_eq(left_value, right_value) {
+                                return false;
+                            }
+                        }
+                        (None, None) => return true,
+                        _ => return false,
+                    }
+                }
+            }
+            _ => match (float_components(left), float_components(right)) {
+                (Some(left_floats), Some(right_floats)) if left.ty() == right.ty() => {
+                    left_floats.len() == right_floats.len()
+                        && left_floats
+                            .iter()
+                            .zip(&right_floats)
+                            .all(|(&a, &b)| self.floats_eq(a, b))
+                }
+                _ => left == right,
+            },
+        }
+    }
+
+    fn floats_eq(&self, left: f64, right: f64) -> bool {
+        (left.is_nan() && right.is_nan())
+            || left == right
+            || (left - right).abs() <= self.options.float_tolerance
+    }
+
+    fn difference(&mut self, path: String, message: String) {
+        self.differences.push(DomDifference { path, message });
+    }
+}
+
+/// Returns every float contained in the given value if it is made up entirely
+/// of floats, or `None` otherwise.
+fn float_components(value: &Variant) -> Option<Vec<f64>> {
+    fn vector3(v: &Vector3) -> [f64; 3] {
+        [v.x as f64, v.y as f64, v.z as f64]
+    }
+
+    fn cframe(cframe: &CFrame) -> Vec<f64> {
+        let o = &cframe.orientation;
+        [cframe.position, o.x, o.y, o.z]
+            .iter()
+            .flat_map(vector3)
+            .collect()
+    }
+
+    Some(match value {
+        Variant::Float32(v) => vec![*v as f64],
+        Variant::Float64(v) => vec![*v],
+        Variant::Vector2(v) => vec![v.x as f64, v.y as f64],
+        Variant::Vector3(v) => vector3(v).to_vec(),
+        Variant::CFrame(v) => cframe(v),
+        Variant::OptionalCFrame(Some(v)) => cframe(v),
+        Variant::Color3(v) => vec![v.r as f64, v.g as f64, v.b as f64],
+        Variant::NumberRange(v) => vec![v.min as f64, v.max as f64],
+        Variant::Rect(v) => vec![
+            v.min.x as f64,
+            v.min.y as f64,
+            v.max.x as f64,
+            v.max.y as f64,
+        ],
+        Variant::Ray(v) => [vector3(&v.origin), vector3(&v.direction)].concat(),
+        Variant::Region3(v) => [vector3(&v.min), vector3(&v.max)].concat(),
+        Variant::NumberSequence(v) => v
+            .keypoints
+            .iter()
+            .flat_map(|k| [k.time as f64, k.value as f64, k.envelope as f64])
+            .collect(),
+        Variant::ColorSequence(v) => v
+            .keypoints
+            .iter()
+            .flat_map(|k| {
+                [
+                    k.time as f64,
+                    k.color.r as f64,
+                    k.color.g as f64,
+                    k.color.b as f64,
+                ]
+            })
+            .collect(),
+        Variant::PhysicalProperties(PhysicalProperties::Custom(v)) => vec![
+            v.density as f64,
+            v.friction as f64,
+            v.elasticity as f64,
+            v.friction_weight as f64,
+            v.elasticity_weight as f64,
+        ],
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::InstanceBuilder;
+
+    #[test]
+    fn equal_with_refs_and_float_error() {
+        let make = |x: f32| {
+            let part =
+                InstanceBuilder::new("Part").with_property("Size", Vector3::new(x, 1.0, 1.0));
+            let weld =
+                InstanceBuilder::new("WeldConstraint").with_property("Part0", part.referent());
+
+            WeakDom::new(InstanceBuilder::new("Model").with_children([part, weld]))
+        };
+
+        assert_eq!(
+            compare_doms(&make(1.0), &make(1.000001), &DomCompareOptions::new()),
+            []
+        );
+    }
+
+    #[test]
+    fn order_insensitive() {
+        let a = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_children([InstanceBuilder::new("Part"), InstanceBuilder::new("Model")]),
+        );
+        let b = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_children([InstanceBuilder::new("Model"), InstanceBuilder::new("Part")]),
+        );
+
+        assert!(!compare_doms(&a, &b, &DomCompareOptions::new()).is_empty());
+        assert_eq!(
+            compare_doms(&a, &b, &DomCompareOptions::new().ignore_child_order(true)),
+            []
+        );
+    }
+
+    #[test]
+    fn differences() {
+        let a = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_child(InstanceBuilder::new("Part").with_property("Transparency", 0.5f32))
+                .with_child(InstanceBuilder::new("Model")),
+        );
+        let b = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_child(
+                    InstanceBuilder::new("Part")
+                        .with_property("Transparency", 0.0f32)
+                        .with_property("Anchored", true),
+                )
+                .with_child(InstanceBuilder::new("Model").with_name("Renamed")),
+        );
+
+        let differences: Vec<String> = compare_doms(&a, &b, &DomCompareOptions::new())
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            differences,
+            [
+                "Folder.Model: Name differs: \"Model\" != \"Renamed\"",
+                "Folder.Part: property Anchored is missing from left dom",
+                "Folder.Part: property Transparency differs: Float32(0.5) != Float32(0.0)",
+            ]
+        );
+    }
+}
@@ -1,8 +1,14 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+};
 
 use rbx_types::{Ref, UniqueId, Variant};
 
-use crate::instance::{Instance, InstanceBuilder};
+use crate::{
+    instance::{Instance, InstanceBuilder},
+    properties::SubtreeProperties,
+};
 
 /// Represents a DOM containing one or more Roblox instances.
 ///
@@ -11,11 +17,22 @@ use crate::instance::{Instance, InstanceBuilder};
 ///
 /// When constructing instances, you'll want to create [`InstanceBuilder`]
 /// objects and insert them into the tree.
+///
+/// `WeakDom` implements `Serialize` and `Deserialize`, so a whole DOM can be
+/// written to and read from any format Serde supports without losing
+/// anything, referents included. It's written as a struct with two fields:
+/// `SharedStrings`, the contents of every `SharedString` in the DOM, and
+/// `Instances`, every instance with its `Referent`, `Parent`, `Name`, `Class`,
+/// and `Properties`. The root comes first and every instance comes after its
+/// parent, so deep trees don't need deep nesting. Property values are tagged
+/// with their type, except that `SharedString` values hold an index into
+/// `SharedStrings`.
 #[derive(Debug)]
 pub struct WeakDom {
     instances: HashMap<Ref, Instance>,
     root_ref: Ref,
     unique_ids: HashSet<UniqueId>,
+    strict_refs: bool,
 }
 
 impl WeakDom {
@@ -25,6 +42,26 @@ impl WeakDom {
             instances: HashMap::new(),
             root_ref: builder.referent,
             unique_ids: HashSet::new(),
+            strict_refs: false,
+        };
+
+        dom.insert(Ref::none(), builder);
+        dom
+    }
+
+    /// Construct a new `WeakDom` described by the given [`InstanceBuilder`],
+    /// with strict Ref checking enabled from the start. See
+    /// [`WeakDom::set_strict_refs`].
+    ///
+    /// ## Panics
+    /// Panics if a Ref property in the tree points to an instance that is not
+    /// in the tree.
+    pub fn with_strict_refs(builder: InstanceBuilder) -> WeakDom {
+        let mut dom = WeakDom {
+            instances: HashMap::new(),
+            root_ref: builder.referent,
+            unique_ids: HashSet::new(),
+            strict_refs: true,
         };
 
         dom.insert(Ref::none(), builder);
@@ -65,14 +102,165 @@ impl WeakDom {
         self.instances.get_mut(&referent)
     }
 
+    /// Returns whether strict Ref checking is enabled for this `WeakDom`. See
+    /// [`WeakDom::set_strict_refs`].
+    pub fn strict_refs(&self) -> bool {
+        self.strict_refs
+    }
+
+    /// Enable or disable strict Ref checking for this `WeakDom`. It is disabled
+    /// by default.
+    ///
+    /// When enabled, [`WeakDom::insert`] panics if any Ref property in the
+    /// inserted tree points to an instance that is neither in this `WeakDom`
+    /// nor in the inserted tree itself. This catches dangling refs when they
+    /// are created instead of when the `WeakDom` is serialized.
+    ///
+    /// [`WeakDom::transfer`] also panics, before moving anything, if the move
+    /// would leave a Ref property dangling in a `WeakDom` that has strict Ref
+    /// checking enabled: either in the moved instances, if the destination is
+    /// strict, or in the instances left behind, if this `WeakDom` is.
+    /// [`WeakDom::transfer_within`] can't leave a Ref property dangling.
+    /// Likewise, [`WeakDom::destroy`] panics, before destroying anything, if
+    /// an instance that isn't being destroyed has a Ref property pointing to
+    /// one that is.
+    ///
+    /// Use [`WeakDom::with_strict_refs`] to also check the tree a `WeakDom` is
+    /// constructed from.
+    pub fn set_strict_refs(&mut self, strict_refs: bool) {
+        self.strict_refs = strict_refs;
+    }
+
     /// Insert a new instance into the DOM with the given parent. The parent is allowed to
     /// be the none Ref.
     ///
     /// ## Panics
     /// Panics if `parent_ref` is some and does not refer to an instance in the DOM.
+    ///
+    /// If strict Ref checking is enabled, will also panic if the tree contains
+    /// a Ref property that does not point to an instance in the DOM or in the
+    /// tree. Use [`WeakDom::try_insert`] to handle these cases as errors.
     pub fn insert(&mut self, parent_ref: Ref, root_builder: InstanceBuilder) -> Ref {
+        if self.strict_refs {
+            if let Err(err) = self.check_refs(&root_builder) {
+                panic!("{}", err);
+            }
+        }
+
+        self.insert_unchecked(parent_ref, root_builder)
+    }
+
+    /// Insert a new instance into the DOM with the given parent, returning an
+    /// error instead of panicking if the insertion would leave the DOM in an
+    /// invalid state. The parent is allowed to be the none Ref.
+    ///
+    /// Ref properties in the tree are always checked by this method, whether
+    /// or not strict Ref checking is enabled.
+    pub fn try_insert(
+        &mut self,
+        parent_ref: Ref,
+        root_builder: InstanceBuilder,
+    ) -> Result<Ref, InsertError> {
+        if parent_ref.is_some() && !self.instances.contains_key(&parent_ref) {
+            return Err(InsertError::ParentNotFound { parent_ref });
+        }
+
+        self.check_refs(&root_builder)?;
+
+        Ok(self.insert_unchecked(parent_ref, root_builder))
+    }
+
+    /// Checks that every Ref property in the given tree points to an instance
+    /// in this `WeakDom` or in the tree itself.
+    fn check_refs(&self, root_builder: &InstanceBuilder) -> Result<(), InsertError> {
+        let mut builder_refs = HashSet::new();
+        let mut to_visit = vec![root_builder];
+
+        while let Some(builder) = to_visit.pop() {
+            builder_refs.insert(builder.referent);
+            to_visit.extend(&builder.children);
+        }
+
+        to_visit.push(root_builder);
+
+        while let Some(builder) = to_visit.pop() {
+            for (property, value) in &builder.properties {
+                if let Variant::Ref(target) = value {
+                    if target.is_some()
+                        && !builder_refs.contains(target)
+                        && !self.instances.contains_key(target)
+                    {
+                        return Err(InsertError::DanglingRef {
+                            name: builder.name.clone(),
+                            class: builder.class.clone(),
+                            property: property.clone(),
+                            target: *target,
+                        });
+                    }
+                }
+            }
+
+            to_visit.extend(&builder.children);
+        }
+
+        Ok(())
+    }
+
+    /// Checks that moving the instance with the given referent and its
+    /// descendants to `dest` won't leave a Ref property dangling in whichever
+    /// of the two `WeakDom`s has strict Ref checking enabled.
+    fn check_transfer_refs(&self, referent: Ref, dest: &WeakDom) -> Result<(), InsertError> {
+        let moving: HashSet<Ref> = self
+            .descendants(referent)
+            .map(|instance| instance.referent)
+            .collect();
+
+        if dest.strict_refs {
+            for instance in self.descendants(referent) {
+                check_dangling(instance, |target| {
+                    !moving.contains(&target) && !dest.instances.contains_key(&target)
+                })?;
+            }
+        }
+
+        if self.strict_refs {
+            self.check_removal_refs(&moving)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that no instance outside of `removing` has a Ref property
+    /// pointing to an instance in `removing`.
+    fn check_removal_refs(&self, removing: &HashSet<Ref>) -> Result<(), InsertError> {
+        for instance in self.instances.values() {
+            if !removing.contains(&instance.referent) {
+                check_dangling(instance, |target| removing.contains(&target))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Inserts a tree of instances without checking Ref properties. Cloning
+    /// uses this because it inserts refs that are rewritten afterwards.
+    pub(crate) fn insert_unchecked(
+        &mut self,
+        parent_ref: Ref,
+        root_builder: InstanceBuilder,
+    ) -> Ref {
         let root_referent = root_builder.referent;
 
+        #[cfg(feature = "tracing")]
+        let span = tracing::trace_span!(
+            "rbx_dom_weak::insert",
+            class = root_builder.class.as_str(),
+            instances = tracing::field::Empty,
+        )
+        .entered();
+        #[cfg(feature = "tracing")]
+        let instances_before = self.instances.len();
+
         // Rather than performing this movement recursively, we instead use a
         // queue that we load the children of each `InstanceBuilder` into.
         // Then we can just iter through that.
@@ -105,6 +293,9 @@ impl WeakDom {
             }
         }
 
+        #[cfg(feature = "tracing")]
+        span.record("instances", self.instances.len() - instances_before);
+
         root_referent
     }
 
@@ -114,7 +305,8 @@ impl WeakDom {
     /// Panics if `referent` does not refer to an instance in the DOM.
     ///
     /// Will also panic if `referent` refers to the root instance in this
-    /// `WeakDom`.
+    /// `WeakDom`, or if strict Ref checking is enabled and an instance that
+    /// isn't being destroyed has a Ref property pointing to one that is.
     pub fn destroy(&mut self, referent: Ref) {
         if referent == self.root_ref {
             panic!("cannot destroy the root instance of a WeakDom");
@@ -125,6 +317,17 @@ impl WeakDom {
             .get(&referent)
             .unwrap_or_else(|| panic!("cannot destroy an instance that does not exist"));
 
+        if self.strict_refs {
+            let removing = self
+                .descendants(referent)
+                .map(|instance| instance.referent)
+                .collect();
+
+            if let Err(err) = self.check_removal_refs(&removing) {
+                panic!("{}", err);
+            }
+        }
+
         let parent_ref = instance.parent;
         if parent_ref.is_some() {
             let parent = self.instances.get_mut(&parent_ref).unwrap();
@@ -151,12 +354,19 @@ impl WeakDom {
     /// `dest_parent_ref` does not refer to an instance in `other_dom`.
     ///
     /// Will also panic if `referent` refers to the root instance in this
-    /// `WeakDom`.
+    /// `WeakDom`, or if strict Ref checking is enabled for either `WeakDom`
+    /// and the move would leave a Ref property in it dangling.
     pub fn transfer(&mut self, referent: Ref, dest: &mut WeakDom, dest_parent_ref: Ref) {
         if referent == self.root_ref {
             panic!("cannot transfer the root instance of WeakDom");
         }
 
+        if self.strict_refs || dest.strict_refs {
+            if let Err(err) = self.check_transfer_refs(referent, dest) {
+                panic!("{}", err);
+            }
+        }
+
         let mut instance = self.inner_remove(referent);
 
         // Remove the instance being moved from its parent's list of children.
@@ -212,7 +422,7 @@ impl WeakDom {
             panic!("cannot transfer the root instance of WeakDom");
         }
 
-        let mut instance = self
+        let instance = self
             .instances
             .get_mut(&referent)
             .unwrap_or_else(|| panic!("cannot move an instance that does not exist"));
@@ -245,11 +455,11 @@ impl WeakDom {
     pub fn clone_within(&mut self, referent: Ref) -> Ref {
         let mut ctx = CloneContext::default();
         let root_builder = ctx.clone_ref_as_builder(self, referent);
-        let root_ref = self.insert(Ref::none(), root_builder);
+        let root_ref = self.insert_unchecked(Ref::none(), root_builder);
 
         while let Some((cloned_parent, uncloned_child)) = ctx.queue.pop_front() {
             let builder = ctx.clone_ref_as_builder(self, uncloned_child);
-            self.insert(cloned_parent, builder);
+            self.insert_unchecked(cloned_parent, builder);
         }
 
         ctx.rewrite_refs(self);
@@ -267,17 +477,59 @@ impl WeakDom {
     pub fn clone_into_external(&self, referent: Ref, dest: &mut WeakDom) -> Ref {
         let mut ctx = CloneContext::default();
         let root_builder = ctx.clone_ref_as_builder(self, referent);
-        let root_ref = dest.insert(Ref::none(), root_builder);
+        let root_ref = dest.insert_unchecked(Ref::none(), root_builder);
 
         while let Some((cloned_parent, uncloned_child)) = ctx.queue.pop_front() {
             let builder = ctx.clone_ref_as_builder(self, uncloned_child);
-            dest.insert(cloned_parent, builder);
+            dest.insert_unchecked(cloned_parent, builder);
         }
 
         ctx.rewrite_refs(dest);
         root_ref
     }
 
+    /// Returns the referents of every instance in the `WeakDom` that has an
+    /// attribute named `name` whose value is equal to `value`.
+    ///
+    /// Instances are visited breadth-first starting at the root. To answer
+    /// many of these queries against the same `WeakDom`, build a
+    /// [`MetadataIndex`][crate::MetadataIndex] instead.
+    pub fn find_by_attribute(&self, name: &str, value: &Variant) -> Vec<Ref> {
+        self.descendants(self.root_ref)
+            .filter(|instance| instance.attribute(name) == Some(value))
+            .map(|instance| instance.referent)
+            .collect()
+    }
+
+    /// Returns the referents of every instance in the `WeakDom` that has the
+    /// given tag in its `Tags` property.
+    ///
+    /// Instances are visited breadth-first starting at the root. To answer
+    /// many of these queries against the same `WeakDom`, build a
+    /// [`MetadataIndex`][crate::MetadataIndex] instead.
+    pub fn find_tagged(&self, tag: &str) -> Vec<Ref> {
+        self.descendants(self.root_ref)
+            .filter(|instance| instance.tags().any(|t| t == tag))
+            .map(|instance| instance.referent)
+            .collect()
+    }
+
+    /// Returns an iterator over every property of the instance with the given
+    /// referent and all of its descendants. See [`SubtreeProperties`] for how
+    /// to filter the properties that are yielded.
+    pub fn subtree_properties(&self, referent: Ref) -> SubtreeProperties<'_> {
+        SubtreeProperties::new(self, referent)
+    }
+
+    /// Returns an iterator over the instance with the given referent and all of
+    /// its descendants, breadth-first.
+    pub(crate) fn descendants(&self, referent: Ref) -> Descendants<'_> {
+        let mut queue = VecDeque::new();
+        queue.push_back(referent);
+
+        Descendants { dom: self, queue }
+    }
+
     fn inner_insert(&mut self, referent: Ref, instance: Instance) {
         self.instances.insert(referent, instance);
 
@@ -322,6 +574,94 @@ impl WeakDom {
     }
 }
 
+/// Returns an error for the first Ref property of `instance` that is some and
+/// that `points_outside` says would be left dangling.
+fn check_dangling<F: Fn(Ref) -> bool>(
+    instance: &Instance,
+    points_outside: F,
+) -> Result<(), InsertError> {
+    for (property, value) in &instance.properties {
+        if let Variant::Ref(target) = value {
+            if target.is_some() && points_outside(*target) {
+                return Err(InsertError::DanglingRef {
+                    name: instance.name.clone(),
+                    class: instance.class.clone(),
+                    property: property.clone(),
+                    target: *target,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// An error returned by [`WeakDom::try_insert`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InsertError {
+    /// The parent the tree was being inserted into does not exist.
+    ParentNotFound {
+        /// The referent of the missing parent.
+        parent_ref: Ref,
+    },
+
+    /// An instance in the tree has a Ref property pointing to an instance that
+    /// is neither in the `WeakDom` nor in the tree being inserted.
+    DanglingRef {
+        /// The Name of the instance with the dangling Ref property.
+        name: String,
+        /// The ClassName of the instance with the dangling Ref property.
+        class: String,
+        /// The name of the dangling Ref property.
+        property: String,
+        /// The referent the property points to.
+        target: Ref,
+    },
+}
+
+impl fmt::Display for InsertError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InsertError::ParentNotFound { parent_ref } => write!(
+                formatter,
+                "cannot insert into parent {} because it does not exist",
+                parent_ref
+            ),
+            InsertError::DanglingRef {
+                name,
+                class,
+                property,
+                target,
+            } => write!(
+                formatter,
+                "property {} of {} {:?} points to {}, which is not in the dom",
+                property, class, name, target
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InsertError {}
+
+/// See [`WeakDom::descendants`].
+pub(crate) struct Descendants<'a> {
+    dom: &'a WeakDom,
+    queue: VecDeque<Ref>,
+}
+
+impl<'a> Iterator for Descendants<'a> {
+    type Item = &'a Instance;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let referent = self.queue.pop_front()?;
+        let instance = self.dom.get_by_ref(referent)?;
+        self.queue.extend(instance.children.iter().copied());
+
+        Some(instance)
+    }
+}
+
 #[derive(Debug, Default)]
 struct CloneContext {
     queue: VecDeque<(Ref, Ref)>,
@@ -399,8 +739,11 @@ impl CloneContext {
 mod test {
     use super::*;
 
-    use crate::DomViewer;
-    use rbx_types::{UniqueId, Variant};
+    use crate::{DomViewer, MetadataIndex};
+    use rbx_types::{
+        Attributes, ChunkPosition, CollisionFidelity, Enum, PhysicalConfigData, SharedString,
+        SmoothGrid, Tags, TerrainChunk, TerrainMaterial, UniqueId, Variant, VariantType, Voxel,
+    };
 
     #[test]
     fn transfer() {
@@ -531,6 +874,226 @@ mod test {
         insta::assert_yaml_snapshot!(viewer.view(&other_dom));
     }
 
+    #[test]
+    fn find_by_attribute_and_tag() {
+        let mut tags = Tags::new();
+        tags.push("Checkpoint");
+
+        let first = InstanceBuilder::new("Part")
+            .with_property("Attributes", Attributes::new().with("BuildId", 5.0))
+            .with_property("Tags", tags.clone());
+        let first_ref = first.referent;
+
+        let second = InstanceBuilder::new("Part")
+            .with_property("Attributes", Attributes::new().with("BuildId", 6.0));
+        let second_ref = second.referent;
+
+        let third = InstanceBuilder::new("Model").with_property("Tags", tags);
+        let third_ref = third.referent;
+
+        let dom = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_child(first)
+                .with_child(second.with_child(third)),
+        );
+
+        let build_id = Variant::Float64(6.0);
+        assert_eq!(dom.find_by_attribute("BuildId", &build_id), [second_ref]);
+        assert_eq!(
+            dom.find_by_attribute("Missing", &build_id),
+            Vec::<Ref>::new()
+        );
+        assert_eq!(dom.find_tagged("Checkpoint"), [first_ref, third_ref]);
+
+        let index = MetadataIndex::new(&dom);
+        assert_eq!(
+            index.find_by_attribute(&dom, "BuildId", &build_id),
+            [second_ref]
+        );
+        assert_eq!(index.find_tagged("Checkpoint"), [first_ref, third_ref]);
+        assert!(index.find_tagged("Missing").is_empty());
+    }
+
+    #[test]
+    fn subtree_properties() {
+        let child = InstanceBuilder::new("Part")
+            .with_property("Transparency", 0.5f32)
+            .with_property("Reflectance", 0.25f32)
+            .with_property("Locked", true);
+        let child_ref = child.referent;
+
+        let dom = WeakDom::new(
+            InstanceBuilder::new("Model")
+                .with_property("Transparency", 1.0f32)
+                .with_child(child),
+        );
+
+        let mut all: Vec<_> = dom.subtree_properties(dom.root_ref()).collect();
+        all.sort_by_key(|(_, name, _)| *name);
+        assert_eq!(all.len(), 4);
+
+        let floats = dom
+            .subtree_properties(dom.root_ref())
+            .with_type(VariantType::Float32)
+            .count();
+        assert_eq!(floats, 3);
+
+        let transparency: Vec<_> = dom
+            .subtree_properties(dom.root_ref())
+            .with_name("Transparency")
+            .with_class("Part")
+            .collect();
+        assert_eq!(
+            transparency,
+            [(child_ref, "Transparency", &Variant::Float32(0.5))]
+        );
+
+        let subtree = dom.subtree_properties(child_ref).count();
+        assert_eq!(subtree, 3);
+    }
+
+    #[test]
+    fn try_insert_dangling_ref() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let dangling = Ref::new();
+
+        let weld = InstanceBuilder::new("WeldConstraint").with_property("Part0", dangling);
+        assert_eq!(
+            dom.try_insert(root_ref, weld),
+            Err(InsertError::DanglingRef {
+                name: "WeldConstraint".to_owned(),
+                class: "WeldConstraint".to_owned(),
+                property: "Part0".to_owned(),
+                target: dangling,
+            })
+        );
+        assert!(dom.root().children().is_empty());
+
+        // Refs to instances in the dom, in the inserted tree, or to nothing at
+        // all are fine.
+        let value = InstanceBuilder::new("ObjectValue").with_property("Value", root_ref);
+        let part = InstanceBuilder::new("Part");
+        let weld = InstanceBuilder::new("WeldConstraint")
+            .with_property("Part0", part.referent())
+            .with_property("Part1", Ref::none());
+        assert!(dom
+            .try_insert(root_ref, part.with_children([value, weld]))
+            .is_ok());
+
+        assert_eq!(
+            dom.try_insert(dangling, InstanceBuilder::new("Folder")),
+            Err(InsertError::ParentNotFound {
+                parent_ref: dangling
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "property Part0 of WeldConstraint")]
+    fn strict_refs_insert() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        dom.set_strict_refs(true);
+
+        // Cloning into a strict dom must still work, since refs are rewritten
+        // after the cloned instances are inserted.
+        let source =
+            WeakDom::new(InstanceBuilder::new("WeldConstraint").with_property("Part0", Ref::new()));
+        source.clone_into_external(source.root_ref(), &mut dom);
+
+        let root_ref = dom.root_ref();
+        dom.insert(
+            root_ref,
+            InstanceBuilder::new("WeldConstraint").with_property("Part0", Ref::new()),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "property Part0 of WeldConstraint")]
+    fn strict_refs_with_strict_refs() {
+        WeakDom::with_strict_refs(
+            InstanceBuilder::new("WeldConstraint").with_property("Part0", Ref::new()),
+        );
+    }
+
+    /// Builds a strict `WeakDom` holding a Model with a Part and a Folder,
+    /// where the Folder holds a WeldConstraint attached to the Part. Returns
+    /// it along with the referents of the Model, Part, and WeldConstraint.
+    fn strict_weld_dom() -> (WeakDom, Ref, Ref, Ref) {
+        let part = InstanceBuilder::new("Part");
+        let weld = InstanceBuilder::new("WeldConstraint").with_property("Part0", part.referent());
+        let model = InstanceBuilder::new("Model");
+        let refs = (model.referent(), part.referent(), weld.referent());
+
+        let dom = WeakDom::with_strict_refs(InstanceBuilder::new("Folder").with_child(
+            model.with_children([part, InstanceBuilder::new("Folder").with_child(weld)]),
+        ));
+
+        (dom, refs.0, refs.1, refs.2)
+    }
+
+    #[test]
+    fn strict_refs_transfer() {
+        let (mut source, model_ref, part_ref, weld_ref) = strict_weld_dom();
+        let mut dest = WeakDom::with_strict_refs(InstanceBuilder::new("Folder"));
+        let dest_root = dest.root_ref();
+
+        // None of these moves can leave a ref dangling, since the part and the
+        // weld stay in the same dom.
+        source.transfer_within(weld_ref, model_ref);
+        source.transfer(model_ref, &mut dest, dest_root);
+
+        assert!(source.get_by_ref(part_ref).is_none());
+        assert_eq!(dest.get_by_ref(weld_ref).unwrap().parent(), model_ref);
+        assert_eq!(dest.get_by_ref(part_ref).unwrap().parent(), model_ref);
+    }
+
+    #[test]
+    #[should_panic(expected = "property Part0 of WeldConstraint")]
+    fn strict_refs_transfer_dest() {
+        let (mut source, _, _, weld_ref) = strict_weld_dom();
+        let mut dest = WeakDom::with_strict_refs(InstanceBuilder::new("Folder"));
+        let dest_root = dest.root_ref();
+
+        // The weld would point to a part that isn't in the destination.
+        source.transfer(weld_ref, &mut dest, dest_root);
+    }
+
+    #[test]
+    #[should_panic(expected = "property Part0 of WeldConstraint")]
+    fn strict_refs_transfer_source() {
+        let (mut source, _, part_ref, _) = strict_weld_dom();
+        let mut dest = WeakDom::new(InstanceBuilder::new("Folder"));
+        let dest_root = dest.root_ref();
+
+        // The weld left behind would point to a part that has been moved out.
+        source.transfer(part_ref, &mut dest, dest_root);
+    }
+
+    #[test]
+    fn strict_refs_destroy() {
+        let (mut dom, model_ref, part_ref, weld_ref) = strict_weld_dom();
+
+        // Destroying the weld leaves nothing pointing at a missing instance,
+        // and neither does destroying the part and the weld together.
+        dom.destroy(weld_ref);
+        dom.transfer_within(part_ref, dom.root_ref());
+        dom.destroy(model_ref);
+
+        let (mut dom, model_ref, _, _) = strict_weld_dom();
+        dom.destroy(model_ref);
+        assert_eq!(dom.root().children().len(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "property Part0 of WeldConstraint")]
+    fn strict_refs_destroy_dangling() {
+        let (mut dom, _, part_ref, _) = strict_weld_dom();
+
+        // The weld left behind would point to a part that has been destroyed.
+        dom.destroy(part_ref);
+    }
+
     #[test]
     fn large_depth_tree() {
         // We've had issues with stack overflows when creating WeakDoms with
@@ -654,4 +1217,57 @@ mod test {
             panic!("UniqueId property must exist and contain a Variant::UniqueId")
         };
     }
+
+    #[test]
+    fn terrain_smooth_grid() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("Terrain"));
+        let terrain = dom.root_mut();
+        assert!(terrain.smooth_grid().is_none());
+
+        let mut chunk = TerrainChunk::new(ChunkPosition::new(0, -1, 0));
+        chunk.set_voxel(1, 2, 3, Voxel::full(TerrainMaterial::Sand));
+
+        let mut grid = SmoothGrid::new();
+        grid.insert_chunk(chunk);
+
+        terrain.set_smooth_grid(&grid).unwrap();
+        assert!(matches!(
+            terrain.properties.get("SmoothGrid"),
+            Some(Variant::BinaryString(_))
+        ));
+
+        let decoded = terrain.smooth_grid().unwrap().unwrap();
+        assert_eq!(decoded, grid);
+        assert_eq!(
+            decoded.voxel(1, -30, 3).terrain_material(),
+            Some(TerrainMaterial::Sand)
+        );
+    }
+
+    #[test]
+    fn mesh_part_physics() {
+        let config = PhysicalConfigData::new(6, vec![0xAB; 16]);
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("MeshPart")
+                .with_property("PhysicalConfigData", SharedString::new(config.to_bytes()))
+                .with_property("CollisionFidelity", Enum::from(CollisionFidelity::Hull)),
+        );
+
+        let mesh_part = dom.root();
+        assert_eq!(mesh_part.physical_config_data().unwrap().unwrap(), config);
+        assert_eq!(
+            mesh_part.collision_fidelity(),
+            Some(CollisionFidelity::Hull)
+        );
+
+        let part = dom.root_mut();
+        part.properties.insert(
+            "PhysicalConfigData".to_owned(),
+            SharedString::new(b"garbage".to_vec()).into(),
+        );
+        part.properties.remove("CollisionFidelity");
+
+        assert!(part.physical_config_data().unwrap().is_err());
+        assert_eq!(part.collision_fidelity(), None);
+    }
 }
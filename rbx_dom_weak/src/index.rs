@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use rbx_types::{Ref, Variant};
+
+use crate::WeakDom;
+
+/// An index over the tags and attributes of every instance in a [`WeakDom`],
+/// used to answer repeated metadata queries without visiting every instance
+/// each time.
+///
+/// The index is a snapshot: it is not updated when the `WeakDom` it was built
+/// from changes. Construct a new index after mutating the tree.
+#[derive(Debug, Default)]
+pub struct MetadataIndex {
+    tagged: HashMap<String, Vec<Ref>>,
+    attributes: HashMap<String, Vec<Ref>>,
+}
+
+impl MetadataIndex {
+    /// Build a new `MetadataIndex` covering every instance in the given
+    /// `WeakDom`.
+    pub fn new(dom: &WeakDom) -> Self {
+        let mut index = Self::default();
+
+        for instance in dom.descendants(dom.root_ref()) {
+            for tag in instance.tags() {
+                let referents = index.tagged.entry(tag.to_owned()).or_default();
+
+                // Tags may be duplicated on a single instance, but we only
+                // want to record the instance once.
+                if referents.last() != Some(&instance.referent()) {
+                    referents.push(instance.referent());
+                }
+            }
+
+            if let Some(Variant::Attributes(attributes)) = instance.properties.get("Attributes") {
+                for (name, _) in attributes.iter() {
+                    index
+                        .attributes
+                        .entry(name.clone())
+                        .or_default()
+                        .push(instance.referent());
+                }
+            }
+        }
+
+        index
+    }
+
+    /// Returns the referents of every indexed instance with the given tag.
+    pub fn find_tagged(&self, tag: &str) -> &[Ref] {
+        self.tagged.get(tag).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Returns the referents of every indexed instance that has an attribute
+    /// named `name` whose value is equal to `value`.
+    ///
+    /// Attribute values are read from `dom`, which should be the same `WeakDom`
+    /// that this index was built from.
+    pub fn find_by_attribute(&self, dom: &WeakDom, name: &str, value: &Variant) -> Vec<Ref> {
+        let Some(referents) = self.attributes.get(name) else {
+            return Vec::new();
+        };
+
+        referents
+            .iter()
+            .copied()
+            .filter(|&referent| {
+                dom.get_by_ref(referent)
+                    .and_then(|instance| instance.attribute(name))
+                    == Some(value)
+            })
+            .collect()
+    }
+}
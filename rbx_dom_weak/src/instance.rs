@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 
-use rbx_types::{Ref, Variant};
+use rbx_types::{
+    BinaryString, CollisionFidelity, Error, PhysicalConfigData, Ref, SmoothGrid, Variant,
+};
 
 /**
 Represents an instance that can be turned into a new
@@ -156,7 +158,7 @@ impl InstanceBuilder {
     where
         I: IntoIterator<Item = InstanceBuilder>,
     {
-        self.children.extend(children.into_iter());
+        self.children.extend(children);
         self
     }
 
@@ -167,7 +169,7 @@ impl InstanceBuilder {
     where
         I: IntoIterator<Item = InstanceBuilder>,
     {
-        self.children.extend(children.into_iter());
+        self.children.extend(children);
     }
 }
 
@@ -213,4 +215,69 @@ impl Instance {
     pub fn parent(&self) -> Ref {
         self.parent
     }
+
+    /// Returns the value of the attribute with the given name, if this instance
+    /// has an `Attributes` property containing it.
+    pub(crate) fn attribute(&self, name: &str) -> Option<&Variant> {
+        match self.properties.get("Attributes") {
+            Some(Variant::Attributes(attributes)) => attributes.get(name),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over the tags in this instance's `Tags` property.
+    pub(crate) fn tags(&self) -> impl Iterator<Item = &str> {
+        let tags = match self.properties.get("Tags") {
+            Some(Variant::Tags(tags)) => Some(tags.iter()),
+            _ => None,
+        };
+
+        tags.into_iter().flatten()
+    }
+
+    /// Parses this instance's `PhysicalConfigData` property, which holds the
+    /// collision geometry of a MeshPart or PartOperation. Returns `None` if
+    /// the instance has no `PhysicalConfigData` property.
+    pub fn physical_config_data(&self) -> Option<Result<PhysicalConfigData, Error>> {
+        match self.properties.get("PhysicalConfigData") {
+            Some(Variant::SharedString(value)) => {
+                Some(PhysicalConfigData::from_bytes(value.data()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the value of this instance's `CollisionFidelity` property, if
+    /// it has one with a known value.
+    pub fn collision_fidelity(&self) -> Option<CollisionFidelity> {
+        match self.properties.get("CollisionFidelity") {
+            Some(Variant::Enum(value)) => CollisionFidelity::from_enum(*value),
+            _ => None,
+        }
+    }
+
+    /// Decodes this instance's `SmoothGrid` property, which holds the voxels
+    /// of a Terrain instance. Returns `None` if the instance has no
+    /// `SmoothGrid` property.
+    pub fn smooth_grid(&self) -> Option<Result<SmoothGrid, Error>> {
+        match self.properties.get("SmoothGrid") {
+            Some(Variant::BinaryString(value)) => {
+                let data: &[u8] = value.as_ref();
+                Some(SmoothGrid::from_reader(data))
+            }
+            _ => None,
+        }
+    }
+
+    /// Encodes `grid` and stores it in this instance's `SmoothGrid`
+    /// property.
+    pub fn set_smooth_grid(&mut self, grid: &SmoothGrid) -> Result<(), Error> {
+        let mut buffer = Vec::new();
+        grid.to_writer(&mut buffer)?;
+
+        self.properties
+            .insert("SmoothGrid".to_owned(), BinaryString::from(buffer).into());
+
+        Ok(())
+    }
 }
@@ -41,14 +41,29 @@
 
 #![deny(missing_docs)]
 
+mod builder;
+mod compare;
 mod dom;
+mod index;
 mod instance;
+mod properties;
+mod scripts;
+mod serialization;
+mod sync;
+mod template;
 mod viewer;
 
 pub use rbx_types as types;
 
 pub use crate::{
-    dom::WeakDom,
+    builder::DomBuilder,
+    compare::{compare_doms, DomCompareOptions, DomDifference},
+    dom::{InsertError, WeakDom},
+    index::MetadataIndex,
     instance::{Instance, InstanceBuilder},
+    properties::SubtreeProperties,
+    scripts::{InjectError, ScriptSources},
+    sync::SyncDom,
+    template::{Template, TemplateOverrides},
     viewer::{DomViewer, ViewedInstance},
 };
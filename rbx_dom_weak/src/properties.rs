@@ -0,0 +1,109 @@
+use std::collections::hash_map;
+
+use rbx_types::{Ref, Variant, VariantType};
+
+use crate::{dom::Descendants, WeakDom};
+
+/// An iterator over every property of an instance and all of its descendants,
+/// created by [`WeakDom::subtree_properties`].
+///
+/// Each item is the referent of the instance the property belongs to, the
+/// name of the property, and its value. Instances are visited breadth-first,
+/// while the order of properties within a single instance is unspecified.
+///
+/// The properties yielded can be narrowed down by type, name, or class:
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbx_dom_weak::types::{Content, VariantType};
+///
+/// let dom = WeakDom::new(InstanceBuilder::new("Folder").with_child(
+///     InstanceBuilder::new("Decal")
+///         .with_property("Texture", Content::from("rbxassetid://1234"))
+///         .with_property("Transparency", 0.5f32),
+/// ));
+///
+/// let textures: Vec<_> = dom
+///     .subtree_properties(dom.root_ref())
+///     .with_type(VariantType::Content)
+///     .collect();
+///
+/// assert_eq!(textures.len(), 1);
+/// assert_eq!(textures[0].1, "Texture");
+/// ```
+pub struct SubtreeProperties<'a> {
+    descendants: Descendants<'a>,
+    current: Option<(Ref, hash_map::Iter<'a, String, Variant>)>,
+    filter: Filter,
+}
+
+#[derive(Default)]
+struct Filter {
+    ty: Option<VariantType>,
+    name: Option<String>,
+    class: Option<String>,
+}
+
+impl Filter {
+    fn matches_class(&self, class: &str) -> bool {
+        self.class.as_deref().is_none_or(|filter| class == filter)
+    }
+
+    fn matches_property(&self, name: &str, value: &Variant) -> bool {
+        self.ty.is_none_or(|ty| value.ty() == ty)
+            && self.name.as_deref().is_none_or(|filter| name == filter)
+    }
+}
+
+impl<'a> SubtreeProperties<'a> {
+    pub(crate) fn new(dom: &'a WeakDom, referent: Ref) -> Self {
+        Self {
+            descendants: dom.descendants(referent),
+            current: None,
+            filter: Filter::default(),
+        }
+    }
+
+    /// Only yield properties whose value is of the given type.
+    pub fn with_type(mut self, ty: VariantType) -> Self {
+        self.filter.ty = Some(ty);
+        self
+    }
+
+    /// Only yield properties with the given name.
+    pub fn with_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.filter.name = Some(name.into());
+        self
+    }
+
+    /// Only yield properties of instances with the given ClassName.
+    pub fn with_class<S: Into<String>>(mut self, class: S) -> Self {
+        self.filter.class = Some(class.into());
+        self
+    }
+}
+
+impl<'a> Iterator for SubtreeProperties<'a> {
+    type Item = (Ref, &'a str, &'a Variant);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((referent, properties)) = &mut self.current {
+                let referent = *referent;
+
+                for (name, value) in properties.by_ref() {
+                    if self.filter.matches_property(name, value) {
+                        return Some((referent, name.as_str(), value));
+                    }
+                }
+            }
+
+            let instance = self.descendants.next()?;
+            if self.filter.matches_class(&instance.class) {
+                self.current = Some((instance.referent(), instance.properties.iter()));
+            } else {
+                self.current = None;
+            }
+        }
+    }
+}
@@ -0,0 +1,349 @@
+use std::{
+    collections::{btree_map, BTreeMap, HashMap},
+    fmt,
+};
+
+use rbx_types::{Ref, Variant};
+
+use crate::WeakDom;
+
+/// The ClassNames of the instances whose sources are extracted.
+const SCRIPT_CLASSES: &[&str] = &["Script", "LocalScript", "ModuleScript"];
+
+/// The sources of every Script, LocalScript, and ModuleScript in a subtree of
+/// a [`WeakDom`], keyed by the path to each script.
+///
+/// This is meant for editing scripts outside of a place or model file: extract
+/// the sources, write them somewhere like the filesystem, and inject the
+/// edited sources back into the `WeakDom` before serializing it again.
+///
+/// Paths are the Names of the instances from the subtree's root down to the
+/// script, not including the root, separated by `/`. When several siblings
+/// have the same Name, the second and later ones get `~2`, `~3`, and so on
+/// appended in the order they appear. `%`, `/`, and `~` in Names are written as
+/// `%25`, `%2F`, and `%7E` so that every path is unambiguous.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, ScriptSources, WeakDom};
+///
+/// let mut dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(
+///     InstanceBuilder::new("ServerScriptService").with_child(
+///         InstanceBuilder::new("Script")
+///             .with_name("Main")
+///             .with_property("Source", "print('hi')"),
+///     ),
+/// ));
+///
+/// let mut sources = ScriptSources::extract(&dom, dom.root_ref());
+/// assert_eq!(sources.get("ServerScriptService/Main"), Some("print('hi')"));
+///
+/// sources.insert("ServerScriptService/Main", "print('bye')");
+///
+/// let root_ref = dom.root_ref();
+/// sources.inject(&mut dom, root_ref).unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScriptSources {
+    scripts: BTreeMap<String, ScriptEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ScriptEntry {
+    class: Option<String>,
+    source: String,
+}
+
+impl ScriptSources {
+    /// Construct an empty `ScriptSources`, usually to fill with
+    /// [`ScriptSources::insert`] before injecting it.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Extract the source of every script in the subtree rooted at `root`.
+    /// Scripts without a `Source` property are given an empty source.
+    pub fn extract(dom: &WeakDom, root: Ref) -> Self {
+        let mut scripts = BTreeMap::new();
+
+        for (path, referent) in script_paths(dom, root) {
+            let instance = dom.get_by_ref(referent).unwrap();
+            let source = match instance.properties.get("Source") {
+                Some(Variant::String(source)) => source.clone(),
+                _ => String::new(),
+            };
+
+            scripts.insert(
+                path,
+                ScriptEntry {
+                    class: Some(instance.class.clone()),
+                    source,
+                },
+            );
+        }
+
+        Self { scripts }
+    }
+
+    /// Returns the source of the script at the given path.
+    pub fn get(&self, path: &str) -> Option<&str> {
+        self.scripts.get(path).map(|entry| entry.source.as_str())
+    }
+
+    /// Returns the ClassName of the script at the given path, if it was
+    /// extracted from a `WeakDom`. This is useful for picking file extensions.
+    pub fn class(&self, path: &str) -> Option<&str> {
+        self.scripts.get(path)?.class.as_deref()
+    }
+
+    /// Set the source of the script at the given path, returning its previous
+    /// source.
+    pub fn insert<P: Into<String>, S: Into<String>>(
+        &mut self,
+        path: P,
+        source: S,
+    ) -> Option<String> {
+        let source = source.into();
+
+        match self.scripts.entry(path.into()) {
+            btree_map::Entry::Occupied(mut entry) => {
+                Some(std::mem::replace(&mut entry.get_mut().source, source))
+            }
+            btree_map::Entry::Vacant(entry) => {
+                entry.insert(ScriptEntry {
+                    class: None,
+                    source,
+                });
+                None
+            }
+        }
+    }
+
+    /// Remove the script at the given path, returning its source.
+    pub fn remove(&mut self, path: &str) -> Option<String> {
+        self.scripts.remove(path).map(|entry| entry.source)
+    }
+
+    /// Returns an iterator over the path and source of every script, ordered
+    /// by path.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.scripts
+            .iter()
+            .map(|(path, entry)| (path.as_str(), entry.source.as_str()))
+    }
+
+    /// Returns the number of scripts.
+    pub fn len(&self) -> usize {
+        self.scripts.len()
+    }
+
+    /// Returns whether there are no scripts.
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+
+    /// Set the `Source` of each script in the subtree rooted at `root` to the
+    /// source with the same path. Scripts without a source here are left
+    /// alone.
+    ///
+    /// Nothing is changed if any path doesn't point to a script in the
+    /// subtree.
+    pub fn inject(&self, dom: &mut WeakDom, root: Ref) -> Result<(), InjectError> {
+        let referents: HashMap<String, Ref> = script_paths(dom, root).into_iter().collect();
+
+        let mut targets = Vec::with_capacity(self.scripts.len());
+        for (path, entry) in &self.scripts {
+            match referents.get(path) {
+                Some(referent) => targets.push((*referent, &entry.source)),
+                None => return Err(InjectError::ScriptNotFound { path: path.clone() }),
+            }
+        }
+
+        for (referent, source) in targets {
+            let instance = dom.get_by_ref_mut(referent).unwrap();
+            instance
+                .properties
+                .insert("Source".to_owned(), Variant::String(source.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+/// An error returned by [`ScriptSources::inject`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InjectError {
+    /// No script in the subtree has the given path.
+    ScriptNotFound {
+        /// The path that didn't match a script.
+        path: String,
+    },
+}
+
+impl fmt::Display for InjectError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InjectError::ScriptNotFound { path } => {
+                write!(formatter, "there is no script at path {:?}", path)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InjectError {}
+
+/// Finds the path to every script in the subtree rooted at `root`, in
+/// depth-first order.
+fn script_paths(dom: &WeakDom, root: Ref) -> Vec<(String, Ref)> {
+    let mut paths = Vec::new();
+    let mut to_visit = vec![(String::new(), root)];
+
+    while let Some((path, referent)) = to_visit.pop() {
+        let instance = match dom.get_by_ref(referent) {
+            Some(instance) => instance,
+            None => continue,
+        };
+
+        if referent != root && SCRIPT_CLASSES.contains(&instance.class.as_str()) {
+            paths.push((path.clone(), referent));
+        }
+
+        let mut name_counts: HashMap<&str, usize> = HashMap::new();
+        let mut children = Vec::with_capacity(instance.children().len());
+
+        for child_ref in instance.children() {
+            let child = dom.get_by_ref(*child_ref).unwrap();
+            let count = name_counts.entry(child.name.as_str()).or_default();
+            *count += 1;
+
+            let mut child_path = path.clone();
+            if !child_path.is_empty() {
+                child_path.push('/');
+            }
+            push_escaped(&mut child_path, &child.name);
+            if *count > 1 {
+                child_path.push('~');
+                child_path.push_str(&count.to_string());
+            }
+
+            children.push((child_path, *child_ref));
+        }
+
+        // Children are pushed in reverse so that they're visited in order.
+        to_visit.extend(children.into_iter().rev());
+    }
+
+    paths
+}
+
+fn push_escaped(path: &mut String, name: &str) {
+    for char in name.chars() {
+        match char {
+            '%' => path.push_str("%25"),
+            '/' => path.push_str("%2F"),
+            '~' => path.push_str("%7E"),
+            char => path.push(char),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::InstanceBuilder;
+
+    fn script(class: &str, name: &str, source: &str) -> InstanceBuilder {
+        InstanceBuilder::new(class)
+            .with_name(name)
+            .with_property("Source", source)
+    }
+
+    #[test]
+    fn extract_and_inject() {
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("ReplicatedStorage")
+                    .with_child(
+                        script("ModuleScript", "Util", "return {}").with_child(script(
+                            "ModuleScript",
+                            "Inner",
+                            "return 1",
+                        )),
+                    )
+                    .with_child(script("ModuleScript", "Util", "return 2"))
+                    .with_child(InstanceBuilder::new("Folder").with_name("a/b~c"))
+                    .with_child(
+                        InstanceBuilder::new("Folder")
+                            .with_name("Client")
+                            .with_child(script("LocalScript", "a/b~c", "print(1)")),
+                    )
+                    .with_child(InstanceBuilder::new("Script").with_name("Empty")),
+            ),
+        );
+
+        let root_ref = dom.root_ref();
+        let mut sources = ScriptSources::extract(&dom, root_ref);
+
+        let paths: Vec<_> = sources.iter().map(|(path, _)| path).collect();
+        assert_eq!(
+            paths,
+            [
+                "ReplicatedStorage/Client/a%2Fb%7Ec",
+                "ReplicatedStorage/Empty",
+                "ReplicatedStorage/Util",
+                "ReplicatedStorage/Util/Inner",
+                "ReplicatedStorage/Util~2",
+            ]
+        );
+        assert_eq!(sources.get("ReplicatedStorage/Util~2"), Some("return 2"));
+        assert_eq!(sources.get("ReplicatedStorage/Empty"), Some(""));
+        assert_eq!(
+            sources.class("ReplicatedStorage/Client/a%2Fb%7Ec"),
+            Some("LocalScript")
+        );
+
+        sources.insert("ReplicatedStorage/Util/Inner", "return 3");
+        sources.inject(&mut dom, root_ref).unwrap();
+
+        assert_eq!(ScriptSources::extract(&dom, root_ref), {
+            let mut expected = sources.clone();
+            expected.insert("ReplicatedStorage/Util/Inner", "return 3");
+            expected
+        });
+
+        let storage = dom.root().children()[0];
+        let util = dom.get_by_ref(storage).unwrap().children()[0];
+        let inner = dom.get_by_ref(util).unwrap().children()[0];
+        assert_eq!(
+            dom.get_by_ref(inner).unwrap().properties.get("Source"),
+            Some(&Variant::String("return 3".to_owned()))
+        );
+    }
+
+    #[test]
+    fn inject_unknown_path() {
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Folder").with_child(script("Script", "Main", "print(1)")),
+        );
+
+        let mut sources = ScriptSources::new();
+        sources.insert("Main", "print(2)");
+        sources.insert("Missing", "print(3)");
+
+        let root_ref = dom.root_ref();
+        assert_eq!(
+            sources.inject(&mut dom, root_ref),
+            Err(InjectError::ScriptNotFound {
+                path: "Missing".to_owned()
+            })
+        );
+
+        // Nothing should have been changed.
+        let main = dom.get_by_ref(dom.root().children()[0]).unwrap();
+        assert_eq!(
+            main.properties.get("Source"),
+            Some(&Variant::String("print(1)".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,460 @@
+//! Serde support for whole `WeakDom`s.
+//!
+//! A `WeakDom` is serialized as a struct with two fields:
+//!
+//! - `SharedStrings`, a list of the contents of every `SharedString` in the
+//!   DOM, each listed once.
+//! - `Instances`, a list of every instance in the DOM. The root comes first,
+//!   and every other instance comes after its parent and after the siblings
+//!   that come before it. Each has a `Referent`, `Parent`, `Name`, `Class`,
+//!   and a map of `Properties`, sorted by name.
+//!
+//! Property values are written as `Variant`s, which are tagged with their
+//! type, like `{"Vector3": [1.0, 2.0, 3.0]}` in JSON. `SharedString` values
+//! are the exception, since they can't be serialized as part of a `Variant`:
+//! they're tagged the same way, but hold an index into `SharedStrings`.
+//!
+//! Referents are kept as they are, so Ref properties still point to the same
+//! instances after a round trip.
+
+use std::{collections::HashMap, convert::TryFrom, fmt};
+
+use rbx_types::{BinaryString, Ref, SharedString, SharedStringHash, Variant, VariantType};
+use serde::{
+    de::{self, DeserializeSeed, EnumAccess, Error as _, IntoDeserializer, VariantAccess, Visitor},
+    forward_to_deserialize_any,
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{InstanceBuilder, WeakDom};
+
+impl Serialize for WeakDom {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut shared_strings = SharedStrings::default();
+        let mut instances = Vec::new();
+
+        // Depth-first, so that every instance comes after its parent, without
+        // recursing on deep trees.
+        let mut to_visit = vec![self.root_ref()];
+        while let Some(referent) = to_visit.pop() {
+            let instance = self.get_by_ref(referent).unwrap();
+
+            let mut properties: Vec<_> = instance
+                .properties
+                .iter()
+                .map(|(name, value)| {
+                    let value = match value {
+                        Variant::SharedString(value) => {
+                            PropertyValue::SharedString(shared_strings.index_of(value))
+                        }
+                        other => PropertyValue::Other(other),
+                    };
+
+                    (name.as_str(), value)
+                })
+                .collect();
+            properties.sort_unstable_by_key(|(name, _)| *name);
+
+            instances.push(SerializedInstance {
+                referent,
+                parent: instance.parent(),
+                name: &instance.name,
+                class: &instance.class,
+                properties: Properties(properties),
+            });
+
+            to_visit.extend(instance.children().iter().rev());
+        }
+
+        // Plain values come before tables, as formats like TOML require.
+        let mut state = serializer.serialize_struct("WeakDom", 2)?;
+        state.serialize_field("SharedStrings", &shared_strings.contents)?;
+        state.serialize_field("Instances", &instances)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for WeakDom {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let serialized = DeserializedDom::deserialize(deserializer)?;
+        let shared_strings: Vec<_> = serialized
+            .shared_strings
+            .into_iter()
+            .map(|contents| SharedString::new(contents.into_vec()))
+            .collect();
+
+        let mut instances = serialized.instances.into_iter();
+        let root = instances
+            .next()
+            .ok_or_else(|| D::Error::custom("a WeakDom must have at least one instance"))?;
+
+        if root.referent.is_none() || root.parent.is_some() {
+            return Err(D::Error::custom(
+                "the first instance must be the root, which has a referent but no parent",
+            ));
+        }
+
+        let mut dom = WeakDom::new(root.into_builder(&shared_strings)?);
+
+        for instance in instances {
+            if instance.referent.is_none() || dom.get_by_ref(instance.referent).is_some() {
+                return Err(D::Error::custom(format_args!(
+                    "referent {} is missing or used by more than one instance",
+                    instance.referent
+                )));
+            }
+
+            let parent = instance.parent;
+            if dom.get_by_ref(parent).is_none() {
+                return Err(D::Error::custom(format_args!(
+                    "instance {} comes before its parent {}",
+                    instance.referent, parent
+                )));
+            }
+
+            dom.insert_unchecked(parent, instance.into_builder(&shared_strings)?);
+        }
+
+        Ok(dom)
+    }
+}
+
+/// The contents of every `SharedString` in a DOM, in the order they were
+/// found.
+#[derive(Default)]
+struct SharedStrings {
+    indices: HashMap<SharedStringHash, u32>,
+    contents: Vec<BinaryString>,
+}
+
+impl SharedStrings {
+    fn index_of(&mut self, value: &SharedString) -> u32 {
+        let contents = &mut self.contents;
+
+        *self.indices.entry(value.hash()).or_insert_with(|| {
+            contents.push(BinaryString::from(value.data()));
+            (contents.len() - 1) as u32
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SerializedInstance<'a> {
+    referent: Ref,
+    parent: Ref,
+    name: &'a str,
+    class: &'a str,
+    properties: Properties<'a>,
+}
+
+struct Properties<'a>(Vec<(&'a str, PropertyValue<'a>)>);
+
+impl Serialize for Properties<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(name, value)| (name, value)))
+    }
+}
+
+enum PropertyValue<'a> {
+    SharedString(u32),
+    Other(&'a Variant),
+}
+
+impl Serialize for PropertyValue<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            PropertyValue::SharedString(index) => serializer.serialize_newtype_variant(
+                "Variant",
+                VariantType::SharedString as u32,
+                "SharedString",
+                index,
+            ),
+            PropertyValue::Other(value) => value.serialize(serializer),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "WeakDom", rename_all = "PascalCase")]
+struct DeserializedDom {
+    #[serde(default)]
+    shared_strings: Vec<BinaryString>,
+    instances: Vec<DeserializedInstance>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DeserializedInstance {
+    referent: Ref,
+    parent: Ref,
+    name: String,
+    class: String,
+    #[serde(default)]
+    properties: HashMap<String, OwnedPropertyValue>,
+}
+
+impl DeserializedInstance {
+    fn into_builder<E: de::Error>(
+        self,
+        shared_strings: &[SharedString],
+    ) -> Result<InstanceBuilder, E> {
+        let mut builder = InstanceBuilder::new(self.class).with_name(self.name);
+        builder.referent = self.referent;
+
+        for (name, value) in self.properties {
+            let value = match value {
+                OwnedPropertyValue::SharedString(index) => shared_strings
+                    .get(index as usize)
+                    .cloned()
+                    .map(Variant::SharedString)
+                    .ok_or_else(|| {
+                        E::custom(format_args!(
+                            "property {} refers to SharedString {}, which doesn't exist",
+                            name, index
+                        ))
+                    })?,
+                OwnedPropertyValue::Other(value) => value,
+            };
+
+            builder.add_property(name, value);
+        }
+
+        Ok(builder)
+    }
+}
+
+enum OwnedPropertyValue {
+    SharedString(u32),
+    Other(Variant),
+}
+
+impl<'de> Deserialize<'de> for OwnedPropertyValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // The names of the variants are only used for error messages by the
+        // formats that ask for them, and the Variant we hand off to checks
+        // them itself.
+        deserializer.deserialize_enum("Variant", &[], PropertyValueVisitor)
+    }
+}
+
+struct PropertyValueVisitor;
+
+impl<'de> Visitor<'de> for PropertyValueVisitor {
+    type Value = OwnedPropertyValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a property value tagged with its type")
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let (tag, access) = data.variant::<Tag>()?;
+
+        if tag.is_shared_string() {
+            access
+                .newtype_variant()
+                .map(OwnedPropertyValue::SharedString)
+        } else {
+            Variant::deserialize(TaggedEnum { tag, access }).map(OwnedPropertyValue::Other)
+        }
+    }
+}
+
+/// The tag of a property value, which is its name in formats that write
+/// variant names and its index in formats that don't.
+enum Tag {
+    Name(String),
+    Index(u32),
+}
+
+impl Tag {
+    fn is_shared_string(&self) -> bool {
+        match self {
+            Tag::Name(name) => name == "SharedString",
+            Tag::Index(index) => *index == VariantType::SharedString as u32,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Tag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TagVisitor;
+
+        impl<'de> Visitor<'de> for TagVisitor {
+            type Value = Tag;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("the name or index of a Variant type")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Tag, E> {
+                Ok(Tag::Name(value.to_owned()))
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Tag, E> {
+                u32::try_from(value)
+                    .map(Tag::Index)
+                    .map_err(|_| E::custom(format_args!("{} is not a Variant type", value)))
+            }
+        }
+
+        deserializer.deserialize_identifier(TagVisitor)
+    }
+}
+
+/// Hands a property value whose tag has already been read off to
+/// `Variant`'s own `Deserialize` impl, as if the tag hadn't been read yet.
+struct TaggedEnum<A> {
+    tag: Tag,
+    access: A,
+}
+
+impl<'de, A: VariantAccess<'de>> Deserializer<'de> for TaggedEnum<A> {
+    type Error = A::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, A::Error> {
+        visitor.visit_enum(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> EnumAccess<'de> for TaggedEnum<A> {
+    type Error = A::Error;
+    type Variant = A;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, A), A::Error> {
+        let value = match self.tag {
+            Tag::Name(name) => seed.deserialize(name.into_deserializer())?,
+            Tag::Index(index) => seed.deserialize(index.into_deserializer())?,
+        };
+
+        Ok((value, self.access))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_types::{Attributes, CFrame, Enum, Matrix3, Tags, Vector3};
+
+    use crate::{compare_doms, DomCompareOptions};
+
+    fn test_dom() -> WeakDom {
+        let mesh = SharedString::new(b"mesh data".to_vec());
+
+        let part = InstanceBuilder::new("Part")
+            .with_name("Target")
+            .with_property(
+                "CFrame",
+                CFrame::new(Vector3::new(1.0, 2.0, 3.0), Matrix3::identity()),
+            )
+            .with_property("Material", Enum::from_u32(256))
+            .with_property("PhysicalConfigData", mesh.clone());
+        let part_ref = part.referent();
+
+        let mut tags = Tags::new();
+        tags.push("Tagged");
+
+        let mut attributes = Attributes::new();
+        attributes.insert("Speed".to_owned(), Variant::Float64(16.0));
+
+        let model = InstanceBuilder::new("Model")
+            .with_property("PrimaryPart", part_ref)
+            .with_property("Tags", tags)
+            .with_property("Attributes", attributes)
+            .with_child(part)
+            .with_child(
+                InstanceBuilder::new("MeshPart")
+                    .with_property("PhysicalConfigData", mesh)
+                    .with_child(InstanceBuilder::new("Folder")),
+            );
+
+        WeakDom::new(InstanceBuilder::new("DataModel").with_child(model))
+    }
+
+    fn assert_round_trip(original: &WeakDom, decoded: &WeakDom) {
+        let differences = compare_doms(original, decoded, &DomCompareOptions::new());
+        assert!(differences.is_empty(), "{:?}", differences);
+
+        // Referents are kept as they are, not just in the same positions.
+        assert_eq!(original.root_ref(), decoded.root_ref());
+        let model = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+        assert_eq!(
+            model.properties.get("PrimaryPart"),
+            Some(&Variant::Ref(model.children()[0]))
+        );
+        assert_eq!(
+            original.get_by_ref(model.children()[0]).unwrap().name,
+            "Target"
+        );
+    }
+
+    #[test]
+    fn json() {
+        let dom = test_dom();
+        let encoded = serde_json::to_string(&dom).unwrap();
+
+        // Both instances share the one copy of the mesh data.
+        let value: serde_json::Value = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(value["SharedStrings"].as_array().unwrap().len(), 1);
+
+        let decoded: WeakDom = serde_json::from_str(&encoded).unwrap();
+        assert_round_trip(&dom, &decoded);
+    }
+
+    #[test]
+    fn yaml() {
+        let dom = test_dom();
+        let encoded = serde_yaml::to_string(&dom).unwrap();
+        let decoded: WeakDom = serde_yaml::from_str(&encoded).unwrap();
+        assert_round_trip(&dom, &decoded);
+    }
+
+    #[test]
+    fn message_pack() {
+        let dom = test_dom();
+        let encoded = rmp_serde::to_vec(&dom).unwrap();
+        let decoded: WeakDom = rmp_serde::from_slice(&encoded).unwrap();
+        assert_round_trip(&dom, &decoded);
+    }
+
+    #[test]
+    fn bincode() {
+        // bincode writes the index of each Variant type instead of its name.
+        let dom = test_dom();
+        let encoded = bincode::serialize(&dom).unwrap();
+        let decoded: WeakDom = bincode::deserialize(&encoded).unwrap();
+        assert_round_trip(&dom, &decoded);
+    }
+
+    #[test]
+    fn parent_must_come_first() {
+        let dom = test_dom();
+        let mut value = serde_json::to_value(&dom).unwrap();
+        value["Instances"].as_array_mut().unwrap().swap(1, 2);
+
+        let err = serde_json::from_value::<WeakDom>(value).unwrap_err();
+        assert!(
+            err.to_string().contains("comes before its parent"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn missing_shared_string() {
+        let dom = test_dom();
+        let mut value = serde_json::to_value(&dom).unwrap();
+        value["SharedStrings"] = serde_json::json!([]);
+
+        let err = serde_json::from_value::<WeakDom>(value).unwrap_err();
+        assert!(err.to_string().contains("doesn't exist"), "{}", err);
+    }
+}
@@ -0,0 +1,142 @@
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::WeakDom;
+
+/**
+A handle to a [`WeakDom`] that can be shared between threads.
+
+Any number of threads can read from the dom at the same time, while writes
+are serialized and wait for all readers to finish. Cloning a `SyncDom` is
+cheap and produces another handle to the same dom.
+
+## Examples
+```
+use std::thread;
+
+use rbx_dom_weak::{InstanceBuilder, SyncDom, WeakDom};
+
+let dom = SyncDom::new(WeakDom::new(InstanceBuilder::new("DataModel")));
+
+let writer = {
+    let dom = dom.clone();
+    thread::spawn(move || {
+        let mut dom = dom.write();
+        let root_ref = dom.root_ref();
+        dom.insert(root_ref, InstanceBuilder::new("Workspace"));
+    })
+};
+
+writer.join().unwrap();
+assert_eq!(dom.read().root().children().len(), 1);
+```
+*/
+#[derive(Debug, Clone)]
+pub struct SyncDom {
+    inner: Arc<RwLock<WeakDom>>,
+}
+
+impl SyncDom {
+    /// Wrap the given `WeakDom` so that it can be shared between threads.
+    pub fn new(dom: WeakDom) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(dom)),
+        }
+    }
+
+    /// Lock the dom for reading, blocking until no thread is writing to it.
+    ///
+    /// ## Panics
+    /// Panics if a thread panicked while writing to the dom, since the dom may
+    /// have been left partially modified.
+    pub fn read(&self) -> RwLockReadGuard<'_, WeakDom> {
+        self.inner
+            .read()
+            .expect("a thread panicked while writing to this SyncDom")
+    }
+
+    /// Lock the dom for writing, blocking until no other thread is reading
+    /// from or writing to it.
+    ///
+    /// ## Panics
+    /// Panics if a thread panicked while writing to the dom, since the dom may
+    /// have been left partially modified.
+    pub fn write(&self) -> RwLockWriteGuard<'_, WeakDom> {
+        self.inner
+            .write()
+            .expect("a thread panicked while writing to this SyncDom")
+    }
+
+    /// Consumes the `SyncDom`, returning the `WeakDom` inside of it if this is
+    /// the only remaining handle. Otherwise, the handle is returned unchanged.
+    pub fn try_into_inner(self) -> Result<WeakDom, SyncDom> {
+        match Arc::try_unwrap(self.inner) {
+            Ok(lock) => Ok(lock
+                .into_inner()
+                .expect("a thread panicked while writing to this SyncDom")),
+            Err(inner) => Err(SyncDom { inner }),
+        }
+    }
+}
+
+impl From<WeakDom> for SyncDom {
+    fn from(dom: WeakDom) -> Self {
+        Self::new(dom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::thread;
+
+    use crate::InstanceBuilder;
+
+    #[test]
+    fn concurrent_readers_and_writer() {
+        let dom = SyncDom::new(WeakDom::new(InstanceBuilder::new("DataModel")));
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let dom = dom.clone();
+                thread::spawn(move || {
+                    for _ in 0..100 {
+                        let dom = dom.read();
+                        for &child in dom.root().children() {
+                            assert!(dom.get_by_ref(child).is_some());
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let writer = {
+            let dom = dom.clone();
+            thread::spawn(move || {
+                for _ in 0..100 {
+                    let mut dom = dom.write();
+                    let root_ref = dom.root_ref();
+                    dom.insert(root_ref, InstanceBuilder::new("Folder"));
+                }
+            })
+        };
+
+        writer.join().unwrap();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        let dom = dom.try_into_inner().unwrap();
+        assert_eq!(dom.root().children().len(), 100);
+    }
+
+    #[test]
+    fn try_into_inner_shared() {
+        let dom = SyncDom::new(WeakDom::new(InstanceBuilder::new("DataModel")));
+        let other = dom.clone();
+
+        let dom = dom.try_into_inner().unwrap_err();
+        drop(other);
+        assert!(dom.try_into_inner().is_ok());
+    }
+}
@@ -0,0 +1,351 @@
+use rbx_types::{Attributes, CFrame, Matrix3, Ref, Variant, Vector3};
+
+use crate::{InstanceBuilder, WeakDom};
+
+/**
+A subtree of instances that can be stamped into a [`WeakDom`] many times.
+
+Creating a `Template` copies the subtree out of its source, so the source can
+be changed or dropped afterwards. Each call to [`Template::instantiate`]
+produces a fresh copy with its own referents. Ref properties that point to
+instances inside the template are rewritten to point to the matching instance
+in the new copy, while Ref properties that point outside of the template are
+set to `Ref::none()`.
+
+## Examples
+```
+use rbx_dom_weak::{InstanceBuilder, Template, TemplateOverrides, WeakDom};
+use rbx_dom_weak::types::{Attributes, CFrame, Matrix3, Vector3};
+
+let source = WeakDom::new(
+    InstanceBuilder::new("Part")
+        .with_name("Checkpoint")
+        .with_property("CFrame", CFrame::new(Vector3::new(0.0, 0.0, 0.0), Matrix3::identity())),
+);
+let template = Template::new(&source, source.root_ref());
+
+let mut dom = WeakDom::new(InstanceBuilder::new("Workspace"));
+let root_ref = dom.root_ref();
+
+for i in 0..3 {
+    let offset = CFrame::new(Vector3::new(i as f32 * 10.0, 0.0, 0.0), Matrix3::identity());
+    let overrides = TemplateOverrides::new()
+        .with_name(format!("Checkpoint{}", i))
+        .with_cframe_offset(offset)
+        .with_attribute("Index", i as f64);
+
+    template.instantiate_with(&mut dom, root_ref, &overrides);
+}
+
+assert_eq!(dom.root().children().len(), 3);
+```
+*/
+#[derive(Debug)]
+pub struct Template {
+    dom: WeakDom,
+    root_ref: Ref,
+}
+
+impl Template {
+    /// Create a new `Template` from the instance with the given `referent` in
+    /// `source` and all of its descendants.
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in `source`.
+    pub fn new(source: &WeakDom, referent: Ref) -> Self {
+        let mut dom = WeakDom::new(InstanceBuilder::new("Folder"));
+        let root_ref = source.clone_into_external(referent, &mut dom);
+        let dom_root = dom.root_ref();
+        dom.transfer_within(root_ref, dom_root);
+
+        Self { dom, root_ref }
+    }
+
+    /// Insert a copy of the template into `dest` under `parent_ref`, returning
+    /// the referent of the copy's root.
+    ///
+    /// ## Panics
+    /// Panics if `parent_ref` does not refer to an instance in `dest`.
+    pub fn instantiate(&self, dest: &mut WeakDom, parent_ref: Ref) -> Ref {
+        self.instantiate_with(dest, parent_ref, &TemplateOverrides::new())
+    }
+
+    /// Insert a copy of the template into `dest` under `parent_ref`, applying
+    /// the given overrides to the copy. Returns the referent of the copy's
+    /// root.
+    ///
+    /// ## Panics
+    /// Panics if `parent_ref` does not refer to an instance in `dest`.
+    pub fn instantiate_with(
+        &self,
+        dest: &mut WeakDom,
+        parent_ref: Ref,
+        overrides: &TemplateOverrides,
+    ) -> Ref {
+        let root_ref = self.dom.clone_into_external(self.root_ref, dest);
+
+        if let Some(offset) = overrides.cframe_offset {
+            offset_subtree(dest, root_ref, offset);
+        }
+
+        let root = dest.get_by_ref_mut(root_ref).unwrap();
+
+        if let Some(name) = &overrides.name {
+            root.name = name.clone();
+        }
+
+        if overrides.attributes.iter().next().is_some() {
+            let attributes = root
+                .properties
+                .entry("Attributes".to_owned())
+                .or_insert_with(|| Variant::Attributes(Attributes::new()));
+
+            match attributes {
+                Variant::Attributes(attributes) => {
+                    for (key, value) in overrides.attributes.iter() {
+                        attributes.insert(key.clone(), value.clone());
+                    }
+                }
+                other => *other = Variant::Attributes(overrides.attributes.clone()),
+            }
+        }
+
+        dest.transfer_within(root_ref, parent_ref);
+        root_ref
+    }
+}
+
+/// Changes to apply to a single copy of a [`Template`], passed to
+/// [`Template::instantiate_with`].
+#[derive(Debug, Default, Clone)]
+pub struct TemplateOverrides {
+    name: Option<String>,
+    cframe_offset: Option<CFrame>,
+    attributes: Attributes,
+}
+
+impl TemplateOverrides {
+    /// Create a new `TemplateOverrides` that leaves the copy unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the Name of the root of the copy.
+    pub fn with_name<S: Into<String>>(self, name: S) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..self
+        }
+    }
+
+    /// Transform the copy by the given CFrame.
+    ///
+    /// The offset is applied to the `CFrame` property of every instance in the
+    /// copy, including parts nested inside of other parts, except for
+    /// Attachments and Bones, since their `CFrame` is relative to their
+    /// parent. It is also applied to any `WorldPivotData` property.
+    pub fn with_cframe_offset(self, offset: CFrame) -> Self {
+        Self {
+            cframe_offset: Some(offset),
+            ..self
+        }
+    }
+
+    /// Set an attribute on the root of the copy, overwriting any attribute
+    /// with the same name that it already had.
+    pub fn with_attribute<K: Into<String>, V: Into<Variant>>(self, key: K, value: V) -> Self {
+        Self {
+            attributes: self.attributes.with(key, value),
+            ..self
+        }
+    }
+}
+
+/// The classes whose `CFrame` is relative to their parent instead of being in
+/// world space. Bone is the only subclass of Attachment.
+const PARENT_RELATIVE_CLASSES: &[&str] = &["Attachment", "Bone"];
+
+fn offset_subtree(dom: &mut WeakDom, root_ref: Ref, offset: CFrame) {
+    let mut to_visit = vec![root_ref];
+
+    while let Some(referent) = to_visit.pop() {
+        let instance = dom.get_by_ref_mut(referent).unwrap();
+        let relative = PARENT_RELATIVE_CLASSES.contains(&instance.class.as_str());
+
+        for (name, value) in instance.properties.iter_mut() {
+            match (name.as_str(), value) {
+                ("CFrame", Variant::CFrame(cframe)) if !relative => {
+                    *cframe = compose(offset, *cframe);
+                }
+                ("WorldPivotData", Variant::OptionalCFrame(Some(cframe))) => {
+                    *cframe = compose(offset, *cframe);
+                }
+                _ => {}
+            }
+        }
+
+        to_visit.extend(instance.children().iter().copied());
+    }
+}
+
+/// Computes `a * b`, transforming `b` by `a`.
+fn compose(a: CFrame, b: CFrame) -> CFrame {
+    let position = transform(a.orientation, b.position);
+    let columns = b.orientation.transpose();
+
+    CFrame::new(
+        Vector3::new(
+            position.x + a.position.x,
+            position.y + a.position.y,
+            position.z + a.position.z,
+        ),
+        Matrix3::new(
+            transform(columns, a.orientation.x),
+            transform(columns, a.orientation.y),
+            transform(columns, a.orientation.z),
+        ),
+    )
+}
+
+fn transform(matrix: Matrix3, vector: Vector3) -> Vector3 {
+    let dot = |row: Vector3| row.x * vector.x + row.y * vector.y + row.z * vector.z;
+    Vector3::new(dot(matrix.x), dot(matrix.y), dot(matrix.z))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::DomViewer;
+
+    #[test]
+    fn instantiate() {
+        let attachment = InstanceBuilder::new("Attachment").with_property(
+            "CFrame",
+            CFrame::new(Vector3::new(0.0, 1.0, 0.0), Matrix3::identity()),
+        );
+
+        let source = WeakDom::new(
+            InstanceBuilder::new("Model")
+                .with_name("Prefab")
+                .with_child(
+                    InstanceBuilder::new("Part")
+                        .with_property(
+                            "CFrame",
+                            CFrame::new(Vector3::new(1.0, 2.0, 3.0), Matrix3::identity()),
+                        )
+                        .with_child(attachment),
+                ),
+        );
+
+        let template = Template::new(&source, source.root_ref());
+
+        let mut dom = WeakDom::new(InstanceBuilder::new("Workspace"));
+        let root_ref = dom.root_ref();
+
+        template.instantiate(&mut dom, root_ref);
+        template.instantiate_with(
+            &mut dom,
+            root_ref,
+            &TemplateOverrides::new()
+                .with_name("Moved")
+                .with_cframe_offset(CFrame::new(
+                    Vector3::new(10.0, 0.0, 0.0),
+                    Matrix3::identity(),
+                ))
+                .with_attribute("Seed", 7.0),
+        );
+
+        // This snapshot should contain two copies of Prefab. The second one is
+        // named Moved, has a Seed attribute, and its Part is offset by 10 studs
+        // on the X axis. The Attachment in both copies should be unchanged.
+        let mut viewer = DomViewer::new();
+        insta::assert_yaml_snapshot!(viewer.view_children(&dom));
+    }
+
+    #[test]
+    fn offset_nested_parts() {
+        let cframe = |x: f32| CFrame::new(Vector3::new(x, 0.0, 0.0), Matrix3::identity());
+
+        let bone = InstanceBuilder::new("Bone").with_property("CFrame", cframe(1.0));
+        let inner = InstanceBuilder::new("Part")
+            .with_property("CFrame", cframe(2.0))
+            .with_child(bone);
+
+        let source = WeakDom::new(
+            InstanceBuilder::new("Part")
+                .with_property("CFrame", cframe(0.0))
+                .with_child(inner),
+        );
+        let template = Template::new(&source, source.root_ref());
+
+        let mut dom = WeakDom::new(InstanceBuilder::new("Workspace"));
+        let root_ref = dom.root_ref();
+        let copy_ref = template.instantiate_with(
+            &mut dom,
+            root_ref,
+            &TemplateOverrides::new().with_cframe_offset(cframe(10.0)),
+        );
+
+        let cframe_of =
+            |referent: Ref| match dom.get_by_ref(referent).unwrap().properties.get("CFrame") {
+                Some(Variant::CFrame(cframe)) => cframe.position.x,
+                other => panic!("expected a CFrame, got {:?}", other),
+            };
+
+        let copy = dom.get_by_ref(copy_ref).unwrap();
+        let copy_inner = copy.children()[0];
+        let copy_bone = dom.get_by_ref(copy_inner).unwrap().children()[0];
+
+        // Both parts are in world space, so both move. The Bone is relative to
+        // the inner part, so it doesn't.
+        assert_eq!(cframe_of(copy_ref), 10.0);
+        assert_eq!(cframe_of(copy_inner), 12.0);
+        assert_eq!(cframe_of(copy_bone), 1.0);
+    }
+
+    #[test]
+    fn refs_rewritten_per_copy() {
+        let target = InstanceBuilder::new("Part");
+        let weld = InstanceBuilder::new("WeldConstraint").with_property("Part0", target.referent());
+
+        let source = WeakDom::new(InstanceBuilder::new("Model").with_children([target, weld]));
+        let template = Template::new(&source, source.root_ref());
+
+        let mut dom = WeakDom::new(InstanceBuilder::new("Workspace"));
+        let root_ref = dom.root_ref();
+
+        for _ in 0..2 {
+            let copy_ref = template.instantiate(&mut dom, root_ref);
+            let copy = dom.get_by_ref(copy_ref).unwrap();
+            let (part_ref, weld_ref) = (copy.children()[0], copy.children()[1]);
+
+            let weld = dom.get_by_ref(weld_ref).unwrap();
+            assert_eq!(weld.properties.get("Part0"), Some(&Variant::Ref(part_ref)));
+        }
+    }
+
+    #[test]
+    fn compose_rotation() {
+        // 90 degrees around the Y axis
+        let rotation = Matrix3::new(
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(-1.0, 0.0, 0.0),
+        );
+
+        let a = CFrame::new(Vector3::new(0.0, 5.0, 0.0), rotation);
+        let b = CFrame::new(Vector3::new(1.0, 0.0, 0.0), rotation);
+        let composed = compose(a, b);
+
+        assert_eq!(composed.position, Vector3::new(0.0, 5.0, -1.0));
+        assert_eq!(
+            composed.orientation,
+            Matrix3::new(
+                Vector3::new(-1.0, 0.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(0.0, 0.0, -1.0),
+            )
+        );
+    }
+}
@@ -0,0 +1,336 @@
+//! Building the JSON and binary buffer of a glTF asset.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::Mesh;
+
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Root {
+    asset: Asset,
+    scene: usize,
+    scenes: Vec<Scene>,
+    nodes: Vec<Node>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    meshes: Vec<MeshJson>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    materials: Vec<Material>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    accessors: Vec<Accessor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    buffer_views: Vec<BufferView>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    buffers: Vec<Buffer>,
+}
+
+#[derive(Debug, Serialize)]
+struct Asset {
+    version: &'static str,
+    generator: &'static str,
+}
+
+impl Default for Asset {
+    fn default() -> Self {
+        Asset {
+            version: "2.0",
+            generator: concat!("rbx_gltf ", env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Scene {
+    nodes: Vec<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Node {
+    pub name: String,
+    pub mesh: usize,
+    pub matrix: [f32; 16],
+    pub extras: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct MeshJson {
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Debug, Serialize)]
+struct Primitive {
+    attributes: BTreeMap<&'static str, usize>,
+    indices: usize,
+    material: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Material {
+    pbr_metallic_roughness: Pbr,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alpha_mode: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Pbr {
+    base_color_factor: [f32; 4],
+    metallic_factor: f32,
+    roughness_factor: f32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: usize,
+    component_type: u32,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<[f32; 3]>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView {
+    buffer: usize,
+    byte_offset: usize,
+    byte_length: usize,
+    target: u32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Buffer {
+    byte_length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uri: Option<String>,
+}
+
+/// The accessors of a mesh that has been added to a [`Document`].
+#[derive(Debug, Clone, Copy)]
+pub struct Geometry {
+    position: usize,
+    normal: Option<usize>,
+    indices: usize,
+}
+
+/// A glTF asset with one scene, built up one node at a time.
+#[derive(Debug, Default)]
+pub struct Document {
+    root: Root,
+    buffer: Vec<u8>,
+    meshes: HashMap<(usize, usize), usize>,
+    materials: HashMap<[u32; 4], usize>,
+}
+
+impl Document {
+    pub fn new() -> Self {
+        let mut document = Document::default();
+        document.root.scenes.push(Scene::default());
+        document
+    }
+
+    /// Writes the vertices and indices of a mesh to the buffer.
+    pub fn add_geometry(&mut self, mesh: &Mesh) -> Geometry {
+        let (min, max) = mesh.bounds().unwrap_or(([0.0; 3], [0.0; 3]));
+        let position = self.add_accessor(
+            mesh.positions
+                .iter()
+                .flatten()
+                .flat_map(|value| value.to_le_bytes()),
+            ARRAY_BUFFER,
+            Accessor {
+                buffer_view: 0,
+                component_type: FLOAT,
+                count: mesh.positions.len(),
+                kind: "VEC3",
+                min: Some(min),
+                max: Some(max),
+            },
+        );
+
+        // glTF requires exactly one normal per vertex, so meshes without
+        // enough are left for viewers to shade flat.
+        let normal = (mesh.normals.len() == mesh.positions.len()).then(|| {
+            self.add_accessor(
+                mesh.normals
+                    .iter()
+                    .flatten()
+                    .flat_map(|value| value.to_le_bytes()),
+                ARRAY_BUFFER,
+                Accessor {
+                    buffer_view: 0,
+                    component_type: FLOAT,
+                    count: mesh.normals.len(),
+                    kind: "VEC3",
+                    min: None,
+                    max: None,
+                },
+            )
+        });
+
+        let indices = self.add_accessor(
+            mesh.indices.iter().flat_map(|value| value.to_le_bytes()),
+            ELEMENT_ARRAY_BUFFER,
+            Accessor {
+                buffer_view: 0,
+                component_type: UNSIGNED_INT,
+                count: mesh.indices.len(),
+                kind: "SCALAR",
+                min: None,
+                max: None,
+            },
+        );
+
+        Geometry {
+            position,
+            normal,
+            indices,
+        }
+    }
+
+    fn add_accessor(
+        &mut self,
+        bytes: impl Iterator<Item = u8>,
+        target: u32,
+        mut accessor: Accessor,
+    ) -> usize {
+        // Every value is four bytes, so views stay aligned without padding.
+        let byte_offset = self.buffer.len();
+        self.buffer.extend(bytes);
+
+        accessor.buffer_view = self.root.buffer_views.len();
+        self.root.buffer_views.push(BufferView {
+            buffer: 0,
+            byte_offset,
+            byte_length: self.buffer.len() - byte_offset,
+            target,
+        });
+
+        self.root.accessors.push(accessor);
+        self.root.accessors.len() - 1
+    }
+
+    /// Returns the mesh drawing `geometry` with a material of the given
+    /// linear color and opacity, adding one if there isn't one yet.
+    pub fn mesh(&mut self, geometry: Geometry, color: [f32; 4]) -> usize {
+        let material = self.material(color);
+        let meshes = &mut self.root.meshes;
+
+        *self
+            .meshes
+            .entry((geometry.position, material))
+            .or_insert_with(|| {
+                let mut attributes = BTreeMap::new();
+                attributes.insert("POSITION", geometry.position);
+                if let Some(normal) = geometry.normal {
+                    attributes.insert("NORMAL", normal);
+                }
+
+                meshes.push(MeshJson {
+                    primitives: vec![Primitive {
+                        attributes,
+                        indices: geometry.indices,
+                        material,
+                    }],
+                });
+                meshes.len() - 1
+            })
+    }
+
+    fn material(&mut self, color: [f32; 4]) -> usize {
+        let materials = &mut self.root.materials;
+
+        *self
+            .materials
+            .entry(color.map(f32::to_bits))
+            .or_insert_with(|| {
+                materials.push(Material {
+                    pbr_metallic_roughness: Pbr {
+                        base_color_factor: color,
+                        metallic_factor: 0.0,
+                        roughness_factor: 1.0,
+                    },
+                    alpha_mode: (color[3] < 1.0).then_some("BLEND"),
+                });
+                materials.len() - 1
+            })
+    }
+
+    pub fn add_node(&mut self, node: Node) {
+        self.root.scenes[0].nodes.push(self.root.nodes.len());
+        self.root.nodes.push(node);
+    }
+
+    /// Writes the asset as a `.gltf` file, with the buffer embedded as a data
+    /// URI.
+    pub fn write_gltf<W: Write>(mut self, mut writer: W) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            self.root.buffers.push(Buffer {
+                byte_length: self.buffer.len(),
+                uri: Some(format!(
+                    "data:application/octet-stream;base64,{}",
+                    base64::encode(&self.buffer)
+                )),
+            });
+        }
+
+        serde_json::to_writer(&mut writer, &self.root)?;
+        Ok(())
+    }
+
+    /// Writes the asset as a binary `.glb` file.
+    pub fn write_glb<W: Write>(mut self, mut writer: W) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            self.root.buffers.push(Buffer {
+                byte_length: self.buffer.len(),
+                uri: None,
+            });
+        }
+
+        let mut json = serde_json::to_vec(&self.root)?;
+        pad(&mut json, b' ');
+        pad(&mut self.buffer, 0);
+
+        let mut length = 12 + 8 + json.len();
+        if !self.buffer.is_empty() {
+            length += 8 + self.buffer.len();
+        }
+
+        writer.write_all(b"glTF")?;
+        writer.write_all(&2u32.to_le_bytes())?;
+        writer.write_all(&(length as u32).to_le_bytes())?;
+
+        writer.write_all(&(json.len() as u32).to_le_bytes())?;
+        writer.write_all(b"JSON")?;
+        writer.write_all(&json)?;
+
+        if !self.buffer.is_empty() {
+            writer.write_all(&(self.buffer.len() as u32).to_le_bytes())?;
+            writer.write_all(b"BIN\0")?;
+            writer.write_all(&self.buffer)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Pads a GLB chunk to a multiple of four bytes.
+fn pad(chunk: &mut Vec<u8>, byte: u8) {
+    while !chunk.len().is_multiple_of(4) {
+        chunk.push(byte);
+    }
+}
@@ -0,0 +1,239 @@
+//! The meshes of the part shapes that Roblox draws itself, one unit wide on
+//! every axis and centered on the origin.
+
+use std::f32::consts::PI;
+
+use crate::Mesh;
+
+/// How many sides cylinders and spheres are drawn with around their axis.
+const SEGMENTS: u32 = 24;
+
+/// How many rings spheres are drawn with from pole to pole.
+const RINGS: u32 = 12;
+
+pub fn block() -> Mesh {
+    let mut mesh = Mesh::default();
+
+    let faces = [
+        ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+        ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([0.0, 0.0, -1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]),
+    ];
+
+    // Each face is a square facing along `normal`, where `u` cross `v` is
+    // `normal` so that its corners go counterclockwise seen from outside.
+    for (normal, u, v) in faces {
+        let corner = |su: f32, sv: f32| {
+            [0, 1, 2].map(|axis| 0.5 * (normal[axis] + su * u[axis] + sv * v[axis]))
+        };
+
+        quad(
+            &mut mesh,
+            [
+                corner(-1.0, -1.0),
+                corner(1.0, -1.0),
+                corner(1.0, 1.0),
+                corner(-1.0, 1.0),
+            ],
+            normal,
+        );
+    }
+
+    mesh
+}
+
+/// A wedge like Roblox's WedgePart, which is full height at the back (+Z)
+/// and slopes down to the bottom at the front (-Z).
+pub fn wedge() -> Mesh {
+    let mut mesh = Mesh::default();
+    let slope = 0.5f32.sqrt();
+
+    quad(
+        &mut mesh,
+        [
+            [-0.5, -0.5, -0.5],
+            [0.5, -0.5, -0.5],
+            [0.5, -0.5, 0.5],
+            [-0.5, -0.5, 0.5],
+        ],
+        [0.0, -1.0, 0.0],
+    );
+    quad(
+        &mut mesh,
+        [
+            [-0.5, -0.5, 0.5],
+            [0.5, -0.5, 0.5],
+            [0.5, 0.5, 0.5],
+            [-0.5, 0.5, 0.5],
+        ],
+        [0.0, 0.0, 1.0],
+    );
+    quad(
+        &mut mesh,
+        [
+            [-0.5, -0.5, -0.5],
+            [-0.5, 0.5, 0.5],
+            [0.5, 0.5, 0.5],
+            [0.5, -0.5, -0.5],
+        ],
+        [0.0, slope, -slope],
+    );
+    triangle(
+        &mut mesh,
+        [[0.5, -0.5, -0.5], [0.5, 0.5, 0.5], [0.5, -0.5, 0.5]],
+        [1.0, 0.0, 0.0],
+    );
+    triangle(
+        &mut mesh,
+        [[-0.5, -0.5, -0.5], [-0.5, -0.5, 0.5], [-0.5, 0.5, 0.5]],
+        [-1.0, 0.0, 0.0],
+    );
+
+    mesh
+}
+
+/// A cylinder along the X axis, like Roblox's cylinder parts.
+pub fn cylinder() -> Mesh {
+    let mut mesh = Mesh::default();
+    let point = |segment: u32| {
+        let angle = 2.0 * PI * segment as f32 / SEGMENTS as f32;
+        (angle.cos(), angle.sin())
+    };
+
+    for segment in 0..SEGMENTS {
+        let (y0, z0) = point(segment);
+        let (y1, z1) = point(segment + 1);
+        let base = mesh.positions.len() as u32;
+
+        for (x, y, z) in [(-0.5, y0, z0), (-0.5, y1, z1), (0.5, y1, z1), (0.5, y0, z0)] {
+            mesh.positions.push([x, 0.5 * y, 0.5 * z]);
+            mesh.normals.push([0.0, y, z]);
+        }
+        mesh.indices
+            .extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+
+        triangle(
+            &mut mesh,
+            [
+                [0.5, 0.0, 0.0],
+                [0.5, 0.5 * y0, 0.5 * z0],
+                [0.5, 0.5 * y1, 0.5 * z1],
+            ],
+            [1.0, 0.0, 0.0],
+        );
+        triangle(
+            &mut mesh,
+            [
+                [-0.5, 0.0, 0.0],
+                [-0.5, 0.5 * y1, 0.5 * z1],
+                [-0.5, 0.5 * y0, 0.5 * z0],
+            ],
+            [-1.0, 0.0, 0.0],
+        );
+    }
+
+    mesh
+}
+
+pub fn sphere() -> Mesh {
+    let mut mesh = Mesh::default();
+
+    for ring in 0..=RINGS {
+        let polar = PI * ring as f32 / RINGS as f32;
+
+        for segment in 0..=SEGMENTS {
+            let azimuth = 2.0 * PI * segment as f32 / SEGMENTS as f32;
+            let normal = [
+                polar.sin() * azimuth.cos(),
+                polar.cos(),
+                polar.sin() * azimuth.sin(),
+            ];
+
+            mesh.positions.push(normal.map(|value| 0.5 * value));
+            mesh.normals.push(normal);
+        }
+    }
+
+    let row = SEGMENTS + 1;
+    for ring in 0..RINGS {
+        for segment in 0..SEGMENTS {
+            let top = ring * row + segment;
+            let bottom = top + row;
+
+            if ring != 0 {
+                mesh.indices.extend_from_slice(&[top, top + 1, bottom]);
+            }
+            if ring != RINGS - 1 {
+                mesh.indices
+                    .extend_from_slice(&[top + 1, bottom + 1, bottom]);
+            }
+        }
+    }
+
+    mesh
+}
+
+/// Adds a flat four-sided face with its corners in counterclockwise order.
+fn quad(mesh: &mut Mesh, corners: [[f32; 3]; 4], normal: [f32; 3]) {
+    let base = mesh.positions.len() as u32;
+
+    mesh.positions.extend_from_slice(&corners);
+    mesh.normals.extend_from_slice(&[normal; 4]);
+    mesh.indices
+        .extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+}
+
+/// Adds a flat triangle with its corners in counterclockwise order.
+fn triangle(mesh: &mut Mesh, corners: [[f32; 3]; 3], normal: [f32; 3]) {
+    let base = mesh.positions.len() as u32;
+
+    mesh.positions.extend_from_slice(&corners);
+    mesh.normals.extend_from_slice(&[normal; 3]);
+    mesh.indices.extend_from_slice(&[base, base + 1, base + 2]);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Checks that every triangle of a mesh is wound counterclockwise seen
+    /// from the side its normals point to, which is what glTF expects.
+    fn assert_outward(mesh: &Mesh) {
+        for triangle in mesh.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.positions[triangle[i] as usize]);
+            let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+            let ac = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+            let cross = [
+                ab[1] * ac[2] - ab[2] * ac[1],
+                ab[2] * ac[0] - ab[0] * ac[2],
+                ab[0] * ac[1] - ab[1] * ac[0],
+            ];
+
+            let normal = mesh.normals[triangle[0] as usize];
+            let dot: f32 = (0..3).map(|axis| cross[axis] * normal[axis]).sum();
+            assert!(dot > 0.0, "triangle {:?} faces inward", triangle);
+        }
+    }
+
+    #[test]
+    fn shapes_face_outward() {
+        assert_outward(&block());
+        assert_outward(&wedge());
+        assert_outward(&cylinder());
+        assert_outward(&sphere());
+    }
+
+    #[test]
+    fn shapes_fit_unit_cube() {
+        for mesh in [block(), wedge(), cylinder(), sphere()] {
+            let (min, max) = mesh.bounds().unwrap();
+            for axis in 0..3 {
+                assert!((min[axis] + 0.5).abs() < 1e-6);
+                assert!((max[axis] - 0.5).abs() < 1e-6);
+            }
+        }
+    }
+}
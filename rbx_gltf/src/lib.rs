@@ -0,0 +1,550 @@
+/*!
+Exports the parts of a Roblox DOM as a glTF scene, for looking at places and
+models outside of Roblox Studio.
+
+Blocks, wedges, cylinders, and spheres are drawn the way Roblox draws them,
+at the position, rotation, and size of each part, in its color and
+transparency. Roblox doesn't give the geometry of MeshParts or unions in the
+file, so they're drawn as boxes the size of the part, unless a
+[`MeshProvider`] can supply the mesh of a MeshPart. [`ResolverMeshProvider`]
+reads Roblox's mesh files through any
+[`ContentResolver`](rbx_content::ContentResolver), like one that reads them
+from a directory.
+
+```
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use rbx_dom_weak::types::Vector3;
+
+let dom = WeakDom::new(InstanceBuilder::new("Part")
+    .with_property("Size", Vector3::new(4.0, 1.0, 2.0)));
+
+let mut glb = Vec::new();
+rbx_gltf::to_glb(&mut glb, &dom, &[dom.root_ref()])?;
+# Ok::<(), rbx_gltf::Error>(())
+```
+
+Both formats use Roblox's coordinates as they are, one unit to one stud. Like
+glTF, Roblox is right-handed with +Y up, but its models face -Z instead of
++Z.
+
+Each part becomes one node of the scene, named after the part. Its `extras`
+hold the full name of the part, like `Workspace.Map.Part`, its ClassName,
+and `"Placeholder": true` when it was drawn as a box in place of its real
+shape.
+*/
+
+mod document;
+mod geometry;
+mod mesh_file;
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use rbx_content::ContentResolver;
+use rbx_dom_weak::{
+    types::{CFrame, Matrix3, Ref, Variant, Vector3},
+    Instance, WeakDom,
+};
+use rbx_reflection::ReflectionDatabase;
+use serde_json::json;
+use thiserror::Error;
+
+use crate::document::{Document, Geometry, Node};
+
+/// The geometry of a mesh: triangles of vertex positions, with an optional
+/// normal for each vertex.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mesh {
+    pub positions: Vec<[f32; 3]>,
+
+    /// One normal for each position, or none to leave shading to the viewer.
+    pub normals: Vec<[f32; 3]>,
+
+    /// Three indices into `positions` for each triangle, counterclockwise seen
+    /// from the outside.
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    /// The smallest and largest position on each axis, if there are any.
+    pub fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        let first = *self.positions.first()?;
+
+        Some(self.positions.iter().fold((first, first), |(min, max), p| {
+            (
+                [0, 1, 2].map(|axis| min[axis].min(p[axis])),
+                [0, 1, 2].map(|axis| max[axis].max(p[axis])),
+            )
+        }))
+    }
+
+    /// Reads the geometry of a Roblox mesh file, the kind of asset that a
+    /// MeshPart's `MeshId` points to. Versions 1 through 5 can be read. Only
+    /// the most detailed level of detail is kept.
+    pub fn from_roblox_mesh(bytes: &[u8]) -> Option<Mesh> {
+        mesh_file::parse(bytes)
+    }
+}
+
+/// Supplies the meshes of MeshParts from their `MeshId`, like by loading them
+/// from a directory of assets.
+///
+/// Meshes can be any size. Each one is stretched so that its bounds fill the
+/// `Size` of the part, the same way Roblox centers and scales meshes.
+///
+/// Closures taking a `&str` and returning an `Option<Mesh>` are providers.
+pub trait MeshProvider {
+    /// Returns the mesh for a `MeshId`, like `rbxassetid://123`, or `None` to
+    /// draw a placeholder instead.
+    fn get_mesh(&self, mesh_id: &str) -> Option<Mesh>;
+}
+
+impl<F> MeshProvider for F
+where
+    F: Fn(&str) -> Option<Mesh>,
+{
+    fn get_mesh(&self, mesh_id: &str) -> Option<Mesh> {
+        self(mesh_id)
+    }
+}
+
+/// A [`MeshProvider`] that gets Roblox mesh files from a [`ContentResolver`].
+///
+/// Meshes that can't be resolved or read are drawn as placeholders.
+pub struct ResolverMeshProvider<R> {
+    resolver: R,
+}
+
+impl<R: ContentResolver> ResolverMeshProvider<R> {
+    pub fn new(resolver: R) -> Self {
+        Self { resolver }
+    }
+}
+
+impl<R: ContentResolver> MeshProvider for ResolverMeshProvider<R> {
+    fn get_mesh(&self, mesh_id: &str) -> Option<Mesh> {
+        let bytes = self.resolver.resolve(mesh_id).ok()?;
+        Mesh::from_roblox_mesh(&bytes)
+    }
+}
+
+/// An error that can occur when exporting a scene.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// One of the instances to export isn't in the DOM.
+    #[error("instance {0} to export is not in the DOM")]
+    MissingInstance(Ref),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Exports the parts of a DOM as a glTF scene, drawing MeshParts with meshes
+/// from a [`MeshProvider`] if it has one.
+#[derive(Default)]
+pub struct Exporter<'a> {
+    mesh_provider: Option<&'a dyn MeshProvider>,
+}
+
+impl<'a> Exporter<'a> {
+    /// Create a new `Exporter` that draws MeshParts as placeholders.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets where the meshes of MeshParts come from.
+    pub fn mesh_provider(mut self, provider: &'a dyn MeshProvider) -> Self {
+        self.mesh_provider = Some(provider);
+        self
+    }
+
+    /// Writes the parts among the given instances and their descendants as a
+    /// binary `.glb` file.
+    pub fn to_glb<W: Write>(&self, writer: W, dom: &WeakDom, refs: &[Ref]) -> Result<(), Error> {
+        self.build(dom, refs)?.write_glb(writer)?;
+        Ok(())
+    }
+
+    /// Writes the parts among the given instances and their descendants as a
+    /// `.gltf` file, with its data embedded in the file.
+    pub fn to_gltf<W: Write>(&self, writer: W, dom: &WeakDom, refs: &[Ref]) -> Result<(), Error> {
+        self.build(dom, refs)?.write_gltf(writer)?;
+        Ok(())
+    }
+
+    fn build(&self, dom: &WeakDom, refs: &[Ref]) -> Result<Document, Error> {
+        let mut builder = SceneBuilder {
+            dom,
+            database: rbx_reflection_database::global(),
+            mesh_provider: self.mesh_provider,
+            document: Document::new(),
+            shapes: HashMap::new(),
+            meshes: HashMap::new(),
+        };
+
+        for &referent in refs {
+            if dom.get_by_ref(referent).is_none() {
+                return Err(Error::MissingInstance(referent));
+            }
+
+            let mut stack = vec![referent];
+            while let Some(referent) = stack.pop() {
+                let instance = dom.get_by_ref(referent).unwrap();
+                builder.add_part(instance);
+                stack.extend(instance.children().iter().rev());
+            }
+        }
+
+        Ok(builder.document)
+    }
+}
+
+/// Exports the parts among the given instances and their descendants as a
+/// binary `.glb` file, drawing MeshParts as placeholders.
+pub fn to_glb<W: Write>(writer: W, dom: &WeakDom, refs: &[Ref]) -> Result<(), Error> {
+    Exporter::new().to_glb(writer, dom, refs)
+}
+
+/// Exports the parts among the given instances and their descendants as a
+/// `.gltf` file, drawing MeshParts as placeholders.
+pub fn to_gltf<W: Write>(writer: W, dom: &WeakDom, refs: &[Ref]) -> Result<(), Error> {
+    Exporter::new().to_gltf(writer, dom, refs)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Shape {
+    Block,
+    Wedge,
+    Cylinder,
+    Ball,
+}
+
+/// The geometry of a mesh from a [`MeshProvider`], with its bounds.
+type LoadedMesh = (Geometry, [f32; 3], [f32; 3]);
+
+struct SceneBuilder<'a> {
+    dom: &'a WeakDom,
+    database: &'a ReflectionDatabase<'a>,
+    mesh_provider: Option<&'a dyn MeshProvider>,
+    document: Document,
+
+    shapes: HashMap<Shape, Geometry>,
+
+    /// The geometry and bounds of each MeshId, or `None` for MeshIds that the
+    /// provider didn't have.
+    meshes: HashMap<String, Option<LoadedMesh>>,
+}
+
+impl SceneBuilder<'_> {
+    fn add_part(&mut self, instance: &Instance) {
+        if !self.database.is_a(&instance.class, "BasePart") {
+            return;
+        }
+
+        let size = match instance.properties.get("Size") {
+            Some(Variant::Vector3(size)) => [size.x, size.y, size.z],
+            _ => [4.0, 1.0, 2.0],
+        };
+        let cframe = match instance.properties.get("CFrame") {
+            Some(Variant::CFrame(cframe)) => *cframe,
+            _ => CFrame::new(Vector3::new(0.0, 0.0, 0.0), Matrix3::identity()),
+        };
+
+        let mut placeholder = false;
+        let (geometry, scale, offset) = match self.shape(instance) {
+            Some(shape) => {
+                let smallest = size[1].min(size[2]);
+                let scale = match shape {
+                    Shape::Block | Shape::Wedge => size,
+                    // Roblox draws cylinders and balls as round as they can
+                    // be in the space they're given.
+                    Shape::Cylinder => [size[0], smallest, smallest],
+                    Shape::Ball => [smallest.min(size[0]); 3],
+                };
+                (self.shape_geometry(shape), scale, [0.0; 3])
+            }
+            None => match self.mesh_geometry(instance) {
+                Some((geometry, min, max)) => {
+                    let scale = [0, 1, 2].map(|axis| match max[axis] - min[axis] {
+                        extent if extent > 0.0 => size[axis] / extent,
+                        _ => 1.0,
+                    });
+                    let offset = [0, 1, 2].map(|axis| -(min[axis] + max[axis]) / 2.0);
+                    (geometry, scale, offset)
+                }
+                None => {
+                    placeholder = true;
+                    (self.shape_geometry(Shape::Block), size, [0.0; 3])
+                }
+            },
+        };
+
+        let mesh = self.document.mesh(geometry, color(instance));
+        let mut extras = json!({
+            "Path": full_name(self.dom, instance.referent()),
+            "ClassName": instance.class,
+        });
+        if placeholder {
+            extras["Placeholder"] = json!(true);
+        }
+
+        self.document.add_node(Node {
+            name: instance.name.clone(),
+            mesh,
+            matrix: matrix(&cframe, scale, offset),
+            extras,
+        });
+    }
+
+    /// Picks the shape Roblox draws a part as, or `None` for parts with
+    /// geometry that isn't a shape, like MeshParts and unions.
+    fn shape(&self, instance: &Instance) -> Option<Shape> {
+        match instance.class.as_str() {
+            "WedgePart" => return Some(Shape::Wedge),
+            "TrussPart" => return Some(Shape::Block),
+            _ => {}
+        }
+
+        if !self.database.is_a(&instance.class, "Part") {
+            return None;
+        }
+
+        // These are the values of the PartType enum. CornerWedges aren't
+        // drawn yet, so they're left as placeholders.
+        match instance.properties.get("Shape") {
+            Some(Variant::Enum(shape)) => match shape.to_u32() {
+                0 => Some(Shape::Ball),
+                1 => Some(Shape::Block),
+                2 => Some(Shape::Cylinder),
+                3 => Some(Shape::Wedge),
+                _ => None,
+            },
+            _ => Some(Shape::Block),
+        }
+    }
+
+    fn shape_geometry(&mut self, shape: Shape) -> Geometry {
+        let document = &mut self.document;
+
+        *self.shapes.entry(shape).or_insert_with(|| {
+            document.add_geometry(&match shape {
+                Shape::Block => geometry::block(),
+                Shape::Wedge => geometry::wedge(),
+                Shape::Cylinder => geometry::cylinder(),
+                Shape::Ball => geometry::sphere(),
+            })
+        })
+    }
+
+    fn mesh_geometry(&mut self, instance: &Instance) -> Option<LoadedMesh> {
+        let provider = self.mesh_provider?;
+        if instance.class != "MeshPart" {
+            return None;
+        }
+
+        let mesh_id: &str = match instance.properties.get("MeshId") {
+            Some(Variant::Content(mesh_id)) => mesh_id.as_ref(),
+            _ => return None,
+        };
+        if mesh_id.is_empty() {
+            return None;
+        }
+
+        let document = &mut self.document;
+        *self.meshes.entry(mesh_id.to_owned()).or_insert_with(|| {
+            let mesh = provider.get_mesh(mesh_id)?;
+            let (min, max) = mesh.bounds()?;
+            Some((document.add_geometry(&mesh), min, max))
+        })
+    }
+}
+
+/// The linear color and opacity of a part.
+fn color(instance: &Instance) -> [f32; 4] {
+    let srgb = match instance.properties.get("Color") {
+        Some(Variant::Color3uint8(color)) => {
+            [color.r, color.g, color.b].map(|value| value as f32 / 255.0)
+        }
+        Some(Variant::Color3(color)) => [color.r, color.g, color.b],
+        _ => [163.0 / 255.0, 162.0 / 255.0, 165.0 / 255.0],
+    };
+    let transparency = match instance.properties.get("Transparency") {
+        Some(Variant::Float32(transparency)) => transparency.clamp(0.0, 1.0),
+        _ => 0.0,
+    };
+
+    let linear = srgb.map(|value| {
+        if value <= 0.04045 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    });
+
+    [linear[0], linear[1], linear[2], 1.0 - transparency]
+}
+
+/// The column-major transform of a node that moves a mesh by `offset`,
+/// scales it by `scale`, then rotates and moves it by `cframe`.
+fn matrix(cframe: &CFrame, scale: [f32; 3], offset: [f32; 3]) -> [f32; 16] {
+    let rows = [
+        cframe.orientation.x,
+        cframe.orientation.y,
+        cframe.orientation.z,
+    ]
+    .map(|row| [row.x, row.y, row.z]);
+    let position = [cframe.position.x, cframe.position.y, cframe.position.z];
+
+    let mut matrix = [0.0; 16];
+    for column in 0..3 {
+        for row in 0..3 {
+            matrix[column * 4 + row] = rows[row][column] * scale[column];
+        }
+    }
+    for row in 0..3 {
+        matrix[12 + row] = position[row]
+            + (0..3)
+                .map(|column| matrix[column * 4 + row] * offset[column])
+                .sum::<f32>();
+    }
+    matrix[15] = 1.0;
+
+    matrix
+}
+
+/// The full name of an instance, like `Workspace.Map.Part`.
+fn full_name(dom: &WeakDom, referent: Ref) -> String {
+    let mut names = Vec::new();
+    let mut current = dom.get_by_ref(referent);
+
+    while let Some(instance) = current {
+        if instance.referent() == dom.root_ref() && !names.is_empty() {
+            break;
+        }
+        names.push(instance.name.as_str());
+        current = dom.get_by_ref(instance.parent());
+    }
+
+    names.reverse();
+    names.join(".")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::convert::TryInto;
+
+    use rbx_dom_weak::{
+        types::{Color3uint8, Content},
+        InstanceBuilder,
+    };
+    use serde_json::Value;
+
+    fn export(dom: &WeakDom, exporter: &Exporter) -> Value {
+        let mut output = Vec::new();
+        exporter
+            .to_gltf(&mut output, dom, dom.root().children())
+            .unwrap();
+        serde_json::from_slice(&output).unwrap()
+    }
+
+    fn place() -> WeakDom {
+        WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Workspace")
+                    .with_child(
+                        InstanceBuilder::new("Part")
+                            .with_name("Floor")
+                            .with_property("Size", Vector3::new(10.0, 1.0, 10.0))
+                            .with_property(
+                                "CFrame",
+                                CFrame::new(Vector3::new(1.0, 2.0, 3.0), Matrix3::identity()),
+                            )
+                            .with_property("Color", Color3uint8::new(255, 0, 0)),
+                    )
+                    .with_child(
+                        InstanceBuilder::new("Model").with_child(
+                            InstanceBuilder::new("MeshPart")
+                                .with_property("MeshId", Content::from("rbxassetid://1"))
+                                .with_property("Size", Vector3::new(2.0, 2.0, 2.0)),
+                        ),
+                    )
+                    .with_child(InstanceBuilder::new("Folder")),
+            ),
+        )
+    }
+
+    #[test]
+    fn parts_become_nodes() {
+        let scene = export(&place(), &Exporter::new());
+        let nodes = scene["nodes"].as_array().unwrap();
+
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0]["name"], "Floor");
+        assert_eq!(nodes[0]["extras"]["Path"], "Workspace.Floor");
+        assert_eq!(
+            nodes[0]["matrix"],
+            json!([
+                10.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 10.0, 0.0, 1.0, 2.0, 3.0, 1.0
+            ])
+        );
+        assert_eq!(nodes[0]["extras"].get("Placeholder"), None);
+        assert_eq!(nodes[1]["extras"]["Path"], "Workspace.Model.MeshPart");
+        assert_eq!(nodes[1]["extras"]["Placeholder"], true);
+
+        let material = &scene["materials"][0]["pbrMetallicRoughness"];
+        assert_eq!(material["baseColorFactor"], json!([1.0, 0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn mesh_provider() {
+        let provider = |mesh_id: &str| {
+            assert_eq!(mesh_id, "rbxassetid://1");
+            Some(Mesh {
+                positions: vec![[0.0, 0.0, 0.0], [4.0, 0.0, 0.0], [0.0, 4.0, 4.0]],
+                normals: Vec::new(),
+                indices: vec![0, 1, 2],
+            })
+        };
+        let scene = export(&place(), &Exporter::new().mesh_provider(&provider));
+        let node = &scene["nodes"][1];
+
+        assert_eq!(node["extras"].get("Placeholder"), None);
+        assert_eq!(
+            node["matrix"],
+            json!([
+                0.5, 0.0, 0.0, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0, 0.0, 0.5, 0.0, -1.0, -1.0, -1.0, 1.0
+            ])
+        );
+        assert_eq!(scene["meshes"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn glb_layout() {
+        let dom = place();
+        let mut output = Vec::new();
+        to_glb(&mut output, &dom, &[dom.root_ref()]).unwrap();
+
+        assert_eq!(&output[0..4], b"glTF");
+        assert_eq!(output.len() % 4, 0);
+        assert_eq!(
+            u32::from_le_bytes(output[8..12].try_into().unwrap()) as usize,
+            output.len()
+        );
+
+        let json_length = u32::from_le_bytes(output[12..16].try_into().unwrap()) as usize;
+        assert_eq!(&output[16..20], b"JSON");
+        assert_eq!(
+            &output[20 + json_length + 4..20 + json_length + 8],
+            b"BIN\0"
+        );
+    }
+
+    #[test]
+    fn missing_instance() {
+        let dom = place();
+        let error = to_glb(&mut Vec::new(), &dom, &[Ref::new()]).unwrap_err();
+        assert!(matches!(error, Error::MissingInstance(_)));
+    }
+}
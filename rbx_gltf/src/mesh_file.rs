@@ -0,0 +1,239 @@
+//! Reading the geometry out of Roblox's mesh files, the assets that the
+//! `MeshId` of a MeshPart points to.
+//!
+//! Versions 1 through 5 are supported. Only the most detailed level of detail
+//! is read, and bones, skinning, and facial animation data are skipped.
+
+use std::convert::TryInto;
+
+use crate::Mesh;
+
+/// Reads a Roblox mesh file, or returns `None` if it's not one that can be
+/// read.
+pub fn parse(bytes: &[u8]) -> Option<Mesh> {
+    let line_end = bytes.iter().position(|&byte| byte == b'\n')?;
+    let version = std::str::from_utf8(&bytes[..line_end]).ok()?.trim();
+    let version = version.strip_prefix("version ")?;
+    let rest = &bytes[line_end + 1..];
+
+    match version {
+        "1.00" => parse_text(rest, 0.5),
+        "1.01" => parse_text(rest, 1.0),
+        "2.00" => parse_binary(rest, Layout::V2),
+        "3.00" | "3.01" => parse_binary(rest, Layout::V3),
+        "4.00" | "4.01" | "5.00" => parse_binary(rest, Layout::V4),
+        _ => None,
+    }
+}
+
+/// Version 1 meshes are text: a count of faces, then every vertex of every
+/// face as `[x,y,z][nx,ny,nz][u,v,w]`. Version 1.00 meshes are twice as large
+/// as they're meant to be drawn.
+fn parse_text(bytes: &[u8], scale: f32) -> Option<Mesh> {
+    let text = std::str::from_utf8(bytes).ok()?;
+    let mut lines = text.lines();
+    let face_count: usize = lines.next()?.trim().parse().ok()?;
+
+    let numbers: Vec<f32> = lines
+        .flat_map(|line| line.split(['[', ']', ',']))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse().ok())
+        .collect::<Option<_>>()?;
+
+    let vertex_count = face_count * 3;
+    if numbers.len() < vertex_count * 9 {
+        return None;
+    }
+
+    let mut mesh = Mesh::default();
+    for vertex in numbers.chunks_exact(9).take(vertex_count) {
+        mesh.positions
+            .push([vertex[0] * scale, vertex[1] * scale, vertex[2] * scale]);
+        mesh.normals.push([vertex[3], vertex[4], vertex[5]]);
+    }
+    mesh.indices = (0..vertex_count as u32).collect();
+
+    Some(mesh)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    /// A header with the sizes of vertices and faces and their counts.
+    V2,
+
+    /// Like version 2, with levels of detail after the faces.
+    V3,
+
+    /// 40 byte vertices followed by skinning data if the mesh has bones, then
+    /// faces and levels of detail.
+    V4,
+}
+
+fn parse_binary(bytes: &[u8], layout: Layout) -> Option<Mesh> {
+    let header_size = read_u16(bytes, 0)? as usize;
+
+    let (vertex_size, face_size, vertex_count, face_count, lod_count, bone_count) = match layout {
+        Layout::V2 => (
+            bytes.get(2).copied()? as usize,
+            bytes.get(3).copied()? as usize,
+            read_u32(bytes, 4)? as usize,
+            read_u32(bytes, 8)? as usize,
+            0,
+            0,
+        ),
+        Layout::V3 => (
+            bytes.get(2).copied()? as usize,
+            bytes.get(3).copied()? as usize,
+            read_u32(bytes, 8)? as usize,
+            read_u32(bytes, 12)? as usize,
+            read_u16(bytes, 6)? as usize,
+            0,
+        ),
+        Layout::V4 => (
+            40,
+            12,
+            read_u32(bytes, 4)? as usize,
+            read_u32(bytes, 8)? as usize,
+            read_u16(bytes, 12)? as usize,
+            read_u16(bytes, 14)? as usize,
+        ),
+    };
+
+    // Every version starts vertices with a position and a normal, and every
+    // face is three vertex indices.
+    if vertex_size < 24 || face_size < 12 {
+        return None;
+    }
+
+    let vertices_start = header_size;
+    let mut faces_start = vertices_start.checked_add(vertex_count.checked_mul(vertex_size)?)?;
+    if bone_count > 0 {
+        faces_start = faces_start.checked_add(vertex_count.checked_mul(8)?)?;
+    }
+    let lods_start = faces_start.checked_add(face_count.checked_mul(face_size)?)?;
+
+    let mut mesh = Mesh::default();
+    for vertex in 0..vertex_count {
+        let start = vertices_start + vertex * vertex_size;
+        let float = |index: usize| read_f32(bytes, start + index * 4);
+
+        mesh.positions.push([float(0)?, float(1)?, float(2)?]);
+        mesh.normals.push([float(3)?, float(4)?, float(5)?]);
+    }
+
+    // The faces of the most detailed level of detail come first, ending
+    // where the second one starts.
+    let mut faces = 0..face_count;
+    if lod_count >= 2 {
+        let end = read_u32(bytes, lods_start + 4)? as usize;
+        faces.end = end.min(face_count);
+    }
+
+    for face in faces {
+        let start = faces_start + face * face_size;
+        for corner in 0..3 {
+            let index = read_u32(bytes, start + corner * 4)?;
+            if index as usize >= vertex_count {
+                return None;
+            }
+            mesh.indices.push(index);
+        }
+    }
+
+    Some(mesh)
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    let bytes = bytes.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn read_f32(bytes: &[u8], offset: usize) -> Option<f32> {
+    read_u32(bytes, offset).map(f32::from_bits)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TRIANGLE: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+
+    /// Writes a version 2 or 3 mesh of one triangle, with a second level of
+    /// detail that's the same triangle again for version 3.
+    fn binary_mesh(version: &str) -> Vec<u8> {
+        let mut bytes = format!("version {}\n", version).into_bytes();
+        let lods = version.starts_with('3');
+        let faces: u32 = if lods { 2 } else { 1 };
+
+        if lods {
+            bytes.extend_from_slice(&16u16.to_le_bytes());
+            bytes.extend_from_slice(&[40, 12]);
+            bytes.extend_from_slice(&4u16.to_le_bytes());
+            bytes.extend_from_slice(&2u16.to_le_bytes());
+        } else {
+            bytes.extend_from_slice(&12u16.to_le_bytes());
+            bytes.extend_from_slice(&[40, 12]);
+        }
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        bytes.extend_from_slice(&faces.to_le_bytes());
+
+        for position in TRIANGLE {
+            for value in position.iter().chain(&[0.0, 0.0, 1.0, 0.5, 0.5]) {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes.extend_from_slice(&[0; 8]);
+        }
+        for _ in 0..faces {
+            for index in 0u32..3 {
+                bytes.extend_from_slice(&index.to_le_bytes());
+            }
+        }
+        if lods {
+            bytes.extend_from_slice(&0u32.to_le_bytes());
+            bytes.extend_from_slice(&1u32.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn version_1() {
+        let text =
+            "version 1.00\n1\n[0,0,0][0,0,1][0,0,0][2,0,0][0,0,1][1,0,0][0,2,0][0,0,1][0,1,0]";
+        let mesh = parse(text.as_bytes()).unwrap();
+
+        assert_eq!(mesh.positions, TRIANGLE);
+        assert_eq!(mesh.normals, [[0.0, 0.0, 1.0]; 3]);
+        assert_eq!(mesh.indices, [0, 1, 2]);
+    }
+
+    #[test]
+    fn version_2() {
+        let mesh = parse(&binary_mesh("2.00")).unwrap();
+
+        assert_eq!(mesh.positions, TRIANGLE);
+        assert_eq!(mesh.normals, [[0.0, 0.0, 1.0]; 3]);
+        assert_eq!(mesh.indices, [0, 1, 2]);
+    }
+
+    #[test]
+    fn version_3_reads_first_lod() {
+        let mesh = parse(&binary_mesh("3.00")).unwrap();
+
+        assert_eq!(mesh.positions, TRIANGLE);
+        assert_eq!(mesh.indices, [0, 1, 2]);
+    }
+
+    #[test]
+    fn unsupported() {
+        assert_eq!(parse(b"version 7.00\nCOREMESH"), None);
+        assert_eq!(parse(b"not a mesh"), None);
+        assert_eq!(parse(b"version 2.00\n\x0c\x00"), None);
+    }
+}
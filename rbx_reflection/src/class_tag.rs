@@ -17,7 +17,8 @@ pub enum ClassTag {
     UserSettings,
 }
 
-#[derive(Debug)]
+#[derive(Debug, thiserror::Error)]
+#[error("unknown class tag '{0}'")]
 pub struct ClassTagFromStrError(String);
 
 impl FromStr for ClassTag {
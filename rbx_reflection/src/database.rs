@@ -5,12 +5,17 @@
 use std::{
     borrow::Cow,
     collections::{HashMap, HashSet},
+    str::FromStr,
 };
 
+use rbx_dom_weak::InstanceBuilder;
 use rbx_types::{Variant, VariantType};
 use serde::{Deserialize, Serialize};
 
-use crate::{ClassTag, PropertyMigration, PropertyTag};
+use crate::{
+    ClassTag, EventDescriptor, FunctionDescriptor, MigrationError, PropertyMigration, PropertyTag,
+    ValidationError,
+};
 
 /// Contains information extracted from Roblox to describe all known Instances
 /// and enums.
@@ -39,6 +44,533 @@ impl<'a> ReflectionDatabase<'a> {
             enums: HashMap::new(),
         }
     }
+
+    /// Returns a copy of this database with only the given classes and enums.
+    /// The superclasses of the classes and the enums that their properties use
+    /// are kept too, so that lookups about the kept classes still work. Names
+    /// that aren't in the database are ignored.
+    ///
+    /// This is useful for embedding a smaller database in builds where size
+    /// matters, like WebAssembly builds for the web.
+    pub fn subset<C, E>(&self, classes: C, enums: E) -> ReflectionDatabase<'a>
+    where
+        C: IntoIterator,
+        C::Item: AsRef<str>,
+        E: IntoIterator,
+        E::Item: AsRef<str>,
+    {
+        let mut subset = ReflectionDatabase {
+            version: self.version,
+            classes: HashMap::new(),
+            enums: HashMap::new(),
+        };
+        let mut enum_names: HashSet<String> = enums
+            .into_iter()
+            .map(|name| name.as_ref().to_owned())
+            .collect();
+
+        for class_name in classes {
+            for class in self.superclass_iter(class_name.as_ref()) {
+                // The rest of the superclasses were kept along with this one.
+                if subset.classes.contains_key(class.name.as_ref()) {
+                    break;
+                }
+
+                for property in class.properties.values() {
+                    if let DataType::Enum(enum_name) = &property.data_type {
+                        enum_names.insert(enum_name.to_string());
+                    }
+                }
+
+                subset.classes.insert(class.name.clone(), class.clone());
+            }
+        }
+
+        for enum_name in enum_names {
+            if let Some((name, descriptor)) = self.enums.get_key_value(enum_name.as_str()) {
+                subset.enums.insert(name.clone(), descriptor.clone());
+            }
+        }
+
+        subset
+    }
+
+    /// Finds the default value of a property for the given class, looking
+    /// through the class's superclasses if it doesn't list one itself.
+    ///
+    /// Defaults are listed under the names they were read with, so
+    /// `property_name` should usually be the property's canonical name.
+    pub fn class_default(&self, class_name: &str, property_name: &str) -> Option<&Variant> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!(
+            "rbx_reflection::class_default",
+            class = class_name,
+            property = property_name,
+        )
+        .entered();
+
+        self.superclass_iter(class_name)
+            .find_map(|class| class.default_properties.get(property_name))
+    }
+
+    /// Returns the default value of every property of the given class that
+    /// serializes, including properties inherited from its superclasses. Like
+    /// properties decoded by rbx_binary and rbx_xml, the defaults are listed
+    /// by canonical name and have the canonical type. Properties that are
+    /// migrated to newer ones are left out, since the newer properties have
+    /// defaults of their own.
+    pub fn class_defaults(&self, class_name: &str) -> Vec<(&str, Variant)> {
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::trace_span!("rbx_reflection::class_defaults", class = class_name).entered();
+
+        let mut defaults: HashMap<&str, Variant> = HashMap::new();
+
+        for class in self.superclass_iter(class_name) {
+            for (name, default) in &class.default_properties {
+                let descriptors = match self.find_property_descriptors(class_name, name) {
+                    Some(descriptors) if descriptors.serialized.is_some() => descriptors,
+                    _ => continue,
+                };
+                let canonical = descriptors.canonical;
+
+                if defaults.contains_key(canonical.name.as_ref()) {
+                    continue;
+                }
+
+                let expected_type = match (&canonical.kind, &canonical.data_type) {
+                    (
+                        PropertyKind::Canonical {
+                            serialization: PropertySerialization::Migrate(_),
+                        },
+                        _,
+                    ) => continue,
+                    (_, DataType::Value(data_type)) => *data_type,
+                    (_, DataType::Enum(_)) => VariantType::Enum,
+                };
+
+                let value = match default {
+                    // Defaults are read from Roblox in the form they're
+                    // serialized in, which is different for Color3s on parts.
+                    Variant::Color3uint8(color) if expected_type == VariantType::Color3 => {
+                        Variant::Color3((*color).into())
+                    }
+                    default if default.ty() == expected_type => default.clone(),
+                    _ => continue,
+                };
+
+                defaults.insert(canonical.name.as_ref(), value);
+            }
+        }
+
+        let mut defaults: Vec<_> = defaults.into_iter().collect();
+        defaults.sort_unstable_by_key(|(name, _)| *name);
+        defaults
+    }
+
+    /// Creates an `InstanceBuilder` for the given class with all of the
+    /// class's default properties from
+    /// [`class_defaults`](Self::class_defaults), so that the instance matches
+    /// what `Instance.new` would create in Roblox Studio.
+    ///
+    /// Classes that aren't in the database get no properties.
+    pub fn instantiate(&self, class_name: &str) -> InstanceBuilder {
+        InstanceBuilder::new(class_name).with_properties(self.class_defaults(class_name))
+    }
+
+    /// Returns an iterator over the descriptors of the given class and each of
+    /// its superclasses, starting with the class itself and ending with the
+    /// root class, usually `Instance`.
+    ///
+    /// The iterator is empty if the class isn't in the database.
+    pub fn superclass_iter(&self, class_name: &str) -> SuperclassIter<'_, 'a> {
+        SuperclassIter {
+            database: self,
+            current: self.classes.get(class_name),
+        }
+    }
+
+    /// Tells whether the given class is `superclass_name` or inherits from it,
+    /// like `Instance:IsA` in Lua.
+    pub fn is_a(&self, class_name: &str, superclass_name: &str) -> bool {
+        self.superclass_iter(class_name)
+            .any(|class| class.name == superclass_name)
+    }
+
+    /// Returns the descriptors of every class that inherits from the given
+    /// class, directly or indirectly, ordered by name.
+    pub fn subclasses_of(&self, class_name: &str) -> Vec<&ClassDescriptor<'a>> {
+        let mut subclasses: Vec<_> = self
+            .classes
+            .values()
+            .filter(|class| class.name != class_name && self.is_a(class.name.as_ref(), class_name))
+            .collect();
+        subclasses.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        subclasses
+    }
+
+    /// Finds the canonical descriptor of the given property, along with the
+    /// descriptor it's serialized as, if it serializes. The property can be
+    /// given by its canonical name, its serialized name, or any of its
+    /// aliases, and is looked up through the class's superclasses.
+    ///
+    /// This is how rbx_binary and rbx_xml decide what properties are called in
+    /// memory and in files, so tools that agree with them should use this too.
+    pub fn find_property_descriptors<'db>(
+        &'db self,
+        class_name: &str,
+        property_name: &str,
+    ) -> Option<PropertyDescriptors<'db>> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!(
+            "rbx_reflection::find_property_descriptors",
+            class = class_name,
+            property = property_name,
+        )
+        .entered();
+
+        let (class, descriptor) = self
+            .superclass_iter(class_name)
+            .find_map(|class| Some((class, class.properties.get(property_name)?)))?;
+
+        // Aliases and the properties they're aliases for are always on the
+        // same class.
+        let canonical = match &descriptor.kind {
+            PropertyKind::Canonical { .. } => descriptor,
+            PropertyKind::Alias { alias_for } => class.properties.get(alias_for.as_ref())?,
+        };
+
+        let serialized = match &canonical.kind {
+            PropertyKind::Canonical { serialization } => match serialization {
+                PropertySerialization::Serializes | PropertySerialization::Migrate(_) => {
+                    Some(canonical)
+                }
+                PropertySerialization::SerializesAs(serialized_name) => {
+                    class.properties.get(serialized_name.as_ref())
+                }
+                PropertySerialization::DoesNotSerialize => None,
+            },
+
+            // If one property in the database calls itself an alias of
+            // another property, that property must be canonical.
+            PropertyKind::Alias { .. } => return None,
+        };
+
+        Some(PropertyDescriptors {
+            canonical,
+            serialized,
+        })
+    }
+
+    /// Describes how the given property is serialized: whether it's written
+    /// to files, which property it's written as, and whether it's only read
+    /// from older files and then migrated to a newer property. Returns `None`
+    /// if the property isn't in the database.
+    ///
+    /// The property can be given by any of its names; see
+    /// [`find_property_descriptors`](Self::find_property_descriptors).
+    pub fn serialization<'db>(
+        &'db self,
+        class_name: &str,
+        property_name: &str,
+    ) -> Option<SerializationBehavior<'db>> {
+        let descriptors = self.find_property_descriptors(class_name, property_name)?;
+
+        Some(
+            match (&descriptors.canonical.kind, descriptors.serialized) {
+                (_, None) => SerializationBehavior::DoesNotSerialize,
+                (
+                    PropertyKind::Canonical {
+                        serialization: PropertySerialization::Migrate(migration),
+                    },
+                    Some(serialized),
+                ) => SerializationBehavior::LoadOnly {
+                    serialized,
+                    migration,
+                },
+                (_, Some(serialized)) => SerializationBehavior::Serializes { serialized },
+            },
+        )
+    }
+
+    /// Finds the migration for the given property, if it's an older property
+    /// that Roblox replaced with a newer one, like `TextLabel.Font` being
+    /// replaced by `TextLabel.FontFace`.
+    pub fn find_migration(
+        &self,
+        class_name: &str,
+        property_name: &str,
+    ) -> Option<&PropertyMigration> {
+        match &self
+            .find_property_descriptors(class_name, property_name)?
+            .canonical
+            .kind
+        {
+            PropertyKind::Canonical {
+                serialization: PropertySerialization::Migrate(migration),
+            } => Some(migration),
+            _ => None,
+        }
+    }
+
+    /// Checks that `value` can be given to the given property: that it has the
+    /// property's type, and that it's one of the items of the property's enum
+    /// if it has one. Values of the type of the property's serialized form are
+    /// accepted too, like Color3uint8 for `BasePart.Color`.
+    ///
+    /// Enums that aren't in the database accept any value. The database
+    /// doesn't record the ranges of numeric properties, so they aren't
+    /// checked.
+    pub fn validate_value(
+        &self,
+        class_name: &str,
+        property_name: &str,
+        value: &Variant,
+    ) -> Result<(), ValidationError> {
+        let descriptors = self
+            .find_property_descriptors(class_name, property_name)
+            .ok_or_else(|| ValidationError::UnknownProperty {
+                class_name: class_name.to_owned(),
+                property_name: property_name.to_owned(),
+            })?;
+
+        let serialized_type =
+            descriptors
+                .serialized
+                .and_then(|serialized| match &serialized.data_type {
+                    DataType::Value(ty) => Some(*ty),
+                    DataType::Enum(_) => None,
+                });
+
+        match &descriptors.canonical.data_type {
+            DataType::Value(expected) => {
+                if value.ty() == *expected || Some(value.ty()) == serialized_type {
+                    Ok(())
+                } else {
+                    Err(ValidationError::WrongType {
+                        class_name: class_name.to_owned(),
+                        property_name: property_name.to_owned(),
+                        expected: *expected,
+                        actual: value.ty(),
+                    })
+                }
+            }
+            DataType::Enum(enum_name) => {
+                let item = match value {
+                    Variant::Enum(item) => item.to_u32(),
+                    _ => {
+                        return Err(ValidationError::WrongType {
+                            class_name: class_name.to_owned(),
+                            property_name: property_name.to_owned(),
+                            expected: VariantType::Enum,
+                            actual: value.ty(),
+                        })
+                    }
+                };
+
+                match self.enums.get(enum_name.as_ref()) {
+                    Some(descriptor) if !descriptor.items.values().any(|&v| v == item) => {
+                        Err(ValidationError::UnknownEnumItem {
+                            class_name: class_name.to_owned(),
+                            property_name: property_name.to_owned(),
+                            enum_name: enum_name.to_string(),
+                            value: item,
+                        })
+                    }
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// Migrates a value of an older property to the property that replaced
+    /// it, returning the name of the new property and its value. Returns
+    /// `Ok(None)` if the property doesn't need to be migrated.
+    ///
+    /// This is what rbx_binary and rbx_xml do while decoding, unless they're
+    /// told not to.
+    pub fn migrate_property(
+        &self,
+        class_name: &str,
+        property_name: &str,
+        value: &Variant,
+    ) -> Result<Option<(&str, Variant)>, MigrationError> {
+        match self.find_migration(class_name, property_name) {
+            Some(migration) => Ok(Some((
+                migration.new_property_name.as_str(),
+                migration.perform(value)?,
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns every class with the given tag, sorted by name.
+    pub fn classes_with_tag(&self, tag: ClassTag) -> Vec<&ClassDescriptor<'a>> {
+        let mut classes: Vec<_> = self
+            .classes
+            .values()
+            .filter(|class| class.has_tag(tag))
+            .collect();
+        classes.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        classes
+    }
+
+    /// Returns every class in the given [category][ClassDescriptor::category],
+    /// in the order Roblox Studio lists them in.
+    pub fn classes_in_category(&self, category: &str) -> Vec<&ClassDescriptor<'a>> {
+        let mut classes: Vec<_> = self
+            .classes
+            .values()
+            .filter(|class| class.category.as_deref() == Some(category))
+            .collect();
+        classes.sort_unstable_by(|a, b| {
+            (a.explorer_order.unwrap_or(u32::MAX), &a.name)
+                .cmp(&(b.explorer_order.unwrap_or(u32::MAX), &b.name))
+        });
+        classes
+    }
+
+    /// Finds the function with the given name on the given class, looking
+    /// through the class's superclasses if it doesn't have one itself.
+    pub fn find_function(
+        &self,
+        class_name: &str,
+        function_name: &str,
+    ) -> Option<&FunctionDescriptor<'a>> {
+        self.superclass_iter(class_name)
+            .find_map(|class| class.functions.get(function_name))
+    }
+
+    /// Finds the event with the given name on the given class, looking through
+    /// the class's superclasses if it doesn't have one itself.
+    pub fn find_event(&self, class_name: &str, event_name: &str) -> Option<&EventDescriptor<'a>> {
+        self.superclass_iter(class_name)
+            .find_map(|class| class.events.get(event_name))
+    }
+
+    /// Finds the callback with the given name on the given class, looking
+    /// through the class's superclasses if it doesn't have one itself.
+    pub fn find_callback(
+        &self,
+        class_name: &str,
+        callback_name: &str,
+    ) -> Option<&FunctionDescriptor<'a>> {
+        self.superclass_iter(class_name)
+            .find_map(|class| class.callbacks.get(callback_name))
+    }
+
+    /// Finds the name of the item of the given enum with the given value.
+    ///
+    /// See [`EnumDescriptor::item_name`].
+    pub fn enum_item_name(&self, enum_name: &str, value: u32) -> Option<&str> {
+        self.enums.get(enum_name)?.item_name(value)
+    }
+
+    /// Finds the value of the item of the given enum with the given name,
+    /// falling back to legacy names and names that only differ in case.
+    ///
+    /// See [`EnumDescriptor::item_value`].
+    pub fn enum_item_value(&self, enum_name: &str, item_name: &str) -> Option<u32> {
+        self.enums.get(enum_name)?.item_value(item_name)
+    }
+
+    /// Finds the current name of the item of the given enum with the given
+    /// name, which might be a legacy name or differ in case.
+    ///
+    /// See [`EnumDescriptor::resolve_item_name`].
+    pub fn resolve_enum_item_name(&self, enum_name: &str, item_name: &str) -> Option<&str> {
+        self.enums.get(enum_name)?.resolve_item_name(item_name)
+    }
+
+    /// Returns every item of the given enum as a name and value, ordered by
+    /// value.
+    pub fn enum_items(&self, enum_name: &str) -> Option<Vec<(&str, u32)>> {
+        Some(self.enums.get(enum_name)?.ordered_items())
+    }
+}
+
+/// An iterator over a class and its superclasses, created by
+/// [`ReflectionDatabase::superclass_iter`].
+#[derive(Debug, Clone)]
+pub struct SuperclassIter<'db, 'a> {
+    database: &'db ReflectionDatabase<'a>,
+    current: Option<&'db ClassDescriptor<'a>>,
+}
+
+impl<'db, 'a> Iterator for SuperclassIter<'db, 'a> {
+    type Item = &'db ClassDescriptor<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let class = self.current?;
+
+        self.current = class
+            .superclass
+            .as_ref()
+            .and_then(|superclass| self.database.classes.get(superclass));
+
+        Some(class)
+    }
+}
+
+/// The descriptors of a property, found with
+/// [`ReflectionDatabase::find_property_descriptors`]. These might be the same
+/// descriptor!
+#[derive(Debug, Clone, Copy)]
+pub struct PropertyDescriptors<'a> {
+    /// The descriptor of the property's canonical form, which is what the
+    /// property is called in memory.
+    pub canonical: &'a PropertyDescriptor<'a>,
+
+    /// The descriptor of the property's serialized form, which is what the
+    /// property is called in files, or `None` if the property doesn't
+    /// serialize.
+    pub serialized: Option<&'a PropertyDescriptor<'a>>,
+}
+
+/// How a property is serialized, found with
+/// [`ReflectionDatabase::serialization`].
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum SerializationBehavior<'a> {
+    /// The property is written to and read from files as `serialized`, which
+    /// might be the property's canonical descriptor.
+    Serializes {
+        serialized: &'a PropertyDescriptor<'a>,
+    },
+
+    /// The property is read from older files as `serialized`, but is migrated
+    /// to a newer property with `migration` when it is, so it's never written.
+    LoadOnly {
+        serialized: &'a PropertyDescriptor<'a>,
+        migration: &'a PropertyMigration,
+    },
+
+    /// The property isn't written to or read from files.
+    DoesNotSerialize,
+}
+
+impl<'a> SerializationBehavior<'a> {
+    /// Tells whether the property is written to files.
+    pub fn serializes(&self) -> bool {
+        matches!(self, Self::Serializes { .. })
+    }
+
+    /// Tells whether the property is read from files but never written to
+    /// them.
+    pub fn is_load_only(&self) -> bool {
+        matches!(self, Self::LoadOnly { .. })
+    }
+
+    /// The name the property has in files, if it's written to or read from
+    /// them.
+    pub fn serialized_name(&self) -> Option<&'a str> {
+        match self {
+            Self::Serializes { serialized } | Self::LoadOnly { serialized, .. } => {
+                Some(serialized.name.as_ref())
+            }
+            Self::DoesNotSerialize => None,
+        }
+    }
 }
 
 /// Describes a class of Instance, its properties, and its relation to other
@@ -67,6 +599,28 @@ pub struct ClassDescriptor<'a> {
     /// defined in serialization or freshly inserted with `Instance.new`.
     #[serde(serialize_with = "crate::serde_util::ordered_map")]
     pub default_properties: HashMap<Cow<'a, str>, Variant>,
+
+    /// A map of all of the functions that can be called on this class.
+    #[serde(default, serialize_with = "crate::serde_util::ordered_map")]
+    pub functions: HashMap<Cow<'a, str>, FunctionDescriptor<'a>>,
+
+    /// A map of all of the events that this class fires.
+    #[serde(default, serialize_with = "crate::serde_util::ordered_map")]
+    pub events: HashMap<Cow<'a, str>, EventDescriptor<'a>>,
+
+    /// A map of all of the callbacks that scripts can assign to this class.
+    #[serde(default, serialize_with = "crate::serde_util::ordered_map")]
+    pub callbacks: HashMap<Cow<'a, str>, FunctionDescriptor<'a>>,
+
+    /// The group that Roblox Studio lists this class under when inserting
+    /// objects, like "Parts" or "Scripting", if it has one.
+    #[serde(default)]
+    pub category: Option<Cow<'a, str>>,
+
+    /// Where Roblox Studio sorts this class in the explorer, lower first. Ties
+    /// are sorted by name.
+    #[serde(default)]
+    pub explorer_order: Option<u32>,
 }
 
 impl<'a> ClassDescriptor<'a> {
@@ -78,8 +632,40 @@ impl<'a> ClassDescriptor<'a> {
             superclass: None,
             properties: HashMap::new(),
             default_properties: HashMap::new(),
+            functions: HashMap::new(),
+            events: HashMap::new(),
+            callbacks: HashMap::new(),
+            category: None,
+            explorer_order: None,
         }
     }
+
+    /// Tells whether this class has the given tag.
+    pub fn has_tag(&self, tag: ClassTag) -> bool {
+        self.tags.contains(&tag)
+    }
+
+    /// Tells whether this class is a service, like Workspace or Lighting.
+    pub fn is_service(&self) -> bool {
+        self.has_tag(ClassTag::Service)
+    }
+
+    /// Tells whether instances of this class can be created with
+    /// `Instance.new`, which isn't the case for abstract classes like BasePart
+    /// or for services.
+    pub fn is_creatable(&self) -> bool {
+        !self.has_tag(ClassTag::NotCreatable) && !self.is_service()
+    }
+
+    /// Tells whether this class is deprecated.
+    pub fn is_deprecated(&self) -> bool {
+        self.has_tag(ClassTag::Deprecated)
+    }
+
+    /// Tells whether Roblox Studio shows this class in its object browser.
+    pub fn is_browsable(&self) -> bool {
+        !self.has_tag(ClassTag::NotBrowsable)
+    }
 }
 
 /// Describes a property on an Instance.
@@ -102,6 +688,12 @@ pub struct PropertyDescriptor<'a> {
 
     /// The kind of property this is, including whether it is canonical.
     pub kind: PropertyKind<'a>,
+
+    /// The security levels needed to read and write this property from Lua,
+    /// if they're known. Databases generated before security levels were
+    /// recorded don't have them.
+    #[serde(default)]
+    pub security: Option<PropertySecurity>,
 }
 
 impl<'a> PropertyDescriptor<'a> {
@@ -115,8 +707,46 @@ impl<'a> PropertyDescriptor<'a> {
             kind: PropertyKind::Canonical {
                 serialization: PropertySerialization::Serializes,
             },
+            security: None,
         }
     }
+
+    /// Tells whether this property has the given tag.
+    pub fn has_tag(&self, tag: PropertyTag) -> bool {
+        self.tags.contains(&tag)
+    }
+
+    /// Tells whether this property is deprecated, which means that Roblox
+    /// has replaced it with something else and may remove it.
+    pub fn is_deprecated(&self) -> bool {
+        self.has_tag(PropertyTag::Deprecated)
+    }
+
+    /// Tells whether this property can't be changed by Lua scripts, even
+    /// though it might still be read by them.
+    pub fn is_read_only(&self) -> bool {
+        self.has_tag(PropertyTag::ReadOnly) || !self.scriptability.can_write()
+    }
+
+    /// Tells whether Lua scripts can read this property.
+    pub fn is_readable(&self) -> bool {
+        self.scriptability.can_read()
+    }
+
+    /// Tells whether Lua scripts can write to this property.
+    pub fn is_writable(&self) -> bool {
+        !self.is_read_only()
+    }
+
+    /// The security level needed to read this property, if it's known.
+    pub fn read_security(&self) -> Option<SecurityLevel> {
+        self.security.map(|security| security.read)
+    }
+
+    /// The security level needed to write to this property, if it's known.
+    pub fn write_security(&self) -> Option<SecurityLevel> {
+        self.security.map(|security| security.write)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -185,6 +815,84 @@ pub enum Scriptability {
     Custom,
 }
 
+impl Scriptability {
+    /// Tells whether Lua scripts can read properties with this
+    /// scriptability, either directly or through other APIs.
+    pub fn can_read(self) -> bool {
+        matches!(self, Self::ReadWrite | Self::Read | Self::Custom)
+    }
+
+    /// Tells whether Lua scripts can write to properties with this
+    /// scriptability, either directly or through other APIs.
+    pub fn can_write(self) -> bool {
+        matches!(self, Self::ReadWrite | Self::Write | Self::Custom)
+    }
+}
+
+/// The security levels needed to read and write a property from Lua.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+#[non_exhaustive]
+pub struct PropertySecurity {
+    /// The security level needed to read the property.
+    pub read: SecurityLevel,
+
+    /// The security level needed to write to the property.
+    pub write: SecurityLevel,
+}
+
+impl PropertySecurity {
+    /// Creates a new `PropertySecurity` with the given levels.
+    pub fn new(read: SecurityLevel, write: SecurityLevel) -> Self {
+        Self { read, write }
+    }
+}
+
+/// A level of security that Lua code needs to use an API, as listed in the
+/// API dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum SecurityLevel {
+    /// Any script can use the API.
+    None,
+
+    /// Plugins and the command bar can use the API.
+    PluginSecurity,
+
+    /// Only the command bar and scripts run by Roblox Studio itself can use
+    /// the API.
+    LocalUserSecurity,
+
+    /// Only scripts made by Roblox can use the API.
+    RobloxScriptSecurity,
+
+    /// Only Roblox's own code can use the API.
+    RobloxSecurity,
+
+    /// No Lua code can use the API.
+    NotAccessibleSecurity,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown security level '{0}'")]
+pub struct SecurityLevelFromStrError(String);
+
+impl FromStr for SecurityLevel {
+    type Err = SecurityLevelFromStrError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value {
+            "None" => Self::None,
+            "PluginSecurity" => Self::PluginSecurity,
+            "LocalUserSecurity" => Self::LocalUserSecurity,
+            "RobloxScriptSecurity" => Self::RobloxScriptSecurity,
+            "RobloxSecurity" => Self::RobloxSecurity,
+            "NotAccessibleSecurity" => Self::NotAccessibleSecurity,
+            _ => return Err(SecurityLevelFromStrError(value.to_owned())),
+        })
+    }
+}
+
 /// Describes a Roblox enum and all of its items.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
@@ -195,6 +903,11 @@ pub struct EnumDescriptor<'a> {
     /// All of the members of this enum, stored as a map from names to values.
     #[serde(serialize_with = "crate::serde_util::ordered_map")]
     pub items: HashMap<Cow<'a, str>, u32>,
+
+    /// Names that items of this enum used to have, mapped to the names they
+    /// have now.
+    #[serde(default, serialize_with = "crate::serde_util::ordered_map")]
+    pub legacy_names: HashMap<Cow<'a, str>, Cow<'a, str>>,
 }
 
 impl<'a> EnumDescriptor<'a> {
@@ -203,6 +916,655 @@ impl<'a> EnumDescriptor<'a> {
         Self {
             name: name.into(),
             items: HashMap::new(),
+            legacy_names: HashMap::new(),
         }
     }
+
+    /// Finds the name of the item with the given value. If more than one item
+    /// has the value, the first name alphabetically is returned.
+    pub fn item_name(&self, value: u32) -> Option<&str> {
+        self.items
+            .iter()
+            .filter(|(_, &item_value)| item_value == value)
+            .map(|(name, _)| name.as_ref())
+            .min()
+    }
+
+    /// Finds the value of the item with the given name, which is resolved
+    /// with [`resolve_item_name`][EnumDescriptor::resolve_item_name].
+    pub fn item_value(&self, name: &str) -> Option<u32> {
+        self.items.get(self.resolve_item_name(name)?).copied()
+    }
+
+    /// Finds the current name of the item with the given name.
+    ///
+    /// If no item has exactly that name, the name is looked up in
+    /// [`legacy_names`][EnumDescriptor::legacy_names], and then compared to
+    /// the current and legacy names without regard to case.
+    pub fn resolve_item_name(&self, name: &str) -> Option<&str> {
+        if let Some((current, _)) = self.items.get_key_value(name) {
+            return Some(current);
+        }
+
+        let current = self.legacy_names.get(name).or_else(|| {
+            self.items
+                .keys()
+                .find(|item_name| item_name.eq_ignore_ascii_case(name))
+                .or_else(|| {
+                    let (_, current) = self
+                        .legacy_names
+                        .iter()
+                        .find(|(legacy, _)| legacy.eq_ignore_ascii_case(name))?;
+                    Some(current)
+                })
+        })?;
+
+        // Legacy names can point at items that have since been removed.
+        let (current, _) = self.items.get_key_value(current.as_ref())?;
+        Some(current)
+    }
+
+    /// Returns the names that the item with the given current name used to
+    /// have, sorted alphabetically.
+    pub fn legacy_item_names(&self, name: &str) -> Vec<&str> {
+        let mut names: Vec<_> = self
+            .legacy_names
+            .iter()
+            .filter(|(_, current)| *current == name)
+            .map(|(legacy, _)| legacy.as_ref())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Returns every item of this enum as a name and value, ordered by value.
+    pub fn ordered_items(&self) -> Vec<(&str, u32)> {
+        let mut items: Vec<_> = self
+            .items
+            .iter()
+            .map(|(name, &value)| (name.as_ref(), value))
+            .collect();
+        items.sort_unstable_by_key(|&(name, value)| (value, name));
+        items
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{MigrationOperation, ParameterDescriptor, TypeDescriptor};
+
+    fn class<'a>(name: &'a str, superclass: Option<&'a str>) -> ClassDescriptor<'a> {
+        let mut class = ClassDescriptor::new(name);
+        class.superclass = superclass.map(Cow::Borrowed);
+        class
+    }
+
+    #[test]
+    fn class_default_through_superclasses() {
+        let mut database = ReflectionDatabase::new();
+
+        let mut instance = class("Instance", None);
+        instance
+            .default_properties
+            .insert("Archivable".into(), Variant::Bool(true));
+        instance
+            .default_properties
+            .insert("Name".into(), Variant::String("Instance".into()));
+
+        let mut folder = class("Folder", Some("Instance"));
+        folder
+            .default_properties
+            .insert("Name".into(), Variant::String("Folder".into()));
+
+        database.classes.insert("Instance".into(), instance);
+        database.classes.insert("Folder".into(), folder);
+
+        assert_eq!(
+            database.class_default("Folder", "Name"),
+            Some(&Variant::String("Folder".into()))
+        );
+        assert_eq!(
+            database.class_default("Folder", "Archivable"),
+            Some(&Variant::Bool(true))
+        );
+        assert_eq!(database.class_default("Folder", "Unknown"), None);
+        assert_eq!(database.class_default("Unknown", "Name"), None);
+    }
+
+    #[test]
+    fn inheritance() {
+        let mut database = ReflectionDatabase::new();
+        for (name, superclass) in [
+            ("Instance", None),
+            ("PVInstance", Some("Instance")),
+            ("BasePart", Some("PVInstance")),
+            ("Part", Some("BasePart")),
+            ("MeshPart", Some("BasePart")),
+            ("Folder", Some("Instance")),
+        ] {
+            database
+                .classes
+                .insert(name.into(), class(name, superclass));
+        }
+
+        let chain: Vec<_> = database
+            .superclass_iter("MeshPart")
+            .map(|class| class.name.as_ref())
+            .collect();
+        assert_eq!(chain, ["MeshPart", "BasePart", "PVInstance", "Instance"]);
+        assert_eq!(database.superclass_iter("Unknown").count(), 0);
+
+        assert!(database.is_a("MeshPart", "BasePart"));
+        assert!(database.is_a("MeshPart", "MeshPart"));
+        assert!(database.is_a("Folder", "Instance"));
+        assert!(!database.is_a("Folder", "BasePart"));
+        assert!(!database.is_a("Unknown", "Instance"));
+
+        let subclasses: Vec<_> = database
+            .subclasses_of("PVInstance")
+            .into_iter()
+            .map(|class| class.name.as_ref())
+            .collect();
+        assert_eq!(subclasses, ["BasePart", "MeshPart", "Part"]);
+        assert!(database.subclasses_of("Folder").is_empty());
+    }
+
+    #[test]
+    fn property_descriptors() {
+        let mut base_part = class("BasePart", Some("Instance"));
+        let mut insert = |name: &'static str, kind| {
+            let mut descriptor =
+                PropertyDescriptor::new(name, DataType::Value(VariantType::Vector3));
+            descriptor.kind = kind;
+            base_part.properties.insert(name.into(), descriptor);
+        };
+
+        insert(
+            "Size",
+            PropertyKind::Canonical {
+                serialization: PropertySerialization::SerializesAs("size".into()),
+            },
+        );
+        insert(
+            "size",
+            PropertyKind::Alias {
+                alias_for: "Size".into(),
+            },
+        );
+        insert(
+            "Velocity",
+            PropertyKind::Canonical {
+                serialization: PropertySerialization::DoesNotSerialize,
+            },
+        );
+        insert(
+            "Broken",
+            PropertyKind::Alias {
+                alias_for: "size".into(),
+            },
+        );
+
+        let mut database = ReflectionDatabase::new();
+        database
+            .classes
+            .insert("Instance".into(), class("Instance", None));
+        database.classes.insert("BasePart".into(), base_part);
+        database
+            .classes
+            .insert("Part".into(), class("Part", Some("BasePart")));
+
+        for name in ["Size", "size"] {
+            let descriptors = database.find_property_descriptors("Part", name).unwrap();
+            assert_eq!(descriptors.canonical.name, "Size");
+            assert_eq!(descriptors.serialized.unwrap().name, "size");
+        }
+
+        let velocity = database
+            .find_property_descriptors("Part", "Velocity")
+            .unwrap();
+        assert_eq!(velocity.canonical.name, "Velocity");
+        assert!(velocity.serialized.is_none());
+
+        let size = database.serialization("Part", "size").unwrap();
+        assert!(size.serializes());
+        assert_eq!(size.serialized_name(), Some("size"));
+
+        let velocity = database.serialization("Part", "Velocity").unwrap();
+        assert!(!velocity.serializes());
+        assert!(!velocity.is_load_only());
+        assert_eq!(velocity.serialized_name(), None);
+
+        assert!(database.serialization("Part", "Color").is_none());
+
+        assert!(database
+            .find_property_descriptors("Part", "Broken")
+            .is_none());
+        assert!(database
+            .find_property_descriptors("Part", "Color")
+            .is_none());
+        assert!(database
+            .find_property_descriptors("Unknown", "Size")
+            .is_none());
+    }
+
+    #[test]
+    fn migrations() {
+        let mut screen_gui = class("ScreenGui", Some("Instance"));
+        let mut ignore_gui_inset =
+            PropertyDescriptor::new("IgnoreGuiInset", DataType::Value(VariantType::Bool));
+        ignore_gui_inset.kind = PropertyKind::Canonical {
+            serialization: PropertySerialization::Migrate(PropertyMigration::new(
+                "ScreenInsets",
+                MigrationOperation::IgnoreGuiInsetToScreenInsets,
+            )),
+        };
+        screen_gui
+            .properties
+            .insert("IgnoreGuiInset".into(), ignore_gui_inset);
+        screen_gui.properties.insert(
+            "ScreenInsets".into(),
+            PropertyDescriptor::new("ScreenInsets", DataType::Enum("ScreenInsets".into())),
+        );
+
+        let mut database = ReflectionDatabase::new();
+        database
+            .classes
+            .insert("Instance".into(), class("Instance", None));
+        database.classes.insert("ScreenGui".into(), screen_gui);
+
+        let (name, value) = database
+            .migrate_property("ScreenGui", "IgnoreGuiInset", &Variant::Bool(true))
+            .unwrap()
+            .unwrap();
+        assert_eq!(name, "ScreenInsets");
+        assert_eq!(value, Variant::Enum(rbx_types::Enum::from_u32(1)));
+
+        let behavior = database
+            .serialization("ScreenGui", "IgnoreGuiInset")
+            .unwrap();
+        assert!(behavior.is_load_only());
+        assert!(!behavior.serializes());
+        assert_eq!(behavior.serialized_name(), Some("IgnoreGuiInset"));
+
+        assert!(database
+            .migrate_property("ScreenGui", "ScreenInsets", &Variant::Bool(true))
+            .unwrap()
+            .is_none());
+        assert!(database
+            .migrate_property("ScreenGui", "IgnoreGuiInset", &Variant::Int32(1))
+            .is_err());
+    }
+
+    #[test]
+    fn instantiate_with_defaults() {
+        let mut instance = class("Instance", None);
+        let mut part = class("Part", Some("Instance"));
+
+        let canonical = |name: &'static str, data_type, serialization| {
+            let mut descriptor = PropertyDescriptor::new(name, DataType::Value(data_type));
+            descriptor.kind = PropertyKind::Canonical { serialization };
+            (Cow::Borrowed(name), descriptor)
+        };
+
+        instance.properties.extend([canonical(
+            "Archivable",
+            VariantType::Bool,
+            PropertySerialization::Serializes,
+        )]);
+        part.properties.extend([
+            canonical(
+                "Color",
+                VariantType::Color3,
+                PropertySerialization::Serializes,
+            ),
+            canonical(
+                "Velocity",
+                VariantType::Vector3,
+                PropertySerialization::DoesNotSerialize,
+            ),
+        ]);
+
+        instance
+            .default_properties
+            .insert("Archivable".into(), Variant::Bool(true));
+        part.default_properties.insert(
+            "Color".into(),
+            Variant::Color3uint8(rbx_types::Color3uint8::new(255, 0, 0)),
+        );
+        part.default_properties.insert(
+            "Velocity".into(),
+            Variant::Vector3(rbx_types::Vector3::new(0.0, 0.0, 0.0)),
+        );
+
+        let mut database = ReflectionDatabase::new();
+        database.classes.insert("Instance".into(), instance);
+        database.classes.insert("Part".into(), part);
+
+        let expected = vec![
+            ("Archivable", Variant::Bool(true)),
+            (
+                "Color",
+                Variant::Color3(rbx_types::Color3::new(1.0, 0.0, 0.0)),
+            ),
+        ];
+        assert_eq!(database.class_defaults("Part"), expected);
+
+        let dom = rbx_dom_weak::WeakDom::new(database.instantiate("Part"));
+        let part = dom.root();
+        assert_eq!(part.class, "Part");
+        assert_eq!(part.name, "Part");
+        assert_eq!(part.properties.len(), 2);
+        assert_eq!(
+            part.properties.get("Archivable"),
+            Some(&Variant::Bool(true))
+        );
+
+        let dom = rbx_dom_weak::WeakDom::new(database.instantiate("Unknown"));
+        assert!(dom.root().properties.is_empty());
+    }
+
+    #[test]
+    fn scriptability_and_tags() {
+        let mut name = PropertyDescriptor::new("Name", DataType::Value(VariantType::String));
+        name.scriptability = Scriptability::ReadWrite;
+        name.security = Some(PropertySecurity::new(
+            SecurityLevel::None,
+            SecurityLevel::None,
+        ));
+        assert!(name.is_readable());
+        assert!(name.is_writable());
+        assert!(!name.is_deprecated());
+        assert_eq!(name.write_security(), Some(SecurityLevel::None));
+
+        let mut class_name =
+            PropertyDescriptor::new("ClassName", DataType::Value(VariantType::String));
+        class_name.scriptability = Scriptability::Read;
+        class_name.tags.insert(PropertyTag::ReadOnly);
+        assert!(class_name.is_readable());
+        assert!(class_name.is_read_only());
+        assert!(class_name.has_tag(PropertyTag::ReadOnly));
+        assert_eq!(class_name.read_security(), None);
+
+        let mut brick_color =
+            PropertyDescriptor::new("brickColor", DataType::Value(VariantType::BrickColor));
+        brick_color.scriptability = Scriptability::ReadWrite;
+        brick_color.tags.insert(PropertyTag::Deprecated);
+        assert!(brick_color.is_deprecated());
+        assert!(brick_color.is_writable());
+
+        let hidden = PropertyDescriptor::new("Hidden", DataType::Value(VariantType::Bool));
+        assert!(!hidden.is_readable());
+        assert!(!hidden.is_writable());
+
+        assert_eq!(
+            "RobloxScriptSecurity".parse::<SecurityLevel>().unwrap(),
+            SecurityLevel::RobloxScriptSecurity
+        );
+        assert!("Unknown".parse::<SecurityLevel>().is_err());
+    }
+
+    #[test]
+    fn validate_value() {
+        let mut part = class("Part", None);
+
+        let mut color = PropertyDescriptor::new("Color", DataType::Value(VariantType::Color3));
+        color.kind = PropertyKind::Canonical {
+            serialization: PropertySerialization::SerializesAs("Color3uint8".into()),
+        };
+        part.properties.insert("Color".into(), color);
+
+        let mut color3uint8 =
+            PropertyDescriptor::new("Color3uint8", DataType::Value(VariantType::Color3uint8));
+        color3uint8.kind = PropertyKind::Alias {
+            alias_for: "Color".into(),
+        };
+        part.properties.insert("Color3uint8".into(), color3uint8);
+
+        part.properties.insert(
+            "Material".into(),
+            PropertyDescriptor::new("Material", DataType::Enum("Material".into())),
+        );
+        part.properties.insert(
+            "Shape".into(),
+            PropertyDescriptor::new("Shape", DataType::Enum("PartType".into())),
+        );
+
+        let mut material = EnumDescriptor::new("Material");
+        material.items.insert("Plastic".into(), 256);
+
+        let mut database = ReflectionDatabase::new();
+        database.classes.insert("Part".into(), part);
+        database.enums.insert("Material".into(), material);
+
+        let color = Variant::Color3(rbx_types::Color3::new(1.0, 0.0, 0.0));
+        let color3uint8 = Variant::Color3uint8(rbx_types::Color3uint8::new(255, 0, 0));
+        assert!(database.validate_value("Part", "Color", &color).is_ok());
+        assert!(database
+            .validate_value("Part", "Color", &color3uint8)
+            .is_ok());
+        assert_eq!(
+            database.validate_value("Part", "Color", &Variant::Bool(true)),
+            Err(ValidationError::WrongType {
+                class_name: "Part".to_owned(),
+                property_name: "Color".to_owned(),
+                expected: VariantType::Color3,
+                actual: VariantType::Bool,
+            })
+        );
+
+        let plastic = Variant::Enum(rbx_types::Enum::from_u32(256));
+        let unknown = Variant::Enum(rbx_types::Enum::from_u32(1));
+        assert!(database
+            .validate_value("Part", "Material", &plastic)
+            .is_ok());
+        assert_eq!(
+            database.validate_value("Part", "Material", &unknown),
+            Err(ValidationError::UnknownEnumItem {
+                class_name: "Part".to_owned(),
+                property_name: "Material".to_owned(),
+                enum_name: "Material".to_owned(),
+                value: 1,
+            })
+        );
+        assert!(matches!(
+            database.validate_value("Part", "Material", &Variant::Float32(256.0)),
+            Err(ValidationError::WrongType {
+                expected: VariantType::Enum,
+                ..
+            })
+        ));
+
+        // PartType isn't in the database, so any item is accepted.
+        assert!(database.validate_value("Part", "Shape", &unknown).is_ok());
+
+        assert!(matches!(
+            database.validate_value("Part", "Velocity", &color),
+            Err(ValidationError::UnknownProperty { .. })
+        ));
+    }
+
+    #[test]
+    fn subset() {
+        let mut base_part = class("BasePart", Some("Instance"));
+        base_part.properties.insert(
+            "Material".into(),
+            PropertyDescriptor::new("Material", DataType::Enum("Material".into())),
+        );
+
+        let mut database = ReflectionDatabase::new();
+        database.version = [0, 600, 0, 0];
+        for class in [
+            class("Instance", None),
+            base_part,
+            class("Part", Some("BasePart")),
+            class("Folder", Some("Instance")),
+        ] {
+            database.classes.insert(class.name.clone(), class);
+        }
+        for name in ["Material", "NormalId", "KeyCode"] {
+            database
+                .enums
+                .insert(name.into(), EnumDescriptor::new(name));
+        }
+
+        let subset = database.subset(["Part", "NotAClass"], ["NormalId"]);
+        assert_eq!(subset.version, database.version);
+
+        let mut classes: Vec<_> = subset.classes.keys().map(|name| name.as_ref()).collect();
+        classes.sort_unstable();
+        assert_eq!(classes, ["BasePart", "Instance", "Part"]);
+
+        let mut enums: Vec<_> = subset.enums.keys().map(|name| name.as_ref()).collect();
+        enums.sort_unstable();
+        assert_eq!(enums, ["Material", "NormalId"]);
+    }
+
+    #[test]
+    fn members() {
+        let object = TypeDescriptor::new("Class", "Instance");
+
+        let mut instance = class("Instance", None);
+        let mut find_first_child = FunctionDescriptor::new("FindFirstChild", object.clone());
+        find_first_child.parameters.push(ParameterDescriptor::new(
+            "name",
+            TypeDescriptor::new("Primitive", "string"),
+        ));
+        instance
+            .functions
+            .insert("FindFirstChild".into(), find_first_child);
+
+        let mut child_added = EventDescriptor::new("ChildAdded");
+        child_added
+            .parameters
+            .push(ParameterDescriptor::new("child", object));
+        instance.events.insert("ChildAdded".into(), child_added);
+
+        let mut bindable = class("BindableFunction", Some("Instance"));
+        bindable.callbacks.insert(
+            "OnInvoke".into(),
+            FunctionDescriptor::new("OnInvoke", TypeDescriptor::new("Group", "Tuple")),
+        );
+
+        let mut database = ReflectionDatabase::new();
+        database.classes.insert("Instance".into(), instance);
+        database.classes.insert("BindableFunction".into(), bindable);
+
+        let function = database
+            .find_function("BindableFunction", "FindFirstChild")
+            .unwrap();
+        assert_eq!(function.parameters[0].name, "name");
+        assert_eq!(function.return_type.name, "Instance");
+
+        assert!(database
+            .find_event("BindableFunction", "ChildAdded")
+            .is_some());
+        assert!(database
+            .find_callback("BindableFunction", "OnInvoke")
+            .is_some());
+        assert!(database.find_callback("Instance", "OnInvoke").is_none());
+        assert!(database.find_function("Instance", "ChildAdded").is_none());
+    }
+
+    #[test]
+    fn class_tags_and_categories() {
+        let mut base_part = class("BasePart", Some("Instance"));
+        base_part.tags.insert(ClassTag::NotCreatable);
+        base_part.tags.insert(ClassTag::NotBrowsable);
+
+        let mut workspace = class("Workspace", Some("Instance"));
+        workspace.tags.insert(ClassTag::Service);
+        workspace.tags.insert(ClassTag::NotCreatable);
+
+        let mut hopper_bin = class("HopperBin", Some("Instance"));
+        hopper_bin.tags.insert(ClassTag::Deprecated);
+        hopper_bin.category = Some("Parts".into());
+
+        let mut part = class("Part", Some("BasePart"));
+        part.category = Some("Parts".into());
+        part.explorer_order = Some(1);
+
+        let mut wedge = class("WedgePart", Some("BasePart"));
+        wedge.category = Some("Parts".into());
+        wedge.explorer_order = Some(1);
+
+        let mut database = ReflectionDatabase::new();
+        for class in [base_part, workspace, hopper_bin, part, wedge] {
+            database.classes.insert(class.name.clone(), class);
+        }
+
+        let creatable: Vec<_> = ["BasePart", "Workspace", "HopperBin", "Part"]
+            .iter()
+            .map(|name| database.classes[*name].is_creatable())
+            .collect();
+        assert_eq!(creatable, [false, false, true, true]);
+
+        assert!(database.classes["Workspace"].is_service());
+        assert!(database.classes["HopperBin"].is_deprecated());
+        assert!(!database.classes["BasePart"].is_browsable());
+
+        let names = |classes: Vec<&ClassDescriptor>| -> Vec<String> {
+            classes.iter().map(|class| class.name.to_string()).collect()
+        };
+        assert_eq!(
+            names(database.classes_with_tag(ClassTag::NotCreatable)),
+            ["BasePart", "Workspace"]
+        );
+        assert_eq!(
+            names(database.classes_in_category("Parts")),
+            ["Part", "WedgePart", "HopperBin"]
+        );
+        assert!(database.classes_in_category("Scripting").is_empty());
+    }
+
+    #[test]
+    fn enum_items() {
+        let mut material = EnumDescriptor::new("Material");
+        material.items.insert("Plastic".into(), 256);
+        material.items.insert("CorrodedMetal".into(), 1040);
+        material.items.insert("Slate".into(), 800);
+        material
+            .legacy_names
+            .insert("Rust".into(), "CorrodedMetal".into());
+
+        let mut database = ReflectionDatabase::new();
+        database.enums.insert("Material".into(), material);
+
+        assert_eq!(database.enum_item_name("Material", 800), Some("Slate"));
+        assert_eq!(database.enum_item_name("Material", 1), None);
+        assert_eq!(database.enum_item_name("Unknown", 800), None);
+
+        assert_eq!(database.enum_item_value("Material", "Slate"), Some(800));
+        assert_eq!(database.enum_item_value("Material", "slate"), Some(800));
+        assert_eq!(database.enum_item_value("Material", "Rust"), Some(1040));
+        assert_eq!(database.enum_item_value("Material", "RUST"), Some(1040));
+        assert_eq!(database.enum_item_value("Material", "Wood"), None);
+
+        assert_eq!(
+            database.resolve_enum_item_name("Material", "rust"),
+            Some("CorrodedMetal")
+        );
+        assert_eq!(
+            database.resolve_enum_item_name("Material", "PLASTIC"),
+            Some("Plastic")
+        );
+        assert_eq!(database.resolve_enum_item_name("Material", "Wood"), None);
+        assert_eq!(
+            database.enums["Material"].legacy_item_names("CorrodedMetal"),
+            vec!["Rust"]
+        );
+        assert!(database.enums["Material"]
+            .legacy_item_names("Slate")
+            .is_empty());
+
+        assert_eq!(
+            database.enum_items("Material"),
+            Some(vec![
+                ("Plastic", 256),
+                ("Slate", 800),
+                ("CorrodedMetal", 1040)
+            ])
+        );
+    }
 }
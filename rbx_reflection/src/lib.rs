@@ -1,10 +1,14 @@
 mod class_tag;
 mod database;
+mod member;
 mod migration;
 mod property_tag;
 mod serde_util;
+mod validation;
 
 pub use class_tag::*;
 pub use database::*;
+pub use member::*;
 pub use migration::*;
 pub use property_tag::*;
+pub use validation::*;
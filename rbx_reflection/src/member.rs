@@ -0,0 +1,171 @@
+use std::{borrow::Cow, collections::HashSet, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::SecurityLevel;
+
+/// Describes a function that can be called on instances of a class, or a
+/// callback that scripts can assign to them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+#[non_exhaustive]
+pub struct FunctionDescriptor<'a> {
+    /// The name of the function, like "FindFirstChild".
+    pub name: Cow<'a, str>,
+
+    /// The parameters of the function, in order.
+    pub parameters: Vec<ParameterDescriptor<'a>>,
+
+    /// The type the function returns.
+    pub return_type: TypeDescriptor<'a>,
+
+    /// The security level needed to call the function, if it's known.
+    #[serde(default)]
+    pub security: Option<SecurityLevel>,
+
+    /// The tags attached to the function.
+    #[serde(default, serialize_with = "crate::serde_util::ordered_set")]
+    pub tags: HashSet<MemberTag>,
+}
+
+impl<'a> FunctionDescriptor<'a> {
+    /// Creates a new `FunctionDescriptor` with the given name and return type,
+    /// and no parameters.
+    pub fn new<S: Into<Cow<'a, str>>>(name: S, return_type: TypeDescriptor<'a>) -> Self {
+        Self {
+            name: name.into(),
+            parameters: Vec::new(),
+            return_type,
+            security: None,
+            tags: HashSet::new(),
+        }
+    }
+}
+
+/// Describes an event that instances of a class fire.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+#[non_exhaustive]
+pub struct EventDescriptor<'a> {
+    /// The name of the event, like "ChildAdded".
+    pub name: Cow<'a, str>,
+
+    /// The parameters that the event passes to its listeners, in order.
+    pub parameters: Vec<ParameterDescriptor<'a>>,
+
+    /// The security level needed to connect to the event, if it's known.
+    #[serde(default)]
+    pub security: Option<SecurityLevel>,
+
+    /// The tags attached to the event.
+    #[serde(default, serialize_with = "crate::serde_util::ordered_set")]
+    pub tags: HashSet<MemberTag>,
+}
+
+impl<'a> EventDescriptor<'a> {
+    /// Creates a new `EventDescriptor` with the given name and no parameters.
+    pub fn new<S: Into<Cow<'a, str>>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            parameters: Vec::new(),
+            security: None,
+            tags: HashSet::new(),
+        }
+    }
+}
+
+/// Describes a parameter of a function, event, or callback.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+#[non_exhaustive]
+pub struct ParameterDescriptor<'a> {
+    /// The name of the parameter.
+    pub name: Cow<'a, str>,
+
+    /// The type of the parameter.
+    #[serde(rename = "Type")]
+    pub parameter_type: TypeDescriptor<'a>,
+
+    /// The value the parameter has when it isn't given, written as Lua, if
+    /// it's optional.
+    #[serde(default)]
+    pub default: Option<Cow<'a, str>>,
+}
+
+impl<'a> ParameterDescriptor<'a> {
+    /// Creates a new `ParameterDescriptor` for a required parameter.
+    pub fn new<S: Into<Cow<'a, str>>>(name: S, parameter_type: TypeDescriptor<'a>) -> Self {
+        Self {
+            name: name.into(),
+            parameter_type,
+            default: None,
+        }
+    }
+}
+
+/// The type of a parameter or return value, exactly as the API dump gives it.
+///
+/// Unlike [`DataType`][crate::DataType], this can describe types that are only
+/// used from Lua, like tuples or functions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TypeDescriptor<'a> {
+    /// The kind of type this is, like "Primitive", "DataType", "Enum",
+    /// "Class", or "Group".
+    pub category: Cow<'a, str>,
+
+    /// The name of the type, like "string", "Vector3", "Material", "Part", or
+    /// "Tuple".
+    pub name: Cow<'a, str>,
+}
+
+impl<'a> TypeDescriptor<'a> {
+    /// Creates a new `TypeDescriptor` with the given category and name.
+    pub fn new<C, N>(category: C, name: N) -> Self
+    where
+        C: Into<Cow<'a, str>>,
+        N: Into<Cow<'a, str>>,
+    {
+        Self {
+            category: category.into(),
+            name: name.into(),
+        }
+    }
+}
+
+// Tags found via:
+// jq '[.Classes | .[] | .Members | .[] | select(.MemberType != "Property") | .Tags // empty] | add | unique' api-dump.json
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum MemberTag {
+    CanYield,
+    CustomLuaState,
+    Deprecated,
+    Hidden,
+    NoYield,
+    NotBrowsable,
+    NotReplicated,
+    Yields,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown member tag '{0}'")]
+pub struct MemberTagFromStrError(String);
+
+impl FromStr for MemberTag {
+    type Err = MemberTagFromStrError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value {
+            "CanYield" => Self::CanYield,
+            "CustomLuaState" => Self::CustomLuaState,
+            "Deprecated" => Self::Deprecated,
+            "Hidden" => Self::Hidden,
+            "NoYield" => Self::NoYield,
+            "NotBrowsable" => Self::NotBrowsable,
+            "NotReplicated" => Self::NotReplicated,
+            "Yields" => Self::Yields,
+            _ => return Err(MemberTagFromStrError(value.to_owned())),
+        })
+    }
+}
@@ -1,4 +1,4 @@
-use rbx_types::{Enum, Font, FontStyle, FontWeight, Variant};
+use rbx_types::{Color3, Enum, Font, FontStyle, FontWeight, Variant};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -33,9 +33,25 @@ pub struct PropertyMigration {
 pub enum MigrationOperation {
     IgnoreGuiInsetToScreenInsets,
     FontToFontFace,
+    BrickColorToColor,
 }
 
 impl PropertyMigration {
+    /// Creates a migration to the property with the given name.
+    pub fn new<S: Into<String>>(new_property_name: S, migration: MigrationOperation) -> Self {
+        Self {
+            new_property_name: new_property_name.into(),
+            migration,
+        }
+    }
+
+    /// The operation that converts values of the old property to values of
+    /// the new one.
+    pub fn operation(&self) -> MigrationOperation {
+        self.migration
+    }
+
+    /// Converts a value of the old property to a value of the new one.
     pub fn perform(&self, input: &Variant) -> Result<Variant, MigrationError> {
         match self.migration {
             MigrationOperation::IgnoreGuiInsetToScreenInsets => {
@@ -152,6 +168,35 @@ impl PropertyMigration {
                     })
                 }
             }
+            MigrationOperation::BrickColorToColor => {
+                if let Variant::BrickColor(value) = input {
+                    Ok(Color3::from(value.to_color3uint8()).into())
+                } else {
+                    Err(MigrationError::InvalidTypeForMigration {
+                        migration: MigrationOperation::BrickColorToColor,
+                        expected: "BrickColor",
+                        actual: input.clone(),
+                    })
+                }
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_types::BrickColor;
+
+    #[test]
+    fn brick_color_to_color() {
+        let migration = PropertyMigration::new("Color3", MigrationOperation::BrickColorToColor);
+
+        assert_eq!(
+            migration.perform(&BrickColor::BrightRed.into()).unwrap(),
+            Variant::Color3(Color3::new(196.0 / 255.0, 40.0 / 255.0, 28.0 / 255.0)),
+        );
+        assert!(migration.perform(&Variant::Bool(true)).is_err());
+    }
+}
@@ -16,7 +16,8 @@ pub enum PropertyTag {
     WriteOnly,
 }
 
-#[derive(Debug)]
+#[derive(Debug, thiserror::Error)]
+#[error("unknown property tag '{0}'")]
 pub struct PropertyTagFromStrError(String);
 
 impl FromStr for PropertyTag {
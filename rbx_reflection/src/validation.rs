@@ -0,0 +1,30 @@
+use rbx_types::VariantType;
+use thiserror::Error;
+
+/// Describes why a value isn't valid for a property, found with
+/// [`ReflectionDatabase::validate_value`][crate::ReflectionDatabase::validate_value].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum ValidationError {
+    #[error("{class_name}.{property_name} is not in the reflection database")]
+    UnknownProperty {
+        class_name: String,
+        property_name: String,
+    },
+    #[error(
+        "Invalid type for {class_name}.{property_name}: expected {expected:?}, got {actual:?}"
+    )]
+    WrongType {
+        class_name: String,
+        property_name: String,
+        expected: VariantType,
+        actual: VariantType,
+    },
+    #[error("Invalid value for {class_name}.{property_name}: {value} is not an item of enum {enum_name}")]
+    UnknownEnumItem {
+        class_name: String,
+        property_name: String,
+        enum_name: String,
+        value: u32,
+    },
+}
@@ -0,0 +1,590 @@
+use std::{
+    borrow::Cow,
+    collections::{HashSet, VecDeque},
+};
+
+use rbx_dom_weak::{types::VariantType, WeakDom};
+use rbx_reflection::{
+    ClassDescriptor, DataType, EnumDescriptor, EventDescriptor, FunctionDescriptor,
+    ParameterDescriptor, PropertyDescriptor, PropertyKind, PropertySecurity, PropertySerialization,
+    PropertyTag, ReflectionDatabase, Scriptability, SecurityLevel, TypeDescriptor,
+};
+use serde::Deserialize;
+
+use crate::error::{Error, InnerError};
+
+/// Builds a reflection database from the JSON API dump that Roblox Studio
+/// writes with `-API`, so that new releases of Roblox can be used without
+/// waiting for a new release of this crate.
+///
+/// API dumps don't describe how properties are serialized beyond whether they
+/// save, so for classes that are also in the bundled database, the kinds of
+/// known properties, the extra properties added by rbx-dom like
+/// `BasePart.size`, and the default values are carried over from it. Use
+/// [`apply_defaults`] to replace the defaults with ones read from Roblox.
+///
+/// The API dump doesn't contain the version of Roblox it came from, so the
+/// `version` of the database is left as 0.0.0.0. Properties with types that
+/// rbx_types doesn't support and tags that rbx_reflection doesn't know about
+/// are skipped.
+pub fn from_api_dump(json: &[u8]) -> Result<ReflectionDatabase<'static>, Error> {
+    let dump: Dump =
+        serde_json::from_slice(json).map_err(|source| InnerError::ApiDump { source })?;
+
+    let mut database = ReflectionDatabase::new();
+    let bundled = crate::get();
+
+    for dump_class in dump.classes {
+        let mut class = ClassDescriptor::new(dump_class.name);
+
+        if dump_class.superclass != "<<<ROOT>>>" {
+            class.superclass = Some(Cow::Owned(dump_class.superclass));
+        }
+        class.tags = parse_tags(&dump_class.tags);
+
+        for member in dump_class.members {
+            match member {
+                DumpClassMember::Property(dump_property) => {
+                    if let Some(property) = convert_property(dump_property) {
+                        class.properties.insert(property.name.clone(), property);
+                    }
+                }
+                DumpClassMember::Function(dump_function) => {
+                    let function = convert_function(dump_function);
+                    class.functions.insert(function.name.clone(), function);
+                }
+                DumpClassMember::Event(dump_event) => {
+                    let event = convert_event(dump_event);
+                    class.events.insert(event.name.clone(), event);
+                }
+                DumpClassMember::Callback(dump_callback) => {
+                    let callback = convert_function(dump_callback);
+                    class.callbacks.insert(callback.name.clone(), callback);
+                }
+                DumpClassMember::Other => {}
+            }
+        }
+
+        if let Some(bundled_class) = bundled.classes.get(class.name.as_ref()) {
+            for (name, bundled_property) in &bundled_class.properties {
+                match class.properties.get_mut(name) {
+                    Some(property) => property.kind = bundled_property.kind.clone(),
+                    None => {
+                        class
+                            .properties
+                            .insert(name.clone(), bundled_property.clone());
+                    }
+                }
+            }
+
+            class.default_properties = bundled_class.default_properties.clone();
+        }
+
+        database.classes.insert(class.name.clone(), class);
+    }
+
+    for dump_enum in dump.enums {
+        let mut descriptor = EnumDescriptor::new(dump_enum.name);
+
+        for item in dump_enum.items {
+            for legacy_name in item.legacy_names {
+                descriptor
+                    .legacy_names
+                    .insert(Cow::Owned(legacy_name), Cow::Owned(item.name.clone()));
+            }
+
+            descriptor.items.insert(Cow::Owned(item.name), item.value);
+        }
+
+        database.enums.insert(descriptor.name.clone(), descriptor);
+    }
+
+    Ok(database)
+}
+
+/// Sets the default properties of classes in `database` from the instances in
+/// `defaults`, like a place that had one of every class inserted into it with
+/// `Instance.new` and was saved from Roblox Studio.
+///
+/// Only the first instance of each class is used. References and
+/// SharedStrings are skipped because their defaults aren't meaningful.
+pub fn apply_defaults(database: &mut ReflectionDatabase<'_>, defaults: &WeakDom) {
+    let mut found_classes = HashSet::new();
+    let mut to_visit: VecDeque<_> = defaults.root().children().iter().copied().collect();
+
+    while let Some(referent) = to_visit.pop_front() {
+        let instance = match defaults.get_by_ref(referent) {
+            Some(instance) => instance,
+            None => continue,
+        };
+
+        to_visit.extend(instance.children());
+
+        if !found_classes.insert(instance.class.as_str()) {
+            continue;
+        }
+
+        let class = match database.classes.get_mut(instance.class.as_str()) {
+            Some(class) => class,
+            None => continue,
+        };
+
+        for (name, value) in &instance.properties {
+            match value.ty() {
+                VariantType::Ref | VariantType::SharedString => {}
+                _ => {
+                    class
+                        .default_properties
+                        .insert(Cow::Owned(name.clone()), value.clone());
+                }
+            }
+        }
+    }
+}
+
+fn convert_property(dump_property: DumpClassProperty) -> Option<PropertyDescriptor<'static>> {
+    let tags: HashSet<PropertyTag> = parse_tags(&dump_property.tags);
+
+    let data_type = match dump_property.value_type.category {
+        ValueCategory::Enum => DataType::Enum(Cow::Owned(dump_property.value_type.name)),
+        ValueCategory::Class => DataType::Value(VariantType::Ref),
+        ValueCategory::Primitive | ValueCategory::DataType => {
+            DataType::Value(variant_type_from_str(&dump_property.value_type.name)?)
+        }
+    };
+
+    let read_security = dump_property.security.read.parse().ok();
+    let write_security = dump_property.security.write.parse().ok();
+
+    let scriptable = |security| {
+        matches!(
+            security,
+            Some(SecurityLevel::None | SecurityLevel::PluginSecurity)
+        )
+    };
+    let read = scriptable(read_security);
+    let write = scriptable(write_security) && !tags.contains(&PropertyTag::ReadOnly);
+
+    let scriptability = if tags.contains(&PropertyTag::NotScriptable) {
+        Scriptability::None
+    } else {
+        match (read, write) {
+            (true, true) => Scriptability::ReadWrite,
+            (true, false) => Scriptability::Read,
+            (false, true) => Scriptability::Write,
+            (false, false) => Scriptability::None,
+        }
+    };
+
+    let serialization =
+        if dump_property.serialization.can_save && !tags.contains(&PropertyTag::ReadOnly) {
+            PropertySerialization::Serializes
+        } else {
+            PropertySerialization::DoesNotSerialize
+        };
+
+    let mut property = PropertyDescriptor::new(dump_property.name, data_type);
+    property.scriptability = scriptability;
+    property.tags = tags;
+    property.kind = PropertyKind::Canonical { serialization };
+    property.security = read_security
+        .zip(write_security)
+        .map(|(read, write)| PropertySecurity::new(read, write));
+
+    Some(property)
+}
+
+fn convert_function(dump_function: DumpClassFunction) -> FunctionDescriptor<'static> {
+    let mut function =
+        FunctionDescriptor::new(dump_function.name, convert_type(dump_function.return_type));
+    function.parameters = convert_parameters(dump_function.parameters);
+    function.security = dump_function.security.parse().ok();
+    function.tags = parse_tags(&dump_function.tags);
+    function
+}
+
+fn convert_event(dump_event: DumpClassEvent) -> EventDescriptor<'static> {
+    let mut event = EventDescriptor::new(dump_event.name);
+    event.parameters = convert_parameters(dump_event.parameters);
+    event.security = dump_event.security.parse().ok();
+    event.tags = parse_tags(&dump_event.tags);
+    event
+}
+
+fn convert_parameters(dump_parameters: Vec<DumpParameter>) -> Vec<ParameterDescriptor<'static>> {
+    dump_parameters
+        .into_iter()
+        .map(|dump_parameter| {
+            let mut parameter =
+                ParameterDescriptor::new(dump_parameter.name, convert_type(dump_parameter.ty));
+            parameter.default = dump_parameter.default.map(Cow::Owned);
+            parameter
+        })
+        .collect()
+}
+
+fn convert_type(dump_type: DumpType) -> TypeDescriptor<'static> {
+    TypeDescriptor::new(dump_type.category, dump_type.name)
+}
+
+fn parse_tags<T: std::str::FromStr + Eq + std::hash::Hash>(tags: &[Tag]) -> HashSet<T> {
+    tags.iter()
+        .filter_map(|tag| match tag {
+            Tag::Regular(tag) => tag.parse().ok(),
+            Tag::Named {} => None,
+        })
+        .collect()
+}
+
+fn variant_type_from_str(value: &str) -> Option<VariantType> {
+    Some(match value {
+        "Axes" => VariantType::Axes,
+        "BinaryString" => VariantType::BinaryString,
+        "BrickColor" => VariantType::BrickColor,
+        "CFrame" => VariantType::CFrame,
+        "Color3" => VariantType::Color3,
+        "Color3uint8" => VariantType::Color3uint8,
+        "ColorSequence" => VariantType::ColorSequence,
+        "Content" => VariantType::Content,
+        "Faces" => VariantType::Faces,
+        "Font" => VariantType::Font,
+        "Instance" => VariantType::Ref,
+        "NumberRange" => VariantType::NumberRange,
+        "NumberSequence" => VariantType::NumberSequence,
+        "OptionalCoordinateFrame" => VariantType::OptionalCFrame,
+        "PhysicalProperties" => VariantType::PhysicalProperties,
+        "Ray" => VariantType::Ray,
+        "Rect" => VariantType::Rect,
+        "Region3" => VariantType::Region3,
+        "Region3int16" => VariantType::Region3int16,
+        "SecurityCapabilities" => VariantType::SecurityCapabilities,
+        "SharedString" => VariantType::SharedString,
+        "UDim" => VariantType::UDim,
+        "UDim2" => VariantType::UDim2,
+        "Vector2" => VariantType::Vector2,
+        "Vector2int16" => VariantType::Vector2int16,
+        "Vector3" => VariantType::Vector3,
+        "Vector3int16" => VariantType::Vector3int16,
+        "bool" => VariantType::Bool,
+        "double" => VariantType::Float64,
+        "float" => VariantType::Float32,
+        "int" => VariantType::Int32,
+        "int64" => VariantType::Int64,
+        "string" => VariantType::String,
+
+        // ProtectedString is handled as the same as string
+        "ProtectedString" => VariantType::String,
+
+        _ => return None,
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Dump {
+    classes: Vec<DumpClass>,
+    enums: Vec<DumpEnum>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DumpClass {
+    name: String,
+    superclass: String,
+
+    #[serde(default)]
+    tags: Vec<Tag>,
+    members: Vec<DumpClassMember>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "MemberType")]
+enum DumpClassMember {
+    Property(DumpClassProperty),
+    Function(DumpClassFunction),
+    Event(DumpClassEvent),
+    Callback(DumpClassFunction),
+
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DumpClassFunction {
+    name: String,
+    #[serde(default)]
+    parameters: Vec<DumpParameter>,
+    return_type: DumpType,
+    #[serde(default)]
+    security: String,
+
+    #[serde(default)]
+    tags: Vec<Tag>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DumpClassEvent {
+    name: String,
+    #[serde(default)]
+    parameters: Vec<DumpParameter>,
+    #[serde(default)]
+    security: String,
+
+    #[serde(default)]
+    tags: Vec<Tag>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DumpParameter {
+    name: String,
+    #[serde(rename = "Type")]
+    ty: DumpType,
+    default: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DumpType {
+    name: String,
+    category: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DumpClassProperty {
+    name: String,
+    value_type: ValueType,
+    serialization: Serialization,
+    security: DumpPropertySecurity,
+
+    #[serde(default)]
+    tags: Vec<Tag>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ValueType {
+    name: String,
+    category: ValueCategory,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+enum ValueCategory {
+    Primitive,
+    DataType,
+    Enum,
+    Class,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DumpPropertySecurity {
+    read: String,
+    write: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Serialization {
+    can_save: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DumpEnum {
+    name: String,
+    items: Vec<DumpEnumItem>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DumpEnumItem {
+    name: String,
+    value: u32,
+    #[serde(default)]
+    legacy_names: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Tag {
+    Regular(String),
+    Named {},
+}
+
+#[cfg(test)]
+mod test {
+    use rbx_dom_weak::{types::Vector3, InstanceBuilder};
+
+    use super::*;
+
+    static DUMP: &str = r#"{
+        "Classes": [
+            {
+                "Name": "Instance",
+                "Superclass": "<<<ROOT>>>",
+                "Tags": ["NotCreatable", "SomeFutureTag"],
+                "Members": [
+                    {
+                        "MemberType": "Property",
+                        "Name": "Name",
+                        "ValueType": { "Category": "Primitive", "Name": "string" },
+                        "Serialization": { "CanLoad": true, "CanSave": true },
+                        "Security": { "Read": "None", "Write": "None" }
+                    },
+                    {
+                        "MemberType": "Function",
+                        "Name": "FindFirstChild",
+                        "Parameters": [
+                            {
+                                "Name": "name",
+                                "Type": { "Category": "Primitive", "Name": "string" }
+                            },
+                            {
+                                "Name": "recursive",
+                                "Type": { "Category": "Primitive", "Name": "bool" },
+                                "Default": "false"
+                            }
+                        ],
+                        "ReturnType": { "Category": "Class", "Name": "Instance" },
+                        "Security": "None",
+                        "Tags": ["SomeFutureTag"]
+                    },
+                    {
+                        "MemberType": "Event",
+                        "Name": "ChildAdded",
+                        "Parameters": [
+                            {
+                                "Name": "child",
+                                "Type": { "Category": "Class", "Name": "Instance" }
+                            }
+                        ],
+                        "Security": "None",
+                        "Tags": []
+                    }
+                ]
+            },
+            {
+                "Name": "BasePart",
+                "Superclass": "Instance",
+                "Members": [
+                    {
+                        "MemberType": "Property",
+                        "Name": "Size",
+                        "ValueType": { "Category": "DataType", "Name": "Vector3" },
+                        "Serialization": { "CanLoad": true, "CanSave": true },
+                        "Security": { "Read": "None", "Write": "None" },
+                        "Tags": [{ "PreferredDescriptorName": "Size" }]
+                    },
+                    {
+                        "MemberType": "Property",
+                        "Name": "FutureValue",
+                        "ValueType": { "Category": "DataType", "Name": "FutureType" },
+                        "Serialization": { "CanLoad": true, "CanSave": true },
+                        "Security": { "Read": "RobloxScriptSecurity", "Write": "None" }
+                    }
+                ]
+            }
+        ],
+        "Enums": [
+            {
+                "Name": "NormalId",
+                "Items": [
+                    { "Name": "Right", "Value": 0 },
+                    { "Name": "Top", "Value": 1 }
+                ]
+            },
+            {
+                "Name": "Material",
+                "Items": [
+                    { "Name": "CorrodedMetal", "Value": 1040, "LegacyNames": ["Rust"] }
+                ]
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn convert_dump() {
+        let database = from_api_dump(DUMP.as_bytes()).unwrap();
+
+        let instance = &database.classes["Instance"];
+        assert_eq!(instance.superclass, None);
+        assert_eq!(instance.tags.len(), 1);
+        assert!(matches!(
+            instance.properties["Name"].scriptability,
+            Scriptability::ReadWrite
+        ));
+
+        let part = &database.classes["BasePart"];
+        assert_eq!(part.superclass.as_deref(), Some("Instance"));
+        assert!(!part.properties.contains_key("FutureValue"));
+        assert_eq!(
+            instance.properties["Name"].security,
+            Some(PropertySecurity::new(
+                SecurityLevel::None,
+                SecurityLevel::None
+            ))
+        );
+        assert!(matches!(
+            part.properties["Size"].data_type,
+            DataType::Value(VariantType::Vector3)
+        ));
+
+        // How properties serialize and properties only known to the bundled
+        // database carry over for classes that are in both.
+        assert!(matches!(
+            &part.properties["Size"].kind,
+            PropertyKind::Canonical {
+                serialization: PropertySerialization::SerializesAs(name),
+            } if name == "size"
+        ));
+        assert!(matches!(
+            &part.properties["size"].kind,
+            PropertyKind::Alias { alias_for } if alias_for == "Size"
+        ));
+
+        let find_first_child = database
+            .find_function("BasePart", "FindFirstChild")
+            .unwrap();
+        assert_eq!(find_first_child.parameters.len(), 2);
+        assert_eq!(
+            find_first_child.parameters[1].default.as_deref(),
+            Some("false")
+        );
+        assert_eq!(find_first_child.return_type.category, "Class");
+        assert_eq!(find_first_child.security, Some(SecurityLevel::None));
+        assert!(find_first_child.tags.is_empty());
+
+        let child_added = database.find_event("BasePart", "ChildAdded").unwrap();
+        assert_eq!(child_added.parameters[0].parameter_type.name, "Instance");
+
+        assert_eq!(database.enums["NormalId"].items["Top"], 1);
+        assert_eq!(database.enum_item_value("Material", "Rust"), Some(1040));
+    }
+
+    #[test]
+    fn invalid_dump() {
+        assert!(from_api_dump(b"{ \"Classes\": 5 }").is_err());
+    }
+
+    #[test]
+    fn defaults_from_dom() {
+        let mut database = from_api_dump(DUMP.as_bytes()).unwrap();
+
+        let size = Vector3::new(4.0, 1.0, 2.0);
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(InstanceBuilder::new("BasePart").with_property("Size", size)),
+        );
+
+        apply_defaults(&mut database, &dom);
+
+        assert_eq!(
+            database.classes["BasePart"].default_properties["Size"],
+            size.into()
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use std::{io, path::PathBuf};
+
+use thiserror::Error;
+
+/// Represents an error that occurred while loading, encoding, or patching a
+/// reflection database.
+#[derive(Debug, Error)]
+#[error(transparent)]
+pub struct Error {
+    source: Box<InnerError>,
+}
+
+impl From<InnerError> for Error {
+    fn from(inner: InnerError) -> Self {
+        Self {
+            source: Box::new(inner),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum InnerError {
+    #[error("Could not read reflection database from {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+
+    #[error("Could not write reflection database to {}: {source}", path.display())]
+    Write { path: PathBuf, source: io::Error },
+
+    #[error("Could not decode reflection database as MessagePack: {source}")]
+    MessagePack {
+        #[from]
+        source: rmp_serde::decode::Error,
+    },
+
+    #[error("Could not encode reflection database as MessagePack: {source}")]
+    EncodeMessagePack {
+        #[from]
+        source: rmp_serde::encode::Error,
+    },
+
+    #[error("Could not decode reflection database as JSON: {source}")]
+    Json {
+        #[from]
+        source: serde_json::Error,
+    },
+
+    #[error("Could not encode reflection database as JSON: {source}")]
+    EncodeJson { source: serde_json::Error },
+
+    #[error("Could not decode reflection database as YAML: {source}")]
+    Yaml { source: serde_yaml::Error },
+
+    #[error("Could not encode reflection database as YAML: {source}")]
+    EncodeYaml { source: serde_yaml::Error },
+
+    #[error("Could not decode API dump: {source}")]
+    ApiDump { source: serde_json::Error },
+
+    #[error("Could not read patch {}: {source}", path.display())]
+    PatchIo { path: PathBuf, source: io::Error },
+
+    #[error("Could not decode patch {name}: {source}")]
+    PatchYaml {
+        name: String,
+        source: serde_yaml::Error,
+    },
+
+    #[error("Class {class_name} changed by patch {patch} does not exist in the database")]
+    PatchUnknownClass { patch: String, class_name: String },
+
+    #[error(
+        "Property {class_name}.{property_name} changed by patch {patch} \
+        does not exist in the database"
+    )]
+    PatchUnknownProperty {
+        patch: String,
+        class_name: String,
+        property_name: String,
+    },
+
+    #[error(
+        "Property {class_name}.{property_name} added by patch {patch} \
+        already exists in the database"
+    )]
+    PatchPropertyExists {
+        patch: String,
+        class_name: String,
+        property_name: String,
+    },
+
+    #[error(
+        "Property {class_name}.{property_name} in patch {patch} \
+        cannot have both AliasFor and Serialization"
+    )]
+    PatchAliasAndSerialization {
+        patch: String,
+        class_name: String,
+        property_name: String,
+    },
+
+    #[error("Invalid default in patch {patch}: {source}")]
+    PatchDefault {
+        patch: String,
+        source: rbx_reflection::ValidationError,
+    },
+
+    #[error(
+        "Unknown format for reflection database {} -- \
+        Supported formats are JSON (.json), MessagePack (.msgpack), and YAML (.yml or .yaml)",
+        path.display()
+    )]
+    UnknownFormat { path: PathBuf },
+}
@@ -1,15 +1,219 @@
+mod api_dump;
+mod error;
+mod patch;
+
+use std::{
+    fs,
+    path::Path,
+    sync::{OnceLock, PoisonError, RwLock},
+};
+
 use rbx_reflection::ReflectionDatabase;
 
+use crate::error::InnerError;
+
+pub use crate::{
+    api_dump::{apply_defaults, from_api_dump},
+    error::Error,
+    patch::{apply_patches, Patch, PatchConflict},
+};
+
 static ENCODED_DATABASE: &[u8] = include_bytes!("../database.msgpack");
 
+/// Every database bundled with this crate, oldest first, with the version of
+/// Roblox each is for. The newest is always bundled and comes last. Databases
+/// for older versions can be added before it, each behind its own
+/// `roblox-<version>` feature so that only the versions a tool needs are
+/// compiled in.
+static BUNDLED: &[(u32, &[u8])] = &[
+    #[cfg(feature = "roblox-530")]
+    (530, include_bytes!("../database-530.msgpack")),
+    (582, ENCODED_DATABASE),
+];
+
+/// The database installed with [`set_global`], if there is one.
+static GLOBAL: RwLock<Option<&'static ReflectionDatabase<'static>>> = RwLock::new(None);
+
 lazy_static::lazy_static! {
-    static ref DATABASE: ReflectionDatabase<'static> = {
-        rmp_serde::decode::from_slice(ENCODED_DATABASE).unwrap_or_else(|e| panic!("could not decode reflection database because: {}", e))
-    };
+    static ref DECODED: Vec<OnceLock<ReflectionDatabase<'static>>> =
+        BUNDLED.iter().map(|_| OnceLock::new()).collect();
 }
 
+fn decode_bundled(index: usize) -> &'static ReflectionDatabase<'static> {
+    DECODED[index].get_or_init(|| {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "rbx_reflection_database::decode",
+            version = BUNDLED[index].0
+        )
+        .entered();
+
+        rmp_serde::decode::from_slice(BUNDLED[index].1)
+            .unwrap_or_else(|e| panic!("could not decode reflection database because: {}", e))
+    })
+}
+
+/// Returns the newest reflection database bundled with this crate.
 pub fn get() -> &'static ReflectionDatabase<'static> {
-    &DATABASE
+    decode_bundled(BUNDLED.len() - 1)
+}
+
+/// Returns the process-wide default reflection database, which is the one
+/// installed with [`set_global`] or, if there isn't one, the newest bundled
+/// database.
+///
+/// This is the database that rbx_binary and rbx_xml use when they aren't
+/// given one, so a tool can install a patched or newer database once
+/// instead of passing it to every deserializer and serializer.
+pub fn global() -> &'static ReflectionDatabase<'static> {
+    GLOBAL
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .unwrap_or_else(get)
+}
+
+/// Installs a database as the one returned by [`global`], replacing any that
+/// was installed before. A database can be made `'static` by leaking it with
+/// [`Box::leak`].
+///
+/// Deserializers, serializers, and options that were created before this is
+/// called keep using the database that was global when they were created.
+pub fn set_global(database: &'static ReflectionDatabase<'static>) {
+    *GLOBAL.write().unwrap_or_else(PoisonError::into_inner) = Some(database);
+}
+
+/// Removes the database installed with [`set_global`], so that [`global`]
+/// returns the newest bundled database again.
+pub fn reset_global() {
+    *GLOBAL.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Returns the bundled reflection database for the given version of Roblox,
+/// like `582` for version 0.582, or `None` if it isn't bundled. Each database
+/// is only decoded the first time it's asked for.
+pub fn get_bundled(version: u32) -> Option<&'static ReflectionDatabase<'static>> {
+    let index = BUNDLED
+        .iter()
+        .position(|(bundled_version, _)| *bundled_version == version)?;
+
+    Some(decode_bundled(index))
+}
+
+/// Returns the versions of Roblox that there are bundled reflection databases
+/// for, oldest first.
+pub fn bundled_versions() -> impl Iterator<Item = u32> {
+    BUNDLED.iter().map(|(version, _)| *version)
+}
+
+/// Decodes a reflection database from MessagePack, the format of the database
+/// bundled with this crate.
+pub fn from_msgpack(bytes: &[u8]) -> Result<ReflectionDatabase<'static>, Error> {
+    Ok(rmp_serde::decode::from_slice(bytes).map_err(InnerError::from)?)
+}
+
+/// Encodes a reflection database as MessagePack, the format that
+/// [`from_msgpack`] reads. Along with
+/// [`ReflectionDatabase::subset`][rbx_reflection::ReflectionDatabase::subset],
+/// this can be used to embed a smaller database than the bundled one.
+pub fn to_msgpack(database: &ReflectionDatabase) -> Result<Vec<u8>, Error> {
+    Ok(rmp_serde::to_vec(database).map_err(InnerError::from)?)
+}
+
+/// Decodes a reflection database from JSON, like the output of
+/// `rbx_reflector generate database.json`.
+pub fn from_json(bytes: &[u8]) -> Result<ReflectionDatabase<'static>, Error> {
+    Ok(serde_json::from_slice(bytes).map_err(InnerError::from)?)
+}
+
+/// Encodes a reflection database as pretty-printed JSON, the format that
+/// [`from_json`] reads.
+///
+/// JSON can't hold the NaN and infinite values that some defaults have, so
+/// they come back as errors when the result is read. Use [`to_yaml`] for a
+/// readable form that round trips exactly.
+pub fn to_json(database: &ReflectionDatabase) -> Result<String, Error> {
+    Ok(serde_json::to_string_pretty(database)
+        .map_err(|source| InnerError::EncodeJson { source })?)
+}
+
+/// Decodes a reflection database from YAML, like the output of [`to_yaml`].
+pub fn from_yaml(bytes: &[u8]) -> Result<ReflectionDatabase<'static>, Error> {
+    Ok(serde_yaml::from_slice(bytes).map_err(|source| InnerError::Yaml { source })?)
+}
+
+/// Encodes a reflection database as YAML.
+///
+/// Classes, properties, and enums are written in alphabetical order, so a
+/// database kept in this form can be patched by hand and the patches kept as
+/// text diffs, then turned back into MessagePack with [`from_yaml`] and
+/// [`to_msgpack`].
+pub fn to_yaml(database: &ReflectionDatabase) -> Result<String, Error> {
+    Ok(serde_yaml::to_string(database).map_err(|source| InnerError::EncodeYaml { source })?)
+}
+
+/// The formats that [`load`] and [`save`] pick between by file extension.
+#[derive(Clone, Copy)]
+enum Format {
+    Json,
+    MessagePack,
+    Yaml,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Result<Self, Error> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Ok(Format::Json),
+            Some("msgpack") => Ok(Format::MessagePack),
+            Some("yml" | "yaml") => Ok(Format::Yaml),
+            _ => Err(InnerError::UnknownFormat {
+                path: path.to_owned(),
+            }
+            .into()),
+        }
+    }
+}
+
+/// Reads a reflection database from a file. The format is inferred from the
+/// file's extension, and can be JSON (.json), MessagePack (.msgpack), or YAML
+/// (.yml or .yaml).
+///
+/// This is useful for pinning a specific Roblox version, or for using a
+/// database generated from a build of Roblox that this crate doesn't know
+/// about, without waiting for a new release of this crate.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<ReflectionDatabase<'static>, Error> {
+    let path = path.as_ref();
+
+    let decode = match Format::from_path(path)? {
+        Format::Json => from_json,
+        Format::MessagePack => from_msgpack,
+        Format::Yaml => from_yaml,
+    };
+
+    let contents = fs::read(path).map_err(|source| InnerError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+
+    decode(&contents)
+}
+
+/// Writes a reflection database to a file, in the format given by the file's
+/// extension. See [`load`] for the formats that are supported.
+pub fn save<P: AsRef<Path>>(database: &ReflectionDatabase, path: P) -> Result<(), Error> {
+    let path = path.as_ref();
+
+    let contents = match Format::from_path(path)? {
+        Format::Json => to_json(database)?.into_bytes(),
+        Format::MessagePack => to_msgpack(database)?,
+        Format::Yaml => to_yaml(database)?.into_bytes(),
+    };
+
+    fs::write(path, contents).map_err(|source| InnerError::Write {
+        path: path.to_owned(),
+        source,
+    })?;
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -20,4 +224,125 @@ mod test {
     fn smoke_test() {
         let _database = get();
     }
+
+    #[test]
+    fn bundled() {
+        let newest = bundled_versions().last().unwrap();
+        assert!(std::ptr::eq(get_bundled(newest).unwrap(), get()));
+        assert!(get_bundled(0).is_none());
+
+        for version in bundled_versions() {
+            assert_eq!(get_bundled(version).unwrap().version[1], version);
+        }
+    }
+
+    #[cfg(feature = "roblox-530")]
+    #[test]
+    fn older_bundled() {
+        assert_eq!(bundled_versions().collect::<Vec<_>>(), [530, 582]);
+
+        let old = get_bundled(530).unwrap();
+        let new = get_bundled(582).unwrap();
+        assert_eq!(old.version[1], 530);
+        assert_eq!(new.version[1], 582);
+        assert!(std::ptr::eq(new, get()));
+        assert_ne!(old.classes.len(), new.classes.len());
+    }
+
+    #[test]
+    fn global_database() {
+        assert!(std::ptr::eq(global(), get()));
+
+        let database = Box::leak(Box::new(get().subset(["Folder"], Vec::<&str>::new())));
+        set_global(database);
+        assert!(std::ptr::eq(global(), database));
+
+        reset_global();
+        assert!(std::ptr::eq(global(), get()));
+    }
+
+    #[test]
+    fn load_json() {
+        let json = br#"{
+            "Version": [0, 600, 1, 6000717],
+            "Classes": {
+                "Folder": {
+                    "Name": "Folder",
+                    "Tags": [],
+                    "Superclass": "Instance",
+                    "Properties": {},
+                    "DefaultProperties": {}
+                }
+            },
+            "Enums": {}
+        }"#;
+
+        let database = from_json(json).unwrap();
+        assert_eq!(database.version, [0, 600, 1, 6000717]);
+        assert_eq!(
+            database.classes["Folder"].superclass.as_deref(),
+            Some("Instance")
+        );
+    }
+
+    #[test]
+    fn load_msgpack() {
+        let database = from_msgpack(ENCODED_DATABASE).unwrap();
+        assert_eq!(database.version, get().version);
+        assert_eq!(database.classes.len(), get().classes.len());
+    }
+
+    #[test]
+    fn subset_round_trip() {
+        let subset = get().subset(["Part"], ["KeyCode"]);
+        let decoded = from_msgpack(&to_msgpack(&subset).unwrap()).unwrap();
+
+        assert!(decoded.classes.contains_key("Part"));
+        assert!(decoded.classes.contains_key("Instance"));
+        assert!(!decoded.classes.contains_key("Folder"));
+        assert!(decoded.enums.contains_key("KeyCode"));
+        assert!(decoded.enums.contains_key("Material"));
+        assert_eq!(
+            decoded.class_default("Part", "Anchored"),
+            get().class_default("Part", "Anchored")
+        );
+    }
+
+    #[test]
+    fn yaml_round_trip() {
+        let yaml = to_yaml(get()).unwrap();
+        let decoded = from_yaml(yaml.as_bytes()).unwrap();
+
+        assert_eq!(to_yaml(&decoded).unwrap(), yaml);
+        assert_eq!(to_msgpack(&decoded).unwrap(), to_msgpack(get()).unwrap());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let subset = get().subset(["Folder"], Vec::<&str>::new());
+        let dir =
+            std::env::temp_dir().join(format!("rbx_reflection_database-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        for name in ["database.json", "database.msgpack", "database.yml"] {
+            let path = dir.join(name);
+            save(&subset, &path).unwrap();
+
+            let loaded = load(&path).unwrap();
+            assert_eq!(loaded.version, subset.version);
+            assert!(loaded.classes.contains_key("Folder"));
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(save(&subset, dir.join("database.toml")).is_err());
+    }
+
+    #[test]
+    fn load_invalid() {
+        assert!(from_msgpack(b"not a database").is_err());
+        assert!(from_json(b"{}").is_err());
+
+        let error = load("database.toml").unwrap_err();
+        assert!(error.to_string().contains("Unknown format"));
+    }
 }
@@ -0,0 +1,493 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fmt, fs,
+    path::Path,
+};
+
+use rbx_dom_weak::types::Variant;
+use rbx_reflection::{
+    DataType, PropertyDescriptor, PropertyKind, PropertyMigration, PropertySerialization,
+    ReflectionDatabase, Scriptability,
+};
+use serde::Deserialize;
+
+use crate::error::{Error, InnerError};
+
+/// A set of changes to a reflection database, read from YAML in the same
+/// format as the patches that rbx_reflector applies when generating the
+/// bundled database, so that mistakes in the bundled database can be fixed
+/// without waiting for a new release of this crate.
+///
+/// A patch can change the descriptors of existing properties, add new
+/// properties, and set the defaults of properties:
+///
+/// ```yaml
+/// Change:
+///   BasePart:
+///     Size:
+///       Serialization:
+///         Type: SerializesAs
+///         As: size
+/// Add:
+///   Part:
+///     PluginData:
+///       DataType:
+///         Value: String
+///       Scriptability: ReadWrite
+/// Defaults:
+///   Part:
+///     Anchored:
+///       Bool: true
+/// ```
+///
+/// Patches are applied with [`apply_patches`].
+#[derive(Debug)]
+pub struct Patch {
+    name: String,
+    contents: PatchContents,
+}
+
+impl Patch {
+    /// Reads a patch from YAML. The name is used to tell patches apart in
+    /// errors and conflicts, and is usually the path the patch came from.
+    pub fn from_yaml<S: Into<String>>(name: S, bytes: &[u8]) -> Result<Self, Error> {
+        let name = name.into();
+
+        match serde_yaml::from_slice(bytes) {
+            Ok(contents) => Ok(Self { name, contents }),
+            Err(source) => Err(InnerError::PatchYaml { name, source }.into()),
+        }
+    }
+
+    /// Reads a patch from a YAML file, named after its path.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+
+        let contents = fs::read(path).map_err(|source| InnerError::PatchIo {
+            path: path.to_owned(),
+            source,
+        })?;
+
+        Self::from_yaml(path.display().to_string(), &contents)
+    }
+
+    /// The name of this patch.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// A property that more than one patch passed to [`apply_patches`] changed.
+/// The later patch's changes were kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PatchConflict {
+    /// The name of the class that the property is on.
+    pub class_name: String,
+
+    /// The name of the property.
+    pub property_name: String,
+
+    /// Whether the conflicting patches set the property's default rather
+    /// than its descriptor.
+    pub default: bool,
+
+    /// The name of the patch that changed the property first.
+    pub earlier_patch: String,
+
+    /// The name of the patch whose changes were kept.
+    pub later_patch: String,
+}
+
+impl fmt::Display for PatchConflict {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "The {} of {}.{} was changed by both {} and {}; the changes from {} were kept",
+            if self.default {
+                "default"
+            } else {
+                "descriptor"
+            },
+            self.class_name,
+            self.property_name,
+            self.earlier_patch,
+            self.later_patch,
+            self.later_patch
+        )
+    }
+}
+
+/// Applies patches to a database in order, so that later patches win when
+/// they change the same property as earlier ones. Each time that happens,
+/// it's returned as a [`PatchConflict`].
+///
+/// Within a patch, properties are added first, then changed, then their
+/// defaults are set, so a patch can set the default of a property that it
+/// adds. Changing or setting the default of a property that doesn't exist,
+/// adding a property that the class or one of its superclasses already has,
+/// or setting a default that isn't valid for its property are errors, and
+/// leave the database partially patched.
+///
+/// To patch the bundled database, clone it first:
+///
+/// ```no_run
+/// # fn main() -> Result<(), rbx_reflection_database::Error> {
+/// use rbx_reflection_database::{apply_patches, Patch};
+///
+/// let patches = [Patch::load("fixes.yml")?, Patch::load("plugin.yml")?];
+///
+/// let mut database = rbx_reflection_database::get().clone();
+/// for conflict in apply_patches(&mut database, &patches)? {
+///     eprintln!("{}", conflict);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn apply_patches<'p, I>(
+    database: &mut ReflectionDatabase<'static>,
+    patches: I,
+) -> Result<Vec<PatchConflict>, Error>
+where
+    I: IntoIterator<Item = &'p Patch>,
+{
+    // Which patch last changed each property, keyed by class, property, and
+    // whether it was the default that was changed.
+    let mut changed_by: HashMap<(String, String, bool), &str> = HashMap::new();
+    let mut conflicts = Vec::new();
+
+    let mut record = |class_name: &str, property_name: &str, default: bool, patch: &'p str| {
+        let key = (class_name.to_owned(), property_name.to_owned(), default);
+
+        if let Some(earlier_patch) = changed_by.insert(key, patch) {
+            conflicts.push(PatchConflict {
+                class_name: class_name.to_owned(),
+                property_name: property_name.to_owned(),
+                default,
+                earlier_patch: earlier_patch.to_owned(),
+                later_patch: patch.to_owned(),
+            });
+            true
+        } else {
+            false
+        }
+    };
+
+    for patch in patches {
+        let name = patch.name.as_str();
+
+        for (class_name, class_additions) in &patch.contents.add {
+            for (property_name, addition) in class_additions {
+                let kind = property_kind(name, class_name, property_name, addition.kind())?
+                    .unwrap_or(PropertyKind::Canonical {
+                        serialization: PropertySerialization::Serializes,
+                    });
+
+                class_mut(database, name, class_name)?;
+                let added_earlier = record(class_name, property_name, false, name);
+
+                // Properties of superclasses count too, since adding one again
+                // would hide the original.
+                let exists = database
+                    .find_property_descriptors(class_name, property_name)
+                    .is_some();
+
+                if exists && !added_earlier {
+                    return Err(InnerError::PatchPropertyExists {
+                        patch: name.to_owned(),
+                        class_name: class_name.clone(),
+                        property_name: property_name.clone(),
+                    }
+                    .into());
+                }
+
+                let mut property =
+                    PropertyDescriptor::new(property_name.clone(), addition.data_type.clone());
+                property.kind = kind;
+                property.scriptability = addition.scriptability.unwrap_or(Scriptability::None);
+
+                class_mut(database, name, class_name)?
+                    .properties
+                    .insert(Cow::Owned(property_name.clone()), property);
+            }
+        }
+
+        for (class_name, class_changes) in &patch.contents.change {
+            for (property_name, change) in class_changes {
+                let kind = property_kind(name, class_name, property_name, change.kind())?;
+
+                let property = class_mut(database, name, class_name)?
+                    .properties
+                    .get_mut(property_name.as_str())
+                    .ok_or_else(|| InnerError::PatchUnknownProperty {
+                        patch: name.to_owned(),
+                        class_name: class_name.clone(),
+                        property_name: property_name.clone(),
+                    })?;
+
+                record(class_name, property_name, false, name);
+
+                if let Some(data_type) = &change.data_type {
+                    property.data_type = data_type.clone();
+                }
+                if let Some(kind) = kind {
+                    property.kind = kind;
+                }
+                if let Some(scriptability) = change.scriptability {
+                    property.scriptability = scriptability;
+                }
+            }
+        }
+
+        for (class_name, class_defaults) in &patch.contents.defaults {
+            class_mut(database, name, class_name)?;
+
+            for (property_name, value) in class_defaults {
+                database
+                    .validate_value(class_name, property_name, value)
+                    .map_err(|source| InnerError::PatchDefault {
+                        patch: name.to_owned(),
+                        source,
+                    })?;
+
+                // Defaults are stored under the canonical name of their
+                // property, even if the patch used an alias.
+                let canonical_name = database
+                    .find_property_descriptors(class_name, property_name)
+                    .map(|descriptors| descriptors.canonical.name.clone().into_owned())
+                    .unwrap_or_else(|| property_name.clone());
+
+                record(class_name, &canonical_name, true, name);
+
+                class_mut(database, name, class_name)?
+                    .default_properties
+                    .insert(Cow::Owned(canonical_name), value.clone());
+            }
+        }
+    }
+
+    Ok(conflicts)
+}
+
+fn class_mut<'d>(
+    database: &'d mut ReflectionDatabase<'static>,
+    patch: &str,
+    class_name: &str,
+) -> Result<&'d mut rbx_reflection::ClassDescriptor<'static>, Error> {
+    database.classes.get_mut(class_name).ok_or_else(|| {
+        InnerError::PatchUnknownClass {
+            patch: patch.to_owned(),
+            class_name: class_name.to_owned(),
+        }
+        .into()
+    })
+}
+
+fn property_kind(
+    patch: &str,
+    class_name: &str,
+    property_name: &str,
+    kind: Result<Option<PropertyKind<'static>>, ()>,
+) -> Result<Option<PropertyKind<'static>>, Error> {
+    kind.map_err(|()| {
+        InnerError::PatchAliasAndSerialization {
+            patch: patch.to_owned(),
+            class_name: class_name.to_owned(),
+            property_name: property_name.to_owned(),
+        }
+        .into()
+    })
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+struct PatchContents {
+    #[serde(default)]
+    change: BTreeMap<String, BTreeMap<String, PropertyChange>>,
+
+    #[serde(default)]
+    add: BTreeMap<String, BTreeMap<String, PropertyAddition>>,
+
+    #[serde(default)]
+    defaults: BTreeMap<String, BTreeMap<String, Variant>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+struct PropertyChange {
+    data_type: Option<DataType<'static>>,
+    alias_for: Option<String>,
+    serialization: Option<Serialization>,
+    scriptability: Option<Scriptability>,
+}
+
+impl PropertyChange {
+    fn kind(&self) -> Result<Option<PropertyKind<'static>>, ()> {
+        to_kind(&self.alias_for, &self.serialization)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+struct PropertyAddition {
+    data_type: DataType<'static>,
+    alias_for: Option<String>,
+    serialization: Option<Serialization>,
+    scriptability: Option<Scriptability>,
+}
+
+impl PropertyAddition {
+    fn kind(&self) -> Result<Option<PropertyKind<'static>>, ()> {
+        to_kind(&self.alias_for, &self.serialization)
+    }
+}
+
+fn to_kind(
+    alias_for: &Option<String>,
+    serialization: &Option<Serialization>,
+) -> Result<Option<PropertyKind<'static>>, ()> {
+    match (alias_for, serialization) {
+        (Some(alias), None) => Ok(Some(PropertyKind::Alias {
+            alias_for: Cow::Owned(alias.clone()),
+        })),
+        (None, Some(serialization)) => Ok(Some(PropertyKind::Canonical {
+            serialization: serialization.clone().into(),
+        })),
+        (None, None) => Ok(None),
+        (Some(_), Some(_)) => Err(()),
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "Type", rename_all = "PascalCase", deny_unknown_fields)]
+enum Serialization {
+    Serializes,
+    DoesNotSerialize,
+    #[serde(rename_all = "PascalCase")]
+    SerializesAs {
+        #[serde(rename = "As")]
+        serializes_as: String,
+    },
+    Migrate(PropertyMigration),
+}
+
+impl From<Serialization> for PropertySerialization<'_> {
+    fn from(value: Serialization) -> Self {
+        match value {
+            Serialization::Serializes => PropertySerialization::Serializes,
+            Serialization::DoesNotSerialize => PropertySerialization::DoesNotSerialize,
+            Serialization::SerializesAs { serializes_as } => {
+                PropertySerialization::SerializesAs(Cow::Owned(serializes_as))
+            }
+            Serialization::Migrate(migration) => PropertySerialization::Migrate(migration),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_reflection::PropertyTag;
+
+    fn patch(name: &str, yaml: &str) -> Patch {
+        Patch::from_yaml(name, yaml.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn add_change_and_default() {
+        let patch = patch(
+            "first",
+            r#"
+            Add:
+              Part:
+                PluginData:
+                  DataType:
+                    Value: String
+                  Scriptability: ReadWrite
+            Change:
+              BasePart:
+                Anchored:
+                  Serialization:
+                    Type: DoesNotSerialize
+            Defaults:
+              Part:
+                PluginData:
+                  String: hello
+                Anchored:
+                  Bool: true
+            "#,
+        );
+
+        let mut database = crate::get().clone();
+        let conflicts = apply_patches(&mut database, [&patch]).unwrap();
+        assert!(conflicts.is_empty());
+
+        let plugin_data = &database.classes["Part"].properties["PluginData"];
+        assert!(matches!(plugin_data.data_type, DataType::Value(_)));
+        assert!(plugin_data.is_writable());
+        assert!(!plugin_data.has_tag(PropertyTag::Deprecated));
+
+        assert!(!database
+            .serialization("Part", "Anchored")
+            .unwrap()
+            .serializes());
+        assert_eq!(
+            database.class_default("Part", "PluginData"),
+            Some(&Variant::String("hello".to_owned()))
+        );
+        assert_eq!(
+            database.class_default("Part", "Anchored"),
+            Some(&Variant::Bool(true))
+        );
+    }
+
+    #[test]
+    fn later_patches_win() {
+        let first = patch(
+            "first",
+            "Defaults: { Part: { Anchored: { Bool: true } } }\n\
+             Add: { Part: { PluginData: { DataType: { Value: String } } } }",
+        );
+        let second = patch(
+            "second",
+            "Defaults: { Part: { Anchored: { Bool: false } } }\n\
+             Add: { Part: { PluginData: { DataType: { Value: Int32 } } } }",
+        );
+
+        let mut database = crate::get().clone();
+        let conflicts = apply_patches(&mut database, [&first, &second]).unwrap();
+
+        assert_eq!(conflicts.len(), 2);
+        assert!(conflicts.iter().all(|conflict| {
+            conflict.class_name == "Part"
+                && conflict.earlier_patch == "first"
+                && conflict.later_patch == "second"
+        }));
+        assert_eq!(
+            database.class_default("Part", "Anchored"),
+            Some(&Variant::Bool(false))
+        );
+    }
+
+    #[test]
+    fn invalid_patches() {
+        let cases = [
+            "Change: { Part: { NotAProperty: { Scriptability: Read } } }",
+            "Change: { NotAClass: { Name: { Scriptability: Read } } }",
+            "Add: { Part: { Anchored: { DataType: { Value: Bool } } } }",
+            "Defaults: { Part: { Anchored: { String: yes } } }",
+            "Change: { Part: { Shape: { AliasFor: shape, Serialization: { Type: Serializes } } } }",
+        ];
+
+        for yaml in cases {
+            let mut database = crate::get().clone();
+            let patches = [patch("invalid", yaml)];
+            let error = apply_patches(&mut database, &patches).unwrap_err();
+            assert!(error.to_string().contains("invalid"), "{}", error);
+        }
+
+        assert!(Patch::from_yaml("unknown", b"Remove: {}").is_err());
+    }
+}
@@ -1,6 +1,5 @@
-use std::collections::HashMap;
-
-use serde::Deserialize;
+use rbx_reflection::SecurityLevel;
+use serde::{de::IgnoredAny, Deserialize};
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
@@ -25,20 +24,52 @@ pub struct DumpClass {
 pub enum DumpClassMember {
     Property(DumpClassProperty),
 
-    #[serde(rename_all = "PascalCase")]
-    Function {
-        name: String,
-    },
+    Function(DumpClassFunction),
+    Event(DumpClassEvent),
+    Callback(DumpClassFunction),
+}
 
-    #[serde(rename_all = "PascalCase")]
-    Event {
-        name: String,
-    },
+/// A function or callback.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DumpClassFunction {
+    pub name: String,
+    pub parameters: Vec<DumpParameter>,
+    pub return_type: DumpType,
+    pub security: Security,
 
-    #[serde(rename_all = "PascalCase")]
-    Callback {
-        name: String,
-    },
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DumpClassEvent {
+    pub name: String,
+    pub parameters: Vec<DumpParameter>,
+    pub security: Security,
+
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DumpParameter {
+    pub name: String,
+    #[serde(rename = "Type")]
+    pub parameter_type: DumpType,
+    pub default: Option<String>,
+}
+
+/// The type of a parameter or return value. Unlike [`ValueType`], these can
+/// have categories like `Group` that properties never use, so the category is
+/// kept as it is.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DumpType {
+    pub name: String,
+    pub category: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -86,6 +117,19 @@ pub enum Security {
     RobloxSecurity,
 }
 
+impl From<Security> for SecurityLevel {
+    fn from(security: Security) -> Self {
+        match security {
+            Security::None => SecurityLevel::None,
+            Security::LocalUserSecurity => SecurityLevel::LocalUserSecurity,
+            Security::PluginSecurity => SecurityLevel::PluginSecurity,
+            Security::RobloxScriptSecurity => SecurityLevel::RobloxScriptSecurity,
+            Security::NotAccessibleSecurity => SecurityLevel::NotAccessibleSecurity,
+            Security::RobloxSecurity => SecurityLevel::RobloxSecurity,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct PropertySecurity {
@@ -97,7 +141,6 @@ pub struct PropertySecurity {
 #[serde(rename_all = "PascalCase")]
 pub struct Serialization {
     pub can_save: bool,
-    pub can_load: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -112,11 +155,16 @@ pub struct DumpEnum {
 pub struct DumpEnumItem {
     pub name: String,
     pub value: u32,
+    #[serde(default)]
+    pub legacy_names: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
 pub enum Tag {
     Regular(String),
-    Named(HashMap<String, String>),
+
+    /// Tags with a value, like `{"PreferredDescriptorName": "Size"}`, which
+    /// aren't used.
+    Named(IgnoredAny),
 }
@@ -9,16 +9,22 @@ use std::{
 use anyhow::{bail, Context};
 use clap::Parser;
 use rbx_reflection::{
-    ClassDescriptor, DataType, EnumDescriptor, PropertyDescriptor, PropertyKind,
-    PropertySerialization, PropertyTag, ReflectionDatabase, Scriptability,
+    ClassDescriptor, DataType, EnumDescriptor, EventDescriptor, FunctionDescriptor,
+    ParameterDescriptor, PropertyDescriptor, PropertyKind, PropertySecurity, PropertySerialization,
+    PropertyTag, ReflectionDatabase, Scriptability, TypeDescriptor,
 };
 use rbx_types::VariantType;
+use roblox_install::RobloxStudio;
 use tempfile::tempdir;
 
 use crate::{
-    api_dump::{Dump, DumpClassMember, Security, Tag, ValueCategory},
+    api_dump::{
+        Dump, DumpClassEvent, DumpClassFunction, DumpClassMember, DumpParameter, DumpType,
+        Security, Tag, ValueCategory,
+    },
     defaults::apply_defaults,
     patches::Patches,
+    reflection_metadata::apply_reflection_metadata,
 };
 
 use super::{defaults_place::DefaultsPlaceSubcommand, dump::DumpSubcommand};
@@ -65,6 +71,17 @@ impl GenerateSubcommand {
 
         apply_defaults(&mut database, &defaults_place_path)?;
 
+        let studio_install =
+            RobloxStudio::locate().context("Could not locate Roblox Studio install")?;
+        // ReflectionMetadata.xml sits next to the content directory on every
+        // platform.
+        let metadata_path = studio_install
+            .content_path()
+            .with_file_name("ReflectionMetadata.xml");
+        if let Err(err) = apply_reflection_metadata(&mut database, &metadata_path) {
+            log::warn!("Class categories could not be read: {:?}", err);
+        }
+
         database.version = studio_info.version;
 
         for path in &self.output {
@@ -178,6 +195,10 @@ fn apply_dump(database: &mut ReflectionDatabase, dump: &Dump) -> anyhow::Result<
                 property.scriptability = scriptability;
                 property.tags = tags;
                 property.kind = kind;
+                property.security = Some(PropertySecurity::new(
+                    dump_property.security.read.into(),
+                    dump_property.security.write.into(),
+                ));
 
                 properties.insert(Cow::Owned(dump_property.name.clone()), property);
             }
@@ -188,6 +209,24 @@ fn apply_dump(database: &mut ReflectionDatabase, dump: &Dump) -> anyhow::Result<
         class.tags = tags;
         class.properties = properties;
 
+        for member in &dump_class.members {
+            match member {
+                DumpClassMember::Function(dump_function) => {
+                    let function = convert_function(dump_function);
+                    class.functions.insert(function.name.clone(), function);
+                }
+                DumpClassMember::Event(dump_event) => {
+                    let event = convert_event(dump_event);
+                    class.events.insert(event.name.clone(), event);
+                }
+                DumpClassMember::Callback(dump_callback) => {
+                    let callback = convert_function(dump_callback);
+                    class.callbacks.insert(callback.name.clone(), callback);
+                }
+                DumpClassMember::Property(_) => {}
+            }
+        }
+
         database
             .classes
             .insert(Cow::Owned(dump_class.name.clone()), class);
@@ -200,6 +239,13 @@ fn apply_dump(database: &mut ReflectionDatabase, dump: &Dump) -> anyhow::Result<
             descriptor
                 .items
                 .insert(Cow::Owned(dump_item.name.clone()), dump_item.value);
+
+            for legacy_name in &dump_item.legacy_names {
+                descriptor.legacy_names.insert(
+                    Cow::Owned(legacy_name.clone()),
+                    Cow::Owned(dump_item.name.clone()),
+                );
+            }
         }
 
         database
@@ -210,6 +256,54 @@ fn apply_dump(database: &mut ReflectionDatabase, dump: &Dump) -> anyhow::Result<
     Ok(())
 }
 
+fn convert_function(dump_function: &DumpClassFunction) -> FunctionDescriptor<'static> {
+    let mut function = FunctionDescriptor::new(
+        dump_function.name.clone(),
+        convert_type(&dump_function.return_type),
+    );
+    function.parameters = convert_parameters(&dump_function.parameters);
+    function.security = Some(dump_function.security.into());
+    function.tags = convert_member_tags(&dump_function.tags);
+    function
+}
+
+fn convert_event(dump_event: &DumpClassEvent) -> EventDescriptor<'static> {
+    let mut event = EventDescriptor::new(dump_event.name.clone());
+    event.parameters = convert_parameters(&dump_event.parameters);
+    event.security = Some(dump_event.security.into());
+    event.tags = convert_member_tags(&dump_event.tags);
+    event
+}
+
+fn convert_parameters(dump_parameters: &[DumpParameter]) -> Vec<ParameterDescriptor<'static>> {
+    dump_parameters
+        .iter()
+        .map(|dump_parameter| {
+            let mut parameter = ParameterDescriptor::new(
+                dump_parameter.name.clone(),
+                convert_type(&dump_parameter.parameter_type),
+            );
+            parameter.default = dump_parameter.default.clone().map(Cow::Owned);
+            parameter
+        })
+        .collect()
+}
+
+fn convert_type(dump_type: &DumpType) -> TypeDescriptor<'static> {
+    TypeDescriptor::new(dump_type.category.clone(), dump_type.name.clone())
+}
+
+/// Member tags that rbx_reflection doesn't know about are skipped, since new
+/// ones are added to functions and events more often than to properties.
+fn convert_member_tags<T: std::str::FromStr + Eq + std::hash::Hash>(tags: &[Tag]) -> HashSet<T> {
+    tags.iter()
+        .filter_map(|tag| match tag {
+            Tag::Regular(tag) => tag.parse().ok(),
+            Tag::Named(_) => None,
+        })
+        .collect()
+}
+
 fn variant_type_from_str(value: &str) -> anyhow::Result<Option<VariantType>> {
     Ok(Some(match value {
         "Axes" => VariantType::Axes,
@@ -231,6 +325,7 @@ fn variant_type_from_str(value: &str) -> anyhow::Result<Option<VariantType>> {
         "Rect" => VariantType::Rect,
         "Region3" => VariantType::Region3,
         "Region3int16" => VariantType::Region3int16,
+        "SecurityCapabilities" => VariantType::SecurityCapabilities,
         "SharedString" => VariantType::SharedString,
         "UDim" => VariantType::UDim,
         "UDim2" => VariantType::UDim2,
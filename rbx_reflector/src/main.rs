@@ -2,6 +2,7 @@ mod api_dump;
 mod cli;
 mod defaults;
 mod patches;
+mod reflection_metadata;
 
 use clap::Parser;
 
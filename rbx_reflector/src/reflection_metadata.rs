@@ -0,0 +1,64 @@
+use std::{collections::VecDeque, fs::File, io::BufReader, path::Path};
+
+use anyhow::Context;
+use rbx_dom_weak::{types::Variant, Instance};
+use rbx_reflection::ReflectionDatabase;
+
+/// Fills in the category and explorer order of each class from the
+/// ReflectionMetadata.xml file that comes with Roblox Studio, which has the
+/// parts of the reflection data that the API dump leaves out.
+pub fn apply_reflection_metadata(
+    database: &mut ReflectionDatabase,
+    metadata_path: &Path,
+) -> anyhow::Result<()> {
+    let file =
+        BufReader::new(File::open(metadata_path).context("Could not find ReflectionMetadata.xml")?);
+
+    let decode_options = rbx_xml::DecodeOptions::new()
+        .property_behavior(rbx_xml::DecodePropertyBehavior::ReadUnknown);
+
+    let tree = rbx_xml::from_reader(file, decode_options)
+        .context("Could not decode ReflectionMetadata.xml")?;
+
+    let mut to_visit = VecDeque::from_iter(tree.root().children());
+
+    while let Some(referent) = to_visit.pop_front() {
+        let instance = tree.get_by_ref(*referent).unwrap();
+
+        to_visit.extend(instance.children());
+
+        if instance.class == "ReflectionMetadataClass" {
+            apply_class_metadata(database, instance);
+        }
+    }
+
+    Ok(())
+}
+
+fn apply_class_metadata(database: &mut ReflectionDatabase, instance: &Instance) {
+    let class = match database.classes.get_mut(instance.name.as_str()) {
+        Some(class_descriptor) => class_descriptor,
+        None => {
+            log::debug!(
+                "Class {} found in ReflectionMetadata.xml but not reflection database",
+                instance.name
+            );
+
+            return;
+        }
+    };
+
+    // Values are stored as strings, but might have been converted to the types
+    // the reflection database gives them.
+    if let Some(Variant::String(category)) = instance.properties.get("ClassCategory") {
+        if !category.is_empty() {
+            class.category = Some(category.clone().into());
+        }
+    }
+
+    class.explorer_order = match instance.properties.get("ExplorerOrder") {
+        Some(Variant::Int32(order)) => u32::try_from(*order).ok(),
+        Some(Variant::String(order)) => order.parse().ok(),
+        _ => None,
+    };
+}
@@ -0,0 +1,239 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use rbx_dom_weak::{
+    types::{BinaryString, Ref, Variant},
+    WeakDom,
+};
+use serde_json::Value;
+
+use crate::SnapshotOptions;
+
+/// Writes a DOM as a snapshot.
+///
+/// Each instance is a line with its name and class, indented by depth, and is
+/// followed by its properties sorted by name. Instances that a Ref property
+/// points to are marked with a number, like `@3`, which is their position in
+/// the tree, and Ref properties are written as that number.
+pub fn to_snapshot_with(dom: &WeakDom, options: &SnapshotOptions) -> String {
+    let mut order = Vec::new();
+    let mut to_visit = vec![(dom.root_ref(), 0)];
+    while let Some((referent, depth)) = to_visit.pop() {
+        order.push((referent, depth));
+
+        let instance = dom.get_by_ref(referent).unwrap();
+        to_visit.extend(
+            instance
+                .children()
+                .iter()
+                .rev()
+                .map(|&child| (child, depth + 1)),
+        );
+    }
+
+    let numbers: HashMap<Ref, usize> = order
+        .iter()
+        .enumerate()
+        .map(|(index, &(referent, _))| (referent, index + 1))
+        .collect();
+
+    let targets: HashSet<Ref> = order
+        .iter()
+        .flat_map(|&(referent, _)| dom.get_by_ref(referent).unwrap().properties.values())
+        .filter_map(|value| match value {
+            Variant::Ref(target) => Some(*target),
+            _ => None,
+        })
+        .collect();
+
+    let formatter = ValueFormatter { options, numbers };
+    let mut output = String::new();
+
+    for (referent, depth) in order {
+        let instance = dom.get_by_ref(referent).unwrap();
+        let indent = "  ".repeat(depth);
+
+        write!(output, "{}{} ({})", indent, instance.name, instance.class).unwrap();
+        if targets.contains(&referent) {
+            write!(output, " @{}", formatter.numbers[&referent]).unwrap();
+        }
+        output.push('\n');
+
+        let mut properties: Vec<_> = instance.properties.iter().collect();
+        properties.sort_unstable_by_key(|(name, _)| *name);
+
+        for (name, value) in properties {
+            formatter.write_property(&mut output, &indent, name, value);
+        }
+    }
+
+    output
+}
+
+struct ValueFormatter<'a> {
+    options: &'a SnapshotOptions,
+    numbers: HashMap<Ref, usize>,
+}
+
+impl ValueFormatter<'_> {
+    fn write_property(&self, output: &mut String, indent: &str, name: &str, value: &Variant) {
+        let ty = value.ty();
+
+        // Multi-line strings, like script sources, are written one line at a
+        // time so that diffs point at the lines that changed.
+        if let Variant::String(value) = value {
+            if value.contains('\n') {
+                writeln!(output, "{}  {}: {:?} |", indent, name, ty).unwrap();
+                for line in value.split('\n') {
+                    writeln!(output, "{}    {}", indent, line.trim_end_matches('\r')).unwrap();
+                }
+                return;
+            }
+        }
+
+        let value = match value {
+            Variant::Ref(target) if target.is_none() => "nil".to_owned(),
+            Variant::Ref(target) => match self.numbers.get(target) {
+                Some(number) => format!("@{}", number),
+                None => "<outside of the DOM>".to_owned(),
+            },
+            Variant::BinaryString(value) => self.format_binary(value.as_ref()),
+            Variant::SharedString(value) => self.format_binary(value.data()),
+            other => self.format_serialized(other),
+        };
+
+        writeln!(output, "{}  {}: {:?} {}", indent, name, ty, value).unwrap();
+    }
+
+    fn format_binary(&self, data: &[u8]) -> String {
+        if data.len() > self.options.max_binary_len {
+            let hash = blake3::hash(data).to_hex();
+            format!("<{} bytes, blake3 {}>", data.len(), &hash[..16])
+        } else {
+            self.format_serialized(&Variant::BinaryString(BinaryString::from(data)))
+        }
+    }
+
+    /// Formats a value through its serde representation, which covers every
+    /// type and lets floats be rounded wherever they are inside of it.
+    fn format_serialized(&self, value: &Variant) -> String {
+        match serde_json::to_value(value) {
+            // Variants are serialized tagged with their type, which is
+            // already written.
+            Ok(Value::Object(tagged)) if tagged.len() == 1 => {
+                let mut output = String::new();
+                self.write_json(&mut output, tagged.values().next().unwrap());
+                output
+            }
+            _ => format!("{:?}", value),
+        }
+    }
+
+    fn write_json(&self, output: &mut String, value: &Value) {
+        match value {
+            Value::Null => output.push_str("null"),
+            Value::Bool(value) => write!(output, "{}", value).unwrap(),
+            Value::Number(number) if number.is_f64() => {
+                let number = number.as_f64().unwrap();
+                write!(output, "{}", self.round(number)).unwrap();
+            }
+            Value::Number(number) => write!(output, "{}", number).unwrap(),
+            Value::String(value) => write!(output, "{:?}", value).unwrap(),
+            Value::Array(values) => {
+                output.push('[');
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        output.push_str(", ");
+                    }
+                    self.write_json(output, value);
+                }
+                output.push(']');
+            }
+            Value::Object(fields) => {
+                output.push('{');
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        output.push_str(", ");
+                    }
+                    write!(output, "{}: ", key).unwrap();
+                    self.write_json(output, value);
+                }
+                output.push('}');
+            }
+        }
+    }
+
+    fn round(&self, number: f64) -> f64 {
+        let factor = 10f64.powi(self.options.float_precision as i32);
+        let rounded = (number * factor).round() / factor;
+
+        // Don't let -0 and 0 differ.
+        if rounded == 0.0 {
+            0.0
+        } else {
+            rounded
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{
+        types::{Attributes, CFrame, Color3, Matrix3, SharedString, Tags, Vector3},
+        InstanceBuilder,
+    };
+
+    use crate::to_snapshot;
+
+    #[test]
+    fn model() {
+        let part = InstanceBuilder::new("Part")
+            .with_name("Handle")
+            .with_property(
+                "CFrame",
+                CFrame::new(Vector3::new(1.0, 2.5, -3.0), Matrix3::identity()),
+            )
+            .with_property("Color", Color3::new(0.1, 0.2, 0.3))
+            .with_property("Transparency", -0.00001f32)
+            .with_property("PhysicalConfigData", SharedString::new(vec![7; 100]));
+        let part_ref = part.referent();
+
+        let mut tags = Tags::new();
+        tags.push("Weapon");
+
+        let mut attributes = Attributes::new();
+        attributes.insert("Damage".to_owned(), Variant::Float64(12.123456));
+
+        let tool = InstanceBuilder::new("Tool")
+            .with_name("Sword")
+            .with_property("Tags", tags)
+            .with_property("Attributes", attributes)
+            .with_property("Grip", Ref::none())
+            .with_child(part)
+            .with_child(
+                InstanceBuilder::new("Script")
+                    .with_property("Source", "print(\"Hello\")\nprint(\"World\")")
+                    .with_property("Target", part_ref)
+                    .with_property("LinkedSource", BinaryString::from(b"short".as_ref())),
+            );
+
+        let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(tool));
+        insta::assert_snapshot!(to_snapshot(&dom));
+    }
+
+    #[test]
+    fn floats_within_precision() {
+        let snapshot = |value: f32| {
+            to_snapshot(&WeakDom::new(
+                InstanceBuilder::new("NumberValue").with_property("Value", value),
+            ))
+        };
+
+        assert_eq!(snapshot(0.1 + 0.2), snapshot(0.3));
+        assert_ne!(snapshot(0.3), snapshot(0.4));
+    }
+}
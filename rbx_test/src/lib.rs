@@ -0,0 +1,279 @@
+/*!
+Snapshot testing for Roblox DOMs.
+
+[`to_snapshot`] writes a DOM as text that stays the same from run to run:
+instances in tree order, properties sorted by name, floats rounded, and Refs
+numbered by where their targets are in the tree instead of by referent.
+[`assert_snapshot`] compares that text against a file checked in next to
+the test and panics with a diff if they don't match.
+
+```no_run
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+
+let dom = WeakDom::new(InstanceBuilder::new("Folder").with_property("Value", 1.0f32));
+
+rbx_test::assert_snapshot("tests/snapshots/folder.txt", &dom);
+```
+
+[`assert_dom_eq!`] compares two DOMs against each other instead, using
+[`compare_doms`](rbx_dom_weak::compare_doms).
+
+Set the `RBX_UPDATE_SNAPSHOTS` environment variable to write new or changed
+snapshots instead of failing:
+
+```bash
+RBX_UPDATE_SNAPSHOTS=1 cargo test
+```
+*/
+
+mod format;
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use rbx_dom_weak::{compare_doms, DomCompareOptions, WeakDom};
+use similar::TextDiff;
+use thiserror::Error;
+
+pub use crate::format::to_snapshot_with;
+
+/// The environment variable that makes [`assert_snapshot`] write snapshots
+/// instead of comparing against them.
+pub const UPDATE_VARIABLE: &str = "RBX_UPDATE_SNAPSHOTS";
+
+/// Options for writing snapshots with [`to_snapshot_with`] and
+/// [`assert_snapshot_with`].
+#[derive(Debug, Clone)]
+pub struct SnapshotOptions {
+    pub(crate) float_precision: u32,
+    pub(crate) max_binary_len: usize,
+}
+
+impl SnapshotOptions {
+    /// Create a new `SnapshotOptions` that rounds floats to four decimal
+    /// places and writes binary data up to 64 bytes long in full.
+    pub fn new() -> Self {
+        SnapshotOptions {
+            float_precision: 4,
+            max_binary_len: 64,
+        }
+    }
+
+    /// Set how many decimal places floats are rounded to, so that small
+    /// differences from floating point math don't change snapshots.
+    pub fn float_precision(self, float_precision: u32) -> Self {
+        Self {
+            float_precision,
+            ..self
+        }
+    }
+
+    /// Set the longest `BinaryString` or `SharedString` that's written out in
+    /// full. Longer ones are written as their length and a hash.
+    pub fn max_binary_len(self, max_binary_len: usize) -> Self {
+        Self {
+            max_binary_len,
+            ..self
+        }
+    }
+}
+
+impl Default for SnapshotOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Writes a DOM as a snapshot using the default [`SnapshotOptions`].
+pub fn to_snapshot(dom: &WeakDom) -> String {
+    to_snapshot_with(dom, &SnapshotOptions::new())
+}
+
+/// Compares a DOM against the snapshot at `path` using the default
+/// [`SnapshotOptions`], panicking with a diff if they don't match. See
+/// [`check_snapshot`] for how `path` is found.
+#[track_caller]
+pub fn assert_snapshot<P: AsRef<Path>>(path: P, dom: &WeakDom) {
+    assert_snapshot_with(path, dom, &SnapshotOptions::new())
+}
+
+/// Compares a DOM against the snapshot at `path` using the given options,
+/// panicking with a diff if they don't match.
+#[track_caller]
+pub fn assert_snapshot_with<P: AsRef<Path>>(path: P, dom: &WeakDom, options: &SnapshotOptions) {
+    if let Err(err) = check_snapshot(path, &to_snapshot_with(dom, options)) {
+        panic!("{}", err);
+    }
+}
+
+/// Asserts that two [`WeakDom`] objects are structurally equal, panicking with
+/// a readable list of their differences if they are not.
+///
+/// By default, floats are compared with a small tolerance and children must
+/// be in the same order. Pass a [`DomCompareOptions`] as the third argument to
+/// change this.
+///
+/// ```
+/// use rbx_dom_weak::{DomCompareOptions, InstanceBuilder, WeakDom};
+/// use rbx_test::assert_dom_eq;
+///
+/// let a = WeakDom::new(InstanceBuilder::new("Folder").with_children([
+///     InstanceBuilder::new("Part"),
+///     InstanceBuilder::new("Model"),
+/// ]));
+/// let b = WeakDom::new(InstanceBuilder::new("Folder").with_children([
+///     InstanceBuilder::new("Model"),
+///     InstanceBuilder::new("Part"),
+/// ]));
+///
+/// assert_dom_eq!(a, b, DomCompareOptions::new().ignore_child_order(true));
+/// ```
+#[macro_export]
+macro_rules! assert_dom_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::assert_dom_eq!($left, $right, ::std::default::Default::default())
+    };
+    ($left:expr, $right:expr, $options:expr $(,)?) => {
+        $crate::assert_dom_eq_with(&$left, &$right, &$options)
+    };
+}
+
+/// The implementation of [`assert_dom_eq!`], which is a macro so that the
+/// options can be left out.
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_dom_eq_with(left: &WeakDom, right: &WeakDom, options: &DomCompareOptions) {
+    let differences = compare_doms(left, right, options);
+
+    if !differences.is_empty() {
+        let mut message = String::new();
+        for difference in &differences {
+            message.push_str(&format!("\n  {}", difference));
+        }
+
+        panic!(
+            "assertion failed: `(left == right)`\n{} difference(s) between doms:{}",
+            differences.len(),
+            message
+        );
+    }
+}
+
+/// Compares `actual` against the snapshot at `path`. Relative paths are
+/// relative to the crate's manifest directory when run by Cargo.
+///
+/// If the `RBX_UPDATE_SNAPSHOTS` environment variable is set, a missing or
+/// different snapshot is written instead, creating any missing directories.
+pub fn check_snapshot<P: AsRef<Path>>(path: P, actual: &str) -> Result<(), SnapshotError> {
+    let path = resolve_path(path.as_ref());
+
+    let expected = match fs::read_to_string(&path) {
+        Ok(expected) => Some(expected),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => return Err(SnapshotError::Io(path, err)),
+    };
+
+    // Files checked out on Windows may have gained carriage returns.
+    let expected = expected.map(|expected| expected.replace("\r\n", "\n"));
+    if expected.as_deref() == Some(actual) {
+        return Ok(());
+    }
+
+    if env::var_os(UPDATE_VARIABLE).is_some() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| SnapshotError::Io(path.clone(), err))?;
+        }
+
+        return fs::write(&path, actual).map_err(|err| SnapshotError::Io(path, err));
+    }
+
+    match expected {
+        None => Err(SnapshotError::Missing(path)),
+        Some(expected) => {
+            let diff = TextDiff::from_lines(expected.as_str(), actual)
+                .unified_diff()
+                .header("expected", "actual")
+                .to_string();
+
+            Err(SnapshotError::Mismatch(path, diff))
+        }
+    }
+}
+
+fn resolve_path(path: &Path) -> PathBuf {
+    match env::var_os("CARGO_MANIFEST_DIR") {
+        Some(manifest_dir) if path.is_relative() => Path::new(&manifest_dir).join(path),
+        _ => path.to_owned(),
+    }
+}
+
+/// Why a snapshot didn't match.
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    /// There's no snapshot at the path yet.
+    #[error("snapshot {} doesn't exist. Run with {}=1 to create it.", .0.display(), UPDATE_VARIABLE)]
+    Missing(PathBuf),
+
+    /// The snapshot didn't match. Holds a unified diff from the snapshot to
+    /// the actual value.
+    #[error("snapshot {} doesn't match. Run with {}=1 to update it.\n\n{}", .0.display(), UPDATE_VARIABLE, .1)]
+    Mismatch(PathBuf, String),
+
+    /// The snapshot couldn't be read or written.
+    #[error("couldn't access {}: {}", .0.display(), .1)]
+    Io(PathBuf, #[source] io::Error),
+}
+
+#[cfg(test)]
+mod test {
+    use rbx_dom_weak::{types::Vector3, InstanceBuilder};
+
+    use super::*;
+
+    #[test]
+    fn check() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.txt");
+
+        assert!(matches!(
+            check_snapshot(&path, "Folder (Folder)\n"),
+            Err(SnapshotError::Missing(_))
+        ));
+
+        fs::write(&path, "Folder (Folder)\r\n").unwrap();
+        check_snapshot(&path, "Folder (Folder)\n").unwrap();
+
+        match check_snapshot(&path, "Model (Model)\n") {
+            Err(SnapshotError::Mismatch(_, diff)) => {
+                assert!(diff.contains("-Folder (Folder)"), "{}", diff);
+                assert!(diff.contains("+Model (Model)"), "{}", diff);
+            }
+            other => panic!("expected a mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn dom_eq() {
+        let make = |x: f32| {
+            let part =
+                InstanceBuilder::new("Part").with_property("Size", Vector3::new(x, 1.0, 1.0));
+            let weld =
+                InstanceBuilder::new("WeldConstraint").with_property("Part0", part.referent());
+
+            WeakDom::new(InstanceBuilder::new("Model").with_children([part, weld]))
+        };
+
+        assert_dom_eq!(make(1.0), make(1.000001));
+    }
+
+    #[test]
+    #[should_panic(expected = "1 difference(s) between doms")]
+    fn dom_eq_panics() {
+        let a = WeakDom::new(InstanceBuilder::new("Folder"));
+        let b = WeakDom::new(InstanceBuilder::new("Model").with_name("Folder"));
+
+        assert_dom_eq!(a, b);
+    }
+}
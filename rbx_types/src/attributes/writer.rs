@@ -1,5 +1,4 @@
 use std::{
-    borrow::Borrow,
     collections::BTreeMap,
     io::{self, Write},
 };
@@ -24,8 +23,6 @@ pub(crate) fn write_attributes<W: Write>(
     writer.write_all(&(map.len() as u32).to_le_bytes())?;
 
     for (name, variant) in map {
-        let variant = variant.borrow();
-
         write_string(&mut writer, name)?;
 
         let type_id = type_id::from_variant_type(variant.ty())
@@ -96,10 +93,7 @@ pub(crate) fn write_attributes<W: Write>(
                 write_u16(&mut writer, font.weight.as_u16())?;
                 write_u8(&mut writer, font.style.as_u8())?;
                 write_string(&mut writer, &font.family)?;
-                write_string(
-                    &mut writer,
-                    &font.cached_face_id.clone().unwrap_or_default(),
-                )?;
+                write_string(&mut writer, font.cached_face_id.clone().unwrap_or_default())?;
             }
 
             other_variant => unreachable!("variant {:?} was not implemented", other_variant),
@@ -316,9 +316,9 @@ pub struct Vector3 {
 }
 
 fn approx_unit_or_zero(value: f32) -> Option<i32> {
-    if value.abs() <= std::f32::EPSILON {
+    if value.abs() <= f32::EPSILON {
         Some(0)
-    } else if value.abs() - 1.0 <= std::f32::EPSILON {
+    } else if value.abs() - 1.0 <= f32::EPSILON {
         Some(1.0f32.copysign(value) as i32)
     } else {
         None
@@ -782,7 +782,7 @@ impl CFrame {
     #[cfg(feature = "impl")]
     pub fn lerp(&self, goal: &CFrame, alpha: f32) -> Self {
         if alpha == 0.0 {
-            self.clone()
+            *self
         } else if alpha == 1.0 {
             *goal
         } else {
@@ -973,7 +973,9 @@ impl LuaUserData for CFrame {
 /// X Y Z. The [`RotationOrder`] Enum specifies the order in which the 3 rotations are applied.
 #[cfg(feature = "impl")]
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "impl", derive(Default))]
 pub enum RotationOrder {
+    #[cfg_attr(feature = "impl", default)]
     XYZ = 0,
     XZY = 1,
     YZX = 2,
@@ -982,13 +984,6 @@ pub enum RotationOrder {
     ZYX = 5,
 }
 
-#[cfg(feature = "impl")]
-impl Default for RotationOrder {
-    fn default() -> Self {
-        Self::XYZ
-    }
-}
-
 /// Used to represent the `orientation` field of `CFrame` and not a standalone
 /// type in Roblox.
 ///
@@ -1403,9 +1398,9 @@ impl Color3uint8 {
 impl From<Color3> for Color3uint8 {
     fn from(value: Color3) -> Self {
         Self {
-            r: ((value.r.max(0.0).min(1.0)) * 255.0).round() as u8,
-            g: ((value.g.max(0.0).min(1.0)) * 255.0).round() as u8,
-            b: ((value.b.max(0.0).min(1.0)) * 255.0).round() as u8,
+            r: (value.r.clamp(0.0, 1.0) * 255.0).round() as u8,
+            g: (value.g.clamp(0.0, 1.0) * 255.0).round() as u8,
+            b: (value.b.clamp(0.0, 1.0) * 255.0).round() as u8,
         }
     }
 }
@@ -2151,3 +2146,29 @@ mod algebra_test {
         euler_angles_identity(-0.1, 0.3, -0.2);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basic_rotation_ids() {
+        let ids: Vec<u8> = (0..=u8::MAX)
+            .filter(|&id| Matrix3::from_basic_rotation_id(id).is_ok())
+            .collect();
+
+        assert_eq!(ids.len(), 24);
+
+        for id in ids {
+            let matrix = Matrix3::from_basic_rotation_id(id).unwrap();
+            assert_eq!(matrix.to_basic_rotation_id(), Some(id));
+        }
+
+        let skewed = Matrix3::new(
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+        assert_eq!(skewed.to_basic_rotation_id(), None);
+    }
+}
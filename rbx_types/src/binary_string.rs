@@ -88,7 +88,9 @@ mod serde_impl {
     impl<'de> Deserialize<'de> for BinaryString {
         fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
             if deserializer.is_human_readable() {
-                let encoded = <&str>::deserialize(deserializer)?;
+                // Not every deserializer can lend out strings, like ones that
+                // read from a stream or that unescape strings.
+                let encoded = String::deserialize(deserializer)?;
                 let buffer = base64::decode(encoded).map_err(D::Error::custom)?;
 
                 Ok(BinaryString { buffer })
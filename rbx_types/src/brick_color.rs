@@ -1,5 +1,7 @@
 use std::fmt;
 
+use crate::Color3uint8;
+
 macro_rules! make_brick_color {
     ({
         $([
@@ -62,6 +64,17 @@ macro_rules! make_brick_color {
                     _ => None,
                 }
             }
+
+            /// Returns the color that this BrickColor stands for.
+            ///
+            /// This is equivalent to `BrickColor.Color` from within Roblox.
+            pub fn to_color3uint8(self) -> Color3uint8 {
+                match self {
+                    $(
+                        BrickColor::$enum => Color3uint8::new($color3_r, $color3_g, $color3_b),
+                    )+
+                }
+            }
         }
 
         impl fmt::Display for BrickColor {
@@ -335,6 +348,14 @@ mod tests {
     fn from_number() {
         assert_eq!(BrickColor::from_number(1030), Some(BrickColor::PastelBrown));
     }
+
+    #[test]
+    fn to_color3uint8() {
+        assert_eq!(
+            BrickColor::BrightRed.to_color3uint8(),
+            Color3uint8::new(196, 40, 28)
+        );
+    }
 }
 
 #[cfg(all(test, feature = "serde"))]
@@ -1,6 +1,8 @@
+use std::fmt;
+
 use thiserror::Error;
 
-use crate::{AttributeError, Matrix3Error};
+use crate::{AttributeError, Matrix3Error, PhysicalConfigError, Ref, SmoothGridError};
 
 /// Represents an error that occurred when using a fallible method.
 #[derive(Debug, Error)]
@@ -9,6 +11,17 @@ pub struct Error {
     source: Box<InnerError>,
 }
 
+impl DomError for Error {
+    fn code(&self) -> ErrorCode {
+        match &*self.source {
+            InnerError::AttributeError(_) => ErrorCode::InvalidAttributes,
+            InnerError::Matrix3Error(_) => ErrorCode::InvalidRotation,
+            InnerError::PhysicalConfig(_) => ErrorCode::InvalidPhysicalConfig,
+            InnerError::SmoothGrid(_) => ErrorCode::InvalidSmoothGrid,
+        }
+    }
+}
+
 impl From<AttributeError> for Error {
     fn from(source: AttributeError) -> Self {
         Self {
@@ -25,6 +38,22 @@ impl From<Matrix3Error> for Error {
     }
 }
 
+impl From<PhysicalConfigError> for Error {
+    fn from(source: PhysicalConfigError) -> Self {
+        Self {
+            source: Box::new(source.into()),
+        }
+    }
+}
+
+impl From<SmoothGridError> for Error {
+    fn from(source: SmoothGridError) -> Self {
+        Self {
+            source: Box::new(source.into()),
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 enum InnerError {
     #[error(transparent)]
@@ -32,4 +61,271 @@ enum InnerError {
 
     #[error(transparent)]
     Matrix3Error(#[from] Matrix3Error),
+
+    #[error(transparent)]
+    PhysicalConfig(#[from] PhysicalConfigError),
+
+    #[error(transparent)]
+    SmoothGrid(#[from] SmoothGridError),
+}
+
+/// The details shared by the errors of rbx_types, rbx_binary, and rbx_xml,
+/// for handling them without looking at their messages.
+///
+/// Every method but [`code`](DomError::code) returns `None` when the error
+/// doesn't have that detail, or it isn't known where the error happened.
+pub trait DomError: std::error::Error {
+    /// What kind of error this is.
+    fn code(&self) -> ErrorCode;
+
+    /// Where in the file being read the error happened.
+    fn location(&self) -> Option<ErrorLocation> {
+        None
+    }
+
+    /// The instance the error is about.
+    fn referent(&self) -> Option<Ref> {
+        None
+    }
+
+    /// The ClassName of the instance the error is about.
+    fn class_name(&self) -> Option<&str> {
+        None
+    }
+
+    /// The name of the property the error is about, as it appears in the
+    /// file for errors that happened while reading one.
+    fn property_name(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// A machine-readable kind of error, returned by [`DomError::code`]. Codes
+/// stay the same when error messages are reworded, and have a stable name
+/// from [`as_str`](ErrorCode::as_str) for logging or sending elsewhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// Reading or writing failed.
+    Io,
+
+    /// Decoding was cancelled by its caller.
+    Cancelled,
+
+    /// The file went over one of the limits the decoder was given.
+    LimitExceeded,
+
+    /// The file didn't start with the header of the format being read.
+    InvalidHeader,
+
+    /// The file, or part of it, is from a version of the format that isn't
+    /// understood.
+    UnknownVersion,
+
+    /// The file ended in the middle of something.
+    UnexpectedEof,
+
+    /// The file isn't well-formed XML.
+    Xml,
+
+    /// An XML document had an element or text where it shouldn't.
+    UnexpectedElement,
+
+    /// An XML element was missing an attribute it needs.
+    MissingAttribute,
+
+    /// A binary file contained a chunk that isn't understood.
+    UnknownChunk,
+
+    /// Data was left over at the end of a chunk.
+    TrailingData,
+
+    /// A binary file claimed to contain more values than could fit.
+    CountTooLarge,
+
+    /// A binary file referred to a class that wasn't declared.
+    UnknownTypeId,
+
+    /// An instance was referred to that doesn't exist, like a Ref property
+    /// pointing to a referent that no instance has.
+    UnknownReferent,
+
+    /// The same referent was given to more than one instance.
+    DuplicateReferent,
+
+    /// A property isn't in the reflection database.
+    UnknownProperty,
+
+    /// A property has a type that can't be read or written.
+    UnsupportedPropertyType,
+
+    /// A property has a different type than the reflection database gives
+    /// it, and couldn't be converted.
+    PropertyTypeMismatch,
+
+    /// A property's value couldn't be read or written.
+    InvalidPropertyValue,
+
+    /// An Enum property has a value that isn't one of its enum's items.
+    UnknownEnumValue,
+
+    /// A property refers to a SharedString that the file doesn't contain.
+    MissingSharedString,
+
+    /// A Ref property points to an instance that isn't being written.
+    ExternalReference,
+
+    /// A property couldn't be migrated to the property that replaced it.
+    Migration,
+
+    /// A custom codec failed to read or write a property.
+    Codec,
+
+    /// An unknown element that was kept from reading a file couldn't be
+    /// written back out.
+    UnknownElement,
+
+    /// A streaming encoder was given events in an order that doesn't make a
+    /// valid document, like a property outside of any instance.
+    InvalidStreamEvent,
+
+    /// Something that would only be a warning was found while decoding in
+    /// strict mode.
+    StrictWarning,
+
+    /// Attributes couldn't be decoded or encoded.
+    InvalidAttributes,
+
+    /// A CFrame had a rotation that isn't valid.
+    InvalidRotation,
+
+    /// PhysicalConfigData couldn't be decoded.
+    InvalidPhysicalConfig,
+
+    /// SmoothGrid terrain data couldn't be decoded.
+    InvalidSmoothGrid,
+}
+
+impl ErrorCode {
+    /// The name of this code in `snake_case`, like `limit_exceeded`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::Io => "io",
+            ErrorCode::Cancelled => "cancelled",
+            ErrorCode::LimitExceeded => "limit_exceeded",
+            ErrorCode::InvalidHeader => "invalid_header",
+            ErrorCode::UnknownVersion => "unknown_version",
+            ErrorCode::UnexpectedEof => "unexpected_eof",
+            ErrorCode::Xml => "xml",
+            ErrorCode::UnexpectedElement => "unexpected_element",
+            ErrorCode::MissingAttribute => "missing_attribute",
+            ErrorCode::UnknownChunk => "unknown_chunk",
+            ErrorCode::TrailingData => "trailing_data",
+            ErrorCode::CountTooLarge => "count_too_large",
+            ErrorCode::UnknownTypeId => "unknown_type_id",
+            ErrorCode::UnknownReferent => "unknown_referent",
+            ErrorCode::DuplicateReferent => "duplicate_referent",
+            ErrorCode::UnknownProperty => "unknown_property",
+            ErrorCode::UnsupportedPropertyType => "unsupported_property_type",
+            ErrorCode::PropertyTypeMismatch => "property_type_mismatch",
+            ErrorCode::InvalidPropertyValue => "invalid_property_value",
+            ErrorCode::UnknownEnumValue => "unknown_enum_value",
+            ErrorCode::MissingSharedString => "missing_shared_string",
+            ErrorCode::ExternalReference => "external_reference",
+            ErrorCode::Migration => "migration",
+            ErrorCode::Codec => "codec",
+            ErrorCode::UnknownElement => "unknown_element",
+            ErrorCode::InvalidStreamEvent => "invalid_stream_event",
+            ErrorCode::StrictWarning => "strict_warning",
+            ErrorCode::InvalidAttributes => "invalid_attributes",
+            ErrorCode::InvalidRotation => "invalid_rotation",
+            ErrorCode::InvalidPhysicalConfig => "invalid_physical_config",
+            ErrorCode::InvalidSmoothGrid => "invalid_smooth_grid",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+/// Where in a file an error happened, returned by [`DomError::location`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorLocation {
+    /// In a chunk of a binary file.
+    Chunk {
+        /// The index of the chunk, counting from zero.
+        index: usize,
+
+        /// The byte offset of the start of the chunk from the start of the
+        /// file.
+        offset: u64,
+
+        /// How many bytes into the chunk's decompressed contents the error
+        /// happened, if they could be read.
+        position: Option<usize>,
+    },
+
+    /// At a position in an XML document.
+    LineColumn {
+        /// The line, starting at 1.
+        line: usize,
+
+        /// The column, starting at 1.
+        column: usize,
+    },
+}
+
+impl fmt::Display for ErrorLocation {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorLocation::Chunk {
+                index,
+                offset,
+                position: Some(position),
+            } => write!(
+                formatter,
+                "chunk {} at byte {}, {} bytes into its contents",
+                index, offset, position
+            ),
+            ErrorLocation::Chunk { index, offset, .. } => {
+                write!(formatter, "chunk {} at byte {}", index, offset)
+            }
+            ErrorLocation::LineColumn { line, column } => {
+                write!(formatter, "line {}, column {}", line, column)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn codes() {
+        let error = Error::from(AttributeError::InvalidValueType(0xff));
+        assert_eq!(error.code(), ErrorCode::InvalidAttributes);
+        assert_eq!(error.location(), None);
+        assert_eq!(error.code().to_string(), "invalid_attributes");
+    }
+
+    #[test]
+    fn locations() {
+        let chunk = ErrorLocation::Chunk {
+            index: 2,
+            offset: 300,
+            position: Some(4),
+        };
+        assert_eq!(
+            chunk.to_string(),
+            "chunk 2 at byte 300, 4 bytes into its contents"
+        );
+
+        let line_column = ErrorLocation::LineColumn { line: 3, column: 7 };
+        assert_eq!(line_column.to_string(), "line 3, column 7");
+    }
 }
@@ -238,8 +238,8 @@ mod serde_impl {
         fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
             let mut flags = FaceFlags::empty();
 
-            while let Some(face_str) = seq.next_element::<&str>()? {
-                match face_str {
+            while let Some(face_str) = seq.next_element::<String>()? {
+                match face_str.as_str() {
                     "Right" => flags |= FaceFlags::RIGHT,
                     "Top" => flags |= FaceFlags::TOP,
                     "Back" => flags |= FaceFlags::BACK,
@@ -170,10 +170,10 @@ impl LuaUserData for Font {
         fields.add_field_method_get("Weight", |_lua, this| Ok(this.weight));
         fields.add_field_method_get("Style", |_lua, this| Ok(this.style));
         fields.add_field_method_get("Bold", |_lua, this| {
-            Ok(match this.weight {
-                FontWeight::SemiBold | FontWeight::Bold | FontWeight::ExtraBold => true,
-                _ => false,
-            })
+            Ok(matches!(
+                this.weight,
+                FontWeight::SemiBold | FontWeight::Bold | FontWeight::ExtraBold
+            ))
         });
     }
 }
@@ -12,9 +12,12 @@ mod error;
 mod faces;
 mod font;
 mod lister;
+mod physical_config;
 mod physical_properties;
 mod referent;
+mod security_capabilities;
 mod shared_string;
+mod smooth_grid;
 mod tags;
 mod unique_id;
 mod variant;
@@ -28,9 +31,12 @@ pub use content::*;
 pub use error::*;
 pub use faces::*;
 pub use font::*;
+pub use physical_config::*;
 pub use physical_properties::*;
 pub use referent::*;
+pub use security_capabilities::*;
 pub use shared_string::*;
+pub use smooth_grid::*;
 pub use tags::*;
 pub use unique_id::*;
 pub use variant::*;
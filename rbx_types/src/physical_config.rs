@@ -0,0 +1,158 @@
+use std::convert::TryFrom;
+
+use thiserror::Error;
+
+use crate::{Enum, Error as CrateError};
+
+const MAGIC: &[u8] = b"CSGPHS";
+
+/// The collision geometry of a MeshPart or PartOperation, stored in its
+/// `PhysicalConfigData` property.
+///
+/// The data starts with the magic bytes `CSGPHS` and a little-endian 32-bit
+/// version number. The layout of the rest of the data depends on the version
+/// and the part's [`CollisionFidelity`], and is kept as an opaque payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhysicalConfigData {
+    version: u32,
+    payload: Vec<u8>,
+}
+
+impl PhysicalConfigData {
+    /// Creates a `PhysicalConfigData` from its version and the data that
+    /// follows the header.
+    pub fn new(version: u32, payload: Vec<u8>) -> Self {
+        Self { version, payload }
+    }
+
+    /// Parses the contents of a `PhysicalConfigData` property.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CrateError> {
+        let rest = data
+            .strip_prefix(MAGIC)
+            .ok_or(PhysicalConfigError::BadMagic)?;
+
+        if rest.len() < 4 {
+            return Err(PhysicalConfigError::Truncated.into());
+        }
+
+        let (version, payload) = rest.split_at(4);
+        let version = u32::from_le_bytes(<[u8; 4]>::try_from(version).unwrap());
+
+        Ok(Self {
+            version,
+            payload: payload.to_vec(),
+        })
+    }
+
+    /// Encodes this `PhysicalConfigData` into the form stored in the
+    /// `PhysicalConfigData` property.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(MAGIC.len() + 4 + self.payload.len());
+        output.extend_from_slice(MAGIC);
+        output.extend_from_slice(&self.version.to_le_bytes());
+        output.extend_from_slice(&self.payload);
+        output
+    }
+
+    /// The format version of this collision geometry.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// The data following the header, whose layout depends on the version.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Whether this data contains no geometry beyond its header.
+    pub fn is_empty(&self) -> bool {
+        self.payload.is_empty()
+    }
+}
+
+/// How closely the collision geometry of a MeshPart or PartOperation follows
+/// its visual geometry, corresponding to the `CollisionFidelity` enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CollisionFidelity {
+    Default,
+    Hull,
+    Box,
+    PreciseConvexDecomposition,
+}
+
+impl CollisionFidelity {
+    /// Converts the value of a `CollisionFidelity` property, returning `None`
+    /// if it isn't a known fidelity.
+    pub fn from_enum(value: Enum) -> Option<Self> {
+        Some(match value.to_u32() {
+            0 => Self::Default,
+            1 => Self::Hull,
+            2 => Self::Box,
+            3 => Self::PreciseConvexDecomposition,
+            _ => return None,
+        })
+    }
+}
+
+impl From<CollisionFidelity> for Enum {
+    fn from(value: CollisionFidelity) -> Self {
+        Enum::from_u32(match value {
+            CollisionFidelity::Default => 0,
+            CollisionFidelity::Hull => 1,
+            CollisionFidelity::Box => 2,
+            CollisionFidelity::PreciseConvexDecomposition => 3,
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum PhysicalConfigError {
+    #[error("PhysicalConfigData did not start with CSGPHS")]
+    BadMagic,
+
+    #[error("PhysicalConfigData ended before its version")]
+    Truncated,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let data = PhysicalConfigData::new(6, vec![1, 2, 3]);
+        let encoded = data.to_bytes();
+
+        assert_eq!(&encoded[..10], b"CSGPHS\x06\0\0\0");
+        assert_eq!(PhysicalConfigData::from_bytes(&encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(PhysicalConfigData::from_bytes(b"").is_err());
+        assert!(PhysicalConfigData::from_bytes(b"CSGK\0\0\0\0\0\0").is_err());
+        assert!(PhysicalConfigData::from_bytes(b"CSGPHS\x03\0").is_err());
+
+        let empty = PhysicalConfigData::from_bytes(b"CSGPHS\0\0\0\0").unwrap();
+        assert_eq!(empty.version(), 0);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn collision_fidelity() {
+        for fidelity in [
+            CollisionFidelity::Default,
+            CollisionFidelity::Hull,
+            CollisionFidelity::Box,
+            CollisionFidelity::PreciseConvexDecomposition,
+        ] {
+            assert_eq!(
+                CollisionFidelity::from_enum(fidelity.into()),
+                Some(fidelity)
+            );
+        }
+
+        assert_eq!(CollisionFidelity::from_enum(Enum::from_u32(4)), None);
+    }
+}
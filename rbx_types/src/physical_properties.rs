@@ -40,6 +40,7 @@ pub struct CustomPhysicalProperties {
     pub elasticity_weight: f32,
 }
 
+#[cfg(feature = "mlua")]
 impl<'lua> FromLua<'lua> for PhysicalProperties {
     fn from_lua(value: LuaValue<'lua>, _lua: &'lua Lua) -> LuaResult<Self> {
         let LuaValue::UserData(value) = value else {
@@ -61,6 +62,7 @@ impl<'lua> FromLua<'lua> for PhysicalProperties {
     }
 }
 
+#[cfg(feature = "mlua")]
 impl LuaUserData for PhysicalProperties {
     fn add_fields<'lua, F: LuaUserDataFields<'lua, Self>>(fields: &mut F) {
         // Note: at least in Roblox studio, Default materials seem to return 0.0 for every field.
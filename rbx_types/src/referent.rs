@@ -95,6 +95,12 @@ mod serde_impl {
             Ok(Ref(NonZeroU128::new(value)))
         }
 
+        // Some formats, like CBOR, write a u128 that fits in a u64 the same way
+        // as any other integer, and read it back as a u64.
+        fn visit_u64<E: Error>(self, value: u64) -> Result<Self::Value, E> {
+            self.visit_u128(value.into())
+        }
+
         fn visit_str<E: Error>(self, ref_str: &str) -> Result<Self::Value, E> {
             let ref_value = u128::from_str_radix(ref_str, 16).map_err(E::custom)?;
             Ok(Ref(NonZeroU128::new(ref_value)))
@@ -126,7 +132,7 @@ mod test {
         let thirty = Ref(NonZeroU128::new(30));
         assert_eq!(thirty.to_string(), "0000000000000000000000000000001e");
 
-        let max = Ref(NonZeroU128::new(u128::max_value()));
+        let max = Ref(NonZeroU128::new(u128::MAX));
         assert_eq!(max.to_string(), "ffffffffffffffffffffffffffffffff");
     }
 
@@ -144,7 +150,7 @@ mod test {
 
         assert_eq!(
             Ref::from_str("ffffffffffffffffffffffffffffffff").unwrap(),
-            Ref(NonZeroU128::new(u128::max_value()))
+            Ref(NonZeroU128::new(u128::MAX))
         );
     }
 
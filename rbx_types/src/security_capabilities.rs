@@ -0,0 +1,62 @@
+/// The set of security capabilities granted to an instance, like a script or
+/// the container it runs in.
+///
+/// Roblox stores capabilities as a 64-bit bitfield. rbx_types doesn't assign
+/// meaning to individual bits, since Roblox adds new capabilities regularly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct SecurityCapabilities {
+    bits: u64,
+}
+
+impl SecurityCapabilities {
+    /// Creates a `SecurityCapabilities` with no capabilities set.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a `SecurityCapabilities` from its raw bitfield.
+    #[inline]
+    pub fn from_bits(bits: u64) -> Self {
+        Self { bits }
+    }
+
+    /// Returns the raw bitfield of this `SecurityCapabilities`.
+    #[inline]
+    pub fn bits(self) -> u64 {
+        self.bits
+    }
+
+    /// Returns whether every capability set in `other` is also set in `self`.
+    #[inline]
+    pub fn contains(self, other: Self) -> bool {
+        self.bits & other.bits == other.bits
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn contains() {
+        let capabilities = SecurityCapabilities::from_bits(0b1011);
+
+        assert!(capabilities.contains(SecurityCapabilities::from_bits(0b0011)));
+        assert!(capabilities.contains(SecurityCapabilities::new()));
+        assert!(!capabilities.contains(SecurityCapabilities::from_bits(0b0100)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn human_de() {
+        let capabilities: SecurityCapabilities = serde_json::from_str("12").unwrap();
+
+        assert_eq!(capabilities, SecurityCapabilities::from_bits(12));
+    }
+}
@@ -0,0 +1,19 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub(crate) enum SmoothGridError {
+    #[error("unknown SmoothGrid version {version} with chunk size exponent {chunk_size}")]
+    UnknownVersion { version: u8, chunk_size: u8 },
+
+    #[error("terrain chunk at {x}, {y}, {z} ended before all of its voxels were read")]
+    TruncatedChunk { x: i32, y: i32, z: i32 },
+
+    #[error("a run of voxels extended past the end of the chunk at {x}, {y}, {z}")]
+    RunOverflow { x: i32, y: i32, z: i32 },
+
+    #[error("terrain chunk position was too large")]
+    PositionOverflow,
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
@@ -0,0 +1,395 @@
+//! Terrain stores its voxels in the `SmoothGrid` property, a BinaryString
+//! with the following layout:
+//!
+//! * A two byte header: the version (`1`), then the base-2 logarithm of the
+//!   width of a chunk in voxels (`5`, for 32 voxel wide chunks).
+//! * Chunks, until the end of the data. Each chunk starts with its position
+//!   in chunks as three zigzag-encoded LEB128 varints (X, Y, then Z), each
+//!   relative to the position of the chunk before it.
+//! * The voxels of the chunk, X changing fastest and Z slowest, as runs of
+//!   identical voxels. A run is a byte holding the material in its low six
+//!   bits, then an occupancy byte if bit 6 is set, then a byte holding the
+//!   run length minus one if bit 7 is set. Runs without an occupancy byte are
+//!   completely full, or completely empty for air. Runs without a length byte
+//!   are one voxel long.
+
+mod error;
+mod reader;
+mod writer;
+
+use std::io::{Read, Write};
+
+use crate::Error;
+
+use self::reader::read_smooth_grid;
+use self::writer::write_smooth_grid;
+
+pub(crate) use self::error::SmoothGridError;
+
+const VERSION: u8 = 1;
+const CHUNK_SIZE_EXPONENT: u8 = 5;
+
+/// The width, height, and depth of a terrain chunk in voxels.
+pub const CHUNK_SIZE: usize = 1 << CHUNK_SIZE_EXPONENT;
+
+const CHUNK_VOLUME: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+
+/// The voxels of a Terrain instance, decoded from its `SmoothGrid` property.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SmoothGrid {
+    chunks: Vec<TerrainChunk>,
+}
+
+impl SmoothGrid {
+    /// Creates a `SmoothGrid` with no chunks, which is terrain made entirely
+    /// of air.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads from a serialized SmoothGrid, and produces a new `SmoothGrid`
+    /// from it.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, Error> {
+        Ok(Self {
+            chunks: read_smooth_grid(reader)?,
+        })
+    }
+
+    /// Writes the SmoothGrid in its serialized form to the writer.
+    pub fn to_writer<W: Write>(&self, writer: W) -> Result<(), Error> {
+        write_smooth_grid(&self.chunks, writer).map_err(|err| SmoothGridError::from(err).into())
+    }
+
+    /// Returns the chunks of this grid, in the order they are stored.
+    pub fn chunks(&self) -> &[TerrainChunk] {
+        &self.chunks
+    }
+
+    /// Returns the chunk at the given position, in chunks.
+    pub fn chunk(&self, position: ChunkPosition) -> Option<&TerrainChunk> {
+        self.chunks.iter().find(|chunk| chunk.position == position)
+    }
+
+    /// Returns the chunk at the given position, in chunks.
+    pub fn chunk_mut(&mut self, position: ChunkPosition) -> Option<&mut TerrainChunk> {
+        self.chunks
+            .iter_mut()
+            .find(|chunk| chunk.position == position)
+    }
+
+    /// Inserts a chunk, returning the chunk that was at the same position if
+    /// there was one.
+    pub fn insert_chunk(&mut self, chunk: TerrainChunk) -> Option<TerrainChunk> {
+        match self.chunk_mut(chunk.position) {
+            Some(existing) => Some(std::mem::replace(existing, chunk)),
+            None => {
+                self.chunks.push(chunk);
+                None
+            }
+        }
+    }
+
+    /// Removes the chunk at the given position, in chunks.
+    pub fn remove_chunk(&mut self, position: ChunkPosition) -> Option<TerrainChunk> {
+        let index = self
+            .chunks
+            .iter()
+            .position(|chunk| chunk.position == position)?;
+
+        Some(self.chunks.remove(index))
+    }
+
+    /// Returns the voxel at the given position, in voxels. Voxels outside of
+    /// any chunk are air.
+    pub fn voxel(&self, x: i32, y: i32, z: i32) -> Voxel {
+        let (position, local) = split_voxel_position(x, y, z);
+
+        match self.chunk(position) {
+            Some(chunk) => chunk.voxel(local[0], local[1], local[2]),
+            None => Voxel::AIR,
+        }
+    }
+}
+
+/// The position of a terrain chunk, measured in chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ChunkPosition {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl ChunkPosition {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+}
+
+/// A cube of [`CHUNK_SIZE`] voxels on each side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerrainChunk {
+    position: ChunkPosition,
+    voxels: Vec<Voxel>,
+}
+
+impl TerrainChunk {
+    /// Creates a chunk made entirely of air.
+    pub fn new(position: ChunkPosition) -> Self {
+        Self {
+            position,
+            voxels: vec![Voxel::AIR; CHUNK_VOLUME],
+        }
+    }
+
+    /// The position of this chunk, in chunks.
+    pub fn position(&self) -> ChunkPosition {
+        self.position
+    }
+
+    /// Returns the voxel at the given position within this chunk.
+    ///
+    /// ## Panics
+    /// Panics if any coordinate is not less than [`CHUNK_SIZE`].
+    pub fn voxel(&self, x: usize, y: usize, z: usize) -> Voxel {
+        self.voxels[voxel_index(x, y, z)]
+    }
+
+    /// Replaces the voxel at the given position within this chunk.
+    ///
+    /// ## Panics
+    /// Panics if any coordinate is not less than [`CHUNK_SIZE`].
+    pub fn set_voxel(&mut self, x: usize, y: usize, z: usize, voxel: Voxel) {
+        self.voxels[voxel_index(x, y, z)] = voxel;
+    }
+
+    /// Returns every voxel in this chunk, X changing fastest and Z slowest.
+    pub fn voxels(&self) -> &[Voxel] {
+        &self.voxels
+    }
+
+    /// Returns whether every voxel in this chunk is empty.
+    pub fn is_empty(&self) -> bool {
+        self.voxels.iter().all(|voxel| voxel.occupancy == 0)
+    }
+}
+
+/// A single terrain voxel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Voxel {
+    /// The ID of this voxel's material. See [`TerrainMaterial`] for the IDs
+    /// of known materials.
+    pub material: u8,
+
+    /// How full this voxel is, from 0 for empty to 255 for full.
+    pub occupancy: u8,
+}
+
+impl Voxel {
+    /// An empty voxel.
+    pub const AIR: Voxel = Voxel {
+        material: 0,
+        occupancy: 0,
+    };
+
+    pub fn new(material: u8, occupancy: u8) -> Self {
+        Self {
+            material,
+            occupancy,
+        }
+    }
+
+    /// A completely full voxel of the given material.
+    pub fn full(material: TerrainMaterial) -> Self {
+        Self::new(material as u8, 255)
+    }
+
+    /// The material of this voxel, if it's one rbx_types knows about.
+    pub fn terrain_material(&self) -> Option<TerrainMaterial> {
+        TerrainMaterial::from_id(self.material)
+    }
+
+    /// The occupancy of runs of `material` stored without an occupancy byte.
+    fn default_occupancy(material: u8) -> u8 {
+        if material == 0 {
+            0
+        } else {
+            255
+        }
+    }
+
+    fn default_occupancy_for_material(&self) -> u8 {
+        Self::default_occupancy(self.material)
+    }
+}
+
+/// The materials terrain voxels can be made of, with their IDs in the
+/// SmoothGrid format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+#[non_exhaustive]
+pub enum TerrainMaterial {
+    Air = 0,
+    Water = 1,
+    Grass = 2,
+    Slate = 3,
+    Concrete = 4,
+    Brick = 5,
+    Sand = 6,
+    WoodPlanks = 7,
+    Rock = 8,
+    Glacier = 9,
+    Snow = 10,
+    Sandstone = 11,
+    Mud = 12,
+    Basalt = 13,
+    Ground = 14,
+    CrackedLava = 15,
+    Asphalt = 16,
+    Cobblestone = 17,
+    Ice = 18,
+    LeafyGrass = 19,
+    Salt = 20,
+    Limestone = 21,
+    Pavement = 22,
+}
+
+impl TerrainMaterial {
+    /// Returns the material with the given SmoothGrid ID.
+    pub fn from_id(id: u8) -> Option<Self> {
+        use TerrainMaterial::*;
+
+        Some(match id {
+            0 => Air,
+            1 => Water,
+            2 => Grass,
+            3 => Slate,
+            4 => Concrete,
+            5 => Brick,
+            6 => Sand,
+            7 => WoodPlanks,
+            8 => Rock,
+            9 => Glacier,
+            10 => Snow,
+            11 => Sandstone,
+            12 => Mud,
+            13 => Basalt,
+            14 => Ground,
+            15 => CrackedLava,
+            16 => Asphalt,
+            17 => Cobblestone,
+            18 => Ice,
+            19 => LeafyGrass,
+            20 => Salt,
+            21 => Limestone,
+            22 => Pavement,
+            _ => return None,
+        })
+    }
+}
+
+fn voxel_index(x: usize, y: usize, z: usize) -> usize {
+    assert!(
+        x < CHUNK_SIZE && y < CHUNK_SIZE && z < CHUNK_SIZE,
+        "voxel position {}, {}, {} is outside of the chunk",
+        x,
+        y,
+        z
+    );
+
+    x + CHUNK_SIZE * (y + CHUNK_SIZE * z)
+}
+
+/// Splits a position in voxels into the position of its chunk and its
+/// position within that chunk.
+fn split_voxel_position(x: i32, y: i32, z: i32) -> (ChunkPosition, [usize; 3]) {
+    let size = CHUNK_SIZE as i32;
+    let position = ChunkPosition::new(x.div_euclid(size), y.div_euclid(size), z.div_euclid(size));
+    let local = [
+        x.rem_euclid(size) as usize,
+        y.rem_euclid(size) as usize,
+        z.rem_euclid(size) as usize,
+    ];
+
+    (position, local)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(grid: &SmoothGrid) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        grid.to_writer(&mut buffer).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn empty_grid() {
+        let encoded = encode(&SmoothGrid::new());
+        assert_eq!(encoded, [1, 5]);
+
+        let decoded = SmoothGrid::from_reader(encoded.as_slice()).unwrap();
+        assert_eq!(decoded, SmoothGrid::new());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut chunk = TerrainChunk::new(ChunkPosition::new(-1, 0, 2));
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                chunk.set_voxel(x, 0, z, Voxel::full(TerrainMaterial::Grass));
+            }
+        }
+        chunk.set_voxel(3, 1, 4, Voxel::new(TerrainMaterial::Water as u8, 100));
+
+        let mut grid = SmoothGrid::new();
+        grid.insert_chunk(chunk);
+        grid.insert_chunk(TerrainChunk::new(ChunkPosition::new(5, -3, 0)));
+
+        let encoded = encode(&grid);
+        let decoded = SmoothGrid::from_reader(encoded.as_slice()).unwrap();
+        assert_eq!(decoded, grid);
+
+        assert_eq!(
+            decoded.voxel(-32, 0, 64).terrain_material(),
+            Some(TerrainMaterial::Grass)
+        );
+        assert_eq!(decoded.voxel(-29, 1, 68).occupancy, 100);
+        assert_eq!(decoded.voxel(1000, 1000, 1000), Voxel::AIR);
+        assert!(decoded
+            .chunk(ChunkPosition::new(5, -3, 0))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn run_encoding() {
+        // A single full chunk of rock is 128 runs of 256 voxels, each stored
+        // as a flags byte and a length byte.
+        let mut chunk = TerrainChunk::new(ChunkPosition::new(0, 0, 0));
+        chunk.voxels = vec![Voxel::full(TerrainMaterial::Rock); CHUNK_VOLUME];
+
+        let mut grid = SmoothGrid::new();
+        grid.insert_chunk(chunk);
+
+        let encoded = encode(&grid);
+        assert_eq!(encoded.len(), 2 + 3 + 128 * 2);
+        assert_eq!(&encoded[5..7], &[0b1000_0000 | 8, 255]);
+    }
+
+    #[test]
+    fn invalid_data() {
+        assert!(SmoothGrid::from_reader(&[2, 5][..]).is_err());
+
+        // A chunk whose only run is 256 voxels long.
+        assert!(SmoothGrid::from_reader(&[1, 5, 0, 0, 0, 0b1000_0000, 255][..]).is_err());
+
+        // A run that doesn't fit in the space left in the chunk.
+        let mut overflowing = vec![1, 5, 0, 0, 0];
+        for _ in 0..128 {
+            overflowing.extend_from_slice(&[0b1000_0000, 254]);
+        }
+        overflowing.extend_from_slice(&[0b1000_0000, 255]);
+        assert!(SmoothGrid::from_reader(overflowing.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    convert::TryFrom,
+    io::{self, Read},
+};
+
+use super::{ChunkPosition, SmoothGridError, TerrainChunk, Voxel, CHUNK_VOLUME};
+
+/// Reads every chunk from a serialized SmoothGrid.
+pub(crate) fn read_smooth_grid<R: Read>(
+    mut input: R,
+) -> Result<Vec<TerrainChunk>, SmoothGridError> {
+    let mut header = [0; 2];
+    input.read_exact(&mut header)?;
+
+    if header != [super::VERSION, super::CHUNK_SIZE_EXPONENT] {
+        return Err(SmoothGridError::UnknownVersion {
+            version: header[0],
+            chunk_size: header[1],
+        });
+    }
+
+    let mut chunks = Vec::new();
+    let mut last = ChunkPosition::new(0, 0, 0);
+
+    // Chunks continue until the end of the input, so the only way to know
+    // we're done is failing to read the start of another chunk.
+    while let Some(dx) = read_first_varint(&mut input)? {
+        let position = ChunkPosition::new(
+            offset(last.x, dx)?,
+            offset(last.y, read_varint(&mut input)?)?,
+            offset(last.z, read_varint(&mut input)?)?,
+        );
+
+        chunks.push(read_chunk(&mut input, position)?);
+        last = position;
+    }
+
+    Ok(chunks)
+}
+
+fn read_chunk<R: Read>(
+    input: &mut R,
+    position: ChunkPosition,
+) -> Result<TerrainChunk, SmoothGridError> {
+    let truncated = |_| SmoothGridError::TruncatedChunk {
+        x: position.x,
+        y: position.y,
+        z: position.z,
+    };
+
+    let mut voxels = Vec::with_capacity(CHUNK_VOLUME);
+
+    while voxels.len() < CHUNK_VOLUME {
+        let flags = read_u8(input).map_err(truncated)?;
+        let material = flags & 0b0011_1111;
+
+        let occupancy = if flags & 0b0100_0000 != 0 {
+            read_u8(input).map_err(truncated)?
+        } else {
+            Voxel::default_occupancy(material)
+        };
+
+        let count = if flags & 0b1000_0000 != 0 {
+            read_u8(input).map_err(truncated)? as usize + 1
+        } else {
+            1
+        };
+
+        if voxels.len() + count > CHUNK_VOLUME {
+            return Err(SmoothGridError::RunOverflow {
+                x: position.x,
+                y: position.y,
+                z: position.z,
+            });
+        }
+
+        voxels.extend(std::iter::repeat_n(Voxel::new(material, occupancy), count));
+    }
+
+    Ok(TerrainChunk { position, voxels })
+}
+
+fn offset(base: i32, delta: i64) -> Result<i32, SmoothGridError> {
+    i32::try_from(base as i64 + delta).map_err(|_| SmoothGridError::PositionOverflow)
+}
+
+/// Reads a varint, returning `None` if the input ends before it starts.
+fn read_first_varint<R: Read>(input: &mut R) -> Result<Option<i64>, SmoothGridError> {
+    let mut first = [0];
+    if input.read(&mut first)? == 0 {
+        return Ok(None);
+    }
+
+    read_varint_from(first[0], input).map(Some)
+}
+
+fn read_varint<R: Read>(input: &mut R) -> Result<i64, SmoothGridError> {
+    let first = read_u8(input)?;
+    read_varint_from(first, input)
+}
+
+/// Reads the rest of a zigzag-encoded LEB128 varint whose first byte is
+/// `first`.
+fn read_varint_from<R: Read>(first: u8, input: &mut R) -> Result<i64, SmoothGridError> {
+    let mut value = 0u64;
+    let mut byte = first;
+    let mut shift = 0;
+
+    loop {
+        if shift >= 64 {
+            return Err(SmoothGridError::PositionOverflow);
+        }
+
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+
+        shift += 7;
+        byte = read_u8(input)?;
+    }
+
+    Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+}
+
+fn read_u8<R: Read>(input: &mut R) -> io::Result<u8> {
+    let mut buffer = [0];
+    input.read_exact(&mut buffer)?;
+    Ok(buffer[0])
+}
@@ -0,0 +1,73 @@
+use std::io::{self, Write};
+
+use super::{ChunkPosition, TerrainChunk};
+
+/// Writes `chunks` as a serialized SmoothGrid.
+pub(crate) fn write_smooth_grid<W: Write>(
+    chunks: &[TerrainChunk],
+    mut output: W,
+) -> io::Result<()> {
+    output.write_all(&[super::VERSION, super::CHUNK_SIZE_EXPONENT])?;
+
+    let mut last = ChunkPosition::new(0, 0, 0);
+
+    for chunk in chunks {
+        let position = chunk.position;
+        write_varint(&mut output, position.x as i64 - last.x as i64)?;
+        write_varint(&mut output, position.y as i64 - last.y as i64)?;
+        write_varint(&mut output, position.z as i64 - last.z as i64)?;
+
+        write_chunk(&mut output, chunk)?;
+        last = position;
+    }
+
+    Ok(())
+}
+
+fn write_chunk<W: Write>(output: &mut W, chunk: &TerrainChunk) -> io::Result<()> {
+    let mut voxels = chunk.voxels.iter().peekable();
+
+    while let Some(&voxel) = voxels.next() {
+        // Runs can be at most 256 voxels long since their length is stored
+        // in a single byte.
+        let mut count = 1;
+        while count < 256 && voxels.peek() == Some(&&voxel) {
+            voxels.next();
+            count += 1;
+        }
+
+        let mut flags = voxel.material & 0b0011_1111;
+        if voxel.occupancy != voxel.default_occupancy_for_material() {
+            flags |= 0b0100_0000;
+        }
+        if count > 1 {
+            flags |= 0b1000_0000;
+        }
+
+        output.write_all(&[flags])?;
+        if flags & 0b0100_0000 != 0 {
+            output.write_all(&[voxel.occupancy])?;
+        }
+        if count > 1 {
+            output.write_all(&[(count - 1) as u8])?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes `value` as a zigzag-encoded LEB128 varint.
+fn write_varint<W: Write>(output: &mut W, value: i64) -> io::Result<()> {
+    let mut value = ((value << 1) ^ (value >> 63)) as u64;
+
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            return output.write_all(&[byte]);
+        }
+
+        output.write_all(&[byte | 0x80])?;
+    }
+}
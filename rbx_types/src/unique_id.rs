@@ -19,6 +19,18 @@ lazy_static! {
     pub static ref EPOCH: SystemTime = UNIX_EPOCH - Duration::from_secs(EPOCH_AS_UNIX);
 }
 
+/// The current time. `SystemTime::now` panics on wasm32-unknown-unknown, so
+/// the time is taken from JavaScript there instead.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown", feature = "js")))]
+fn system_now() -> SystemTime {
+    SystemTime::now()
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown", feature = "js"))]
+fn system_now() -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs_f64(js_sys::Date::now() / 1000.0)
+}
+
 /// Represents an error that can occur when constructing a new `UniqueId`.
 #[derive(Debug, Error)]
 pub enum UniqueIdError {
@@ -55,7 +67,7 @@ impl UniqueId {
     }
 
     pub fn now() -> Result<Self, UniqueIdError> {
-        let time = SystemTime::now()
+        let time = system_now()
             .duration_since(*EPOCH)
             .map_err(|_| UniqueIdError::SystemPastTime)?;
 
@@ -227,10 +239,10 @@ mod test {
         let de: UniqueId = bincode::deserialize(&ser).unwrap();
 
         // Bincode prefixes vectors with the vector's length as a little-endian `u64`
-        assert_eq!(ser[0..8].as_ref(), 16_u64.to_le_bytes());
+        assert_eq!(&ser[0..8], 16_u64.to_le_bytes());
 
         assert_eq!(
-            ser[8..].as_ref(),
+            &ser[8..],
             b"\x10\x20\x30\x40\x50\x60\x70\x80\xfa\xca\xde\x00\x13\x37\x00\x00"
         );
         assert_eq!(de, uid);
@@ -0,0 +1,686 @@
+//! Checking the health of a place: what's in it, what's likely to be a
+//! problem, and where its size comes from.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use anyhow::Context;
+use rbx_content::{ContentResolver, ResolveError};
+use rbx_dom_weak::{
+    types::{Ref, SharedStringHash, Variant},
+    Instance, WeakDom,
+};
+use rbx_reflection::ReflectionDatabase;
+use serde::Serialize;
+
+use crate::view::full_name;
+
+/// What [`analyze`] looks at beyond the DOM itself.
+pub struct AnalyzeOptions<'a> {
+    /// Where to get textures from to measure them, if anywhere.
+    pub assets: Option<&'a dyn ContentResolver>,
+
+    /// How many pixels wide or tall a texture can be before it's reported.
+    pub max_texture_size: u32,
+
+    /// How many levels below the root to report serialized sizes for. `1`
+    /// reports the size of each service.
+    pub depth: usize,
+}
+
+/// The results of [`analyze`].
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Report {
+    /// The number of instances in the DOM, not counting the root.
+    pub instances: usize,
+
+    /// Each child of the root, in order.
+    pub services: Vec<ServiceReport>,
+
+    /// How many scripts of each kind there are: `Server`, `Client`, `Plugin`,
+    /// or `Legacy` for Scripts by their RunContext, `Client` for
+    /// LocalScripts, and `Module` for ModuleScripts.
+    pub scripts: BTreeMap<String, usize>,
+
+    /// SharedStrings used by more than one instance, largest first.
+    pub duplicate_shared_strings: Vec<DuplicateSharedString>,
+
+    /// Textures larger than the limit, largest first. Only textures that
+    /// could be resolved are measured.
+    pub oversized_textures: Vec<OversizedTexture>,
+
+    /// Ref properties pointing to instances that aren't in the DOM.
+    pub broken_refs: Vec<BrokenRef>,
+
+    /// The size of each subtree up to the requested depth when serialized on
+    /// its own as a binary model, largest first. These don't add up to the
+    /// size of the whole file, since each one has its own header and copy of
+    /// the SharedStrings it uses.
+    pub subtree_sizes: Vec<SubtreeSize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ServiceReport {
+    pub name: String,
+    pub class_name: String,
+
+    /// The number of instances in the service, including itself.
+    pub instances: usize,
+
+    /// The number of BaseParts in the service that aren't anchored.
+    pub unanchored_parts: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DuplicateSharedString {
+    /// The hash of the SharedString, in hex.
+    pub hash: String,
+    pub size: usize,
+
+    /// Every property using the SharedString, like `Workspace.Union.MeshData`.
+    pub uses: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct OversizedTexture {
+    pub content_id: String,
+    pub width: u32,
+    pub height: u32,
+
+    /// Every property using the texture, like `Workspace.Part.Decal.Texture`.
+    pub uses: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrokenRef {
+    pub path: String,
+    pub property: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SubtreeSize {
+    pub path: String,
+    pub class_name: String,
+    pub instances: usize,
+    pub bytes: usize,
+}
+
+/// Analyzes a DOM, usually of a place.
+pub fn analyze(dom: &WeakDom, options: &AnalyzeOptions<'_>) -> anyhow::Result<Report> {
+    let database = rbx_reflection_database::global();
+    let mut analyzer = Analyzer {
+        dom,
+        database,
+        scripts: BTreeMap::new(),
+        shared_strings: HashMap::new(),
+        textures: BTreeMap::new(),
+        broken_refs: Vec::new(),
+    };
+
+    let mut services = Vec::new();
+    for &referent in dom.root().children() {
+        let service = dom.get_by_ref(referent).unwrap();
+        let mut report = ServiceReport {
+            name: service.name.clone(),
+            class_name: service.class.clone(),
+            instances: 0,
+            unanchored_parts: 0,
+        };
+
+        for instance in subtree(dom, referent) {
+            report.instances += 1;
+            if analyzer.is_unanchored_part(instance) {
+                report.unanchored_parts += 1;
+            }
+            analyzer.visit(instance);
+        }
+
+        services.push(report);
+    }
+
+    let mut duplicate_shared_strings: Vec<_> = analyzer
+        .shared_strings
+        .into_iter()
+        .filter(|(_, (_, uses))| uses.len() > 1)
+        .map(|(hash, (size, uses))| DuplicateSharedString {
+            hash: hex(hash.as_bytes()),
+            size,
+            uses,
+        })
+        .collect();
+    duplicate_shared_strings.sort_by(|a, b| {
+        (b.size * b.uses.len())
+            .cmp(&(a.size * a.uses.len()))
+            .then_with(|| a.hash.cmp(&b.hash))
+    });
+
+    let mut oversized_textures = Vec::new();
+    if let Some(assets) = options.assets {
+        for (content_id, uses) in analyzer.textures {
+            let size = match assets.resolve(&content_id) {
+                Ok(bytes) => match imagesize::blob_size(&bytes) {
+                    Ok(size) => size,
+                    Err(_) => continue,
+                },
+                Err(ResolveError::NotFound(_)) | Err(ResolveError::Unsupported(_)) => continue,
+                Err(error) => return Err(error.into()),
+            };
+
+            let (width, height) = (size.width as u32, size.height as u32);
+            if width > options.max_texture_size || height > options.max_texture_size {
+                oversized_textures.push(OversizedTexture {
+                    content_id,
+                    width,
+                    height,
+                    uses,
+                });
+            }
+        }
+    }
+    oversized_textures.sort_by_key(|texture| {
+        std::cmp::Reverse(u64::from(texture.width) * u64::from(texture.height))
+    });
+
+    Ok(Report {
+        instances: services.iter().map(|service| service.instances).sum(),
+        services,
+        scripts: analyzer.scripts,
+        duplicate_shared_strings,
+        oversized_textures,
+        broken_refs: analyzer.broken_refs,
+        subtree_sizes: subtree_sizes(dom, options.depth)?,
+    })
+}
+
+struct Analyzer<'a> {
+    dom: &'a WeakDom,
+    database: &'a ReflectionDatabase<'a>,
+    scripts: BTreeMap<String, usize>,
+
+    /// The size of each SharedString and the properties that use it.
+    shared_strings: HashMap<SharedStringHash, (usize, Vec<String>)>,
+
+    /// The properties that use each texture.
+    textures: BTreeMap<String, Vec<String>>,
+    broken_refs: Vec<BrokenRef>,
+}
+
+impl Analyzer<'_> {
+    fn visit(&mut self, instance: &Instance) {
+        if let Some(kind) = self.script_kind(instance) {
+            *self.scripts.entry(kind).or_default() += 1;
+        }
+
+        let mut properties: Vec<_> = instance.properties.iter().collect();
+        properties.sort_unstable_by_key(|(name, _)| *name);
+
+        let dom = self.dom;
+        for (name, value) in properties {
+            let use_path = || format!("{}.{}", full_name(dom, instance.referent()), name);
+
+            match value {
+                Variant::SharedString(value) => {
+                    let (_, uses) = self
+                        .shared_strings
+                        .entry(value.hash())
+                        .or_insert_with(|| (value.data().len(), Vec::new()));
+                    uses.push(use_path());
+                }
+                Variant::Content(value) if is_texture_property(name) => {
+                    let content_id: &str = value.as_ref();
+                    if !content_id.is_empty() {
+                        let use_path = use_path();
+                        self.textures
+                            .entry(content_id.to_owned())
+                            .or_default()
+                            .push(use_path);
+                    }
+                }
+                Variant::Ref(target)
+                    if target.is_some() && self.dom.get_by_ref(*target).is_none() =>
+                {
+                    self.broken_refs.push(BrokenRef {
+                        path: full_name(self.dom, instance.referent()),
+                        property: name.clone(),
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn script_kind(&self, instance: &Instance) -> Option<String> {
+        match instance.class.as_str() {
+            "LocalScript" => Some("Client".to_owned()),
+            "ModuleScript" => Some("Module".to_owned()),
+            "Script" => {
+                let context = match instance.properties.get("RunContext") {
+                    Some(Variant::Enum(value)) => self
+                        .database
+                        .enum_item_name("RunContext", value.to_u32())
+                        .unwrap_or("Legacy"),
+                    _ => "Legacy",
+                };
+                Some(context.to_owned())
+            }
+            _ => None,
+        }
+    }
+
+    fn is_unanchored_part(&self, instance: &Instance) -> bool {
+        self.database.is_a(&instance.class, "BasePart")
+            && !matches!(
+                instance.properties.get("Anchored"),
+                Some(Variant::Bool(true))
+            )
+    }
+}
+
+/// Tells whether a Content property holds an image, going by its name, like
+/// `Texture`, `TextureID`, `Image`, `ColorMap`, `SkyboxBk`, or
+/// `ShirtTemplate`.
+fn is_texture_property(name: &str) -> bool {
+    name.contains("Texture")
+        || name.contains("Image")
+        || name.ends_with("Map")
+        || name.starts_with("Skybox")
+        || name.ends_with("Template")
+        || name == "Graphic"
+}
+
+/// Returns an instance and all of its descendants, parents before children.
+fn subtree(dom: &WeakDom, referent: Ref) -> Vec<&Instance> {
+    let mut instances = Vec::new();
+    let mut stack = vec![referent];
+
+    while let Some(referent) = stack.pop() {
+        let instance = dom.get_by_ref(referent).unwrap();
+        stack.extend(instance.children().iter().rev());
+        instances.push(instance);
+    }
+
+    instances
+}
+
+fn subtree_sizes(dom: &WeakDom, depth: usize) -> anyhow::Result<Vec<SubtreeSize>> {
+    let mut sizes = Vec::new();
+    let mut current = dom.root().children().to_vec();
+
+    for _ in 0..depth {
+        let mut next = Vec::new();
+
+        for referent in current {
+            let instance = dom.get_by_ref(referent).unwrap();
+            let path = full_name(dom, referent);
+            let bytes = rbx_binary::to_vec(dom, &[referent])
+                .with_context(|| format!("could not serialize {}", path))?
+                .len();
+
+            sizes.push(SubtreeSize {
+                path,
+                class_name: instance.class.clone(),
+                instances: subtree(dom, referent).len(),
+                bytes,
+            });
+            next.extend_from_slice(instance.children());
+        }
+
+        current = next;
+    }
+
+    sizes.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.path.cmp(&b.path)));
+    Ok(sizes)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut output, byte| {
+        write!(output, "{:02x}", byte).unwrap();
+        output
+    })
+}
+
+/// Formats a number of bytes for people to read, like `1.5 MB`.
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: &[&str] = &["KB", "MB", "GB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Prints a report for people to read. Only the sections with something in
+/// them are shown, except for the instance counts.
+pub fn print_report(report: &Report) -> io::Result<()> {
+    let mut output = String::new();
+
+    writeln!(output, "Instances: {}", report.instances).unwrap();
+    for service in &report.services {
+        write!(output, "  {}: {}", service.name, service.instances).unwrap();
+        if service.unanchored_parts > 0 {
+            write!(output, " ({} unanchored parts)", service.unanchored_parts).unwrap();
+        }
+        writeln!(output).unwrap();
+    }
+
+    if !report.scripts.is_empty() {
+        writeln!(output, "\nScripts:").unwrap();
+        for (kind, count) in &report.scripts {
+            writeln!(output, "  {}: {}", kind, count).unwrap();
+        }
+    }
+
+    if !report.duplicate_shared_strings.is_empty() {
+        writeln!(output, "\nDuplicate SharedStrings:").unwrap();
+        for shared_string in &report.duplicate_shared_strings {
+            writeln!(
+                output,
+                "  {} ({}) in {} places, like {}",
+                &shared_string.hash[..16],
+                format_bytes(shared_string.size),
+                shared_string.uses.len(),
+                shared_string.uses[0]
+            )
+            .unwrap();
+        }
+    }
+
+    if !report.oversized_textures.is_empty() {
+        writeln!(output, "\nOversized textures:").unwrap();
+        for texture in &report.oversized_textures {
+            writeln!(
+                output,
+                "  {} ({}x{}) in {} places, like {}",
+                texture.content_id,
+                texture.width,
+                texture.height,
+                texture.uses.len(),
+                texture.uses[0]
+            )
+            .unwrap();
+        }
+    }
+
+    if !report.broken_refs.is_empty() {
+        writeln!(output, "\nBroken refs:").unwrap();
+        for broken_ref in &report.broken_refs {
+            writeln!(output, "  {}.{}", broken_ref.path, broken_ref.property).unwrap();
+        }
+    }
+
+    if !report.subtree_sizes.is_empty() {
+        writeln!(output, "\nSerialized sizes:").unwrap();
+        for size in &report.subtree_sizes {
+            writeln!(
+                output,
+                "  {}: {} ({} instances)",
+                size.path,
+                format_bytes(size.bytes),
+                size.instances
+            )
+            .unwrap();
+        }
+    }
+
+    io::stdout().lock().write_all(output.as_bytes())
+}
+
+/// Prints a report as JSON, for other tools to read.
+pub fn print_json(report: &Report) -> anyhow::Result<()> {
+    let mut output = io::stdout().lock();
+    serde_json::to_writer_pretty(&mut output, report)?;
+    writeln!(output)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{
+        types::{Content, Enum, SharedString},
+        InstanceBuilder,
+    };
+
+    /// Resolves every Content URL to a PNG whose width and height are given
+    /// by the URL, like `rbxassetid://64x32`, or to nothing if it isn't one.
+    struct Images;
+
+    impl ContentResolver for Images {
+        fn resolve(&self, url: &str) -> Result<Vec<u8>, ResolveError> {
+            let size = url.strip_prefix("rbxassetid://").and_then(|size| {
+                let (width, height) = size.split_once('x')?;
+                Some((width.parse::<u32>().ok()?, height.parse::<u32>().ok()?))
+            });
+            let (width, height) = size.ok_or_else(|| ResolveError::NotFound(url.to_owned()))?;
+
+            // Only the header of a PNG is needed to find its size.
+            let mut png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+            png.extend_from_slice(&13u32.to_be_bytes());
+            png.extend_from_slice(b"IHDR");
+            png.extend_from_slice(&width.to_be_bytes());
+            png.extend_from_slice(&height.to_be_bytes());
+            png.extend_from_slice(&[8, 6, 0, 0, 0, 0, 0, 0, 0]);
+            Ok(png)
+        }
+    }
+
+    fn test_dom() -> WeakDom {
+        let database = rbx_reflection_database::global();
+        let server = database.enum_item_value("RunContext", "Server").unwrap();
+        let mesh = SharedString::new(vec![7; 100]);
+
+        let anchored = InstanceBuilder::new("Part")
+            .with_name("Anchored")
+            .with_property("Anchored", true);
+        let union = |name: &str| {
+            InstanceBuilder::new("UnionOperation")
+                .with_name(name)
+                .with_property("Anchored", true)
+                .with_property("MeshData2", mesh.clone())
+        };
+        let weld = InstanceBuilder::new("WeldConstraint")
+            .with_property("Part0", anchored.referent())
+            .with_property("Part1", Ref::new());
+
+        let workspace =
+            InstanceBuilder::new("Workspace")
+                .with_child(anchored)
+                .with_child(InstanceBuilder::new("Part").with_name("Loose"))
+                .with_child(
+                    InstanceBuilder::new("Model")
+                        .with_child(union("First"))
+                        .with_child(union("Second"))
+                        .with_child(
+                            InstanceBuilder::new("Part")
+                                .with_name("AlsoLoose")
+                                .with_child(InstanceBuilder::new("Decal").with_property(
+                                    "Texture",
+                                    Content::from("rbxassetid://2048x512"),
+                                )),
+                        ),
+                )
+                .with_child(weld);
+
+        let storage = InstanceBuilder::new("ReplicatedStorage")
+            .with_child(InstanceBuilder::new("ModuleScript"))
+            .with_child(InstanceBuilder::new("ModuleScript"))
+            .with_child(InstanceBuilder::new("LocalScript"))
+            .with_child(
+                InstanceBuilder::new("Script").with_property("RunContext", Enum::from_u32(server)),
+            )
+            .with_child(InstanceBuilder::new("Script"))
+            .with_child(
+                InstanceBuilder::new("ImageLabel")
+                    .with_property("Image", Content::from("rbxassetid://256x256")),
+            );
+
+        WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(workspace)
+                .with_child(storage),
+        )
+    }
+
+    fn options(assets: Option<&dyn ContentResolver>, depth: usize) -> AnalyzeOptions<'_> {
+        AnalyzeOptions {
+            assets,
+            max_texture_size: 1024,
+            depth,
+        }
+    }
+
+    #[test]
+    fn counts() {
+        let report = analyze(&test_dom(), &options(None, 0)).unwrap();
+
+        assert_eq!(report.instances, 16);
+
+        let services: Vec<_> = report
+            .services
+            .iter()
+            .map(|service| {
+                (
+                    service.name.as_str(),
+                    service.class_name.as_str(),
+                    service.instances,
+                    service.unanchored_parts,
+                )
+            })
+            .collect();
+        assert_eq!(
+            services,
+            [
+                ("Workspace", "Workspace", 9, 2),
+                ("ReplicatedStorage", "ReplicatedStorage", 7, 0),
+            ]
+        );
+
+        let scripts: Vec<_> = report
+            .scripts
+            .iter()
+            .map(|(kind, count)| (kind.as_str(), *count))
+            .collect();
+        assert_eq!(
+            scripts,
+            [("Client", 1), ("Legacy", 1), ("Module", 2), ("Server", 1)]
+        );
+
+        // Nothing is measured without a way to get textures, or a depth.
+        assert!(report.oversized_textures.is_empty());
+        assert!(report.subtree_sizes.is_empty());
+    }
+
+    #[test]
+    fn duplicate_shared_strings() {
+        let report = analyze(&test_dom(), &options(None, 0)).unwrap();
+
+        let duplicate = match report.duplicate_shared_strings.as_slice() {
+            [duplicate] => duplicate,
+            other => panic!("unexpected duplicates {:?}", other),
+        };
+        assert_eq!(duplicate.size, 100);
+        assert_eq!(duplicate.hash.len(), 64);
+        assert_eq!(
+            duplicate.uses,
+            [
+                "Workspace.Model.First.MeshData2",
+                "Workspace.Model.Second.MeshData2"
+            ]
+        );
+    }
+
+    #[test]
+    fn broken_refs() {
+        let report = analyze(&test_dom(), &options(None, 0)).unwrap();
+
+        let broken: Vec<_> = report
+            .broken_refs
+            .iter()
+            .map(|broken| format!("{}.{}", broken.path, broken.property))
+            .collect();
+        assert_eq!(broken, ["Workspace.WeldConstraint.Part1"]);
+    }
+
+    #[test]
+    fn oversized_textures() {
+        let report = analyze(&test_dom(), &options(Some(&Images), 0)).unwrap();
+
+        let texture = match report.oversized_textures.as_slice() {
+            [texture] => texture,
+            other => panic!("unexpected textures {:?}", other),
+        };
+        assert_eq!(texture.content_id, "rbxassetid://2048x512");
+        assert_eq!((texture.width, texture.height), (2048, 512));
+        assert_eq!(texture.uses, ["Workspace.Model.AlsoLoose.Decal.Texture"]);
+    }
+
+    #[test]
+    fn subtree_sizes() {
+        let dom = test_dom();
+        let report = analyze(&dom, &options(None, 2)).unwrap();
+
+        let mut sizes: Vec<_> = report
+            .subtree_sizes
+            .iter()
+            .map(|size| (size.path.as_str(), size.class_name.as_str(), size.instances))
+            .collect();
+        sizes.sort_unstable();
+        assert_eq!(
+            sizes,
+            [
+                ("ReplicatedStorage", "ReplicatedStorage", 7),
+                ("ReplicatedStorage.ImageLabel", "ImageLabel", 1),
+                ("ReplicatedStorage.LocalScript", "LocalScript", 1),
+                ("ReplicatedStorage.ModuleScript", "ModuleScript", 1),
+                ("ReplicatedStorage.ModuleScript", "ModuleScript", 1),
+                ("ReplicatedStorage.Script", "Script", 1),
+                ("ReplicatedStorage.Script", "Script", 1),
+                ("Workspace", "Workspace", 9),
+                ("Workspace.Anchored", "Part", 1),
+                ("Workspace.Loose", "Part", 1),
+                ("Workspace.Model", "Model", 5),
+                ("Workspace.WeldConstraint", "WeldConstraint", 1),
+            ]
+        );
+
+        // Each size is what the subtree takes up as a binary model on its own,
+        // and sizes are listed largest first.
+        let workspace = report
+            .subtree_sizes
+            .iter()
+            .find(|size| size.path == "Workspace")
+            .unwrap();
+        let referent = dom.root().children()[0];
+        assert_eq!(
+            workspace.bytes,
+            rbx_binary::to_vec(&dom, &[referent]).unwrap().len()
+        );
+        assert!(report
+            .subtree_sizes
+            .windows(2)
+            .all(|pair| pair[0].bytes >= pair[1].bytes));
+    }
+
+    #[test]
+    fn format_sizes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.0 MB");
+    }
+}
@@ -0,0 +1,885 @@
+//! Finding and printing the differences between two DOMs.
+//!
+//! Instances are paired up by their UniqueId when both files have them, which
+//! follows instances that were moved or renamed. Otherwise, the children of
+//! paired instances are paired by ClassName and Name. Instances left over on
+//! each side are additions and removals, except that an instance removed from
+//! one place and added in another with the same ClassName and Name is treated
+//! as a move.
+
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::io::{self, IsTerminal, Write};
+
+use rbx_dom_weak::{
+    types::{Ref, UniqueId, Variant},
+    WeakDom,
+};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::view::{full_name, ValueFormatter};
+
+/// A difference between two DOMs, found with [`diff_doms`].
+#[derive(Debug)]
+pub enum Change {
+    /// An instance, along with its descendants, is only in the right DOM.
+    Added { right: Ref },
+
+    /// An instance, along with its descendants, is only in the left DOM.
+    Removed { left: Ref },
+
+    /// An instance has a different parent in each DOM.
+    Moved { left: Ref, right: Ref },
+
+    /// A property of an instance is different in each DOM, or is missing
+    /// from one of them. `ClassName` and `Name` are included.
+    Changed {
+        left: Ref,
+        right: Ref,
+        property: String,
+    },
+}
+
+/// Finds the differences between two DOMs, starting at their roots.
+pub fn diff_doms(left: &WeakDom, right: &WeakDom) -> Vec<Change> {
+    let mut differ = Differ {
+        left,
+        right,
+        pairs: HashMap::new(),
+        paired_right: HashSet::new(),
+        visited: HashSet::new(),
+    };
+
+    differ.pair(left.root_ref(), right.root_ref());
+    differ.pair_unique_ids();
+
+    differ.pair_children(left.root_ref());
+    let unvisited: Vec<Ref> = differ
+        .pairs
+        .keys()
+        .copied()
+        .filter(|left_ref| !differ.visited.contains(left_ref))
+        .collect();
+    for left_ref in unvisited {
+        differ.pair_children(left_ref);
+    }
+
+    differ.pair_moves();
+    differ.changes()
+}
+
+struct Differ<'a> {
+    left: &'a WeakDom,
+    right: &'a WeakDom,
+
+    /// Instances in the left DOM paired with instances in the right DOM.
+    pairs: HashMap<Ref, Ref>,
+    paired_right: HashSet<Ref>,
+
+    /// Paired instances in the left DOM whose children have been paired.
+    visited: HashSet<Ref>,
+}
+
+impl Differ<'_> {
+    fn pair(&mut self, left_ref: Ref, right_ref: Ref) {
+        self.pairs.insert(left_ref, right_ref);
+        self.paired_right.insert(right_ref);
+    }
+
+    fn pair_unique_ids(&mut self) {
+        let right_ids: HashMap<UniqueId, Ref> = unique_ids(self.right).collect();
+
+        for (id, left_ref) in unique_ids(self.left).collect::<Vec<_>>() {
+            if let Some(&right_ref) = right_ids.get(&id) {
+                if !self.pairs.contains_key(&left_ref) && !self.paired_right.contains(&right_ref) {
+                    self.pair(left_ref, right_ref);
+                }
+            }
+        }
+    }
+
+    /// Pairs the unpaired children of a paired instance by ClassName and Name,
+    /// then does the same for every paired child.
+    fn pair_children(&mut self, left_ref: Ref) {
+        let mut stack = vec![left_ref];
+
+        while let Some(left_ref) = stack.pop() {
+            if !self.visited.insert(left_ref) {
+                continue;
+            }
+
+            let right_ref = self.pairs[&left_ref];
+            let left_instance = self.left.get_by_ref(left_ref).unwrap();
+            let right_children = self.right.get_by_ref(right_ref).unwrap().children();
+
+            for &left_child_ref in left_instance.children() {
+                if !self.pairs.contains_key(&left_child_ref) {
+                    let left_child = self.left.get_by_ref(left_child_ref).unwrap();
+
+                    let candidate = right_children.iter().copied().find(|right_child_ref| {
+                        let right_child = self.right.get_by_ref(*right_child_ref).unwrap();
+                        !self.paired_right.contains(right_child_ref)
+                            && right_child.class == left_child.class
+                            && right_child.name == left_child.name
+                    });
+
+                    if let Some(right_child_ref) = candidate {
+                        self.pair(left_child_ref, right_child_ref);
+                    }
+                }
+
+                if self.pairs.contains_key(&left_child_ref) {
+                    stack.push(left_child_ref);
+                }
+            }
+        }
+    }
+
+    /// Pairs instances that were removed from one place and added to another
+    /// with the same ClassName and Name, treating them as moves. This includes
+    /// instances inside of ones that were removed or added, like a part moved
+    /// out of a folder that was then deleted.
+    fn pair_moves(&mut self) {
+        let (left, right) = (self.left, self.right);
+
+        // Added instances, in tree order, keyed by their ClassName and Name so
+        // that each removed instance only has to look at the ones it could be
+        // paired with.
+        let mut added: HashMap<(&str, &str), VecDeque<Ref>> = HashMap::new();
+        for right_ref in descendants(right) {
+            if !self.paired_right.contains(&right_ref) {
+                let instance = right.get_by_ref(right_ref).unwrap();
+                added
+                    .entry((&instance.class, &instance.name))
+                    .or_default()
+                    .push_back(right_ref);
+            }
+        }
+
+        let removed: Vec<Ref> = descendants(left)
+            .into_iter()
+            .filter(|left_ref| !self.pairs.contains_key(left_ref))
+            .collect();
+
+        for left_ref in removed {
+            if self.pairs.contains_key(&left_ref) {
+                continue;
+            }
+
+            let left_instance = left.get_by_ref(left_ref).unwrap();
+            let candidates = match added.get_mut(&(&left_instance.class, &left_instance.name)) {
+                Some(candidates) => candidates,
+                None => continue,
+            };
+
+            // Pairing the children of a move can pair added instances too,
+            // and those are dropped here as they come up.
+            while let Some(right_ref) = candidates.pop_front() {
+                if !self.paired_right.contains(&right_ref) {
+                    self.pair(left_ref, right_ref);
+                    self.pair_children(left_ref);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// The unpaired instances in the left DOM whose parents are paired.
+    fn removed(&self) -> Vec<Ref> {
+        unpaired(self.left, |referent| self.pairs.contains_key(&referent))
+    }
+
+    /// The unpaired instances in the right DOM whose parents are paired.
+    fn added(&self) -> Vec<Ref> {
+        unpaired(self.right, |referent| self.paired_right.contains(&referent))
+    }
+
+    fn changes(&self) -> Vec<Change> {
+        let mut changes = Vec::new();
+
+        for left_ref in descendants(self.left) {
+            let right_ref = match self.pairs.get(&left_ref) {
+                Some(&right_ref) => right_ref,
+                None => continue,
+            };
+
+            let left_instance = self.left.get_by_ref(left_ref).unwrap();
+            let right_instance = self.right.get_by_ref(right_ref).unwrap();
+
+            if self.pairs.get(&left_instance.parent()) != Some(&right_instance.parent()) {
+                changes.push(Change::Moved {
+                    left: left_ref,
+                    right: right_ref,
+                });
+            }
+
+            let mut changed = |property: &str| {
+                changes.push(Change::Changed {
+                    left: left_ref,
+                    right: right_ref,
+                    property: property.to_owned(),
+                })
+            };
+
+            if left_instance.class != right_instance.class {
+                changed("ClassName");
+            }
+            if left_instance.name != right_instance.name {
+                changed("Name");
+            }
+
+            let names: BTreeSet<&String> = left_instance
+                .properties
+                .keys()
+                .chain(right_instance.properties.keys())
+                .collect();
+
+            for name in names {
+                let equal = match (
+                    left_instance.properties.get(name),
+                    right_instance.properties.get(name),
+                ) {
+                    (Some(left_value), Some(right_value)) => {
+                        self.values_eq(left_value, right_value)
+                    }
+                    _ => false,
+                };
+
+                if !equal {
+                    changed(name);
+                }
+            }
+        }
+
+        changes.extend(
+            self.removed()
+                .into_iter()
+                .map(|left| Change::Removed { left }),
+        );
+        changes.extend(
+            self.added()
+                .into_iter()
+                .map(|right| Change::Added { right }),
+        );
+
+        changes
+    }
+
+    fn values_eq(&self, left: &Variant, right: &Variant) -> bool {
+        match (left, right) {
+            (Variant::Ref(left), Variant::Ref(right)) => {
+                if left.is_none() || right.is_none() {
+                    left == right
+                } else {
+                    self.pairs.get(left) == Some(right)
+                }
+            }
+            (Variant::Float32(left), Variant::Float32(right)) => {
+                left == right || (left.is_nan() && right.is_nan())
+            }
+            (Variant::Float64(left), Variant::Float64(right)) => {
+                left == right || (left.is_nan() && right.is_nan())
+            }
+            _ => left == right,
+        }
+    }
+}
+
+fn unique_ids(dom: &WeakDom) -> impl Iterator<Item = (UniqueId, Ref)> + '_ {
+    descendants(dom).into_iter().filter_map(move |referent| {
+        match dom.get_by_ref(referent).unwrap().properties.get("UniqueId") {
+            Some(Variant::UniqueId(id)) => Some((*id, referent)),
+            _ => None,
+        }
+    })
+}
+
+/// Every instance in a DOM except its root, parents before their children.
+fn descendants(dom: &WeakDom) -> Vec<Ref> {
+    let mut referents = Vec::new();
+    let mut stack: Vec<Ref> = dom.root().children().iter().rev().copied().collect();
+
+    while let Some(referent) = stack.pop() {
+        referents.push(referent);
+        stack.extend(dom.get_by_ref(referent).unwrap().children().iter().rev());
+    }
+
+    referents
+}
+
+fn unpaired(dom: &WeakDom, is_paired: impl Fn(Ref) -> bool) -> Vec<Ref> {
+    descendants(dom)
+        .into_iter()
+        .filter(|&referent| {
+            let parent = dom.get_by_ref(referent).unwrap().parent();
+            !is_paired(referent) && is_paired(parent)
+        })
+        .collect()
+}
+
+/// How [`print_report`] colors its output.
+#[derive(Debug, Clone, Copy)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl std::str::FromStr for ColorChoice {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        match value {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => anyhow::bail!(
+                "unknown color choice {:?}, expected auto, always, or never",
+                value
+            ),
+        }
+    }
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Prints the changes between two DOMs for people to read, one line per
+/// instance followed by the properties that changed on it.
+pub fn print_report(
+    left: &WeakDom,
+    right: &WeakDom,
+    changes: &[Change],
+    color: ColorChoice,
+) -> io::Result<()> {
+    let stdout = io::stdout();
+    let color = match color {
+        ColorChoice::Auto => stdout.is_terminal(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    };
+    let paint = |code: &'static str| if color { code } else { "" };
+    let reset = paint(RESET);
+
+    let database = rbx_reflection_database::global();
+    let left_values = ValueFormatter {
+        dom: left,
+        database,
+    };
+    let right_values = ValueFormatter {
+        dom: right,
+        database,
+    };
+
+    let mut output = stdout.lock();
+    let mut last_changed = None;
+
+    for change in changes {
+        match change {
+            Change::Added { right: right_ref } => {
+                let instance = right.get_by_ref(*right_ref).unwrap();
+                writeln!(
+                    output,
+                    "{}+ {} ({}){}",
+                    paint(GREEN),
+                    full_name(right, *right_ref),
+                    instance.class,
+                    reset
+                )?;
+            }
+            Change::Removed { left: left_ref } => {
+                let instance = left.get_by_ref(*left_ref).unwrap();
+                writeln!(
+                    output,
+                    "{}- {} ({}){}",
+                    paint(RED),
+                    full_name(left, *left_ref),
+                    instance.class,
+                    reset
+                )?;
+            }
+            Change::Moved {
+                left: left_ref,
+                right: right_ref,
+            } => {
+                let instance = right.get_by_ref(*right_ref).unwrap();
+                writeln!(
+                    output,
+                    "{}> {} ({}), moved from {}{}",
+                    paint(CYAN),
+                    full_name(right, *right_ref),
+                    instance.class,
+                    full_name(left, *left_ref),
+                    reset
+                )?;
+            }
+            Change::Changed {
+                left: left_ref,
+                right: right_ref,
+                property,
+            } => {
+                let left_instance = left.get_by_ref(*left_ref).unwrap();
+                let right_instance = right.get_by_ref(*right_ref).unwrap();
+
+                if last_changed != Some(*left_ref) {
+                    writeln!(
+                        output,
+                        "{}~ {} ({}){}",
+                        paint(YELLOW),
+                        full_name(right, *right_ref),
+                        right_instance.class,
+                        reset
+                    )?;
+                    last_changed = Some(*left_ref);
+                }
+
+                let format = |formatter: &ValueFormatter, instance: &rbx_dom_weak::Instance| {
+                    match property.as_str() {
+                        "ClassName" => Some(instance.class.clone()),
+                        "Name" => Some(format!("{:?}", instance.name)),
+                        _ => instance
+                            .properties
+                            .get(property)
+                            .map(|value| formatter.format(&instance.class, property, value)),
+                    }
+                    .unwrap_or_else(|| "(none)".to_owned())
+                };
+
+                writeln!(
+                    output,
+                    "    {}: {}{}{} -> {}{}{}",
+                    property,
+                    paint(RED),
+                    format(&left_values, left_instance),
+                    reset,
+                    paint(GREEN),
+                    format(&right_values, right_instance),
+                    reset
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+#[serde(tag = "Kind")]
+enum JsonChange {
+    Added {
+        #[serde(rename = "Path")]
+        path: String,
+        #[serde(rename = "ClassName")]
+        class_name: String,
+    },
+    Removed {
+        #[serde(rename = "Path")]
+        path: String,
+        #[serde(rename = "ClassName")]
+        class_name: String,
+    },
+    Moved {
+        #[serde(rename = "From")]
+        from: String,
+        #[serde(rename = "To")]
+        to: String,
+        #[serde(rename = "ClassName")]
+        class_name: String,
+    },
+    Changed {
+        #[serde(rename = "Path")]
+        path: String,
+        #[serde(rename = "ClassName")]
+        class_name: String,
+        #[serde(rename = "Property")]
+        property: String,
+        #[serde(rename = "Old")]
+        old: serde_json::Value,
+        #[serde(rename = "New")]
+        new: serde_json::Value,
+    },
+}
+
+/// Prints the changes between two DOMs as a JSON array, for other tools to
+/// read. Property values are written the same way as rbx-util's JSON form
+/// writes them, except that Refs are the full names of the instances they
+/// point to, and missing values are `null`.
+pub fn print_json(left: &WeakDom, right: &WeakDom, changes: &[Change]) -> anyhow::Result<()> {
+    let json_changes: Vec<JsonChange> = changes
+        .iter()
+        .map(|change| match change {
+            Change::Added { right: right_ref } => JsonChange::Added {
+                path: full_name(right, *right_ref),
+                class_name: right.get_by_ref(*right_ref).unwrap().class.clone(),
+            },
+            Change::Removed { left: left_ref } => JsonChange::Removed {
+                path: full_name(left, *left_ref),
+                class_name: left.get_by_ref(*left_ref).unwrap().class.clone(),
+            },
+            Change::Moved {
+                left: left_ref,
+                right: right_ref,
+            } => JsonChange::Moved {
+                from: full_name(left, *left_ref),
+                to: full_name(right, *right_ref),
+                class_name: right.get_by_ref(*right_ref).unwrap().class.clone(),
+            },
+            Change::Changed {
+                left: left_ref,
+                right: right_ref,
+                property,
+            } => JsonChange::Changed {
+                path: full_name(right, *right_ref),
+                class_name: right.get_by_ref(*right_ref).unwrap().class.clone(),
+                property: property.clone(),
+                old: json_value(left, *left_ref, property),
+                new: json_value(right, *right_ref, property),
+            },
+        })
+        .collect();
+
+    let mut output = io::stdout().lock();
+    serde_json::to_writer_pretty(&mut output, &json_changes)?;
+    writeln!(output)?;
+    Ok(())
+}
+
+fn json_value(dom: &WeakDom, referent: Ref, property: &str) -> serde_json::Value {
+    let instance = dom.get_by_ref(referent).unwrap();
+
+    let value = match property {
+        "ClassName" => return json!(instance.class),
+        "Name" => return json!(instance.name),
+        _ => match instance.properties.get(property) {
+            Some(value) => value,
+            None => return serde_json::Value::Null,
+        },
+    };
+
+    match value {
+        Variant::Ref(target) if target.is_none() => json!({ "Ref": null }),
+        Variant::Ref(target) => json!({ "Ref": full_name(dom, *target) }),
+        Variant::SharedString(shared_string) => {
+            json!({ "SharedString": base64::encode(shared_string.data()) })
+        }
+        other => serde_json::to_value(other).unwrap_or(serde_json::Value::Null),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    /// Describes each change by the names of the instances involved, so that
+    /// tests don't depend on referents.
+    fn describe(left: &WeakDom, right: &WeakDom) -> Vec<String> {
+        let mut changes: Vec<String> = diff_doms(left, right)
+            .iter()
+            .map(|change| match change {
+                Change::Added { right: right_ref } => format!("+ {}", full_name(right, *right_ref)),
+                Change::Removed { left: left_ref } => format!("- {}", full_name(left, *left_ref)),
+                Change::Moved {
+                    left: left_ref,
+                    right: right_ref,
+                } => format!(
+                    "> {} from {}",
+                    full_name(right, *right_ref),
+                    full_name(left, *left_ref)
+                ),
+                Change::Changed {
+                    right: right_ref,
+                    property,
+                    ..
+                } => format!("~ {}.{}", full_name(right, *right_ref), property),
+            })
+            .collect();
+
+        changes.sort();
+        changes
+    }
+
+    fn unique_id(index: u32) -> UniqueId {
+        UniqueId::new(index, 0, 0)
+    }
+
+    #[test]
+    fn identical() {
+        let make =
+            || {
+                WeakDom::new(InstanceBuilder::new("DataModel").with_child(
+                    InstanceBuilder::new("Folder").with_child(
+                        InstanceBuilder::new("Part").with_property("Transparency", 0.5f32),
+                    ),
+                ))
+            };
+
+        assert!(describe(&make(), &make()).is_empty());
+    }
+
+    #[test]
+    fn property_changes() {
+        let left = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Part")
+                    .with_property("Transparency", 0.5f32)
+                    .with_property("Anchored", true)
+                    .with_property("Reflectance", f32::NAN),
+            ),
+        );
+        let right = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Part")
+                    .with_property("Transparency", 0.25f32)
+                    .with_property("Locked", true)
+                    .with_property("Reflectance", f32::NAN),
+            ),
+        );
+
+        assert_eq!(
+            describe(&left, &right),
+            ["~ Part.Anchored", "~ Part.Locked", "~ Part.Transparency"]
+        );
+    }
+
+    /// Ensures that Refs are equal when they point to paired instances, even
+    /// though the referents are different in each DOM.
+    #[test]
+    fn ref_properties() {
+        let make = |target: &str| {
+            let a = InstanceBuilder::new("Part").with_name("A");
+            let b = InstanceBuilder::new("Part").with_name("B");
+            let target = if target == "A" {
+                a.referent()
+            } else {
+                b.referent()
+            };
+            let weld = InstanceBuilder::new("WeldConstraint").with_property("Part0", target);
+
+            WeakDom::new(InstanceBuilder::new("DataModel").with_children([a, b, weld]))
+        };
+
+        assert!(describe(&make("A"), &make("A")).is_empty());
+        assert_eq!(describe(&make("A"), &make("B")), ["~ WeldConstraint.Part0"]);
+    }
+
+    #[test]
+    fn added_and_removed() {
+        let left = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(InstanceBuilder::new("Folder").with_name("Old"))
+                .with_child(InstanceBuilder::new("Folder").with_name("Kept")),
+        );
+        let right = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(InstanceBuilder::new("Folder").with_name("Kept"))
+                .with_child(
+                    InstanceBuilder::new("Folder")
+                        .with_name("New")
+                        .with_child(InstanceBuilder::new("Part")),
+                ),
+        );
+
+        // The Part inside New is part of New being added, so it isn't listed
+        // on its own.
+        assert_eq!(describe(&left, &right), ["+ New", "- Old"]);
+    }
+
+    /// Ensures that an instance with the same UniqueId is followed through a
+    /// rename, instead of being treated as removed and added.
+    #[test]
+    fn rename_with_unique_id() {
+        let left = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Part")
+                    .with_name("Before")
+                    .with_property("UniqueId", unique_id(1)),
+            ),
+        );
+        let right = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Part")
+                    .with_name("After")
+                    .with_property("UniqueId", unique_id(1)),
+            ),
+        );
+
+        assert_eq!(describe(&left, &right), ["~ After.Name"]);
+    }
+
+    /// Without UniqueIds, a renamed instance can't be told apart from one
+    /// that was replaced.
+    #[test]
+    fn rename_without_unique_id() {
+        let left = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(InstanceBuilder::new("Part").with_name("Before")),
+        );
+        let right = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(InstanceBuilder::new("Part").with_name("After")),
+        );
+
+        assert_eq!(describe(&left, &right), ["+ After", "- Before"]);
+    }
+
+    #[test]
+    fn reparent() {
+        let left = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(
+                    InstanceBuilder::new("Folder").with_name("From").with_child(
+                        InstanceBuilder::new("Part")
+                            .with_name("Moved")
+                            .with_child(InstanceBuilder::new("Decal")),
+                    ),
+                )
+                .with_child(InstanceBuilder::new("Folder").with_name("To")),
+        );
+        let right = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(InstanceBuilder::new("Folder").with_name("From"))
+                .with_child(
+                    InstanceBuilder::new("Folder").with_name("To").with_child(
+                        InstanceBuilder::new("Part")
+                            .with_name("Moved")
+                            .with_child(InstanceBuilder::new("Decal")),
+                    ),
+                ),
+        );
+
+        // The Decal moves along with its parent, so only the Part is listed.
+        assert_eq!(describe(&left, &right), ["> To.Moved from From.Moved"]);
+    }
+
+    /// Ensures that an instance moved out of a folder that was deleted is a
+    /// move, not a removal and an addition.
+    #[test]
+    fn reparent_out_of_removed() {
+        let left = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Folder")
+                    .with_name("Deleted")
+                    .with_child(InstanceBuilder::new("Part").with_name("Saved")),
+            ),
+        );
+        let right = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(InstanceBuilder::new("Part").with_name("Saved")),
+        );
+
+        assert_eq!(
+            describe(&left, &right),
+            ["- Deleted", "> Saved from Deleted.Saved"]
+        );
+    }
+
+    #[test]
+    fn reparent_with_unique_id() {
+        let left = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(
+                    InstanceBuilder::new("Folder").with_name("A").with_child(
+                        InstanceBuilder::new("Part")
+                            .with_name("Before")
+                            .with_property("UniqueId", unique_id(1)),
+                    ),
+                )
+                .with_child(InstanceBuilder::new("Folder").with_name("B")),
+        );
+        let right = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(InstanceBuilder::new("Folder").with_name("A"))
+                .with_child(
+                    InstanceBuilder::new("Folder").with_name("B").with_child(
+                        InstanceBuilder::new("Part")
+                            .with_name("After")
+                            .with_property("UniqueId", unique_id(1)),
+                    ),
+                ),
+        );
+
+        assert_eq!(
+            describe(&left, &right),
+            ["> B.After from A.Before", "~ B.After.Name"]
+        );
+    }
+
+    /// Ensures that siblings with the same ClassName and Name are paired in
+    /// order, so that changing one of them only reports that one.
+    #[test]
+    fn duplicate_sibling_names() {
+        let make = |values: [&str; 3]| {
+            WeakDom::new(InstanceBuilder::new("DataModel").with_children(
+                values.iter().map(|value| {
+                    InstanceBuilder::new("StringValue").with_property("Value", *value)
+                }),
+            ))
+        };
+
+        let left = make(["a", "b", "c"]);
+        let right = make(["a", "changed", "c"]);
+
+        let changes = diff_doms(&left, &right);
+        let changed: Vec<_> = changes
+            .iter()
+            .map(|change| match change {
+                Change::Changed {
+                    right: right_ref,
+                    property,
+                    ..
+                } => (*right_ref, property.as_str()),
+                other => panic!("unexpected change {:?}", other),
+            })
+            .collect();
+
+        assert_eq!(changed, [(right.root().children()[1], "Value")]);
+    }
+
+    /// Ensures that when there are more duplicates on one side, the extras
+    /// are additions or removals.
+    #[test]
+    fn duplicate_sibling_names_uneven() {
+        let make = |count: usize| {
+            WeakDom::new(
+                InstanceBuilder::new("DataModel")
+                    .with_children((0..count).map(|_| InstanceBuilder::new("Part"))),
+            )
+        };
+
+        assert_eq!(describe(&make(2), &make(3)), ["+ Part"]);
+        assert_eq!(describe(&make(3), &make(1)), ["- Part", "- Part"]);
+    }
+
+    /// Ensures that pairing moves stays fast when there are many unpaired
+    /// instances, most of which can't be paired with each other.
+    #[test]
+    fn many_moves() {
+        let make = |parent: &str| {
+            WeakDom::new(
+                InstanceBuilder::new("DataModel")
+                    .with_child(InstanceBuilder::new("Folder").with_name("A"))
+                    .with_child(InstanceBuilder::new("Folder").with_name("B"))
+                    .with_child(
+                        InstanceBuilder::new("Folder")
+                            .with_name(parent)
+                            .with_children(
+                                (0..20_000)
+                                    .map(|i| InstanceBuilder::new("Part").with_name(i.to_string())),
+                            ),
+                    ),
+            )
+        };
+
+        let changes = diff_doms(&make("Old"), &make("New"));
+        let moves = changes
+            .iter()
+            .filter(|change| matches!(change, Change::Moved { .. }))
+            .count();
+        assert_eq!(moves, 20_000);
+    }
+}
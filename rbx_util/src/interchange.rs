@@ -0,0 +1,322 @@
+//! Forms of a DOM for other tools to process: JSON, which is easier to read
+//! and edit than any of the formats Roblox uses, and MessagePack and CBOR,
+//! which are compact and quick to read and write.
+//!
+//! Instances are written as a tree, each with a referent that Ref properties
+//! point to. Referents are numbered in the order instances appear, so the same
+//! DOM is always written the same way. SharedStrings are written separately
+//! from other values, since they can't be serialized as part of a Variant: as
+//! base64 in JSON, and as bytes otherwise.
+//!
+//! MessagePack and CBOR use the compact forms that rbx_types gives values in
+//! formats that aren't human-readable, like integers for Refs.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+
+use anyhow::bail;
+use rbx_dom_weak::{
+    types::{BinaryString, Ref, SharedString, Variant},
+    InstanceBuilder, WeakDom,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::model::Format;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", bound(deserialize = "V: Deserialize<'de>"))]
+struct SerdeModel<V> {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+
+    instances: Vec<SerdeInstance<V>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", bound(deserialize = "V: Deserialize<'de>"))]
+struct SerdeInstance<V> {
+    referent: Ref,
+    name: String,
+    class_name: String,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    properties: BTreeMap<String, V>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    children: Vec<SerdeInstance<V>>,
+}
+
+/// How each form writes the value of a property.
+trait PropertyValue: Sized {
+    fn from_variant(value: Variant) -> Self;
+    fn into_variant(self) -> anyhow::Result<Variant>;
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum JsonValue {
+    SharedString {
+        #[serde(rename = "SharedString")]
+        base64: String,
+    },
+    Other(Variant),
+}
+
+impl PropertyValue for JsonValue {
+    fn from_variant(value: Variant) -> Self {
+        match value {
+            Variant::SharedString(shared_string) => JsonValue::SharedString {
+                base64: base64::encode(shared_string.data()),
+            },
+            other => JsonValue::Other(other),
+        }
+    }
+
+    fn into_variant(self) -> anyhow::Result<Variant> {
+        Ok(match self {
+            JsonValue::SharedString { base64 } => {
+                Variant::SharedString(SharedString::new(base64::decode(base64)?))
+            }
+            JsonValue::Other(value) => value,
+        })
+    }
+}
+
+/// Values in MessagePack and CBOR are tagged, since untagged enums are read
+/// as if the format were human-readable.
+#[derive(Debug, Serialize, Deserialize)]
+enum CompactValue {
+    SharedString(BinaryString),
+    Other(Variant),
+}
+
+impl PropertyValue for CompactValue {
+    fn from_variant(value: Variant) -> Self {
+        match value {
+            Variant::SharedString(shared_string) => {
+                CompactValue::SharedString(shared_string.data().to_vec().into())
+            }
+            other => CompactValue::Other(other),
+        }
+    }
+
+    fn into_variant(self) -> anyhow::Result<Variant> {
+        Ok(match self {
+            CompactValue::SharedString(data) => {
+                Variant::SharedString(SharedString::new(data.into_vec()))
+            }
+            CompactValue::Other(value) => value,
+        })
+    }
+}
+
+/// Writes the children of the root of `dom` in the given form, along with the
+/// metadata from a binary file.
+pub fn to_writer<W: Write>(
+    mut output: W,
+    format: Format,
+    dom: &WeakDom,
+    metadata: &HashMap<String, String>,
+) -> anyhow::Result<()> {
+    match format {
+        Format::Json => {
+            let model: SerdeModel<JsonValue> = to_model(dom, metadata);
+            serde_json::to_writer_pretty(&mut output, &model)?;
+            writeln!(output)?;
+        }
+        Format::MessagePack => {
+            let model: SerdeModel<CompactValue> = to_model(dom, metadata);
+            // Structs are written as maps so that fields can be left out.
+            rmp_serde::encode::write_named(&mut output, &model)?;
+        }
+        Format::Cbor => {
+            let model: SerdeModel<CompactValue> = to_model(dom, metadata);
+            let mut serializer = serde_cbor::Serializer::new(serde_cbor::ser::IoWrite::new(output));
+            // The self-describe tag is how a CBOR file can be recognized.
+            serializer.self_describe()?;
+            model.serialize(&mut serializer)?;
+        }
+        other => bail!("{:?} is not an interchange format", other),
+    }
+
+    Ok(())
+}
+
+/// Reads a DOM written by [`to_writer`] into a DOM whose root is a
+/// `DataModel`, returning it with the metadata that was stored alongside it.
+pub fn from_reader<R: Read>(
+    input: R,
+    format: Format,
+) -> anyhow::Result<(WeakDom, HashMap<String, String>)> {
+    match format {
+        Format::Json => from_model::<JsonValue>(serde_json::from_reader(input)?),
+        Format::MessagePack => from_model::<CompactValue>(rmp_serde::from_read(input)?),
+        Format::Cbor => from_model::<CompactValue>(serde_cbor::from_reader(input)?),
+        other => bail!("{:?} is not an interchange format", other),
+    }
+}
+
+fn to_model<V: PropertyValue>(dom: &WeakDom, metadata: &HashMap<String, String>) -> SerdeModel<V> {
+    let mut referents = HashMap::new();
+    for &child in dom.root().children() {
+        number_referents(dom, child, &mut referents);
+    }
+
+    SerdeModel {
+        metadata: metadata
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect(),
+        instances: dom
+            .root()
+            .children()
+            .iter()
+            .map(|&child| to_serde_instance(dom, child, &referents))
+            .collect(),
+    }
+}
+
+fn from_model<V: PropertyValue>(
+    model: SerdeModel<V>,
+) -> anyhow::Result<(WeakDom, HashMap<String, String>)> {
+    let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let mut referents = HashMap::new();
+    let root_ref = dom.root_ref();
+
+    for instance in model.instances {
+        let builder = to_builder(instance, &mut referents)?;
+        dom.insert(root_ref, builder);
+    }
+
+    // Ref properties were read with the referents from the file, which only
+    // mean something once every instance has been created.
+    for &new_ref in referents.values() {
+        let instance = dom.get_by_ref_mut(new_ref).unwrap();
+
+        for value in instance.properties.values_mut() {
+            if let Variant::Ref(referent) = value {
+                *referent = referents.get(referent).copied().unwrap_or_else(Ref::none);
+            }
+        }
+    }
+
+    Ok((dom, model.metadata.into_iter().collect()))
+}
+
+fn number_referents(dom: &WeakDom, referent: Ref, referents: &mut HashMap<Ref, Ref>) {
+    let number = format!("{:x}", referents.len() + 1);
+    referents.insert(referent, number.parse().unwrap());
+
+    for &child in dom.get_by_ref(referent).unwrap().children() {
+        number_referents(dom, child, referents);
+    }
+}
+
+fn to_serde_instance<V: PropertyValue>(
+    dom: &WeakDom,
+    referent: Ref,
+    referents: &HashMap<Ref, Ref>,
+) -> SerdeInstance<V> {
+    let instance = dom.get_by_ref(referent).unwrap();
+
+    let properties = instance
+        .properties
+        .iter()
+        .map(|(name, value)| {
+            let value = match value {
+                Variant::Ref(target) => {
+                    Variant::Ref(referents.get(target).copied().unwrap_or_else(Ref::none))
+                }
+                other => other.clone(),
+            };
+
+            (name.clone(), V::from_variant(value))
+        })
+        .collect();
+
+    SerdeInstance {
+        referent: referents[&referent],
+        name: instance.name.clone(),
+        class_name: instance.class.clone(),
+        properties,
+        children: instance
+            .children()
+            .iter()
+            .map(|&child| to_serde_instance(dom, child, referents))
+            .collect(),
+    }
+}
+
+fn to_builder<V: PropertyValue>(
+    instance: SerdeInstance<V>,
+    referents: &mut HashMap<Ref, Ref>,
+) -> anyhow::Result<InstanceBuilder> {
+    let mut builder = InstanceBuilder::new(instance.class_name).with_name(instance.name);
+
+    for (name, value) in instance.properties {
+        builder.add_property(name, value.into_variant()?);
+    }
+
+    referents.insert(instance.referent, builder.referent());
+
+    let children = instance
+        .children
+        .into_iter()
+        .map(|child| to_builder(child, referents))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(builder.with_children(children))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{compare_doms, DomCompareOptions};
+
+    /// Ensures that each form keeps Refs pointing at the same instances,
+    /// SharedStrings and metadata.
+    #[test]
+    fn round_trip() {
+        let target = InstanceBuilder::new("Part").with_name("Target");
+        let tree = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Folder")
+                    .with_child(
+                        InstanceBuilder::new("ObjectValue")
+                            .with_property("Value", target.referent()),
+                    )
+                    .with_child(target)
+                    .with_child(
+                        InstanceBuilder::new("UnionOperation")
+                            .with_property("MeshData2", SharedString::new(vec![7; 64])),
+                    ),
+            ),
+        );
+        let metadata = HashMap::from([("ExplicitAutoJoints".to_owned(), "true".to_owned())]);
+
+        for format in [Format::Json, Format::MessagePack, Format::Cbor] {
+            let mut buffer = Vec::new();
+            to_writer(&mut buffer, format, &tree, &metadata).unwrap();
+            let (dom, read_metadata) = from_reader(buffer.as_slice(), format).unwrap();
+
+            let differences = compare_doms(&tree, &dom, &DomCompareOptions::new());
+            assert!(differences.is_empty(), "{:?}: {:#?}", format, differences);
+            assert_eq!(read_metadata, metadata, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn other_formats() {
+        let tree = WeakDom::new(InstanceBuilder::new("DataModel"));
+
+        for format in [Format::Binary, Format::Xml, Format::Project] {
+            let error = to_writer(Vec::new(), format, &tree, &HashMap::new()).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!("{:?} is not an interchange format", format)
+            );
+            assert!(from_reader(&b"{}"[..], format).is_err());
+        }
+    }
+}
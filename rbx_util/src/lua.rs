@@ -0,0 +1,1111 @@
+//! Running Luau scripts against a DOM, with a subset of Roblox's Instance API.
+//!
+//! Scripts see the file through `game`, the way they would in Roblox. They can
+//! read and change the names, parents, properties, attributes, and tags of
+//! instances, and create new ones with `Instance.new`. `readAsset` returns the
+//! bytes of an asset as a string, if rbx-util was told where to find assets.
+
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+use anyhow::anyhow;
+use mlua::prelude::*;
+use rbx_content::ContentResolver;
+use rbx_dom_weak::{
+    types::{
+        Attributes, Axes, BinaryString, CFrame, Color3, Color3uint8, ColorSequence, Content, Enum,
+        Faces, Font, NumberRange, NumberSequence, PhysicalProperties, Ray, Rect, Ref, Region3,
+        Region3int16, Tags, UDim, UDim2, Variant, VariantType, Vector2, Vector2int16, Vector3,
+        Vector3int16,
+    },
+    Instance, InstanceBuilder, WeakDom,
+};
+use rbx_reflection::{ClassTag, DataType, ReflectionDatabase};
+
+use crate::model::Model;
+
+/// Runs the script at `script_path` against the DOM of `model`, changing it in
+/// place. The script gets `args` as its `...`, and reads assets through
+/// `assets`.
+pub fn run_script(
+    script_path: &Path,
+    model: &mut Model,
+    assets: Option<Box<dyn ContentResolver>>,
+    args: Vec<String>,
+) -> anyhow::Result<()> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!(
+        "rbx_util::run_script",
+        script = %script_path.display(),
+    )
+    .entered();
+
+    let source = fs_err::read_to_string(script_path)?;
+
+    // Scripts can parent instances to nil and keep using them, so they need
+    // somewhere to live. The instances of the file are moved under `game`,
+    // and the root of the DOM stands in for nil.
+    let mut dom = WeakDom::new(InstanceBuilder::new("Folder").with_name("nil"));
+    let game = dom.insert(
+        dom.root_ref(),
+        InstanceBuilder::new("DataModel").with_name("game"),
+    );
+    for child in model.dom.root().children().to_vec() {
+        model.dom.transfer(child, &mut dom, game);
+    }
+
+    let state = Rc::new(State {
+        dom: RefCell::new(dom),
+        game,
+        database: rbx_reflection_database::global(),
+        assets,
+    });
+
+    let lua = Lua::new();
+    add_globals(&lua, &state).map_err(|error| anyhow!("{}", error))?;
+
+    let result = lua
+        .load(source.as_str())
+        .set_name(script_path.display().to_string())
+        .call::<_, ()>(args.into_iter().collect::<mlua::Variadic<_>>());
+
+    // The instances go back into the model even if the script failed, so it's
+    // never left half empty.
+    let mut dom = state.dom.borrow_mut();
+    let root_ref = model.dom.root_ref();
+    for child in dom.get_by_ref(game).unwrap().children().to_vec() {
+        dom.transfer(child, &mut model.dom, root_ref);
+    }
+
+    result.map_err(|error| anyhow!("{}", error))
+}
+
+struct State {
+    dom: RefCell<WeakDom>,
+    game: Ref,
+    database: &'static ReflectionDatabase<'static>,
+    assets: Option<Box<dyn ContentResolver>>,
+}
+
+impl State {
+    fn is_a(&self, class_name: &str, superclass_name: &str) -> bool {
+        class_name == superclass_name || self.database.is_a(class_name, superclass_name)
+    }
+
+    fn is_valid_class(&self, class_name: &str) -> bool {
+        self.database.classes.contains_key(class_name)
+    }
+}
+
+fn runtime_error(message: String) -> LuaError {
+    LuaError::RuntimeError(message)
+}
+
+fn add_globals(lua: &Lua, state: &Rc<State>) -> LuaResult<()> {
+    let globals = lua.globals();
+
+    globals.set("game", LuaInstance::new(state, state.game))?;
+
+    let instance = lua.create_table()?;
+    let instance_state = Rc::clone(state);
+    instance.set(
+        "new",
+        lua.create_function(
+            move |_, (class_name, parent): (String, Option<LuaInstance>)| {
+                let state = &instance_state;
+                if !state.is_valid_class(&class_name) {
+                    return Err(runtime_error(format!(
+                        "{} is not a valid class name",
+                        class_name
+                    )));
+                }
+
+                let mut dom = state.dom.borrow_mut();
+                let parent_ref = match &parent {
+                    Some(parent) => {
+                        parent.instance(&dom)?;
+                        parent.referent
+                    }
+                    None => dom.root_ref(),
+                };
+
+                let referent = dom.insert(parent_ref, state.database.instantiate(&class_name));
+                Ok(LuaInstance::new(state, referent))
+            },
+        )?,
+    )?;
+    globals.set("Instance", instance)?;
+
+    let vector2 = lua.create_table()?;
+    vector2.set(
+        "new",
+        lua.create_function(|_, (x, y): (Option<f32>, Option<f32>)| {
+            Ok(Vector2::new(x.unwrap_or(0.0), y.unwrap_or(0.0)))
+        })?,
+    )?;
+    globals.set("Vector2", vector2)?;
+
+    let vector3 = lua.create_table()?;
+    vector3.set(
+        "new",
+        lua.create_function(|_, (x, y, z): (Option<f32>, Option<f32>, Option<f32>)| {
+            Ok(Vector3::new(
+                x.unwrap_or(0.0),
+                y.unwrap_or(0.0),
+                z.unwrap_or(0.0),
+            ))
+        })?,
+    )?;
+    globals.set("Vector3", vector3)?;
+
+    let color3 = lua.create_table()?;
+    color3.set(
+        "new",
+        lua.create_function(|_, (r, g, b): (Option<f32>, Option<f32>, Option<f32>)| {
+            Ok(Color3::new(
+                r.unwrap_or(0.0),
+                g.unwrap_or(0.0),
+                b.unwrap_or(0.0),
+            ))
+        })?,
+    )?;
+    color3.set(
+        "fromRGB",
+        lua.create_function(|_, (r, g, b): (Option<u8>, Option<u8>, Option<u8>)| {
+            Ok(Color3::from(Color3uint8::new(
+                r.unwrap_or(0),
+                g.unwrap_or(0),
+                b.unwrap_or(0),
+            )))
+        })?,
+    )?;
+    globals.set("Color3", color3)?;
+
+    let udim = lua.create_table()?;
+    udim.set(
+        "new",
+        lua.create_function(|_, (scale, offset): (Option<f32>, Option<i32>)| {
+            Ok(UDim::new(scale.unwrap_or(0.0), offset.unwrap_or(0)))
+        })?,
+    )?;
+    globals.set("UDim", udim)?;
+
+    let udim2 = lua.create_table()?;
+    udim2.set(
+        "new",
+        lua.create_function(
+            |_,
+             (x_scale, x_offset, y_scale, y_offset): (
+                Option<f32>,
+                Option<i32>,
+                Option<f32>,
+                Option<i32>,
+            )| {
+                Ok(UDim2::new(
+                    UDim::new(x_scale.unwrap_or(0.0), x_offset.unwrap_or(0)),
+                    UDim::new(y_scale.unwrap_or(0.0), y_offset.unwrap_or(0)),
+                ))
+            },
+        )?,
+    )?;
+    globals.set("UDim2", udim2)?;
+
+    let asset_state = Rc::clone(state);
+    globals.set(
+        "readAsset",
+        lua.create_function(move |lua, url: String| {
+            let assets = asset_state.assets.as_ref().ok_or_else(|| {
+                runtime_error("readAsset needs rbx-util to be run with --assets".to_owned())
+            })?;
+
+            let bytes = assets
+                .resolve(&url)
+                .map_err(|error| runtime_error(error.to_string()))?;
+            lua.create_string(&bytes)
+        })?,
+    )?;
+
+    Ok(())
+}
+
+/// An instance as scripts see it. It only holds a referent, so it goes on
+/// working when the instance is moved, and errors once it's destroyed.
+#[derive(Clone)]
+struct LuaInstance {
+    state: Rc<State>,
+    referent: Ref,
+}
+
+impl LuaInstance {
+    fn new(state: &Rc<State>, referent: Ref) -> Self {
+        Self {
+            state: Rc::clone(state),
+            referent,
+        }
+    }
+
+    fn with_ref(&self, referent: Ref) -> Self {
+        Self::new(&self.state, referent)
+    }
+
+    fn instance<'a>(&self, dom: &'a WeakDom) -> LuaResult<&'a Instance> {
+        dom.get_by_ref(self.referent)
+            .ok_or_else(|| runtime_error("the instance has been destroyed".to_owned()))
+    }
+
+    fn instance_mut<'a>(&self, dom: &'a mut WeakDom) -> LuaResult<&'a mut Instance> {
+        dom.get_by_ref_mut(self.referent)
+            .ok_or_else(|| runtime_error("the instance has been destroyed".to_owned()))
+    }
+
+    /// The parent of the instance, which is `None` when it's parented to nil.
+    fn parent(&self, dom: &WeakDom) -> LuaResult<Option<LuaInstance>> {
+        let parent = self.instance(dom)?.parent();
+
+        if parent == dom.root_ref() {
+            Ok(None)
+        } else {
+            Ok(Some(self.with_ref(parent)))
+        }
+    }
+
+    /// The ancestors of the instance, from its parent up, not including nil.
+    fn ancestors<'a>(&self, dom: &'a WeakDom) -> LuaResult<Vec<&'a Instance>> {
+        let mut ancestors = Vec::new();
+        let mut current = self.instance(dom)?.parent();
+
+        while current != dom.root_ref() {
+            let instance = dom.get_by_ref(current).unwrap();
+            ancestors.push(instance);
+            current = instance.parent();
+        }
+
+        Ok(ancestors)
+    }
+
+    /// The descendants of the instance in depth-first order.
+    fn descendants<'a>(&self, dom: &'a WeakDom) -> LuaResult<Vec<&'a Instance>> {
+        let mut descendants = Vec::new();
+        let mut stack: Vec<Ref> = self
+            .instance(dom)?
+            .children()
+            .iter()
+            .rev()
+            .copied()
+            .collect();
+
+        while let Some(referent) = stack.pop() {
+            let instance = dom.get_by_ref(referent).unwrap();
+            descendants.push(instance);
+            stack.extend(instance.children().iter().rev());
+        }
+
+        Ok(descendants)
+    }
+
+    fn find_child(
+        &self,
+        recursive: Option<bool>,
+        predicate: impl Fn(&Instance) -> bool,
+    ) -> LuaResult<Option<LuaInstance>> {
+        let dom = self.state.dom.borrow();
+
+        let found = if recursive.unwrap_or(false) {
+            self.descendants(&dom)?
+                .into_iter()
+                .find(|child| predicate(child))
+        } else {
+            self.instance(&dom)?
+                .children()
+                .iter()
+                .map(|&child| dom.get_by_ref(child).unwrap())
+                .find(|child| predicate(child))
+        };
+
+        Ok(found.map(|child| self.with_ref(child.referent())))
+    }
+
+    fn find_ancestor(
+        &self,
+        predicate: impl Fn(&Instance) -> bool,
+    ) -> LuaResult<Option<LuaInstance>> {
+        let dom = self.state.dom.borrow();
+        let found = self
+            .ancestors(&dom)?
+            .into_iter()
+            .find(|ancestor| predicate(ancestor));
+
+        Ok(found.map(|ancestor| self.with_ref(ancestor.referent())))
+    }
+
+    /// Like Roblox's `GetFullName`, which leaves out `game`.
+    fn full_name(&self) -> LuaResult<String> {
+        let dom = self.state.dom.borrow();
+        let instance = self.instance(&dom)?;
+
+        let mut names = vec![instance.name.as_str()];
+        for ancestor in self.ancestors(&dom)? {
+            if ancestor.referent() == self.state.game {
+                break;
+            }
+            names.push(ancestor.name.as_str());
+        }
+
+        names.reverse();
+        Ok(names.join("."))
+    }
+
+    fn set_parent(&self, parent: Option<LuaInstance>) -> LuaResult<()> {
+        if self.referent == self.state.game {
+            return Err(runtime_error(
+                "the Parent property of game is locked".to_owned(),
+            ));
+        }
+
+        let mut dom = self.state.dom.borrow_mut();
+        self.instance(&dom)?;
+
+        let parent_ref = match parent {
+            Some(parent) => {
+                let mut current = parent.instance(&dom)?.referent();
+                while current.is_some() {
+                    if current == self.referent {
+                        return Err(runtime_error(
+                            "cannot parent an instance to itself or one of its descendants"
+                                .to_owned(),
+                        ));
+                    }
+                    current = dom.get_by_ref(current).unwrap().parent();
+                }
+
+                parent.referent
+            }
+            None => dom.root_ref(),
+        };
+
+        dom.transfer_within(self.referent, parent_ref);
+        Ok(())
+    }
+
+    fn get_property<'lua>(&self, lua: &'lua Lua, key: &str) -> LuaResult<LuaValue<'lua>> {
+        let dom = self.state.dom.borrow();
+        let instance = self.instance(&dom)?;
+
+        match key {
+            "Name" => return instance.name.as_str().into_lua(lua),
+            "ClassName" => return instance.class.as_str().into_lua(lua),
+            "Parent" => return self.parent(&dom)?.into_lua(lua),
+            _ => {}
+        }
+
+        let database = self.state.database;
+        let descriptors = database.find_property_descriptors(&instance.class, key);
+        let name = descriptors.map_or(key, |descriptors| descriptors.canonical.name.as_ref());
+
+        if let Some(value) = instance.properties.get(name) {
+            return variant_to_lua(lua, self, &dom, value);
+        }
+
+        // Properties that aren't in the file have their default value.
+        if descriptors.is_some() {
+            let default = database
+                .superclass_iter(&instance.class)
+                .find_map(|class| class.default_properties.get(name));
+
+            if let Some(value) = default {
+                return variant_to_lua(lua, self, &dom, value);
+            }
+        }
+
+        let child = instance
+            .children()
+            .iter()
+            .map(|&child| dom.get_by_ref(child).unwrap())
+            .find(|child| child.name == key);
+
+        match child {
+            Some(child) => self.with_ref(child.referent()).into_lua(lua),
+            None => Err(runtime_error(format!(
+                "{} is not a valid member of {} {:?}",
+                key, instance.class, instance.name
+            ))),
+        }
+    }
+
+    fn set_property<'lua>(
+        &self,
+        lua: &'lua Lua,
+        key: &str,
+        value: LuaValue<'lua>,
+    ) -> LuaResult<()> {
+        match key {
+            "Name" => {
+                let name = String::from_lua(value, lua)?;
+                let mut dom = self.state.dom.borrow_mut();
+                self.instance_mut(&mut dom)?.name = name;
+                return Ok(());
+            }
+            "Parent" => return self.set_parent(Option::<LuaInstance>::from_lua(value, lua)?),
+            "ClassName" => {
+                return Err(runtime_error(
+                    "the ClassName property is read-only".to_owned(),
+                ))
+            }
+            _ => {}
+        }
+
+        let (name, ty) = {
+            let dom = self.state.dom.borrow();
+            let instance = self.instance(&dom)?;
+            let descriptors = self
+                .state
+                .database
+                .find_property_descriptors(&instance.class, key);
+            let name = descriptors.map_or(key, |descriptors| descriptors.canonical.name.as_ref());
+
+            // The type of a property comes from the value it has now, which
+            // may differ from the database for files saved by other versions
+            // of Roblox.
+            let ty = match (instance.properties.get(name), descriptors) {
+                (Some(existing), _) => Some(existing.ty()),
+                (None, Some(descriptors)) => match &descriptors.canonical.data_type {
+                    DataType::Value(ty) => Some(*ty),
+                    DataType::Enum(_) => Some(VariantType::Enum),
+                    _ => None,
+                },
+                (None, None) => None,
+            };
+
+            match ty {
+                Some(ty) => (name.to_owned(), ty),
+                None => {
+                    return Err(runtime_error(format!(
+                        "{} is not a valid member of {} {:?}",
+                        key, instance.class, instance.name
+                    )))
+                }
+            }
+        };
+
+        let value = lua_to_variant(lua, value, ty)?;
+
+        let mut dom = self.state.dom.borrow_mut();
+        self.instance_mut(&mut dom)?.properties.insert(name, value);
+        Ok(())
+    }
+
+    fn tags(&self) -> LuaResult<Vec<String>> {
+        let dom = self.state.dom.borrow();
+
+        match self.instance(&dom)?.properties.get("Tags") {
+            Some(Variant::Tags(tags)) => Ok(tags.iter().map(str::to_owned).collect()),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn set_tags(&self, tags: Vec<String>) -> LuaResult<()> {
+        let mut dom = self.state.dom.borrow_mut();
+        let instance = self.instance_mut(&mut dom)?;
+        instance
+            .properties
+            .insert("Tags".to_owned(), Variant::Tags(Tags::from(tags)));
+        Ok(())
+    }
+}
+
+impl<'lua> FromLua<'lua> for LuaInstance {
+    fn from_lua(value: LuaValue<'lua>, _lua: &'lua Lua) -> LuaResult<Self> {
+        match &value {
+            LuaValue::UserData(data) if data.is::<Self>() => Ok(data.borrow::<Self>()?.clone()),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "Instance",
+                message: None,
+            }),
+        }
+    }
+}
+
+impl LuaUserData for LuaInstance {
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_meta_method(LuaMetaMethod::Index, |lua, this, key: String| {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!("rbx_util::lua_get", key = key.as_str()).entered();
+
+            this.get_property(lua, &key)
+        });
+        methods.add_meta_method(
+            LuaMetaMethod::NewIndex,
+            |lua, this, (key, value): (String, LuaValue)| {
+                #[cfg(feature = "tracing")]
+                let _span = tracing::trace_span!("rbx_util::lua_set", key = key.as_str()).entered();
+
+                this.set_property(lua, &key, value)
+            },
+        );
+        methods.add_meta_method(LuaMetaMethod::Eq, |_, this, other: LuaInstance| {
+            Ok(this.referent == other.referent)
+        });
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| {
+            let dom = this.state.dom.borrow();
+            Ok(this.instance(&dom)?.name.clone())
+        });
+
+        add_method(methods, "GetChildren", |_, this, ()| {
+            let dom = this.state.dom.borrow();
+            let children = this.instance(&dom)?.children();
+            Ok(children
+                .iter()
+                .map(|&child| this.with_ref(child))
+                .collect::<Vec<_>>())
+        });
+        add_method(methods, "GetDescendants", |_, this, ()| {
+            let dom = this.state.dom.borrow();
+            let descendants = this.descendants(&dom)?;
+            Ok(descendants
+                .into_iter()
+                .map(|descendant| this.with_ref(descendant.referent()))
+                .collect::<Vec<_>>())
+        });
+        add_method(
+            methods,
+            "FindFirstChild",
+            |_, this, (name, recursive): (String, Option<bool>)| {
+                this.find_child(recursive, |child| child.name == name)
+            },
+        );
+        add_method(
+            methods,
+            "FindFirstChildOfClass",
+            |_, this, class_name: String| this.find_child(None, |child| child.class == class_name),
+        );
+        add_method(
+            methods,
+            "FindFirstChildWhichIsA",
+            |_, this, (class_name, recursive): (String, Option<bool>)| {
+                this.find_child(recursive, |child| {
+                    this.state.is_a(&child.class, &class_name)
+                })
+            },
+        );
+        add_method(methods, "FindFirstAncestor", |_, this, name: String| {
+            this.find_ancestor(|ancestor| ancestor.name == name)
+        });
+        add_method(
+            methods,
+            "FindFirstAncestorOfClass",
+            |_, this, class_name: String| {
+                this.find_ancestor(|ancestor| ancestor.class == class_name)
+            },
+        );
+        add_method(
+            methods,
+            "FindFirstAncestorWhichIsA",
+            |_, this, class_name: String| {
+                this.find_ancestor(|ancestor| this.state.is_a(&ancestor.class, &class_name))
+            },
+        );
+        add_method(methods, "IsA", |_, this, class_name: String| {
+            let dom = this.state.dom.borrow();
+            Ok(this.state.is_a(&this.instance(&dom)?.class, &class_name))
+        });
+        add_method(methods, "IsDescendantOf", |_, this, other: LuaInstance| {
+            let dom = this.state.dom.borrow();
+            let ancestors = this.ancestors(&dom)?;
+            Ok(ancestors
+                .iter()
+                .any(|ancestor| ancestor.referent() == other.referent))
+        });
+        add_method(methods, "IsAncestorOf", |_, this, other: LuaInstance| {
+            let dom = this.state.dom.borrow();
+            let ancestors = other.ancestors(&dom)?;
+            Ok(ancestors
+                .iter()
+                .any(|ancestor| ancestor.referent() == this.referent))
+        });
+        add_method(methods, "GetFullName", |_, this, ()| this.full_name());
+
+        add_method(methods, "GetService", |_, this, class_name: String| {
+            let is_service = this
+                .state
+                .database
+                .classes
+                .get(class_name.as_str())
+                .is_some_and(|class| class.tags.contains(&ClassTag::Service));
+            if !is_service {
+                return Err(runtime_error(format!(
+                    "{} is not a valid service name",
+                    class_name
+                )));
+            }
+
+            if let Some(service) = this.find_child(None, |child| child.class == class_name)? {
+                return Ok(service);
+            }
+
+            let mut dom = this.state.dom.borrow_mut();
+            this.instance(&dom)?;
+            let referent = dom.insert(this.referent, this.state.database.instantiate(&class_name));
+            Ok(this.with_ref(referent))
+        });
+
+        add_method(methods, "Clone", |_, this, ()| {
+            let mut dom = this.state.dom.borrow_mut();
+            this.instance(&dom)?;
+
+            let clone = dom.clone_within(this.referent);
+            let root_ref = dom.root_ref();
+            dom.transfer_within(clone, root_ref);
+            Ok(this.with_ref(clone))
+        });
+        add_method(methods, "Destroy", |_, this, ()| {
+            if this.referent == this.state.game {
+                return Err(runtime_error("game cannot be destroyed".to_owned()));
+            }
+
+            let mut dom = this.state.dom.borrow_mut();
+            this.instance(&dom)?;
+            dom.destroy(this.referent);
+            Ok(())
+        });
+        add_method(methods, "ClearAllChildren", |_, this, ()| {
+            let mut dom = this.state.dom.borrow_mut();
+            for child in this.instance(&dom)?.children().to_vec() {
+                dom.destroy(child);
+            }
+            Ok(())
+        });
+
+        add_method(methods, "GetAttribute", |lua, this, name: String| {
+            let dom = this.state.dom.borrow();
+            match this.instance(&dom)?.properties.get("Attributes") {
+                Some(Variant::Attributes(attributes)) => match attributes.get(name.as_str()) {
+                    Some(value) => variant_to_lua(lua, this, &dom, value),
+                    None => Ok(LuaValue::Nil),
+                },
+                _ => Ok(LuaValue::Nil),
+            }
+        });
+        add_method(methods, "GetAttributes", |lua, this, ()| {
+            let dom = this.state.dom.borrow();
+            let table = lua.create_table()?;
+
+            if let Some(Variant::Attributes(attributes)) =
+                this.instance(&dom)?.properties.get("Attributes")
+            {
+                for (name, value) in attributes.iter() {
+                    table.set(name.as_str(), variant_to_lua(lua, this, &dom, value)?)?;
+                }
+            }
+
+            Ok(table)
+        });
+        add_method(
+            methods,
+            "SetAttribute",
+            |lua, this, (name, value): (String, LuaValue)| {
+                let value = match value {
+                    LuaValue::Nil => None,
+                    value => Some(attribute_to_variant(lua, value)?),
+                };
+
+                let mut dom = this.state.dom.borrow_mut();
+                let properties = &mut this.instance_mut(&mut dom)?.properties;
+                let attributes = properties
+                    .entry("Attributes".to_owned())
+                    .or_insert_with(|| Variant::Attributes(Attributes::new()));
+
+                if let Variant::Attributes(attributes) = attributes {
+                    match value {
+                        Some(value) => attributes.insert(name, value),
+                        None => attributes.remove(name.as_str()),
+                    };
+                }
+                Ok(())
+            },
+        );
+
+        add_method(methods, "GetTags", |_, this, ()| this.tags());
+        add_method(methods, "HasTag", |_, this, tag: String| {
+            Ok(this.tags()?.contains(&tag))
+        });
+        add_method(methods, "AddTag", |_, this, tag: String| {
+            let mut tags = this.tags()?;
+            if !tags.contains(&tag) {
+                tags.push(tag);
+                this.set_tags(tags)?;
+            }
+            Ok(())
+        });
+        add_method(methods, "RemoveTag", |_, this, tag: String| {
+            let mut tags = this.tags()?;
+            tags.retain(|existing| *existing != tag);
+            this.set_tags(tags)
+        });
+    }
+}
+
+/// Adds a method to Instance, which is called in a span named after it when
+/// tracing is enabled.
+fn add_method<'lua, M, A, R, F>(methods: &mut M, name: &'static str, method: F)
+where
+    M: LuaUserDataMethods<'lua, LuaInstance>,
+    A: FromLuaMulti<'lua>,
+    R: IntoLuaMulti<'lua>,
+    F: Fn(&'lua Lua, &LuaInstance, A) -> LuaResult<R> + 'static,
+{
+    methods.add_method(name, move |lua, this, args| {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("rbx_util::lua_call", method = name).entered();
+
+        method(lua, this, args)
+    });
+}
+
+fn variant_to_lua<'lua>(
+    lua: &'lua Lua,
+    instance: &LuaInstance,
+    dom: &WeakDom,
+    value: &Variant,
+) -> LuaResult<LuaValue<'lua>> {
+    match value.clone() {
+        Variant::Bool(value) => value.into_lua(lua),
+        Variant::Int32(value) => value.into_lua(lua),
+        Variant::Int64(value) => value.into_lua(lua),
+        Variant::Float32(value) => value.into_lua(lua),
+        Variant::Float64(value) => value.into_lua(lua),
+        Variant::String(value) => value.into_lua(lua),
+        Variant::Content(value) => value.into_lua(lua),
+        Variant::BinaryString(value) => lua
+            .create_string(AsRef::<[u8]>::as_ref(&value))
+            .map(LuaValue::String),
+        Variant::Enum(value) => value.into_lua(lua),
+        Variant::Tags(tags) => tags.iter().collect::<Vec<_>>().into_lua(lua),
+        Variant::Ref(referent) => match dom.get_by_ref(referent) {
+            Some(_) => instance.with_ref(referent).into_lua(lua),
+            None => Ok(LuaValue::Nil),
+        },
+
+        Variant::Color3uint8(value) => Color3::from(value).into_lua(lua),
+        Variant::OptionalCFrame(value) => value.into_lua(lua),
+
+        Variant::Axes(value) => value.into_lua(lua),
+        Variant::CFrame(value) => value.into_lua(lua),
+        Variant::Color3(value) => value.into_lua(lua),
+        Variant::ColorSequence(value) => value.into_lua(lua),
+        Variant::Faces(value) => value.into_lua(lua),
+        Variant::Font(value) => value.into_lua(lua),
+        Variant::NumberRange(value) => value.into_lua(lua),
+        Variant::NumberSequence(value) => value.into_lua(lua),
+        Variant::PhysicalProperties(value) => value.into_lua(lua),
+        Variant::Ray(value) => value.into_lua(lua),
+        Variant::Rect(value) => value.into_lua(lua),
+        Variant::Region3(value) => value.into_lua(lua),
+        Variant::Region3int16(value) => value.into_lua(lua),
+        Variant::UDim(value) => value.into_lua(lua),
+        Variant::UDim2(value) => value.into_lua(lua),
+        Variant::Vector2(value) => value.into_lua(lua),
+        Variant::Vector2int16(value) => value.into_lua(lua),
+        Variant::Vector3(value) => value.into_lua(lua),
+        Variant::Vector3int16(value) => value.into_lua(lua),
+
+        other => Err(runtime_error(format!(
+            "values of type {:?} can't be used from Lua",
+            other.ty()
+        ))),
+    }
+}
+
+fn lua_to_variant<'lua>(
+    lua: &'lua Lua,
+    value: LuaValue<'lua>,
+    ty: VariantType,
+) -> LuaResult<Variant> {
+    Ok(match ty {
+        VariantType::Bool => Variant::Bool(bool::from_lua(value, lua)?),
+        VariantType::Int32 => Variant::Int32(i32::from_lua(value, lua)?),
+        VariantType::Int64 => Variant::Int64(i64::from_lua(value, lua)?),
+        VariantType::Float32 => Variant::Float32(f32::from_lua(value, lua)?),
+        VariantType::Float64 => Variant::Float64(f64::from_lua(value, lua)?),
+        VariantType::String => Variant::String(String::from_lua(value, lua)?),
+        VariantType::Content => Variant::Content(Content::from_lua(value, lua)?),
+        VariantType::BinaryString => Variant::BinaryString(BinaryString::from(
+            LuaString::from_lua(value, lua)?.as_bytes().to_vec(),
+        )),
+        VariantType::Enum => Variant::Enum(Enum::from_lua(value, lua)?),
+        VariantType::Tags => Variant::Tags(Tags::from(Vec::<String>::from_lua(value, lua)?)),
+        VariantType::Ref => Variant::Ref(
+            Option::<LuaInstance>::from_lua(value, lua)?
+                .map_or_else(Ref::none, |instance| instance.referent),
+        ),
+
+        VariantType::Color3uint8 => {
+            Variant::Color3uint8(Color3uint8::from(Color3::from_lua(value, lua)?))
+        }
+        VariantType::OptionalCFrame => {
+            Variant::OptionalCFrame(Option::<CFrame>::from_lua(value, lua)?)
+        }
+
+        VariantType::Axes => Variant::Axes(Axes::from_lua(value, lua)?),
+        VariantType::CFrame => Variant::CFrame(CFrame::from_lua(value, lua)?),
+        VariantType::Color3 => Variant::Color3(Color3::from_lua(value, lua)?),
+        VariantType::ColorSequence => Variant::ColorSequence(ColorSequence::from_lua(value, lua)?),
+        VariantType::Faces => Variant::Faces(Faces::from_lua(value, lua)?),
+        VariantType::Font => Variant::Font(Font::from_lua(value, lua)?),
+        VariantType::NumberRange => Variant::NumberRange(NumberRange::from_lua(value, lua)?),
+        VariantType::NumberSequence => {
+            Variant::NumberSequence(NumberSequence::from_lua(value, lua)?)
+        }
+        VariantType::PhysicalProperties => {
+            Variant::PhysicalProperties(PhysicalProperties::from_lua(value, lua)?)
+        }
+        VariantType::Ray => Variant::Ray(Ray::from_lua(value, lua)?),
+        VariantType::Rect => Variant::Rect(Rect::from_lua(value, lua)?),
+        VariantType::Region3 => Variant::Region3(Region3::from_lua(value, lua)?),
+        VariantType::Region3int16 => Variant::Region3int16(Region3int16::from_lua(value, lua)?),
+        VariantType::UDim => Variant::UDim(UDim::from_lua(value, lua)?),
+        VariantType::UDim2 => Variant::UDim2(UDim2::from_lua(value, lua)?),
+        VariantType::Vector2 => Variant::Vector2(Vector2::from_lua(value, lua)?),
+        VariantType::Vector2int16 => Variant::Vector2int16(Vector2int16::from_lua(value, lua)?),
+        VariantType::Vector3 => Variant::Vector3(Vector3::from_lua(value, lua)?),
+        VariantType::Vector3int16 => Variant::Vector3int16(Vector3int16::from_lua(value, lua)?),
+
+        other => {
+            return Err(runtime_error(format!(
+                "values of type {:?} can't be set from Lua",
+                other
+            )))
+        }
+    })
+}
+
+/// Converts the value of an attribute, whose type comes from the value itself
+/// since attributes aren't in the reflection database.
+fn attribute_to_variant<'lua>(lua: &'lua Lua, value: LuaValue<'lua>) -> LuaResult<Variant> {
+    macro_rules! userdata_type {
+        ($data:expr, $($ty:ident),*) => {
+            $(if $data.is::<$ty>() { Some(VariantType::$ty) } else)* { None }
+        };
+    }
+
+    let ty = match &value {
+        LuaValue::Boolean(_) => Some(VariantType::Bool),
+        LuaValue::Integer(_) | LuaValue::Number(_) => Some(VariantType::Float64),
+        LuaValue::String(_) => Some(VariantType::String),
+        LuaValue::UserData(data) => userdata_type!(
+            data,
+            CFrame,
+            Color3,
+            ColorSequence,
+            Font,
+            NumberRange,
+            NumberSequence,
+            Rect,
+            UDim,
+            UDim2,
+            Vector2,
+            Vector3
+        ),
+        _ => None,
+    };
+
+    match ty {
+        Some(ty) => lua_to_variant(lua, value, ty),
+        None => Err(runtime_error(format!(
+            "values of type {} can't be stored in attributes",
+            value.type_name()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    fn test_model() -> Model {
+        let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(
+            InstanceBuilder::new("Workspace").with_children([
+                InstanceBuilder::new("Part").with_name("Old"),
+                InstanceBuilder::new("Part").with_name("Doomed"),
+            ]),
+        ));
+
+        Model {
+            dom,
+            metadata: HashMap::new(),
+            unknown_properties: Vec::new(),
+            unknown_elements: Vec::new(),
+        }
+    }
+
+    /// Runs `source` as a script against `model`, with no assets.
+    fn run(name: &str, source: &str, model: &mut Model, args: &[&str]) -> anyhow::Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("rbx_util_lua_{}_{}.luau", name, std::process::id()));
+        fs_err::write(&path, source).unwrap();
+
+        let result = run_script(
+            &path,
+            model,
+            None,
+            args.iter().map(|arg| arg.to_string()).collect(),
+        );
+        fs_err::remove_file(&path).unwrap();
+        result
+    }
+
+    fn child<'a>(dom: &'a WeakDom, parent: Ref, name: &str) -> &'a Instance {
+        dom.get_by_ref(parent)
+            .unwrap()
+            .children()
+            .iter()
+            .map(|&child| dom.get_by_ref(child).unwrap())
+            .find(|child| child.name == name)
+            .unwrap_or_else(|| panic!("no child named {}", name))
+    }
+
+    #[test]
+    fn change_dom() {
+        let mut model = test_model();
+        run(
+            "change_dom",
+            r#"
+                local workspace = game:GetService("Workspace")
+
+                local part = workspace.Old
+                part.Name = "New"
+                part.Anchored = true
+                part.Size = Vector3.new(4, 1, 2)
+                part:SetAttribute("Health", 100)
+                part:AddTag("Enemy")
+
+                local value = Instance.new("StringValue", workspace)
+                value.Name = "Greeting"
+                value.Value = "Hello, " .. part:GetFullName()
+
+                workspace.Doomed:Destroy()
+            "#,
+            &mut model,
+            &[],
+        )
+        .unwrap();
+
+        let dom = &model.dom;
+        let workspace = child(dom, dom.root_ref(), "Workspace");
+        assert_eq!(workspace.children().len(), 2);
+
+        let part = child(dom, workspace.referent(), "New");
+        assert_eq!(part.properties.get("Anchored"), Some(&Variant::Bool(true)));
+        assert_eq!(
+            part.properties.get("Size"),
+            Some(&Variant::Vector3(Vector3::new(4.0, 1.0, 2.0)))
+        );
+        match part.properties.get("Attributes") {
+            Some(Variant::Attributes(attributes)) => {
+                assert_eq!(attributes.get("Health"), Some(&Variant::Float64(100.0)));
+            }
+            other => panic!("unexpected attributes {:?}", other),
+        }
+        match part.properties.get("Tags") {
+            Some(Variant::Tags(tags)) => assert_eq!(tags.iter().collect::<Vec<_>>(), ["Enemy"]),
+            other => panic!("unexpected tags {:?}", other),
+        }
+
+        let value = child(dom, workspace.referent(), "Greeting");
+        assert_eq!(value.class, "StringValue");
+        assert_eq!(
+            value.properties.get("Value"),
+            Some(&Variant::String("Hello, Workspace.New".to_owned()))
+        );
+    }
+
+    #[test]
+    fn arguments() {
+        let mut model = test_model();
+        run(
+            "arguments",
+            r#"
+                local first, second = ...
+                game.Workspace.Old.Name = first .. second
+            "#,
+            &mut model,
+            &["Left", "Right"],
+        )
+        .unwrap();
+
+        let dom = &model.dom;
+        let workspace = child(dom, dom.root_ref(), "Workspace");
+        child(dom, workspace.referent(), "LeftRight");
+    }
+
+    /// Ensures that a script that fails still leaves the model with every
+    /// instance it had, including changes made before the error.
+    #[test]
+    fn error_keeps_instances() {
+        let mut model = test_model();
+        let error = run(
+            "error_keeps_instances",
+            r#"
+                game.Workspace.Old.Name = "Renamed"
+                game.Workspace.Renamed.ClassName = "Model"
+            "#,
+            &mut model,
+            &[],
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("the ClassName property is read-only"),
+            "{}",
+            error
+        );
+
+        let dom = &model.dom;
+        let workspace = child(dom, dom.root_ref(), "Workspace");
+        assert_eq!(workspace.children().len(), 2);
+        child(dom, workspace.referent(), "Renamed");
+        child(dom, workspace.referent(), "Doomed");
+    }
+
+    #[test]
+    fn invalid_class() {
+        let mut model = test_model();
+        let error = run(
+            "invalid_class",
+            r#"Instance.new("NotAClass", game.Workspace)"#,
+            &mut model,
+            &[],
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("NotAClass is not a valid class name"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn read_asset_without_assets() {
+        let mut model = test_model();
+        let error = run(
+            "read_asset_without_assets",
+            r#"readAsset("rbxassetid://1234")"#,
+            &mut model,
+            &[],
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("readAsset needs rbx-util to be run with --assets"),
+            "{}",
+            error
+        );
+    }
+}
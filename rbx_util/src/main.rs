@@ -1,11 +1,30 @@
-use std::io::{self, BufReader, BufWriter};
+mod analyze;
+mod diff;
+mod interchange;
+#[cfg(feature = "lua")]
+mod lua;
+mod model;
+mod project;
+mod view;
+
+use std::io::{self, BufRead, BufReader, BufWriter};
 use std::path::{Path, PathBuf};
 use std::process;
+use std::str::FromStr;
 
-use anyhow::{anyhow, bail, Context};
+use anyhow::bail;
 use fs_err::File;
+use rbx_binary::CompressionOptions;
+use rbx_content::{ChainResolver, ContentResolver, FileSystemResolver};
+use rbx_gltf::{Exporter, ResolverMeshProvider};
+use rbx_xml::CompatibilityTarget;
 use structopt::StructOpt;
 
+use crate::analyze::AnalyzeOptions;
+use crate::diff::ColorChoice;
+use crate::model::{Format, WriteOptions};
+use crate::view::ViewOptions;
+
 #[derive(Debug, StructOpt)]
 struct Options {
     #[structopt(subcommand)]
@@ -15,91 +34,363 @@ struct Options {
 #[derive(Debug, StructOpt)]
 enum Subcommand {
     /// Convert a model or place file in one format to another.
-    Convert { input: PathBuf, output: PathBuf },
+    ///
+    /// The format of the input is detected from its contents, and the format
+    /// of the output is picked by its extension: rbxm or rbxl for binary,
+    /// rbxmx or rbxlx for XML, and json, msgpack, or cbor for rbx-util's
+    /// interchange forms. Rojo projects can be read from and written to a
+    /// project.json file, and a directory of files can be read as one.
+    Convert {
+        input: PathBuf,
+        output: PathBuf,
+
+        /// Convert without the reflection database, keeping every property's
+        /// name and type exactly as they are stored in the input file.
+        /// Properties of unknown binary types are kept when converting to
+        /// XML and back.
+        #[structopt(long)]
+        raw: bool,
+
+        /// Write binary files without compressing them.
+        #[structopt(long, conflicts_with = "compression-level")]
+        uncompressed: bool,
+
+        /// Compress binary files with LZ4 HC at this level instead of the
+        /// faster default compressor. Higher levels are smaller and slower.
+        #[structopt(long)]
+        compression_level: Option<i32>,
+
+        /// Which versions of Roblox Studio XML files are written for: modern,
+        /// or legacy for older versions and tools.
+        #[structopt(long, default_value = "modern")]
+        compatibility: Compatibility,
+    },
+
+    /// Export the parts of a model or place file as a glTF scene, picking
+    /// between glb and gltf by the output's extension.
+    ///
+    /// Blocks, wedges, cylinders, and spheres are drawn as they are in
+    /// Roblox. MeshParts are drawn with their meshes if they can be found
+    /// with --assets, and as boxes the size of the part otherwise, like
+    /// unions.
+    Export {
+        input: PathBuf,
+        output: PathBuf,
+
+        #[structopt(flatten)]
+        assets: AssetOptions,
+    },
+
+    /// Print a model or place file as a tree of instances.
+    #[structopt(alias = "dump")]
+    View {
+        input: PathBuf,
+
+        /// Show the properties of each instance.
+        #[structopt(long, short)]
+        properties: bool,
+
+        /// Show the attributes of each instance.
+        #[structopt(long, short)]
+        attributes: bool,
+
+        /// Only show instances up to this many levels below the first ones
+        /// shown.
+        #[structopt(long)]
+        depth: Option<usize>,
+
+        /// Only show instances of this class or its subclasses, along with the
+        /// instances that contain them. Can be given more than once.
+        #[structopt(long = "class", number_of_values = 1)]
+        classes: Vec<String>,
+
+        /// Start at the instance at this path, like Workspace.Map, instead of
+        /// at the top of the file.
+        #[structopt(long)]
+        path: Option<String>,
+    },
+
+    /// Show the differences between two model or place files: instances that
+    /// were added, removed, or moved, and properties that changed.
+    Diff {
+        left: PathBuf,
+        right: PathBuf,
+
+        /// Print the differences as JSON instead.
+        #[structopt(long)]
+        json: bool,
+
+        /// Whether to color the output: auto, always, or never.
+        #[structopt(long, default_value = "auto")]
+        color: ColorChoice,
+    },
+
+    /// Check the health of a place: instances and unanchored parts in each
+    /// service, scripts by where they run, SharedStrings used more than once,
+    /// oversized textures, broken Refs, and how large each part of the place
+    /// is when serialized.
+    Analyze {
+        input: PathBuf,
+
+        /// Print the report as JSON instead.
+        #[structopt(long)]
+        json: bool,
+
+        /// Where to find textures to check the sizes of.
+        #[structopt(flatten)]
+        assets: AssetOptions,
+
+        /// How many pixels wide or tall a texture can be before it's reported.
+        #[structopt(long, default_value = "1024")]
+        max_texture_size: u32,
+
+        /// How many levels below the top of the file to report serialized
+        /// sizes for.
+        #[structopt(long, default_value = "1")]
+        depth: usize,
+    },
+
+    /// Run a Luau script against a model or place file, then write the file
+    /// back out with the script's changes.
+    ///
+    /// The script sees the file as `game`, and can use a subset of Roblox's
+    /// Instance API on it. Any extra arguments are passed to the script as
+    /// `...`.
+    #[cfg(feature = "lua")]
+    Run {
+        script: PathBuf,
+        input: PathBuf,
+
+        /// Where to write the changed file, instead of over the input.
+        #[structopt(long, short)]
+        output: Option<PathBuf>,
+
+        /// Where `readAsset` finds assets.
+        #[structopt(flatten)]
+        assets: AssetOptions,
+
+        args: Vec<String>,
+    },
 
     /// View a binary file as an undefined text representation.
     ViewBinary { input: PathBuf },
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum ModelKind {
-    Binary,
-    Xml,
+/// Where commands that need assets, like textures and meshes, get them from.
+#[derive(Debug, StructOpt)]
+struct AssetOptions {
+    /// A directory of assets, named by asset ID like 123.png, with files for
+    /// rbxasset:// URLs at their paths within it.
+    #[structopt(long = "assets")]
+    dir: Option<PathBuf>,
+
+    /// Download assets that aren't in the assets directory from Roblox.
+    #[cfg(feature = "http")]
+    #[structopt(long)]
+    fetch_assets: bool,
+
+    /// The Open Cloud API key to download assets with.
+    #[cfg(feature = "http")]
+    #[structopt(long, env = "ROBLOX_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
 }
 
-impl ModelKind {
-    fn from_path(path: &Path) -> anyhow::Result<ModelKind> {
-        match path.extension().and_then(|ext| ext.to_str()) {
-            Some("rbxm") | Some("rbxl") => Ok(ModelKind::Binary),
-            Some("rbxmx") | Some("rbxlx") => Ok(ModelKind::Xml),
+impl AssetOptions {
+    /// Builds a resolver for these options, or returns `None` if no assets
+    /// were asked for.
+    fn resolver(self) -> Option<impl ContentResolver> {
+        let mut resolver = ChainResolver::new();
+        let mut empty = true;
 
-            _ => Err(anyhow!(
-                "not a Roblox model or place file: {}",
-                path.display()
-            )),
+        if let Some(dir) = self.dir {
+            resolver = resolver.with(FileSystemResolver::new(dir));
+            empty = false;
         }
+
+        #[cfg(feature = "http")]
+        if self.fetch_assets {
+            resolver = resolver.with(rbx_content::HttpResolver::new().api_key(self.api_key));
+            empty = false;
+        }
+
+        (!empty).then_some(resolver)
     }
 }
 
-fn run(options: Options) -> anyhow::Result<()> {
-    match options.subcommand {
-        Subcommand::Convert { input, output } => convert(&input, &output)?,
-        Subcommand::ViewBinary { input } => view_binary(&input)?,
+/// The versions of Roblox Studio that XML files are written for.
+#[derive(Debug, Clone, Copy)]
+enum Compatibility {
+    Modern,
+    Legacy,
+}
+
+impl FromStr for Compatibility {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        match value {
+            "modern" => Ok(Compatibility::Modern),
+            "legacy" => Ok(Compatibility::Legacy),
+            _ => bail!(
+                "unknown compatibility {:?}, expected modern or legacy",
+                value
+            ),
+        }
     }
+}
 
-    Ok(())
+impl From<Compatibility> for CompatibilityTarget {
+    fn from(value: Compatibility) -> Self {
+        match value {
+            Compatibility::Modern => CompatibilityTarget::Modern,
+            Compatibility::Legacy => CompatibilityTarget::Legacy,
+        }
+    }
 }
 
-fn convert(input_path: &Path, output_path: &Path) -> anyhow::Result<()> {
-    let input_kind = ModelKind::from_path(input_path)?;
-    let output_kind = ModelKind::from_path(output_path)?;
+fn run(options: Options) -> anyhow::Result<()> {
+    match options.subcommand {
+        Subcommand::Convert {
+            input,
+            output,
+            raw,
+            uncompressed,
+            compression_level,
+            compatibility,
+        } => {
+            let compression = if uncompressed {
+                CompressionOptions::uncompressed()
+            } else {
+                CompressionOptions::new().level(compression_level)
+            };
 
-    let input_file = BufReader::new(File::open(input_path)?);
+            let options = WriteOptions {
+                raw,
+                compression,
+                compatibility: compatibility.into(),
+            };
 
-    let dom = match input_kind {
-        ModelKind::Xml => {
-            let options = rbx_xml::DecodeOptions::new()
-                .property_behavior(rbx_xml::DecodePropertyBehavior::ReadUnknown);
+            model::write(&output, &model::read(&input, raw)?, &options)?;
+        }
+        Subcommand::Export {
+            input,
+            output,
+            assets,
+        } => export(&input, &output, assets)?,
+        Subcommand::View {
+            input,
+            properties,
+            attributes,
+            depth,
+            classes,
+            path,
+        } => {
+            let options = ViewOptions {
+                properties,
+                attributes,
+                depth,
+                classes,
+                path,
+            };
+
+            view::print_tree(&model::read(&input, false)?.dom, &options)?;
+        }
+        Subcommand::Diff {
+            left,
+            right,
+            json,
+            color,
+        } => {
+            let left = model::read(&left, false)?.dom;
+            let right = model::read(&right, false)?.dom;
+            let changes = diff::diff_doms(&left, &right);
 
-            rbx_xml::from_reader(input_file, options)
-                .with_context(|| format!("Failed to read {}", input_path.display()))?
+            if json {
+                diff::print_json(&left, &right, &changes)?;
+            } else {
+                diff::print_report(&left, &right, &changes, color)?;
+            }
         }
+        Subcommand::Analyze {
+            input,
+            json,
+            assets,
+            max_texture_size,
+            depth,
+        } => {
+            let resolver = assets.resolver();
+            let options = AnalyzeOptions {
+                assets: resolver
+                    .as_ref()
+                    .map(|resolver| resolver as &dyn ContentResolver),
+                max_texture_size,
+                depth,
+            };
+            let report = analyze::analyze(&model::read(&input, false)?.dom, &options)?;
 
-        ModelKind::Binary => rbx_binary::from_reader(input_file)
-            .with_context(|| format!("Failed to read {}", input_path.display()))?,
-    };
+            if json {
+                analyze::print_json(&report)?;
+            } else {
+                analyze::print_report(&report)?;
+            }
+        }
+        #[cfg(feature = "lua")]
+        Subcommand::Run {
+            script,
+            input,
+            output,
+            assets,
+            args,
+        } => {
+            let mut model = model::read(&input, false)?;
+            let resolver = assets
+                .resolver()
+                .map(|resolver| Box::new(resolver) as Box<dyn ContentResolver>);
+            lua::run_script(&script, &mut model, resolver, args)?;
 
-    let root_ids = dom.root().children();
+            let options = WriteOptions {
+                raw: false,
+                compression: CompressionOptions::new(),
+                compatibility: CompatibilityTarget::Modern,
+            };
+            model::write(output.as_ref().unwrap_or(&input), &model, &options)?;
+        }
+        Subcommand::ViewBinary { input } => view_binary(&input)?,
+    }
 
-    let output_file = BufWriter::new(File::create(output_path)?);
+    Ok(())
+}
 
-    match output_kind {
-        ModelKind::Xml => {
-            let options = rbx_xml::EncodeOptions::new()
-                .property_behavior(rbx_xml::EncodePropertyBehavior::WriteUnknown);
+fn export(input_path: &Path, output_path: &Path, assets: AssetOptions) -> anyhow::Result<()> {
+    let dom = model::read(input_path, false)?.dom;
+    let refs = dom.root().children();
+    let output = BufWriter::new(File::create(output_path)?);
 
-            rbx_xml::to_writer(output_file, &dom, root_ids, options)
-                .with_context(|| format!("Failed to write {}", output_path.display()))?;
-        }
+    let meshes = assets.resolver().map(ResolverMeshProvider::new);
+    let mut exporter = Exporter::new();
+    if let Some(meshes) = &meshes {
+        exporter = exporter.mesh_provider(meshes);
+    }
 
-        ModelKind::Binary => {
-            rbx_binary::to_writer(output_file, &dom, root_ids)
-                .with_context(|| format!("Failed to write {}", output_path.display()))?;
-        }
+    match output_path.extension().and_then(|ext| ext.to_str()) {
+        Some("glb") => exporter.to_glb(output, &dom, refs)?,
+        Some("gltf") => exporter.to_gltf(output, &dom, refs)?,
+        _ => bail!(
+            "not a glTF file, expected .glb or .gltf: {}",
+            output_path.display()
+        ),
     }
 
     Ok(())
 }
 
 fn view_binary(input_path: &Path) -> anyhow::Result<()> {
-    let input_kind = ModelKind::from_path(input_path)?;
+    let mut input_file = BufReader::new(File::open(input_path)?);
 
-    if input_kind != ModelKind::Binary {
+    if Format::detect(input_file.fill_buf()?) != Some(Format::Binary) {
         bail!("not a binary model or place file: {}", input_path.display());
     }
 
-    let input_file = BufReader::new(File::open(input_path)?);
-
     let model = rbx_binary::text_format::DecodedModel::from_reader(input_file);
 
     let stdout = io::stdout();
@@ -110,6 +401,9 @@ fn view_binary(input_path: &Path) -> anyhow::Result<()> {
 }
 
 fn main() {
+    #[cfg(feature = "tracing")]
+    env_logger::init();
+
     let options = Options::from_args();
 
     if let Err(err) = run(options) {
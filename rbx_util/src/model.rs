@@ -0,0 +1,604 @@
+//! Reading and writing model and place files in every format rbx-util knows.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, BufWriter};
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use fs_err::File;
+use rbx_binary::{CompressionOptions, UnknownProperty};
+use rbx_dom_weak::{types::Ref, WeakDom};
+use rbx_xml::{CompatibilityTarget, UnknownElement, UnknownElementKind};
+use xml::{attribute::OwnedAttribute, escape::escape_str_attribute, reader::XmlEvent};
+
+use crate::{interchange, project};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Binary,
+    Xml,
+    Json,
+    MessagePack,
+    Cbor,
+    Project,
+}
+
+impl Format {
+    /// Picks a format from the extension of a file, or picks
+    /// [`Format::Project`] for a Rojo project file or a directory.
+    pub fn from_path(path: &Path) -> anyhow::Result<Format> {
+        if project::is_project(path) {
+            return Ok(Format::Project);
+        }
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("rbxm") | Some("rbxl") => Ok(Format::Binary),
+            Some("rbxmx") | Some("rbxlx") => Ok(Format::Xml),
+            Some("json") => Ok(Format::Json),
+            Some("msgpack") => Ok(Format::MessagePack),
+            Some("cbor") => Ok(Format::Cbor),
+
+            _ => Err(anyhow!(
+                "not a Roblox model or place file: {}",
+                path.display()
+            )),
+        }
+    }
+
+    /// Picks a format from the first bytes of a file, or returns `None` if
+    /// they don't look like any of them.
+    ///
+    /// MessagePack files have nothing to recognize them by, so they're only
+    /// picked by their extension.
+    pub fn detect(contents: &[u8]) -> Option<Format> {
+        if contents.starts_with(b"<roblox!") {
+            return Some(Format::Binary);
+        }
+        if contents.starts_with(b"\xD9\xD9\xF7") {
+            return Some(Format::Cbor);
+        }
+
+        let contents = contents.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(contents);
+        let start = contents.iter().find(|byte| !byte.is_ascii_whitespace())?;
+
+        match start {
+            b'<' => Some(Format::Xml),
+            b'{' => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A DOM read from a file, along with what's needed to write it back out the
+/// way it came in.
+pub struct Model {
+    pub dom: WeakDom,
+    pub metadata: HashMap<String, String>,
+    pub unknown_properties: Vec<UnknownProperty>,
+    pub unknown_elements: Vec<UnknownElement>,
+}
+
+/// Reads a model or place file in any format, detecting which it is from its
+/// contents, or reads a Rojo project.
+///
+/// When `raw` is set, the reflection database isn't used, so every property
+/// keeps its name and type exactly as they are stored in the file. Projects
+/// always need the reflection database to know the types of their properties.
+pub fn read(path: &Path, raw: bool) -> anyhow::Result<Model> {
+    if project::is_project(path) {
+        let dom =
+            project::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+        return Ok(Model {
+            dom,
+            metadata: HashMap::new(),
+            unknown_properties: Vec::new(),
+            unknown_elements: Vec::new(),
+        });
+    }
+
+    let mut input = BufReader::new(File::open(path)?);
+
+    let format = match Format::detect(input.fill_buf()?) {
+        Some(format) => format,
+        None => Format::from_path(path)?,
+    };
+
+    let mut model = Model {
+        dom: WeakDom::new(rbx_dom_weak::InstanceBuilder::new("DataModel")),
+        metadata: HashMap::new(),
+        unknown_properties: Vec::new(),
+        unknown_elements: Vec::new(),
+    };
+
+    match format {
+        Format::Xml => {
+            let behavior = if raw {
+                rbx_xml::DecodePropertyBehavior::NoReflection
+            } else {
+                rbx_xml::DecodePropertyBehavior::ReadUnknown
+            };
+            let options = rbx_xml::DecodeOptions::new()
+                .property_behavior(behavior)
+                .preserve_unknown_elements(raw);
+
+            let (dom, report) = rbx_xml::from_reader_with_report(input, options)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+
+            let (unknown_properties, unknown_elements) =
+                from_unknown_elements(&dom, report.into_unknown_elements())
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+
+            model.dom = dom;
+            model.unknown_properties = unknown_properties;
+            model.unknown_elements = unknown_elements;
+        }
+
+        Format::Binary => {
+            let (dom, report) = rbx_binary::Deserializer::new()
+                .reflection(!raw)
+                .preserve_unknown_properties(raw)
+                .deserialize_with_report(input)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+
+            model.dom = dom;
+            model.metadata = report.metadata().clone();
+            model.unknown_properties = report.into_unknown_properties();
+        }
+
+        Format::Json | Format::MessagePack | Format::Cbor => {
+            let (dom, metadata) = interchange::from_reader(input, format)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+
+            model.dom = dom;
+            model.metadata = metadata;
+        }
+
+        Format::Project => unreachable!("projects are read before their contents"),
+    }
+
+    Ok(model)
+}
+
+/// How [`write`] writes a model.
+pub struct WriteOptions {
+    /// Whether to write without the reflection database. See [`read`].
+    pub raw: bool,
+
+    /// How binary files are compressed.
+    pub compression: CompressionOptions,
+
+    /// Which versions of Roblox Studio XML files are written for.
+    pub compatibility: CompatibilityTarget,
+}
+
+/// Writes the children of the root of a model to a file, in the format given
+/// by its extension, or as a Rojo project.
+pub fn write(path: &Path, model: &Model, options: &WriteOptions) -> anyhow::Result<()> {
+    let format = Format::from_path(path)?;
+    let root_ids = model.dom.root().children();
+
+    if format == Format::Project {
+        project::write(path, &model.dom, &xml_options(options))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        // Projects hold any instances they can't write as files in XML models.
+        warn_dropped(model);
+        return Ok(());
+    }
+
+    let output = BufWriter::new(File::create(path)?);
+
+    match format {
+        Format::Xml => {
+            let mut elements = model.unknown_elements.clone();
+            elements.extend(to_unknown_elements(&model.unknown_properties));
+            let options = xml_options(options).unknown_elements(elements);
+
+            rbx_xml::to_writer(output, &model.dom, root_ids, options)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+
+        Format::Binary => {
+            rbx_binary::Serializer::new()
+                .reflection(!options.raw)
+                .compression(options.compression)
+                .metadata(model.metadata.clone())
+                .unknown_properties(model.unknown_properties.clone())
+                .serialize(output, &model.dom, root_ids)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+
+            warn_dropped_elements(&model.unknown_elements);
+        }
+
+        Format::Json | Format::MessagePack | Format::Cbor => {
+            interchange::to_writer(output, format, &model.dom, &model.metadata)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+
+            warn_dropped(model);
+        }
+
+        Format::Project => unreachable!("projects are written before opening a file"),
+    }
+
+    Ok(())
+}
+
+fn xml_options(options: &WriteOptions) -> rbx_xml::EncodeOptions {
+    let behavior = if options.raw {
+        rbx_xml::EncodePropertyBehavior::NoReflection
+    } else {
+        rbx_xml::EncodePropertyBehavior::WriteUnknown
+    };
+
+    rbx_xml::EncodeOptions::new()
+        .property_behavior(behavior)
+        .compatibility_target(options.compatibility)
+}
+
+/// The name of the elements that hold properties of unknown binary types in
+/// XML files. Roblox has no such type, so rbx_xml keeps them as unknown
+/// elements when decoding without reflection.
+const UNKNOWN_PROPERTY_TAG: &str = "UnknownBinaryProperty";
+
+/// Turns properties of unknown binary types into XML elements, one for each
+/// instance, so that they survive being converted to XML and back.
+///
+/// A property's values can't be split apart without knowing its type, so
+/// the element of the first instance holds the data for all of them, and
+/// every element records where its instance falls in that data.
+fn to_unknown_elements(unknown_properties: &[UnknownProperty]) -> Vec<UnknownElement> {
+    let mut elements = Vec::new();
+
+    for unknown in unknown_properties {
+        let name = escape_str_attribute(unknown.property_name());
+
+        for (index, &referent) in unknown.referents().iter().enumerate() {
+            let start = format!(
+                "<{} name=\"{}\" typeId=\"{}\" index=\"{}\"",
+                UNKNOWN_PROPERTY_TAG,
+                name,
+                unknown.type_id(),
+                index
+            );
+
+            let xml = if index == 0 {
+                let data = base64::encode(unknown.data());
+                format!("{}>{}</{}>", start, data, UNKNOWN_PROPERTY_TAG)
+            } else {
+                format!("{} />", start)
+            };
+
+            elements.push(UnknownElement::new(
+                referent,
+                UnknownElementKind::Property,
+                xml,
+            ));
+        }
+    }
+
+    elements
+}
+
+/// Reassembles the properties written by [`to_unknown_elements`], returning
+/// them along with the unknown elements that weren't written by it.
+fn from_unknown_elements(
+    dom: &WeakDom,
+    elements: Vec<UnknownElement>,
+) -> anyhow::Result<(Vec<UnknownProperty>, Vec<UnknownElement>)> {
+    struct Pieces {
+        type_id: u8,
+        referents: BTreeMap<usize, Ref>,
+        data: Option<Vec<u8>>,
+    }
+
+    let mut others = Vec::new();
+    let mut properties: BTreeMap<(String, String), Pieces> = BTreeMap::new();
+
+    for element in elements {
+        let (attributes, text) = match parse_unknown_property(element.xml())? {
+            Some(parsed) => parsed,
+            None => {
+                others.push(element);
+                continue;
+            }
+        };
+
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|attribute| attribute.name.local_name == name)
+                .map(|attribute| attribute.value.as_str())
+                .ok_or_else(|| {
+                    anyhow!("{} is missing its {} attribute", UNKNOWN_PROPERTY_TAG, name)
+                })
+        };
+
+        let property_name = attribute("name")?.to_owned();
+        let type_id: u8 = attribute("typeId")?.parse()?;
+        let index: usize = attribute("index")?.parse()?;
+
+        let class_name = match dom.get_by_ref(element.referent()) {
+            Some(instance) => instance.class.clone(),
+            None => continue,
+        };
+
+        let pieces = properties
+            .entry((class_name, property_name))
+            .or_insert_with(|| Pieces {
+                type_id,
+                referents: BTreeMap::new(),
+                data: None,
+            });
+
+        if pieces.type_id != type_id || pieces.referents.insert(index, element.referent()).is_some()
+        {
+            bail!("{} elements disagree with each other", UNKNOWN_PROPERTY_TAG);
+        }
+        if index == 0 {
+            pieces.data = Some(base64::decode(text.trim())?);
+        }
+    }
+
+    let mut unknown_properties = Vec::new();
+
+    for ((class_name, property_name), pieces) in properties {
+        let complete = pieces
+            .referents
+            .keys()
+            .copied()
+            .eq(0..pieces.referents.len());
+        let data = match pieces.data {
+            Some(data) if complete => data,
+            _ => bail!(
+                "the {} elements of {}.{} are incomplete",
+                UNKNOWN_PROPERTY_TAG,
+                class_name,
+                property_name
+            ),
+        };
+
+        unknown_properties.push(UnknownProperty::new(
+            class_name,
+            property_name,
+            pieces.type_id,
+            pieces.referents.into_values().collect(),
+            data,
+        ));
+    }
+
+    Ok((unknown_properties, others))
+}
+
+/// Reads the attributes and text of `xml` if it's an element written by
+/// [`to_unknown_elements`].
+fn parse_unknown_property(xml: &str) -> anyhow::Result<Option<(Vec<OwnedAttribute>, String)>> {
+    let mut attributes = None;
+    let mut text = String::new();
+
+    for event in xml::EventReader::from_str(xml) {
+        match event? {
+            XmlEvent::StartElement {
+                name,
+                attributes: element_attributes,
+                ..
+            } if attributes.is_none() => {
+                if name.local_name != UNKNOWN_PROPERTY_TAG {
+                    return Ok(None);
+                }
+                attributes = Some(element_attributes);
+            }
+            XmlEvent::Characters(characters) => text.push_str(&characters),
+            _ => {}
+        }
+    }
+
+    Ok(attributes.map(|attributes| (attributes, text)))
+}
+
+/// Warns about everything in `model` that a format other than XML can't hold.
+fn warn_dropped(model: &Model) {
+    for unknown in &model.unknown_properties {
+        eprintln!(
+            "Warning: dropped property {}.{}, which has an unknown type ({:#04x})",
+            unknown.class_name(),
+            unknown.property_name(),
+            unknown.type_id(),
+        );
+    }
+
+    warn_dropped_elements(&model.unknown_elements);
+}
+
+fn warn_dropped_elements(unknown_elements: &[UnknownElement]) {
+    for element in unknown_elements {
+        eprintln!(
+            "Warning: dropped an element that only XML files can hold: {}",
+            element.xml()
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{
+        compare_doms,
+        types::{Content, SharedString, Vector3},
+        DomCompareOptions, InstanceBuilder,
+    };
+
+    const FORMATS: [(Format, &str); 5] = [
+        (Format::Binary, "rbxm"),
+        (Format::Xml, "rbxmx"),
+        (Format::Json, "json"),
+        (Format::MessagePack, "msgpack"),
+        (Format::Cbor, "cbor"),
+    ];
+
+    fn test_dom() -> WeakDom {
+        let part = InstanceBuilder::new("Part")
+            .with_name("Part")
+            .with_property("Anchored", true)
+            .with_property("Size", Vector3::new(4.0, 1.0, 2.0));
+        let weld = InstanceBuilder::new("WeldConstraint")
+            .with_property("Part0", part.referent())
+            .with_property("Part1", Ref::none());
+
+        WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Model")
+                    .with_name("Root")
+                    .with_child(part)
+                    .with_child(weld)
+                    .with_child(
+                        InstanceBuilder::new("StringValue")
+                            .with_name("Greeting")
+                            .with_property("Value", "Hello, world!"),
+                    )
+                    .with_child(
+                        InstanceBuilder::new("Decal")
+                            .with_property("Texture", Content::from("rbxassetid://1234")),
+                    )
+                    .with_child(
+                        InstanceBuilder::new("UnionOperation")
+                            .with_property("MeshData2", SharedString::new(vec![7; 64])),
+                    ),
+            ),
+        )
+    }
+
+    fn test_model() -> Model {
+        Model {
+            dom: test_dom(),
+            metadata: HashMap::new(),
+            unknown_properties: Vec::new(),
+            unknown_elements: Vec::new(),
+        }
+    }
+
+    fn test_options() -> WriteOptions {
+        WriteOptions {
+            raw: false,
+            compression: CompressionOptions::new(),
+            compatibility: CompatibilityTarget::Modern,
+        }
+    }
+
+    /// Ensures that a DOM comes back unchanged after being written in one
+    /// format, converted to each other format, and read again.
+    #[test]
+    fn round_trip_every_pair() {
+        let tree = test_dom();
+        let model = test_model();
+
+        let dir = std::env::temp_dir().join(format!("rbx_util_round_trip_{}", std::process::id()));
+        fs_err::create_dir_all(&dir).unwrap();
+
+        for (from, from_extension) in FORMATS {
+            let input = dir.join(format!("input.{}", from_extension));
+            write(&input, &model, &test_options()).unwrap();
+
+            for (to, to_extension) in FORMATS {
+                let output = dir.join(format!("output.{}", to_extension));
+                write(&output, &read(&input, false).unwrap(), &test_options()).unwrap();
+
+                let differences = compare_doms(
+                    &tree,
+                    &read(&output, false).unwrap().dom,
+                    &DomCompareOptions::new(),
+                );
+                assert!(
+                    differences.is_empty(),
+                    "{:?} to {:?}: {:#?}",
+                    from,
+                    to,
+                    differences
+                );
+            }
+        }
+
+        fs_err::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unknown_extension() {
+        let dir = std::env::temp_dir().join(format!("rbx_util_extension_{}", std::process::id()));
+        fs_err::create_dir_all(&dir).unwrap();
+        let path = dir.join("model.txt");
+
+        let error = write(&path, &test_model(), &test_options()).unwrap_err();
+        assert!(!path.exists());
+
+        // Files are read by their contents first, so only one whose contents
+        // aren't recognized either is rejected.
+        fs_err::write(&path, "local x = 1").unwrap();
+        let read_error = read(&path, false).err().unwrap();
+        fs_err::remove_dir_all(&dir).unwrap();
+
+        for error in [error, read_error] {
+            assert_eq!(
+                error.to_string(),
+                format!("not a Roblox model or place file: {}", path.display())
+            );
+        }
+    }
+
+    /// Ensures that a property of a type rbx_binary doesn't know survives
+    /// being converted from a binary file to XML and back.
+    #[test]
+    fn unknown_property_through_xml() {
+        let first = InstanceBuilder::new("StringValue").with_name("First");
+        let second = InstanceBuilder::new("StringValue").with_name("Second");
+        let unknown = UnknownProperty::new(
+            "StringValue".to_owned(),
+            "Mystery".to_owned(),
+            0x7f,
+            vec![second.referent(), first.referent()],
+            vec![1, 2, 3, 4, 5, 6],
+        );
+        let tree = WeakDom::new(InstanceBuilder::new("Folder").with_children([first, second]));
+
+        let dir = std::env::temp_dir().join(format!("rbx_util_transcode_{}", std::process::id()));
+        fs_err::create_dir_all(&dir).unwrap();
+        let binary = dir.join("input.rbxm");
+        let xml = dir.join("converted.rbxmx");
+        let output = dir.join("output.rbxm");
+
+        rbx_binary::Serializer::new()
+            .unknown_properties(vec![unknown])
+            .serialize(File::create(&binary).unwrap(), &tree, &[tree.root_ref()])
+            .unwrap();
+
+        let options = WriteOptions {
+            raw: true,
+            compression: CompressionOptions::new(),
+            compatibility: CompatibilityTarget::Modern,
+        };
+        write(&xml, &read(&binary, true).unwrap(), &options).unwrap();
+        write(&output, &read(&xml, true).unwrap(), &options).unwrap();
+
+        let (dom, report) = rbx_binary::Deserializer::new()
+            .preserve_unknown_properties(true)
+            .deserialize_with_report(BufReader::new(File::open(&output).unwrap()))
+            .unwrap();
+        fs_err::remove_dir_all(&dir).unwrap();
+
+        let unknown = match report.unknown_properties() {
+            [unknown] => unknown,
+            other => panic!("unexpected unknown properties {:?}", other),
+        };
+        assert_eq!(unknown.class_name(), "StringValue");
+        assert_eq!(unknown.property_name(), "Mystery");
+        assert_eq!(unknown.type_id(), 0x7f);
+        assert_eq!(unknown.data(), [1, 2, 3, 4, 5, 6]);
+
+        let names: Vec<_> = unknown
+            .referents()
+            .iter()
+            .map(|&referent| dom.get_by_ref(referent).unwrap().name.as_str())
+            .collect();
+        assert_eq!(names, ["Second", "First"]);
+    }
+}
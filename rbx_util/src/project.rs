@@ -0,0 +1,1094 @@
+//! Reading and writing Rojo projects: a `*.project.json` file describing a
+//! tree of instances, along with the files and directories it points to.
+//!
+//! Files are turned into instances the way Rojo does it:
+//!
+//! - Directories become Folders, or the script in their `init.lua` with the
+//!   rest of the directory as its children.
+//! - `*.server.lua`, `*.client.lua`, and `*.lua` become Scripts, LocalScripts,
+//!   and ModuleScripts, as do the same names ending in `.luau`.
+//! - `*.txt` becomes a StringValue.
+//! - `*.rbxm`, `*.rbxmx`, and `*.model.json` become the instance they contain.
+//! - `*.project.json` becomes the tree of that project.
+//! - `*.meta.json`, or `init.meta.json` in a directory, changes the class,
+//!   properties, or attributes of the instance next to it.
+//!
+//! Classes named by project, meta, and model files must be known to the
+//! reflection database, so that typos are caught when the project is read.
+//!
+//! Property values can be written implicitly, like `[1, 2, 3]` for a Vector3,
+//! when the reflection database knows their type, or explicitly, like
+//! `{"Vector3": [1, 2, 3]}`.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context};
+use fs_err as fs;
+use rbx_dom_weak::{
+    types::{Enum, Ref, Variant},
+    Instance, InstanceBuilder, WeakDom,
+};
+use rbx_reflection::{ClassTag, DataType, ReflectionDatabase};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::model;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Project {
+    name: String,
+    tree: ProjectNode,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProjectNode {
+    #[serde(
+        rename = "$className",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    class_name: Option<String>,
+
+    #[serde(rename = "$path", default, skip_serializing_if = "Option::is_none")]
+    path: Option<PathBuf>,
+
+    #[serde(
+        rename = "$properties",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    properties: BTreeMap<String, Value>,
+
+    #[serde(
+        rename = "$attributes",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    attributes: BTreeMap<String, Value>,
+
+    // Rojo uses this when syncing into Studio, which doesn't matter here.
+    #[serde(
+        rename = "$ignoreUnknownInstances",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    ignore_unknown_instances: Option<bool>,
+
+    #[serde(flatten)]
+    children: BTreeMap<String, ProjectNode>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Meta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    class_name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    properties: BTreeMap<String, Value>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    attributes: BTreeMap<String, Value>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ignore_unknown_instances: Option<bool>,
+}
+
+/// An instance in a `*.model.json` file, which Rojo reads with keys in either
+/// case.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelJsonInstance {
+    #[serde(alias = "Name", default)]
+    name: Option<String>,
+
+    #[serde(alias = "ClassName")]
+    class_name: String,
+
+    #[serde(alias = "Properties", default)]
+    properties: BTreeMap<String, Value>,
+
+    #[serde(alias = "Attributes", default)]
+    attributes: BTreeMap<String, Value>,
+
+    #[serde(alias = "Children", default)]
+    children: Vec<ModelJsonInstance>,
+}
+
+/// The kinds of script Rojo makes from files, by the suffix before `.lua`.
+const SCRIPT_SUFFIXES: &[(&str, &str)] = &[
+    (".server", "Script"),
+    (".client", "LocalScript"),
+    ("", "ModuleScript"),
+];
+
+/// Tells whether `path` should be read as a Rojo project: a `*.project.json`
+/// file, or a directory of files.
+pub fn is_project(path: &Path) -> bool {
+    path.is_dir() || file_name(path).ends_with(".project.json")
+}
+
+/// Reads the Rojo project at `path`, which is either a `*.project.json` file
+/// or a directory. A directory is read as its `default.project.json` if it has
+/// one, or else as a tree of files.
+///
+/// A project whose tree is a `DataModel` is read as a place, with its services
+/// at the top of the DOM. Any other project is read as a model containing the
+/// one instance at the top of its tree.
+pub fn read(path: &Path) -> anyhow::Result<WeakDom> {
+    let mut reader = ProjectReader {
+        dom: WeakDom::new(InstanceBuilder::new("DataModel")),
+        database: rbx_reflection_database::global(),
+    };
+    let root_ref = reader.dom.root_ref();
+
+    let project_path = path.join("default.project.json");
+    let path = if path.is_dir() && project_path.is_file() {
+        project_path.as_path()
+    } else {
+        path
+    };
+
+    if path.is_dir() {
+        reader.read_path(root_ref, path)?;
+        return Ok(reader.dom);
+    }
+
+    let project = read_project_file(path)?;
+    let base = path.parent().unwrap();
+
+    if project.tree.class_name.as_deref() == Some("DataModel") && project.tree.path.is_none() {
+        for (name, child) in &project.tree.children {
+            reader.read_node(root_ref, name, child, base)?;
+        }
+    } else {
+        reader.read_node(root_ref, &project.name, &project.tree, base)?;
+    }
+
+    Ok(reader.dom)
+}
+
+fn read_project_file(path: &Path) -> anyhow::Result<Project> {
+    let contents = fs::read(path)?;
+    serde_json::from_slice(&contents)
+        .with_context(|| format!("Failed to read project {}", path.display()))
+}
+
+fn file_name(path: &Path) -> &str {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+}
+
+struct ProjectReader<'a> {
+    dom: WeakDom,
+    database: &'a ReflectionDatabase<'a>,
+}
+
+impl ProjectReader<'_> {
+    fn read_node(
+        &mut self,
+        parent: Ref,
+        name: &str,
+        node: &ProjectNode,
+        base: &Path,
+    ) -> anyhow::Result<Ref> {
+        // Rojo lets services leave out their class, since it's the same as
+        // their name.
+        let class_name = match &node.class_name {
+            Some(class_name) => Some(class_name.as_str()),
+            None if is_service(self.database, name) => Some(name),
+            None => None,
+        };
+        if let Some(class_name) = class_name {
+            self.check_class(name, class_name)?;
+        }
+
+        let referent = match (&node.path, class_name) {
+            (Some(path), _) => {
+                let path = base.join(path);
+                let referent = self
+                    .read_path(parent, &path)?
+                    .ok_or_else(|| anyhow!("{} is not a file Rojo can read", path.display()))?;
+
+                if let Some(class_name) = class_name {
+                    self.set_folder_class(referent, class_name, &path)?;
+                }
+                referent
+            }
+            (None, Some(class_name)) => self.dom.insert(parent, InstanceBuilder::new(class_name)),
+            (None, None) => bail!("{} has neither a $className nor a $path", name),
+        };
+
+        self.dom.get_by_ref_mut(referent).unwrap().name = name.to_owned();
+        self.apply_values(referent, &node.properties, &node.attributes)?;
+
+        for (child_name, child) in &node.children {
+            self.read_node(referent, child_name, child, base)?;
+        }
+
+        Ok(referent)
+    }
+
+    /// Reads a file or directory into an instance under `parent`, or returns
+    /// `None` if it isn't something Rojo turns into an instance.
+    fn read_path(&mut self, parent: Ref, path: &Path) -> anyhow::Result<Option<Ref>> {
+        if path.is_dir() {
+            let project_path = path.join("default.project.json");
+            if project_path.is_file() {
+                return self.read_nested_project(parent, &project_path).map(Some);
+            }
+            return self.read_dir(parent, path).map(Some);
+        }
+
+        let name = file_name(path);
+
+        if name.ends_with(".meta.json") {
+            return Ok(None);
+        }
+        if name.ends_with(".project.json") {
+            return self.read_nested_project(parent, path).map(Some);
+        }
+
+        let (stem, referent) = if let Some(stem) = name.strip_suffix(".model.json") {
+            let contents = fs::read(path)?;
+            let instance: ModelJsonInstance = serde_json::from_slice(&contents)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            (stem, self.read_model_json(parent, stem, instance)?)
+        } else if let Some(stem) = name
+            .strip_suffix(".rbxm")
+            .or_else(|| name.strip_suffix(".rbxmx"))
+        {
+            (stem, self.read_model_file(parent, path)?)
+        } else if let Some(stem) = name.strip_suffix(".txt") {
+            let value = fs::read_to_string(path)?;
+            let builder = InstanceBuilder::new("StringValue").with_property("Value", value);
+            (stem, self.dom.insert(parent, builder))
+        } else if let Some((stem, class_name)) = script_stem(name) {
+            let source = fs::read_to_string(path)?;
+            let builder = InstanceBuilder::new(class_name).with_property("Source", source);
+            (stem, self.dom.insert(parent, builder))
+        } else {
+            return Ok(None);
+        };
+
+        self.dom.get_by_ref_mut(referent).unwrap().name = stem.to_owned();
+        self.apply_meta(
+            referent,
+            &path.with_file_name(format!("{}.meta.json", stem)),
+        )?;
+
+        Ok(Some(referent))
+    }
+
+    fn read_dir(&mut self, parent: Ref, path: &Path) -> anyhow::Result<Ref> {
+        let mut entries = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort();
+
+        let init = entries.iter().find_map(|entry| {
+            script_stem(file_name(entry))
+                .filter(|(stem, _)| *stem == "init")
+                .map(|(_, class_name)| (entry.clone(), class_name))
+        });
+
+        let builder = match &init {
+            Some((init_path, class_name)) => InstanceBuilder::new(*class_name)
+                .with_property("Source", fs::read_to_string(init_path)?),
+            None => InstanceBuilder::new("Folder"),
+        };
+        let referent = self.dom.insert(parent, builder.with_name(file_name(path)));
+
+        for entry in &entries {
+            let is_init = matches!(&init, Some((init_path, _)) if init_path == entry);
+            if !is_init && file_name(entry) != "init.meta.json" {
+                self.read_path(referent, entry)?;
+            }
+        }
+
+        self.apply_meta(referent, &path.join("init.meta.json"))?;
+        Ok(referent)
+    }
+
+    fn read_nested_project(&mut self, parent: Ref, path: &Path) -> anyhow::Result<Ref> {
+        let project = read_project_file(path)?;
+        self.read_node(parent, &project.name, &project.tree, path.parent().unwrap())
+    }
+
+    fn read_model_json(
+        &mut self,
+        parent: Ref,
+        name: &str,
+        instance: ModelJsonInstance,
+    ) -> anyhow::Result<Ref> {
+        let name = instance.name.as_deref().unwrap_or(name);
+        self.check_class(name, &instance.class_name)?;
+        let builder = InstanceBuilder::new(&instance.class_name).with_name(name);
+        let referent = self.dom.insert(parent, builder);
+
+        self.apply_values(referent, &instance.properties, &instance.attributes)?;
+
+        for child in instance.children {
+            let child_name = child.class_name.clone();
+            self.read_model_json(referent, &child_name, child)?;
+        }
+
+        Ok(referent)
+    }
+
+    fn read_model_file(&mut self, parent: Ref, path: &Path) -> anyhow::Result<Ref> {
+        let mut model = model::read(path, false)?;
+
+        let referent = match model.dom.root().children() {
+            [referent] => *referent,
+            _ => bail!(
+                "{} must contain exactly one instance to be part of a project",
+                path.display()
+            ),
+        };
+
+        // Moving the instance keeps its referent, so Ref properties within
+        // the file still point where they did.
+        model.dom.transfer(referent, &mut self.dom, parent);
+        Ok(referent)
+    }
+
+    fn apply_meta(&mut self, referent: Ref, path: &Path) -> anyhow::Result<()> {
+        if !path.is_file() {
+            return Ok(());
+        }
+
+        let contents = fs::read(path)?;
+        let meta: Meta = serde_json::from_slice(&contents)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        if let Some(class_name) = &meta.class_name {
+            self.check_class(&path.display().to_string(), class_name)?;
+            self.set_folder_class(referent, class_name, path)?;
+        }
+        self.apply_values(referent, &meta.properties, &meta.attributes)
+            .with_context(|| format!("Failed to apply {}", path.display()))
+    }
+
+    fn check_class(&self, name: &str, class_name: &str) -> anyhow::Result<()> {
+        if !self.database.classes.contains_key(class_name) {
+            bail!(
+                "{} has the class {}, which isn't a known class",
+                name,
+                class_name
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Changes the class of an instance that was read from a directory, which
+    /// is the only kind Rojo lets a project or meta file change the class of.
+    fn set_folder_class(
+        &mut self,
+        referent: Ref,
+        class_name: &str,
+        path: &Path,
+    ) -> anyhow::Result<()> {
+        let instance = self.dom.get_by_ref_mut(referent).unwrap();
+
+        if instance.class != "Folder" {
+            bail!(
+                "the class of {} can't be changed, since it isn't a directory",
+                path.display()
+            );
+        }
+
+        instance.class = class_name.to_owned();
+        Ok(())
+    }
+
+    fn apply_values(
+        &mut self,
+        referent: Ref,
+        properties: &BTreeMap<String, Value>,
+        attributes: &BTreeMap<String, Value>,
+    ) -> anyhow::Result<()> {
+        let instance = self.dom.get_by_ref_mut(referent).unwrap();
+
+        for (key, value) in properties {
+            let (name, value) = resolve_property(self.database, &instance.class, key, value)?;
+            instance.properties.insert(name, value);
+        }
+
+        if !attributes.is_empty() {
+            let mut resolved = match instance.properties.remove("Attributes") {
+                Some(Variant::Attributes(existing)) => existing,
+                _ => Default::default(),
+            };
+            for (key, value) in attributes {
+                let value = resolve_attribute(value)
+                    .with_context(|| format!("Invalid value for attribute {}", key))?;
+                resolved.insert(key.clone(), value);
+            }
+            instance
+                .properties
+                .insert("Attributes".to_owned(), Variant::Attributes(resolved));
+        }
+
+        Ok(())
+    }
+}
+
+fn is_service(database: &ReflectionDatabase, class_name: &str) -> bool {
+    matches!(
+        database.classes.get(class_name),
+        Some(class) if class.tags.contains(&ClassTag::Service)
+    )
+}
+
+/// Splits a file name like `Main.server.lua` into its stem and the class of
+/// script Rojo makes from it.
+fn script_stem(name: &str) -> Option<(&str, &'static str)> {
+    let rest = name
+        .strip_suffix(".luau")
+        .or_else(|| name.strip_suffix(".lua"))?;
+
+    SCRIPT_SUFFIXES.iter().find_map(|(suffix, class_name)| {
+        rest.strip_suffix(suffix)
+            .filter(|stem| !stem.is_empty())
+            .map(|stem| (stem, *class_name))
+    })
+}
+
+/// Converts the value of a property in a project or meta file, returning it
+/// with the name the property has in memory.
+fn resolve_property(
+    database: &ReflectionDatabase,
+    class_name: &str,
+    key: &str,
+    value: &Value,
+) -> anyhow::Result<(String, Variant)> {
+    let descriptors = database.find_property_descriptors(class_name, key);
+    let name = descriptors
+        .map_or(key, |descriptors| descriptors.canonical.name.as_ref())
+        .to_owned();
+
+    if let Some(variant) = explicit_value(value) {
+        return Ok((name, variant));
+    }
+
+    let descriptors = descriptors.ok_or_else(|| {
+        anyhow!(
+            "{}.{} isn't a known property, so its type must be given, like {{\"String\": \"value\"}}",
+            class_name,
+            key
+        )
+    })?;
+
+    let variant = match (&descriptors.canonical.data_type, value) {
+        (DataType::Enum(enum_name), Value::String(item_name)) => database
+            .enums
+            .get(enum_name.as_ref())
+            .and_then(|descriptor| descriptor.items.get(item_name.as_str()))
+            .map(|&item| Variant::Enum(Enum::from_u32(item)))
+            .ok_or_else(|| anyhow!("{} is not an item of Enum.{}", item_name, enum_name))?,
+        (DataType::Enum(_), value) => Variant::Enum(
+            serde_json::from_value(value.clone())
+                .with_context(|| format!("Invalid value for {}.{}", class_name, key))?,
+        ),
+        (DataType::Value(ty), value) => {
+            let mut tagged = serde_json::Map::new();
+            tagged.insert(format!("{:?}", ty), value.clone());
+
+            serde_json::from_value(Value::Object(tagged))
+                .with_context(|| format!("Invalid value for {}.{}", class_name, key))?
+        }
+        _ => bail!("{}.{} can't be set from a project", class_name, key),
+    };
+
+    Ok((name, variant))
+}
+
+/// Converts the value of an attribute, which can be given implicitly as a
+/// bool, number, or string.
+fn resolve_attribute(value: &Value) -> anyhow::Result<Variant> {
+    match value {
+        Value::Bool(value) => Ok(Variant::Bool(*value)),
+        Value::Number(value) => Ok(Variant::Float64(value.as_f64().unwrap())),
+        Value::String(value) => Ok(Variant::String(value.clone())),
+        value => explicit_value(value)
+            .ok_or_else(|| anyhow!("expected a bool, number, string, or explicit value")),
+    }
+}
+
+/// Reads a value with its type given, like `{"Vector3": [1, 2, 3]}`.
+fn explicit_value(value: &Value) -> Option<Variant> {
+    match value {
+        Value::Object(map) if map.len() == 1 => serde_json::from_value(value.clone()).ok(),
+        _ => None,
+    }
+}
+
+/// Writes the children of the root of `dom` as a Rojo project: a project file
+/// at `path`, and the files it points to next to it.
+///
+/// A place is written with one entry for each service, whose contents go in a
+/// directory of the same name. Anything else must be a single instance, which
+/// becomes the tree of the project.
+///
+/// Folders and scripts are written as directories and files, with any
+/// properties that differ from their defaults in meta files. Every other
+/// instance is written as an XML model file with all of its descendants.
+/// Existing files are overwritten, but files that aren't part of the DOM are
+/// left alone.
+pub fn write(
+    path: &Path,
+    dom: &WeakDom,
+    xml_options: &rbx_xml::EncodeOptions,
+) -> anyhow::Result<()> {
+    let writer = ProjectWriter {
+        dom,
+        database: rbx_reflection_database::global(),
+        xml_options,
+    };
+    let base = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(base)?;
+
+    let children = dom.root().children();
+    let is_place = !children.is_empty()
+        && children
+            .iter()
+            .all(|&child| is_service(writer.database, &dom.get_by_ref(child).unwrap().class));
+
+    let (name, tree) = if is_place {
+        // Places are named after their project file, or the directory it's in
+        // when it's the default project.
+        let mut name = file_name(path)
+            .strip_suffix(".project.json")
+            .unwrap_or_default()
+            .to_owned();
+        if name == "default" {
+            name = file_name(&base.canonicalize()?).to_owned();
+        }
+
+        let mut tree = ProjectNode {
+            class_name: Some("DataModel".to_owned()),
+            ..Default::default()
+        };
+        let mut names = HashSet::new();
+
+        for &child in children {
+            let service = dom.get_by_ref(child).unwrap();
+            writer.claim_name(&mut names, base, &service.name)?;
+
+            let mut node = ProjectNode {
+                class_name: Some(service.class.clone()),
+                properties: writer.changed_properties(service, &[], true),
+                ..Default::default()
+            };
+            if !service.children().is_empty() {
+                let dir = base.join(&service.name);
+                fs::create_dir_all(&dir)?;
+                writer.write_children(&dir, service)?;
+                node.path = Some(PathBuf::from(&service.name));
+            }
+
+            tree.children.insert(service.name.clone(), node);
+        }
+
+        (name, tree)
+    } else {
+        let child = match children {
+            [child] => *child,
+            _ => bail!("only a place or a model with one instance can be written as a project"),
+        };
+
+        // The name of a model project is the name of its instance.
+        let name = dom.get_by_ref(child).unwrap().name.clone();
+        let tree = ProjectNode {
+            path: Some(writer.write_instance(base, child)?),
+            ..Default::default()
+        };
+
+        (name, tree)
+    };
+
+    let mut contents = serde_json::to_string_pretty(&Project { name, tree })?;
+    contents.push('\n');
+    fs::write(path, contents)?;
+
+    Ok(())
+}
+
+struct ProjectWriter<'a> {
+    dom: &'a WeakDom,
+    database: &'a ReflectionDatabase<'a>,
+    xml_options: &'a rbx_xml::EncodeOptions,
+}
+
+impl ProjectWriter<'_> {
+    /// Writes an instance into `dir`, returning the name of the file or
+    /// directory it was written as.
+    fn write_instance(&self, dir: &Path, referent: Ref) -> anyhow::Result<PathBuf> {
+        let instance = self.dom.get_by_ref(referent).unwrap();
+        let name = &instance.name;
+
+        let script_suffix = SCRIPT_SUFFIXES
+            .iter()
+            .find(|(_, class_name)| *class_name == instance.class)
+            .map(|(suffix, _)| *suffix);
+        let is_folder = instance.class == "Folder";
+
+        let meta = match (script_suffix, is_folder) {
+            (Some(_), _) => self.meta(instance, &["Source"]),
+            (None, true) => self.meta(instance, &[]),
+            (None, false) => None,
+        };
+
+        let (file_name, meta_path) = match (meta, script_suffix) {
+            // Folders, and scripts with children, are written as directories.
+            (Some(meta), _) if is_folder || !instance.children().is_empty() => {
+                let path = dir.join(name);
+                fs::create_dir_all(&path)?;
+
+                if let Some(suffix) = script_suffix {
+                    fs::write(
+                        path.join(format!("init{}.luau", suffix)),
+                        script_source(instance),
+                    )?;
+                }
+                self.write_children(&path, instance)?;
+
+                (
+                    PathBuf::from(name),
+                    Some((path.join("init.meta.json"), meta)),
+                )
+            }
+            (Some(meta), Some(suffix)) => {
+                let file_name = format!("{}{}.luau", name, suffix);
+                fs::write(dir.join(&file_name), script_source(instance))?;
+
+                let meta_path = dir.join(format!("{}.meta.json", name));
+                (PathBuf::from(file_name), Some((meta_path, meta)))
+            }
+            _ => {
+                let file_name = format!("{}.rbxmx", name);
+                let output = std::io::BufWriter::new(fs::File::create(dir.join(&file_name))?);
+                rbx_xml::to_writer(output, self.dom, &[referent], self.xml_options.clone())
+                    .with_context(|| {
+                        format!("Failed to write {}", dir.join(&file_name).display())
+                    })?;
+
+                (PathBuf::from(file_name), None)
+            }
+        };
+
+        if let Some((meta_path, meta)) = meta_path {
+            if meta.properties.is_empty() && meta.attributes.is_empty() {
+                if meta_path.is_file() {
+                    fs::remove_file(&meta_path)?;
+                }
+            } else {
+                let mut contents = serde_json::to_string_pretty(&meta)?;
+                contents.push('\n');
+                fs::write(meta_path, contents)?;
+            }
+        }
+
+        Ok(file_name)
+    }
+
+    fn write_children(&self, dir: &Path, instance: &Instance) -> anyhow::Result<()> {
+        let mut names = HashSet::new();
+
+        for &child in instance.children() {
+            let name = &self.dom.get_by_ref(child).unwrap().name;
+            self.claim_name(&mut names, dir, name)?;
+            self.write_instance(dir, child)?;
+        }
+
+        Ok(())
+    }
+
+    /// Makes sure that an instance can be written as a file named after it,
+    /// which no other instance in the same directory has already taken.
+    fn claim_name(
+        &self,
+        names: &mut HashSet<String>,
+        dir: &Path,
+        name: &str,
+    ) -> anyhow::Result<()> {
+        let is_valid = !name.is_empty()
+            && name != "."
+            && name != ".."
+            && !name.starts_with("init.")
+            && !name.contains(&['/', '\\', ':', '*', '?', '"', '<', '>', '|'][..]);
+        if !is_valid {
+            bail!(
+                "the instance {:?} in {} can't be written as a file",
+                name,
+                dir.display()
+            );
+        }
+
+        // Some file systems don't tell names apart by case.
+        if !names.insert(name.to_lowercase()) {
+            bail!(
+                "more than one instance in {} is named {:?}",
+                dir.display(),
+                name
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Builds the meta file for a folder or script, or returns `None` if one
+    /// of its properties can't be written in one.
+    fn meta(&self, instance: &Instance, skip: &[&str]) -> Option<Meta> {
+        let properties = instance
+            .properties
+            .iter()
+            .filter(|(key, _)| !skip.contains(&key.as_str()));
+        if properties
+            .clone()
+            .any(|(_, value)| matches!(value, Variant::Ref(_) | Variant::SharedString(_)))
+        {
+            return None;
+        }
+
+        let attributes = match instance.properties.get("Attributes") {
+            Some(Variant::Attributes(attributes)) => attributes
+                .iter()
+                .map(|(key, value)| Some((key.clone(), serde_json::to_value(value).ok()?)))
+                .collect::<Option<_>>()?,
+            _ => BTreeMap::new(),
+        };
+
+        let mut skip = skip.to_vec();
+        skip.push("Attributes");
+
+        Some(Meta {
+            properties: self.changed_properties(instance, &skip, false),
+            attributes,
+            ..Default::default()
+        })
+    }
+
+    /// The properties of an instance that differ from the defaults for its
+    /// class, written explicitly. Properties that can't be written are left
+    /// out, with a warning if `warn` is set.
+    fn changed_properties(
+        &self,
+        instance: &Instance,
+        skip: &[&str],
+        warn: bool,
+    ) -> BTreeMap<String, Value> {
+        let defaults = self.database.class_defaults(&instance.class);
+        let mut properties = BTreeMap::new();
+
+        for (key, value) in &instance.properties {
+            if skip.contains(&key.as_str())
+                || defaults
+                    .iter()
+                    .any(|(name, default)| *name == key.as_str() && default == value)
+            {
+                continue;
+            }
+
+            match value {
+                Variant::Ref(_) | Variant::SharedString(_) => {}
+                value => {
+                    if let Ok(json) = serde_json::to_value(value) {
+                        properties.insert(key.clone(), json);
+                        continue;
+                    }
+                }
+            }
+
+            if warn {
+                eprintln!(
+                    "Warning: dropped property {}.{}, which can't be written in a project",
+                    instance.name, key
+                );
+            }
+        }
+
+        properties
+    }
+}
+
+fn script_source(instance: &Instance) -> String {
+    match instance.properties.get("Source") {
+        Some(Variant::String(source)) => source.clone(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{compare_doms, types::Vector3, DomCompareOptions};
+
+    /// A directory for a test's files that's removed when the test ends.
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "rbx_util_project_{}_{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, relative: &str, contents: &str) {
+            let path = self.0.join(relative);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn child<'a>(dom: &'a WeakDom, parent: &Instance, name: &str) -> &'a Instance {
+        parent
+            .children()
+            .iter()
+            .map(|&referent| dom.get_by_ref(referent).unwrap())
+            .find(|instance| instance.name == name)
+            .unwrap_or_else(|| panic!("{} has no child named {}", parent.name, name))
+    }
+
+    fn assert_same(left: &WeakDom, right: &WeakDom) {
+        let options = DomCompareOptions::new().ignore_child_order(true);
+        let differences = compare_doms(left, right, &options);
+        assert!(differences.is_empty(), "{:#?}", differences);
+    }
+
+    /// Reads a place project using every kind of node, writes it back out as
+    /// a project, and reads that again.
+    #[test]
+    fn place_round_trip() {
+        let dir = TestDir::new("place");
+        dir.write(
+            "default.project.json",
+            r#"{
+                "name": "Game",
+                "tree": {
+                    "$className": "DataModel",
+                    "ReplicatedStorage": {
+                        "$path": "shared",
+                        "$ignoreUnknownInstances": true,
+                        "Settings": {
+                            "$className": "Configuration",
+                            "$attributes": { "Difficulty": 3 }
+                        }
+                    },
+                    "Workspace": {
+                        "$properties": { "Gravity": 100 },
+                        "Baseplate": {
+                            "$className": "Part",
+                            "$properties": {
+                                "Anchored": true,
+                                "Size": { "Vector3": [64, 1, 64] }
+                            }
+                        }
+                    }
+                }
+            }"#,
+        );
+        dir.write("shared/Util.lua", "return {}\n");
+        dir.write("shared/Main.server.luau", "print(\"hi\")\n");
+        dir.write("shared/Message.txt", "Hello");
+        dir.write("shared/Module/init.lua", "return 1\n");
+        dir.write("shared/Module/Helper.client.lua", "-- helper\n");
+
+        let dom = read(&dir.0).unwrap();
+        let root = dom.root();
+
+        let storage = child(&dom, root, "ReplicatedStorage");
+        assert_eq!(storage.class, "ReplicatedStorage");
+        assert_eq!(child(&dom, storage, "Util").class, "ModuleScript");
+        assert_eq!(child(&dom, storage, "Main").class, "Script");
+        assert_eq!(
+            child(&dom, storage, "Message").properties.get("Value"),
+            Some(&Variant::String("Hello".to_owned()))
+        );
+        let module = child(&dom, storage, "Module");
+        assert_eq!(module.class, "ModuleScript");
+        assert_eq!(child(&dom, module, "Helper").class, "LocalScript");
+        assert_eq!(child(&dom, storage, "Settings").class, "Configuration");
+
+        let workspace = child(&dom, root, "Workspace");
+        assert_eq!(
+            workspace.properties.get("Gravity"),
+            Some(&Variant::Float32(100.0))
+        );
+        let baseplate = child(&dom, workspace, "Baseplate");
+        assert_eq!(
+            baseplate.properties.get("Anchored"),
+            Some(&Variant::Bool(true))
+        );
+        assert_eq!(
+            baseplate.properties.get("Size"),
+            Some(&Variant::Vector3(Vector3::new(64.0, 1.0, 64.0)))
+        );
+
+        let output = TestDir::new("place_output");
+        let project_path = output.0.join("default.project.json");
+        write(&project_path, &dom, &rbx_xml::EncodeOptions::new()).unwrap();
+
+        let project = read_project_file(&project_path).unwrap();
+        assert_eq!(project.tree.class_name.as_deref(), Some("DataModel"));
+        assert_eq!(
+            project.tree.children["ReplicatedStorage"].path.as_deref(),
+            Some(Path::new("ReplicatedStorage"))
+        );
+        assert!(project.tree.children["Workspace"]
+            .properties
+            .contains_key("Gravity"));
+
+        assert_same(&dom, &read(&output.0).unwrap());
+    }
+
+    /// Writes a model as a project and reads it back, including a folder whose
+    /// properties go in a meta file.
+    #[test]
+    fn model_round_trip() {
+        let tree = InstanceBuilder::new("Folder")
+            .with_name("Tool")
+            .with_property("Archivable", false)
+            .with_child(
+                InstanceBuilder::new("Script")
+                    .with_name("Main")
+                    .with_property("Source", "print(1)\n"),
+            )
+            .with_child(
+                InstanceBuilder::new("Part")
+                    .with_name("Handle")
+                    .with_property("Anchored", true),
+            );
+        let dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(tree));
+
+        let output = TestDir::new("model_output");
+        let project_path = output.0.join("tool.project.json");
+        write(&project_path, &dom, &rbx_xml::EncodeOptions::new()).unwrap();
+
+        let project = read_project_file(&project_path).unwrap();
+        assert_eq!(project.name, "Tool");
+        assert_eq!(project.tree.path.as_deref(), Some(Path::new("Tool")));
+        assert!(output.0.join("Tool/Main.server.luau").is_file());
+        assert!(output.0.join("Tool/Handle.rbxmx").is_file());
+        assert!(output.0.join("Tool/init.meta.json").is_file());
+
+        assert_same(&dom, &read(&project_path).unwrap());
+    }
+
+    #[test]
+    fn missing_path() {
+        let dir = TestDir::new("missing_path");
+        dir.write(
+            "default.project.json",
+            r#"{ "name": "Model", "tree": { "$path": "src/Missing.lua" } }"#,
+        );
+
+        let err = read(&dir.0).unwrap_err();
+        assert!(format!("{:#}", err).contains("Missing.lua"), "{:#}", err);
+    }
+
+    #[test]
+    fn unreadable_path() {
+        let dir = TestDir::new("unreadable_path");
+        dir.write("image.png", "");
+        dir.write(
+            "default.project.json",
+            r#"{ "name": "Model", "tree": { "$path": "image.png" } }"#,
+        );
+
+        let err = read(&dir.0).unwrap_err();
+        assert!(
+            err.to_string().contains("is not a file Rojo can read"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[test]
+    fn missing_project_file() {
+        let dir = TestDir::new("missing_project");
+        assert!(read(&dir.0.join("missing.project.json")).is_err());
+    }
+
+    #[test]
+    fn unknown_class() {
+        let dir = TestDir::new("unknown_class");
+        dir.write(
+            "default.project.json",
+            r#"{ "name": "Model", "tree": { "$className": "NotARealClass" } }"#,
+        );
+
+        let err = read(&dir.0).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Model has the class NotARealClass, which isn't a known class"
+        );
+    }
+
+    #[test]
+    fn unknown_class_in_meta() {
+        let dir = TestDir::new("unknown_class_meta");
+        dir.write("src/init.meta.json", r#"{ "className": "NotARealClass" }"#);
+        dir.write(
+            "default.project.json",
+            r#"{ "name": "Model", "tree": { "$path": "src" } }"#,
+        );
+
+        let err = read(&dir.0).unwrap_err();
+        assert!(err.to_string().contains("NotARealClass"), "{:#}", err);
+    }
+
+    #[test]
+    fn unknown_property() {
+        let dir = TestDir::new("unknown_property");
+        dir.write(
+            "default.project.json",
+            r#"{
+                "name": "Model",
+                "tree": { "$className": "Folder", "$properties": { "Mystery": 1 } }
+            }"#,
+        );
+
+        let err = read(&dir.0).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Folder.Mystery isn't a known property"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[test]
+    fn neither_class_nor_path() {
+        let dir = TestDir::new("neither");
+        dir.write(
+            "default.project.json",
+            r#"{ "name": "Model", "tree": { "Child": {} , "$className": "Folder" } }"#,
+        );
+
+        let err = read(&dir.0).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Child has neither a $className nor a $path"
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Printing a DOM as an indented tree of instances.
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use anyhow::anyhow;
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    Instance, WeakDom,
+};
+use rbx_reflection::{DataType, ReflectionDatabase};
+
+/// What [`print_tree`] shows.
+pub struct ViewOptions {
+    /// Whether to list the properties of each instance below it.
+    pub properties: bool,
+
+    /// Whether to list the attributes of each instance below it.
+    pub attributes: bool,
+
+    /// How many levels below the starting instances to show, if limited.
+    pub depth: Option<usize>,
+
+    /// If not empty, only instances of these classes or their subclasses are
+    /// shown, along with the instances that contain them.
+    pub classes: Vec<String>,
+
+    /// The path of the instance to start at, like `Workspace.Map`, instead of
+    /// the children of the root.
+    pub path: Option<String>,
+}
+
+/// Prints the instances of `dom` as a tree, one instance per line, indented
+/// by depth.
+pub fn print_tree(dom: &WeakDom, options: &ViewOptions) -> anyhow::Result<()> {
+    let database = rbx_reflection_database::global();
+    let printer = TreePrinter {
+        dom,
+        options,
+        database,
+        formatter: ValueFormatter { dom, database },
+    };
+
+    let starts = match &options.path {
+        Some(path) => vec![find_path(dom, path)?],
+        None => dom.root().children().to_vec(),
+    };
+
+    let mut output = String::new();
+    for referent in starts {
+        printer.print_instance(&mut output, referent, 0);
+    }
+
+    io::stdout().lock().write_all(output.as_bytes())?;
+    Ok(())
+}
+
+/// Finds the instance at a path of names separated by dots, starting from the
+/// children of the root.
+fn find_path(dom: &WeakDom, path: &str) -> anyhow::Result<Ref> {
+    let mut current = dom.root();
+
+    for name in path.split('.') {
+        current = current
+            .children()
+            .iter()
+            .map(|&child| dom.get_by_ref(child).unwrap())
+            .find(|child| child.name == name)
+            .ok_or_else(|| anyhow!("no instance at {}", path))?;
+    }
+
+    Ok(current.referent())
+}
+
+/// The full name of an instance, like `Workspace.Map.Part`.
+pub fn full_name(dom: &WeakDom, referent: Ref) -> String {
+    let mut names = Vec::new();
+    let mut current = dom.get_by_ref(referent);
+
+    while let Some(instance) = current {
+        if instance.referent() == dom.root_ref() {
+            break;
+        }
+        names.push(instance.name.as_str());
+        current = dom.get_by_ref(instance.parent());
+    }
+
+    names.reverse();
+    names.join(".")
+}
+
+struct TreePrinter<'a> {
+    dom: &'a WeakDom,
+    options: &'a ViewOptions,
+    database: &'a ReflectionDatabase<'a>,
+    formatter: ValueFormatter<'a>,
+}
+
+impl TreePrinter<'_> {
+    fn print_instance(&self, output: &mut String, referent: Ref, depth: usize) {
+        if !self.is_shown(referent) {
+            return;
+        }
+
+        let instance = self.dom.get_by_ref(referent).unwrap();
+        let indent = "  ".repeat(depth);
+        writeln!(output, "{}{} ({})", indent, instance.name, instance.class).unwrap();
+
+        if self.options.properties {
+            self.print_properties(output, instance, &indent);
+        }
+        if self.options.attributes {
+            self.print_attributes(output, instance, &indent);
+        }
+
+        if !matches!(self.options.depth, Some(max) if depth >= max) {
+            for &child in instance.children() {
+                self.print_instance(output, child, depth + 1);
+            }
+        }
+    }
+
+    fn print_properties(&self, output: &mut String, instance: &Instance, indent: &str) {
+        let mut properties: Vec<_> = instance
+            .properties
+            .iter()
+            .filter(|(_, value)| !matches!(value, Variant::Attributes(_)))
+            .collect();
+        properties.sort_unstable_by_key(|(name, _)| *name);
+
+        for (name, value) in properties {
+            let value = self.formatter.format(&instance.class, name, value);
+            writeln!(output, "{}  - {}: {}", indent, name, value).unwrap();
+        }
+    }
+
+    fn print_attributes(&self, output: &mut String, instance: &Instance, indent: &str) {
+        let attributes = instance.properties.values().find_map(|value| match value {
+            Variant::Attributes(attributes) => Some(attributes),
+            _ => None,
+        });
+
+        let mut attributes: Vec<_> = match attributes {
+            Some(attributes) => attributes.iter().collect(),
+            None => return,
+        };
+        attributes.sort_unstable_by_key(|(name, _)| *name);
+
+        for (name, value) in attributes {
+            // Attributes aren't in the reflection database, so don't let one
+            // be mistaken for a property with the same name.
+            let value = self.formatter.format(&instance.class, "", value);
+            writeln!(output, "{}  @ {}: {}", indent, name, value).unwrap();
+        }
+    }
+
+    /// Tells whether an instance passes the class filter, either itself or
+    /// through one of its descendants.
+    fn is_shown(&self, referent: Ref) -> bool {
+        if self.options.classes.is_empty() {
+            return true;
+        }
+
+        let instance = self.dom.get_by_ref(referent).unwrap();
+        let matches =
+            self.options.classes.iter().any(|class| {
+                instance.class == *class || self.database.is_a(&instance.class, class)
+            });
+
+        matches
+            || instance
+                .children()
+                .iter()
+                .any(|&child| self.is_shown(child))
+    }
+}
+
+/// Formats values for people to read, with Enum items named and Refs written
+/// as the full name of the instance they point to.
+pub struct ValueFormatter<'a> {
+    pub dom: &'a WeakDom,
+    pub database: &'a ReflectionDatabase<'a>,
+}
+
+impl ValueFormatter<'_> {
+    /// Formats a value of a property, or of an attribute when `property_name`
+    /// is empty.
+    pub fn format(&self, class_name: &str, property_name: &str, value: &Variant) -> String {
+        match value {
+            Variant::Bool(value) => value.to_string(),
+            Variant::Int32(value) => value.to_string(),
+            Variant::Int64(value) => value.to_string(),
+            Variant::Float32(value) => value.to_string(),
+            Variant::Float64(value) => value.to_string(),
+            Variant::String(value) => format!("{:?}", value),
+            Variant::Content(value) => format!("{:?}", value.as_ref() as &str),
+            Variant::Vector2(value) => format!("{}, {}", value.x, value.y),
+            Variant::Vector3(value) => format!("{}, {}, {}", value.x, value.y, value.z),
+            Variant::Color3(value) => format!("{}, {}, {}", value.r, value.g, value.b),
+            Variant::Color3uint8(value) => format!("{}, {}, {}", value.r, value.g, value.b),
+            Variant::BinaryString(value) => {
+                format!("<{} bytes>", AsRef::<[u8]>::as_ref(value).len())
+            }
+            Variant::SharedString(value) => format!("<{} bytes>", value.data().len()),
+            Variant::Tags(tags) => format!("{:?}", tags.iter().collect::<Vec<_>>()),
+            Variant::Ref(referent) if referent.is_none() => "nil".to_owned(),
+            Variant::Ref(referent) => match self.dom.get_by_ref(*referent) {
+                Some(_) => full_name(self.dom, *referent),
+                None => format!("<unknown {}>", referent),
+            },
+            Variant::Enum(value) => {
+                let item = self
+                    .database
+                    .find_property_descriptors(class_name, property_name)
+                    .and_then(|descriptors| match &descriptors.canonical.data_type {
+                        DataType::Enum(enum_name) => self
+                            .database
+                            .enum_item_name(enum_name, value.to_u32())
+                            .map(|item| format!("Enum.{}.{}", enum_name, item)),
+                        _ => None,
+                    });
+
+                item.unwrap_or_else(|| value.to_u32().to_string())
+            }
+            other => format!("{:?}", other),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::io::{self, Read};
+
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    sync::mpsc,
+};
+
+/// How many bytes are read from the async reader at a time.
+const READ_SIZE: usize = 8 * 1024;
+
+/// How many reads can be waiting to be parsed before reading stops until the
+/// parser catches up.
+const READ_AHEAD: usize = 4;
+
+/// A blocking reader that hands over the bytes read from an async reader by
+/// [`pump`], so that the XML parser, which can't be suspended partway through
+/// a document, can parse it on another thread as it arrives.
+pub struct ChannelReader {
+    receiver: mpsc::Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl ChannelReader {
+    /// Creates a `ChannelReader`, along with the sender that [`pump`] fills it
+    /// with.
+    pub fn new() -> (mpsc::Sender<io::Result<Vec<u8>>>, Self) {
+        let (sender, receiver) = mpsc::channel(READ_AHEAD);
+        let reader = ChannelReader {
+            receiver,
+            chunk: Vec::new(),
+            position: 0,
+        };
+
+        (sender, reader)
+    }
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.chunk.len() {
+            match self.receiver.blocking_recv() {
+                Some(Ok(chunk)) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                Some(Err(err)) => return Err(err),
+                None => return Ok(0),
+            }
+        }
+
+        let remaining = &self.chunk[self.position..];
+        let len = remaining.len().min(buf.len());
+        buf[..len].copy_from_slice(&remaining[..len]);
+        self.position += len;
+
+        Ok(len)
+    }
+}
+
+/// Reads `reader` to its end, sending what's read to a [`ChannelReader`].
+/// Stops early if the `ChannelReader` is dropped, like when parsing fails.
+pub async fn pump<R: AsyncRead + Unpin>(mut reader: R, sender: mpsc::Sender<io::Result<Vec<u8>>>) {
+    loop {
+        let mut chunk = vec![0; READ_SIZE];
+        let result = match reader.read(&mut chunk).await {
+            Ok(0) => return,
+            Ok(len) => {
+                chunk.truncate(len);
+                Ok(chunk)
+            }
+            Err(err) => Err(err),
+        };
+        let failed = result.is_err();
+
+        if sender.send(result).await.is_err() || failed {
+            return;
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use std::io::{self, Write};
+
+use base64::DecodeError;
+
+/// How many bytes are encoded at a time. This is a multiple of three, so only
+/// the last group of a value is ever padded.
+const ENCODE_CHUNK_LEN: usize = 3 * 1024;
+
+/// Encodes `data` as base64 into `output` a fixed-size group at a time, so the
+/// encoded value is never held as one big string.
+pub(crate) fn encode_to<W: Write>(data: &[u8], output: &mut W) -> io::Result<()> {
+    let mut buffer = [0; ENCODE_CHUNK_LEN / 3 * 4];
+
+    for chunk in data.chunks(ENCODE_CHUNK_LEN) {
+        let len = encode_slice(chunk, &mut buffer);
+        output.write_all(&buffer[..len])?;
+    }
+
+    Ok(())
+}
+
+/// Encodes `input` into the start of `output`, returning how many characters
+/// were written.
+#[cfg(not(feature = "simd"))]
+fn encode_slice(input: &[u8], output: &mut [u8]) -> usize {
+    base64::encode_config_slice(input, base64::STANDARD, output)
+}
+
+#[cfg(feature = "simd")]
+fn encode_slice(input: &[u8], output: &mut [u8]) -> usize {
+    base64_simd::STANDARD
+        .encode(input, base64_simd::Out::from_slice(output))
+        .len()
+}
+
+/// Decodes `input`, appending the decoded bytes to `output`.
+#[cfg(not(feature = "simd"))]
+fn decode_buf(input: &[u8], output: &mut Vec<u8>) -> Result<(), DecodeError> {
+    base64::decode_config_buf(input, base64::STANDARD, output)
+}
+
+#[cfg(feature = "simd")]
+fn decode_buf(input: &[u8], output: &mut Vec<u8>) -> Result<(), DecodeError> {
+    // base64-simd reserves exactly what each call needs, so reserve ahead of
+    // it to keep growing the output amortized.
+    output.reserve(input.len() / 4 * 3);
+
+    // base64-simd doesn't say where input is invalid and rejects groups
+    // without padding, which the scalar decoder accepts. It leaves `output`
+    // untouched when it fails, so anything it rejects is decoded again by the
+    // scalar decoder, which accepts the same input and reports the same errors
+    // as it does without this feature.
+    if base64_simd::STANDARD.decode_append(input, output).is_ok() {
+        return Ok(());
+    }
+
+    base64::decode_config_buf(input, base64::STANDARD, output)
+}
+
+/// Decodes base64 that arrives in pieces, like the character events of a
+/// long BinaryString, straight into its output buffer.
+///
+/// Whitespace anywhere in the input is skipped, since Roblox wraps long values
+/// across lines. Whole groups of four characters are decoded directly from
+/// each piece, so the input is never copied into one big string first.
+pub(crate) struct Base64Decoder {
+    output: Vec<u8>,
+
+    /// Characters of a group that was split between pieces or by whitespace.
+    partial: [u8; 4],
+    partial_len: usize,
+
+    /// How many characters have been decoded so far, to correct the offsets
+    /// in errors.
+    offset: usize,
+
+    /// Whether the last group decoded ended in padding, after which there
+    /// can't be any more characters.
+    padded: bool,
+}
+
+impl Base64Decoder {
+    /// Creates a decoder for about `encoded_len` characters of base64.
+    pub fn with_capacity(encoded_len: usize) -> Self {
+        Base64Decoder {
+            output: Vec::with_capacity(encoded_len / 4 * 3),
+            partial: [0; 4],
+            partial_len: 0,
+            offset: 0,
+            padded: false,
+        }
+    }
+
+    /// Decodes the next piece of the input.
+    pub fn push(&mut self, piece: &str) -> Result<(), DecodeError> {
+        for mut segment in piece.split(char::is_whitespace).map(str::as_bytes) {
+            if segment.is_empty() {
+                continue;
+            }
+
+            if self.partial_len > 0 {
+                let needed = (4 - self.partial_len).min(segment.len());
+                self.partial[self.partial_len..self.partial_len + needed]
+                    .copy_from_slice(&segment[..needed]);
+                self.partial_len += needed;
+                segment = &segment[needed..];
+
+                if self.partial_len < 4 {
+                    continue;
+                }
+
+                let group = self.partial;
+                self.decode(&group)?;
+                self.partial_len = 0;
+            }
+
+            let whole = segment.len() - segment.len() % 4;
+            if whole > 0 {
+                self.decode(&segment[..whole])?;
+            }
+
+            let rest = &segment[whole..];
+            self.partial[..rest.len()].copy_from_slice(rest);
+            self.partial_len = rest.len();
+        }
+
+        Ok(())
+    }
+
+    /// Decodes whatever is left of the input, returning the decoded bytes.
+    pub fn finish(mut self) -> Result<Vec<u8>, DecodeError> {
+        if self.partial_len > 0 {
+            let group = self.partial;
+            self.decode(&group[..self.partial_len])?;
+        }
+
+        Ok(self.output)
+    }
+
+    fn decode(&mut self, input: &[u8]) -> Result<(), DecodeError> {
+        if self.padded {
+            return Err(DecodeError::InvalidByte(self.offset - 1, b'='));
+        }
+
+        decode_buf(input, &mut self.output).map_err(|error| match error {
+            DecodeError::InvalidByte(offset, byte) => {
+                DecodeError::InvalidByte(self.offset + offset, byte)
+            }
+            DecodeError::InvalidLastSymbol(offset, byte) => {
+                DecodeError::InvalidLastSymbol(self.offset + offset, byte)
+            }
+            DecodeError::InvalidLength => DecodeError::InvalidLength,
+        })?;
+
+        self.offset += input.len();
+        self.padded = input.last() == Some(&b'=');
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn decode_pieces(pieces: &[&str]) -> Result<Vec<u8>, DecodeError> {
+        let mut decoder = Base64Decoder::with_capacity(0);
+        for piece in pieces {
+            decoder.push(piece)?;
+        }
+        decoder.finish()
+    }
+
+    #[test]
+    fn matches_decoding_all_at_once() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 7 + i / 3) as u8).collect();
+        let encoded = base64::encode(&data);
+
+        for split in [1, 2, 3, 5, 64, 76, 999] {
+            let wrapped: Vec<String> = encoded
+                .as_bytes()
+                .chunks(split)
+                .map(|piece| format!("{}\r\n ", std::str::from_utf8(piece).unwrap()))
+                .collect();
+            let pieces: Vec<&str> = wrapped.iter().map(String::as_str).collect();
+
+            assert_eq!(decode_pieces(&pieces).unwrap(), data, "split {}", split);
+        }
+    }
+
+    #[test]
+    fn encode_matches_encoding_all_at_once() {
+        for len in [0, 1, 2, 3, ENCODE_CHUNK_LEN, ENCODE_CHUNK_LEN * 2 + 1] {
+            let data: Vec<u8> = (0..len).map(|i| (i * 7 + i / 3) as u8).collect();
+
+            let mut encoded = Vec::new();
+            encode_to(&data, &mut encoded).unwrap();
+
+            assert_eq!(encoded, base64::encode(&data).into_bytes(), "len {}", len);
+        }
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn simd_matches_scalar() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 7 + i / 3) as u8).collect();
+
+        for len in [0, 1, 2, 3, 4, 31, 32, 33, 64, 1000] {
+            let mut simd = [0; 2000];
+            let mut scalar = [0; 2000];
+            let simd_len = encode_slice(&data[..len], &mut simd);
+            let scalar_len =
+                base64::encode_config_slice(&data[..len], base64::STANDARD, &mut scalar);
+            assert_eq!(simd[..simd_len], scalar[..scalar_len], "len {}", len);
+
+            let encoded = &scalar[..scalar_len];
+            let mut decoded = vec![1, 2, 3];
+            decode_buf(encoded, &mut decoded).unwrap();
+            assert_eq!(decoded[3..], data[..len], "len {}", len);
+        }
+
+        for input in [
+            &b"SGk"[..],
+            b"SGVsbG8",
+            b"SGl=",
+            b"SG*=",
+            b"S===",
+            b"SGk=SGk=",
+            b"SGVsbG8sIHdvcmxkIQ==",
+        ] {
+            let mut simd = vec![1, 2, 3];
+            let mut scalar = vec![1, 2, 3];
+            let simd_result = decode_buf(input, &mut simd);
+            let scalar_result = base64::decode_config_buf(input, base64::STANDARD, &mut scalar);
+
+            assert_eq!(simd_result, scalar_result, "{:?}", input);
+            if simd_result.is_ok() {
+                assert_eq!(simd, scalar, "{:?}", input);
+            }
+        }
+    }
+
+    #[test]
+    fn padding_split_by_whitespace() {
+        assert_eq!(
+            decode_pieces(&["SGVsbG8s IHdv  cmxkIQ= ", "="]).unwrap(),
+            b"Hello, world!"
+        );
+    }
+
+    #[test]
+    fn data_after_padding() {
+        assert!(decode_pieces(&["SGk=", "SGk="]).is_err());
+    }
+
+    #[test]
+    fn invalid_byte_offset() {
+        assert_eq!(
+            decode_pieces(&["SGVs", "bG*s"]),
+            Err(DecodeError::InvalidByte(6, b'*'))
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{Read, Write},
+    sync::Arc,
+};
+
+use rbx_dom_weak::types::{Variant, VariantType};
+
+use crate::{
+    deserializer_core::XmlEventReader,
+    error::{DecodeError, DecodeErrorKind, EncodeError, EncodeErrorKind},
+    serializer_core::XmlEventWriter,
+    terrain::{StructuredTerrain, SMOOTH_GRID_CLASSES},
+};
+
+/// The error type returned by a [`PropertyCodec`].
+pub type CodecError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Decodes and encodes property values that rbx_xml doesn't support itself,
+/// like datatypes that are newer than rbx_xml or that are only understood by a
+/// specific plugin.
+///
+/// Codecs work with the XML text of a single property element, including its
+/// own start and end tags, like `<Vector4 name="Size">...</Vector4>`. They're
+/// registered with [`Codecs`].
+pub trait PropertyCodec: Send + Sync {
+    /// Decodes the value of the property element `xml`.
+    fn decode(&self, xml: &str) -> Result<Variant, CodecError>;
+
+    /// Encodes `value` as a property element with the given name, returning
+    /// the element's XML.
+    fn encode(&self, name: &str, value: &Variant) -> Result<String, CodecError>;
+}
+
+/// A set of [`PropertyCodec`]s to use in place of rbx_xml's own decoding and
+/// encoding, given to
+/// [`DecodeOptions::codecs`][crate::DecodeOptions::codecs] and
+/// [`EncodeOptions::codecs`][crate::EncodeOptions::codecs].
+///
+/// Codecs registered for a specific property are preferred over codecs
+/// registered for a type.
+#[derive(Clone, Default)]
+pub struct Codecs {
+    by_property: HashMap<(String, String), Arc<dyn PropertyCodec>>,
+    by_xml_type: HashMap<String, Arc<dyn PropertyCodec>>,
+    by_variant_type: HashMap<VariantType, Arc<dyn PropertyCodec>>,
+}
+
+impl Codecs {
+    /// Constructs a `Codecs` with no codecs registered.
+    pub fn new() -> Self {
+        Codecs::default()
+    }
+
+    /// Uses `codec` for the property of the given class with the given name.
+    ///
+    /// When decoding, `property` is the name of the property as it appears in
+    /// the document, and the value is kept under that name without going
+    /// through the reflection database. When encoding, `property` is the name
+    /// of the property in the `WeakDom`, and `codec` is given that name.
+    pub fn property<C: PropertyCodec + 'static>(
+        mut self,
+        class: &str,
+        property: &str,
+        codec: C,
+    ) -> Self {
+        self.by_property
+            .insert((class.to_owned(), property.to_owned()), Arc::new(codec));
+        self
+    }
+
+    /// Uses `codec` to decode property elements named `xml_type_name`, and to
+    /// encode values of type `variant_type`.
+    ///
+    /// Decoded values are handled like any other value afterwards, so they
+    /// can be converted to the type the reflection database expects.
+    pub fn xml_type<C: PropertyCodec + 'static>(
+        mut self,
+        xml_type_name: &str,
+        variant_type: VariantType,
+        codec: C,
+    ) -> Self {
+        let codec: Arc<dyn PropertyCodec> = Arc::new(codec);
+        self.by_xml_type
+            .insert(xml_type_name.to_owned(), Arc::clone(&codec));
+        self.by_variant_type.insert(variant_type, codec);
+        self
+    }
+
+    /// Writes the `SmoothGrid` property of Terrain and TerrainRegion
+    /// instances as readable chunks of voxels, one run of identical voxels at
+    /// a time, instead of as base64. This makes terrain changes show up in
+    /// diffs, but Roblox can't read the result.
+    ///
+    /// Both the structured form and the usual BinaryString are decoded, into
+    /// the same BinaryString. Grids that rbx_types can't decode are still
+    /// written as BinaryStrings.
+    pub fn structured_terrain(self) -> Self {
+        SMOOTH_GRID_CLASSES.iter().fold(self, |codecs, class| {
+            codecs.property(class, "SmoothGrid", StructuredTerrain)
+        })
+    }
+
+    pub(crate) fn for_property(&self, class: &str, property: &str) -> Option<&dyn PropertyCodec> {
+        if self.by_property.is_empty() {
+            return None;
+        }
+
+        self.by_property
+            .get(&(class.to_owned(), property.to_owned()))
+            .map(|codec| codec.as_ref())
+    }
+
+    pub(crate) fn for_xml_type(&self, xml_type_name: &str) -> Option<&dyn PropertyCodec> {
+        self.by_xml_type
+            .get(xml_type_name)
+            .map(|codec| codec.as_ref())
+    }
+
+    pub(crate) fn for_variant_type(&self, variant_type: VariantType) -> Option<&dyn PropertyCodec> {
+        self.by_variant_type
+            .get(&variant_type)
+            .map(|codec| codec.as_ref())
+    }
+}
+
+impl fmt::Debug for Codecs {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Codecs")
+            .field("properties", &self.by_property.keys().collect::<Vec<_>>())
+            .field("xml_types", &self.by_xml_type.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Reads the property element at the head of `reader` with `codec`.
+pub(crate) fn read_with_codec<R: Read>(
+    reader: &mut XmlEventReader<R>,
+    codec: &dyn PropertyCodec,
+    xml_type_name: &str,
+) -> Result<Variant, DecodeError> {
+    let xml = reader.read_raw_element()?;
+
+    codec.decode(&xml).map_err(|error| {
+        reader.error(DecodeErrorKind::Codec {
+            type_name: xml_type_name.to_owned(),
+            error,
+        })
+    })
+}
+
+/// Writes `value` as a property element with the given name using `codec`.
+pub(crate) fn write_with_codec<W: Write>(
+    writer: &mut XmlEventWriter<W>,
+    codec: &dyn PropertyCodec,
+    xml_property_name: &str,
+    value: &Variant,
+) -> Result<(), EncodeError> {
+    let xml = codec.encode(xml_property_name, value).map_err(|error| {
+        writer.error(EncodeErrorKind::Codec {
+            property_name: xml_property_name.to_owned(),
+            error,
+        })
+    })?;
+
+    writer.write_raw_element(&xml)
+}
@@ -4,7 +4,9 @@
 use std::borrow::{Borrow, Cow};
 use std::convert::TryInto;
 
-use rbx_dom_weak::types::{Attributes, BrickColor, Color3uint8, Tags, Variant, VariantType};
+use rbx_dom_weak::types::{
+    Attributes, BrickColor, Color3uint8, SharedString, Tags, Variant, VariantType,
+};
 
 pub trait ConvertVariant: Clone + Sized {
     fn try_convert(self, target_type: VariantType) -> Result<Self, String> {
@@ -53,6 +55,11 @@ impl ConvertVariant for Variant {
                     .map_err(|_| "Tags contain invalid UTF-8")?
                     .into(),
             )),
+            // Older files have blobs written inline that are SharedStrings
+            // now, which are deduplicated when they're written back out.
+            (Variant::BinaryString(value), VariantType::SharedString) => Ok(Cow::Owned(
+                SharedString::new(value.clone().into_vec()).into(),
+            )),
             (Variant::BinaryString(value), VariantType::Attributes) => {
                 let bytes: &[u8] = value.as_ref();
 
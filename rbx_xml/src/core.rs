@@ -1,8 +1,13 @@
 use std::io::{Read, Write};
 
-use rbx_reflection::{PropertyDescriptor, PropertyKind, PropertySerialization};
+use rbx_dom_weak::types::{Enum, Variant, VariantType};
+use rbx_reflection::{
+    DataType, PropertyDescriptor, PropertyKind, PropertySerialization, ReflectionDatabase,
+    SerializationBehavior,
+};
 
 use crate::{
+    conversion::ConvertVariant,
     deserializer_core::XmlEventReader,
     error::{DecodeError, EncodeError},
     serializer_core::{XmlEventWriter, XmlWriteEvent},
@@ -40,108 +45,179 @@ pub trait XmlType: Sized {
 }
 
 pub fn find_canonical_property_descriptor(
+    database: &'static ReflectionDatabase<'static>,
     class_name: &str,
     property_name: &str,
 ) -> Option<&'static PropertyDescriptor<'static>> {
-    find_property_descriptors(class_name, property_name).map(|(canonical, _serialized)| canonical)
+    find_property_descriptors(database, class_name, property_name)
+        .map(|(canonical, _serialized)| canonical)
 }
 
+/// Finds the descriptor that the given property is written to files as.
+/// Properties that are only loaded from older files are still written as
+/// themselves, since a DOM that has them was never migrated.
 pub fn find_serialized_property_descriptor(
+    database: &'static ReflectionDatabase<'static>,
     class_name: &str,
     property_name: &str,
 ) -> Option<&'static PropertyDescriptor<'static>> {
-    find_property_descriptors(class_name, property_name).map(|(_canonical, serialized)| serialized)
+    let class_name = known_class_name(database, class_name);
+
+    match database.serialization(class_name, property_name)? {
+        SerializationBehavior::Serializes { serialized }
+        | SerializationBehavior::LoadOnly { serialized, .. } => Some(serialized),
+        _ => None,
+    }
+}
+
+/// Finds the property descriptor with exactly the given name, without
+/// following aliases or serialized names to other descriptors. Properties that
+/// don't serialize aren't found.
+pub fn find_exact_property_descriptor(
+    database: &'static ReflectionDatabase<'static>,
+    class_name: &str,
+    property_name: &str,
+) -> Option<&'static PropertyDescriptor<'static>> {
+    let descriptor = database
+        .superclass_iter(class_name)
+        .find_map(|class_descriptor| class_descriptor.properties.get(property_name))?;
+
+    match &descriptor.kind {
+        PropertyKind::Canonical {
+            serialization: PropertySerialization::DoesNotSerialize,
+        } => None,
+        _ => Some(descriptor),
+    }
+}
+
+/// Tells whether the given property is the source of a script, which Roblox
+/// stores as a `ProtectedString`.
+pub fn is_script_source(
+    database: &'static ReflectionDatabase<'static>,
+    class_name: &str,
+    property_name: &str,
+) -> bool {
+    if property_name != "Source" {
+        return false;
+    }
+
+    database.is_a(class_name, "LuaSourceContainer")
+}
+
+/// Finds the older property that the given property was migrated from, along
+/// with the value it needs to have to migrate to `value`. Returns `None` if the
+/// property wasn't migrated from another one, or if no value of the older
+/// property migrates to `value`.
+pub fn find_legacy_property(
+    database: &'static ReflectionDatabase<'static>,
+    class_name: &str,
+    property_name: &str,
+    value: &Variant,
+) -> Option<(&'static str, Variant)> {
+    for class_descriptor in database.superclass_iter(class_name) {
+        for descriptor in class_descriptor.properties.values() {
+            let migration = match &descriptor.kind {
+                PropertyKind::Canonical {
+                    serialization: PropertySerialization::Migrate(migration),
+                } if migration.new_property_name == property_name => migration,
+                _ => continue,
+            };
+
+            let mut candidates = match &descriptor.data_type {
+                DataType::Value(VariantType::Bool) => {
+                    vec![Variant::Bool(false), Variant::Bool(true)]
+                }
+                DataType::Enum(enum_name) => database
+                    .enum_items(enum_name)?
+                    .into_iter()
+                    .map(|(_, value)| Variant::Enum(Enum::from_u32(value)))
+                    .collect(),
+                _ => return None,
+            };
+
+            let index = candidates.iter().position(|candidate| {
+                migration
+                    .perform(candidate)
+                    .is_ok_and(|migrated| is_same_value(&migrated, value))
+            })?;
+
+            return Some((descriptor.name.as_ref(), candidates.swap_remove(index)));
+        }
+    }
+
+    None
+}
+
+/// Tells whether `value` is the reflection database's default value for the
+/// given property, which can be given by either its canonical or serialized
+/// name.
+pub fn is_default_value(
+    database: &'static ReflectionDatabase<'static>,
+    class_name: &str,
+    property_name: &str,
+    value: &Variant,
+) -> bool {
+    // Defaults are listed under a mix of canonical and serialized names.
+    let default = database
+        .class_default(class_name, property_name)
+        .or_else(|| {
+            let descriptor =
+                find_serialized_property_descriptor(database, class_name, property_name)?;
+            database.class_default(class_name, descriptor.name.as_ref())
+        });
+
+    match default {
+        Some(default) => value
+            .try_convert_ref(default.ty())
+            .is_ok_and(|converted| is_same_value(&converted, default)),
+        None => false,
+    }
+}
+
+/// Returns the reflection database's default value for every property of the
+/// given class that can be serialized, by canonical name and converted to the
+/// canonical type, like properties are when they're deserialized.
+pub fn default_properties(
+    database: &'static ReflectionDatabase<'static>,
+    class_name: &str,
+) -> Vec<(&'static str, Variant)> {
+    database.class_defaults(class_name)
+}
+
+/// Compares two values, ignoring the parts of them that Roblox fills in by
+/// itself, like the cached face IDs of Fonts.
+fn is_same_value(a: &Variant, b: &Variant) -> bool {
+    match (a, b) {
+        (Variant::Font(a), Variant::Font(b)) => {
+            a.family == b.family && a.weight == b.weight && a.style == b.style
+        }
+        (a, b) => a == b,
+    }
 }
 
 /// Find both the canonical and serialized property descriptors for a given
 /// class and property name pair. These might be the same descriptor!
+/// Properties that don't serialize aren't found.
 fn find_property_descriptors(
+    database: &'static ReflectionDatabase<'static>,
     class_name: &str,
     property_name: &str,
 ) -> Option<(
     &'static PropertyDescriptor<'static>,
     &'static PropertyDescriptor<'static>,
 )> {
-    let class_descriptor = rbx_reflection_database::get().classes.get(class_name)?;
-
-    let mut current_class_descriptor = class_descriptor;
-
-    // We need to find the canonical property descriptor associated with
-    // the property we're trying to deserialize.
-    //
-    // At each step of the loop, we're checking a new class descriptor
-    // to see if it has an entry for the property name we're looking for.
-    loop {
-        // If this class descriptor knows about this property name,
-        // we're pretty much done!
-        if let Some(property_descriptor) = current_class_descriptor.properties.get(property_name) {
-            match &property_descriptor.kind {
-                PropertyKind::Canonical { serialization } => match serialization {
-                    PropertySerialization::Serializes | PropertySerialization::Migrate { .. } => {
-                        return Some((property_descriptor, property_descriptor))
-                    }
-                    PropertySerialization::DoesNotSerialize => {
-                        // FIXME: Is this the correct solution?
-                        return None;
-                    }
-                    PropertySerialization::SerializesAs(serialized_name) => {
-                        let serialized_descriptor = current_class_descriptor
-                            .properties
-                            .get(serialized_name.as_ref())
-                            .unwrap();
-
-                        return Some((property_descriptor, serialized_descriptor));
-                    }
-                    _ => unimplemented!(),
-                },
-                PropertyKind::Alias { alias_for } => {
-                    let canonical_descriptor = current_class_descriptor
-                        .properties
-                        .get(alias_for.as_ref())
-                        .unwrap();
-
-                    // FIXME: This code is duplicated with above.
-                    match &canonical_descriptor.kind {
-                        PropertyKind::Canonical { serialization } => match serialization {
-                            PropertySerialization::Serializes
-                            | PropertySerialization::Migrate { .. } => {
-                                return Some((canonical_descriptor, canonical_descriptor))
-                            }
-                            PropertySerialization::DoesNotSerialize => {
-                                // FIXME: Is this the correct solution?
-                                return None;
-                            }
-                            PropertySerialization::SerializesAs(serialized_name) => {
-                                let serialized_descriptor = current_class_descriptor
-                                    .properties
-                                    .get(serialized_name.as_ref())
-                                    .unwrap();
-
-                                return Some((canonical_descriptor, serialized_descriptor));
-                            }
-                            _ => unimplemented!(),
-                        },
-                        _ => return None,
-                    }
-                }
-                // FIXME
-                _ => unimplemented!(),
-            }
-        }
-
-        if let Some(superclass_name) = &current_class_descriptor.superclass {
-            // If a property descriptor isn't found in our class, check
-            // our superclass.
-
-            current_class_descriptor = rbx_reflection_database::get()
-                .classes
-                .get(superclass_name)
-                .expect("Superclass in reflection database didn't exist");
-        } else {
-            // This property isn't known by any class in the reflection
-            // database.
+    let class_name = known_class_name(database, class_name);
+    let descriptors = database.find_property_descriptors(class_name, property_name)?;
+    Some((descriptors.canonical, descriptors.serialized?))
+}
 
-            return None;
-        }
+/// Classes that are newer than the reflection database still have the
+/// properties of Instance, like Attributes and Tags, so their properties are
+/// looked up as if they were an Instance.
+fn known_class_name<'a>(database: &ReflectionDatabase, class_name: &'a str) -> &'a str {
+    if database.classes.contains_key(class_name) {
+        class_name
+    } else {
+        "Instance"
     }
 }
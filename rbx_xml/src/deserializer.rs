@@ -1,6 +1,13 @@
 use std::{
+    cell::Cell,
     collections::{HashMap, HashSet},
-    io::Read,
+    fmt,
+    io::{self, Read},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
 use log::trace;
@@ -8,30 +15,86 @@ use rbx_dom_weak::{
     types::{Ref, SharedString, Variant, VariantType},
     InstanceBuilder, WeakDom,
 };
-use rbx_reflection::{DataType, PropertyKind, PropertySerialization};
+use rbx_reflection::{DataType, PropertyKind, PropertySerialization, ReflectionDatabase};
 
 use crate::{
+    codec::{read_with_codec, Codecs},
     conversion::ConvertVariant,
-    core::find_canonical_property_descriptor,
+    core::{
+        default_properties, find_canonical_property_descriptor, find_exact_property_descriptor,
+    },
     error::{DecodeError, DecodeErrorKind},
+    report::{
+        Annotation, AnnotationContent, AnnotationPosition, DecodeReport, DecodeWarning, Skipped,
+        UnknownElement, UnknownElementKind, UnresolvedReference,
+    },
+    selector::{Selector, SelectorCandidate, SelectorPlan},
+    serializer::ExternalReferences,
     types::read_value_xml,
 };
 
-use crate::deserializer_core::{XmlEventReader, XmlReadEvent};
+use crate::deserializer_core::{ReaderLimits, XmlEventReader, XmlReadEvent};
+
+pub fn decode_internal<R: Read>(
+    source: R,
+    options: DecodeOptions,
+) -> Result<(WeakDom, DecodeReport), DecodeError> {
+    #[cfg(feature = "tracing")]
+    let span = tracing::debug_span!(
+        "rbx_xml::deserialize",
+        instances = tracing::field::Empty,
+        property_values = tracing::field::Empty,
+    )
+    .entered();
 
-pub fn decode_internal<R: Read>(source: R, options: DecodeOptions) -> Result<WeakDom, DecodeError> {
     let mut tree = WeakDom::new(InstanceBuilder::new("DataModel"));
 
     let root_id = tree.root_ref();
 
-    let mut iterator = XmlEventReader::from_source(source);
-    let mut state = ParseState::new(&mut tree, options);
+    let bytes_read = Rc::new(Cell::new(0));
+    let source = CountingReader::new(source, Rc::clone(&bytes_read));
+
+    let mut iterator = XmlEventReader::with_limits(source, options.reader_limits());
+    if options.preserve_comments {
+        iterator.collect_annotations();
+    }
+
+    let mut state = ParseState::new(&mut tree, options, bytes_read);
+
+    if let Err(error) = deserialize_root(&mut iterator, &mut state, root_id) {
+        // The XML parser can't read past problems with the document itself,
+        // so in recovery mode we keep everything that was read before them.
+        if !state.options.recover
+            || error.is_recoverable()
+            || error.is_cancelled()
+            || error.is_limit_exceeded()
+        {
+            return Err(error);
+        }
+
+        state
+            .report
+            .warnings
+            .push(DecodeWarning::new(Skipped::Remainder, &error));
+    }
+
+    apply_referent_rewrites(&mut iterator, &mut state)?;
+    apply_shared_string_rewrites(&mut iterator, &mut state)?;
 
-    deserialize_root(&mut iterator, &mut state, root_id)?;
-    apply_referent_rewrites(&mut state);
-    apply_shared_string_rewrites(&mut state);
+    log::debug!(
+        "Decoded {} instances and {} property values",
+        state.instances_decoded,
+        state.property_values
+    );
 
-    Ok(tree)
+    #[cfg(feature = "tracing")]
+    {
+        span.record("instances", state.instances_decoded);
+        span.record("property_values", state.property_values);
+    }
+
+    let report = state.report;
+    Ok((tree, report))
 }
 
 /// Describes the strategy that rbx_xml should use when deserializing
@@ -67,10 +130,28 @@ pub enum DecodePropertyBehavior {
     NoReflection,
 }
 
+/// A callback told how far decoding has gotten after each instance.
+type ProgressCallback = Arc<dyn Fn(&DecodeProgress) + Send + Sync>;
+
 /// Options available for deserializing an XML-format model or place.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct DecodeOptions {
     property_behavior: DecodePropertyBehavior,
+    progress: Option<ProgressCallback>,
+    cancel_token: Option<Arc<AtomicBool>>,
+    pub(crate) preserve_unknown_elements: bool,
+    pub(crate) codecs: Codecs,
+    recover: bool,
+    external_references: ExternalReferences,
+    map_property_names: bool,
+    migrate_properties: bool,
+    apply_default_properties: bool,
+    selector: Option<Selector>,
+    preserve_comments: bool,
+    max_depth: Option<usize>,
+    max_properties: Option<usize>,
+    max_entity_expansion: Option<usize>,
+    pub(crate) database: &'static ReflectionDatabase<'static>,
 }
 
 impl DecodeOptions {
@@ -79,6 +160,21 @@ impl DecodeOptions {
     pub fn new() -> Self {
         DecodeOptions {
             property_behavior: DecodePropertyBehavior::IgnoreUnknown,
+            progress: None,
+            cancel_token: None,
+            preserve_unknown_elements: false,
+            codecs: Codecs::new(),
+            recover: false,
+            external_references: ExternalReferences::Null,
+            map_property_names: true,
+            migrate_properties: true,
+            apply_default_properties: false,
+            selector: None,
+            preserve_comments: false,
+            max_depth: None,
+            max_properties: None,
+            max_entity_expansion: None,
+            database: rbx_reflection_database::global(),
         }
     }
 
@@ -86,7 +182,228 @@ impl DecodeOptions {
     /// ones.
     #[inline]
     pub fn property_behavior(self, property_behavior: DecodePropertyBehavior) -> Self {
-        DecodeOptions { property_behavior }
+        DecodeOptions {
+            property_behavior,
+            ..self
+        }
+    }
+
+    /// Calls `callback` with how far decoding has gotten each time an
+    /// instance is read, which is useful for showing a progress bar while
+    /// loading large places.
+    pub fn on_progress<F: Fn(&DecodeProgress) + Send + Sync + 'static>(self, callback: F) -> Self {
+        DecodeOptions {
+            progress: Some(Arc::new(callback)),
+            ..self
+        }
+    }
+
+    /// Sets a flag that cancels decoding when set to `true`, from this thread
+    /// or any other.
+    ///
+    /// The flag is checked each time an instance is read. Once it's set,
+    /// decoding stops with an error for which [`DecodeError::is_cancelled`]
+    /// returns `true`.
+    pub fn cancel_token(self, token: Arc<AtomicBool>) -> Self {
+        DecodeOptions {
+            cancel_token: Some(token),
+            ..self
+        }
+    }
+
+    /// Keeps elements that rbx_xml doesn't understand as raw XML instead of
+    /// dropping them. Defaults to `false`.
+    ///
+    /// This covers properties that aren't in the reflection database when
+    /// using [`DecodePropertyBehavior::IgnoreUnknown`], properties with value
+    /// types rbx_xml can't decode, and elements inside of an `Item` other than
+    /// `Properties` and child `Item`s, which are otherwise an error.
+    ///
+    /// The kept elements are listed in the [`DecodeReport`] returned by
+    /// [`from_reader_with_report`][crate::from_reader_with_report], and can be
+    /// written back out with
+    /// [`EncodeOptions::unknown_elements`][crate::EncodeOptions::unknown_elements].
+    pub fn preserve_unknown_elements(self, enabled: bool) -> Self {
+        DecodeOptions {
+            preserve_unknown_elements: enabled,
+            ..self
+        }
+    }
+
+    /// Determines whether the document's comments and processing
+    /// instructions are kept, along with where they appear. Defaults to
+    /// `false`.
+    ///
+    /// The kept annotations are listed in the [`DecodeReport`] returned by
+    /// [`from_reader_with_report`][crate::from_reader_with_report], and can be
+    /// written back out with
+    /// [`EncodeOptions::annotations`][crate::EncodeOptions::annotations].
+    pub fn preserve_comments(self, enabled: bool) -> Self {
+        DecodeOptions {
+            preserve_comments: enabled,
+            ..self
+        }
+    }
+
+    /// Decodes properties with `codecs` instead of rbx_xml's own decoding
+    /// where they apply, which allows reading datatypes that rbx_xml doesn't
+    /// support.
+    pub fn codecs(self, codecs: Codecs) -> Self {
+        DecodeOptions { codecs, ..self }
+    }
+
+    /// Enables or disables recovery mode, for reading what can be read from a
+    /// document with mistakes in it, like a hand-edited model. Defaults to
+    /// `false`.
+    ///
+    /// In recovery mode, properties, instances, and other elements that can't
+    /// be decoded are skipped instead of failing. If the document ends early
+    /// or stops being well-formed XML, the instances read up to that point
+    /// are kept. Everything that was skipped is listed in the
+    /// [warnings](DecodeReport::warnings) of the [`DecodeReport`] returned by
+    /// [`from_reader_with_report`][crate::from_reader_with_report].
+    ///
+    /// A document that isn't a version rbx_xml understands is still an error.
+    pub fn recover(self, enabled: bool) -> Self {
+        DecodeOptions {
+            recover: enabled,
+            ..self
+        }
+    }
+
+    /// Determines what happens to Ref properties that point to referents that
+    /// no instance in the document has. Defaults to
+    /// [`ExternalReferences::Null`].
+    ///
+    /// Every such property is listed in the [`DecodeReport`] returned by
+    /// [`from_reader_with_report`][crate::from_reader_with_report] unless
+    /// decoding fails because of it.
+    pub fn external_references(self, external_references: ExternalReferences) -> Self {
+        DecodeOptions {
+            external_references,
+            ..self
+        }
+    }
+
+    /// Determines whether properties are renamed from the names Roblox
+    /// serializes them with, like `size` and `Color3uint8`, to their canonical
+    /// names, like `Size` and `Color`. Defaults to `true`.
+    ///
+    /// When this is disabled, properties keep the names they have in the
+    /// document and aren't migrated to newer properties, but their values are
+    /// still converted to the types that the reflection database gives those
+    /// names. [`DecodePropertyBehavior::NoReflection`] turns off the
+    /// conversions too.
+    pub fn map_property_names(self, enabled: bool) -> Self {
+        DecodeOptions {
+            map_property_names: enabled,
+            ..self
+        }
+    }
+
+    /// Determines whether properties that Roblox has replaced with newer ones
+    /// are migrated to them, like `TextLabel.Font` to `TextLabel.FontFace`.
+    /// Defaults to `true`.
+    ///
+    /// When this is disabled, older properties keep their names and values,
+    /// so documents come out the way they were saved. See
+    /// [`ReflectionDatabase::migrate_property`][rbx_reflection::ReflectionDatabase::migrate_property]
+    /// for migrating them later. Properties are never migrated when
+    /// [`map_property_names`](Self::map_property_names) is disabled.
+    pub fn migrate_properties(self, enabled: bool) -> Self {
+        DecodeOptions {
+            migrate_properties: enabled,
+            ..self
+        }
+    }
+
+    /// Uses the given reflection database instead of
+    /// [`rbx_reflection_database::global`], like one for the version of Roblox that
+    /// the documents being read were saved with. See
+    /// [`rbx_reflection_database::get_bundled`].
+    pub fn reflection_database(self, database: &'static ReflectionDatabase<'static>) -> Self {
+        DecodeOptions { database, ..self }
+    }
+
+    /// Determines whether properties that are missing from an instance are
+    /// given their default values from the reflection database, like Roblox
+    /// does when it loads a file. Defaults to `false`.
+    ///
+    /// This undoes
+    /// [`EncodeOptions::include_default_properties`][crate::EncodeOptions::include_default_properties]:
+    /// a document written without its default properties decodes the same as
+    /// one written with them. Defaults aren't applied when
+    /// [`map_property_names`](Self::map_property_names) is disabled, since
+    /// they're looked up by canonical name.
+    pub fn apply_default_properties(self, enabled: bool) -> Self {
+        DecodeOptions {
+            apply_default_properties: enabled,
+            ..self
+        }
+    }
+
+    /// Only decodes the instances chosen by `selector` and their descendants,
+    /// skipping the rest of the document. This makes it much cheaper to pull
+    /// a few instances out of a large place.
+    ///
+    /// This doesn't apply to [`stream_from_reader`][crate::stream_from_reader].
+    pub fn select(self, selector: Selector) -> Self {
+        DecodeOptions {
+            selector: Some(selector),
+            ..self
+        }
+    }
+
+    /// Limits how deeply elements can be nested in the document, counting the
+    /// `roblox` element, every `Item`, and the elements of property values.
+    /// Documents nested any deeper fail to decode. There's no limit by
+    /// default.
+    ///
+    /// This, [`max_properties_per_instance`](Self::max_properties_per_instance),
+    /// and [`max_entity_expansion`](Self::max_entity_expansion) are meant for
+    /// services that decode documents from users they don't trust. Going over
+    /// any of them can't be recovered from in [recovery mode](Self::recover).
+    /// Errors from the first two are recognized by
+    /// [`DecodeError::is_limit_exceeded`], while entities that expand too far
+    /// are reported by the XML parser.
+    pub fn max_depth(self, max_depth: usize) -> Self {
+        DecodeOptions {
+            max_depth: Some(max_depth),
+            ..self
+        }
+    }
+
+    /// Limits how many properties a single instance can have. Documents with
+    /// an instance with any more fail to decode. There's no limit by default.
+    pub fn max_properties_per_instance(self, max_properties: usize) -> Self {
+        DecodeOptions {
+            max_properties: Some(max_properties),
+            ..self
+        }
+    }
+
+    /// Limits how many characters of expanded entities, declared in the
+    /// document's DTD, can be waiting to be read at once. This guards against
+    /// entities that expand to each other many times over, like the "billion
+    /// laughs" attack. Defaults to the XML parser's own limit of one million
+    /// characters. The parser also never expands entities more than ten
+    /// levels deep.
+    ///
+    /// Roblox never writes DTDs, so services that only accept documents from
+    /// Roblox can set this to `0`.
+    pub fn max_entity_expansion(self, max_length: usize) -> Self {
+        DecodeOptions {
+            max_entity_expansion: Some(max_length),
+            ..self
+        }
+    }
+
+    /// The limits that apply to the XML reader itself.
+    pub(crate) fn reader_limits(&self) -> ReaderLimits {
+        ReaderLimits {
+            max_depth: self.max_depth,
+            max_entity_expansion: self.max_entity_expansion,
+        }
     }
 
     /// A utility function to determine whether or not we should reference the
@@ -102,10 +419,76 @@ impl Default for DecodeOptions {
     }
 }
 
+impl fmt::Debug for DecodeOptions {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("DecodeOptions")
+            .field("property_behavior", &self.property_behavior)
+            .field("progress", &self.progress.as_ref().map(|_| "<callback>"))
+            .field("cancel_token", &self.cancel_token)
+            .field("preserve_unknown_elements", &self.preserve_unknown_elements)
+            .field("codecs", &self.codecs)
+            .field("recover", &self.recover)
+            .field("external_references", &self.external_references)
+            .field("map_property_names", &self.map_property_names)
+            .field("migrate_properties", &self.migrate_properties)
+            .field("apply_default_properties", &self.apply_default_properties)
+            .field("selector", &self.selector)
+            .field("preserve_comments", &self.preserve_comments)
+            .field("max_depth", &self.max_depth)
+            .field("max_properties", &self.max_properties)
+            .field("max_entity_expansion", &self.max_entity_expansion)
+            .field("database", &self.database.version)
+            .finish()
+    }
+}
+
+/// How far decoding has gotten through a document, passed to the callback
+/// given to [`DecodeOptions::on_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeProgress {
+    bytes_read: u64,
+    instances_decoded: usize,
+}
+
+impl DecodeProgress {
+    /// The number of bytes of the document that have been read so far. The
+    /// XML parser reads ahead, so this can be a little past the instance
+    /// that was just decoded.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// The number of instances that have been read so far.
+    pub fn instances_decoded(&self) -> usize {
+        self.instances_decoded
+    }
+}
+
+/// Wraps a reader to keep track of how many bytes have been read from it.
+pub(crate) struct CountingReader<R> {
+    inner: R,
+    bytes_read: Rc<Cell<u64>>,
+}
+
+impl<R> CountingReader<R> {
+    pub(crate) fn new(inner: R, bytes_read: Rc<Cell<u64>>) -> Self {
+        CountingReader { inner, bytes_read }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buffer)?;
+        self.bytes_read.set(self.bytes_read.get() + len as u64);
+        Ok(len)
+    }
+}
+
 /// The state needed to deserialize an XML model into an `WeakDom`.
 pub struct ParseState<'a> {
-    tree: &'a mut WeakDom,
-    options: DecodeOptions,
+    pub(crate) tree: &'a mut WeakDom,
+    pub(crate) options: DecodeOptions,
 
     /// Metadata deserialized from 'Meta' fields in the file.
     /// Known fields are:
@@ -122,36 +505,53 @@ pub struct ParseState<'a> {
     /// A list of Ref property rewrites to apply. After the first
     /// deserialization pass, we enumerate over this list and fill in the
     /// correct Ref value by using the referents map.
-    referent_rewrites: Vec<ReferentRewrite>,
+    pub(crate) referent_rewrites: Vec<ReferentRewrite>,
 
     /// A map from shared string hashes (currently MD5, decided by Roblox) to
     /// the actual SharedString type.
-    known_shared_strings: HashMap<String, SharedString>,
+    pub(crate) known_shared_strings: HashMap<String, SharedString>,
 
     /// A list of SharedString properties to set in the tree as a secondary
     /// pass. This works just like referent rewriting since the shared string
     /// dictionary is usually at the end of the XML file.
-    shared_string_rewrites: Vec<SharedStringRewrite>,
+    pub(crate) shared_string_rewrites: Vec<SharedStringRewrite>,
 
     /// Contains all of the unknown types that have been found so far. Tracking
     /// them here helps ensure that we only output a warning once per type.
     unknown_type_names: HashSet<String>,
+
+    /// The number of bytes read from the document so far.
+    bytes_read: Rc<Cell<u64>>,
+
+    /// The number of instances read so far.
+    instances_decoded: usize,
+
+    /// The number of property values given to instances so far.
+    property_values: usize,
+
+    /// Everything else that was found while decoding that the caller might
+    /// want to know about.
+    pub(crate) report: DecodeReport,
 }
 
-struct ReferentRewrite {
+pub(crate) struct ReferentRewrite {
     id: Ref,
     property_name: String,
-    referent_value: String,
+    pub(crate) referent_value: String,
 }
 
-struct SharedStringRewrite {
+pub(crate) struct SharedStringRewrite {
     id: Ref,
     property_name: String,
-    shared_string_hash: String,
+    pub(crate) shared_string_hash: String,
 }
 
 impl<'a> ParseState<'a> {
-    fn new(tree: &mut WeakDom, options: DecodeOptions) -> ParseState {
+    pub(crate) fn new(
+        tree: &mut WeakDom,
+        options: DecodeOptions,
+        bytes_read: Rc<Cell<u64>>,
+    ) -> ParseState<'_> {
         ParseState {
             tree,
             options,
@@ -161,7 +561,30 @@ impl<'a> ParseState<'a> {
             known_shared_strings: HashMap::new(),
             shared_string_rewrites: Vec::new(),
             unknown_type_names: HashSet::new(),
+            bytes_read,
+            instances_decoded: 0,
+            property_values: 0,
+            report: DecodeReport::default(),
+        }
+    }
+
+    /// Called when the deserializer has read the start of an instance.
+    /// Returns whether decoding should continue.
+    pub(crate) fn instance_visited(&mut self) -> bool {
+        self.instances_decoded += 1;
+
+        if let Some(callback) = &self.options.progress {
+            callback(&DecodeProgress {
+                bytes_read: self.bytes_read.get(),
+                instances_decoded: self.instances_decoded,
+            });
         }
+
+        !self
+            .options
+            .cancel_token
+            .as_ref()
+            .is_some_and(|token| token.load(Ordering::Relaxed))
     }
 
     /// Called when the deserializer encounters an unknown property type.
@@ -182,6 +605,170 @@ impl<'a> ParseState<'a> {
         );
     }
 
+    /// Returns an error if an instance that already has `count` properties
+    /// isn't allowed to have another one.
+    pub(crate) fn check_property_count<R: Read>(
+        &self,
+        reader: &XmlEventReader<R>,
+        count: usize,
+    ) -> Result<(), DecodeError> {
+        match self.options.max_properties {
+            Some(max) if count >= max => Err(reader.error(DecodeErrorKind::LimitExceeded {
+                limit: "properties in one instance",
+                max,
+            })),
+            _ => Ok(()),
+        }
+    }
+
+    /// Consumes the next element as raw XML if unknown elements are being
+    /// preserved, or skips over it otherwise.
+    pub fn unknown_element_visited<R: Read>(
+        &mut self,
+        reader: &mut XmlEventReader<R>,
+        id: Ref,
+        kind: UnknownElementKind,
+    ) -> Result<(), DecodeError> {
+        if self.options.preserve_unknown_elements {
+            let xml = reader.read_raw_element()?;
+            self.report
+                .unknown_elements
+                .push(UnknownElement::new(id, kind, xml));
+        } else {
+            reader.eat_unknown_tag()?;
+        }
+
+        Ok(())
+    }
+
+    /// In recovery mode, records `error` as a warning and skips the rest of
+    /// the element it happened in, which started at `depth`. Otherwise,
+    /// returns `error`.
+    pub(crate) fn recover<R: Read>(
+        &mut self,
+        reader: &mut XmlEventReader<R>,
+        depth: usize,
+        error: DecodeError,
+        skipped: Skipped,
+    ) -> Result<(), DecodeError> {
+        if !self.options.recover || !error.is_recoverable() {
+            return Err(error);
+        }
+
+        log::warn!("Skipping part of XML document: {}", error);
+        self.report
+            .warnings
+            .push(DecodeWarning::new(skipped, &error));
+
+        reader.skip_to_depth(depth)
+    }
+
+    /// Records the comments and processing instructions read since this was
+    /// last called as being at `position`.
+    fn annotations_visited<R: Read>(
+        &mut self,
+        reader: &mut XmlEventReader<R>,
+        position: AnnotationPosition,
+    ) {
+        let annotations = reader.take_annotations();
+        self.add_annotations(annotations, position);
+    }
+
+    fn add_annotations(
+        &mut self,
+        annotations: Vec<AnnotationContent>,
+        position: AnnotationPosition,
+    ) {
+        for content in annotations {
+            self.report
+                .annotations
+                .push(Annotation::new(position.clone(), content));
+        }
+    }
+
+    /// Creates an instance to decode an `Item` into.
+    fn start_instance(
+        &mut self,
+        parent_id: Ref,
+        class_name: String,
+        referent: Option<String>,
+    ) -> Ref {
+        let instance_id = self
+            .tree
+            .insert(parent_id, InstanceBuilder::new(class_name));
+
+        if let Some(referent) = referent {
+            self.referents_to_ids.insert(referent, instance_id);
+        }
+
+        instance_id
+    }
+
+    /// Removes an instance that was just started and hasn't been given any
+    /// children. This is much cheaper than `remove_instance`, since anything
+    /// recorded about the instance is at the end of each list.
+    fn discard_instance(&mut self, id: Ref, referent: Option<&str>) {
+        self.tree.destroy(id);
+
+        if let Some(referent) = referent {
+            self.referents_to_ids.remove(referent);
+        }
+
+        while self
+            .referent_rewrites
+            .last()
+            .is_some_and(|rewrite| rewrite.id == id)
+        {
+            self.referent_rewrites.pop();
+        }
+        while self
+            .shared_string_rewrites
+            .last()
+            .is_some_and(|rewrite| rewrite.id == id)
+        {
+            self.shared_string_rewrites.pop();
+        }
+        while self
+            .report
+            .unknown_elements
+            .last()
+            .is_some_and(|element| element.referent == id)
+        {
+            self.report.unknown_elements.pop();
+        }
+        while self
+            .report
+            .annotations
+            .last()
+            .is_some_and(|annotation| annotation.position.instance() == Some(id))
+        {
+            self.report.annotations.pop();
+        }
+    }
+
+    /// Removes an instance that couldn't be decoded in recovery mode, along
+    /// with everything that refers to it or its descendants.
+    fn remove_instance(&mut self, id: Ref) {
+        self.tree.destroy(id);
+
+        let tree = &*self.tree;
+        self.referents_to_ids
+            .retain(|_, id| tree.get_by_ref(*id).is_some());
+        self.referent_rewrites
+            .retain(|rewrite| tree.get_by_ref(rewrite.id).is_some());
+        self.shared_string_rewrites
+            .retain(|rewrite| tree.get_by_ref(rewrite.id).is_some());
+        self.report
+            .unknown_elements
+            .retain(|element| tree.get_by_ref(element.referent).is_some());
+        self.report.annotations.retain(|annotation| {
+            annotation
+                .position
+                .instance()
+                .is_none_or(|instance| tree.get_by_ref(instance).is_some())
+        });
+    }
+
     /// Marks that a property on this instance needs to be rewritten once we
     /// have a complete view of how referents map to Ref values.
     ///
@@ -212,46 +799,107 @@ impl<'a> ParseState<'a> {
     }
 }
 
-fn apply_referent_rewrites(state: &mut ParseState) {
+fn apply_referent_rewrites<R: Read>(
+    reader: &mut XmlEventReader<R>,
+    state: &mut ParseState,
+) -> Result<(), DecodeError> {
     for rewrite in &state.referent_rewrites {
-        let new_value = match state.referents_to_ids.get(&rewrite.referent_value) {
-            Some(id) => *id,
-            None => continue,
-        };
-
         let instance = state
             .tree
             .get_by_ref_mut(rewrite.id)
             .expect("rbx_xml bug: had ID in referent rewrite list that didn't end up in the tree");
 
+        let new_value = match state.referents_to_ids.get(&rewrite.referent_value) {
+            Some(id) => *id,
+            None => {
+                // The property was set to null when it was read.
+                match state.options.external_references {
+                    ExternalReferences::Null => {}
+                    ExternalReferences::Drop => {
+                        instance.properties.remove(&rewrite.property_name);
+                    }
+                    ExternalReferences::Error => {
+                        return Err(reader.error(DecodeErrorKind::UnresolvedReference {
+                            class_name: instance.class.clone(),
+                            property_name: rewrite.property_name.clone(),
+                            referent: rewrite.referent_value.clone(),
+                        }));
+                    }
+                }
+
+                state
+                    .report
+                    .unresolved_references
+                    .push(UnresolvedReference::new(
+                        rewrite.id,
+                        rewrite.property_name.clone(),
+                        rewrite.referent_value.clone(),
+                    ));
+
+                continue;
+            }
+        };
+
         instance
             .properties
             .insert(rewrite.property_name.clone(), Variant::Ref(new_value));
     }
+
+    Ok(())
 }
 
-fn apply_shared_string_rewrites(state: &mut ParseState) {
+fn apply_shared_string_rewrites<R: Read>(
+    reader: &mut XmlEventReader<R>,
+    state: &mut ParseState,
+) -> Result<(), DecodeError> {
     for rewrite in &state.shared_string_rewrites {
-        let new_value = match state.known_shared_strings.get(&rewrite.shared_string_hash) {
-            Some(v) => v.clone(),
-            None => continue,
-        };
-
         let instance = state.tree.get_by_ref_mut(rewrite.id).expect(
             "rbx_xml bug: had ID in SharedString rewrite list that didn't end up in the tree",
         );
 
+        let new_value = match state.known_shared_strings.get(&rewrite.shared_string_hash) {
+            Some(v) => v.clone(),
+            None => {
+                let error = reader.error(DecodeErrorKind::MissingSharedString {
+                    class_name: instance.class.clone(),
+                    property_name: rewrite.property_name.clone(),
+                    hash: rewrite.shared_string_hash.clone(),
+                });
+
+                if !state.options.recover {
+                    return Err(error);
+                }
+
+                // The property was read as a placeholder, which shouldn't be
+                // left in its place.
+                instance.properties.remove(&rewrite.property_name);
+
+                log::warn!("Skipping part of XML document: {}", error);
+                state.report.warnings.push(DecodeWarning::new(
+                    Skipped::Property {
+                        class_name: instance.class.clone(),
+                        property_name: rewrite.property_name.clone(),
+                    },
+                    &error,
+                ));
+
+                continue;
+            }
+        };
+
         instance.properties.insert(
             rewrite.property_name.clone(),
             Variant::SharedString(new_value),
         );
     }
+
+    Ok(())
 }
 
-fn deserialize_root<R: Read>(
+/// Consumes the start of the document up to and including the opening
+/// `roblox` tag, checking that the document is a version we understand.
+pub(crate) fn read_document_start<R: Read>(
     reader: &mut XmlEventReader<R>,
-    state: &mut ParseState,
-    parent_id: Ref,
 ) -> Result<(), DecodeError> {
     match reader.expect_next()? {
         XmlReadEvent::StartDocument { .. } => {}
@@ -275,51 +923,75 @@ fn deserialize_root<R: Read>(
         return Err(reader.error(DecodeErrorKind::WrongDocVersion(doc_version)));
     }
 
+    Ok(())
+}
+
+fn deserialize_root<R: Read>(
+    reader: &mut XmlEventReader<R>,
+    state: &mut ParseState,
+    parent_id: Ref,
+) -> Result<(), DecodeError> {
+    read_document_start(reader)?;
+    state.annotations_visited(reader, AnnotationPosition::Prolog);
+
+    let selector = state.options.selector.clone();
+
     loop {
         match reader.expect_peek()? {
             XmlReadEvent::StartElement { name, .. } => {
                 match name.local_name.as_str() {
-                    "Item" => {
-                        deserialize_instance(reader, state, parent_id)?;
-                    }
+                    "Item" => match &selector {
+                        Some(selector) => {
+                            select_instance(reader, state, parent_id, selector, &mut Vec::new())?;
+                        }
+                        None => {
+                            deserialize_instance(reader, state, parent_id)?;
+                        }
+                    },
                     "External" => {
                         // This tag is always meaningless, there's nothing to do
                         // here except skip it.
                         reader.eat_unknown_tag()?;
                     }
                     "Meta" => {
-                        deserialize_metadata(reader, state)?;
+                        let depth = reader.depth();
+                        if let Err(error) = deserialize_metadata(reader, state) {
+                            state.recover(reader, depth, error, Skipped::Element)?;
+                        }
                     }
                     "SharedStrings" => {
                         deserialize_shared_string_dict(reader, state)?;
                     }
-                    _ => {
-                        let event = reader.expect_next().unwrap();
-                        return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
-                    }
+                    _ => unexpected_event(reader, state)?,
                 }
             }
-            XmlReadEvent::EndElement { name } => {
-                if name.local_name == "roblox" {
-                    reader.expect_next().unwrap();
-                    break;
-                } else {
-                    let event = reader.expect_next().unwrap();
-                    return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
-                }
+            XmlReadEvent::EndElement { name } if name.local_name == "roblox" => {
+                reader.expect_next().unwrap();
+                state.annotations_visited(reader, AnnotationPosition::EndOfDocument);
+                break;
             }
             XmlReadEvent::EndDocument => break,
-            _ => {
-                let event = reader.expect_next().unwrap();
-                return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
-            }
+            _ => unexpected_event(reader, state)?,
         }
     }
 
     Ok(())
 }
 
-fn deserialize_metadata<R: Read>(
+/// Consumes an event that isn't valid where it appears, which is an error
+/// unless we're in recovery mode, where the event is skipped instead.
+fn unexpected_event<R: Read>(
+    reader: &mut XmlEventReader<R>,
+    state: &mut ParseState,
+) -> Result<(), DecodeError> {
+    let depth = reader.depth();
+    let event = reader.expect_next()?;
+    let error = reader.error(DecodeErrorKind::UnexpectedXmlEvent(event));
+
+    state.recover(reader, depth, error, Skipped::Element)
+}
+
+pub(crate) fn deserialize_metadata<R: Read>(
     reader: &mut XmlEventReader<R>,
     state: &mut ParseState,
 ) -> Result<(), DecodeError> {
@@ -352,26 +1024,16 @@ fn deserialize_shared_string_dict<R: Read>(
 
     loop {
         match reader.expect_peek()? {
-            XmlReadEvent::StartElement { name, .. } => {
-                if name.local_name == "SharedString" {
-                    deserialize_shared_string(reader, state)?;
-                } else {
-                    let event = reader.expect_next().unwrap();
-                    return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
+            XmlReadEvent::StartElement { name, .. } if name.local_name == "SharedString" => {
+                let depth = reader.depth();
+                if let Err(error) = deserialize_shared_string(reader, state) {
+                    state.recover(reader, depth, error, Skipped::Element)?;
                 }
             }
-            XmlReadEvent::EndElement { name } => {
-                if name.local_name == "SharedStrings" {
-                    break;
-                } else {
-                    let event = reader.expect_next().unwrap();
-                    return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
-                }
-            }
-            _ => {
-                let event = reader.expect_next().unwrap();
-                return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
+            XmlReadEvent::EndElement { name } if name.local_name == "SharedStrings" => {
+                break;
             }
+            _ => unexpected_event(reader, state)?,
         }
     }
 
@@ -379,7 +1041,7 @@ fn deserialize_shared_string_dict<R: Read>(
     Ok(())
 }
 
-fn deserialize_shared_string<R: Read>(
+pub(crate) fn deserialize_shared_string<R: Read>(
     reader: &mut XmlEventReader<R>,
     state: &mut ParseState,
 ) -> Result<(), DecodeError> {
@@ -406,55 +1068,258 @@ fn deserialize_shared_string<R: Read>(
     Ok(())
 }
 
+/// Consumes the opening tag of an `Item`, returning its ClassName and
+/// referent.
+pub(crate) fn read_item_start<R: Read>(
+    reader: &mut XmlEventReader<R>,
+) -> Result<(String, Option<String>), DecodeError> {
+    let attributes = reader.expect_start_with_name("Item")?;
+
+    let mut class = None;
+    let mut referent = None;
+
+    for attribute in attributes.into_iter() {
+        match attribute.name.local_name.as_str() {
+            "class" => class = Some(attribute.value),
+            "referent" => referent = Some(attribute.value),
+            _ => {}
+        }
+    }
+
+    let class = class.ok_or_else(|| reader.error(DecodeErrorKind::MissingAttribute("class")))?;
+
+    Ok((class, referent))
+}
+
 fn deserialize_instance<R: Read>(
     reader: &mut XmlEventReader<R>,
     state: &mut ParseState,
     parent_id: Ref,
 ) -> Result<(), DecodeError> {
-    let (class_name, referent) = {
-        let attributes = reader.expect_start_with_name("Item")?;
+    let depth = reader.depth();
 
-        let mut class = None;
-        let mut referent = None;
+    let (class_name, referent) = match read_item_start(reader) {
+        Ok(item) => item,
+        Err(error) => {
+            return state.recover(reader, depth, error, Skipped::Instance { class_name: None })
+        }
+    };
 
-        for attribute in attributes.into_iter() {
-            match attribute.name.local_name.as_str() {
-                "class" => class = Some(attribute.value),
-                "referent" => referent = Some(attribute.value),
-                _ => {}
+    trace!("Class {} with referent {:?}", class_name, referent);
+
+    let instance_id = state.start_instance(parent_id, class_name, referent);
+    state.annotations_visited(reader, AnnotationPosition::BeforeInstance(instance_id));
+
+    if !state.instance_visited() {
+        return Err(reader.error(DecodeErrorKind::Cancelled));
+    }
+
+    finish_instance(reader, state, depth, instance_id, HashMap::new())
+}
+
+/// Reads the rest of an instance that was started with
+/// `ParseState::start_instance`, whose `Properties` might have been read into
+/// `properties` already. `depth` is the depth of the instance's `Item`.
+fn finish_instance<R: Read>(
+    reader: &mut XmlEventReader<R>,
+    state: &mut ParseState,
+    depth: usize,
+    instance_id: Ref,
+    mut properties: HashMap<String, Variant>,
+) -> Result<(), DecodeError> {
+    let result = deserialize_instance_contents(reader, state, instance_id, &mut properties);
+
+    // If decoding stops partway through an instance in recovery mode, the
+    // properties read so far are still kept.
+    let result = match result {
+        Ok(()) => apply_properties(reader, state, instance_id, properties),
+        Err(error) => {
+            if state.options.recover && !error.is_recoverable() {
+                apply_properties(reader, state, instance_id, properties).ok();
             }
+
+            Err(error)
         }
+    };
 
-        let class =
-            class.ok_or_else(|| reader.error(DecodeErrorKind::MissingAttribute("class")))?;
+    match result {
+        Ok(()) => Ok(()),
+        Err(error) if state.options.recover && error.is_recoverable() => {
+            let class_name = state.tree.get_by_ref(instance_id).unwrap().class.clone();
+            state.remove_instance(instance_id);
+
+            state.recover(
+                reader,
+                depth,
+                error,
+                Skipped::Instance {
+                    class_name: Some(class_name),
+                },
+            )
+        }
+        Err(error) => Err(error),
+    }
+}
 
-        (class, referent)
-    };
+/// Reads an instance when only the instances chosen by `selector` are being
+/// decoded, none of which are its ancestors. `path` holds the Names of its
+/// ancestors.
+fn select_instance<R: Read>(
+    reader: &mut XmlEventReader<R>,
+    state: &mut ParseState,
+    parent_id: Ref,
+    selector: &Selector,
+    path: &mut Vec<String>,
+) -> Result<(), DecodeError> {
+    let depth = reader.depth();
+    let (class_name, referent) = read_item_start(reader)?;
 
-    trace!("Class {} with referent {:?}", class_name, referent);
+    // Annotations are only kept for the instances that are selected.
+    let annotations = reader.take_annotations();
+
+    if !state.instance_visited() {
+        return Err(reader.error(DecodeErrorKind::Cancelled));
+    }
+
+    match selector.plan(&class_name, path.len()) {
+        SelectorPlan::Decode => {
+            trace!("Selected class {} with referent {:?}", class_name, referent);
 
-    let builder = InstanceBuilder::new(class_name);
-    let instance_id = state.tree.insert(parent_id, builder);
+            let instance_id = state.start_instance(parent_id, class_name, referent);
+            state.add_annotations(annotations, AnnotationPosition::BeforeInstance(instance_id));
+            return finish_instance(reader, state, depth, instance_id, HashMap::new());
+        }
+        // Class selectors don't look at paths, so there's no need to read the
+        // Name to put in this one.
+        SelectorPlan::Search => path.push(class_name),
+        SelectorPlan::ReadName => {
+            let name = read_instance_name(reader)?.unwrap_or(class_name);
+            path.push(name);
+        }
+        SelectorPlan::TryDecode => {
+            let instance_id = state.start_instance(parent_id, class_name.clone(), referent.clone());
+
+            let mut properties = HashMap::new();
+            if let XmlReadEvent::StartElement { name, .. } = reader.expect_peek()? {
+                if name.local_name == "Properties" {
+                    deserialize_properties(reader, state, instance_id, &mut properties)?;
+                }
+            }
 
-    if let Some(referent) = referent {
-        state.referents_to_ids.insert(referent, instance_id);
+            match properties.get("Name") {
+                Some(Variant::String(name)) => path.push(name.clone()),
+                _ => path.push(class_name.clone()),
+            }
+
+            let candidate = SelectorCandidate {
+                class_name: &class_name,
+                path,
+                referent: referent.as_deref(),
+            };
+
+            if selector.matches(&candidate) {
+                trace!("Selected path {:?} with referent {:?}", path, referent);
+
+                path.pop();
+                state.add_annotations(annotations, AnnotationPosition::BeforeInstance(instance_id));
+                return finish_instance(reader, state, depth, instance_id, properties);
+            }
+
+            state.discard_instance(instance_id, referent.as_deref());
+        }
     }
 
-    let mut properties: HashMap<String, Variant> = HashMap::new();
+    let result = search_instance_contents(reader, state, parent_id, selector, path);
+    path.pop();
+    result
+}
+
+/// Reads the rest of an instance that wasn't selected, looking for selected
+/// instances among its descendants. `path` holds the Names of the instance and
+/// its ancestors.
+fn search_instance_contents<R: Read>(
+    reader: &mut XmlEventReader<R>,
+    state: &mut ParseState,
+    parent_id: Ref,
+    selector: &Selector,
+    path: &mut Vec<String>,
+) -> Result<(), DecodeError> {
+    let search_children = selector.could_match_below(path);
 
+    loop {
+        match reader.expect_peek()? {
+            XmlReadEvent::StartElement { name, .. } => {
+                if name.local_name == "Item" && search_children {
+                    select_instance(reader, state, parent_id, selector, path)?;
+                } else {
+                    reader.eat_unknown_tag()?;
+                }
+            }
+            XmlReadEvent::EndElement { name } if name.local_name == "Item" => {
+                reader.expect_next().unwrap();
+                reader.take_annotations();
+                return Ok(());
+            }
+            _ => {
+                let event = reader.expect_next().unwrap();
+                return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
+            }
+        }
+    }
+}
+
+/// Reads an instance's `Properties`, if it's next, without decoding anything
+/// but its Name.
+fn read_instance_name<R: Read>(
+    reader: &mut XmlEventReader<R>,
+) -> Result<Option<String>, DecodeError> {
+    match reader.expect_peek()? {
+        XmlReadEvent::StartElement { name, .. } if name.local_name == "Properties" => {}
+        _ => return Ok(None),
+    }
+
+    reader.expect_start_with_name("Properties")?;
+
+    let mut instance_name = None;
+    while let Some((xml_type_name, xml_property_name)) = peek_property_element(reader)? {
+        if xml_type_name == "string" && xml_property_name == "Name" {
+            reader.expect_start_with_name("string")?;
+            instance_name = Some(reader.read_characters()?);
+            reader.expect_end_with_name("string")?;
+        } else {
+            reader.eat_unknown_tag()?;
+        }
+    }
+
+    Ok(instance_name)
+}
+
+/// Reads everything inside of an `Item` up to and including its end tag,
+/// collecting its properties into `properties`.
+fn deserialize_instance_contents<R: Read>(
+    reader: &mut XmlEventReader<R>,
+    state: &mut ParseState,
+    instance_id: Ref,
+    properties: &mut HashMap<String, Variant>,
+) -> Result<(), DecodeError> {
     loop {
         match reader.expect_peek()? {
             XmlReadEvent::StartElement { name, .. } => match name.local_name.as_str() {
                 "Properties" => {
-                    deserialize_properties(reader, state, instance_id, &mut properties)?;
+                    deserialize_properties(reader, state, instance_id, properties)?;
                 }
                 "Item" => {
                     deserialize_instance(reader, state, instance_id)?;
                 }
-                _ => {
-                    let event = reader.expect_next().unwrap();
-                    return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
+                "External" => {
+                    // Old versions of Roblox wrote these inside of instances
+                    // too, and they're just as meaningless there.
+                    reader.eat_unknown_tag()?;
+                }
+                _ if state.options.preserve_unknown_elements => {
+                    state.unknown_element_visited(reader, instance_id, UnknownElementKind::Item)?;
                 }
+                _ => unexpected_event(reader, state)?,
             },
             XmlReadEvent::EndElement { name } => {
                 if name.local_name != "Item" {
@@ -463,32 +1328,56 @@ fn deserialize_instance<R: Read>(
                 }
 
                 reader.expect_next().unwrap();
+                state.annotations_visited(reader, AnnotationPosition::EndOfInstance(instance_id));
 
                 break;
             }
-            _ => {
-                let event = reader.expect_next().unwrap();
-                return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
-            }
+            _ => unexpected_event(reader, state)?,
         }
     }
 
-    let instance = state.tree.get_by_ref_mut(instance_id).unwrap();
-
-    instance.name = match properties.remove("Name") {
-        Some(value) => match value {
-            Variant::String(value) => value,
-            _ => return Err(reader.error(DecodeErrorKind::NameMustBeString(value.ty()))),
-        },
+    Ok(())
+}
 
-        // TODO: Use reflection to get default name instead. This should only
-        // matter for ValueBase instances in files created by tools other than
-        // Roblox Studio.
-        None => instance.class.clone(),
-    };
+/// Moves the properties read from an instance's `Properties` into the
+/// instance itself.
+fn apply_properties<R: Read>(
+    reader: &mut XmlEventReader<R>,
+    state: &mut ParseState,
+    instance_id: Ref,
+    mut properties: HashMap<String, Variant>,
+) -> Result<(), DecodeError> {
+    let instance = state.tree.get_by_ref_mut(instance_id).unwrap();
+    let name = properties.remove("Name");
+
+    if state.options.apply_default_properties
+        && state.options.map_property_names
+        && state.options.use_reflection()
+    {
+        // Names are kept on the instance rather than in its properties.
+        for (property_name, default) in default_properties(state.options.database, &instance.class)
+            .into_iter()
+            .filter(|(property_name, _)| *property_name != "Name")
+        {
+            properties
+                .entry(property_name.to_owned())
+                .or_insert(default);
+        }
+    }
 
+    state.property_values += properties.len();
     instance.properties = properties;
 
+    // TODO: Use reflection to get default name instead. This should only
+    // matter for ValueBase instances in files created by tools other than
+    // Roblox Studio. Until then, instances without a Name keep their
+    // ClassName as their name.
+    match name {
+        Some(Variant::String(value)) => instance.name = value,
+        Some(value) => return Err(reader.error(DecodeErrorKind::NameMustBeString(value.ty()))),
+        None => {}
+    }
+
     Ok(())
 }
 
@@ -514,164 +1403,277 @@ fn deserialize_properties<R: Read>(
     );
 
     loop {
-        let (xml_type_name, xml_property_name) = {
-            match reader.expect_peek()? {
-                XmlReadEvent::StartElement {
-                    name, attributes, ..
-                } => {
-                    let mut xml_property_name = None;
-
-                    for attribute in attributes {
-                        if attribute.name.local_name.as_str() == "name" {
-                            xml_property_name = Some(attribute.value.to_owned());
-                            break;
-                        }
-                    }
-
-                    let xml_property_name = match xml_property_name {
-                        Some(value) => value,
-                        None => return Err(reader.error(DecodeErrorKind::MissingAttribute("name"))),
-                    };
+        let depth = reader.depth();
 
-                    (name.local_name.to_owned(), xml_property_name)
-                }
-                XmlReadEvent::EndElement { name } => {
-                    if name.local_name == "Properties" {
-                        reader.expect_next()?;
-                        return Ok(());
-                    } else {
-                        let err = DecodeErrorKind::UnexpectedXmlEvent(reader.expect_next()?);
-                        return Err(reader.error(err));
-                    }
-                }
-                _ => {
-                    let err = DecodeErrorKind::UnexpectedXmlEvent(reader.expect_next()?);
-                    return Err(reader.error(err));
-                }
+        let (xml_type_name, xml_property_name) = match peek_property_element(reader) {
+            Ok(Some(element)) => element,
+            Ok(None) => {
+                state.annotations_visited(reader, AnnotationPosition::EndOfProperties(instance_id));
+                break;
+            }
+            Err(error) => {
+                state.recover(reader, depth, error, Skipped::Element)?;
+                continue;
             }
         };
 
-        log::trace!(
-            "Deserializing property {}.{}, of type {}",
-            class_name,
+        // The name is needed again to describe errors from reading it.
+        let property_name = xml_property_name.clone();
+
+        let result = read_property(
+            reader,
+            state,
+            instance_id,
+            &class_name,
+            &xml_type_name,
             xml_property_name,
-            xml_type_name
         );
 
-        let maybe_descriptor = if state.options.use_reflection() {
-            find_canonical_property_descriptor(&class_name, &xml_property_name)
-        } else {
-            None
-        };
-
-        if let Some(descriptor) = maybe_descriptor {
-            let value =
-                match read_value_xml(reader, state, &xml_type_name, instance_id, &descriptor.name)?
-                {
-                    Some(value) => value,
-                    None => continue,
+        match result {
+            Ok(Some(property)) => {
+                // Annotations before properties that aren't kept stay where
+                // they are, so they end up before the next property instead.
+                state.annotations_visited(
+                    reader,
+                    AnnotationPosition::BeforeProperty {
+                        instance: instance_id,
+                        property_name: property.name.clone(),
+                    },
+                );
+
+                if !props.contains_key(&property.name) {
+                    state.check_property_count(reader, props.len())?;
+                    props.insert(property.name, property.value);
+                } else if !property.is_migration {
+                    props.insert(property.name, property.value);
+                }
+            }
+            Ok(None) => {}
+            Err(error) => {
+                let error = error.with_property(Some(instance_id), &class_name, &property_name);
+                let skipped = Skipped::Property {
+                    class_name: class_name.clone(),
+                    property_name,
                 };
 
-            let xml_ty = value.ty();
-
-            // The property descriptor might specify a different type than the
-            // one we saw in the XML.
-            //
-            // This happens when property types are upgraded or if the
-            // serialized data type is different than the canonical one.
-            //
-            // For example:
-            // - Int/Float widening from 32-bit to 64-bit
-            // - BrickColor properties turning into Color3
-            let expected_type = match &descriptor.data_type {
-                DataType::Value(data_type) => *data_type,
-                DataType::Enum(_enum_name) => VariantType::Enum,
-                _ => unimplemented!(),
-            };
-            log::trace!("property's read type: {xml_ty:?}, canonical type: {expected_type:?}");
-
-            let value = match value.try_convert(expected_type) {
-                Ok(value) => value,
-
-                // The property descriptor disagreed, and there was no
-                // conversion available. This is always an error.
-                Err(message) => {
-                    return Err(
-                        reader.error(DecodeErrorKind::UnsupportedPropertyConversion {
-                            class_name: class_name.clone(),
-                            property_name: descriptor.name.to_string(),
-                            expected_type,
-                            actual_type: xml_ty,
-                            message,
-                        }),
-                    );
+                state.recover(reader, depth, error, skipped)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Peeks at the next property element inside of `Properties`, returning its
+/// type and name, or consumes the end of `Properties` and returns `None` if
+/// there are no more.
+pub(crate) fn peek_property_element<R: Read>(
+    reader: &mut XmlEventReader<R>,
+) -> Result<Option<(String, String)>, DecodeError> {
+    match reader.expect_peek()? {
+        XmlReadEvent::StartElement {
+            name, attributes, ..
+        } => {
+            let mut xml_property_name = None;
+
+            for attribute in attributes {
+                if attribute.name.local_name.as_str() == "name" {
+                    xml_property_name = Some(attribute.value.to_owned());
+                    break;
                 }
+            }
+
+            let xml_property_name = match xml_property_name {
+                Some(value) => value,
+                None => {
+                    // The element is consumed so that it can be skipped in
+                    // recovery mode.
+                    reader.expect_next()?;
+                    return Err(reader.error(DecodeErrorKind::MissingAttribute("name")));
+                }
+            };
+
+            Ok(Some((name.local_name.to_owned(), xml_property_name)))
+        }
+        XmlReadEvent::EndElement { name } if name.local_name == "Properties" => {
+            reader.expect_next()?;
+            Ok(None)
+        }
+        _ => {
+            let err = DecodeErrorKind::UnexpectedXmlEvent(reader.expect_next()?);
+            Err(reader.error(err))
+        }
+    }
+}
+
+/// A property read by `read_property`, with its canonical name and type.
+pub(crate) struct ReadProperty {
+    pub name: String,
+    pub value: Variant,
+
+    /// Whether this property was migrated from an older one. Migrated values
+    /// don't replace a value of the new property that's already been read.
+    pub is_migration: bool,
+}
+
+/// Reads the property element at the head of `reader`, which belongs to the
+/// instance `instance_id` of the given class. Uses the reflection database to
+/// convert it to its canonical name and type according to the decode
+/// options.
+///
+/// Returns `None` if the property was skipped.
+pub(crate) fn read_property<R: Read>(
+    reader: &mut XmlEventReader<R>,
+    state: &mut ParseState,
+    instance_id: Ref,
+    class_name: &str,
+    xml_type_name: &str,
+    xml_property_name: String,
+) -> Result<Option<ReadProperty>, DecodeError> {
+    log::trace!(
+        "Deserializing property {}.{}, of type {}",
+        class_name,
+        xml_property_name,
+        xml_type_name
+    );
+
+    if let Some(codec) = state
+        .options
+        .codecs
+        .for_property(class_name, &xml_property_name)
+    {
+        let value = read_with_codec(reader, codec, xml_type_name)?;
+
+        return Ok(Some(ReadProperty {
+            name: xml_property_name,
+            value,
+            is_migration: false,
+        }));
+    }
+
+    let maybe_descriptor = if !state.options.use_reflection() {
+        None
+    } else if state.options.map_property_names {
+        find_canonical_property_descriptor(state.options.database, class_name, &xml_property_name)
+    } else {
+        find_exact_property_descriptor(state.options.database, class_name, &xml_property_name)
+    };
+
+    if let Some(descriptor) = maybe_descriptor {
+        let value =
+            match read_value_xml(reader, state, xml_type_name, instance_id, &descriptor.name)? {
+                Some(value) => value,
+                None => return Ok(None),
             };
 
-            match &descriptor.kind {
-                PropertyKind::Canonical {
-                    serialization: PropertySerialization::Migrate(migration),
-                } => {
-                    let new_property_name = &migration.new_property_name;
-                    let old_property_name = &descriptor.name;
+        let xml_ty = value.ty();
+
+        // The property descriptor might specify a different type than the
+        // one we saw in the XML.
+        //
+        // This happens when property types are upgraded or if the
+        // serialized data type is different than the canonical one.
+        //
+        // For example:
+        // - Int/Float widening from 32-bit to 64-bit
+        // - BrickColor properties turning into Color3
+        let expected_type = match &descriptor.data_type {
+            DataType::Value(data_type) => *data_type,
+            DataType::Enum(_enum_name) => VariantType::Enum,
+            _ => unimplemented!(),
+        };
+        log::trace!("property's read type: {xml_ty:?}, canonical type: {expected_type:?}");
+
+        let value = match value.try_convert(expected_type) {
+            Ok(value) => value,
+
+            // The property descriptor disagreed, and there was no
+            // conversion available. This is always an error.
+            Err(message) => {
+                return Err(
+                    reader.error(DecodeErrorKind::UnsupportedPropertyConversion {
+                        class_name: class_name.to_owned(),
+                        property_name: descriptor.name.to_string(),
+                        expected_type,
+                        actual_type: xml_ty,
+                        message,
+                    }),
+                );
+            }
+        };
 
-                    if !props.contains_key(new_property_name) {
+        match &descriptor.kind {
+            PropertyKind::Canonical {
+                serialization: PropertySerialization::Migrate(migration),
+            } if state.options.map_property_names && state.options.migrate_properties => {
+                let new_property_name = &migration.new_property_name;
+                let old_property_name = &descriptor.name;
+
+                log::trace!(
+                    "Attempting to migrate property {old_property_name} to {new_property_name}"
+                );
+                match migration.perform(&value) {
+                    Ok(migrated_value) => {
                         log::trace!(
-                            "Attempting to migrate property {old_property_name} to {new_property_name}"
+                            "Successfully migrated property {old_property_name} to {new_property_name}"
                         );
-                        match migration.perform(&value) {
-                            Ok(migrated_value) => {
-                                props.insert(new_property_name.to_string(), migrated_value);
-                                log::trace!(
-                                    "Successfully migrated property {old_property_name} to {new_property_name}"
-                                );
-                            }
-                            Err(error) => {
-                                return Err(reader.error(DecodeErrorKind::MigrationError(error)));
-                            }
-                        }
+                        Ok(Some(ReadProperty {
+                            name: new_property_name.to_string(),
+                            value: migrated_value,
+                            is_migration: true,
+                        }))
                     }
+                    Err(error) => Err(reader.error(DecodeErrorKind::MigrationError(error))),
                 }
-                _ => {
-                    props.insert(descriptor.name.to_string(), value);
-                }
-            };
-        } else {
-            match state.options.property_behavior {
-                DecodePropertyBehavior::IgnoreUnknown => {
-                    // We don't care about this property, so we can read it and
-                    // throw it into the void.
-
-                    read_value_xml(
-                        reader,
-                        state,
-                        &xml_type_name,
-                        instance_id,
-                        &xml_property_name,
-                    )?;
-                }
-                DecodePropertyBehavior::ReadUnknown | DecodePropertyBehavior::NoReflection => {
-                    // We'll take this value as-is with no conversions on either
-                    // the name or value.
-
-                    let value = match read_value_xml(
-                        reader,
-                        state,
-                        &xml_type_name,
-                        instance_id,
-                        &xml_property_name,
-                    )? {
-                        Some(value) => value,
-                        None => continue,
-                    };
-                    props.insert(xml_property_name, value);
-                }
-                DecodePropertyBehavior::ErrorOnUnknown => {
-                    return Err(reader.error(DecodeErrorKind::UnknownProperty {
-                        class_name,
-                        property_name: xml_property_name,
-                    }));
-                }
+            }
+            _ => Ok(Some(ReadProperty {
+                name: descriptor.name.to_string(),
+                value,
+                is_migration: false,
+            })),
+        }
+    } else {
+        match state.options.property_behavior {
+            DecodePropertyBehavior::IgnoreUnknown if state.options.preserve_unknown_elements => {
+                state.unknown_element_visited(reader, instance_id, UnknownElementKind::Property)?;
+                Ok(None)
+            }
+            DecodePropertyBehavior::IgnoreUnknown => {
+                // We don't care about this property, so we can read it and
+                // throw it into the void.
+
+                read_value_xml(
+                    reader,
+                    state,
+                    xml_type_name,
+                    instance_id,
+                    &xml_property_name,
+                )?;
+                Ok(None)
+            }
+            DecodePropertyBehavior::ReadUnknown | DecodePropertyBehavior::NoReflection => {
+                // We'll take this value as-is with no conversions on either
+                // the name or value.
+
+                let value = read_value_xml(
+                    reader,
+                    state,
+                    xml_type_name,
+                    instance_id,
+                    &xml_property_name,
+                )?;
+
+                Ok(value.map(|value| ReadProperty {
+                    name: xml_property_name,
+                    value,
+                    is_migration: false,
+                }))
+            }
+            DecodePropertyBehavior::ErrorOnUnknown => {
+                Err(reader.error(DecodeErrorKind::UnknownProperty {
+                    class_name: class_name.to_owned(),
+                    property_name: xml_property_name,
+                }))
             }
         }
     }
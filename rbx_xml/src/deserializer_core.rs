@@ -1,32 +1,202 @@
-use std::io::Read;
+use std::io::{self, Read};
 
 use log::trace;
-use xml::{attribute::OwnedAttribute, reader::ParserConfig};
+use xml::{
+    attribute::OwnedAttribute,
+    reader::{ParserConfig, ParserConfig2},
+    writer::EmitterConfig,
+};
 
 use crate::{
+    base64_stream::Base64Decoder,
     core::XmlType,
     error::{DecodeError as NewDecodeError, DecodeErrorKind},
+    report::AnnotationContent,
 };
 
 pub use xml::reader::Error as XmlReadError;
 pub use xml::reader::XmlEvent as XmlReadEvent;
 pub type XmlReadResult = Result<XmlReadEvent, XmlReadError>;
 
+/// Wraps a reader to skip a byte order mark and whitespace that come before
+/// an XML declaration. xml-rs doesn't allow anything before the declaration,
+/// but Roblox Studio opens documents that have whitespace there.
+///
+/// Documents without a declaration are left alone, so that the positions in
+/// errors stay accurate.
+struct SkipLeadingWhitespace<R> {
+    inner: R,
+
+    /// The start of the document, read while looking for a declaration.
+    prefix: Vec<u8>,
+
+    /// How much of `prefix` has been returned or skipped.
+    position: usize,
+    checked: bool,
+}
+
+const BYTE_ORDER_MARK: &[u8] = b"\xEF\xBB\xBF";
+const DECLARATION_START: &[u8] = b"<?xml";
+
+impl<R: Read> SkipLeadingWhitespace<R> {
+    fn new(inner: R) -> Self {
+        SkipLeadingWhitespace {
+            inner,
+            prefix: Vec::new(),
+            position: 0,
+            checked: false,
+        }
+    }
+
+    /// Reads from the start of the document until it's clear whether it has a
+    /// declaration after some whitespace, skipping the whitespace if so.
+    fn check_prefix(&mut self) -> io::Result<()> {
+        let mut chunk = [0; 64];
+
+        loop {
+            let start = self.content_start();
+            if self.prefix.len() >= start + DECLARATION_START.len() {
+                break;
+            }
+
+            let len = self.inner.read(&mut chunk)?;
+            if len == 0 {
+                break;
+            }
+
+            self.prefix.extend_from_slice(&chunk[..len]);
+        }
+
+        let start = self.content_start();
+        if start > 0 && self.prefix[start..].starts_with(DECLARATION_START) {
+            self.position = start;
+        }
+
+        self.checked = true;
+        Ok(())
+    }
+
+    /// The index of the first byte in `prefix` that isn't part of a byte
+    /// order mark or whitespace.
+    fn content_start(&self) -> usize {
+        let bom_len = if self.prefix.starts_with(BYTE_ORDER_MARK) {
+            BYTE_ORDER_MARK.len()
+        } else {
+            0
+        };
+
+        self.prefix[bom_len..]
+            .iter()
+            .position(|byte| !byte.is_ascii_whitespace())
+            .map_or(self.prefix.len(), |index| bom_len + index)
+    }
+}
+
+impl<R: Read> Read for SkipLeadingWhitespace<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if !self.checked {
+            self.check_prefix()?;
+        }
+
+        if self.position < self.prefix.len() {
+            let remaining = &self.prefix[self.position..];
+            let len = remaining.len().min(buffer.len());
+            buffer[..len].copy_from_slice(&remaining[..len]);
+            self.position += len;
+
+            return Ok(len);
+        }
+
+        self.inner.read(buffer)
+    }
+}
+
 /// A wrapper around an XML event iterator created by xml-rs.
 pub struct XmlEventReader<R: Read> {
-    reader: xml::EventReader<R>,
+    reader: xml::EventReader<SkipLeadingWhitespace<R>>,
     peeked: Option<Result<XmlReadEvent, xml::reader::Error>>,
     finished: bool,
+
+    /// The number of elements that have been started but not ended, not
+    /// counting a peeked event.
+    depth: usize,
+
+    /// The comments and processing instructions read since they were last
+    /// taken, if they're being collected.
+    annotations: Option<Vec<AnnotationContent>>,
+
+    max_depth: Option<usize>,
+}
+
+/// Limits on the documents an `XmlEventReader` will read, from
+/// `DecodeOptions`.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ReaderLimits {
+    pub max_depth: Option<usize>,
+    pub max_entity_expansion: Option<usize>,
 }
 
 impl<R: Read> Iterator for XmlEventReader<R> {
     type Item = XmlReadResult;
 
     fn next(&mut self) -> Option<XmlReadResult> {
-        if let Some(value) = self.peeked.take() {
-            return Some(value);
+        let next = match self.peeked.take() {
+            Some(value) => Some(value),
+            None => self.next_event(),
+        };
+
+        match &next {
+            Some(Ok(XmlReadEvent::StartElement { .. })) => self.depth += 1,
+            Some(Ok(XmlReadEvent::EndElement { .. })) => self.depth -= 1,
+            _ => {}
+        }
+
+        next
+    }
+}
+
+impl<R: Read> XmlEventReader<R> {
+    /// Constructs a new `XmlEventReader` from a source that implements `Read`.
+    pub fn from_source(source: R) -> XmlEventReader<R> {
+        Self::with_limits(source, ReaderLimits::default())
+    }
+
+    /// Constructs a new `XmlEventReader` that fails on documents that go over
+    /// `limits`.
+    pub(crate) fn with_limits(source: R, limits: ReaderLimits) -> XmlEventReader<R> {
+        let mut config = ParserConfig2::from(ParserConfig::new().ignore_comments(false));
+        if let Some(max_length) = limits.max_entity_expansion {
+            config = config.max_entity_expansion_length(max_length);
+        }
+
+        XmlEventReader {
+            reader: config.create_reader(SkipLeadingWhitespace::new(source)),
+            peeked: None,
+            finished: false,
+            depth: 0,
+            annotations: None,
+            max_depth: limits.max_depth,
         }
+    }
+
+    /// Starts keeping the comments and processing instructions that are read,
+    /// which are skipped like whitespace otherwise.
+    pub fn collect_annotations(&mut self) {
+        self.annotations.get_or_insert_with(Vec::new);
+    }
 
+    /// Takes the comments and processing instructions read since this was
+    /// last called, including those before a peeked event.
+    pub fn take_annotations(&mut self) -> Vec<AnnotationContent> {
+        match &mut self.annotations {
+            Some(annotations) => std::mem::take(annotations),
+            None => Vec::new(),
+        }
+    }
+
+    /// Reads the next event from xml-rs, skipping whitespace, comments, and
+    /// processing instructions.
+    fn next_event(&mut self) -> Option<XmlReadResult> {
         if self.finished {
             return None;
         }
@@ -35,6 +205,17 @@ impl<R: Read> Iterator for XmlEventReader<R> {
             match self.reader.next() {
                 Ok(item) => match item {
                     XmlReadEvent::Whitespace(_) => continue,
+                    XmlReadEvent::Comment(text) => {
+                        if let Some(annotations) = &mut self.annotations {
+                            annotations.push(AnnotationContent::Comment(text));
+                        }
+                    }
+                    XmlReadEvent::ProcessingInstruction { name, data } => {
+                        if let Some(annotations) = &mut self.annotations {
+                            annotations
+                                .push(AnnotationContent::ProcessingInstruction { name, data });
+                        }
+                    }
                     XmlReadEvent::EndDocument => {
                         self.finished = true;
                         return Some(Ok(item));
@@ -48,21 +229,6 @@ impl<R: Read> Iterator for XmlEventReader<R> {
             }
         }
     }
-}
-
-impl<R: Read> XmlEventReader<R> {
-    /// Constructs a new `XmlEventReader` from a source that implements `Read`.
-    pub fn from_source(source: R) -> XmlEventReader<R> {
-        let reader = ParserConfig::new()
-            .ignore_comments(true)
-            .create_reader(source);
-
-        XmlEventReader {
-            reader,
-            peeked: None,
-            finished: false,
-        }
-    }
 
     /// Borrows the next element from the event stream without consuming it.
     pub fn peek(&mut self) -> Option<&XmlReadResult> {
@@ -70,16 +236,47 @@ impl<R: Read> XmlEventReader<R> {
             return self.peeked.as_ref();
         }
 
-        self.peeked = self.next();
+        self.peeked = self.next_event();
         self.peeked.as_ref()
     }
 
+    /// The number of elements that have been started and not yet ended by
+    /// the events consumed so far.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Consumes events until the reader is back out to `depth`, finishing
+    /// any elements that were started below it.
+    pub fn skip_to_depth(&mut self, depth: usize) -> Result<(), NewDecodeError> {
+        while self.depth > depth {
+            self.expect_next()?;
+        }
+
+        Ok(())
+    }
+
+    /// The line and column of the most recently read event, counted the same
+    /// way as the locations of errors.
+    pub(crate) fn location(&self) -> (usize, usize) {
+        use xml::common::Position;
+
+        let position = self.reader.position();
+        ((position.row + 1) as usize, position.column as usize)
+    }
+
     pub(crate) fn error<T: Into<DecodeErrorKind>>(&self, kind: T) -> NewDecodeError {
         NewDecodeError::new_from_reader(kind.into(), &self.reader)
     }
 
     pub fn expect_next(&mut self) -> Result<XmlReadEvent, NewDecodeError> {
         match self.next() {
+            Some(Ok(_)) if self.max_depth.is_some_and(|max| self.depth > max) => {
+                Err(self.error(DecodeErrorKind::LimitExceeded {
+                    limit: "nested elements",
+                    max: self.max_depth.unwrap(),
+                }))
+            }
             Some(Ok(event)) => Ok(event),
             Some(Err(err)) => Err(self.error(err)),
             None => Err(self.error(DecodeErrorKind::UnexpectedEof)),
@@ -221,19 +418,43 @@ impl<R: Read> XmlEventReader<R> {
         Ok(buffer)
     }
 
+    /// The same as `read_characters`, but without any whitespace around the
+    /// characters. Values like numbers can't contain whitespace, but documents
+    /// written by hand or by other tools sometimes have it around them anyway.
+    pub fn read_trimmed_characters(&mut self) -> Result<String, NewDecodeError> {
+        let contents = self.read_characters()?;
+        let trimmed = contents.trim();
+
+        if trimmed.len() == contents.len() {
+            Ok(contents)
+        } else {
+            Ok(trimmed.to_owned())
+        }
+    }
+
     /// Reads characters from the head of the deserializer and attempts to parse
     /// them as base64 and turn them into a buffer of bytes.
     ///
     /// In Roblox XML model files, binary data is base64 encoded and
     /// line-wrapped, meaning we have to be careful to ignore whitespace.
+    ///
+    /// Each character event is decoded as soon as it's read, so large values
+    /// aren't copied into one string before being decoded.
     pub fn read_base64_characters(&mut self) -> Result<Vec<u8>, NewDecodeError> {
-        let contents: String = self
-            .read_characters()?
-            .chars()
-            .filter(|c| !c.is_whitespace())
-            .collect();
+        let first = match self.read_one_characters_event()? {
+            Some(first) => first,
+            None => return Ok(Vec::new()),
+        };
 
-        base64::decode(contents).map_err(|e| self.error(e))
+        let mut decoder = Base64Decoder::with_capacity(first.len());
+        decoder.push(&first).map_err(|e| self.error(e))?;
+        drop(first);
+
+        while let Some(piece) = self.read_one_characters_event()? {
+            decoder.push(&piece).map_err(|e| self.error(e))?;
+        }
+
+        decoder.finish().map_err(|e| self.error(e))
     }
 
     /// Reads a tag completely and returns its text content. This is intended
@@ -248,7 +469,7 @@ impl<R: Read> XmlEventReader<R> {
     /// </Vector3>
     pub fn read_tag_contents(&mut self, expected_name: &str) -> Result<String, NewDecodeError> {
         self.expect_start_with_name(expected_name)?;
-        let contents = self.read_characters()?;
+        let contents = self.read_trimmed_characters()?;
         self.expect_end_with_name(expected_name)?;
 
         Ok(contents)
@@ -272,6 +493,40 @@ impl<R: Read> XmlEventReader<R> {
         Ok(value)
     }
 
+    /// Consume events from the iterator until we reach the end of the next tag,
+    /// returning the tag and everything inside of it as XML text.
+    pub fn read_raw_element(&mut self) -> Result<String, NewDecodeError> {
+        let mut output = Vec::new();
+        let mut writer = EmitterConfig::new()
+            .write_document_declaration(false)
+            .normalize_empty_elements(false)
+            .create_writer(&mut output);
+        let mut depth = 0;
+
+        loop {
+            let event = self.expect_next()?;
+
+            match &event {
+                XmlReadEvent::StartElement { .. } => depth += 1,
+                XmlReadEvent::EndElement { .. } => depth -= 1,
+                _ => {}
+            }
+
+            if let Some(writer_event) = event.as_writer_event() {
+                writer
+                    .write(writer_event)
+                    .map_err(|err| self.error(DecodeErrorKind::RawXml(err)))?;
+            }
+
+            if depth == 0 {
+                break;
+            }
+        }
+
+        // The input was valid UTF-8 and xml-rs only writes what it read.
+        Ok(String::from_utf8(output).unwrap())
+    }
+
     /// Consume events from the iterator until we reach the end of the next tag.
     pub fn eat_unknown_tag(&mut self) -> Result<(), NewDecodeError> {
         let mut depth = 0;
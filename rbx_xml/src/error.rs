@@ -3,7 +3,9 @@ use std::{
     io::{self, Read, Write},
 };
 
-use rbx_dom_weak::types::VariantType;
+use rbx_dom_weak::types::{DomError, ErrorCode, ErrorLocation, Ref, VariantType};
+
+use crate::codec::CodecError;
 
 /// An error that can occur when deserializing an XML-format model or place.
 #[derive(Debug)]
@@ -28,10 +30,65 @@ impl DecodeError {
                 kind,
                 line: (pos.row + 1) as usize,
                 column: pos.column as usize,
+                property: None,
             }),
         }
     }
 
+    /// Tells whether this error happened because decoding was cancelled with
+    /// [`DecodeOptions::cancel_token`][crate::DecodeOptions::cancel_token].
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.inner.kind, DecodeErrorKind::Cancelled)
+    }
+
+    /// Tells whether this error happened because the document went over one
+    /// of the limits set with [`DecodeOptions`][crate::DecodeOptions], like
+    /// [`DecodeOptions::max_depth`][crate::DecodeOptions::max_depth].
+    pub fn is_limit_exceeded(&self) -> bool {
+        matches!(self.inner.kind, DecodeErrorKind::LimitExceeded { .. })
+    }
+
+    /// Tells whether decoding can continue after this error by skipping the
+    /// part of the document it happened in. Errors from the XML parser can't
+    /// be recovered from, since it won't read past them.
+    pub(crate) fn is_recoverable(&self) -> bool {
+        !matches!(
+            self.inner.kind,
+            DecodeErrorKind::Xml(_)
+                | DecodeErrorKind::UnexpectedEof
+                | DecodeErrorKind::Cancelled
+                | DecodeErrorKind::LimitExceeded { .. }
+        )
+    }
+
+    /// Records that this error happened while reading the given property,
+    /// unless it already knows which property it's about.
+    pub(crate) fn with_property(
+        mut self,
+        referent: Option<Ref>,
+        class_name: &str,
+        property_name: &str,
+    ) -> DecodeError {
+        if self.inner.property.is_none() {
+            self.inner.property = Some(PropertyContext {
+                referent,
+                class_name: class_name.to_owned(),
+                property_name: property_name.to_owned(),
+            });
+        }
+
+        self
+    }
+
+    pub(crate) fn kind(&self) -> &DecodeErrorKind {
+        &self.inner.kind
+    }
+
+    /// The description of the error, without its location.
+    pub(crate) fn message(&self) -> String {
+        self.inner.kind.to_string()
+    }
+
     /// 1-based line number in the document where the error occured.
     pub fn line(&self) -> usize {
         self.inner.line
@@ -59,11 +116,55 @@ impl std::error::Error for DecodeError {
     }
 }
 
+impl DomError for DecodeError {
+    fn code(&self) -> ErrorCode {
+        self.inner.kind.code()
+    }
+
+    fn location(&self) -> Option<ErrorLocation> {
+        Some(ErrorLocation::LineColumn {
+            line: self.inner.line,
+            column: self.inner.column,
+        })
+    }
+
+    fn referent(&self) -> Option<Ref> {
+        self.inner.property.as_ref()?.referent
+    }
+
+    fn class_name(&self) -> Option<&str> {
+        match &self.inner.property {
+            Some(property) => Some(&property.class_name),
+            None => self.inner.kind.names().map(|(class_name, _)| class_name),
+        }
+    }
+
+    fn property_name(&self) -> Option<&str> {
+        match &self.inner.property {
+            Some(property) => Some(&property.property_name),
+            None => self
+                .inner
+                .kind
+                .names()
+                .map(|(_, property_name)| property_name),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct DecodeErrorImpl {
     kind: DecodeErrorKind,
     line: usize,
     column: usize,
+    property: Option<PropertyContext>,
+}
+
+/// The property that an error happened while reading or writing.
+#[derive(Debug)]
+struct PropertyContext {
+    referent: Option<Ref>,
+    class_name: String,
+    property_name: String,
 }
 
 #[derive(Debug)]
@@ -74,10 +175,16 @@ pub(crate) enum DecodeErrorKind {
     ParseInt(std::num::ParseIntError),
     DecodeBase64(base64::DecodeError),
     MigrationError(rbx_reflection::MigrationError),
+    RawXml(xml::writer::Error),
+    Codec {
+        type_name: String,
+        error: CodecError,
+    },
 
     // Errors specific to rbx_xml
     WrongDocVersion(String),
     UnexpectedEof,
+    Cancelled,
     UnexpectedXmlEvent(xml::reader::XmlEvent),
     MissingAttribute(&'static str),
     UnknownProperty {
@@ -93,10 +200,24 @@ pub(crate) enum DecodeErrorKind {
         actual_type: VariantType,
         message: String,
     },
+    UnresolvedReference {
+        class_name: String,
+        property_name: String,
+        referent: String,
+    },
+    MissingSharedString {
+        class_name: String,
+        property_name: String,
+        hash: String,
+    },
     InvalidPropertyData {
         property_type: &'static str,
         error: String,
     },
+    LimitExceeded {
+        limit: &'static str,
+        max: usize,
+    },
 }
 
 impl fmt::Display for DecodeErrorKind {
@@ -109,11 +230,18 @@ impl fmt::Display for DecodeErrorKind {
             ParseInt(err) => write!(output, "{}", err),
             DecodeBase64(err) => write!(output, "{}", err),
             MigrationError(err) => write!(output, "{}", err),
+            RawXml(err) => write!(output, "Could not preserve unknown element: {}", err),
+            Codec { type_name, error } => write!(
+                output,
+                "Custom codec could not decode property of type {}: {}",
+                type_name, error
+            ),
 
             WrongDocVersion(version) => {
                 write!(output, "Invalid version '{}', expected version 4", version)
             }
             UnexpectedEof => write!(output, "Unexpected end-of-file"),
+            Cancelled => write!(output, "Decoding was cancelled"),
             UnexpectedXmlEvent(event) => write!(output, "Unexpected XML event {:?}", event),
             MissingAttribute(attribute_name) => {
                 write!(output, "Missing attribute '{}'", attribute_name)
@@ -144,6 +272,24 @@ impl fmt::Display for DecodeErrorKind {
                  When trying to convert, this error occured: {}",
                 class_name, property_name, expected_type, actual_type, message
             ),
+            UnresolvedReference {
+                class_name,
+                property_name,
+                referent,
+            } => write!(
+                output,
+                "Property {}.{} points to referent '{}', which no instance has",
+                class_name, property_name, referent
+            ),
+            MissingSharedString {
+                class_name,
+                property_name,
+                hash,
+            } => write!(
+                output,
+                "Property {}.{} refers to SharedString '{}', which isn't in the document's SharedStrings",
+                class_name, property_name, hash
+            ),
             InvalidPropertyData {
                 property_type,
                 error,
@@ -151,6 +297,68 @@ impl fmt::Display for DecodeErrorKind {
                 output,
                 "Could not decode property of type {property_type} because: {error}"
             ),
+            LimitExceeded { limit, max } => {
+                write!(output, "Document has more than the limit of {} {}", max, limit)
+            }
+        }
+    }
+}
+
+impl DecodeErrorKind {
+    fn code(&self) -> ErrorCode {
+        use self::DecodeErrorKind::*;
+
+        match self {
+            Xml(_) | RawXml(_) => ErrorCode::Xml,
+            ParseFloat(_)
+            | ParseInt(_)
+            | DecodeBase64(_)
+            | InvalidContent(_)
+            | InvalidPropertyData { .. } => ErrorCode::InvalidPropertyValue,
+            MigrationError(_) => ErrorCode::Migration,
+            Codec { .. } => ErrorCode::Codec,
+
+            WrongDocVersion(_) => ErrorCode::UnknownVersion,
+            UnexpectedEof => ErrorCode::UnexpectedEof,
+            Cancelled => ErrorCode::Cancelled,
+            UnexpectedXmlEvent(_) => ErrorCode::UnexpectedElement,
+            MissingAttribute(_) => ErrorCode::MissingAttribute,
+            UnknownProperty { .. } => ErrorCode::UnknownProperty,
+            NameMustBeString(_) | UnsupportedPropertyConversion { .. } => {
+                ErrorCode::PropertyTypeMismatch
+            }
+            UnresolvedReference { .. } => ErrorCode::UnknownReferent,
+            MissingSharedString { .. } => ErrorCode::MissingSharedString,
+            LimitExceeded { .. } => ErrorCode::LimitExceeded,
+        }
+    }
+
+    /// The ClassName and property name that this error names itself.
+    fn names(&self) -> Option<(&str, &str)> {
+        use self::DecodeErrorKind::*;
+
+        match self {
+            UnknownProperty {
+                class_name,
+                property_name,
+            }
+            | UnsupportedPropertyConversion {
+                class_name,
+                property_name,
+                ..
+            }
+            | UnresolvedReference {
+                class_name,
+                property_name,
+                ..
+            }
+            | MissingSharedString {
+                class_name,
+                property_name,
+                ..
+            } => Some((class_name, property_name)),
+
+            _ => None,
         }
     }
 }
@@ -165,6 +373,8 @@ impl std::error::Error for DecodeErrorKind {
             ParseInt(err) => Some(err),
             DecodeBase64(err) => Some(err),
             MigrationError(err) => Some(err),
+            RawXml(err) => Some(err),
+            Codec { error, .. } => Some(error.as_ref()),
 
             _ => None,
         }
@@ -199,7 +409,7 @@ impl From<base64::DecodeError> for DecodeErrorKind {
 #[derive(Debug)]
 pub struct EncodeError {
     // This Box helps reduce the size of EncodeError a lot, which is important.
-    kind: Box<EncodeErrorKind>,
+    inner: Box<EncodeErrorImpl>,
 }
 
 impl EncodeError {
@@ -207,35 +417,140 @@ impl EncodeError {
         kind: EncodeErrorKind,
         _writer: &xml::EventWriter<W>,
     ) -> EncodeError {
+        EncodeError::from(kind)
+    }
+
+    /// Records that this error happened while writing the given property,
+    /// unless it already knows which property it's about.
+    pub(crate) fn with_property(
+        mut self,
+        referent: Option<Ref>,
+        class_name: &str,
+        property_name: &str,
+    ) -> EncodeError {
+        if self.inner.property.is_none() {
+            self.inner.property = Some(PropertyContext {
+                referent,
+                class_name: class_name.to_owned(),
+                property_name: property_name.to_owned(),
+            });
+        }
+
+        self
+    }
+}
+
+impl From<EncodeErrorKind> for EncodeError {
+    fn from(kind: EncodeErrorKind) -> EncodeError {
         EncodeError {
-            kind: Box::new(kind),
+            inner: Box::new(EncodeErrorImpl {
+                kind,
+                property: None,
+            }),
         }
     }
 }
 
+impl From<io::Error> for EncodeError {
+    fn from(error: io::Error) -> EncodeError {
+        EncodeError::from(EncodeErrorKind::Io(error))
+    }
+}
+
 impl fmt::Display for EncodeError {
     fn fmt(&self, output: &mut fmt::Formatter) -> fmt::Result {
-        write!(output, "{}", self.kind)
+        write!(output, "{}", self.inner.kind)
     }
 }
 
 impl std::error::Error for EncodeError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        self.kind.source()
+        self.inner.kind.source()
     }
 }
 
+impl DomError for EncodeError {
+    fn code(&self) -> ErrorCode {
+        use self::EncodeErrorKind::*;
+
+        match &self.inner.kind {
+            Io(_) => ErrorCode::Io,
+            Xml(_) => ErrorCode::Xml,
+            Type(err) => err.code(),
+            UnknownElement(_) => ErrorCode::UnknownElement,
+            Codec { .. } => ErrorCode::Codec,
+
+            UnknownProperty { .. } => ErrorCode::UnknownProperty,
+            UnsupportedPropertyType(_) => ErrorCode::UnsupportedPropertyType,
+            InvalidStreamEvent(_) => ErrorCode::InvalidStreamEvent,
+            ExternalReference { .. } => ErrorCode::ExternalReference,
+            UnsupportedPropertyConversion { .. } => ErrorCode::PropertyTypeMismatch,
+        }
+    }
+
+    fn referent(&self) -> Option<Ref> {
+        self.inner.property.as_ref()?.referent
+    }
+
+    fn class_name(&self) -> Option<&str> {
+        use self::EncodeErrorKind::*;
+
+        match (&self.inner.property, &self.inner.kind) {
+            (Some(property), _) => Some(&property.class_name),
+            (
+                None,
+                UnknownProperty { class_name, .. }
+                | ExternalReference { class_name, .. }
+                | UnsupportedPropertyConversion { class_name, .. },
+            ) => Some(class_name),
+            _ => None,
+        }
+    }
+
+    fn property_name(&self) -> Option<&str> {
+        use self::EncodeErrorKind::*;
+
+        match (&self.inner.property, &self.inner.kind) {
+            (Some(property), _) => Some(&property.property_name),
+            (
+                None,
+                Codec { property_name, .. }
+                | UnknownProperty { property_name, .. }
+                | ExternalReference { property_name, .. }
+                | UnsupportedPropertyConversion { property_name, .. },
+            ) => Some(property_name),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct EncodeErrorImpl {
+    kind: EncodeErrorKind,
+    property: Option<PropertyContext>,
+}
+
 #[derive(Debug)]
 pub(crate) enum EncodeErrorKind {
     Io(io::Error),
     Xml(xml::writer::Error),
     Type(rbx_dom_weak::types::Error),
+    UnknownElement(xml::reader::Error),
+    Codec {
+        property_name: String,
+        error: CodecError,
+    },
 
     UnknownProperty {
         class_name: String,
         property_name: String,
     },
     UnsupportedPropertyType(VariantType),
+    InvalidStreamEvent(&'static str),
+    ExternalReference {
+        class_name: String,
+        property_name: String,
+    },
     UnsupportedPropertyConversion {
         class_name: String,
         property_name: String,
@@ -253,6 +568,15 @@ impl fmt::Display for EncodeErrorKind {
             Io(err) => write!(output, "{}", err),
             Xml(err) => write!(output, "{}", err),
             Type(err) => write!(output, "{}", err),
+            UnknownElement(err) => write!(output, "Unknown element is not valid XML: {}", err),
+            Codec {
+                property_name,
+                error,
+            } => write!(
+                output,
+                "Custom codec could not encode property {}: {}",
+                property_name, error
+            ),
 
             UnknownProperty {
                 class_name,
@@ -265,6 +589,15 @@ impl fmt::Display for EncodeErrorKind {
             UnsupportedPropertyType(ty) => {
                 write!(output, "Properties of type {:?} cannot be encoded yet", ty)
             }
+            InvalidStreamEvent(explain) => write!(output, "Invalid stream event: {}", explain),
+            ExternalReference {
+                class_name,
+                property_name,
+            } => write!(
+                output,
+                "Property {}.{} points to an instance that isn't being serialized",
+                class_name, property_name
+            ),
             UnsupportedPropertyConversion {
                 class_name,
                 property_name,
@@ -289,9 +622,13 @@ impl std::error::Error for EncodeErrorKind {
             Io(err) => Some(err),
             Xml(err) => Some(err),
             Type(err) => Some(err),
+            UnknownElement(err) => Some(err),
+            Codec { error, .. } => Some(error.as_ref()),
 
             UnknownProperty { .. }
             | UnsupportedPropertyType(_)
+            | InvalidStreamEvent(_)
+            | ExternalReference { .. }
             | UnsupportedPropertyConversion { .. } => None,
         }
     }
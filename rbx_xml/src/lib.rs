@@ -105,14 +105,24 @@
 
 #![deny(missing_docs)]
 
+#[cfg(feature = "tokio")]
+mod async_reader;
+mod base64_stream;
+mod codec;
 mod conversion;
 mod core;
 mod deserializer;
 mod deserializer_core;
 mod error;
+mod report;
+mod selector;
 mod serializer;
 mod serializer_core;
+mod stream;
+mod stream_writer;
+mod terrain;
 mod types;
+mod validation;
 
 #[cfg(test)]
 mod test_util;
@@ -123,35 +133,118 @@ use std::io::{Read, Write};
 
 use rbx_dom_weak::{types::Ref, WeakDom};
 
-use crate::{deserializer::decode_internal, serializer::encode_internal};
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    deserializer::decode_internal, serializer::encode_internal, stream::stream_internal,
+    validation::validate_internal,
+};
+
+#[cfg(feature = "tokio")]
+use crate::serializer::Encoder;
 
 pub use crate::{
-    deserializer::{DecodeOptions, DecodePropertyBehavior},
+    codec::{CodecError, Codecs, PropertyCodec},
+    deserializer::{DecodeOptions, DecodeProgress, DecodePropertyBehavior},
     error::{DecodeError, EncodeError},
-    serializer::{EncodeOptions, EncodePropertyBehavior},
+    report::{
+        Annotation, AnnotationContent, AnnotationPosition, DecodeReport, DecodeWarning,
+        EncodeReport, ExternalReference, Skipped, UnknownElement, UnknownElementKind,
+        UnresolvedReference,
+    },
+    selector::{Selector, SelectorCandidate},
+    serializer::{
+        AttributeOrder, CompatibilityTarget, ControlCharacters, EncodeOptions,
+        EncodePropertyBehavior, ExternalReferences, Indentation, NewlineStyle, ReferentStyle,
+        ScriptSourceStyle,
+    },
+    stream::{StreamHandler, StreamValue},
+    stream_writer::StreamWriter,
+    validation::{Diagnostic, DiagnosticCode, Severity, ValidationReport},
 };
 
+pub use rbx_dom_weak::types::{DomError, ErrorCode, ErrorLocation};
+
 /// Decodes an XML-format model or place from something that implements the
 /// `std::io::Read` trait.
 pub fn from_reader<R: Read>(reader: R, options: DecodeOptions) -> Result<WeakDom, DecodeError> {
-    decode_internal(reader, options)
+    decode_internal(reader, options).map(|(dom, _)| dom)
 }
 
 /// Decodes an XML-format model or place from something that implements the
 /// `std::io::Read` trait using the default decoder options.
 pub fn from_reader_default<R: Read>(reader: R) -> Result<WeakDom, DecodeError> {
-    decode_internal(reader, DecodeOptions::default())
+    from_reader(reader, DecodeOptions::default())
+}
+
+/// Decodes an XML-format model or place from something that implements the
+/// `std::io::Read` trait, also returning a [`DecodeReport`] describing what
+/// couldn't be represented in the `WeakDom`.
+pub fn from_reader_with_report<R: Read>(
+    reader: R,
+    options: DecodeOptions,
+) -> Result<(WeakDom, DecodeReport), DecodeError> {
+    decode_internal(reader, options)
 }
 
 /// Decodes an XML-format model or place from a string.
 pub fn from_str<S: AsRef<str>>(reader: S, options: DecodeOptions) -> Result<WeakDom, DecodeError> {
-    decode_internal(reader.as_ref().as_bytes(), options)
+    from_reader(reader.as_ref().as_bytes(), options)
 }
 
 /// Decodes an XML-format model or place from a string using the default decoder
 /// options.
 pub fn from_str_default<S: AsRef<str>>(reader: S) -> Result<WeakDom, DecodeError> {
-    decode_internal(reader.as_ref().as_bytes(), DecodeOptions::default())
+    from_reader(reader.as_ref().as_bytes(), DecodeOptions::default())
+}
+
+/// Decodes an XML-format model or place from bytes that are already in memory,
+/// like a file handed over from JavaScript.
+pub fn from_slice(input: &[u8], options: DecodeOptions) -> Result<WeakDom, DecodeError> {
+    from_reader(input, options)
+}
+
+/// Decodes an XML-format model or place from a string, also returning a
+/// [`DecodeReport`] describing what couldn't be represented in the `WeakDom`.
+pub fn from_str_with_report<S: AsRef<str>>(
+    reader: S,
+    options: DecodeOptions,
+) -> Result<(WeakDom, DecodeReport), DecodeError> {
+    decode_internal(reader.as_ref().as_bytes(), options)
+}
+
+/// Reads an XML-format model or place from something that implements the
+/// `std::io::Read` trait, passing its instances and properties to `handler` as
+/// they're read instead of building a `WeakDom`.
+///
+/// Memory use doesn't grow with the size of the document, which makes this
+/// useful for scanning huge places, like to pull out the source of every
+/// script. Unknown elements kept by
+/// [`DecodeOptions::preserve_unknown_elements`] are skipped instead.
+pub fn stream_from_reader<R: Read, H: StreamHandler>(
+    reader: R,
+    options: DecodeOptions,
+    handler: H,
+) -> Result<(), DecodeError> {
+    stream_internal(reader, options, handler)
+}
+
+/// Checks an XML-format model or place from something that implements the
+/// `std::io::Read` trait against the structure rbx_xml expects and the
+/// reflection database, without building a `WeakDom`.
+///
+/// Instead of stopping at the first problem, as much of the document as
+/// possible is checked, and every problem found is returned as a
+/// [`Diagnostic`]. Only malformed XML stops validation early.
+pub fn validate_reader<R: Read>(reader: R) -> ValidationReport {
+    validate_internal(reader)
+}
+
+/// Checks an XML-format model or place from a string. See
+/// [`validate_reader`] for details.
+pub fn validate_str<S: AsRef<str>>(reader: S) -> ValidationReport {
+    validate_internal(reader.as_ref().as_bytes())
 }
 
 /// Serializes a subset of the given tree to an XML format model or place,
@@ -162,6 +255,19 @@ pub fn to_writer<W: Write>(
     ids: &[Ref],
     options: EncodeOptions,
 ) -> Result<(), EncodeError> {
+    encode_internal(writer, tree, ids, options).map(|_| ())
+}
+
+/// Serializes a subset of the given tree to an XML format model or place,
+/// writing to something that implements the `std::io::Write` trait, also
+/// returning an [`EncodeReport`] describing how Ref properties pointing outside
+/// of the serialized instances were handled.
+pub fn to_writer_with_report<W: Write>(
+    writer: W,
+    tree: &WeakDom,
+    ids: &[Ref],
+    options: EncodeOptions,
+) -> Result<EncodeReport, EncodeError> {
     encode_internal(writer, tree, ids, options)
 }
 
@@ -173,5 +279,65 @@ pub fn to_writer_default<W: Write>(
     tree: &WeakDom,
     ids: &[Ref],
 ) -> Result<(), EncodeError> {
-    encode_internal(writer, tree, ids, EncodeOptions::default())
+    encode_internal(writer, tree, ids, EncodeOptions::default()).map(|_| ())
+}
+
+/// Serializes a subset of the given tree to an XML format model or place,
+/// returning the bytes.
+pub fn to_vec(tree: &WeakDom, ids: &[Ref], options: EncodeOptions) -> Result<Vec<u8>, EncodeError> {
+    let mut output = Vec::new();
+    encode_internal(&mut output, tree, ids, options)?;
+    Ok(output)
+}
+
+/// Decodes an XML-format model or place from something that implements
+/// tokio's `AsyncRead` trait.
+///
+/// The XML parser can't be suspended partway through a document, so it runs
+/// on tokio's blocking thread pool while the document is read on the calling
+/// task and handed to it as it arrives. Only the last few reads are held in
+/// memory at once, not the whole document. This must be called from within a
+/// tokio runtime.
+#[cfg(feature = "tokio")]
+pub async fn from_reader_async<R: AsyncRead + Unpin>(
+    reader: R,
+    options: DecodeOptions,
+) -> Result<WeakDom, DecodeError> {
+    let (sender, channel_reader) = async_reader::ChannelReader::new();
+    let decode = tokio::task::spawn_blocking(move || from_reader(channel_reader, options));
+
+    async_reader::pump(reader, sender).await;
+
+    match decode.await {
+        Ok(result) => result,
+        Err(err) => std::panic::resume_unwind(err.into_panic()),
+    }
+}
+
+/// Serializes a subset of the given tree to an XML format model or place,
+/// writing to something that implements tokio's `AsyncWrite` trait.
+///
+/// The document is encoded one instance at a time, and what's been encoded
+/// is written to `writer` after each one, so the whole document is never
+/// held in memory at once.
+#[cfg(feature = "tokio")]
+pub async fn to_writer_async<W: AsyncWrite + Unpin>(
+    mut writer: W,
+    tree: &WeakDom,
+    ids: &[Ref],
+    options: EncodeOptions,
+) -> Result<(), EncodeError> {
+    let mut encoder = Encoder::new(Vec::new(), tree, ids, options)?;
+
+    while encoder.step()? {
+        let output = encoder.output_mut();
+        writer.write_all(output).await?;
+        output.clear();
+    }
+
+    let (_, output) = encoder.finish()?;
+    writer.write_all(&output).await?;
+    writer.flush().await?;
+
+    Ok(())
 }
@@ -0,0 +1,349 @@
+use rbx_dom_weak::types::Ref;
+
+use crate::error::DecodeError;
+
+/// Information gathered while decoding a document that doesn't fit in the
+/// resulting `WeakDom`, returned by
+/// [`from_reader_with_report`][crate::from_reader_with_report] and
+/// [`from_str_with_report`][crate::from_str_with_report].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodeReport {
+    pub(crate) unknown_elements: Vec<UnknownElement>,
+    pub(crate) warnings: Vec<DecodeWarning>,
+    pub(crate) unresolved_references: Vec<UnresolvedReference>,
+    pub(crate) annotations: Vec<Annotation>,
+}
+
+impl DecodeReport {
+    /// The elements that rbx_xml couldn't decode and kept as raw XML, in the
+    /// order they appear in the document. These are only recorded when
+    /// [`DecodeOptions::preserve_unknown_elements`][crate::DecodeOptions::preserve_unknown_elements]
+    /// is enabled.
+    pub fn unknown_elements(&self) -> &[UnknownElement] {
+        &self.unknown_elements
+    }
+
+    /// Consumes the report, returning its unknown elements so that they can
+    /// be passed to
+    /// [`EncodeOptions::unknown_elements`][crate::EncodeOptions::unknown_elements].
+    pub fn into_unknown_elements(self) -> Vec<UnknownElement> {
+        self.unknown_elements
+    }
+
+    /// The problems in the document that were worked around, in the order
+    /// they were found. These are only recorded when
+    /// [`DecodeOptions::recover`][crate::DecodeOptions::recover] is enabled.
+    pub fn warnings(&self) -> &[DecodeWarning] {
+        &self.warnings
+    }
+
+    /// Every Ref property that pointed to a referent that no instance in the
+    /// document has, which was handled according to
+    /// [`DecodeOptions::external_references`][crate::DecodeOptions::external_references].
+    pub fn unresolved_references(&self) -> &[UnresolvedReference] {
+        &self.unresolved_references
+    }
+
+    /// The comments and processing instructions in the document, in the
+    /// order they appear. These are only recorded when
+    /// [`DecodeOptions::preserve_comments`][crate::DecodeOptions::preserve_comments]
+    /// is enabled, and can be passed to
+    /// [`EncodeOptions::annotations`][crate::EncodeOptions::annotations].
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+}
+
+/// A Ref property in a decoded document that pointed to a referent that no
+/// instance in the document has.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedReference {
+    instance: Ref,
+    property_name: String,
+    referent: String,
+}
+
+impl UnresolvedReference {
+    pub(crate) fn new(instance: Ref, property_name: String, referent: String) -> Self {
+        UnresolvedReference {
+            instance,
+            property_name,
+            referent,
+        }
+    }
+
+    /// The instance that has the property.
+    pub fn instance(&self) -> Ref {
+        self.instance
+    }
+
+    /// The name of the property.
+    pub fn property_name(&self) -> &str {
+        &self.property_name
+    }
+
+    /// The referent the property pointed to.
+    pub fn referent(&self) -> &str {
+        &self.referent
+    }
+}
+
+/// Information gathered while encoding a document, returned by
+/// [`to_writer_with_report`][crate::to_writer_with_report].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EncodeReport {
+    pub(crate) external_references: Vec<ExternalReference>,
+}
+
+impl EncodeReport {
+    /// Every Ref property that pointed to an instance that wasn't serialized,
+    /// which was handled according to
+    /// [`EncodeOptions::external_references`][crate::EncodeOptions::external_references].
+    pub fn external_references(&self) -> &[ExternalReference] {
+        &self.external_references
+    }
+}
+
+/// A Ref property that pointed to an instance that wasn't being serialized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalReference {
+    instance: Ref,
+    property_name: String,
+    target: Ref,
+}
+
+impl ExternalReference {
+    pub(crate) fn new(instance: Ref, property_name: String, target: Ref) -> Self {
+        ExternalReference {
+            instance,
+            property_name,
+            target,
+        }
+    }
+
+    /// The instance that has the property.
+    pub fn instance(&self) -> Ref {
+        self.instance
+    }
+
+    /// The name of the property.
+    pub fn property_name(&self) -> &str {
+        &self.property_name
+    }
+
+    /// The instance the property pointed to.
+    pub fn target(&self) -> Ref {
+        self.target
+    }
+}
+
+/// A problem in a document that was decoded in recovery mode, and the part of
+/// the document that was skipped because of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeWarning {
+    skipped: Skipped,
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+impl DecodeWarning {
+    pub(crate) fn new(skipped: Skipped, error: &DecodeError) -> Self {
+        DecodeWarning {
+            skipped,
+            line: error.line(),
+            column: error.column(),
+            message: error.message(),
+        }
+    }
+
+    /// What was left out of the decoded `WeakDom`.
+    pub fn skipped(&self) -> &Skipped {
+        &self.skipped
+    }
+
+    /// 1-based line number in the document where the problem was found.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// 1-based column number in the document where the problem was found.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// A description of the problem, which is the message of the error that
+    /// decoding would have failed with.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// The part of a document that was skipped because of a [`DecodeWarning`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Skipped {
+    /// A property that couldn't be decoded. The instance is kept without it.
+    Property {
+        /// The ClassName of the instance the property belongs to.
+        class_name: String,
+
+        /// The name of the property as it appears in the document.
+        property_name: String,
+    },
+
+    /// An instance that couldn't be decoded, which is left out along with
+    /// all of its descendants.
+    Instance {
+        /// The ClassName of the instance, if it had one.
+        class_name: Option<String>,
+    },
+
+    /// An element that rbx_xml didn't expect where it was.
+    Element,
+
+    /// Everything after the problem, because the document either ended early
+    /// or isn't well-formed XML past that point. Instances read up to there
+    /// are kept, but the instance being read loses its properties.
+    Remainder,
+}
+
+/// An element of an instance that rbx_xml doesn't understand, kept as raw XML
+/// so that it can be written back out unchanged.
+///
+/// This is usually a property that is newer than rbx_xml's reflection
+/// database, or that has a value type rbx_xml can't decode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownElement {
+    pub(crate) referent: Ref,
+    pub(crate) kind: UnknownElementKind,
+    pub(crate) xml: String,
+}
+
+impl UnknownElement {
+    /// Creates an `UnknownElement` belonging to the instance `referent` from
+    /// the text of a single XML element.
+    pub fn new(referent: Ref, kind: UnknownElementKind, xml: String) -> Self {
+        UnknownElement {
+            referent,
+            kind,
+            xml,
+        }
+    }
+
+    /// The instance that this element belongs to.
+    pub fn referent(&self) -> Ref {
+        self.referent
+    }
+
+    /// Where in the instance the element appeared.
+    pub fn kind(&self) -> UnknownElementKind {
+        self.kind
+    }
+
+    /// The element's XML, including its own start and end tags.
+    pub fn xml(&self) -> &str {
+        &self.xml
+    }
+}
+
+/// Where an [`UnknownElement`] appears in its instance's `Item` element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum UnknownElementKind {
+    /// A property element inside of `Properties`.
+    Property,
+
+    /// An element directly inside of `Item` that isn't `Properties` or a
+    /// child `Item`.
+    Item,
+}
+
+/// A comment or processing instruction from a document, kept so that it can be
+/// written back out in the same place.
+///
+/// Annotations can only be kept in the places described by
+/// [`AnnotationPosition`]. Ones found anywhere else, like inside of a
+/// property's value, are moved to the next of those places.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    pub(crate) position: AnnotationPosition,
+    pub(crate) content: AnnotationContent,
+}
+
+impl Annotation {
+    /// Creates an `Annotation` with the given content at `position`.
+    pub fn new(position: AnnotationPosition, content: AnnotationContent) -> Self {
+        Annotation { position, content }
+    }
+
+    /// Where in the document the annotation appears.
+    pub fn position(&self) -> &AnnotationPosition {
+        &self.position
+    }
+
+    /// What the annotation is.
+    pub fn content(&self) -> &AnnotationContent {
+        &self.content
+    }
+}
+
+/// The contents of an [`Annotation`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AnnotationContent {
+    /// A comment, containing the text between `<!--` and `-->`.
+    Comment(String),
+
+    /// A processing instruction, like `<?name data?>`.
+    ProcessingInstruction {
+        /// The target of the processing instruction.
+        name: String,
+
+        /// Everything after the target, if there's anything.
+        data: Option<String>,
+    },
+}
+
+/// Where an [`Annotation`] appears in a document.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AnnotationPosition {
+    /// Before the document's `roblox` element.
+    Prolog,
+
+    /// Before the `Item` element of an instance.
+    BeforeInstance(Ref),
+
+    /// Before one of an instance's properties, which is named as it is in the
+    /// `WeakDom`.
+    BeforeProperty {
+        /// The instance that has the property.
+        instance: Ref,
+
+        /// The name of the property.
+        property_name: String,
+    },
+
+    /// At the end of an instance's `Properties` element.
+    EndOfProperties(Ref),
+
+    /// At the end of an instance's `Item` element, after its children.
+    EndOfInstance(Ref),
+
+    /// At the end of the document's `roblox` element.
+    EndOfDocument,
+}
+
+impl AnnotationPosition {
+    /// The instance the annotation is attached to, if it's attached to one.
+    pub fn instance(&self) -> Option<Ref> {
+        match self {
+            AnnotationPosition::BeforeInstance(instance)
+            | AnnotationPosition::BeforeProperty { instance, .. }
+            | AnnotationPosition::EndOfProperties(instance)
+            | AnnotationPosition::EndOfInstance(instance) => Some(*instance),
+            AnnotationPosition::Prolog | AnnotationPosition::EndOfDocument => None,
+        }
+    }
+}
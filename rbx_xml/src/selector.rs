@@ -0,0 +1,153 @@
+use std::{fmt, sync::Arc};
+
+/// A predicate deciding which instances are selected.
+type SelectorPredicate = Arc<dyn Fn(&SelectorCandidate) -> bool + Send + Sync>;
+
+/// Chooses which instances of a document are decoded, given to
+/// [`DecodeOptions::select`][crate::DecodeOptions::select].
+///
+/// Selected instances are decoded along with all of their descendants, and
+/// become children of the decoded `WeakDom`'s root. Nothing else in the
+/// document is kept, and as much of it as possible is skipped without being
+/// decoded: a class selector never looks at the properties of the instances it
+/// passes over, and a path selector skips every subtree that isn't on its
+/// path. Ref properties pointing to instances that weren't selected are
+/// handled like any other Refs to referents that aren't in the document, as
+/// described by
+/// [`DecodeOptions::external_references`][crate::DecodeOptions::external_references].
+///
+/// Descendants of selected instances aren't checked against the selector,
+/// since they're already being decoded.
+#[derive(Clone)]
+pub struct Selector {
+    kind: SelectorKind,
+}
+
+#[derive(Clone)]
+enum SelectorKind {
+    Class(String),
+    Path(Vec<String>),
+    Predicate(SelectorPredicate),
+}
+
+/// What the decoder has to do with an instance to find out whether it's
+/// selected, which depends on as little of the instance as possible.
+pub(crate) enum SelectorPlan {
+    /// The instance is selected.
+    Decode,
+
+    /// The instance isn't selected, but its descendants might be.
+    Search,
+
+    /// The instance isn't selected, but its descendants might be depending on
+    /// its name.
+    ReadName,
+
+    /// Whether the instance is selected depends on its name, so it needs to
+    /// be decoded before we know.
+    TryDecode,
+}
+
+impl Selector {
+    /// Selects every instance with the given ClassName.
+    pub fn class<S: Into<String>>(class_name: S) -> Self {
+        Selector {
+            kind: SelectorKind::Class(class_name.into()),
+        }
+    }
+
+    /// Selects the instances at `path`, which is the Names of an instance and
+    /// its ancestors separated by `/`, starting from an instance at the top of
+    /// the document. In a place, `"Workspace/Map"` selects every child of
+    /// Workspace named Map.
+    pub fn path(path: &str) -> Self {
+        Selector {
+            kind: SelectorKind::Path(path.split('/').map(str::to_owned).collect()),
+        }
+    }
+
+    /// Selects the instances accepted by `predicate`. Every instance that
+    /// isn't inside a selected instance has to be decoded to be given to
+    /// `predicate`, so this is slower than the other selectors.
+    pub fn predicate<F: Fn(&SelectorCandidate) -> bool + Send + Sync + 'static>(
+        predicate: F,
+    ) -> Self {
+        Selector {
+            kind: SelectorKind::Predicate(Arc::new(predicate)),
+        }
+    }
+
+    /// Decides what to do with an instance with the given ClassName, whose
+    /// ancestors have `depth` Names.
+    pub(crate) fn plan(&self, class_name: &str, depth: usize) -> SelectorPlan {
+        match &self.kind {
+            SelectorKind::Class(selected) if selected == class_name => SelectorPlan::Decode,
+            SelectorKind::Class(_) => SelectorPlan::Search,
+            SelectorKind::Path(path) if depth + 1 < path.len() => SelectorPlan::ReadName,
+            SelectorKind::Path(_) | SelectorKind::Predicate(_) => SelectorPlan::TryDecode,
+        }
+    }
+
+    /// Tells whether the instance described by `candidate` is selected.
+    pub(crate) fn matches(&self, candidate: &SelectorCandidate) -> bool {
+        match &self.kind {
+            SelectorKind::Class(class_name) => candidate.class_name == class_name,
+            SelectorKind::Path(path) => path.as_slice() == candidate.path,
+            SelectorKind::Predicate(predicate) => predicate(candidate),
+        }
+    }
+
+    /// Tells whether any descendant of the instance at `path` could be
+    /// selected.
+    pub(crate) fn could_match_below(&self, path: &[String]) -> bool {
+        match &self.kind {
+            SelectorKind::Path(selected) => {
+                path.len() < selected.len() && selected.starts_with(path)
+            }
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Debug for Selector {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            SelectorKind::Class(class_name) => {
+                formatter.debug_tuple("Class").field(class_name).finish()
+            }
+            SelectorKind::Path(path) => formatter.debug_tuple("Path").field(path).finish(),
+            SelectorKind::Predicate(_) => formatter.write_str("Predicate(<predicate>)"),
+        }
+    }
+}
+
+/// An instance being considered by a [`Selector::predicate`].
+#[derive(Debug)]
+pub struct SelectorCandidate<'a> {
+    pub(crate) class_name: &'a str,
+    pub(crate) path: &'a [String],
+    pub(crate) referent: Option<&'a str>,
+}
+
+impl SelectorCandidate<'_> {
+    /// The instance's ClassName.
+    pub fn class_name(&self) -> &str {
+        self.class_name
+    }
+
+    /// The instance's Name.
+    pub fn name(&self) -> &str {
+        self.path.last().map(String::as_str).unwrap_or_default()
+    }
+
+    /// The Names of the instance's ancestors and the instance itself,
+    /// starting from the top of the document.
+    pub fn path(&self) -> &[String] {
+        self.path
+    }
+
+    /// The instance's referent in the document, if it has one.
+    pub fn referent(&self) -> Option<&str> {
+        self.referent
+    }
+}
@@ -1,18 +1,28 @@
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
     io::Write,
+    sync::Arc,
 };
 
 use rbx_dom_weak::{
     types::{Ref, SharedString, SharedStringHash, Variant, VariantType},
-    WeakDom,
+    Instance, WeakDom,
 };
-use rbx_reflection::DataType;
+use rbx_reflection::{DataType, ReflectionDatabase};
 
 use crate::{
+    codec::{write_with_codec, Codecs},
     conversion::ConvertVariant,
-    core::find_serialized_property_descriptor,
+    core::{
+        find_exact_property_descriptor, find_legacy_property, find_serialized_property_descriptor,
+        is_default_value, is_script_source,
+    },
     error::{EncodeError as NewEncodeError, EncodeErrorKind},
+    report::{
+        Annotation, AnnotationPosition, EncodeReport, ExternalReference, UnknownElement,
+        UnknownElementKind,
+    },
     types::write_value_xml,
 };
 
@@ -23,22 +33,145 @@ pub fn encode_internal<W: Write>(
     tree: &WeakDom,
     ids: &[Ref],
     options: EncodeOptions,
-) -> Result<(), NewEncodeError> {
-    let mut writer = XmlEventWriter::from_output(output);
-    let mut state = EmitState::new(options);
+) -> Result<EncodeReport, NewEncodeError> {
+    let mut encoder = Encoder::new(output, tree, ids, options)?;
+    while encoder.step()? {}
+    encoder.finish().map(|(report, _)| report)
+}
+
+/// What's left to do for an instance being serialized by an [`Encoder`].
+enum Step {
+    /// Write the instance and its properties, then its children.
+    Start(Ref),
+
+    /// Close the instance after its children have been written, along with
+    /// the annotations that go after them.
+    End(Vec<Annotation>),
+}
+
+/// Serializes a tree one instance at a time, so that whatever has been
+/// written can be taken out of the output between instances.
+pub(crate) struct Encoder<'a, W: Write> {
+    writer: XmlEventWriter<W>,
+    state: EmitState,
+    tree: &'a WeakDom,
+    document_annotations: Vec<Annotation>,
+    property_buffer: Vec<(&'a String, &'a Variant)>,
+
+    /// The steps left to take, with the next one last.
+    steps: Vec<Step>,
+
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
 
-    writer.write(XmlWriteEvent::start_element("roblox").attr("version", "4"))?;
+impl<'a, W: Write> Encoder<'a, W> {
+    /// Starts serializing the instances `ids` from `tree` to `output`,
+    /// writing everything that goes before the first instance.
+    pub fn new(
+        output: W,
+        tree: &'a WeakDom,
+        ids: &[Ref],
+        options: EncodeOptions,
+    ) -> Result<Self, NewEncodeError> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "rbx_xml::serialize",
+            instances = tracing::field::Empty,
+            property_values = tracing::field::Empty,
+        );
+        #[cfg(feature = "tracing")]
+        let _entered = span.clone().entered();
 
-    let mut property_buffer = Vec::new();
-    for id in ids {
-        serialize_instance(&mut writer, &mut state, tree, *id, &mut property_buffer)?;
+        let mut writer = XmlEventWriter::from_output(output, &options);
+        let mut state = EmitState::new(options);
+        state.collect_included(tree, ids);
+
+        let mut document_annotations = state.annotations.remove(&Ref::none()).unwrap_or_default();
+        write_annotations(&mut writer, &mut document_annotations, |position| {
+            *position == AnnotationPosition::Prolog
+        })?;
+
+        writer.write(XmlWriteEvent::start_element("roblox").attr("version", "4"))?;
+
+        Ok(Encoder {
+            writer,
+            state,
+            tree,
+            document_annotations,
+            property_buffer: Vec::new(),
+            steps: ids.iter().rev().map(|&id| Step::Start(id)).collect(),
+            #[cfg(feature = "tracing")]
+            span,
+        })
     }
 
-    serialize_shared_strings(&mut writer, &mut state)?;
+    /// Writes the start of the next instance, or the end of the last one
+    /// whose children have all been written. Returns whether there's
+    /// anything left to write before [`finish`](Self::finish).
+    pub fn step(&mut self) -> Result<bool, NewEncodeError> {
+        #[cfg(feature = "tracing")]
+        let _entered = self.span.clone().entered();
 
-    writer.write(XmlWriteEvent::end_element())?;
+        match self.steps.pop() {
+            Some(Step::Start(id)) => {
+                if let Some((instance, annotations)) = start_instance(
+                    &mut self.writer,
+                    &mut self.state,
+                    self.tree,
+                    id,
+                    &mut self.property_buffer,
+                )? {
+                    self.steps.push(Step::End(annotations));
+                    self.steps
+                        .extend(instance.children().iter().rev().map(|&id| Step::Start(id)));
+                }
+            }
+            Some(Step::End(mut annotations)) => {
+                write_annotations(&mut self.writer, &mut annotations, |_| true)?;
+                self.writer.write(XmlWriteEvent::end_element())?;
+            }
+            None => {}
+        }
 
-    Ok(())
+        Ok(!self.steps.is_empty())
+    }
+
+    /// The output that has been written so far.
+    #[cfg(feature = "tokio")]
+    pub fn output_mut(&mut self) -> &mut W {
+        self.writer.inner_mut()
+    }
+
+    /// Writes everything that goes after the last instance, returning the
+    /// report of how the document was serialized along with the output.
+    pub fn finish(mut self) -> Result<(EncodeReport, W), NewEncodeError> {
+        #[cfg(feature = "tracing")]
+        let _entered = self.span.clone().entered();
+
+        while self.step()? {}
+
+        serialize_shared_strings(&mut self.writer, &mut self.state)?;
+
+        write_annotations(&mut self.writer, &mut self.document_annotations, |_| true)?;
+
+        self.writer.write(XmlWriteEvent::end_element())?;
+
+        log::debug!(
+            "Encoded {} instances and {} property values",
+            self.state.included.len(),
+            self.state.property_values
+        );
+
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("instances", self.state.included.len());
+            self.span
+                .record("property_values", self.state.property_values);
+        }
+
+        Ok((self.state.report, self.writer.into_inner()))
+    }
 }
 
 /// Describes the strategy that rbx_xml should use when serializing properties.
@@ -71,10 +204,162 @@ pub enum EncodePropertyBehavior {
     NoReflection,
 }
 
+/// How elements are indented in serialized XML.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Indentation {
+    /// Writes the whole document on one line.
+    None,
+
+    /// Indents each level with the given number of spaces. Two spaces is the
+    /// default.
+    Spaces(usize),
+
+    /// Indents each level with one tab, like Roblox Studio does.
+    Tabs,
+}
+
+/// The line endings used in serialized XML when it's indented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NewlineStyle {
+    /// Unix-style line endings, `\n`. This is the default.
+    Lf,
+
+    /// Windows-style line endings, `\r\n`, like Roblox Studio uses.
+    CrLf,
+}
+
+/// The order that the attributes of each element are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AttributeOrder {
+    /// Writes attributes in the order rbx_xml produces them, which matches
+    /// Roblox Studio for the elements rbx_xml knows about. This is the
+    /// default.
+    Default,
+
+    /// Sorts the attributes of every element by name.
+    Alphabetical,
+}
+
+/// How the sources of scripts are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ScriptSourceStyle {
+    /// Writes sources like any other string: as escaped characters, or as
+    /// CDATA if they start or end with whitespace. This is the default.
+    Auto,
+
+    /// Always writes sources as CDATA in `ProtectedString` elements, like
+    /// Roblox Studio does. Sources that contain `<`, `>`, or `&` stay
+    /// readable this way.
+    Cdata,
+}
+
+/// What happens to control characters in strings, like `U+0001`, which XML
+/// documents can't contain. Tabs and line endings aren't affected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ControlCharacters {
+    /// Control characters are written as they are. This is the default.
+    Keep,
+
+    /// Control characters are written as character references, like `&#1;`.
+    /// Some tools accept these even though they aren't allowed by XML 1.0,
+    /// but rbx_xml doesn't read them back.
+    Escape,
+
+    /// Control characters are left out.
+    Remove,
+}
+
+/// Which versions of Roblox Studio and other tools serialized documents need
+/// to be readable by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CompatibilityTarget {
+    /// Writes properties in their newest forms, like current versions of
+    /// Roblox Studio do. This is the default.
+    Modern,
+
+    /// Writes properties in forms that older versions of Roblox Studio and
+    /// older tools understand. Properties that replaced older ones, like
+    /// `FontFace`, are written as the property they replaced, like `Font`, when
+    /// their value can be represented by it. Attributes are left out.
+    Legacy,
+}
+
+/// What happens to Ref properties that point to instances that aren't part of
+/// a document.
+///
+/// When encoding, these are Refs to instances that aren't being serialized.
+/// When decoding, these are Refs to referents that no instance in the document
+/// has, which usually come from a model that was assembled from fragments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ExternalReferences {
+    /// The properties are set to null. This is the default.
+    Null,
+
+    /// The properties are left out.
+    Drop,
+
+    /// The properties cause an error.
+    Error,
+}
+
+/// How the referents that identify instances in a document are made.
+///
+/// Every style writes the same referents when the same `WeakDom` is
+/// serialized twice with the same options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ReferentStyle {
+    /// Numbers counting up from 0, in the order the instances are first
+    /// written or pointed to. This is the default.
+    Sequential,
+
+    /// The same numbers as [`ReferentStyle::Sequential`], written like
+    /// `RBX0000000001` to resemble the referents Roblox Studio writes.
+    Prefixed,
+
+    /// Derived from each instance's `UniqueId`, or from a hash of its path
+    /// from the serialized instances when it doesn't have one. Instances keep
+    /// their referents when other instances are added or removed, so diffs
+    /// between documents only mention the instances that changed.
+    Stable,
+}
+
+/// A filter deciding which instances are serialized.
+type InstanceFilter = Arc<dyn Fn(&Instance) -> bool + Send + Sync>;
+
+/// A filter deciding which properties are serialized, given a ClassName and a
+/// property name.
+type PropertyFilter = Arc<dyn Fn(&str, &str) -> bool + Send + Sync>;
+
 /// Options available for serializing an XML-format model or place.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct EncodeOptions {
     property_behavior: EncodePropertyBehavior,
+    unknown_elements: Vec<UnknownElement>,
+    annotations: Vec<Annotation>,
+    pub(crate) indentation: Indentation,
+    pub(crate) newline_style: NewlineStyle,
+    pub(crate) attribute_order: AttributeOrder,
+    pub(crate) xml_declaration: bool,
+    script_source_style: ScriptSourceStyle,
+    pub(crate) preserve_line_endings: bool,
+    pub(crate) control_characters: ControlCharacters,
+    compatibility_target: CompatibilityTarget,
+    map_property_names: bool,
+    include_default_properties: bool,
+    pub(crate) codecs: Codecs,
+    instance_filter: Option<InstanceFilter>,
+    property_filter: Option<PropertyFilter>,
+    external_references: ExternalReferences,
+    referent_style: ReferentStyle,
+    pub(crate) database: &'static ReflectionDatabase<'static>,
 }
 
 impl EncodeOptions {
@@ -83,6 +368,24 @@ impl EncodeOptions {
     pub fn new() -> Self {
         EncodeOptions {
             property_behavior: EncodePropertyBehavior::IgnoreUnknown,
+            unknown_elements: Vec::new(),
+            annotations: Vec::new(),
+            indentation: Indentation::Spaces(2),
+            newline_style: NewlineStyle::Lf,
+            attribute_order: AttributeOrder::Default,
+            xml_declaration: false,
+            script_source_style: ScriptSourceStyle::Auto,
+            preserve_line_endings: false,
+            control_characters: ControlCharacters::Keep,
+            compatibility_target: CompatibilityTarget::Modern,
+            map_property_names: true,
+            include_default_properties: true,
+            codecs: Codecs::new(),
+            instance_filter: None,
+            property_filter: None,
+            external_references: ExternalReferences::Null,
+            referent_style: ReferentStyle::Sequential,
+            database: rbx_reflection_database::global(),
         }
     }
 
@@ -90,12 +393,247 @@ impl EncodeOptions {
     /// ones.
     #[inline]
     pub fn property_behavior(self, property_behavior: EncodePropertyBehavior) -> Self {
-        EncodeOptions { property_behavior }
+        EncodeOptions {
+            property_behavior,
+            ..self
+        }
+    }
+
+    /// Writes `elements`, which were kept by
+    /// [`DecodeOptions::preserve_unknown_elements`][crate::DecodeOptions::preserve_unknown_elements],
+    /// back into the instances they belong to. Elements belonging to
+    /// instances that aren't serialized are ignored.
+    pub fn unknown_elements(self, elements: Vec<UnknownElement>) -> Self {
+        EncodeOptions {
+            unknown_elements: elements,
+            ..self
+        }
+    }
+
+    /// Writes `annotations`, which were kept by
+    /// [`DecodeOptions::preserve_comments`][crate::DecodeOptions::preserve_comments],
+    /// back into the places they came from. Annotations attached to instances
+    /// that aren't serialized are ignored, and annotations before properties
+    /// that aren't written are moved to the end of their instance's
+    /// properties.
+    pub fn annotations(self, annotations: Vec<Annotation>) -> Self {
+        EncodeOptions {
+            annotations,
+            ..self
+        }
+    }
+
+    /// Determines how elements are indented. Defaults to two spaces.
+    pub fn indentation(self, indentation: Indentation) -> Self {
+        EncodeOptions {
+            indentation,
+            ..self
+        }
+    }
+
+    /// Determines which line endings are used between indented elements.
+    /// Defaults to [`NewlineStyle::Lf`].
+    pub fn newline_style(self, newline_style: NewlineStyle) -> Self {
+        EncodeOptions {
+            newline_style,
+            ..self
+        }
+    }
+
+    /// Determines the order that each element's attributes are written in.
+    /// Defaults to [`AttributeOrder::Default`].
+    pub fn attribute_order(self, attribute_order: AttributeOrder) -> Self {
+        EncodeOptions {
+            attribute_order,
+            ..self
+        }
+    }
+
+    /// Determines whether the document starts with an XML declaration,
+    /// `<?xml version="1.0" encoding="UTF-8"?>`. Defaults to `false`.
+    pub fn xml_declaration(self, enabled: bool) -> Self {
+        EncodeOptions {
+            xml_declaration: enabled,
+            ..self
+        }
+    }
+
+    /// Determines how the `Source` properties of scripts are written. Defaults
+    /// to [`ScriptSourceStyle::Auto`].
+    pub fn script_source_style(self, script_source_style: ScriptSourceStyle) -> Self {
+        EncodeOptions {
+            script_source_style,
+            ..self
+        }
+    }
+
+    /// Determines whether carriage returns in strings are written as `&#13;`.
+    /// XML parsers turn `\r\n` and lone `\r` in documents into `\n`, so
+    /// this is needed for strings like script sources with Windows line
+    /// endings to be read back exactly. Defaults to `false`.
+    pub fn preserve_line_endings(self, enabled: bool) -> Self {
+        EncodeOptions {
+            preserve_line_endings: enabled,
+            ..self
+        }
+    }
+
+    /// Determines what happens to control characters in strings. Defaults to
+    /// [`ControlCharacters::Keep`].
+    pub fn control_characters(self, control_characters: ControlCharacters) -> Self {
+        EncodeOptions {
+            control_characters,
+            ..self
+        }
+    }
+
+    /// Determines which forms properties are written in. Defaults to
+    /// [`CompatibilityTarget::Modern`].
+    pub fn compatibility_target(self, compatibility_target: CompatibilityTarget) -> Self {
+        EncodeOptions {
+            compatibility_target,
+            ..self
+        }
+    }
+
+    /// Determines whether properties are renamed from their canonical names,
+    /// like `Size` and `Color`, to the names Roblox serializes them with, like
+    /// `size` and `Color3uint8`. Defaults to `true`.
+    ///
+    /// When this is disabled, properties are written with the names they have
+    /// in the `WeakDom`, but their values are still converted to the types
+    /// that the reflection database gives those names.
+    /// [`EncodePropertyBehavior::NoReflection`] turns off the conversions too.
+    pub fn map_property_names(self, enabled: bool) -> Self {
+        EncodeOptions {
+            map_property_names: enabled,
+            ..self
+        }
+    }
+
+    /// Determines whether properties that have their default value are
+    /// written. Defaults to `true`.
+    ///
+    /// Values are compared against the defaults in the reflection database.
+    /// Leaving them out can make files much smaller. Roblox fills in defaults
+    /// by itself when loading a file, and rbx_xml does the same when
+    /// [`DecodeOptions::apply_default_properties`][crate::DecodeOptions::apply_default_properties]
+    /// is enabled.
+    pub fn include_default_properties(self, include: bool) -> Self {
+        EncodeOptions {
+            include_default_properties: include,
+            ..self
+        }
+    }
+
+    /// Uses the given reflection database instead of
+    /// [`rbx_reflection_database::global`], like one for the version of Roblox that
+    /// the documents being written should open in. See
+    /// [`rbx_reflection_database::get_bundled`].
+    pub fn reflection_database(self, database: &'static ReflectionDatabase<'static>) -> Self {
+        EncodeOptions { database, ..self }
+    }
+
+    /// Encodes properties with `codecs` instead of rbx_xml's own encoding
+    /// where they apply, which allows writing datatypes that rbx_xml doesn't
+    /// support.
+    pub fn codecs(self, codecs: Codecs) -> Self {
+        EncodeOptions { codecs, ..self }
+    }
+
+    /// Only serializes the instances accepted by `filter`. Instances that
+    /// aren't accepted are left out along with all of their descendants,
+    /// including when they're one of the instances passed to
+    /// [`to_writer`][crate::to_writer].
+    ///
+    /// Ref properties pointing to instances that were left out are written
+    /// with referents that don't appear in the document, so they decode as
+    /// null.
+    pub fn instance_filter<F: Fn(&Instance) -> bool + Send + Sync + 'static>(
+        self,
+        filter: F,
+    ) -> Self {
+        EncodeOptions {
+            instance_filter: Some(Arc::new(filter)),
+            ..self
+        }
+    }
+
+    /// Leaves out every instance with one of the given ClassNames, along with
+    /// its descendants. See [`instance_filter`](Self::instance_filter).
+    pub fn exclude_classes<I, S>(self, classes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let classes: HashSet<String> = classes.into_iter().map(Into::into).collect();
+        self.instance_filter(move |instance| !classes.contains(&instance.class))
+    }
+
+    /// Only serializes the properties accepted by `filter`, which is given the
+    /// ClassName of an instance and the name of one of its properties.
+    ///
+    /// Property names are the ones they have in the `WeakDom`, like `Size`
+    /// instead of `size`. Names are always written.
+    pub fn property_filter<F: Fn(&str, &str) -> bool + Send + Sync + 'static>(
+        self,
+        filter: F,
+    ) -> Self {
+        EncodeOptions {
+            property_filter: Some(Arc::new(filter)),
+            ..self
+        }
+    }
+
+    /// Leaves out properties with one of the given names, on instances of any
+    /// class. See [`property_filter`](Self::property_filter).
+    pub fn exclude_properties<I, S>(self, properties: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let properties: HashSet<String> = properties.into_iter().map(Into::into).collect();
+        self.property_filter(move |_, property| !properties.contains(property))
+    }
+
+    /// Determines what happens to Ref properties that point to instances that
+    /// aren't being serialized, including instances left out by
+    /// [`instance_filter`](Self::instance_filter). Defaults to
+    /// [`ExternalReferences::Null`].
+    ///
+    /// Use [`to_writer_with_report`][crate::to_writer_with_report] to find
+    /// out which properties this happened to.
+    pub fn external_references(self, external_references: ExternalReferences) -> Self {
+        EncodeOptions {
+            external_references,
+            ..self
+        }
+    }
+
+    /// Determines how instances' referents are made. Defaults to
+    /// [`ReferentStyle::Sequential`].
+    pub fn referent_style(self, referent_style: ReferentStyle) -> Self {
+        EncodeOptions {
+            referent_style,
+            ..self
+        }
     }
 
     pub(crate) fn use_reflection(&self) -> bool {
         self.property_behavior != EncodePropertyBehavior::NoReflection
     }
+
+    fn includes_instance(&self, instance: &Instance) -> bool {
+        self.instance_filter
+            .as_ref()
+            .is_none_or(|filter| filter(instance))
+    }
+
+    fn includes_property(&self, class_name: &str, property_name: &str) -> bool {
+        self.property_filter
+            .as_ref()
+            .is_none_or(|filter| filter(class_name, property_name))
+    }
 }
 
 impl Default for EncodeOptions {
@@ -104,12 +642,52 @@ impl Default for EncodeOptions {
     }
 }
 
+impl fmt::Debug for EncodeOptions {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("EncodeOptions")
+            .field("property_behavior", &self.property_behavior)
+            .field("unknown_elements", &self.unknown_elements)
+            .field("annotations", &self.annotations)
+            .field("indentation", &self.indentation)
+            .field("newline_style", &self.newline_style)
+            .field("attribute_order", &self.attribute_order)
+            .field("xml_declaration", &self.xml_declaration)
+            .field("script_source_style", &self.script_source_style)
+            .field("preserve_line_endings", &self.preserve_line_endings)
+            .field("control_characters", &self.control_characters)
+            .field("compatibility_target", &self.compatibility_target)
+            .field("map_property_names", &self.map_property_names)
+            .field(
+                "include_default_properties",
+                &self.include_default_properties,
+            )
+            .field("codecs", &self.codecs)
+            .field(
+                "instance_filter",
+                &self.instance_filter.as_ref().map(|_| "<filter>"),
+            )
+            .field(
+                "property_filter",
+                &self.property_filter.as_ref().map(|_| "<filter>"),
+            )
+            .field("external_references", &self.external_references)
+            .field("referent_style", &self.referent_style)
+            .field("database", &self.database.version)
+            .finish()
+    }
+}
+
 pub struct EmitState {
-    options: EncodeOptions,
+    pub(crate) options: EncodeOptions,
 
     /// A map of IDs written so far to the generated referent that they use.
     /// This map is used to correctly emit Ref properties.
-    referent_map: HashMap<Ref, u32>,
+    referent_map: HashMap<Ref, String>,
+
+    /// Every referent in `referent_map`, so that stable referents that collide
+    /// can be told apart.
+    used_referents: HashSet<String>,
 
     /// The referent value that will be used for emitting the next instance.
     next_referent: u32,
@@ -117,57 +695,206 @@ pub struct EmitState {
     /// A map of all shared strings referenced so far while generating XML. This
     /// map will be written as the file's SharedString dictionary.
     shared_strings_to_emit: BTreeMap<SharedStringHash, SharedString>,
+
+    /// The unknown elements from the options, grouped by the instance they
+    /// belong to.
+    unknown_elements: HashMap<Ref, Vec<UnknownElement>>,
+
+    /// The annotations from the options, grouped by the instance they're
+    /// attached to. Annotations that aren't attached to an instance are kept
+    /// under `Ref::none()`.
+    annotations: HashMap<Ref, Vec<Annotation>>,
+
+    /// Every instance that will be serialized, which Ref properties are
+    /// checked against.
+    included: HashSet<Ref>,
+
+    report: EncodeReport,
+
+    /// The number of property values written so far.
+    property_values: usize,
 }
 
 impl EmitState {
-    pub fn new(options: EncodeOptions) -> EmitState {
+    pub fn new(mut options: EncodeOptions) -> EmitState {
+        let mut unknown_elements: HashMap<Ref, Vec<UnknownElement>> = HashMap::new();
+        for element in std::mem::take(&mut options.unknown_elements) {
+            unknown_elements
+                .entry(element.referent)
+                .or_default()
+                .push(element);
+        }
+
+        let mut annotations: HashMap<Ref, Vec<Annotation>> = HashMap::new();
+        for annotation in std::mem::take(&mut options.annotations) {
+            annotations
+                .entry(annotation.position.instance().unwrap_or_else(Ref::none))
+                .or_default()
+                .push(annotation);
+        }
+
         EmitState {
             options,
             referent_map: HashMap::new(),
+            used_referents: HashSet::new(),
             next_referent: 0,
             shared_strings_to_emit: BTreeMap::new(),
+            unknown_elements,
+            annotations,
+            included: HashSet::new(),
+            report: EncodeReport::default(),
+            property_values: 0,
         }
     }
 
-    pub fn map_id(&mut self, id: Ref) -> u32 {
-        match self.referent_map.get(&id) {
-            Some(&value) => value,
-            None => {
-                let referent = self.next_referent;
-                self.referent_map.insert(id, referent);
-                self.next_referent += 1;
-                referent
+    /// Finds every instance that will be serialized from the given roots,
+    /// which are the ones accepted by the instance filter whose ancestors are
+    /// also accepted.
+    ///
+    /// With [`ReferentStyle::Stable`], this is also where every instance is
+    /// given its referent, since those depend on where instances are instead
+    /// of the order they're written in.
+    fn collect_included(&mut self, tree: &WeakDom, ids: &[Ref]) {
+        let stable = self.options.referent_style == ReferentStyle::Stable;
+        let mut to_visit = if stable {
+            child_paths(tree, "", ids)
+        } else {
+            ids.iter().map(|id| (*id, String::new())).collect()
+        };
+
+        while let Some((id, path)) = to_visit.pop() {
+            let instance = tree.get_by_ref(id).unwrap();
+
+            if !self.options.includes_instance(instance) {
+                continue;
+            }
+
+            self.included.insert(id);
+
+            if stable {
+                self.insert_referent(id, stable_referent(instance, &path));
+                to_visit.extend(child_paths(tree, &path, instance.children()));
+            } else {
+                to_visit.extend(instance.children().iter().map(|id| (*id, String::new())));
             }
         }
     }
 
+    pub fn map_id(&mut self, id: Ref) -> String {
+        if let Some(referent) = self.referent_map.get(&id) {
+            return referent.clone();
+        }
+
+        let referent = match self.options.referent_style {
+            ReferentStyle::Prefixed => format!("RBX{:010}", self.next_referent),
+            _ => self.next_referent.to_string(),
+        };
+        self.next_referent += 1;
+
+        self.insert_referent(id, referent)
+    }
+
+    /// Gives `id` the referent `referent`, or a variation of it if another
+    /// instance already has it, returning the referent that was used.
+    fn insert_referent(&mut self, id: Ref, referent: String) -> String {
+        let mut unique = referent.clone();
+        let mut suffix = 1;
+        while self.used_referents.contains(&unique) {
+            suffix += 1;
+            unique = format!("{}_{}", referent, suffix);
+        }
+
+        self.used_referents.insert(unique.clone());
+        self.referent_map.insert(id, unique.clone());
+        unique
+    }
+
     pub fn add_shared_string(&mut self, value: SharedString) {
         self.shared_strings_to_emit.insert(value.hash(), value);
     }
 }
 
+/// Pairs each of `children` with its path, which is `parent_path` followed by
+/// the child's ClassName and Name. Siblings with the same ClassName and Name
+/// are numbered in order so that their paths differ.
+fn child_paths(tree: &WeakDom, parent_path: &str, children: &[Ref]) -> Vec<(Ref, String)> {
+    let mut counts: HashMap<(&str, &str), usize> = HashMap::new();
+
+    children
+        .iter()
+        .map(|id| {
+            let child = tree.get_by_ref(*id).unwrap();
+            let count = counts
+                .entry((child.class.as_str(), child.name.as_str()))
+                .or_default();
+            *count += 1;
+
+            let path = format!("{}/{}:{}~{}", parent_path, child.class, child.name, count);
+            (*id, path)
+        })
+        .collect()
+}
+
+/// Makes the referent that [`ReferentStyle::Stable`] uses for `instance`,
+/// which has the path `path` as made by `child_paths`.
+fn stable_referent(instance: &Instance, path: &str) -> String {
+    if let Some(Variant::UniqueId(unique_id)) = instance.properties.get("UniqueId") {
+        if unique_id.random() != 0 || unique_id.time() != 0 || unique_id.index() != 0 {
+            return format!("RBX{}", unique_id).to_uppercase();
+        }
+    }
+
+    // This is the 64-bit FNV-1a hash, which is simple and the same
+    // everywhere, unlike the hashers in std.
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in path.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    format!("RBX{:016X}", hash)
+}
+
 /// Serialize a single instance.
 ///
 /// `property_buffer` is a Vec that can be reused between calls to
 /// serialize_instance to make sorting properties more efficient.
-fn serialize_instance<'a, W: Write>(
+/// Writes the start of an instance and its properties, returning the
+/// instance and the annotations that go after its children, or `None` if the
+/// instance isn't being serialized. The instance is finished by
+/// [`Encoder::step`] once its children have been written.
+fn start_instance<'a, W: Write>(
     writer: &mut XmlEventWriter<W>,
     state: &mut EmitState,
     tree: &'a WeakDom,
     id: Ref,
     property_buffer: &mut Vec<(&'a String, &'a Variant)>,
-) -> Result<(), NewEncodeError> {
+) -> Result<Option<(&'a Instance, Vec<Annotation>)>, NewEncodeError> {
+    if !state.included.contains(&id) {
+        return Ok(None);
+    }
+
     let instance = tree.get_by_ref(id).unwrap();
+
     let mapped_id = state.map_id(id);
 
+    let mut annotations = state.annotations.remove(&id).unwrap_or_default();
+    write_annotations(writer, &mut annotations, |position| {
+        matches!(position, AnnotationPosition::BeforeInstance(_))
+    })?;
+
     writer.write(
         XmlWriteEvent::start_element("Item")
             .attr("class", &instance.class)
-            .attr("referent", &mapped_id.to_string()),
+            .attr("referent", &mapped_id),
     )?;
 
     writer.write(XmlWriteEvent::start_element("Properties"))?;
 
+    write_annotations(writer, &mut annotations, |position| {
+        is_before_property(position, "Name")
+    })?;
+
     write_value_xml(
         writer,
         state,
@@ -178,69 +905,238 @@ fn serialize_instance<'a, W: Write>(
     // Move references to our properties into property_buffer so we can sort
     // them and iterate them in order.
     property_buffer.extend(&instance.properties);
+    state.property_values += instance.properties.len();
     property_buffer.sort_unstable_by_key(|(key, _)| *key);
 
     for (property_name, value) in property_buffer.drain(..) {
-        let maybe_serialized_descriptor = if state.options.use_reflection() {
-            find_serialized_property_descriptor(&instance.class, property_name)
-        } else {
-            None
+        write_annotations(writer, &mut annotations, |position| {
+            is_before_property(position, property_name)
+        })?;
+
+        if let Variant::Ref(target) = value {
+            if target.is_some()
+                && !state.included.contains(target)
+                && state
+                    .options
+                    .includes_property(&instance.class, property_name)
+            {
+                write_external_reference(writer, state, instance, property_name, *target)
+                    .map_err(|err| err.with_property(Some(id), &instance.class, property_name))?;
+                continue;
+            }
+        }
+
+        write_property(writer, state, &instance.class, property_name, value)
+            .map_err(|err| err.with_property(Some(id), &instance.class, property_name))?;
+    }
+
+    let unknown_elements = state.unknown_elements.remove(&id).unwrap_or_default();
+    write_unknown_elements(writer, &unknown_elements, UnknownElementKind::Property)?;
+
+    write_annotations(writer, &mut annotations, |position| {
+        matches!(
+            position,
+            AnnotationPosition::BeforeProperty { .. } | AnnotationPosition::EndOfProperties(_)
+        )
+    })?;
+
+    writer.write(XmlWriteEvent::end_element())?;
+
+    write_unknown_elements(writer, &unknown_elements, UnknownElementKind::Item)?;
+
+    Ok(Some((instance, annotations)))
+}
+
+/// Writes a single property of an instance of the given class, using the
+/// reflection database to convert it to its serialized name and type according
+/// to the encode options.
+pub(crate) fn write_property<W: Write>(
+    writer: &mut XmlEventWriter<W>,
+    state: &mut EmitState,
+    class_name: &str,
+    property_name: &str,
+    value: &Variant,
+) -> Result<(), NewEncodeError> {
+    if !state.options.includes_property(class_name, property_name) {
+        return Ok(());
+    }
+
+    if let Some(codec) = state.options.codecs.for_property(class_name, property_name) {
+        return write_with_codec(writer, codec, property_name, value);
+    }
+
+    if !state.options.include_default_properties
+        && state.options.use_reflection()
+        && is_default_value(state.options.database, class_name, property_name, value)
+    {
+        log::trace!(
+            "Skipping property {}.{} because it has its default value",
+            class_name,
+            property_name
+        );
+        return Ok(());
+    }
+
+    if state.options.compatibility_target == CompatibilityTarget::Legacy {
+        if let Variant::Attributes(_) = value {
+            return Ok(());
+        }
+
+        if let Some((legacy_name, legacy_value)) =
+            find_legacy_property(state.options.database, class_name, property_name, value)
+        {
+            return write_value_xml(writer, state, legacy_name, &legacy_value);
+        }
+    }
+
+    if let Variant::String(source) = value {
+        if state.options.script_source_style == ScriptSourceStyle::Cdata
+            && is_script_source(state.options.database, class_name, property_name)
+        {
+            writer.write(
+                XmlWriteEvent::start_element("ProtectedString").attr("name", property_name),
+            )?;
+            writer.write_cdata(source)?;
+            return writer.end_element();
+        }
+    }
+
+    let maybe_serialized_descriptor = if !state.options.use_reflection() {
+        None
+    } else if state.options.map_property_names {
+        find_serialized_property_descriptor(state.options.database, class_name, property_name)
+    } else {
+        find_exact_property_descriptor(state.options.database, class_name, property_name)
+    };
+
+    if let Some(serialized_descriptor) = maybe_serialized_descriptor {
+        let data_type = match &serialized_descriptor.data_type {
+            DataType::Value(data_type) => *data_type,
+            DataType::Enum(_enum_name) => VariantType::Enum,
+            _ => unimplemented!(),
         };
 
-        if let Some(serialized_descriptor) = maybe_serialized_descriptor {
-            let data_type = match &serialized_descriptor.data_type {
-                DataType::Value(data_type) => *data_type,
-                DataType::Enum(_enum_name) => VariantType::Enum,
-                _ => unimplemented!(),
-            };
-
-            let converted_value = match value.try_convert_ref(data_type) {
-                Ok(value) => value,
-                Err(message) => {
-                    return Err(
-                        writer.error(EncodeErrorKind::UnsupportedPropertyConversion {
-                            class_name: instance.class.clone(),
-                            property_name: property_name.to_string(),
-                            expected_type: data_type,
-                            actual_type: value.ty(),
-                            message,
-                        }),
-                    )
-                }
-            };
+        let converted_value = match value.try_convert_ref(data_type) {
+            Ok(value) => value,
+            Err(message) => {
+                return Err(
+                    writer.error(EncodeErrorKind::UnsupportedPropertyConversion {
+                        class_name: class_name.to_owned(),
+                        property_name: property_name.to_string(),
+                        expected_type: data_type,
+                        actual_type: value.ty(),
+                        message,
+                    }),
+                )
+            }
+        };
 
-            write_value_xml(writer, state, &serialized_descriptor.name, &converted_value)?;
-        } else {
-            match state.options.property_behavior {
-                EncodePropertyBehavior::IgnoreUnknown => {}
-                EncodePropertyBehavior::WriteUnknown | EncodePropertyBehavior::NoReflection => {
-                    // We'll take this value as-is with no conversions on
-                    // either the name or value.
+        write_value_xml(writer, state, &serialized_descriptor.name, &converted_value)?;
+    } else {
+        match state.options.property_behavior {
+            EncodePropertyBehavior::IgnoreUnknown => {}
+            EncodePropertyBehavior::WriteUnknown | EncodePropertyBehavior::NoReflection => {
+                // We'll take this value as-is with no conversions on
+                // either the name or value.
 
-                    write_value_xml(writer, state, property_name, value)?;
-                }
-                EncodePropertyBehavior::ErrorOnUnknown => {
-                    return Err(writer.error(EncodeErrorKind::UnknownProperty {
-                        class_name: instance.class.clone(),
-                        property_name: property_name.clone(),
-                    }));
-                }
+                write_value_xml(writer, state, property_name, value)?;
+            }
+            EncodePropertyBehavior::ErrorOnUnknown => {
+                return Err(writer.error(EncodeErrorKind::UnknownProperty {
+                    class_name: class_name.to_owned(),
+                    property_name: property_name.to_owned(),
+                }));
             }
         }
     }
 
-    writer.write(XmlWriteEvent::end_element())?;
+    Ok(())
+}
+
+/// Handles a Ref property of `instance` that points to `target`, which isn't
+/// being serialized.
+fn write_external_reference<W: Write>(
+    writer: &mut XmlEventWriter<W>,
+    state: &mut EmitState,
+    instance: &Instance,
+    property_name: &str,
+    target: Ref,
+) -> Result<(), NewEncodeError> {
+    match state.options.external_references {
+        ExternalReferences::Null => {
+            write_property(
+                writer,
+                state,
+                &instance.class,
+                property_name,
+                &Variant::Ref(Ref::none()),
+            )?;
+        }
+        ExternalReferences::Drop => {}
+        ExternalReferences::Error => {
+            return Err(writer.error(EncodeErrorKind::ExternalReference {
+                class_name: instance.class.clone(),
+                property_name: property_name.to_owned(),
+            }));
+        }
+    }
+
+    state
+        .report
+        .external_references
+        .push(ExternalReference::new(
+            instance.referent(),
+            property_name.to_owned(),
+            target,
+        ));
+
+    Ok(())
+}
+
+fn is_before_property(position: &AnnotationPosition, name: &str) -> bool {
+    matches!(
+        position,
+        AnnotationPosition::BeforeProperty { property_name, .. } if property_name == name
+    )
+}
 
-    for child_id in instance.children() {
-        serialize_instance(writer, state, tree, *child_id, property_buffer)?;
+/// Writes the annotations at the positions accepted by `is_here`, removing
+/// them from `annotations`.
+fn write_annotations<W: Write, F: Fn(&AnnotationPosition) -> bool>(
+    writer: &mut XmlEventWriter<W>,
+    annotations: &mut Vec<Annotation>,
+    is_here: F,
+) -> Result<(), NewEncodeError> {
+    if annotations.is_empty() {
+        return Ok(());
     }
 
-    writer.write(XmlWriteEvent::end_element())?;
+    let mut remaining = Vec::new();
+    for annotation in annotations.drain(..) {
+        if is_here(&annotation.position) {
+            writer.write_annotation(&annotation.content)?;
+        } else {
+            remaining.push(annotation);
+        }
+    }
 
+    *annotations = remaining;
     Ok(())
 }
 
-fn serialize_shared_strings<W: Write>(
+fn write_unknown_elements<W: Write>(
+    writer: &mut XmlEventWriter<W>,
+    elements: &[UnknownElement],
+    kind: UnknownElementKind,
+) -> Result<(), NewEncodeError> {
+    for element in elements.iter().filter(|element| element.kind == kind) {
+        writer.write_raw_element(&element.xml)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn serialize_shared_strings<W: Write>(
     writer: &mut XmlEventWriter<W>,
     state: &mut EmitState,
 ) -> Result<(), NewEncodeError> {
@@ -249,22 +1145,37 @@ fn serialize_shared_strings<W: Write>(
     }
 
     writer.write(XmlWriteEvent::start_element("SharedStrings"))?;
+    write_shared_string_entries(writer, state)?;
+    writer.end_element()?;
+
+    Ok(())
+}
 
+/// Writes an entry inside of `SharedStrings` for every shared string
+/// referenced so far.
+pub(crate) fn write_shared_string_entries<W: Write>(
+    writer: &mut XmlEventWriter<W>,
+    state: &mut EmitState,
+) -> Result<(), NewEncodeError> {
     for value in state.shared_strings_to_emit.values() {
         // Roblox expects SharedString hashes to be the same length as an MD5
         // hash: 16 bytes, so we truncate our larger hashes to fit.
         let full_hash = value.hash();
         let truncated_hash = &full_hash.as_bytes()[..16];
 
-        writer.write(
-            XmlWriteEvent::start_element("SharedString")
-                .attr("md5", &base64::encode(truncated_hash)),
-        )?;
-
-        writer.write_string(&base64::encode(value.data()))?;
-        writer.end_element()?;
+        write_shared_string_entry(writer, &base64::encode(truncated_hash), value.data())?;
     }
 
-    writer.end_element()?;
     Ok(())
 }
+
+/// Writes the entry inside of `SharedStrings` for a single shared string.
+pub(crate) fn write_shared_string_entry<W: Write>(
+    writer: &mut XmlEventWriter<W>,
+    md5: &str,
+    data: &[u8],
+) -> Result<(), NewEncodeError> {
+    writer.write(XmlWriteEvent::start_element("SharedString").attr("md5", md5))?;
+    writer.write_base64(data)?;
+    writer.end_element()
+}
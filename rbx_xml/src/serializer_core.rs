@@ -1,12 +1,18 @@
 use std::{fmt::Write as FmtWrite, io::Write};
 
-use xml::writer::{EmitterConfig, EventWriter};
+use xml::{
+    reader::{ParserConfig, XmlEvent as XmlReadEvent},
+    writer::{EmitterConfig, EventWriter},
+};
 
 pub use xml::writer::XmlEvent as XmlWriteEvent;
 
 use crate::{
+    base64_stream,
     core::XmlType,
     error::{EncodeError as NewEncodeError, EncodeErrorKind},
+    report::AnnotationContent,
+    serializer::{AttributeOrder, ControlCharacters, EncodeOptions, Indentation, NewlineStyle},
 };
 
 /// A wrapper around an xml-rs `EventWriter` as well as other state kept around
@@ -14,23 +20,94 @@ use crate::{
 pub struct XmlEventWriter<W> {
     inner: EventWriter<W>,
     character_buffer: String,
+    attribute_order: AttributeOrder,
+    escaping: TextEscaping,
+}
+
+/// The options that affect how strings are escaped.
+#[derive(Debug, Clone, Copy)]
+struct TextEscaping {
+    preserve_line_endings: bool,
+    control_characters: ControlCharacters,
+}
+
+impl TextEscaping {
+    /// Tells whether `value` has characters that xml-rs wouldn't escape the
+    /// way these options ask for.
+    fn applies_to(&self, value: &str) -> bool {
+        value.chars().any(|char| {
+            (char == '\r' && self.preserve_line_endings)
+                || (is_control_character(char)
+                    && self.control_characters != ControlCharacters::Keep)
+        })
+    }
+
+    /// Pushes a character that has to be escaped according to these options
+    /// as a character reference, or nothing if it's removed, returning
+    /// whether it was handled.
+    fn push_reference(&self, output: &mut String, char: char) -> bool {
+        if char == '\r' && self.preserve_line_endings {
+            output.push_str("&#13;");
+            return true;
+        }
+
+        if !is_control_character(char) {
+            return false;
+        }
+
+        match self.control_characters {
+            ControlCharacters::Keep => return false,
+            ControlCharacters::Escape => write!(output, "&#{};", char as u32).unwrap(),
+            ControlCharacters::Remove => {}
+        }
+
+        true
+    }
 }
 
 impl<W: Write> XmlEventWriter<W> {
-    /// Constructs an `XmlEventWriter` from an output that implements `Write`.
-    pub fn from_output(output: W) -> XmlEventWriter<W> {
-        let inner = EmitterConfig::new()
-            .perform_indent(true)
-            .write_document_declaration(false)
-            .normalize_empty_elements(false)
-            .create_writer(output);
+    /// Constructs an `XmlEventWriter` from an output that implements `Write`,
+    /// formatting the document as described by `options`.
+    pub fn from_output(output: W, options: &EncodeOptions) -> XmlEventWriter<W> {
+        let mut config = EmitterConfig::new()
+            .write_document_declaration(options.xml_declaration)
+            .normalize_empty_elements(false);
+
+        config = match options.indentation {
+            Indentation::None => config.perform_indent(false),
+            Indentation::Spaces(count) => {
+                config.perform_indent(true).indent_string(" ".repeat(count))
+            }
+            Indentation::Tabs => config.perform_indent(true).indent_string("\t"),
+        };
+
+        config = match options.newline_style {
+            NewlineStyle::Lf => config.line_separator("\n"),
+            NewlineStyle::CrLf => config.line_separator("\r\n"),
+        };
 
         XmlEventWriter {
-            inner,
+            inner: config.create_writer(output),
             character_buffer: String::new(),
+            attribute_order: options.attribute_order,
+            escaping: TextEscaping {
+                preserve_line_endings: options.preserve_line_endings,
+                control_characters: options.control_characters,
+            },
         }
     }
 
+    /// The output that events are written to.
+    #[cfg(feature = "tokio")]
+    pub fn inner_mut(&mut self) -> &mut W {
+        self.inner.inner_mut()
+    }
+
+    /// Consumes the writer, returning its output.
+    pub fn into_inner(self) -> W {
+        self.inner.into_inner()
+    }
+
     pub(crate) fn error<T: Into<EncodeErrorKind>>(&self, kind: T) -> NewEncodeError {
         NewEncodeError::new_from_writer(kind.into(), &self.inner)
     }
@@ -46,12 +123,104 @@ impl<W: Write> XmlEventWriter<W> {
     where
         E: Into<XmlWriteEvent<'a>>,
     {
+        let event = match (event.into(), self.attribute_order) {
+            (
+                XmlWriteEvent::StartElement {
+                    name,
+                    attributes,
+                    namespace,
+                },
+                AttributeOrder::Alphabetical,
+            ) => {
+                let mut attributes = attributes.into_owned();
+                attributes
+                    .sort_by_key(|attribute| (attribute.name.prefix, attribute.name.local_name));
+
+                XmlWriteEvent::StartElement {
+                    name,
+                    attributes: attributes.into(),
+                    namespace,
+                }
+            }
+            (event, _) => event,
+        };
+
         self.inner.write(event).map_err(|e| self.error(e))
     }
 
+    /// Writes the elements in `xml`, which was produced by
+    /// `XmlEventReader::read_raw_element`, to the output stream.
+    pub fn write_raw_element(&mut self, xml: &str) -> Result<(), NewEncodeError> {
+        let reader = ParserConfig::new()
+            .ignore_comments(true)
+            .create_reader(xml.as_bytes());
+
+        for event in reader {
+            let event = event.map_err(|err| self.error(EncodeErrorKind::UnknownElement(err)))?;
+
+            match event {
+                XmlReadEvent::StartDocument { .. }
+                | XmlReadEvent::EndDocument
+                | XmlReadEvent::Whitespace(_) => {}
+                event => {
+                    if let Some(event) = event.as_writer_event() {
+                        self.write(event)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes a comment or processing instruction to the output stream.
+    pub fn write_annotation(&mut self, content: &AnnotationContent) -> Result<(), NewEncodeError> {
+        match content {
+            AnnotationContent::Comment(text) => self.write(XmlWriteEvent::comment(text)),
+            AnnotationContent::ProcessingInstruction { name, data } => {
+                self.write(XmlWriteEvent::processing_instruction(name, data.as_deref()))
+            }
+        }
+    }
+
     /// Writes a string slice to the output stream as characters or CDATA.
     pub fn write_string(&mut self, value: &str) -> Result<(), NewEncodeError> {
-        write_characters_or_cdata(&mut self.inner, value)
+        write_characters_or_cdata(&mut self.inner, self.escaping, value, false)
+    }
+
+    /// Writes a string slice to the output stream as CDATA, splitting it into
+    /// multiple sections where it contains `]]>` or characters that have to
+    /// be written as references.
+    pub fn write_cdata(&mut self, value: &str) -> Result<(), NewEncodeError> {
+        write_characters_or_cdata(&mut self.inner, self.escaping, value, true)
+    }
+
+    /// Writes `data` as base64 characters, encoding it straight into the
+    /// output stream a piece at a time.
+    pub fn write_base64(&mut self, data: &[u8]) -> Result<(), NewEncodeError> {
+        self.write_base64_text(data, false)
+    }
+
+    /// The same as `write_base64`, but wraps the characters in CDATA.
+    pub fn write_base64_cdata(&mut self, data: &[u8]) -> Result<(), NewEncodeError> {
+        self.write_base64_text(data, true)
+    }
+
+    fn write_base64_text(&mut self, data: &[u8], cdata: bool) -> Result<(), NewEncodeError> {
+        // Base64 never has to be escaped, so like escaped text, it's written
+        // by hand after an empty characters event that finishes the start tag.
+        self.write(XmlWriteEvent::characters(""))?;
+
+        let output = self.inner.inner_mut();
+        if cdata {
+            output.write_all(b"<![CDATA[")?;
+        }
+        base64_stream::encode_to(data, output)?;
+        if cdata {
+            output.write_all(b"]]>")?;
+        }
+
+        Ok(())
     }
 
     /// Writes a value that implements `Display` as characters or CDATA. Resuses
@@ -61,7 +230,12 @@ impl<W: Write> XmlEventWriter<W> {
         value: T,
     ) -> Result<(), NewEncodeError> {
         write!(self.character_buffer, "{}", value).unwrap();
-        write_characters_or_cdata(&mut self.inner, &self.character_buffer)?;
+        write_characters_or_cdata(
+            &mut self.inner,
+            self.escaping,
+            &self.character_buffer,
+            false,
+        )?;
         self.character_buffer.clear();
 
         Ok(())
@@ -119,7 +293,9 @@ impl<W: Write> XmlEventWriter<W> {
 /// and `write_characters` without borrowing issues.
 fn write_characters_or_cdata<W: Write>(
     writer: &mut EventWriter<W>,
+    escaping: TextEscaping,
     value: &str,
+    force_cdata: bool,
 ) -> Result<(), NewEncodeError> {
     let first_char = value.chars().next();
     let last_char = value.chars().next_back();
@@ -133,7 +309,23 @@ fn write_characters_or_cdata<W: Write>(
         (None, None) => false,
     };
 
-    if has_outer_whitespace {
+    let use_cdata = force_cdata || has_outer_whitespace;
+
+    if escaping.applies_to(value) {
+        let escaped = if use_cdata {
+            escape_cdata(escaping, value)
+        } else {
+            escape_characters(escaping, value)
+        };
+
+        // xml-rs can't write text that's already escaped, so we write an
+        // empty characters event to finish the start tag and then write the
+        // text ourselves.
+        writer
+            .write(XmlWriteEvent::characters(""))
+            .map_err(|e| NewEncodeError::new_from_writer(e.into(), writer))?;
+        writer.inner_mut().write_all(escaped.as_bytes())?;
+    } else if use_cdata {
         writer
             .write(XmlWriteEvent::cdata(value))
             .map_err(|e| NewEncodeError::new_from_writer(e.into(), writer))?;
@@ -145,3 +337,59 @@ fn write_characters_or_cdata<W: Write>(
 
     Ok(())
 }
+
+/// Tells whether `char` is a control character that XML documents can't
+/// contain.
+fn is_control_character(char: char) -> bool {
+    char < '\u{20}' && !matches!(char, '\t' | '\n' | '\r')
+}
+
+/// Escapes `value` as characters, like xml-rs does, and also according to
+/// `escaping`.
+fn escape_characters(escaping: TextEscaping, value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+
+    for char in value.chars() {
+        match char {
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '&' => output.push_str("&amp;"),
+            char => {
+                if !escaping.push_reference(&mut output, char) {
+                    output.push(char);
+                }
+            }
+        }
+    }
+
+    output
+}
+
+/// Escapes `value` as one or more CDATA sections. Character references can't
+/// go inside of CDATA, so sections are closed around them. `]]>` would end a
+/// section, so sections are also split between its `]]` and `>`.
+fn escape_cdata(escaping: TextEscaping, value: &str) -> String {
+    let mut output = String::with_capacity(value.len() + 12);
+    output.push_str("<![CDATA[");
+
+    for char in value.chars() {
+        let mut reference = String::new();
+
+        if escaping.push_reference(&mut reference, char) {
+            if !reference.is_empty() {
+                output.push_str("]]>");
+                output.push_str(&reference);
+                output.push_str("<![CDATA[");
+            }
+        } else {
+            if char == '>' && output.ends_with("]]") {
+                output.push_str("]]><![CDATA[");
+            }
+
+            output.push(char);
+        }
+    }
+
+    output.push_str("]]>");
+    output
+}
@@ -0,0 +1,282 @@
+use std::{cell::Cell, collections::HashSet, io::Read, rc::Rc};
+
+use rbx_dom_weak::{
+    types::{Ref, SharedString, Variant},
+    InstanceBuilder, WeakDom,
+};
+
+use crate::{
+    deserializer::{
+        deserialize_metadata, deserialize_shared_string, peek_property_element,
+        read_document_start, read_item_start, read_property, CountingReader, DecodeOptions,
+        ParseState,
+    },
+    deserializer_core::{XmlEventReader, XmlReadEvent},
+    error::{DecodeError, DecodeErrorKind},
+};
+
+/// Receives the contents of a document from
+/// [`stream_from_reader`][crate::stream_from_reader] as it is read, without a
+/// `WeakDom` being built.
+///
+/// Events are delivered in document order. Each instance gets a call to
+/// [`StreamHandler::start_instance`], followed by a call to
+/// [`StreamHandler::property`] for each of its properties, then the events for
+/// each of its children, and finally a call to
+/// [`StreamHandler::end_instance`]. The document's SharedStrings, which Roblox
+/// writes after every instance, are delivered through
+/// [`StreamHandler::shared_string`].
+pub trait StreamHandler {
+    /// Called when an instance with the given ClassName starts. `referent` is
+    /// the instance's referent in the document, which Ref properties pointing
+    /// to it will contain.
+    fn start_instance(&mut self, class: &str, referent: Option<&str>);
+
+    /// Called for each property of the instance that was most recently
+    /// started and hasn't ended yet. The instance's name is delivered as a
+    /// String property named `Name`.
+    fn property(&mut self, name: &str, value: StreamValue);
+
+    /// Called when the instance that was most recently started ends.
+    fn end_instance(&mut self);
+
+    /// Called for each entry in the document's SharedString dictionary. `hash`
+    /// matches the hashes given in [`StreamValue::SharedString`].
+    fn shared_string(&mut self, hash: &str, value: SharedString);
+}
+
+impl<H: StreamHandler + ?Sized> StreamHandler for &mut H {
+    fn start_instance(&mut self, class: &str, referent: Option<&str>) {
+        (**self).start_instance(class, referent)
+    }
+
+    fn property(&mut self, name: &str, value: StreamValue) {
+        (**self).property(name, value)
+    }
+
+    fn end_instance(&mut self) {
+        (**self).end_instance()
+    }
+
+    fn shared_string(&mut self, hash: &str, value: SharedString) {
+        (**self).shared_string(hash, value)
+    }
+}
+
+/// The value of a property passed to [`StreamHandler::property`].
+///
+/// Refs and SharedStrings point to things that can appear later in the
+/// document, so they're given as they appear in the document instead of being
+/// resolved.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum StreamValue {
+    /// Any value that isn't a Ref or SharedString.
+    Value(Variant),
+
+    /// A Ref property, containing the referent of the instance it points to,
+    /// or `None` if it's null.
+    Ref(Option<String>),
+
+    /// A SharedString property, containing the hash of its value in the
+    /// document's SharedString dictionary.
+    SharedString(String),
+}
+
+pub fn stream_internal<R: Read, H: StreamHandler>(
+    source: R,
+    options: DecodeOptions,
+    mut handler: H,
+) -> Result<(), DecodeError> {
+    // Properties are decoded with the same code used to build a WeakDom,
+    // which needs an instance for them to belong to. Only this one instance is
+    // ever created, and it's never given any properties.
+    let mut scratch = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let scratch_id = scratch.root_ref();
+
+    let bytes_read = Rc::new(Cell::new(0));
+    let source = CountingReader::new(source, Rc::clone(&bytes_read));
+
+    let mut reader = XmlEventReader::with_limits(source, options.reader_limits());
+    let mut state = ParseState::new(&mut scratch, options, bytes_read);
+
+    read_document_start(&mut reader)?;
+
+    loop {
+        match reader.expect_peek()? {
+            XmlReadEvent::StartElement { name, .. } => match name.local_name.as_str() {
+                "Item" => {
+                    stream_instance(&mut reader, &mut state, &mut handler, scratch_id)?;
+                }
+                "External" => {
+                    reader.eat_unknown_tag()?;
+                }
+                "Meta" => {
+                    deserialize_metadata(&mut reader, &mut state)?;
+                }
+                "SharedStrings" => {
+                    stream_shared_string_dict(&mut reader, &mut state, &mut handler)?;
+                }
+                _ => {
+                    let event = reader.expect_next().unwrap();
+                    return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
+                }
+            },
+            XmlReadEvent::EndElement { name } if name.local_name == "roblox" => {
+                reader.expect_next().unwrap();
+                break;
+            }
+            XmlReadEvent::EndDocument => break,
+            _ => {
+                let event = reader.expect_next().unwrap();
+                return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn stream_instance<R: Read, H: StreamHandler>(
+    reader: &mut XmlEventReader<R>,
+    state: &mut ParseState,
+    handler: &mut H,
+    scratch_id: Ref,
+) -> Result<(), DecodeError> {
+    let (class_name, referent) = read_item_start(reader)?;
+
+    // Warnings about unknown property types mention the scratch instance's
+    // class, so it needs to match the instance being read.
+    state.tree.get_by_ref_mut(scratch_id).unwrap().class = class_name.clone();
+
+    handler.start_instance(&class_name, referent.as_deref());
+
+    if !state.instance_visited() {
+        return Err(reader.error(DecodeErrorKind::Cancelled));
+    }
+
+    // The names of the properties read so far, so that migrated properties
+    // don't replace the properties they were migrated to.
+    let mut property_names = HashSet::new();
+
+    loop {
+        match reader.expect_peek()? {
+            XmlReadEvent::StartElement { name, .. } => match name.local_name.as_str() {
+                "Properties" => {
+                    reader.expect_start_with_name("Properties")?;
+
+                    while let Some((xml_type_name, xml_property_name)) =
+                        peek_property_element(reader)?
+                    {
+                        let property = read_property(
+                            reader,
+                            state,
+                            scratch_id,
+                            &class_name,
+                            &xml_type_name,
+                            xml_property_name,
+                        )?;
+
+                        if let Some(property) = property {
+                            if property.is_migration && property_names.contains(&property.name) {
+                                continue;
+                            }
+
+                            if !property_names.contains(&property.name) {
+                                state.check_property_count(reader, property_names.len())?;
+                            }
+
+                            let value = stream_value(state, property.value);
+                            handler.property(&property.name, value);
+                            property_names.insert(property.name);
+                        }
+                    }
+                }
+                "Item" => {
+                    stream_instance(reader, state, handler, scratch_id)?;
+                }
+                "External" => {
+                    reader.eat_unknown_tag()?;
+                }
+                _ if state.options.preserve_unknown_elements => {
+                    reader.eat_unknown_tag()?;
+                }
+                _ => {
+                    let event = reader.expect_next().unwrap();
+                    return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
+                }
+            },
+            XmlReadEvent::EndElement { name } => {
+                if name.local_name != "Item" {
+                    let event = reader.expect_next().unwrap();
+                    return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
+                }
+
+                reader.expect_next().unwrap();
+
+                break;
+            }
+            _ => {
+                let event = reader.expect_next().unwrap();
+                return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
+            }
+        }
+    }
+
+    // Unknown elements can't be attached to the instances they came from
+    // when streaming, so they aren't kept around.
+    state.report.unknown_elements.clear();
+
+    handler.end_instance();
+
+    Ok(())
+}
+
+/// Turns a value read by `read_property` into a `StreamValue`. Refs and
+/// SharedStrings are read as placeholders, with what they point to recorded
+/// as a rewrite in `state`, which we take back out here.
+fn stream_value(state: &mut ParseState, value: Variant) -> StreamValue {
+    if let Some(rewrite) = state.shared_string_rewrites.pop() {
+        return StreamValue::SharedString(rewrite.shared_string_hash);
+    }
+
+    match value {
+        Variant::Ref(_) => StreamValue::Ref(
+            state
+                .referent_rewrites
+                .pop()
+                .map(|rewrite| rewrite.referent_value),
+        ),
+        value => StreamValue::Value(value),
+    }
+}
+
+fn stream_shared_string_dict<R: Read, H: StreamHandler>(
+    reader: &mut XmlEventReader<R>,
+    state: &mut ParseState,
+    handler: &mut H,
+) -> Result<(), DecodeError> {
+    reader.expect_start_with_name("SharedStrings")?;
+
+    loop {
+        match reader.expect_peek()? {
+            XmlReadEvent::StartElement { name, .. } if name.local_name == "SharedString" => {
+                deserialize_shared_string(reader, state)?;
+
+                for (hash, value) in state.known_shared_strings.drain() {
+                    handler.shared_string(&hash, value);
+                }
+            }
+            XmlReadEvent::EndElement { name } if name.local_name == "SharedStrings" => {
+                break;
+            }
+            _ => {
+                let event = reader.expect_next().unwrap();
+                return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
+            }
+        }
+    }
+
+    reader.expect_end_with_name("SharedStrings")?;
+    Ok(())
+}
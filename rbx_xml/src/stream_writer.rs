@@ -0,0 +1,223 @@
+use std::io::Write;
+
+use rbx_dom_weak::types::{SharedString, Variant};
+
+use crate::{
+    error::{EncodeError, EncodeErrorKind},
+    serializer::{
+        serialize_shared_strings, write_property, write_shared_string_entries,
+        write_shared_string_entry, EmitState, EncodeOptions,
+    },
+    serializer_core::{XmlEventWriter, XmlWriteEvent},
+    stream::{StreamHandler, StreamValue},
+    types::write_value_xml,
+};
+
+/// Writes an XML-format model or place from a stream of events, as they
+/// arrive.
+///
+/// `StreamWriter` implements [`StreamHandler`], so it can be given directly to
+/// [`stream_from_reader`][crate::stream_from_reader] to copy a document while
+/// it's read, or its events can be produced by hand. Output is written to the
+/// underlying writer as each event is handled, so nothing but the names of the
+/// currently open instances is held in memory.
+///
+/// A `StreamHandler` can't return errors, so the first error that happens is
+/// kept and every event after it is ignored. It's returned by
+/// [`StreamWriter::finish`], which must be called to complete the document.
+///
+/// Serializing a `WeakDom` with [`to_writer`][crate::to_writer] also writes
+/// output as it goes, and doesn't need a `StreamWriter`.
+pub struct StreamWriter<W: Write> {
+    writer: XmlEventWriter<W>,
+    state: EmitState,
+
+    /// The ClassNames of the instances that have been started but not ended.
+    classes: Vec<String>,
+
+    /// Whether the `Properties` element of the innermost instance is open.
+    in_properties: bool,
+
+    /// Whether the `SharedStrings` element is open.
+    in_shared_strings: bool,
+
+    /// The first error that happened, after which nothing else is written.
+    error: Option<EncodeError>,
+}
+
+impl<W: Write> StreamWriter<W> {
+    /// Creates a `StreamWriter` that writes to `output`, serializing
+    /// properties and formatting the document according to `options`.
+    pub fn new(output: W, options: EncodeOptions) -> Self {
+        let mut writer = XmlEventWriter::from_output(output, &options);
+        let error = writer
+            .write(XmlWriteEvent::start_element("roblox").attr("version", "4"))
+            .err();
+
+        StreamWriter {
+            writer,
+            state: EmitState::new(options),
+            classes: Vec::new(),
+            in_properties: false,
+            in_shared_strings: false,
+            error,
+        }
+    }
+
+    /// Finishes the document, returning the first error that happened while
+    /// writing it, if any.
+    pub fn finish(mut self) -> Result<(), EncodeError> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+
+        if !self.classes.is_empty() {
+            return Err(self.writer.error(EncodeErrorKind::InvalidStreamEvent(
+                "the stream ended with instances that were never ended",
+            )));
+        }
+
+        // SharedString values given as Variants are collected while writing
+        // and written after everything else, like in a serialized WeakDom.
+        if self.in_shared_strings {
+            write_shared_string_entries(&mut self.writer, &mut self.state)?;
+            self.writer.end_element()?;
+        } else {
+            serialize_shared_strings(&mut self.writer, &mut self.state)?;
+        }
+
+        self.writer.end_element()
+    }
+
+    fn handle<F>(&mut self, event: F)
+    where
+        F: FnOnce(&mut Self) -> Result<(), EncodeError>,
+    {
+        if self.error.is_none() {
+            self.error = event(self).err();
+        }
+    }
+
+    fn invalid(&self, explain: &'static str) -> EncodeError {
+        self.writer
+            .error(EncodeErrorKind::InvalidStreamEvent(explain))
+    }
+
+    /// Closes the `Properties` element of the innermost instance if it's
+    /// still open, since nothing but properties can go inside of it.
+    fn close_properties(&mut self) -> Result<(), EncodeError> {
+        if self.in_properties {
+            self.writer.end_element()?;
+            self.in_properties = false;
+        }
+
+        Ok(())
+    }
+
+    fn write_start_instance(
+        &mut self,
+        class: &str,
+        referent: Option<&str>,
+    ) -> Result<(), EncodeError> {
+        if self.in_shared_strings {
+            return Err(self.invalid("an instance was started after SharedStrings"));
+        }
+
+        self.close_properties()?;
+
+        let mut start = XmlWriteEvent::start_element("Item").attr("class", class);
+        if let Some(referent) = referent {
+            start = start.attr("referent", referent);
+        }
+        self.writer.write(start)?;
+        self.writer
+            .write(XmlWriteEvent::start_element("Properties"))?;
+
+        self.classes.push(class.to_owned());
+        self.in_properties = true;
+
+        Ok(())
+    }
+
+    fn write_property(&mut self, name: &str, value: StreamValue) -> Result<(), EncodeError> {
+        let class = match self.classes.last() {
+            Some(class) => class,
+            None => return Err(self.invalid("a property was given outside of an instance")),
+        };
+
+        if !self.in_properties {
+            return Err(self.invalid("a property was given after the instance's children"));
+        }
+
+        match value {
+            // Refs inside of a Variant point to instances in a WeakDom, which
+            // don't have referents in the document.
+            StreamValue::Value(Variant::Ref(_)) => {
+                Err(self.invalid("Ref properties must be given as StreamValue::Ref"))
+            }
+            // Names are always written as they are, like when serializing a
+            // WeakDom.
+            StreamValue::Value(value) if name == "Name" => {
+                write_value_xml(&mut self.writer, &mut self.state, name, &value)
+            }
+            StreamValue::Value(value) => {
+                write_property(&mut self.writer, &mut self.state, class, name, &value)
+                    .map_err(|err| err.with_property(None, class, name))
+            }
+            StreamValue::Ref(referent) => {
+                self.writer
+                    .write(XmlWriteEvent::start_element("Ref").attr("name", name))?;
+                self.writer
+                    .write_string(referent.as_deref().unwrap_or("null"))?;
+                self.writer.end_element()
+            }
+            StreamValue::SharedString(hash) => {
+                self.writer
+                    .write(XmlWriteEvent::start_element("SharedString").attr("name", name))?;
+                self.writer.write_string(&hash)?;
+                self.writer.end_element()
+            }
+        }
+    }
+
+    fn write_end_instance(&mut self) -> Result<(), EncodeError> {
+        if self.classes.pop().is_none() {
+            return Err(self.invalid("an instance was ended without being started"));
+        }
+
+        self.close_properties()?;
+        self.writer.end_element()
+    }
+
+    fn write_shared_string(&mut self, hash: &str, value: SharedString) -> Result<(), EncodeError> {
+        if !self.classes.is_empty() {
+            return Err(self.invalid("a SharedString was given inside of an instance"));
+        }
+
+        if !self.in_shared_strings {
+            self.writer
+                .write(XmlWriteEvent::start_element("SharedStrings"))?;
+            self.in_shared_strings = true;
+        }
+
+        write_shared_string_entry(&mut self.writer, hash, value.data())
+    }
+}
+
+impl<W: Write> StreamHandler for StreamWriter<W> {
+    fn start_instance(&mut self, class: &str, referent: Option<&str>) {
+        self.handle(|writer| writer.write_start_instance(class, referent))
+    }
+
+    fn property(&mut self, name: &str, value: StreamValue) {
+        self.handle(|writer| writer.write_property(name, value))
+    }
+
+    fn end_instance(&mut self) {
+        self.handle(|writer| writer.write_end_instance())
+    }
+
+    fn shared_string(&mut self, hash: &str, value: SharedString) {
+        self.handle(|writer| writer.write_shared_string(hash, value))
+    }
+}
@@ -0,0 +1,222 @@
+use std::fmt::Write as _;
+
+use rbx_dom_weak::types::{
+    BinaryString, ChunkPosition, SmoothGrid, TerrainChunk, TerrainMaterial, Variant, Voxel,
+    CHUNK_SIZE,
+};
+
+use crate::{
+    codec::{CodecError, PropertyCodec},
+    core::XmlType,
+    deserializer_core::{XmlEventReader, XmlReadEvent},
+};
+
+/// The classes whose `SmoothGrid` property is written in the structured form
+/// by [`Codecs::structured_terrain`][crate::Codecs::structured_terrain].
+pub(crate) const SMOOTH_GRID_CLASSES: &[&str] = &["Terrain", "TerrainRegion"];
+
+const CHUNK_VOLUME: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+
+/// Writes `SmoothGrid` properties as readable chunks of voxels instead of
+/// base64, and reads either form back into a BinaryString.
+///
+/// The voxels of each chunk are written as runs separated by spaces, X
+/// changing fastest and Z slowest. A run is a material, then `:` and an
+/// occupancy if it isn't the material's usual occupancy, then `*` and a
+/// length if it's longer than one voxel:
+///
+/// ```xml
+/// <SmoothGrid name="SmoothGrid">
+///     <Chunk x="0" y="-1" z="0">Air*16384 Grass*16352 Grass:128*32</Chunk>
+/// </SmoothGrid>
+/// ```
+///
+/// Grids that can't be decoded are written as a BinaryString, so that they're
+/// still kept.
+pub(crate) struct StructuredTerrain;
+
+impl PropertyCodec for StructuredTerrain {
+    fn decode(&self, xml: &str) -> Result<Variant, CodecError> {
+        let mut reader = XmlEventReader::from_source(xml.as_bytes());
+        reader.expect_next()?;
+
+        let name = match reader.expect_peek()? {
+            XmlReadEvent::StartElement { name, .. } => name.local_name.clone(),
+            _ => return Err("expected a property element".into()),
+        };
+
+        let data = match name.as_str() {
+            "SmoothGrid" => {
+                let mut buffer = Vec::new();
+                read_structured(&mut reader)?.to_writer(&mut buffer)?;
+                BinaryString::from(buffer)
+            }
+            _ => BinaryString::read_outer_xml(&mut reader)?,
+        };
+
+        Ok(Variant::BinaryString(data))
+    }
+
+    fn encode(&self, name: &str, value: &Variant) -> Result<String, CodecError> {
+        let data: &[u8] = match value {
+            Variant::BinaryString(data) => data.as_ref(),
+            _ => return Err(format!("expected a BinaryString, got {:?}", value.ty()).into()),
+        };
+
+        let name = xml::escape::escape_str_attribute(name);
+
+        match SmoothGrid::from_reader(data) {
+            Ok(grid) => Ok(write_structured(&name, &grid)),
+            Err(_) => Ok(format!(
+                "<BinaryString name=\"{}\">{}</BinaryString>",
+                name,
+                base64::encode(data)
+            )),
+        }
+    }
+}
+
+fn write_structured(name: &str, grid: &SmoothGrid) -> String {
+    let mut output = format!("<SmoothGrid name=\"{}\">", name);
+
+    for chunk in grid.chunks() {
+        let position = chunk.position();
+        write!(
+            output,
+            "<Chunk x=\"{}\" y=\"{}\" z=\"{}\">",
+            position.x, position.y, position.z
+        )
+        .unwrap();
+
+        let mut voxels = chunk.voxels().iter().peekable();
+        let mut first = true;
+
+        while let Some(voxel) = voxels.next() {
+            let mut length = 1;
+            while voxels.next_if_eq(&voxel).is_some() {
+                length += 1;
+            }
+
+            if !first {
+                output.push(' ');
+            }
+            first = false;
+
+            match voxel.terrain_material() {
+                Some(material) => write!(output, "{:?}", material).unwrap(),
+                None => write!(output, "{}", voxel.material).unwrap(),
+            }
+            if voxel.occupancy != default_occupancy(voxel.material) {
+                write!(output, ":{}", voxel.occupancy).unwrap();
+            }
+            if length > 1 {
+                write!(output, "*{}", length).unwrap();
+            }
+        }
+
+        output.push_str("</Chunk>");
+    }
+
+    output.push_str("</SmoothGrid>");
+    output
+}
+
+fn read_structured<R: std::io::Read>(
+    reader: &mut XmlEventReader<R>,
+) -> Result<SmoothGrid, CodecError> {
+    reader.expect_start_with_name("SmoothGrid")?;
+
+    let mut grid = SmoothGrid::new();
+
+    loop {
+        match reader.expect_peek()? {
+            XmlReadEvent::StartElement { name, .. } if name.local_name == "Chunk" => {}
+            XmlReadEvent::EndElement { name } if name.local_name == "SmoothGrid" => break,
+            _ => return Err("expected a Chunk element".into()),
+        }
+
+        let mut position = [None; 3];
+        for attribute in reader.expect_start_with_name("Chunk")? {
+            let index = match attribute.name.local_name.as_str() {
+                "x" => 0,
+                "y" => 1,
+                "z" => 2,
+                _ => continue,
+            };
+            position[index] = Some(attribute.value.parse::<i32>()?);
+        }
+
+        let position = match position {
+            [Some(x), Some(y), Some(z)] => ChunkPosition::new(x, y, z),
+            _ => return Err("Chunk elements need x, y, and z attributes".into()),
+        };
+
+        let mut chunk = TerrainChunk::new(position);
+        let mut index = 0;
+
+        for run in reader.read_characters()?.split_whitespace() {
+            let (voxel, length) = parse_run(run)?;
+
+            if index + length > CHUNK_VOLUME {
+                return Err(
+                    format!("chunk {:?} has more than {} voxels", position, CHUNK_VOLUME).into(),
+                );
+            }
+
+            for index in index..index + length {
+                let x = index % CHUNK_SIZE;
+                let y = index / CHUNK_SIZE % CHUNK_SIZE;
+                let z = index / (CHUNK_SIZE * CHUNK_SIZE);
+                chunk.set_voxel(x, y, z, voxel);
+            }
+            index += length;
+        }
+
+        if index != CHUNK_VOLUME {
+            return Err(format!(
+                "chunk {:?} has {} voxels instead of {}",
+                position, index, CHUNK_VOLUME
+            )
+            .into());
+        }
+
+        reader.expect_end_with_name("Chunk")?;
+        grid.insert_chunk(chunk);
+    }
+
+    reader.expect_end_with_name("SmoothGrid")?;
+    Ok(grid)
+}
+
+/// Parses a run of voxels like `Grass:128*32`.
+fn parse_run(run: &str) -> Result<(Voxel, usize), CodecError> {
+    let (voxel, length) = match run.split_once('*') {
+        Some((voxel, length)) => (voxel, length.parse()?),
+        None => (run, 1),
+    };
+    let (material, occupancy) = match voxel.split_once(':') {
+        Some((material, occupancy)) => (material, Some(occupancy.parse()?)),
+        None => (voxel, None),
+    };
+
+    let material = match material.parse::<u8>() {
+        Ok(id) => id,
+        Err(_) => (0..=u8::MAX)
+            .filter_map(TerrainMaterial::from_id)
+            .find(|known| format!("{:?}", known) == material)
+            .ok_or_else(|| format!("unknown terrain material {:?}", material))?
+            as u8,
+    };
+
+    let occupancy = occupancy.unwrap_or_else(|| default_occupancy(material));
+    Ok((Voxel::new(material, occupancy), length))
+}
+
+/// The occupancy of voxels of `material` when it isn't written.
+fn default_occupancy(material: u8) -> u8 {
+    if material == TerrainMaterial::Air as u8 {
+        0
+    } else {
+        255
+    }
+}
@@ -1,6 +1,9 @@
 use std::fmt::Debug;
 
-use crate::{core::XmlType, deserializer_core::XmlEventReader, serializer_core::XmlEventWriter};
+use crate::{
+    core::XmlType, deserializer_core::XmlEventReader, serializer_core::XmlEventWriter,
+    EncodeOptions,
+};
 
 pub fn test_xml_round_trip<T>(test_value: &T)
 where
@@ -9,7 +12,7 @@ where
     let _ = env_logger::try_init();
 
     let mut buffer = Vec::new();
-    let mut writer = XmlEventWriter::from_output(&mut buffer);
+    let mut writer = XmlEventWriter::from_output(&mut buffer, &EncodeOptions::new());
 
     test_value.write_outer_xml("foo", &mut writer).unwrap();
 
@@ -30,7 +33,7 @@ where
     let _ = env_logger::try_init();
 
     let mut buffer = Vec::new();
-    let mut writer = XmlEventWriter::from_output(&mut buffer);
+    let mut writer = XmlEventWriter::from_output(&mut buffer, &EncodeOptions::new());
 
     test_value.write_outer_xml("foo", &mut writer).unwrap();
 
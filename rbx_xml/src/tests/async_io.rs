@@ -0,0 +1,161 @@
+//! Tests for the async entry points
+
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use rbx_dom_weak::{types::Variant, InstanceBuilder, WeakDom};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::{DecodeOptions, EncodeOptions};
+
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+fn round_trip() {
+    let _ = env_logger::try_init();
+
+    let tree = WeakDom::new(InstanceBuilder::new("StringValue").with_property("Value", "Hello"));
+
+    let mut expected = Vec::new();
+    crate::to_writer_default(&mut expected, &tree, &[tree.root_ref()]).unwrap();
+
+    let mut buffer = Vec::new();
+    block_on(crate::to_writer_async(
+        &mut buffer,
+        &tree,
+        &[tree.root_ref()],
+        EncodeOptions::new(),
+    ))
+    .unwrap();
+    assert_eq!(buffer, expected);
+
+    let decoded = block_on(crate::from_reader_async(
+        buffer.as_slice(),
+        DecodeOptions::new(),
+    ))
+    .unwrap();
+    let value = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(
+        value.properties.get("Value"),
+        Some(&Variant::String("Hello".to_owned()))
+    );
+}
+
+/// Records each write it's given separately.
+#[derive(Default)]
+struct RecordingWriter {
+    writes: Vec<Vec<u8>>,
+}
+
+impl AsyncWrite for RecordingWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.writes.push(buf.to_vec());
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Hands out a few bytes of `input` at a time, then fails if `fail` is set.
+struct TrickleReader<'a> {
+    input: &'a [u8],
+    fail: bool,
+}
+
+impl AsyncRead for TrickleReader<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.input.is_empty() && self.fail {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::ConnectionReset,
+                "connection reset",
+            )));
+        }
+
+        let len = self.input.len().min(buf.remaining()).min(7);
+        buf.put_slice(&self.input[..len]);
+        self.input = &self.input[len..];
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn many_values() -> WeakDom {
+    WeakDom::new(InstanceBuilder::new("Folder").with_children(
+        (0..20).map(|i| InstanceBuilder::new("StringValue").with_property("Value", i.to_string())),
+    ))
+}
+
+/// Ensures that the document is written out as each instance is encoded,
+/// instead of all at once at the end.
+#[test]
+fn writes_as_it_goes() {
+    let tree = many_values();
+
+    let mut expected = Vec::new();
+    crate::to_writer_default(&mut expected, &tree, &[tree.root_ref()]).unwrap();
+
+    let mut writer = RecordingWriter::default();
+    block_on(crate::to_writer_async(
+        &mut writer,
+        &tree,
+        &[tree.root_ref()],
+        EncodeOptions::new(),
+    ))
+    .unwrap();
+
+    // One write for each of the 21 instances' properties, and more for the
+    // ends of the instances and the document.
+    assert!(writer.writes.len() > 21);
+    assert!(writer
+        .writes
+        .iter()
+        .all(|write| write.len() < expected.len() / 2));
+    assert_eq!(writer.writes.concat(), expected);
+}
+
+/// Ensures that a document arriving a few bytes at a time is decoded, and
+/// that a read failing partway through is an error.
+#[test]
+fn reads_as_it_goes() {
+    let tree = many_values();
+
+    let mut buffer = Vec::new();
+    crate::to_writer_default(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    let reader = TrickleReader {
+        input: &buffer,
+        fail: false,
+    };
+    let decoded = block_on(crate::from_reader_async(reader, DecodeOptions::new())).unwrap();
+    let folder = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(folder.children().len(), 20);
+
+    let reader = TrickleReader {
+        input: &buffer[..buffer.len() / 2],
+        fail: true,
+    };
+    let result = block_on(crate::from_reader_async(reader, DecodeOptions::new()));
+    assert!(result.is_err());
+}
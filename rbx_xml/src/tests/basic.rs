@@ -1,12 +1,19 @@
 //! Basic functionality tests
 
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
 use rbx_dom_weak::types::{
-    Attributes, BinaryString, BrickColor, Color3, ColorSequence, ColorSequenceKeypoint,
-    NumberRange, NumberSequence, NumberSequenceKeypoint, Rect, Tags, UDim, UDim2, UniqueId,
-    Variant, Vector2, Vector3,
+    Attributes, BinaryString, BrickColor, Color3, ColorSequence, ColorSequenceKeypoint, Enum, Font,
+    FontStyle, FontWeight, NumberRange, NumberSequence, NumberSequenceKeypoint, Rect, Ref,
+    SharedString, Tags, UDim, UDim2, UniqueId, Variant, Vector2, Vector3,
 };
 use rbx_dom_weak::{InstanceBuilder, WeakDom};
 
+use crate::{DomError, ErrorCode, ErrorLocation};
+
 #[test]
 fn with_bool() {
     let _ = env_logger::try_init();
@@ -263,3 +270,1818 @@ fn number_widening() {
         Some(&Variant::Float64(1337.0))
     );
 }
+
+#[test]
+fn progress_and_cancel() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="Folder" referent="a">
+                <Item class="Folder" referent="b"></Item>
+                <Item class="Folder" referent="c"></Item>
+            </Item>
+        </roblox>
+    "#;
+
+    let updates = Arc::new(Mutex::new(Vec::new()));
+    let options = crate::DecodeOptions::new().on_progress({
+        let updates = Arc::clone(&updates);
+        move |progress| updates.lock().unwrap().push(*progress)
+    });
+    crate::from_str(document, options).unwrap();
+
+    let updates = updates.lock().unwrap();
+    let counts: Vec<_> = updates
+        .iter()
+        .map(|progress| progress.instances_decoded())
+        .collect();
+    assert_eq!(counts, [1, 2, 3]);
+    assert!(updates
+        .iter()
+        .all(|progress| progress.bytes_read() <= document.len() as u64));
+
+    let token = Arc::new(AtomicBool::new(false));
+    let options = crate::DecodeOptions::new()
+        .cancel_token(Arc::clone(&token))
+        .on_progress({
+            let token = Arc::clone(&token);
+            move |_| token.store(true, Ordering::Relaxed)
+        });
+
+    let error = crate::from_str(document, options).unwrap_err();
+    assert!(error.is_cancelled());
+    assert!(!crate::from_str_default("<roblox>")
+        .unwrap_err()
+        .is_cancelled());
+}
+
+#[test]
+fn unknown_element_round_trip() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="Folder" referent="folder">
+                <Properties>
+                    <string name="Name">Folder</string>
+                    <string name="NotARealProperty">Hello</string>
+                    <FancyNewType name="Fancy" level="3"><X>1</X><Y>2</Y></FancyNewType>
+                </Properties>
+                <FancyNewSection>Some data</FancyNewSection>
+            </Item>
+        </roblox>
+    "#;
+
+    assert!(crate::from_str_default(document).is_err());
+
+    let options = crate::DecodeOptions::new().preserve_unknown_elements(true);
+    let (dom, report) = crate::from_str_with_report(document, options.clone()).unwrap();
+    let folder_ref = dom.root().children()[0];
+
+    let elements = report.unknown_elements();
+    assert_eq!(elements.len(), 3);
+    assert!(elements
+        .iter()
+        .all(|element| element.referent() == folder_ref));
+    assert_eq!(
+        elements[0].xml(),
+        r#"<string name="NotARealProperty">Hello</string>"#
+    );
+    assert_eq!(
+        elements[1].xml(),
+        r#"<FancyNewType name="Fancy" level="3"><X>1</X><Y>2</Y></FancyNewType>"#
+    );
+    assert_eq!(
+        elements[2].xml(),
+        "<FancyNewSection>Some data</FancyNewSection>"
+    );
+    assert_eq!(elements[2].kind(), crate::UnknownElementKind::Item);
+
+    let mut encoded = Vec::new();
+    crate::to_writer(
+        &mut encoded,
+        &dom,
+        &[folder_ref],
+        crate::EncodeOptions::new().unknown_elements(elements.to_vec()),
+    )
+    .unwrap();
+
+    let (round_trip, round_trip_report) =
+        crate::from_reader_with_report(encoded.as_slice(), options).unwrap();
+    let round_trip_xml: Vec<_> = round_trip_report
+        .unknown_elements()
+        .iter()
+        .map(|element| (element.kind(), element.xml()))
+        .collect();
+    let original_xml: Vec<_> = elements
+        .iter()
+        .map(|element| (element.kind(), element.xml()))
+        .collect();
+    assert_eq!(round_trip_xml, original_xml);
+
+    let folder = round_trip
+        .get_by_ref(round_trip.root().children()[0])
+        .unwrap();
+    assert_eq!(folder.name, "Folder");
+}
+
+#[derive(Default)]
+struct RecordingHandler {
+    events: Vec<String>,
+}
+
+impl crate::StreamHandler for RecordingHandler {
+    fn start_instance(&mut self, class: &str, referent: Option<&str>) {
+        self.events.push(format!("start {} {:?}", class, referent));
+    }
+
+    fn property(&mut self, name: &str, value: crate::StreamValue) {
+        self.events.push(format!("property {} {:?}", name, value));
+    }
+
+    fn end_instance(&mut self) {
+        self.events.push("end".to_owned());
+    }
+
+    fn shared_string(&mut self, hash: &str, value: rbx_dom_weak::types::SharedString) {
+        self.events
+            .push(format!("shared string {} {:?}", hash, value.data()));
+    }
+}
+
+#[test]
+fn stream_events() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="Folder" referent="folder">
+                <Properties>
+                    <string name="Name">Scripts</string>
+                </Properties>
+                <Item class="Script" referent="script">
+                    <Properties>
+                        <ProtectedString name="Source">print("Hello")</ProtectedString>
+                        <Ref name="Target">folder</Ref>
+                        <Ref name="Nothing">null</Ref>
+                        <SharedString name="Data">aGFzaA==</SharedString>
+                    </Properties>
+                </Item>
+            </Item>
+            <SharedStrings>
+                <SharedString md5="aGFzaA==">SGVsbG8=</SharedString>
+            </SharedStrings>
+        </roblox>
+    "#;
+
+    let mut handler = RecordingHandler::default();
+    let options =
+        crate::DecodeOptions::new().property_behavior(crate::DecodePropertyBehavior::NoReflection);
+    crate::stream_from_reader(document.as_bytes(), options, &mut handler).unwrap();
+
+    assert_eq!(
+        handler.events,
+        [
+            r#"start Folder Some("folder")"#,
+            r#"property Name Value(String("Scripts"))"#,
+            r#"start Script Some("script")"#,
+            r#"property Source Value(String("print(\"Hello\")"))"#,
+            r#"property Target Ref(Some("folder"))"#,
+            "property Nothing Ref(None)",
+            r#"property Data SharedString("aGFzaA==")"#,
+            "end",
+            "end",
+            "shared string aGFzaA== [72, 101, 108, 108, 111]",
+        ]
+    );
+}
+
+#[test]
+fn stream_writer_round_trip() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="Folder" referent="folder">
+                <Properties>
+                    <string name="Name">Scripts</string>
+                </Properties>
+                <Item class="ObjectValue" referent="value">
+                    <Properties>
+                        <Ref name="Value">folder</Ref>
+                        <SharedString name="Data">aGFzaA==</SharedString>
+                    </Properties>
+                </Item>
+            </Item>
+            <SharedStrings>
+                <SharedString md5="aGFzaA==">SGVsbG8=</SharedString>
+            </SharedStrings>
+        </roblox>
+    "#;
+
+    let options =
+        crate::DecodeOptions::new().property_behavior(crate::DecodePropertyBehavior::NoReflection);
+
+    let mut output = Vec::new();
+    let mut writer = crate::StreamWriter::new(&mut output, crate::EncodeOptions::new());
+    crate::stream_from_reader(document.as_bytes(), options.clone(), &mut writer).unwrap();
+    writer.finish().unwrap();
+
+    let mut expected = RecordingHandler::default();
+    crate::stream_from_reader(document.as_bytes(), options.clone(), &mut expected).unwrap();
+
+    let mut actual = RecordingHandler::default();
+    crate::stream_from_reader(output.as_slice(), options, &mut actual).unwrap();
+
+    assert_eq!(actual.events, expected.events);
+}
+
+#[test]
+fn stream_writer_invalid_events() {
+    let _ = env_logger::try_init();
+
+    let mut writer = crate::StreamWriter::new(Vec::new(), crate::EncodeOptions::new());
+    crate::StreamHandler::start_instance(&mut writer, "Folder", None);
+    assert!(writer.finish().is_err());
+
+    let mut writer = crate::StreamWriter::new(Vec::new(), crate::EncodeOptions::new());
+    crate::StreamHandler::end_instance(&mut writer);
+    assert!(writer.finish().is_err());
+
+    let mut writer = crate::StreamWriter::new(Vec::new(), crate::EncodeOptions::new());
+    crate::StreamHandler::property(
+        &mut writer,
+        "Value",
+        crate::StreamValue::Value(Variant::Bool(true)),
+    );
+    assert!(writer.finish().is_err());
+}
+
+/// A codec for a made-up `Token` type, which holds a string.
+struct TokenCodec;
+
+impl crate::PropertyCodec for TokenCodec {
+    fn decode(&self, xml: &str) -> Result<Variant, crate::CodecError> {
+        let start = xml.find('>').ok_or("missing start tag")? + 1;
+        let end = xml.rfind("</").ok_or("missing end tag")?;
+
+        Ok(Variant::BinaryString(xml.as_bytes()[start..end].into()))
+    }
+
+    fn encode(&self, name: &str, value: &Variant) -> Result<String, crate::CodecError> {
+        match value {
+            Variant::BinaryString(value) => Ok(format!(
+                r#"<Token name="{}">{}</Token>"#,
+                name,
+                std::str::from_utf8(value.as_ref())?
+            )),
+            _ => Err("tokens must be BinaryStrings".into()),
+        }
+    }
+}
+
+#[test]
+fn custom_codecs() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="Folder" referent="folder">
+                <Properties>
+                    <string name="Name">Folder</string>
+                    <Token name="Secret">hunter2</Token>
+                    <string name="Label">hello</string>
+                </Properties>
+            </Item>
+        </roblox>
+    "#;
+
+    let codecs = crate::Codecs::new()
+        .xml_type(
+            "Token",
+            rbx_dom_weak::types::VariantType::BinaryString,
+            TokenCodec,
+        )
+        .property("Folder", "Label", TokenCodec);
+
+    let decode_options = crate::DecodeOptions::new()
+        .property_behavior(crate::DecodePropertyBehavior::ReadUnknown)
+        .codecs(codecs.clone());
+    let tree = crate::from_str(document, decode_options).unwrap();
+
+    let folder = tree.get_by_ref(tree.root().children()[0]).unwrap();
+    assert_eq!(
+        folder.properties.get("Secret"),
+        Some(&Variant::BinaryString(b"hunter2".to_vec().into()))
+    );
+    assert_eq!(
+        folder.properties.get("Label"),
+        Some(&Variant::BinaryString(b"hello".to_vec().into()))
+    );
+
+    let mut output = Vec::new();
+    let encode_options = crate::EncodeOptions::new()
+        .property_behavior(crate::EncodePropertyBehavior::WriteUnknown)
+        .codecs(codecs);
+    crate::to_writer(&mut output, &tree, tree.root().children(), encode_options).unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains(r#"<Token name="Secret">hunter2</Token>"#));
+    assert!(output.contains(r#"<Token name="Label">hello</Token>"#));
+
+    // Errors returned by codecs are passed along.
+    let mut tree = rbx_dom_weak::WeakDom::new(InstanceBuilder::new("DataModel"));
+    let folder = tree.insert(
+        tree.root_ref(),
+        InstanceBuilder::new("Folder").with_property("Secret", 5),
+    );
+    let codecs = crate::Codecs::new().property("Folder", "Secret", TokenCodec);
+    let encode_options = crate::EncodeOptions::new().codecs(codecs);
+    assert!(crate::to_writer(Vec::new(), &tree, &[folder], encode_options).is_err());
+}
+
+#[test]
+fn recover_malformed() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="Folder" referent="folder">
+                <Properties>
+                    <string name="Name">Kept</string>
+                    <bool>true</bool>
+                    <int name="Broken">not a number</int>
+                </Properties>
+                <Item referent="classless">
+                    <Properties>
+                        <string name="Name">No class</string>
+                    </Properties>
+                </Item>
+                <Item class="ObjectValue" referent="skipped">
+                    <Properties>
+                        <int name="Name">5</int>
+                    </Properties>
+                </Item>
+                <Item class="ObjectValue" referent="value">
+                    <Properties>
+                        <string name="Name">Value</string>
+                        <Ref name="Value">skipped</Ref>
+                    </Properties>
+                </Item>
+            </Item>
+        </roblox>
+    "#;
+
+    let options = crate::DecodeOptions::new()
+        .property_behavior(crate::DecodePropertyBehavior::ReadUnknown)
+        .recover(true);
+    let (tree, report) = crate::from_str_with_report(document, options.clone()).unwrap();
+
+    let folder = tree.get_by_ref(tree.root().children()[0]).unwrap();
+    assert_eq!(folder.name, "Kept");
+    assert_eq!(folder.properties.get("Broken"), None);
+    assert_eq!(folder.children().len(), 1);
+
+    // Refs to instances that were skipped are null.
+    let value = tree.get_by_ref(folder.children()[0]).unwrap();
+    assert_eq!(value.name, "Value");
+    assert_eq!(
+        value.properties.get("Value"),
+        Some(&Variant::Ref(rbx_dom_weak::types::Ref::none()))
+    );
+
+    let skipped: Vec<_> = report
+        .warnings()
+        .iter()
+        .map(|warning| (warning.skipped().clone(), warning.line()))
+        .collect();
+    assert_eq!(
+        skipped,
+        [
+            (crate::Skipped::Element, 6),
+            (
+                crate::Skipped::Property {
+                    class_name: "Folder".to_owned(),
+                    property_name: "Broken".to_owned(),
+                },
+                7
+            ),
+            (crate::Skipped::Instance { class_name: None }, 9),
+            (
+                crate::Skipped::Instance {
+                    class_name: Some("ObjectValue".to_owned()),
+                },
+                18
+            ),
+        ]
+    );
+
+    // Without recovery mode, the first problem is an error.
+    let error = crate::from_str(document, options.recover(false)).unwrap_err();
+    assert_eq!(error.line(), 6);
+    assert_eq!(error.code(), ErrorCode::MissingAttribute);
+}
+
+#[test]
+fn error_details() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="IntValue" referent="value">
+                <Properties>
+                    <int name="Value">not a number</int>
+                </Properties>
+            </Item>
+        </roblox>
+    "#;
+
+    let error = crate::from_str_default(document).unwrap_err();
+    assert_eq!(error.code(), ErrorCode::InvalidPropertyValue);
+    assert_eq!(
+        DomError::location(&error),
+        Some(ErrorLocation::LineColumn {
+            line: error.line(),
+            column: error.column(),
+        })
+    );
+    assert!(error.referent().is_some());
+    assert_eq!(error.class_name(), Some("IntValue"));
+    assert_eq!(error.property_name(), Some("Value"));
+
+    // Errors outside of any property don't name one.
+    let error = crate::from_str_default(r#"<roblox version="3"></roblox>"#).unwrap_err();
+    assert_eq!(error.code(), ErrorCode::UnknownVersion);
+    assert_eq!(error.referent(), None);
+    assert_eq!(error.property_name(), None);
+}
+
+#[test]
+fn recover_truncated() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="Folder" referent="folder">
+                <Properties>
+                    <string name="Name">Outer</string>
+                </Properties>
+                <Item class="Folder" referent="inner">
+                    <Properties>
+                        <string name="Name">Inner</string>
+    "#;
+
+    let options = crate::DecodeOptions::new().recover(true);
+    let (tree, report) = crate::from_str_with_report(document, options).unwrap();
+
+    let outer = tree.get_by_ref(tree.root().children()[0]).unwrap();
+    assert_eq!(outer.name, "Outer");
+
+    let inner = tree.get_by_ref(outer.children()[0]).unwrap();
+    assert_eq!(inner.name, "Inner");
+
+    assert_eq!(report.warnings().len(), 1);
+    assert_eq!(report.warnings()[0].skipped(), &crate::Skipped::Remainder);
+
+    assert!(crate::from_str_default(document).is_err());
+}
+
+#[test]
+fn encode_filters() {
+    let _ = env_logger::try_init();
+
+    let mut tree = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let model_ref = tree.insert(
+        tree.root_ref(),
+        InstanceBuilder::new("Model").with_name("Model"),
+    );
+    tree.insert(
+        model_ref,
+        InstanceBuilder::new("Script")
+            .with_name("Script")
+            .with_property("Source", "print('secret')")
+            .with_property("Disabled", true),
+    );
+    let camera = tree.insert(model_ref, InstanceBuilder::new("Camera"));
+    tree.insert(camera, InstanceBuilder::new("Folder").with_name("InCamera"));
+    tree.insert(
+        model_ref,
+        InstanceBuilder::new("Folder").with_name("Dropped"),
+    );
+
+    let options = crate::EncodeOptions::new()
+        .exclude_classes(["Camera"])
+        .exclude_properties(["Source"]);
+
+    let mut output = Vec::new();
+    crate::to_writer(&mut output, &tree, &[model_ref], options).unwrap();
+
+    let decoded = crate::from_reader_default(output.as_slice()).unwrap();
+    let model = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    let names: Vec<_> = model
+        .children()
+        .iter()
+        .map(|child| decoded.get_by_ref(*child).unwrap().name.as_str())
+        .collect();
+    assert_eq!(names, ["Script", "Dropped"]);
+
+    let script = decoded.get_by_ref(model.children()[0]).unwrap();
+    assert_eq!(script.properties.get("Source"), None);
+    assert_eq!(
+        script.properties.get("Disabled"),
+        Some(&Variant::Bool(true))
+    );
+
+    let options =
+        crate::EncodeOptions::new().instance_filter(|instance| instance.name != "Dropped");
+
+    let mut output = Vec::new();
+    crate::to_writer(&mut output, &tree, &[model_ref], options).unwrap();
+
+    let decoded = crate::from_reader_default(output.as_slice()).unwrap();
+    let model = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(model.children().len(), 2);
+}
+
+#[test]
+fn external_references() {
+    let _ = env_logger::try_init();
+
+    let mut tree = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let outside = tree.insert(tree.root_ref(), InstanceBuilder::new("Part"));
+    let model_ref = tree.insert(
+        tree.root_ref(),
+        InstanceBuilder::new("Model").with_property("PrimaryPart", outside),
+    );
+
+    let encode = |handling| {
+        let mut output = Vec::new();
+        let options = crate::EncodeOptions::new().external_references(handling);
+        crate::to_writer_with_report(&mut output, &tree, &[model_ref], options)
+            .map(|report| (output, report))
+    };
+
+    let (output, report) = encode(crate::ExternalReferences::Null).unwrap();
+    assert_eq!(report.external_references().len(), 1);
+    assert_eq!(report.external_references()[0].instance(), model_ref);
+    assert_eq!(
+        report.external_references()[0].property_name(),
+        "PrimaryPart"
+    );
+    assert_eq!(report.external_references()[0].target(), outside);
+
+    let decoded = crate::from_reader_default(output.as_slice()).unwrap();
+    let model = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(
+        model.properties.get("PrimaryPart"),
+        Some(&Variant::Ref(Ref::none()))
+    );
+
+    let (output, report) = encode(crate::ExternalReferences::Drop).unwrap();
+    assert_eq!(report.external_references().len(), 1);
+
+    let decoded = crate::from_reader_default(output.as_slice()).unwrap();
+    let model = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(model.properties.get("PrimaryPart"), None);
+
+    let error = encode(crate::ExternalReferences::Error).unwrap_err();
+    assert_eq!(error.code(), ErrorCode::ExternalReference);
+    assert_eq!(error.referent(), Some(model_ref));
+    assert_eq!(error.class_name(), Some("Model"));
+    assert_eq!(error.property_name(), Some("PrimaryPart"));
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="Model" referent="RBX1">
+                <Properties>
+                    <Ref name="PrimaryPart">RBX2</Ref>
+                </Properties>
+            </Item>
+        </roblox>
+    "#;
+
+    let decode = |handling| {
+        let options = crate::DecodeOptions::new().external_references(handling);
+        crate::from_str_with_report(document, options)
+    };
+
+    let (decoded, report) = decode(crate::ExternalReferences::Null).unwrap();
+    let model_ref = decoded.root().children()[0];
+    let model = decoded.get_by_ref(model_ref).unwrap();
+    assert_eq!(
+        model.properties.get("PrimaryPart"),
+        Some(&Variant::Ref(Ref::none()))
+    );
+    assert_eq!(report.unresolved_references().len(), 1);
+    assert_eq!(report.unresolved_references()[0].instance(), model_ref);
+    assert_eq!(
+        report.unresolved_references()[0].property_name(),
+        "PrimaryPart"
+    );
+    assert_eq!(report.unresolved_references()[0].referent(), "RBX2");
+
+    let (decoded, report) = decode(crate::ExternalReferences::Drop).unwrap();
+    let model = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(model.properties.get("PrimaryPart"), None);
+    assert_eq!(report.unresolved_references().len(), 1);
+
+    assert!(decode(crate::ExternalReferences::Error).is_err());
+}
+
+#[test]
+fn script_source_escaping() {
+    let _ = env_logger::try_init();
+
+    let source = "if a < b and c then\r\n\tprint(t[u[1]]>0)\r\nend\u{1}";
+
+    let mut tree = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let script_ref = tree.insert(
+        tree.root_ref(),
+        InstanceBuilder::new("ModuleScript").with_property("Source", source),
+    );
+
+    let options = crate::EncodeOptions::new()
+        .script_source_style(crate::ScriptSourceStyle::Cdata)
+        .preserve_line_endings(true)
+        .control_characters(crate::ControlCharacters::Remove);
+
+    let mut output = Vec::new();
+    crate::to_writer(&mut output, &tree, &[script_ref], options).unwrap();
+    let xml = String::from_utf8(output).unwrap();
+
+    assert!(xml.contains(concat!(
+        r#"<ProtectedString name="Source"><![CDATA[if a < b and c then]]>&#13;<![CDATA["#,
+        "\n\tprint(t[u[1]]]]><![CDATA[>0)]]>&#13;<![CDATA[\nend]]></ProtectedString>",
+    )));
+
+    let decoded = crate::from_str_default(&xml).unwrap();
+    let script = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(
+        script.properties.get("Source"),
+        Some(&Variant::String(source.replace('\u{1}', "")))
+    );
+
+    let options = crate::EncodeOptions::new()
+        .preserve_line_endings(true)
+        .control_characters(crate::ControlCharacters::Escape);
+
+    let mut output = Vec::new();
+    crate::to_writer(&mut output, &tree, &[script_ref], options).unwrap();
+    let xml = String::from_utf8(output).unwrap();
+
+    assert!(xml.contains(concat!(
+        r#"<string name="Source">if a &lt; b and c then&#13;"#,
+        "\n\tprint(t[u[1]]&gt;0)&#13;\nend&#1;</string>",
+    )));
+}
+
+#[test]
+fn tolerant_parsing() {
+    let _ = env_logger::try_init();
+
+    let document = concat!(
+        "\u{feff}\r\n",
+        r#"<?xml version="1.0" encoding="utf-8"?>
+        <roblox version="4">
+            <External>null</External>
+            <External>nil</External>
+            <Item class="Part">
+                <External>nil</External>
+                <Properties>
+                    <float name="Transparency">0.5</float>
+                    <float name="Transparency">
+                        0.25
+                    </float>
+                    <bool name="Anchored"> true </bool>
+                    <token name="Material">	256
+                    </token>
+                    <Vector3 name="size">
+                        <X> 1 </X>
+                        <Y>2
+                        </Y>
+                        <Z>	3</Z>
+                    </Vector3>
+                </Properties>
+                <Item class="Folder">
+                    <Properties>
+                        <string name="Name">Child</string>
+                    </Properties>
+                </Item>
+            </Item>
+        </roblox>
+        "#
+    );
+
+    let tree = crate::from_str_default(document).unwrap();
+
+    let part = tree.get_by_ref(tree.root().children()[0]).unwrap();
+    assert_eq!(
+        part.properties.get("Transparency"),
+        Some(&Variant::Float32(0.25))
+    );
+    assert_eq!(part.properties.get("Anchored"), Some(&Variant::Bool(true)));
+    assert_eq!(
+        part.properties.get("Material"),
+        Some(&Variant::Enum(Enum::from_u32(256)))
+    );
+    assert_eq!(
+        part.properties.get("Size"),
+        Some(&Variant::Vector3(Vector3::new(1.0, 2.0, 3.0)))
+    );
+
+    let child = tree.get_by_ref(part.children()[0]).unwrap();
+    assert_eq!(child.name, "Child");
+}
+
+#[test]
+fn legacy_compatibility_target() {
+    let _ = env_logger::try_init();
+
+    let mut attributes = Attributes::new();
+    attributes.insert("Color".to_owned(), Variant::Bool(true));
+
+    let font = Font::new(
+        "rbxasset://fonts/families/SourceSansPro.json",
+        FontWeight::Bold,
+        FontStyle::Normal,
+    );
+    let custom_font = Font::regular("rbxassetid://12345");
+
+    let mut tree = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let label_ref = tree.insert(
+        tree.root_ref(),
+        InstanceBuilder::new("TextLabel")
+            .with_property("FontFace", font.clone())
+            .with_property("Attributes", attributes),
+    );
+    let custom_ref = tree.insert(
+        tree.root_ref(),
+        InstanceBuilder::new("TextButton").with_property("FontFace", custom_font.clone()),
+    );
+
+    let options =
+        crate::EncodeOptions::new().compatibility_target(crate::CompatibilityTarget::Legacy);
+
+    let mut output = Vec::new();
+    crate::to_writer(&mut output, &tree, &[label_ref, custom_ref], options).unwrap();
+    let xml = String::from_utf8(output).unwrap();
+
+    assert!(xml.contains(r#"<token name="Font">4</token>"#));
+    assert!(!xml.contains("AttributesSerialize"));
+    assert_eq!(xml.matches("<Font name=\"FontFace\">").count(), 1);
+
+    let decoded = crate::from_str_default(&xml).unwrap();
+    let children = decoded.root().children();
+
+    let label = decoded.get_by_ref(children[0]).unwrap();
+    assert_eq!(label.properties.get("FontFace"), Some(&Variant::Font(font)));
+    assert_eq!(label.properties.get("Attributes"), None);
+
+    let button = decoded.get_by_ref(children[1]).unwrap();
+    assert_eq!(
+        button.properties.get("FontFace"),
+        Some(&Variant::Font(custom_font))
+    );
+}
+
+#[test]
+fn unmapped_property_names() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="Part" referent="part">
+                <Properties>
+                    <Vector3 name="size">
+                        <X>1</X>
+                        <Y>2</Y>
+                        <Z>3</Z>
+                    </Vector3>
+                    <Color3uint8 name="Color3uint8">4278190335</Color3uint8>
+                </Properties>
+            </Item>
+            <Item class="TextLabel" referent="label">
+                <Properties>
+                    <token name="Font">4</token>
+                </Properties>
+            </Item>
+        </roblox>
+    "#;
+
+    let decode = |map_property_names| {
+        let options = crate::DecodeOptions::new().map_property_names(map_property_names);
+        crate::from_str(document, options).unwrap()
+    };
+
+    let mapped = decode(true);
+    let part = mapped.get_by_ref(mapped.root().children()[0]).unwrap();
+    assert!(part.properties.contains_key("Size"));
+    assert!(part.properties.contains_key("Color"));
+    let label = mapped.get_by_ref(mapped.root().children()[1]).unwrap();
+    assert!(label.properties.contains_key("FontFace"));
+
+    let unmapped = decode(false);
+    let part = unmapped.get_by_ref(unmapped.root().children()[0]).unwrap();
+    assert_eq!(
+        part.properties.get("size"),
+        Some(&Variant::Vector3(Vector3::new(1.0, 2.0, 3.0)))
+    );
+    assert!(part.properties.contains_key("Color3uint8"));
+    assert!(!part.properties.contains_key("Color"));
+    let label = unmapped.get_by_ref(unmapped.root().children()[1]).unwrap();
+    assert_eq!(
+        label.properties.get("Font"),
+        Some(&Variant::Enum(Enum::from_u32(4)))
+    );
+    assert!(!label.properties.contains_key("FontFace"));
+
+    let options = crate::EncodeOptions::new().map_property_names(false);
+    let mut output = Vec::new();
+    crate::to_writer(&mut output, &unmapped, unmapped.root().children(), options).unwrap();
+    let xml = String::from_utf8(output).unwrap();
+
+    assert!(xml.contains(r#"<Vector3 name="size">"#));
+    assert!(xml.contains(r#"<Color3uint8 name="Color3uint8">"#));
+    assert!(xml.contains(r#"<token name="Font">4</token>"#));
+}
+
+#[test]
+fn migrate_properties() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="ScreenGui" referent="gui">
+                <Properties>
+                    <bool name="IgnoreGuiInset">true</bool>
+                </Properties>
+            </Item>
+        </roblox>
+    "#;
+
+    let migrated = crate::from_str_default(document).unwrap();
+    let gui = migrated.get_by_ref(migrated.root().children()[0]).unwrap();
+    assert_eq!(
+        gui.properties.get("ScreenInsets"),
+        Some(&Variant::Enum(Enum::from_u32(1)))
+    );
+    assert!(!gui.properties.contains_key("IgnoreGuiInset"));
+
+    let options = crate::DecodeOptions::new().migrate_properties(false);
+    let unmigrated = crate::from_str(document, options).unwrap();
+    let gui = unmigrated
+        .get_by_ref(unmigrated.root().children()[0])
+        .unwrap();
+    assert_eq!(
+        gui.properties.get("IgnoreGuiInset"),
+        Some(&Variant::Bool(true))
+    );
+    assert!(!gui.properties.contains_key("ScreenInsets"));
+
+    let database = rbx_reflection_database::get();
+    assert_eq!(
+        database
+            .migrate_property("ScreenGui", "IgnoreGuiInset", &Variant::Bool(true))
+            .unwrap(),
+        Some(("ScreenInsets", Variant::Enum(Enum::from_u32(1))))
+    );
+}
+
+#[test]
+fn migrate_body_colors() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="BodyColors" referent="colors">
+                <Properties>
+                    <int name="HeadColor">21</int>
+                </Properties>
+            </Item>
+        </roblox>
+    "#;
+
+    let migrated = crate::from_str_default(document).unwrap();
+    let colors = migrated.get_by_ref(migrated.root().children()[0]).unwrap();
+    assert_eq!(
+        colors.properties.get("HeadColor3"),
+        Some(&Variant::Color3(Color3::new(
+            196.0 / 255.0,
+            40.0 / 255.0,
+            28.0 / 255.0
+        )))
+    );
+    assert!(!colors.properties.contains_key("HeadColor"));
+}
+
+#[test]
+fn custom_reflection_database() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="Part" referent="RBX1">
+                <Properties>
+                    <Vector3 name="size">
+                        <X>1</X>
+                        <Y>2</Y>
+                        <Z>3</Z>
+                    </Vector3>
+                </Properties>
+            </Item>
+        </roblox>
+    "#;
+
+    let mut database = rbx_reflection::ReflectionDatabase::new();
+    database.classes.insert(
+        "Instance".into(),
+        rbx_reflection::ClassDescriptor::new("Instance"),
+    );
+    let database = Box::leak(Box::new(database));
+
+    let read_part = |options| {
+        let tree = crate::from_str(document, options).unwrap();
+        let part = tree.get_by_ref(tree.root().children()[0]).unwrap();
+        part.properties.clone()
+    };
+
+    let bundled = read_part(crate::DecodeOptions::new());
+    assert!(bundled.contains_key("Size"));
+
+    // Part.size isn't known to the custom database, so it's skipped.
+    let custom = read_part(crate::DecodeOptions::new().reflection_database(database));
+    assert!(!custom.contains_key("Size"));
+    assert!(!custom.contains_key("size"));
+}
+
+#[test]
+fn shared_strings() {
+    let _ = env_logger::try_init();
+
+    let mesh_data = SharedString::new(b"mesh data".to_vec());
+
+    let mut tree = WeakDom::new(InstanceBuilder::new("DataModel"));
+    for _ in 0..3 {
+        tree.insert(
+            tree.root_ref(),
+            InstanceBuilder::new("UnionOperation").with_property("MeshData2", mesh_data.clone()),
+        );
+    }
+
+    let mut output = Vec::new();
+    crate::to_writer_default(&mut output, &tree, tree.root().children()).unwrap();
+    let xml = String::from_utf8(output).unwrap();
+    assert_eq!(xml.matches("<SharedString md5=").count(), 1);
+
+    let decoded = crate::from_str_default(&xml).unwrap();
+    for child in decoded.root().children() {
+        let instance = decoded.get_by_ref(*child).unwrap();
+        assert_eq!(
+            instance.properties.get("MeshData2"),
+            Some(&Variant::SharedString(mesh_data.clone()))
+        );
+    }
+
+    // Blobs written inline by older versions of Roblox become SharedStrings.
+    let document = r#"
+        <roblox version="4">
+            <Item class="UnionOperation">
+                <Properties>
+                    <BinaryString name="MeshData2">bWVzaCBkYXRh</BinaryString>
+                </Properties>
+            </Item>
+        </roblox>
+    "#;
+    let decoded = crate::from_str_default(document).unwrap();
+    let union = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(
+        union.properties.get("MeshData2"),
+        Some(&Variant::SharedString(mesh_data))
+    );
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="UnionOperation">
+                <Properties>
+                    <SharedString name="MeshData2">missing</SharedString>
+                </Properties>
+            </Item>
+            <SharedStrings>
+                <SharedString md5="other">bWVzaCBkYXRh</SharedString>
+            </SharedStrings>
+        </roblox>
+    "#;
+    assert!(crate::from_str_default(document).is_err());
+
+    let options = crate::DecodeOptions::new().recover(true);
+    let (decoded, report) = crate::from_str_with_report(document, options).unwrap();
+    let union = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(union.properties.get("MeshData2"), None);
+    assert_eq!(report.warnings().len(), 1);
+}
+
+#[test]
+fn attributes_on_unknown_classes() {
+    let _ = env_logger::try_init();
+
+    let mut attributes = Attributes::new();
+    attributes.insert("Health".to_owned(), Variant::Float64(100.0));
+
+    let mut tree = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let instance_ref = tree.insert(
+        tree.root_ref(),
+        InstanceBuilder::new("ClassFromTheFuture")
+            .with_property("Attributes", attributes.clone())
+            .with_property("Tags", Tags::from(vec!["Enemy".to_owned()])),
+    );
+
+    let mut output = Vec::new();
+    crate::to_writer_default(&mut output, &tree, &[instance_ref]).unwrap();
+    let xml = String::from_utf8(output).unwrap();
+    assert!(xml.contains(r#"<BinaryString name="AttributesSerialize">"#));
+
+    let decoded = crate::from_str_default(&xml).unwrap();
+    let instance = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(
+        instance.properties.get("Attributes"),
+        Some(&Variant::Attributes(attributes))
+    );
+    assert!(!instance.properties.contains_key("AttributesSerialize"));
+    assert!(instance.properties.contains_key("Tags"));
+}
+
+#[test]
+fn default_properties() {
+    let _ = env_logger::try_init();
+
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder").with_child(
+            InstanceBuilder::new("Part")
+                .with_name("Floor")
+                .with_property("Anchored", true)
+                .with_property("CanCollide", true)
+                .with_property("Transparency", 0.0f32)
+                .with_property("Size", Vector3::new(10.0, 1.0, 10.0)),
+        ),
+    );
+
+    let encode = |include_default_properties| {
+        let options =
+            crate::EncodeOptions::new().include_default_properties(include_default_properties);
+        let mut output = Vec::new();
+        crate::to_writer(&mut output, &tree, tree.root().children(), options).unwrap();
+        String::from_utf8(output).unwrap()
+    };
+
+    let full = encode(true);
+    let stripped = encode(false);
+    assert!(full.contains(r#"name="CanCollide""#));
+    assert!(full.contains(r#"name="Transparency""#));
+    assert!(!stripped.contains(r#"name="CanCollide""#));
+    assert!(!stripped.contains(r#"name="Transparency""#));
+    assert!(stripped.contains(r#"name="Anchored""#));
+    assert!(stripped.contains(r#"name="size""#));
+    assert!(stripped.contains("Floor"));
+
+    let decode = |document: &str| {
+        let options = crate::DecodeOptions::new().apply_default_properties(true);
+        crate::from_str(document, options).unwrap()
+    };
+
+    let from_full = decode(&full);
+    let from_stripped = decode(&stripped);
+    let full_part = from_full
+        .get_by_ref(from_full.root().children()[0])
+        .unwrap();
+    let stripped_part = from_stripped
+        .get_by_ref(from_stripped.root().children()[0])
+        .unwrap();
+
+    assert_eq!(stripped_part.name, "Floor");
+    assert_eq!(stripped_part.properties, full_part.properties);
+    assert_eq!(
+        stripped_part.properties.get("CanCollide"),
+        Some(&Variant::Bool(true))
+    );
+    assert_eq!(
+        stripped_part.properties.get("Anchored"),
+        Some(&Variant::Bool(true))
+    );
+    assert!(stripped_part.properties.contains_key("Material"));
+    assert!(!stripped_part.properties.contains_key("Name"));
+
+    // Without applying defaults, only the properties in the document are read.
+    let plain = crate::from_str_default(&stripped).unwrap();
+    let plain_part = plain.get_by_ref(plain.root().children()[0]).unwrap();
+    assert!(!plain_part.properties.contains_key("CanCollide"));
+}
+
+#[test]
+fn referent_styles() {
+    let _ = env_logger::try_init();
+
+    let unique_id = UniqueId::new(7, 1_000, 0x1234_abcd);
+
+    let mut tree = WeakDom::new(InstanceBuilder::new("Folder"));
+    let root_ref = tree.root_ref();
+    tree.insert(root_ref, InstanceBuilder::new("Part").with_name("Part"));
+    let second = tree.insert(root_ref, InstanceBuilder::new("Part").with_name("Part"));
+    tree.insert(
+        root_ref,
+        InstanceBuilder::new("ObjectValue")
+            .with_property("Value", second)
+            .with_property("UniqueId", unique_id),
+    );
+
+    let encode = |tree: &WeakDom, referent_style| {
+        let options = crate::EncodeOptions::new().referent_style(referent_style);
+        let mut output = Vec::new();
+        crate::to_writer(&mut output, tree, tree.root().children(), options).unwrap();
+        String::from_utf8(output).unwrap()
+    };
+
+    let referents = |document: &str| -> Vec<String> {
+        document
+            .split(r#"referent=""#)
+            .skip(1)
+            .map(|rest| rest[..rest.find('"').unwrap()].to_owned())
+            .collect()
+    };
+
+    let sequential = encode(&tree, crate::ReferentStyle::Sequential);
+    assert_eq!(referents(&sequential), ["0", "1", "2"]);
+
+    let prefixed = encode(&tree, crate::ReferentStyle::Prefixed);
+    assert_eq!(
+        referents(&prefixed),
+        ["RBX0000000000", "RBX0000000001", "RBX0000000002"]
+    );
+
+    let stable = encode(&tree, crate::ReferentStyle::Stable);
+    assert_eq!(stable, encode(&tree, crate::ReferentStyle::Stable));
+
+    let stable_referents = referents(&stable);
+    assert_eq!(stable_referents[2], "RBX000000001234ABCD000003E800000007");
+    assert_ne!(stable_referents[0], stable_referents[1]);
+    assert!(stable.contains(&format!(
+        r#"<Ref name="Value">{}</Ref>"#,
+        stable_referents[1]
+    )));
+
+    // Adding an instance shouldn't change the referents of any others.
+    tree.insert(root_ref, InstanceBuilder::new("Folder"));
+    let mut changed = referents(&encode(&tree, crate::ReferentStyle::Stable));
+    changed.pop();
+    assert_eq!(changed, stable_referents);
+
+    let decoded = crate::from_str_default(&stable).unwrap();
+    let children = decoded.root().children();
+    let value = decoded.get_by_ref(children[2]).unwrap();
+    assert_eq!(
+        value.properties.get("Value"),
+        Some(&Variant::Ref(children[1]))
+    );
+}
+
+#[test]
+fn selectors() {
+    let _ = env_logger::try_init();
+
+    // Lighting's Brightness can't be decoded, which only matters if Lighting's
+    // properties are decoded.
+    let document = r#"
+        <roblox version="4">
+            <Item class="Workspace" referent="workspace">
+                <Properties>
+                    <string name="Name">Workspace</string>
+                </Properties>
+                <Item class="Model" referent="map">
+                    <Properties>
+                        <string name="Name">Map</string>
+                        <Ref name="PrimaryPart">floor</Ref>
+                    </Properties>
+                    <Item class="Part" referent="floor">
+                        <Properties>
+                            <string name="Name">Floor</string>
+                        </Properties>
+                    </Item>
+                    <Item class="ObjectValue" referent="value">
+                        <Properties>
+                            <string name="Name">Target</string>
+                            <Ref name="Value">workspace</Ref>
+                        </Properties>
+                    </Item>
+                </Item>
+                <Item class="Script" referent="script">
+                    <Properties>
+                        <string name="Name">Main</string>
+                    </Properties>
+                </Item>
+            </Item>
+            <Item class="Lighting" referent="lighting">
+                <Properties>
+                    <string name="Name">Lighting</string>
+                    <float name="Brightness">bright</float>
+                </Properties>
+            </Item>
+            <Item class="ReplicatedStorage" referent="storage">
+                <Properties>
+                    <string name="Name">ReplicatedStorage</string>
+                </Properties>
+                <Item class="Folder" referent="folder">
+                    <Properties>
+                        <string name="Name">Map</string>
+                    </Properties>
+                    <Item class="Script" referent="nested">
+                        <Properties>
+                            <string name="Name">Loader</string>
+                        </Properties>
+                    </Item>
+                </Item>
+            </Item>
+        </roblox>
+    "#;
+
+    let select_from = |document: &str, selector| {
+        let options = crate::DecodeOptions::new().select(selector);
+        crate::from_str_with_report(document, options).unwrap()
+    };
+    let select = |selector| select_from(document, selector);
+
+    // Predicates have to see every instance, so this one is decoded too.
+    let decodable = document.replace(r#"<float name="Brightness">bright</float>"#, "");
+
+    let names = |tree: &WeakDom| -> Vec<String> {
+        tree.root()
+            .children()
+            .iter()
+            .map(|id| tree.get_by_ref(*id).unwrap().name.clone())
+            .collect()
+    };
+
+    let (tree, report) = select(crate::Selector::path("Workspace/Map"));
+    assert_eq!(names(&tree), ["Map"]);
+
+    let map = tree.get_by_ref(tree.root().children()[0]).unwrap();
+    assert_eq!(map.class, "Model");
+    assert_eq!(map.children().len(), 2);
+    assert_eq!(
+        map.properties.get("PrimaryPart"),
+        Some(&Variant::Ref(map.children()[0]))
+    );
+
+    // Workspace wasn't selected, so the Ref pointing to it can't be resolved.
+    let target = tree.get_by_ref(map.children()[1]).unwrap();
+    assert_eq!(
+        target.properties.get("Value"),
+        Some(&Variant::Ref(Ref::none()))
+    );
+    assert_eq!(report.unresolved_references().len(), 1);
+    assert_eq!(report.unresolved_references()[0].referent(), "workspace");
+
+    let (tree, _) = select(crate::Selector::class("Script"));
+    assert_eq!(names(&tree), ["Main", "Loader"]);
+
+    let (tree, _) = select_from(
+        &decodable,
+        crate::Selector::predicate(|candidate| candidate.name() == "Map"),
+    );
+    assert_eq!(names(&tree), ["Map", "Map"]);
+
+    let (tree, _) = select_from(
+        &decodable,
+        crate::Selector::predicate(|candidate| {
+            candidate.path() == ["ReplicatedStorage", "Map", "Loader"]
+                && candidate.referent() == Some("nested")
+        }),
+    );
+    assert_eq!(names(&tree), ["Loader"]);
+
+    let (tree, _) = select(crate::Selector::path("Workspace/Missing"));
+    assert!(tree.root().children().is_empty());
+
+    // Without a selector, Lighting's properties are read.
+    assert!(crate::from_str_default(document).is_err());
+}
+
+#[test]
+fn preserved_comments() {
+    let _ = env_logger::try_init();
+
+    let document = r#"<!-- Generated by hand -->
+        <roblox version="4">
+            <?tool config="yes"?>
+            <Item class="Folder" referent="folder">
+                <Properties>
+                    <!-- The name of the folder -->
+                    <string name="Name">Assets</string>
+                    <!-- Nothing else -->
+                </Properties>
+                <!-- The first child -->
+                <Item class="StringValue" referent="value">
+                    <Properties>
+                        <string name="Value">hi</string>
+                    </Properties>
+                </Item>
+                <!-- The end of the folder -->
+            </Item>
+            <!-- The end of the document -->
+        </roblox>
+    "#;
+
+    let options = crate::DecodeOptions::new().preserve_comments(true);
+    let (tree, report) = crate::from_str_with_report(document, options).unwrap();
+
+    let folder = tree.root().children()[0];
+    let value = tree.get_by_ref(folder).unwrap().children()[0];
+    let comment = |text: &str| crate::AnnotationContent::Comment(text.to_owned());
+
+    let expected = [
+        (
+            crate::AnnotationPosition::Prolog,
+            comment(" Generated by hand "),
+        ),
+        (
+            crate::AnnotationPosition::BeforeInstance(folder),
+            crate::AnnotationContent::ProcessingInstruction {
+                name: "tool".to_owned(),
+                data: Some(r#"config="yes""#.to_owned()),
+            },
+        ),
+        (
+            crate::AnnotationPosition::BeforeProperty {
+                instance: folder,
+                property_name: "Name".to_owned(),
+            },
+            comment(" The name of the folder "),
+        ),
+        (
+            crate::AnnotationPosition::EndOfProperties(folder),
+            comment(" Nothing else "),
+        ),
+        (
+            crate::AnnotationPosition::BeforeInstance(value),
+            comment(" The first child "),
+        ),
+        (
+            crate::AnnotationPosition::EndOfInstance(folder),
+            comment(" The end of the folder "),
+        ),
+        (
+            crate::AnnotationPosition::EndOfDocument,
+            comment(" The end of the document "),
+        ),
+    ];
+
+    let positions = |annotations: &[crate::Annotation]| -> Vec<_> {
+        annotations
+            .iter()
+            .map(|annotation| (annotation.position().clone(), annotation.content().clone()))
+            .collect()
+    };
+    assert_eq!(positions(report.annotations()), expected);
+
+    let options = crate::EncodeOptions::new().annotations(report.annotations().to_vec());
+    let mut output = Vec::new();
+    crate::to_writer(&mut output, &tree, &[folder], options).unwrap();
+    let encoded = String::from_utf8(output).unwrap();
+
+    let prolog = encoded.find("<!-- Generated by hand -->").unwrap();
+    let name_comment = encoded.find("<!-- The name of the folder -->").unwrap();
+    let name = encoded
+        .find(r#"<string name="Name">Assets</string>"#)
+        .unwrap();
+    assert!(prolog < encoded.find("<roblox").unwrap());
+    assert!(name_comment < name);
+    assert!(encoded.contains(r#"<?tool config="yes"?>"#));
+
+    // Decoding the output again should find every annotation in the same
+    // place.
+    let options = crate::DecodeOptions::new().preserve_comments(true);
+    let (decoded, decoded_report) = crate::from_str_with_report(&encoded, options).unwrap();
+    assert_eq!(decoded_report.annotations().len(), expected.len());
+
+    let new_folder = decoded.root().children()[0];
+    for (annotation, (position, content)) in decoded_report.annotations().iter().zip(&expected) {
+        assert_eq!(annotation.content(), content);
+        assert_eq!(
+            annotation.position().instance().is_some(),
+            position.instance().is_some()
+        );
+    }
+    assert_eq!(
+        decoded_report.annotations()[2].position(),
+        &crate::AnnotationPosition::BeforeProperty {
+            instance: new_folder,
+            property_name: "Name".to_owned(),
+        }
+    );
+
+    // Without the option, comments and processing instructions are skipped.
+    let (_, report) = crate::from_str_with_report(document, crate::DecodeOptions::new()).unwrap();
+    assert!(report.annotations().is_empty());
+}
+
+#[test]
+fn validation() {
+    let _ = env_logger::try_init();
+
+    let document = r#"<roblox version="4">
+<Item class="Folder" referent="RBX1">
+<Properties>
+<string name="Name">Map</string>
+<Ref name="Unknown">RBX9</Ref>
+</Properties>
+<Item class="Part" referent="RBX2">
+<Properties>
+<string name="Name">Floor</string>
+<string name="Anchored">yes</string>
+<float name="Transparency">clear</float>
+<Wat name="Mystery">?</Wat>
+<SharedString name="PhysicsData">missing</SharedString>
+<token name="Material">1</token>
+</Properties>
+<Bogus />
+</Item>
+<Item class="NotARealClass" referent="RBX2">
+<Properties>
+<string name="Name">Thing</string>
+<int name="Whatever">1</int>
+</Properties>
+</Item>
+</Item>
+</roblox>"#;
+
+    let report = crate::validate_str(document);
+    assert!(!report.is_valid());
+
+    let found: Vec<_> = report
+        .diagnostics()
+        .iter()
+        .map(|diagnostic| {
+            (
+                diagnostic.code(),
+                diagnostic.severity(),
+                diagnostic.path(),
+                diagnostic.property_name(),
+                diagnostic.line(),
+                diagnostic.expected(),
+                diagnostic.actual(),
+            )
+        })
+        .collect();
+
+    use crate::{DiagnosticCode as Code, Severity::*};
+    assert_eq!(
+        found,
+        [
+            (
+                Code::UnknownProperty,
+                Warning,
+                "Map",
+                Some("Unknown"),
+                5,
+                None,
+                None
+            ),
+            (
+                Code::UnresolvedReferent,
+                Warning,
+                "Map",
+                Some("Unknown"),
+                5,
+                None,
+                Some("RBX9")
+            ),
+            (
+                Code::WrongPropertyType,
+                Error,
+                "Map/Floor",
+                Some("Anchored"),
+                10,
+                Some("Bool"),
+                Some("String")
+            ),
+            (
+                Code::InvalidValue,
+                Error,
+                "Map/Floor",
+                Some("Transparency"),
+                11,
+                None,
+                None
+            ),
+            (
+                Code::UnknownProperty,
+                Warning,
+                "Map/Floor",
+                Some("Mystery"),
+                12,
+                None,
+                None
+            ),
+            (
+                Code::UnknownType,
+                Warning,
+                "Map/Floor",
+                Some("Mystery"),
+                12,
+                None,
+                Some("Wat")
+            ),
+            (
+                Code::UnknownProperty,
+                Warning,
+                "Map/Floor",
+                Some("PhysicsData"),
+                13,
+                None,
+                None
+            ),
+            (
+                Code::MissingSharedString,
+                Error,
+                "Map/Floor",
+                Some("PhysicsData"),
+                13,
+                None,
+                Some("missing")
+            ),
+            (
+                Code::InvalidValue,
+                Warning,
+                "Map/Floor",
+                Some("Material"),
+                14,
+                None,
+                Some("1")
+            ),
+            (
+                Code::UnexpectedElement,
+                Error,
+                "Map/Floor",
+                None,
+                16,
+                Some("Properties, Item, or External"),
+                Some("<Bogus>")
+            ),
+            (
+                Code::DuplicateReferent,
+                Error,
+                "Map/Thing",
+                None,
+                18,
+                None,
+                Some("RBX2")
+            ),
+            (
+                Code::UnknownClass,
+                Warning,
+                "Map/Thing",
+                None,
+                18,
+                None,
+                Some("NotARealClass")
+            ),
+        ]
+    );
+
+    let wrong_type = &report.diagnostics()[2];
+    assert_eq!(wrong_type.code().as_str(), "wrong-property-type");
+    assert!(wrong_type
+        .to_string()
+        .starts_with("10:0: error [wrong-property-type] Map/Floor.Anchored: "));
+
+    // Documents produced by the encoder should always be valid.
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder").with_child(
+            InstanceBuilder::new("Part")
+                .with_name("Floor")
+                .with_property("Anchored", true)
+                .with_property("Transparency", 0.5f32),
+        ),
+    );
+    let mut encoded = Vec::new();
+    crate::to_writer_default(&mut encoded, &tree, &[tree.root_ref()]).unwrap();
+    let report = crate::validate_reader(encoded.as_slice());
+    assert!(report.is_valid(), "{:?}", report.diagnostics());
+
+    // Malformed XML stops validation.
+    let report = crate::validate_str(r#"<roblox version="3"><Item class="Folder"></roblox>"#);
+    let codes: Vec<_> = report.diagnostics().iter().map(|d| d.code()).collect();
+    assert_eq!(codes, [Code::WrongVersion, Code::MalformedXml]);
+}
+
+#[test]
+fn terrain_data() {
+    use rbx_dom_weak::types::{ChunkPosition, SmoothGrid, TerrainChunk, TerrainMaterial, Voxel};
+
+    let _ = env_logger::try_init();
+
+    let mut grid = SmoothGrid::new();
+    let mut chunk = TerrainChunk::new(ChunkPosition::new(0, -1, 2));
+    for x in 0..32 {
+        for y in 0..32 {
+            chunk.set_voxel(x, y, 0, Voxel::full(TerrainMaterial::Grass));
+            chunk.set_voxel(x, y, 1, Voxel::new(TerrainMaterial::Grass as u8, 128));
+        }
+    }
+    chunk.set_voxel(5, 2, 5, Voxel::new(40, 255));
+    grid.insert_chunk(chunk);
+
+    let material_colors: Vec<u8> = (0..69).map(|i| (i * 37) as u8).collect();
+    let physics_grid: Vec<u8> = (0..300).map(|i| (i * 11 + 3) as u8).collect();
+
+    let mut tree = WeakDom::new(
+        InstanceBuilder::new("Workspace").with_child(
+            InstanceBuilder::new("Terrain")
+                .with_property(
+                    "MaterialColors",
+                    BinaryString::from(material_colors.clone()),
+                )
+                .with_property("PhysicsGrid", BinaryString::from(physics_grid.clone())),
+        ),
+    );
+    let terrain = tree.root().children()[0];
+    tree.get_by_ref_mut(terrain)
+        .unwrap()
+        .set_smooth_grid(&grid)
+        .unwrap();
+    let smooth_grid = tree
+        .get_by_ref(terrain)
+        .unwrap()
+        .properties
+        .get("SmoothGrid")
+        .cloned()
+        .unwrap();
+
+    let check = |decoded: &WeakDom| {
+        let terrain = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+        assert_eq!(terrain.properties.get("SmoothGrid"), Some(&smooth_grid));
+        assert_eq!(
+            terrain.properties.get("MaterialColors"),
+            Some(&Variant::BinaryString(material_colors.clone().into()))
+        );
+        assert_eq!(
+            terrain.properties.get("PhysicsGrid"),
+            Some(&Variant::BinaryString(physics_grid.clone().into()))
+        );
+        assert_eq!(terrain.smooth_grid().unwrap().unwrap(), grid);
+    };
+
+    // The BinaryStrings should survive a round trip byte for byte.
+    let mut encoded = Vec::new();
+    crate::to_writer_default(&mut encoded, &tree, &[terrain]).unwrap();
+    check(&crate::from_reader_default(encoded.as_slice()).unwrap());
+
+    // Roblox wraps long base64 across lines, which has to be read the same.
+    let wrapped = String::from_utf8(encoded.clone()).unwrap().replace(
+        &base64::encode(&physics_grid),
+        &base64::encode(&physics_grid)
+            .as_bytes()
+            .chunks(72)
+            .map(|line| std::str::from_utf8(line).unwrap())
+            .collect::<Vec<_>>()
+            .join("\r\n"),
+    );
+    assert!(wrapped.contains("\r\n"));
+    check(&crate::from_str_default(&wrapped).unwrap());
+
+    // With the structured form, SmoothGrid is written as runs of voxels, and
+    // can be read back with or without it.
+    let codecs = crate::Codecs::new().structured_terrain();
+    let mut structured = Vec::new();
+    let options = crate::EncodeOptions::new().codecs(codecs.clone());
+    crate::to_writer(&mut structured, &tree, &[terrain], options).unwrap();
+    let structured = String::from_utf8(structured).unwrap();
+
+    assert!(structured.contains(r#"<Chunk x="0" y="-1" z="2">Grass*1024 Grass:128*1024"#));
+    assert!(structured.contains(" 40 "));
+    assert!(structured.contains(r#"<BinaryString name="MaterialColors">"#));
+
+    let options = crate::DecodeOptions::new().codecs(codecs.clone());
+    check(&crate::from_str(&structured, options).unwrap());
+
+    let options = crate::DecodeOptions::new().codecs(codecs);
+    check(&crate::from_reader(encoded.as_slice(), options).unwrap());
+}
+
+#[test]
+fn resource_limits() {
+    let _ = env_logger::try_init();
+
+    let nested = |depth: usize| {
+        let mut document = String::from(r#"<roblox version="4">"#);
+        for _ in 0..depth {
+            document.push_str(r#"<Item class="Folder">"#);
+        }
+        for _ in 0..depth {
+            document.push_str("</Item>");
+        }
+        document.push_str("</roblox>");
+        document
+    };
+
+    // The roblox element counts towards the depth, along with each Item.
+    let options = || crate::DecodeOptions::new().max_depth(5);
+    crate::from_str(nested(4), options()).unwrap();
+    let error = crate::from_str(nested(5), options()).unwrap_err();
+    assert!(error.is_limit_exceeded());
+
+    // Recovery mode doesn't keep going after a limit.
+    let error = crate::from_str(nested(5), options().recover(true)).unwrap_err();
+    assert!(error.is_limit_exceeded());
+
+    let mut handler = crate::tests::basic::NullHandler;
+    let error =
+        crate::stream_from_reader(nested(5).as_bytes(), options(), &mut handler).unwrap_err();
+    assert!(error.is_limit_exceeded());
+
+    let properties = r#"
+        <roblox version="4">
+            <Item class="StringValue">
+                <Properties>
+                    <string name="Name">Value</string>
+                    <string name="Value">a</string>
+                    <string name="Value">b</string>
+                </Properties>
+            </Item>
+        </roblox>
+    "#;
+
+    // Repeating a property doesn't count against the limit.
+    let options = |max| crate::DecodeOptions::new().max_properties_per_instance(max);
+    crate::from_str(properties, options(2)).unwrap();
+    let error = crate::from_str(properties, options(1)).unwrap_err();
+    assert!(error.is_limit_exceeded());
+    let error =
+        crate::stream_from_reader(properties.as_bytes(), options(1), &mut handler).unwrap_err();
+    assert!(error.is_limit_exceeded());
+
+    let entities = |value: &str| {
+        format!(
+            r#"<?xml version="1.0"?>
+            <!DOCTYPE roblox [
+                <!ENTITY lol "lol">
+                <!ENTITY pair "&lol;&lol;">
+                <!ENTITY lol1 "&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;">
+                <!ENTITY lol2 "&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;">
+                <!ENTITY lol3 "&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;">
+                <!ENTITY lol4 "&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;">
+                <!ENTITY lol5 "&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;">
+            ]>
+            <roblox version="4">
+                <Item class="StringValue">
+                    <Properties>
+                        <string name="Value">{}</string>
+                    </Properties>
+                </Item>
+            </roblox>"#,
+            value
+        )
+    };
+
+    let tree = crate::from_str_default(entities("&pair;")).unwrap();
+    let value = tree.get_by_ref(tree.root().children()[0]).unwrap();
+    assert_eq!(
+        value.properties.get("Value"),
+        Some(&Variant::String("lol".repeat(2)))
+    );
+
+    // Entities that expand exponentially are stopped even without a limit.
+    assert!(crate::from_str_default(entities("&lol5;")).is_err());
+
+    let options = crate::DecodeOptions::new().max_entity_expansion(0);
+    assert!(crate::from_str(entities("&pair;"), options).is_err());
+}
+
+struct NullHandler;
+
+impl crate::StreamHandler for NullHandler {
+    fn start_instance(&mut self, _class: &str, _referent: Option<&str>) {}
+    fn property(&mut self, _name: &str, _value: crate::StreamValue) {}
+    fn end_instance(&mut self) {}
+    fn shared_string(&mut self, _hash: &str, _value: SharedString) {}
+}
+
+#[test]
+fn slice_round_trip() {
+    let _ = env_logger::try_init();
+
+    let tree = WeakDom::new(
+        InstanceBuilder::new("StringValue")
+            .with_name("Greeting")
+            .with_property("Value", "Hello"),
+    );
+
+    let encoded = crate::to_vec(&tree, &[tree.root_ref()], crate::EncodeOptions::new()).unwrap();
+    let decoded = crate::from_slice(&encoded, crate::DecodeOptions::new()).unwrap();
+
+    let value = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    assert_eq!(value.name, "Greeting");
+    assert_eq!(
+        value.properties.get("Value"),
+        Some(&Variant::String("Hello".to_owned()))
+    );
+}
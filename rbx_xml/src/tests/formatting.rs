@@ -173,3 +173,56 @@ fn formatting() {
 
     insta::assert_snapshot!("serialized", ser_str)
 }
+
+#[test]
+fn formatting_options() {
+    let _ = env_logger::try_init();
+
+    let document = r#"<roblox version="4">
+        <Item class="Folder" referent="folder">
+            <Properties>
+                <string name="Name">Folder</string>
+                <Sparkly name="Unknown" z="1" a="2"></Sparkly>
+            </Properties>
+        </Item>
+    </roblox>"#;
+    let (dom, report) = crate::from_str_with_report(
+        document,
+        crate::DecodeOptions::new().preserve_unknown_elements(true),
+    )
+    .unwrap();
+
+    let options = crate::EncodeOptions::new()
+        .unknown_elements(report.into_unknown_elements())
+        .indentation(crate::Indentation::Tabs)
+        .newline_style(crate::NewlineStyle::CrLf)
+        .attribute_order(crate::AttributeOrder::Alphabetical)
+        .xml_declaration(true);
+
+    let mut encoded = Vec::new();
+    crate::to_writer(&mut encoded, &dom, dom.root().children(), options).unwrap();
+
+    assert_eq!(
+        String::from_utf8(encoded).unwrap(),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\r\n\
+         <roblox version=\"4\">\r\n\
+         \t<Item class=\"Folder\" referent=\"0\">\r\n\
+         \t\t<Properties>\r\n\
+         \t\t\t<string name=\"Name\">Folder</string>\r\n\
+         \t\t\t<Sparkly a=\"2\" name=\"Unknown\" z=\"1\">\r\n\
+         \t\t\t</Sparkly>\r\n\
+         \t\t</Properties>\r\n\
+         \t</Item>\r\n\
+         </roblox>"
+    );
+
+    let mut encoded = Vec::new();
+    let options = crate::EncodeOptions::new().indentation(crate::Indentation::None);
+    crate::to_writer(&mut encoded, &dom, dom.root().children(), options).unwrap();
+
+    assert_eq!(
+        String::from_utf8(encoded).unwrap(),
+        "<roblox version=\"4\"><Item class=\"Folder\" referent=\"0\"><Properties>\
+         <string name=\"Name\">Folder</string></Properties></Item></roblox>"
+    );
+}
@@ -1,4 +1,6 @@
 //! Tests to ensure the functionality of the parser.
+#[cfg(feature = "tokio")]
+mod async_io;
 mod basic;
 mod edge_cases;
 mod formatting;
@@ -6,7 +6,7 @@ use crate::{
     core::XmlType,
     deserializer_core::XmlEventReader,
     error::{DecodeError, EncodeError},
-    serializer_core::{XmlEventWriter, XmlWriteEvent},
+    serializer_core::XmlEventWriter,
 };
 
 impl XmlType for BinaryString {
@@ -16,7 +16,7 @@ impl XmlType for BinaryString {
         // FIXME: BinaryString should have an is_empty method.
         let contents: &[u8] = self.as_ref();
         if !contents.is_empty() {
-            writer.write(XmlWriteEvent::cdata(&base64::encode(self)))?;
+            writer.write_base64_cdata(contents)?;
         }
 
         Ok(())
@@ -18,7 +18,7 @@ impl XmlType for bool {
     }
 
     fn read_xml<R: Read>(reader: &mut XmlEventReader<R>) -> Result<Self, DecodeError> {
-        let content = reader.read_characters()?;
+        let content = reader.read_trimmed_characters()?;
 
         let value = match content.as_str() {
             "true" => true,
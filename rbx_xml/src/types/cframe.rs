@@ -2,6 +2,7 @@ use std::io::{Read, Write};
 
 use rbx_dom_weak::types::{CFrame, Matrix3, Vector3};
 
+use super::numbers::XmlFloat;
 use crate::{
     core::XmlType,
     deserializer_core::XmlEventReader,
@@ -18,7 +19,7 @@ impl XmlType for CFrame {
 
     fn write_xml<W: Write>(&self, writer: &mut XmlEventWriter<W>) -> Result<(), EncodeError> {
         // FIXME: Should this be built into rbx_types?
-        let as_slice = &[
+        let as_slice = [
             self.position.x,
             self.position.y,
             self.position.z,
@@ -31,9 +32,10 @@ impl XmlType for CFrame {
             self.orientation.z.x,
             self.orientation.z.y,
             self.orientation.z.z,
-        ];
+        ]
+        .map(XmlFloat);
 
-        writer.write_tag_array(as_slice, &TAG_NAMES)?;
+        writer.write_tag_array(&as_slice, &TAG_NAMES)?;
 
         Ok(())
     }
@@ -2,6 +2,7 @@ use std::io::{Read, Write};
 
 use rbx_dom_weak::types::{Color3, ColorSequence, ColorSequenceKeypoint};
 
+use super::numbers::XmlFloat;
 use crate::{
     core::XmlType,
     deserializer_core::XmlEventReader,
@@ -14,13 +15,13 @@ impl XmlType for ColorSequence {
 
     fn write_xml<W: Write>(&self, writer: &mut XmlEventWriter<W>) -> Result<(), EncodeError> {
         for keypoint in &self.keypoints {
-            writer.write_characters(keypoint.time)?;
+            writer.write_characters(XmlFloat(keypoint.time))?;
             writer.write(XmlWriteEvent::characters(" "))?;
-            writer.write_characters(keypoint.color.r)?;
+            writer.write_characters(XmlFloat(keypoint.color.r))?;
             writer.write(XmlWriteEvent::characters(" "))?;
-            writer.write_characters(keypoint.color.g)?;
+            writer.write_characters(XmlFloat(keypoint.color.g))?;
             writer.write(XmlWriteEvent::characters(" "))?;
-            writer.write_characters(keypoint.color.b)?;
+            writer.write_characters(XmlFloat(keypoint.color.b))?;
             writer.write(XmlWriteEvent::characters(" "))?;
 
             // Envelope is always 0 for ColorSequenceKeypoint. This value isn't
@@ -35,8 +36,7 @@ impl XmlType for ColorSequence {
     fn read_xml<R: Read>(reader: &mut XmlEventReader<R>) -> Result<Self, DecodeError> {
         let contents = reader.read_characters()?;
         let mut pieces = contents
-            .split(' ')
-            .filter(|slice| !slice.is_empty())
+            .split_ascii_whitespace()
             .map(|piece| piece.parse::<f32>().map_err(|e| reader.error(e)));
         let mut keypoints = Vec::new();
 
@@ -21,7 +21,7 @@ impl XmlType for Color3 {
     }
 
     fn read_xml<R: Read>(reader: &mut XmlEventReader<R>) -> Result<Self, DecodeError> {
-        let contents = reader.read_characters()?;
+        let contents = reader.read_trimmed_characters()?;
 
         // Color3s have two possibilities:
         // They are either a packed int (like Color3uint8) or they are a triple of
@@ -61,7 +61,7 @@ impl XmlType for Color3uint8 {
 
     fn read_xml<R: Read>(reader: &mut XmlEventReader<R>) -> Result<Self, DecodeError> {
         // Color3uint8s are stored as packed u32s.
-        let content = reader.read_characters()?;
+        let content = reader.read_trimmed_characters()?;
         let packed_value: u32 = content.parse().map_err(|e| reader.error(e))?;
 
         let value = decode_packed_color3(packed_value)?;
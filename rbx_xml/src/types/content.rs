@@ -12,6 +12,9 @@ use crate::{
 // A Content type is serialized as either:
 // <null></null>, which indicates an empty content value
 // <url>something</url>, where 'something' is a URL to use for content.
+//
+// Newer versions of Roblox Studio write URLs as <uri>something</uri> instead,
+// which we read but never write.
 impl XmlType for Content {
     const XML_TAG_NAME: &'static str = "Content";
 
@@ -44,9 +47,9 @@ impl XmlType for Content {
 
                     String::new()
                 }
-                "url" => {
+                "url" | "uri" => {
                     let value = reader.read_characters()?;
-                    reader.expect_end_with_name("url")?;
+                    reader.expect_end_with_name(&name.local_name)?;
 
                     value
                 }
@@ -66,6 +69,23 @@ impl XmlType for Content {
     }
 }
 
+/// Newer versions of Roblox Studio write Content properties with the tag name
+/// `ContentId`. These values are only read, never written, since every version
+/// understands `Content`.
+pub struct ContentIdDummy(pub Content);
+
+impl XmlType for ContentIdDummy {
+    const XML_TAG_NAME: &'static str = "ContentId";
+
+    fn write_xml<W: Write>(&self, _writer: &mut XmlEventWriter<W>) -> Result<(), EncodeError> {
+        panic!("ContentId values are only read, never written.");
+    }
+
+    fn read_xml<R: Read>(reader: &mut XmlEventReader<R>) -> Result<Self, DecodeError> {
+        Ok(ContentIdDummy(Content::read_xml(reader)?))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -106,6 +126,35 @@ mod test {
         );
     }
 
+    #[test]
+    fn deserialize_content_uri() {
+        test_util::test_xml_deserialize(
+            r#"
+                <Content name="something">
+                    <uri>rbxassetid://12345</uri>
+                </Content>
+            "#,
+            &Content::from("rbxassetid://12345"),
+        );
+    }
+
+    #[test]
+    fn deserialize_content_id() {
+        let _ = env_logger::try_init();
+
+        let source = r#"
+            <ContentId name="something">
+                <url>rbxassetid://12345</url>
+            </ContentId>
+        "#;
+
+        let mut reader = XmlEventReader::from_source(source.as_bytes());
+        reader.next().unwrap().unwrap(); // Eat StartDocument event
+        let value = ContentIdDummy::read_outer_xml(&mut reader).unwrap();
+
+        assert_eq!(value.0, Content::from("rbxassetid://12345"));
+    }
+
     #[test]
     fn serialize_content_url() {
         test_util::test_xml_serialize(
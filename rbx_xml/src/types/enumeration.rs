@@ -18,7 +18,7 @@ impl XmlType for Enum {
 
     fn read_xml<R: Read>(reader: &mut XmlEventReader<R>) -> Result<Self, DecodeError> {
         let value: u32 = reader
-            .read_characters()?
+            .read_trimmed_characters()?
             .parse()
             .map_err(|e| reader.error(e))?;
 
@@ -26,6 +26,7 @@ mod physical_properties;
 mod ray;
 mod rect;
 mod referent;
+mod security_capabilities;
 mod shared_string;
 mod strings;
 mod tags;
@@ -37,15 +38,17 @@ use std::io::{Read, Write};
 
 use rbx_dom_weak::types::{
     Axes, BinaryString, CFrame, Color3, Color3uint8, ColorSequence, Content, Enum, Faces, Font,
-    NumberRange, NumberSequence, PhysicalProperties, Ray, Rect, Ref, UDim, UDim2, UniqueId,
-    Variant, Vector2, Vector2int16, Vector3, Vector3int16,
+    NumberRange, NumberSequence, PhysicalProperties, Ray, Rect, Ref, SecurityCapabilities, UDim,
+    UDim2, UniqueId, Variant, Vector2, Vector2int16, Vector3, Vector3int16,
 };
 
 use crate::{
+    codec::{read_with_codec, write_with_codec},
     core::XmlType,
     deserializer::ParseState,
     deserializer_core::XmlEventReader,
     error::{DecodeError, EncodeError, EncodeErrorKind},
+    report::UnknownElementKind,
     serializer::EmitState,
     serializer_core::XmlEventWriter,
 };
@@ -72,6 +75,10 @@ macro_rules! declare_rbx_types {
             instance_id: Ref,
             property_name: &str,
         ) -> Result<Option<Variant>, DecodeError> {
+            if let Some(codec) = state.options.codecs.for_xml_type(xml_type_name) {
+                return read_with_codec(reader, codec, xml_type_name).map(Some);
+            }
+
             match xml_type_name {
                 $(<$inner_type>::XML_TAG_NAME => Ok(Some(Variant::$variant_name(<$inner_type>::read_outer_xml(reader)?))),)*
 
@@ -81,12 +88,18 @@ macro_rules! declare_rbx_types {
                     Ok(Some(Variant::String(value.0)))
                 },
 
+                // ContentId is another name for Content used by newer files
+                self::content::ContentIdDummy::XML_TAG_NAME => {
+                    let value = self::content::ContentIdDummy::read_outer_xml(reader)?;
+                    Ok(Some(Variant::Content(value.0)))
+                },
+
                 self::referent::XML_TAG_NAME => Ok(Some(Variant::Ref(read_ref(reader, instance_id, property_name, state)?))),
                 self::shared_string::XML_TAG_NAME => read_shared_string(reader, instance_id, property_name, state).map(Some),
 
                 _ => {
                     state.unknown_type_visited(instance_id, property_name, xml_type_name);
-                    reader.eat_unknown_tag()?;
+                    state.unknown_element_visited(reader, instance_id, UnknownElementKind::Property)?;
 
                     Ok(None)
                 },
@@ -101,6 +114,10 @@ macro_rules! declare_rbx_types {
             xml_property_name: &str,
             value: &Variant,
         ) -> Result<(), EncodeError> {
+            if let Some(codec) = state.options.codecs.for_variant_type(value.ty()) {
+                return write_with_codec(writer, codec, xml_property_name, value);
+            }
+
             match value {
                 $(Variant::$variant_name(value) => value.write_outer_xml(xml_property_name, writer),)*
 
@@ -144,6 +161,7 @@ declare_rbx_types! {
     PhysicalProperties: PhysicalProperties,
     Ray: Ray,
     Rect: Rect,
+    SecurityCapabilities: SecurityCapabilities,
     String: String,
     UDim2: UDim2,
     UDim: UDim,
@@ -2,6 +2,7 @@ use std::io::{Read, Write};
 
 use rbx_dom_weak::types::NumberRange;
 
+use super::numbers::XmlFloat;
 use crate::{
     core::XmlType,
     deserializer_core::XmlEventReader,
@@ -13,9 +14,9 @@ impl XmlType for NumberRange {
     const XML_TAG_NAME: &'static str = "NumberRange";
 
     fn write_xml<W: Write>(&self, writer: &mut XmlEventWriter<W>) -> Result<(), EncodeError> {
-        writer.write_characters(self.min)?;
+        writer.write_characters(XmlFloat(self.min))?;
         writer.write(XmlWriteEvent::characters(" "))?;
-        writer.write_characters(self.max)?;
+        writer.write_characters(XmlFloat(self.max))?;
         writer.write(XmlWriteEvent::characters(" "))?;
 
         Ok(())
@@ -24,8 +25,7 @@ impl XmlType for NumberRange {
     fn read_xml<R: Read>(reader: &mut XmlEventReader<R>) -> Result<Self, DecodeError> {
         let contents = reader.read_characters()?;
         let mut pieces = contents
-            .split(' ')
-            .filter(|slice| !slice.is_empty())
+            .split_ascii_whitespace()
             .map(|piece| piece.parse::<f32>().map_err(|e| reader.error(e)));
 
         let min = pieces
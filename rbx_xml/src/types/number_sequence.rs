@@ -2,6 +2,7 @@ use std::io::{Read, Write};
 
 use rbx_dom_weak::types::{NumberSequence, NumberSequenceKeypoint};
 
+use super::numbers::XmlFloat;
 use crate::{
     core::XmlType,
     deserializer_core::XmlEventReader,
@@ -14,11 +15,11 @@ impl XmlType for NumberSequence {
 
     fn write_xml<W: Write>(&self, writer: &mut XmlEventWriter<W>) -> Result<(), EncodeError> {
         for keypoint in &self.keypoints {
-            writer.write_characters(keypoint.time)?;
+            writer.write_characters(XmlFloat(keypoint.time))?;
             writer.write(XmlWriteEvent::characters(" "))?;
-            writer.write_characters(keypoint.value)?;
+            writer.write_characters(XmlFloat(keypoint.value))?;
             writer.write(XmlWriteEvent::characters(" "))?;
-            writer.write_characters(keypoint.envelope)?;
+            writer.write_characters(XmlFloat(keypoint.envelope))?;
             writer.write(XmlWriteEvent::characters(" "))?;
         }
 
@@ -28,8 +29,7 @@ impl XmlType for NumberSequence {
     fn read_xml<R: Read>(reader: &mut XmlEventReader<R>) -> Result<Self, DecodeError> {
         let contents = reader.read_characters()?;
         let mut pieces = contents
-            .split(' ')
-            .filter(|slice| !slice.is_empty())
+            .split_ascii_whitespace()
             .map(|piece| piece.parse::<f32>().map_err(|e| reader.error(e)));
         let mut keypoints = Vec::new();
 
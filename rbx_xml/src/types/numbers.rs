@@ -1,4 +1,7 @@
-use std::io::{Read, Write};
+use std::{
+    fmt::{self, Write as _},
+    io::{Read, Write},
+};
 
 use crate::{
     core::XmlType,
@@ -7,8 +10,72 @@ use crate::{
     serializer_core::XmlEventWriter,
 };
 
+/// Displays a float the way Roblox Studio writes it.
+///
+/// Floats are written with the fewest digits that read back as the same
+/// value. Like C's `%g`, scientific notation is only used for exponents
+/// smaller than -4, or at least as large as the number of digits the type can
+/// hold, with the exponent written with a sign and at least two digits, as in
+/// `-4.371139e-08`. Infinities and NaN are written as `INF`, `-INF`, and
+/// `NAN`.
+pub struct XmlFloat<T>(pub T);
+
+/// A buffer on the stack that a float's scientific form can be written into,
+/// which is never longer than this.
+struct ScientificBuffer {
+    bytes: [u8; 32],
+    len: usize,
+}
+
+impl fmt::Write for ScientificBuffer {
+    fn write_str(&mut self, value: &str) -> fmt::Result {
+        let end = self.len + value.len();
+        self.bytes
+            .get_mut(self.len..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(value.as_bytes());
+        self.len = end;
+
+        Ok(())
+    }
+}
+
 macro_rules! float_type {
-    ($rust_type: ident, $xml_name: expr) => {
+    ($rust_type: ident, $xml_name: expr, $digits: expr) => {
+        impl fmt::Display for XmlFloat<$rust_type> {
+            fn fmt(&self, output: &mut fmt::Formatter) -> fmt::Result {
+                let value = self.0;
+
+                if value == $rust_type::INFINITY {
+                    return output.write_str("INF");
+                } else if value == $rust_type::NEG_INFINITY {
+                    return output.write_str("-INF");
+                } else if value.is_nan() {
+                    return output.write_str("NAN");
+                }
+
+                // Rust's scientific form has the shortest digits that round
+                // trip, which we keep and only lay out differently.
+                let mut buffer = ScientificBuffer {
+                    bytes: [0; 32],
+                    len: 0,
+                };
+                write!(buffer, "{:e}", value)?;
+
+                // The buffer only ever contains ASCII written by Rust.
+                let scientific = std::str::from_utf8(&buffer.bytes[..buffer.len]).unwrap();
+                let (mantissa, exponent) = scientific.split_once('e').unwrap();
+                let exponent: i32 = exponent.parse().unwrap();
+
+                if !(-4..$digits).contains(&exponent) {
+                    let sign = if exponent < 0 { '-' } else { '+' };
+                    write!(output, "{}e{}{:02}", mantissa, sign, exponent.abs())
+                } else {
+                    write!(output, "{}", value)
+                }
+            }
+        }
+
         impl XmlType for $rust_type {
             const XML_TAG_NAME: &'static str = $xml_name;
 
@@ -16,24 +83,16 @@ macro_rules! float_type {
                 &self,
                 writer: &mut XmlEventWriter<W>,
             ) -> Result<(), EncodeError> {
-                if *self == std::$rust_type::INFINITY {
-                    writer.write_characters("INF")
-                } else if *self == std::$rust_type::NEG_INFINITY {
-                    writer.write_characters("-INF")
-                } else if self.is_nan() {
-                    writer.write_characters("NAN")
-                } else {
-                    writer.write_characters(self)
-                }
+                writer.write_characters(XmlFloat(*self))
             }
 
             fn read_xml<R: Read>(reader: &mut XmlEventReader<R>) -> Result<Self, DecodeError> {
-                let contents = reader.read_characters()?;
+                let contents = reader.read_trimmed_characters()?;
 
                 Ok(match contents.as_str() {
-                    "INF" => std::$rust_type::INFINITY,
-                    "-INF" => std::$rust_type::NEG_INFINITY,
-                    "NAN" => std::$rust_type::NAN,
+                    "INF" => $rust_type::INFINITY,
+                    "-INF" => $rust_type::NEG_INFINITY,
+                    "NAN" => $rust_type::NAN,
                     number => number.parse().map_err(|e| reader.error(e))?,
                 })
             }
@@ -55,7 +114,7 @@ macro_rules! int_type {
 
             fn read_xml<R: Read>(reader: &mut XmlEventReader<R>) -> Result<Self, DecodeError> {
                 reader
-                    .read_characters()?
+                    .read_trimmed_characters()?
                     .parse()
                     .map_err(|e| reader.error(e))
             }
@@ -63,8 +122,8 @@ macro_rules! int_type {
     };
 }
 
-float_type!(f32, "float");
-float_type!(f64, "double");
+float_type!(f32, "float", 9);
+float_type!(f64, "double", 17);
 int_type!(i32, "int");
 int_type!(i64, "int64");
 
@@ -99,12 +158,9 @@ mod test {
 
     #[test]
     fn test_inf_and_nan_deserialize() {
-        test_util::test_xml_deserialize(r#"<float name="foo">INF</float>"#, &std::f32::INFINITY);
+        test_util::test_xml_deserialize(r#"<float name="foo">INF</float>"#, &f32::INFINITY);
 
-        test_util::test_xml_deserialize(
-            r#"<float name="foo">-INF</float>"#,
-            &std::f32::NEG_INFINITY,
-        );
+        test_util::test_xml_deserialize(r#"<float name="foo">-INF</float>"#, &f32::NEG_INFINITY);
 
         // Can't just use test_util::test_xml_deserialize, because NaN != NaN!
 
@@ -117,12 +173,44 @@ mod test {
         assert!(value.is_nan());
     }
 
+    #[test]
+    fn float_formatting() {
+        use super::XmlFloat;
+
+        assert_eq!(XmlFloat(0.0f32).to_string(), "0");
+        assert_eq!(XmlFloat(-0.0f32).to_string(), "-0");
+        assert_eq!(XmlFloat(0.1f32).to_string(), "0.1");
+        assert_eq!(XmlFloat(0.0001f32).to_string(), "0.0001");
+        assert_eq!(XmlFloat(123456.0f32).to_string(), "123456");
+        assert_eq!(XmlFloat(-4.371139e-8f32).to_string(), "-4.371139e-08");
+        assert_eq!(XmlFloat(1e10f32).to_string(), "1e+10");
+        assert_eq!(XmlFloat(f32::MAX).to_string(), "3.4028235e+38");
+        assert_eq!(XmlFloat(f32::MIN_POSITIVE).to_string(), "1.1754944e-38");
+
+        assert_eq!(XmlFloat(0.1f64).to_string(), "0.1");
+        assert_eq!(XmlFloat(1e16f64).to_string(), "10000000000000000");
+        assert_eq!(XmlFloat(1e17f64).to_string(), "1e+17");
+        assert_eq!(XmlFloat(5e-324f64).to_string(), "5e-324");
+        assert_eq!(XmlFloat(f64::MIN).to_string(), "-1.7976931348623157e+308");
+    }
+
+    #[test]
+    fn float_formatting_round_trips() {
+        for &value in &[-4.371139e-8f32, 1e10, f32::MAX, f32::MIN_POSITIVE, 1e-45] {
+            test_util::test_xml_round_trip(&value);
+        }
+
+        for &value in &[1e17f64, 5e-324, f64::MIN, 0.1 + 0.2] {
+            test_util::test_xml_round_trip(&value);
+        }
+    }
+
     #[test]
     fn test_inf_and_nan_serialize() {
-        test_util::test_xml_serialize(r#"<float name="foo">INF</float>"#, &std::f32::INFINITY);
+        test_util::test_xml_serialize(r#"<float name="foo">INF</float>"#, &f32::INFINITY);
 
-        test_util::test_xml_serialize(r#"<float name="foo">-INF</float>"#, &std::f32::NEG_INFINITY);
+        test_util::test_xml_serialize(r#"<float name="foo">-INF</float>"#, &f32::NEG_INFINITY);
 
-        test_util::test_xml_serialize(r#"<float name="foo">NAN</float>"#, &std::f32::NAN);
+        test_util::test_xml_serialize(r#"<float name="foo">NAN</float>"#, &f32::NAN);
     }
 }
@@ -0,0 +1,61 @@
+use std::io::{Read, Write};
+
+use rbx_dom_weak::types::SecurityCapabilities;
+
+use crate::{
+    core::XmlType,
+    deserializer_core::XmlEventReader,
+    error::{DecodeError, EncodeError},
+    serializer_core::XmlEventWriter,
+};
+
+// SecurityCapabilities are serialized as their bitfield, written as an
+// unsigned integer.
+impl XmlType for SecurityCapabilities {
+    const XML_TAG_NAME: &'static str = "SecurityCapabilities";
+
+    fn write_xml<W: Write>(&self, writer: &mut XmlEventWriter<W>) -> Result<(), EncodeError> {
+        writer.write_characters(self.bits())
+    }
+
+    fn read_xml<R: Read>(reader: &mut XmlEventReader<R>) -> Result<Self, DecodeError> {
+        let bits: u64 = reader
+            .read_trimmed_characters()?
+            .parse()
+            .map_err(|e| reader.error(e))?;
+
+        Ok(SecurityCapabilities::from_bits(bits))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_util;
+
+    #[test]
+    fn round_trip_security_capabilities() {
+        test_util::test_xml_round_trip(&SecurityCapabilities::from_bits(u64::MAX - 7));
+    }
+
+    #[test]
+    fn deserialize_security_capabilities() {
+        test_util::test_xml_deserialize(
+            r#"
+                <SecurityCapabilities name="Capabilities">13</SecurityCapabilities>
+            "#,
+            &SecurityCapabilities::from_bits(13),
+        );
+    }
+
+    #[test]
+    fn serialize_security_capabilities() {
+        test_util::test_xml_serialize(
+            r#"
+                <SecurityCapabilities name="foo">0</SecurityCapabilities>
+            "#,
+            &SecurityCapabilities::new(),
+        );
+    }
+}
@@ -13,7 +13,7 @@ impl XmlType for UniqueId {
     const XML_TAG_NAME: &'static str = "UniqueId";
 
     fn read_xml<R: Read>(reader: &mut XmlEventReader<R>) -> Result<Self, DecodeError> {
-        let content = reader.read_characters()?;
+        let content = reader.read_trimmed_characters()?;
 
         content.parse().map_err(|e| reader.error(e))
     }
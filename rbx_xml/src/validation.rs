@@ -0,0 +1,848 @@
+use std::{borrow::Cow, cell::Cell, collections::HashSet, fmt, io::Read, rc::Rc};
+
+use rbx_dom_weak::{
+    types::{Ref, Variant, VariantType},
+    InstanceBuilder, WeakDom,
+};
+use rbx_reflection::{DataType, ValidationError};
+
+use crate::{
+    conversion::ConvertVariant,
+    core::find_canonical_property_descriptor,
+    deserializer::{
+        deserialize_metadata, deserialize_shared_string, peek_property_element, read_item_start,
+        CountingReader, DecodeOptions, ParseState,
+    },
+    deserializer_core::{XmlEventReader, XmlReadEvent},
+    error::{DecodeError, DecodeErrorKind},
+    types::read_value_xml,
+};
+
+/// The result of checking a document with
+/// [`validate_reader`][crate::validate_reader].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ValidationReport {
+    /// Every problem found in the document, ordered by where they appear.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Returns whether no diagnostics are errors. Documents with only warnings
+    /// can still be decoded with the default [`DecodeOptions`].
+    pub fn is_valid(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.severity != Severity::Error)
+    }
+}
+
+/// A single problem found while validating a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    code: DiagnosticCode,
+    severity: Severity,
+    path: String,
+    property_name: Option<String>,
+    line: usize,
+    column: usize,
+    expected: Option<String>,
+    actual: Option<String>,
+    message: String,
+}
+
+impl Diagnostic {
+    fn new<S: Into<String>>(
+        code: DiagnosticCode,
+        severity: Severity,
+        (line, column): (usize, usize),
+        message: S,
+    ) -> Self {
+        Diagnostic {
+            code,
+            severity,
+            path: String::new(),
+            property_name: None,
+            line,
+            column,
+            expected: None,
+            actual: None,
+            message: message.into(),
+        }
+    }
+
+    fn from_error(error: &DecodeError) -> Self {
+        let code = match error.kind() {
+            DecodeErrorKind::Xml(_) | DecodeErrorKind::UnexpectedEof => {
+                DiagnosticCode::MalformedXml
+            }
+            DecodeErrorKind::UnexpectedXmlEvent(_) => DiagnosticCode::UnexpectedElement,
+            DecodeErrorKind::MissingAttribute(_) => DiagnosticCode::MissingAttribute,
+            DecodeErrorKind::WrongDocVersion(_) => DiagnosticCode::WrongVersion,
+            _ => DiagnosticCode::InvalidValue,
+        };
+
+        let mut diagnostic = Diagnostic::new(
+            code,
+            Severity::Error,
+            (error.line(), error.column()),
+            error.message(),
+        );
+
+        match error.kind() {
+            DecodeErrorKind::UnexpectedXmlEvent(event) => {
+                diagnostic.actual = Some(describe_event(event));
+            }
+            DecodeErrorKind::MissingAttribute(attribute) => {
+                diagnostic.expected = Some((*attribute).to_owned());
+            }
+            _ => {}
+        }
+
+        diagnostic
+    }
+
+    fn with_property(mut self, property_name: &str) -> Self {
+        self.property_name = Some(property_name.to_owned());
+        self
+    }
+
+    fn with_expected<S: Into<String>>(mut self, expected: S) -> Self {
+        self.expected = Some(expected.into());
+        self
+    }
+
+    fn with_actual<S: Into<String>>(mut self, actual: S) -> Self {
+        self.actual = Some(actual.into());
+        self
+    }
+
+    /// What kind of problem this is.
+    pub fn code(&self) -> DiagnosticCode {
+        self.code
+    }
+
+    /// How serious the problem is.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// The Names of the instance the problem is in and its ancestors,
+    /// separated by `/` and starting from an instance at the top of the
+    /// document. Instances without a Name are named after their ClassName.
+    /// This is empty for problems outside of any instance.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The name of the property the problem is in, if it's in one.
+    pub fn property_name(&self) -> Option<&str> {
+        self.property_name.as_deref()
+    }
+
+    /// 1-based line number in the document where the problem is.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// 1-based column number in the document where the problem is.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// What the document should have contained, if there's a single answer,
+    /// like the name of a missing attribute or the type a property should
+    /// have.
+    pub fn expected(&self) -> Option<&str> {
+        self.expected.as_deref()
+    }
+
+    /// What the document contained instead, like an unexpected element or the
+    /// type a property had.
+    pub fn actual(&self) -> Option<&str> {
+        self.actual.as_deref()
+    }
+
+    /// A human-readable description of the problem.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{}:{}: {} [{}]",
+            self.line,
+            self.column,
+            self.severity.as_str(),
+            self.code.as_str()
+        )?;
+
+        if !self.path.is_empty() {
+            write!(formatter, " {}", self.path)?;
+        }
+
+        if let Some(property_name) = &self.property_name {
+            write!(formatter, ".{}", property_name)?;
+        }
+
+        write!(formatter, ": {}", self.message)?;
+
+        match (&self.expected, &self.actual) {
+            (Some(expected), Some(actual)) => {
+                write!(formatter, " (expected {}, found {})", expected, actual)
+            }
+            (Some(expected), None) => write!(formatter, " (expected {})", expected),
+            (None, Some(actual)) => write!(formatter, " (found {})", actual),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Something that the default [`DecodeOptions`] accept, but which is
+    /// probably a mistake or loses information.
+    Warning,
+
+    /// Something that makes decoding the document fail.
+    Error,
+}
+
+impl Severity {
+    /// A short lowercase name for the severity.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// Identifies the kind of problem a [`Diagnostic`] describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DiagnosticCode {
+    /// The document isn't well-formed XML. Nothing after this is checked.
+    MalformedXml,
+
+    /// An element or text appears somewhere it isn't allowed.
+    UnexpectedElement,
+
+    /// An element is missing a required attribute.
+    MissingAttribute,
+
+    /// The document isn't version 4 of the format.
+    WrongVersion,
+
+    /// An instance's ClassName isn't in the reflection database.
+    UnknownClass,
+
+    /// A property isn't in the reflection database.
+    UnknownProperty,
+
+    /// A property has a type that rbx_xml doesn't know about.
+    UnknownType,
+
+    /// A property's type can't be converted to the type the reflection
+    /// database gives it.
+    WrongPropertyType,
+
+    /// A property's value couldn't be read as its type.
+    InvalidValue,
+
+    /// More than one instance has the same referent.
+    DuplicateReferent,
+
+    /// A Ref property points to a referent that no instance in the document
+    /// has.
+    UnresolvedReferent,
+
+    /// A SharedString property has a hash that isn't in the document's
+    /// SharedString dictionary.
+    MissingSharedString,
+}
+
+impl DiagnosticCode {
+    /// A stable, kebab-case name for the code, suitable for matching on in
+    /// scripts.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DiagnosticCode::MalformedXml => "malformed-xml",
+            DiagnosticCode::UnexpectedElement => "unexpected-element",
+            DiagnosticCode::MissingAttribute => "missing-attribute",
+            DiagnosticCode::WrongVersion => "wrong-version",
+            DiagnosticCode::UnknownClass => "unknown-class",
+            DiagnosticCode::UnknownProperty => "unknown-property",
+            DiagnosticCode::UnknownType => "unknown-type",
+            DiagnosticCode::WrongPropertyType => "wrong-property-type",
+            DiagnosticCode::InvalidValue => "invalid-value",
+            DiagnosticCode::DuplicateReferent => "duplicate-referent",
+            DiagnosticCode::UnresolvedReferent => "unresolved-referent",
+            DiagnosticCode::MissingSharedString => "missing-shared-string",
+        }
+    }
+}
+
+pub fn validate_internal<R: Read>(source: R) -> ValidationReport {
+    // Values are read with the same code used to build a WeakDom, which
+    // needs an instance for them to belong to, the same way as when
+    // streaming.
+    let mut scratch = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let scratch_id = scratch.root_ref();
+
+    let bytes_read = Rc::new(Cell::new(0));
+    let source = CountingReader::new(source, Rc::clone(&bytes_read));
+
+    let mut reader = XmlEventReader::from_source(source);
+    let mut state = ParseState::new(&mut scratch, DecodeOptions::new(), bytes_read);
+
+    let mut validator = Validator {
+        scratch_id,
+        diagnostics: Vec::new(),
+        pending: Vec::new(),
+        path: Vec::new(),
+        referents: HashSet::new(),
+        refs: Vec::new(),
+        shared_strings: Vec::new(),
+    };
+
+    if let Err(error) = validator.document(&mut reader, &mut state) {
+        validator
+            .pending
+            .push(Pending::Now(Diagnostic::from_error(&error)));
+    }
+
+    validator.finish(&state)
+}
+
+/// Walks a document, checking it as it goes.
+struct Validator {
+    scratch_id: Ref,
+    diagnostics: Vec<Diagnostic>,
+
+    /// Diagnostics in the instance currently being read, which are given a
+    /// path once the instance's Name is known.
+    pending: Vec<Pending>,
+
+    /// The Names of the instance currently being read and its ancestors.
+    path: Vec<String>,
+
+    /// The referents of every instance read so far.
+    referents: HashSet<String>,
+
+    /// Diagnostics for Refs and SharedStrings, which can only be checked at
+    /// the end of the document, along with what they point to.
+    refs: Vec<(Diagnostic, String)>,
+    shared_strings: Vec<(Diagnostic, String)>,
+}
+
+enum Pending {
+    Now(Diagnostic),
+    Ref(Diagnostic, String),
+    SharedString(Diagnostic, String),
+}
+
+impl Validator {
+    fn push(&mut self, diagnostic: Diagnostic) {
+        self.pending.push(Pending::Now(diagnostic));
+    }
+
+    /// Gives every pending diagnostic the path of the current instance.
+    fn flush(&mut self) {
+        let path = self.path.join("/");
+
+        for pending in self.pending.drain(..) {
+            match pending {
+                Pending::Now(mut diagnostic) => {
+                    diagnostic.path = path.clone();
+                    self.diagnostics.push(diagnostic);
+                }
+                Pending::Ref(mut diagnostic, referent) => {
+                    diagnostic.path = path.clone();
+                    self.refs.push((diagnostic, referent));
+                }
+                Pending::SharedString(mut diagnostic, hash) => {
+                    diagnostic.path = path.clone();
+                    self.shared_strings.push((diagnostic, hash));
+                }
+            }
+        }
+    }
+
+    fn finish(mut self, state: &ParseState) -> ValidationReport {
+        self.flush();
+
+        for (diagnostic, referent) in self.refs {
+            if !self.referents.contains(&referent) {
+                self.diagnostics.push(diagnostic);
+            }
+        }
+
+        for (diagnostic, hash) in self.shared_strings {
+            if !state.known_shared_strings.contains_key(&hash) {
+                self.diagnostics.push(diagnostic);
+            }
+        }
+
+        self.diagnostics
+            .sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+
+        ValidationReport {
+            diagnostics: self.diagnostics,
+        }
+    }
+
+    /// Records `error` and skips the rest of the element it happened in,
+    /// which started at `depth`. Errors that leave the reader unusable are
+    /// returned instead.
+    fn recover<R: Read>(
+        &mut self,
+        reader: &mut XmlEventReader<R>,
+        depth: usize,
+        diagnostic: Diagnostic,
+        error: DecodeError,
+    ) -> Result<(), DecodeError> {
+        if !error.is_recoverable() {
+            return Err(error);
+        }
+
+        self.push(diagnostic);
+        reader.skip_to_depth(depth)?;
+
+        Ok(())
+    }
+
+    /// Consumes an event that isn't allowed where it appears, along with
+    /// everything inside of it.
+    fn unexpected<R: Read>(
+        &mut self,
+        reader: &mut XmlEventReader<R>,
+        expected: &str,
+    ) -> Result<(), DecodeError> {
+        let depth = reader.depth();
+        let event = reader.expect_next()?;
+
+        self.push(
+            Diagnostic::new(
+                DiagnosticCode::UnexpectedElement,
+                Severity::Error,
+                reader.location(),
+                format!("Unexpected XML event {}", describe_event(&event)),
+            )
+            .with_expected(expected)
+            .with_actual(describe_event(&event)),
+        );
+
+        reader.skip_to_depth(depth)?;
+        Ok(())
+    }
+
+    fn document<R: Read>(
+        &mut self,
+        reader: &mut XmlEventReader<R>,
+        state: &mut ParseState,
+    ) -> Result<(), DecodeError> {
+        match reader.expect_next()? {
+            XmlReadEvent::StartDocument { .. } => {}
+            _ => unreachable!(),
+        }
+
+        let attributes = reader.expect_start_with_name("roblox")?;
+        let version = attributes
+            .into_iter()
+            .find(|attribute| attribute.name.local_name == "version")
+            .map(|attribute| attribute.value);
+
+        match version {
+            None => self.push(
+                Diagnostic::new(
+                    DiagnosticCode::MissingAttribute,
+                    Severity::Error,
+                    reader.location(),
+                    "Missing attribute 'version'",
+                )
+                .with_expected("version"),
+            ),
+            Some(version) if version != "4" => self.push(
+                Diagnostic::new(
+                    DiagnosticCode::WrongVersion,
+                    Severity::Error,
+                    reader.location(),
+                    "Documents must be version 4",
+                )
+                .with_expected("4")
+                .with_actual(version),
+            ),
+            Some(_) => {}
+        }
+
+        const EXPECTED: &str = "Item, External, Meta, or SharedStrings";
+
+        loop {
+            match reader.expect_peek()? {
+                XmlReadEvent::StartElement { name, .. } => match name.local_name.as_str() {
+                    "Item" => self.instance(reader, state)?,
+                    "External" => reader.eat_unknown_tag()?,
+                    "Meta" => {
+                        let depth = reader.depth();
+                        if let Err(error) = deserialize_metadata(reader, state) {
+                            self.recover(reader, depth, Diagnostic::from_error(&error), error)?;
+                        }
+                    }
+                    "SharedStrings" => self.shared_string_dict(reader, state)?,
+                    _ => self.unexpected(reader, EXPECTED)?,
+                },
+                XmlReadEvent::EndElement { name } if name.local_name == "roblox" => {
+                    reader.expect_next().unwrap();
+                    break;
+                }
+                XmlReadEvent::EndDocument => break,
+                _ => self.unexpected(reader, EXPECTED)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn shared_string_dict<R: Read>(
+        &mut self,
+        reader: &mut XmlEventReader<R>,
+        state: &mut ParseState,
+    ) -> Result<(), DecodeError> {
+        reader.expect_start_with_name("SharedStrings")?;
+
+        loop {
+            match reader.expect_peek()? {
+                XmlReadEvent::StartElement { name, .. } if name.local_name == "SharedString" => {
+                    let depth = reader.depth();
+                    if let Err(error) = deserialize_shared_string(reader, state) {
+                        self.recover(reader, depth, Diagnostic::from_error(&error), error)?;
+                    }
+                }
+                XmlReadEvent::EndElement { name } if name.local_name == "SharedStrings" => {
+                    break;
+                }
+                _ => self.unexpected(reader, "SharedString")?,
+            }
+        }
+
+        reader.expect_end_with_name("SharedStrings")?;
+        Ok(())
+    }
+
+    fn instance<R: Read>(
+        &mut self,
+        reader: &mut XmlEventReader<R>,
+        state: &mut ParseState,
+    ) -> Result<(), DecodeError> {
+        self.flush();
+
+        let depth = reader.depth();
+        let (class_name, referent) = match read_item_start(reader) {
+            Ok(item) => item,
+            Err(error) => {
+                return self.recover(reader, depth, Diagnostic::from_error(&error), error);
+            }
+        };
+        let position = reader.location();
+
+        self.path.push(class_name.clone());
+
+        if let Some(referent) = referent {
+            if !self.referents.insert(referent.clone()) {
+                self.push(
+                    Diagnostic::new(
+                        DiagnosticCode::DuplicateReferent,
+                        Severity::Error,
+                        position,
+                        "Another instance already has this referent",
+                    )
+                    .with_actual(referent),
+                );
+            }
+        }
+
+        let class_known = rbx_reflection_database::global()
+            .classes
+            .contains_key(class_name.as_str());
+
+        if !class_known {
+            self.push(
+                Diagnostic::new(
+                    DiagnosticCode::UnknownClass,
+                    Severity::Warning,
+                    position,
+                    "Class is not in the reflection database",
+                )
+                .with_actual(class_name.as_str()),
+            );
+        }
+
+        // Warnings about unknown property types mention the scratch instance's
+        // class, so it needs to match the instance being read.
+        state.tree.get_by_ref_mut(self.scratch_id).unwrap().class = class_name.clone();
+
+        const EXPECTED: &str = "Properties, Item, or External";
+
+        loop {
+            match reader.expect_peek()? {
+                XmlReadEvent::StartElement { name, .. } => match name.local_name.as_str() {
+                    "Properties" => {
+                        reader.expect_start_with_name("Properties")?;
+                        self.properties(reader, state, &class_name, class_known)?;
+                        self.flush();
+                    }
+                    "Item" => self.instance(reader, state)?,
+                    "External" => reader.eat_unknown_tag()?,
+                    _ => self.unexpected(reader, EXPECTED)?,
+                },
+                XmlReadEvent::EndElement { name } if name.local_name == "Item" => {
+                    reader.expect_next().unwrap();
+                    break;
+                }
+                _ => self.unexpected(reader, EXPECTED)?,
+            }
+        }
+
+        self.flush();
+        self.path.pop();
+
+        Ok(())
+    }
+
+    fn properties<R: Read>(
+        &mut self,
+        reader: &mut XmlEventReader<R>,
+        state: &mut ParseState,
+        class_name: &str,
+        class_known: bool,
+    ) -> Result<(), DecodeError> {
+        loop {
+            let depth = reader.depth();
+
+            match peek_property_element(reader) {
+                Ok(Some((xml_type_name, property_name))) => {
+                    self.property(
+                        reader,
+                        state,
+                        class_name,
+                        class_known,
+                        &xml_type_name,
+                        &property_name,
+                    )?;
+                }
+                Ok(None) => return Ok(()),
+                Err(error) => {
+                    self.recover(reader, depth, Diagnostic::from_error(&error), error)?;
+                }
+            }
+        }
+    }
+
+    fn property<R: Read>(
+        &mut self,
+        reader: &mut XmlEventReader<R>,
+        state: &mut ParseState,
+        class_name: &str,
+        class_known: bool,
+        xml_type_name: &str,
+        property_name: &str,
+    ) -> Result<(), DecodeError> {
+        let depth = reader.depth();
+        let position = reader.location();
+
+        let descriptor = find_canonical_property_descriptor(
+            rbx_reflection_database::global(),
+            class_name,
+            property_name,
+        );
+
+        // Every property of an unknown class would be unknown, which isn't
+        // worth a warning of its own.
+        if descriptor.is_none() && class_known {
+            self.push(
+                Diagnostic::new(
+                    DiagnosticCode::UnknownProperty,
+                    Severity::Warning,
+                    position,
+                    "Property is not in the reflection database",
+                )
+                .with_property(property_name),
+            );
+        }
+
+        let result = read_value_xml(reader, state, xml_type_name, self.scratch_id, property_name);
+
+        // Refs and SharedStrings are read as placeholders, with what they
+        // point to recorded as a rewrite that we take back out to check later.
+        for rewrite in state.referent_rewrites.drain(..) {
+            let diagnostic = Diagnostic::new(
+                DiagnosticCode::UnresolvedReferent,
+                Severity::Warning,
+                position,
+                "No instance in the document has this referent",
+            )
+            .with_property(property_name)
+            .with_actual(rewrite.referent_value.as_str());
+
+            self.pending
+                .push(Pending::Ref(diagnostic, rewrite.referent_value));
+        }
+
+        let mut is_shared_string = false;
+        for rewrite in state.shared_string_rewrites.drain(..) {
+            let diagnostic = Diagnostic::new(
+                DiagnosticCode::MissingSharedString,
+                Severity::Error,
+                position,
+                "The document has no SharedString with this hash",
+            )
+            .with_property(property_name)
+            .with_actual(rewrite.shared_string_hash.as_str());
+
+            self.pending.push(Pending::SharedString(
+                diagnostic,
+                rewrite.shared_string_hash,
+            ));
+            is_shared_string = true;
+        }
+
+        let value = match result {
+            Ok(Some(value)) => value,
+            Ok(None) => {
+                self.push(
+                    Diagnostic::new(
+                        DiagnosticCode::UnknownType,
+                        Severity::Warning,
+                        position,
+                        "Property has a type that rbx_xml does not know about",
+                    )
+                    .with_property(property_name)
+                    .with_actual(xml_type_name),
+                );
+                return Ok(());
+            }
+            Err(error) => {
+                let mut diagnostic = Diagnostic::from_error(&error).with_property(property_name);
+                diagnostic.line = position.0;
+                diagnostic.column = position.1;
+
+                return self.recover(reader, depth, diagnostic, error);
+            }
+        };
+
+        let actual_type = if is_shared_string {
+            VariantType::SharedString
+        } else {
+            value.ty()
+        };
+
+        if property_name == "Name" {
+            match value {
+                Variant::String(name) => *self.path.last_mut().unwrap() = name,
+                _ => self.push(
+                    Diagnostic::new(
+                        DiagnosticCode::WrongPropertyType,
+                        Severity::Error,
+                        position,
+                        "Name must be a string",
+                    )
+                    .with_property(property_name)
+                    .with_expected("String")
+                    .with_actual(format!("{:?}", actual_type)),
+                ),
+            }
+
+            return Ok(());
+        }
+
+        let expected_type = match descriptor.map(|descriptor| &descriptor.data_type) {
+            Some(DataType::Value(data_type)) => *data_type,
+            Some(DataType::Enum(_)) => VariantType::Enum,
+            _ => return Ok(()),
+        };
+
+        // SharedStrings are read as placeholders, so only their type can be
+        // checked.
+        if is_shared_string {
+            if actual_type != expected_type {
+                self.push(wrong_type(
+                    position,
+                    class_name,
+                    property_name,
+                    expected_type,
+                    actual_type,
+                ));
+            }
+            return Ok(());
+        }
+
+        // The conversions used when decoding fall back to leaving the value
+        // alone, so the value has to be checked afterwards.
+        let value = value
+            .try_convert_ref(expected_type)
+            .unwrap_or(Cow::Borrowed(&value));
+
+        match rbx_reflection_database::global().validate_value(class_name, property_name, &value) {
+            Err(ValidationError::WrongType { expected, .. }) => self.push(wrong_type(
+                position,
+                class_name,
+                property_name,
+                expected,
+                actual_type,
+            )),
+            Err(ValidationError::UnknownEnumItem {
+                enum_name, value, ..
+            }) => self.push(
+                Diagnostic::new(
+                    DiagnosticCode::InvalidValue,
+                    Severity::Warning,
+                    position,
+                    format!("{} is not an item of Enum.{}", value, enum_name),
+                )
+                .with_property(property_name)
+                .with_actual(value.to_string()),
+            ),
+            // Properties of classes that are newer than the reflection
+            // database are found on Instance instead, which validate_value
+            // doesn't do.
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+fn wrong_type(
+    position: (usize, usize),
+    class_name: &str,
+    property_name: &str,
+    expected: VariantType,
+    actual: VariantType,
+) -> Diagnostic {
+    Diagnostic::new(
+        DiagnosticCode::WrongPropertyType,
+        Severity::Error,
+        position,
+        format!("{}.{} has the wrong type", class_name, property_name),
+    )
+    .with_property(property_name)
+    .with_expected(format!("{:?}", expected))
+    .with_actual(format!("{:?}", actual))
+}
+
+fn describe_event(event: &XmlReadEvent) -> String {
+    match event {
+        XmlReadEvent::StartElement { name, .. } => format!("<{}>", name.local_name),
+        XmlReadEvent::EndElement { name } => format!("</{}>", name.local_name),
+        XmlReadEvent::Characters(_) | XmlReadEvent::CData(_) => "text".to_owned(),
+        event => format!("{:?}", event),
+    }
+}